@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use tracing::instrument;
+
+use super::{lookup_entity, validate_id, EntityType, LookupResponse};
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    moderation::Moderator,
+};
+
+/// Everything stored about a video or user, by source
+#[utoipa::path(
+    get,
+    path = "/{entity_type}/{id}",
+    params(
+        ("entity_type" = EntityType, Path, description = "`video` or `user`"),
+        ("id" = String, Path, description = "Video id or user principal"),
+        ("x-delegated-identity" = Option<String>, Header, description = "Moderator's base64-encoded JSON delegated identity, unless calling with the operator token")
+    ),
+    tag = "admin",
+    responses(
+        (status = 200, description = "Per-source state; failed sources carry an error", body = LookupResponse),
+        (status = 400, description = "Invalid id", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 403, description = "Not a moderator", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = []),
+        ("moderator_identity" = [])
+    )
+)]
+#[instrument(skip(state, moderator))]
+pub async fn lookup(
+    State(state): State<Arc<AppState>>,
+    Path((entity_type, id)): Path<(EntityType, String)>,
+    moderator: Option<Extension<Moderator>>,
+) -> Result<Json<LookupResponse>, ApiError> {
+    validate_id(entity_type, &id).map_err(ApiError::InvalidRequest)?;
+
+    let caller = moderator
+        .map(|Extension(Moderator(principal))| principal.to_text())
+        .unwrap_or_else(|| "operator".to_string());
+    log::warn!("Admin lookup of {entity_type:?} {id} by {caller}");
+
+    let sources = lookup_entity(&state, entity_type, &id).await;
+    Ok(Json(LookupResponse {
+        entity_type,
+        id,
+        sources,
+    }))
+}
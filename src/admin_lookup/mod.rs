@@ -0,0 +1,291 @@
+//! Read-only support lookups across kvrocks, Dragonfly and BigQuery.
+//!
+//! `GET /api/v1/admin/lookup/{entity_type}/{id}` gathers everything this
+//! service stores about one video or user into a single JSON view, so
+//! support questions ("why is this video not in the feed?") don't need an
+//! engineer with database access. The endpoint only reads a fixed set of
+//! keys and queries per entity type; the validated id is the only caller
+//! input that reaches them. Each source is read under its own
+//! timeout and reported separately, so one slow store doesn't hide the
+//! others.
+//!
+//! Callers are moderators (delegated identity in `x-delegated-identity`)
+//! or operators (the admin bearer token). Every lookup is logged with the
+//! caller.
+
+pub mod handlers;
+
+use std::{collections::BTreeMap, future::Future, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use candid::Principal;
+use google_cloud_bigquery::{http::job::query::QueryRequest, query::row::Row as QueryRow};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::ApiError,
+    kvrocks::{keys, Table},
+    moderation::{reports::load_reported_video, verify_moderator},
+    posts::cleanup::load_status as load_cleanup_status,
+    referrals::store::get_referred_by,
+    streaks::load_streak,
+    user::blocklist::blocked_set,
+};
+
+const PROJECT_ID: &str = "hot-or-not-feed-intelligence";
+const APPROVAL_TABLE: &str = "`hot-or-not-feed-intelligence.yral_ds.ugc_content_approval`";
+const SOURCE_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_ID_LEN: usize = 128;
+
+pub fn lookup_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(handlers::lookup))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            verify_operator_or_moderator,
+        ))
+        .with_state(state)
+}
+
+/// Lets operators through on the admin token; everyone else must pass
+/// [`verify_moderator`], which records them as a [`Moderator`]
+///
+/// [`Moderator`]: crate::moderation::Moderator
+async fn verify_operator_or_moderator(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if require_operator(request.headers()).is_ok() {
+        return Ok(next.run(request).await);
+    }
+
+    verify_moderator(State(state), request, next).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityType {
+    Video,
+    User,
+}
+
+/// One store's contribution to a lookup
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SourceResult {
+    /// Absent when the read failed
+    pub data: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LookupResponse {
+    pub entity_type: EntityType,
+    pub id: String,
+    /// Keyed by source, e.g. `kvrocks`, `redis`, `bigquery`
+    pub sources: BTreeMap<String, SourceResult>,
+}
+
+pub fn validate_id(entity_type: EntityType, id: &str) -> Result<(), String> {
+    match entity_type {
+        EntityType::Video => {
+            let valid = !id.is_empty()
+                && id.len() <= MAX_ID_LEN
+                && id
+                    .bytes()
+                    .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+            if valid {
+                Ok(())
+            } else {
+                Err(format!("invalid video id: {id}"))
+            }
+        }
+        EntityType::User => Principal::from_text(id)
+            .map(drop)
+            .map_err(|e| format!("invalid user principal: {e}")),
+    }
+}
+
+async fn read_source<F>(source: F) -> SourceResult
+where
+    F: Future<Output = Result<serde_json::Value>>,
+{
+    match tokio::time::timeout(SOURCE_TIMEOUT, source).await {
+        Ok(Ok(data)) => SourceResult {
+            data: Some(data),
+            error: None,
+        },
+        Ok(Err(e)) => SourceResult {
+            data: None,
+            error: Some(format!("{e:#}")),
+        },
+        Err(_) => SourceResult {
+            data: None,
+            error: Some(format!("timed out after {}s", SOURCE_TIMEOUT.as_secs())),
+        },
+    }
+}
+
+/// kvrocks tables keyed by video id, read as raw JSON so the view shows
+/// exactly what is stored
+const VIDEO_TABLES: &[(&str, Table<serde_json::Value>)] = &[
+    ("video_nsfw", Table::hash(keys::VIDEO_NSFW)),
+    ("video_deleted", Table::hash(keys::VIDEO_DELETED)),
+    ("video_hidden", Table::hash(keys::VIDEO_HIDDEN)),
+    ("video_unique_v2", Table::hash(keys::VIDEO_UNIQUE_V2)),
+    ("video_dedup_status", Table::hash(keys::VIDEO_DEDUP_STATUS)),
+    (
+        "user_uploaded_content_approval",
+        Table::hash(keys::USER_UPLOADED_CONTENT_APPROVAL),
+    ),
+    (
+        "bot_uploaded_ai_content",
+        Table::hash(keys::BOT_UPLOADED_AI_CONTENT),
+    ),
+    ("video_metadata", Table::hash(keys::VIDEO_METADATA)),
+    ("video_renditions", Table::json(keys::VIDEO_RENDITIONS)),
+    ("video_thumbnails", Table::json(keys::VIDEO_THUMBNAILS)),
+];
+
+async fn video_kvrocks(state: &AppState, video_id: &str) -> Result<serde_json::Value> {
+    let kvrocks = &state.kvrocks_client;
+    let mut rows = serde_json::Map::new();
+    for (name, table) in VIDEO_TABLES {
+        let row = kvrocks.get(table, video_id).await?;
+        rows.insert(name.to_string(), row.unwrap_or(serde_json::Value::Null));
+    }
+
+    let mut conn = kvrocks.get_connection().await?;
+    let pending_since_ms: Option<f64> = conn.zscore(keys::PENDING_APPROVAL_QUEUE, video_id).await?;
+    rows.insert(
+        "pending_approval_queued_at_ms".to_string(),
+        serde_json::json!(pending_since_ms.map(|score| score as i64)),
+    );
+    Ok(serde_json::Value::Object(rows))
+}
+
+async fn video_redis(state: &AppState, video_id: &str) -> Result<serde_json::Value> {
+    let pool = &state.yral_redis_store_dragonfly;
+    Ok(serde_json::json!({
+        "reports": load_reported_video(pool, video_id).await?,
+        "post_cleanup": load_cleanup_status(pool, video_id).await?,
+    }))
+}
+
+async fn user_redis(state: &AppState, principal: &Principal) -> Result<serde_json::Value> {
+    let pool = &state.yral_redis_store_dragonfly;
+    Ok(serde_json::json!({
+        "streak": load_streak(pool, principal).await?,
+        "referred_by": get_referred_by(pool, principal).await?.map(|p| p.to_text()),
+        "blocked_users": blocked_set(pool, principal).await?.len(),
+    }))
+}
+
+fn video_approval_query(video_id: &str) -> String {
+    format!(
+        "SELECT TO_JSON_STRING(t) FROM {APPROVAL_TABLE} t WHERE video_id = '{video_id}' LIMIT 1"
+    )
+}
+
+fn user_uploads_query(principal: &Principal) -> String {
+    format!(
+        "SELECT TO_JSON_STRING(STRUCT(
+            COUNT(*) AS uploads,
+            COUNTIF(is_approved) AS approved,
+            COUNTIF(NOT is_approved) AS pending,
+            MAX(created_at) AS last_upload_at))
+        FROM {APPROVAL_TABLE}
+        WHERE user_id = '{principal}'"
+    )
+}
+
+/// Runs a query selecting a single JSON string column; returns the rows
+async fn query_json(state: &AppState, query: String) -> Result<Vec<serde_json::Value>> {
+    let request = QueryRequest {
+        query,
+        timeout_ms: Some(SOURCE_TIMEOUT.as_millis() as i64),
+        ..Default::default()
+    };
+    let mut rows = state
+        .bigquery_client
+        .query::<QueryRow>(PROJECT_ID, request)
+        .await?;
+    let mut values = Vec::new();
+    while let Some(row) = rows.next().await? {
+        values.push(serde_json::from_str(&row.column::<String>(0)?)?);
+    }
+    Ok(values)
+}
+
+/// Reads every source for an already validated id
+pub async fn lookup_entity(
+    state: &AppState,
+    entity_type: EntityType,
+    id: &str,
+) -> BTreeMap<String, SourceResult> {
+    let mut sources = BTreeMap::new();
+    match entity_type {
+        EntityType::Video => {
+            let (kvrocks, redis, bigquery) = tokio::join!(
+                read_source(video_kvrocks(state, id)),
+                read_source(video_redis(state, id)),
+                read_source(async {
+                    let rows = query_json(state, video_approval_query(id)).await?;
+                    Ok(serde_json::json!({
+                        "ugc_content_approval": rows.into_iter().next(),
+                    }))
+                }),
+            );
+            sources.insert("kvrocks".to_string(), kvrocks);
+            sources.insert("redis".to_string(), redis);
+            sources.insert("bigquery".to_string(), bigquery);
+        }
+        EntityType::User => {
+            let Ok(principal) = Principal::from_text(id) else {
+                return sources;
+            };
+            let (redis, bigquery) = tokio::join!(
+                read_source(user_redis(state, &principal)),
+                read_source(async {
+                    let rows = query_json(state, user_uploads_query(&principal)).await?;
+                    Ok(serde_json::json!({ "uploads": rows.into_iter().next() }))
+                }),
+            );
+            sources.insert("redis".to_string(), redis);
+            sources.insert("bigquery".to_string(), bigquery);
+        }
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_id() {
+        assert!(validate_id(EntityType::Video, "0f3c9a2b7e_video-1").is_ok());
+        assert!(validate_id(EntityType::Video, "x' OR '1'='1").is_err());
+        assert!(validate_id(EntityType::Video, "").is_err());
+        assert!(validate_id(EntityType::Video, &"a".repeat(MAX_ID_LEN + 1)).is_err());
+
+        assert!(validate_id(
+            EntityType::User,
+            "o7soq-c4ync-cfs3n-i5qbs-472zl-nbxlh-df7r4-2uqpz-svjpz-7ktda-dae"
+        )
+        .is_ok());
+        assert!(validate_id(EntityType::User, "not-a-principal").is_err());
+
+        assert!(video_approval_query("abc").contains("WHERE video_id = 'abc' LIMIT 1"));
+    }
+}
@@ -74,6 +74,7 @@ impl AiVideoDetectorClient {
         let url = format!("{}/detect", self.base_url);
 
         log::info!("Calling AI video detector for URL: {}", video_url);
+        crate::pipeline::telemetry::count_provider_call();
 
         let form = reqwest::multipart::Form::new().text("url", video_url.to_string());
 
@@ -0,0 +1,230 @@
+//! OpenAPI security schemes and per-tag auth examples.
+//!
+//! Protected routes name the scheme they accept with `security(...)` in
+//! their `#[utoipa::path]`. [`SecurityAddon`] declares those schemes on both
+//! docs, and once all routes are collected [`describe_tag_auth`] appends an
+//! example of the expected credentials to every tag's description, so each
+//! group in swagger-ui says how to call it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use utoipa::{
+    openapi::{
+        path::{Operation, PathItem},
+        security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+        tag::TagBuilder,
+        OpenApi, SecurityRequirement,
+    },
+    Modify,
+};
+
+/// Scheme name and an example of the credentials it expects
+const SCHEME_EXAMPLES: &[(&str, &str)] = &[
+    ("bearer", "`Authorization: Bearer <token>`"),
+    (
+        "delegated_identity",
+        "`x-delegated-identity: <base64 DelegatedIdentityWire JSON>`, or `delegated_identity_wire` in the JSON body when the request schema has it",
+    ),
+    (
+        "moderator_identity",
+        "`x-delegated-identity: <base64 DelegatedIdentityWire JSON>` of a moderator, or `delegated_identity_wire` in the JSON body",
+    ),
+    ("api_key", "`x-api-key: <key>`"),
+    (
+        "qstash_signature",
+        "`Upstash-Signature: <JWT>`, added by QStash when it delivers the job",
+    ),
+];
+
+pub struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some(
+                        "Operator token for admin and service routes; the user migration routes take their own API key here",
+                    ))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "delegated_identity",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "x-delegated-identity",
+                "Base64-encoded JSON DelegatedIdentityWire of the signed-in user. Routes whose request body has `delegated_identity_wire` (or `delegated_identity`) read it from the body instead.",
+            ))),
+        );
+        components.add_security_scheme(
+            "moderator_identity",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "x-delegated-identity",
+                "Base64-encoded JSON DelegatedIdentityWire of a whitelisted moderator, for requests without a body; otherwise send it as `delegated_identity_wire` in the body",
+            ))),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "x-api-key",
+                "Per-integration key for the search APIs",
+            ))),
+        );
+        components.add_security_scheme(
+            "qstash_signature",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "Upstash-Signature",
+                "Signature QStash adds to every job it delivers",
+            ))),
+        );
+    }
+}
+
+fn operations(item: &mut PathItem) -> impl Iterator<Item = &mut Operation> {
+    [
+        &mut item.get,
+        &mut item.put,
+        &mut item.post,
+        &mut item.delete,
+        &mut item.options,
+        &mut item.head,
+        &mut item.patch,
+        &mut item.trace,
+    ]
+    .into_iter()
+    .filter_map(Option::as_mut)
+}
+
+/// Scheme names of a requirement; empty for `()`, which marks auth optional
+fn scheme_names(requirement: &SecurityRequirement) -> Vec<String> {
+    match serde_json::to_value(requirement) {
+        Ok(serde_json::Value::Object(map)) => map.keys().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Requires `scheme` on every operation under `prefix` that doesn't name its
+/// own, for route groups guarded by a shared middleware
+pub fn require_scheme(openapi: &mut OpenApi, prefix: &str, scheme: &str) {
+    for (path, item) in openapi.paths.paths.iter_mut() {
+        if !path.starts_with(prefix) {
+            continue;
+        }
+        for operation in operations(item) {
+            operation.security.get_or_insert_with(|| {
+                vec![SecurityRequirement::new(scheme, Vec::<String>::new())]
+            });
+        }
+    }
+}
+
+#[derive(Default)]
+struct TagAuth {
+    schemes: BTreeSet<String>,
+    /// Some routes of the tag take no credentials, or only optionally
+    open: bool,
+}
+
+/// Appends the credentials each tag's routes expect to its description
+pub fn describe_tag_auth(openapi: &mut OpenApi) {
+    let mut by_tag: BTreeMap<String, TagAuth> = BTreeMap::new();
+    for item in openapi.paths.paths.values_mut() {
+        for operation in operations(item) {
+            let requirements = operation.security.clone().unwrap_or_default();
+            for tag in operation.tags.iter().flatten() {
+                let auth = by_tag.entry(tag.clone()).or_default();
+                if requirements.is_empty() {
+                    auth.open = true;
+                }
+                for requirement in &requirements {
+                    let names = scheme_names(requirement);
+                    auth.open |= names.is_empty();
+                    auth.schemes.extend(names);
+                }
+            }
+        }
+    }
+
+    let tags = openapi.tags.get_or_insert_with(Vec::new);
+    for (name, auth) in by_tag {
+        let note = auth_note(&auth);
+        match tags.iter_mut().find(|tag| tag.name == name) {
+            Some(tag) => {
+                tag.description = Some(match tag.description.take() {
+                    Some(description) => format!("{description}\n\n{note}"),
+                    None => note,
+                })
+            }
+            None => tags.push(TagBuilder::new().name(name).description(Some(note)).build()),
+        }
+    }
+}
+
+fn auth_note(auth: &TagAuth) -> String {
+    let examples: Vec<&str> = SCHEME_EXAMPLES
+        .iter()
+        .filter(|(scheme, _)| auth.schemes.contains(*scheme))
+        .map(|(_, example)| *example)
+        .collect();
+    match (examples.is_empty(), auth.open) {
+        (true, _) => "Auth: none.".to_string(),
+        (false, false) => format!("Auth: {}.", examples.join("; or ")),
+        (false, true) => format!(
+            "Auth: {} where the route requires it; other routes are open.",
+            examples.join("; or ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_tag_auth() {
+        let mut openapi: OpenApi = serde_json::from_value(serde_json::json!({
+            "openapi": "3.1.0",
+            "info": { "title": "test", "version": "0" },
+            "paths": {
+                "/bookmarks": {
+                    "get": {
+                        "tags": ["bookmarks"],
+                        "responses": {},
+                        "security": [{ "delegated_identity": [] }]
+                    }
+                },
+                "/leaderboard": {
+                    "get": { "tags": ["leaderboard"], "responses": {} },
+                    "post": {
+                        "tags": ["leaderboard"],
+                        "responses": {},
+                        "security": [{ "bearer": [] }]
+                    }
+                },
+                "/qstash/job": { "post": { "tags": ["qstash"], "responses": {} } }
+            },
+            "tags": [{ "name": "bookmarks", "description": "Saved videos" }]
+        }))
+        .unwrap();
+
+        require_scheme(&mut openapi, "/qstash", "qstash_signature");
+        describe_tag_auth(&mut openapi);
+
+        let description = |name: &str| {
+            openapi
+                .tags
+                .iter()
+                .flatten()
+                .find(|tag| tag.name == name)
+                .and_then(|tag| tag.description.clone())
+                .unwrap()
+        };
+        assert!(description("bookmarks").starts_with("Saved videos\n\nAuth: `x-delegated-identity"));
+        assert!(description("leaderboard").contains("Bearer <token>"));
+        assert!(description("leaderboard").ends_with("other routes are open."));
+        assert!(description("qstash").contains("Upstash-Signature"));
+    }
+}
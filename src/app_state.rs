@@ -1,6 +1,10 @@
 use crate::config::AppConfig;
 use crate::consts::{ANALYTICS_SERVER_URL, NSFW_SERVER_URL, YRAL_METADATA_URL};
 #[cfg(not(feature = "local-bin"))]
+use crate::devices::{fcm::FcmClient, DeviceRegistry};
+#[cfg(not(feature = "local-bin"))]
+use crate::events::notification_templates::TemplateRegistry;
+#[cfg(not(feature = "local-bin"))]
 use crate::events::push_notifications::NotificationClient;
 use crate::kvrocks::KvrocksClient;
 use crate::qstash::client::QStashClient;
@@ -9,6 +13,7 @@ use crate::rewards::RewardsModule;
 use crate::scratchpad::ScratchpadClient;
 use crate::types::RedisPool;
 use crate::utils::naitik_multi_service_client::NaitikMultiServiceClient;
+use crate::utils::read_replicas::ReadReplicas;
 use crate::videogen::comfyui_client::{ComfyUIClient, ComfyUIConfig};
 use crate::videogen::crypto::Crypto;
 use crate::yral_auth::dragonfly::{
@@ -23,8 +28,6 @@ use google_cloud_bigquery::client::{Client, ClientConfig};
 use hyper_util::client::legacy::connect::HttpConnector;
 use ic_agent::identity::Secp256k1Identity;
 use ic_agent::Agent;
-#[cfg(not(feature = "local-bin"))]
-use milvus::client::Client as MilvusClient;
 use reqwest::Client as ReqwestClient;
 use std::env;
 use std::sync::Arc;
@@ -34,6 +37,9 @@ use yral_metadata_client::MetadataClient;
 use yup_oauth2::hyper_rustls::HttpsConnector;
 use yup_oauth2::{authenticator::Authenticator, ServiceAccountAuthenticator};
 
+/// Fail pool checkouts quickly instead of stalling handlers behind a dead Redis
+const REDIS_POOL_CONNECTION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 #[derive(Clone)]
 pub struct MixpanelClient {
     pub client: ReqwestClient,
@@ -84,6 +90,7 @@ pub struct AppState {
     #[cfg(not(feature = "local-bin"))]
     pub yral_redis_store_dragonfly: Arc<DragonflyPool>,
     pub leaderboard_redis_pool: RedisPool,
+    pub leaderboard_read_replicas: ReadReplicas,
     #[cfg(not(feature = "local-bin"))]
     pub rewards_module: RewardsModule,
     pub service_cansister_migration_redis_pool: RedisPool,
@@ -91,7 +98,7 @@ pub struct AppState {
     pub replicate_api_token: String,
     pub user_migration_api_key: String,
     #[cfg(not(feature = "local-bin"))]
-    pub milvus_client: Option<MilvusClient>,
+    pub milvus_client: Option<crate::milvus::MilvusManager>,
     #[cfg(not(feature = "local-bin"))]
     pub kvrocks_client: KvrocksClient,
 
@@ -112,6 +119,11 @@ pub struct AppState {
 impl AppState {
     pub async fn new(app_config: AppConfig) -> Self {
         let leaderboard_redis_pool = init_leaderboard_redis_pool().await;
+        let leaderboard_read_replicas = ReadReplicas::connect_from_env(
+            leaderboard_redis_pool.clone(),
+            "LEADERBOARD_REDIS_REPLICA_URLS",
+        )
+        .await;
         let agent = init_agent().await;
 
         //Initialize central redis data store for auth, metadata and rewards/impressions.
@@ -136,6 +148,11 @@ impl AppState {
         #[cfg(not(feature = "local-bin"))]
         let scratchpad_client = init_scratchpad_client().await;
 
+        #[cfg(not(feature = "local-bin"))]
+        let gchat_auth = init_gchat_auth().await;
+        #[cfg(not(feature = "local-bin"))]
+        let notification_client = init_notification_client(&dragonfly_redis_store, &gchat_auth);
+
         // Initialize ComfyUI client if env vars are configured
         let comfyui_client = ComfyUIConfig::from_env().map(ComfyUIClient::new);
         if comfyui_client.is_some() {
@@ -149,7 +166,7 @@ impl AppState {
             #[cfg(not(feature = "local-bin"))]
             auth: init_auth().await,
             #[cfg(not(feature = "local-bin"))]
-            gchat_auth: init_gchat_auth().await,
+            gchat_auth,
             // ml_server_grpc_channel: init_ml_server_grpc_channel().await,
             qstash: init_qstash(),
             #[cfg(not(feature = "local-bin"))]
@@ -161,14 +178,13 @@ impl AppState {
             #[cfg(not(any(feature = "local-bin", feature = "use-local-agent")))]
             alloydb_client: init_alloydb_client().await,
             #[cfg(not(feature = "local-bin"))]
-            notification_client: NotificationClient::new(
-                env::var("YRAL_METADATA_NOTIFICATION_API_KEY").unwrap_or_default(),
-            ),
+            notification_client,
             #[cfg(not(feature = "local-bin"))]
             yral_auth_dragonfly: dragonfly_redis_store.clone(),
             #[cfg(not(feature = "local-bin"))]
             yral_redis_store_dragonfly: dragonfly_redis_store,
             leaderboard_redis_pool,
+            leaderboard_read_replicas,
             #[cfg(not(feature = "local-bin"))]
             rewards_module,
             config: app_config,
@@ -370,6 +386,31 @@ pub async fn init_gchat_auth() -> Authenticator<HttpsConnector<HttpConnector>> {
         .expect("Failed to build Google Chat authenticator")
 }
 
+/// Sends to registered devices directly through FCM when `FCM_PROJECT_ID` is
+/// set. The yral-mobile service account owns the Firebase project.
+#[cfg(not(feature = "local-bin"))]
+pub fn init_notification_client(
+    dragonfly_redis_store: &Arc<DragonflyPool>,
+    mobile_auth: &Authenticator<HttpsConnector<HttpConnector>>,
+) -> NotificationClient {
+    let client =
+        NotificationClient::new(env::var("YRAL_METADATA_NOTIFICATION_API_KEY").unwrap_or_default())
+            .with_templates(TemplateRegistry::with_overrides(
+                dragonfly_redis_store.clone(),
+            ));
+
+    match env::var("FCM_PROJECT_ID") {
+        Ok(project_id) => client.with_devices(
+            DeviceRegistry::new(dragonfly_redis_store.clone()),
+            FcmClient::new(project_id, mobile_auth.clone()),
+        ),
+        Err(_) => {
+            log::warn!("FCM_PROJECT_ID not set, notifications go through the metadata server only");
+            client
+        }
+    }
+}
+
 pub fn init_qstash() -> QStashState {
     let qstash_key =
         env::var("QSTASH_CURRENT_SIGNING_KEY").expect("QSTASH_CURRENT_SIGNING_KEY is required");
@@ -433,7 +474,11 @@ async fn init_leaderboard_redis_pool() -> RedisPool {
 
     let manager = bb8_redis::RedisConnectionManager::new(redis_url.clone())
         .expect("failed to open connection to redis");
-    RedisPool::builder().build(manager).await.unwrap()
+    RedisPool::builder()
+        .connection_timeout(REDIS_POOL_CONNECTION_TIMEOUT)
+        .build(manager)
+        .await
+        .unwrap()
 }
 
 async fn init_service_canister_migration_redis_pool() -> RedisPool {
@@ -442,7 +487,11 @@ async fn init_service_canister_migration_redis_pool() -> RedisPool {
 
     let manager = bb8_redis::RedisConnectionManager::new(redis_url.clone())
         .expect("failed to open connection to redis");
-    RedisPool::builder().build(manager).await.unwrap()
+    RedisPool::builder()
+        .connection_timeout(REDIS_POOL_CONNECTION_TIMEOUT)
+        .build(manager)
+        .await
+        .unwrap()
 }
 
 async fn init_dragonfly_redis_store_pool() -> Arc<DragonflyPool> {
@@ -461,8 +510,8 @@ async fn init_dragonfly_redis_store_pool() -> Arc<DragonflyPool> {
 }
 
 #[cfg(not(feature = "local-bin"))]
-async fn init_milvus_client(app_config: &AppConfig) -> Option<MilvusClient> {
-    use crate::milvus;
+async fn init_milvus_client(app_config: &AppConfig) -> Option<crate::milvus::MilvusManager> {
+    use crate::milvus::MilvusManager;
 
     let milvus_url = match &app_config.milvus_url {
         Some(url) => url,
@@ -474,23 +523,10 @@ async fn init_milvus_client(app_config: &AppConfig) -> Option<MilvusClient> {
 
     log::info!("Initializing Milvus client at {}", milvus_url);
 
-    match milvus::create_milvus_client(milvus_url.clone()).await {
-        Ok(client) => {
-            log::info!("Milvus client connected successfully");
-
-            // Initialize collection if it doesn't exist
-            if let Err(e) = milvus::init_collection(&client).await {
-                log::error!("Failed to initialize Milvus collection: {}", e);
-                return None;
-            }
-
-            Some(client)
-        }
-        Err(e) => {
-            log::error!("Failed to connect to Milvus: {}", e);
-            None
-        }
-    }
+    // The manager keeps retrying in the background if Milvus isn't up yet
+    let manager = MilvusManager::connect(milvus_url.clone()).await;
+    manager.spawn_health_check();
+    Some(manager)
 }
 
 const KVROCKS_MAX_RETRIES: u32 = 10;
@@ -1,12 +1,26 @@
 use std::collections::HashSet;
 use std::env;
 
-use axum::http::HeaderMap;
+use axum::http::{header, HeaderMap};
+use candid::Principal;
 use jsonwebtoken::{Algorithm, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 use tonic::metadata::MetadataValue;
 use tonic::{Request, Status};
 
+use crate::{
+    app_state::AppState,
+    error::ApiError,
+    types::DelegatedIdentityWire,
+    utils::delegated_identity::{
+        delegated_identity_wire_from_headers, verify_delegated_identity_request,
+    },
+};
+
+/// Bearer token(s) for operator endpoints, comma-separated so a key can be
+/// rotated. Kept apart from the event-ingest tokens every producer holds.
+const OPERATOR_TOKEN_ENV: &str = "OFF_CHAIN_AGENT_ADMIN_TOKEN";
+
 #[allow(clippy::result_large_err)]
 pub fn check_auth_grpc(req: Request<()>) -> Result<Request<()>, Status> {
     let mut grpc_token = env::var("GRPC_AUTH_TOKEN").expect("GRPC_AUTH_TOKEN is required");
@@ -41,6 +55,77 @@ pub fn check_auth_events(req_token: Option<String>) -> Result<(), anyhow::Error>
     }
 }
 
+/// Checks the `x-api-key` header against the comma-separated keys in
+/// `env_var`, so each external consumer can hold its own rotatable key
+pub fn check_api_key(headers: &HeaderMap, env_var: &str) -> Result<(), anyhow::Error> {
+    let Some(req_key) = headers
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok())
+    else {
+        return Err(anyhow::anyhow!("Missing x-api-key header"));
+    };
+    let keys = env::var(env_var).unwrap_or_default();
+
+    if keys
+        .split(',')
+        .map(str::trim)
+        .any(|key| !key.is_empty() && key == req_key.trim())
+    {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Invalid API key"))
+    }
+}
+
+fn operator_token_matches(keys: &str, req_token: &str) -> bool {
+    keys.split(',')
+        .map(str::trim)
+        .any(|key| !key.is_empty() && key == req_token.trim())
+}
+
+/// Guards operator-only endpoints with the admin bearer token. Fails closed
+/// when no admin token is configured.
+pub fn require_operator(headers: &HeaderMap) -> Result<(), ApiError> {
+    let Some(req_token) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return Err(ApiError::Unauthorized(
+            "Missing operator bearer token".to_string(),
+        ));
+    };
+    let keys = env::var(OPERATOR_TOKEN_ENV).unwrap_or_default();
+
+    if operator_token_matches(&keys, req_token) {
+        Ok(())
+    } else {
+        Err(ApiError::Unauthorized("Invalid operator token".to_string()))
+    }
+}
+
+/// Principal behind a delegated identity wire, recorded as the request's user.
+/// The request nonce in `headers` is checked like on every other user route.
+pub async fn require_user_wire(
+    state: &AppState,
+    delegated_identity_wire: DelegatedIdentityWire,
+    headers: &HeaderMap,
+) -> Result<Principal, ApiError> {
+    let user_info = verify_delegated_identity_request(state, delegated_identity_wire, headers)
+        .await
+        .map_err(|e| ApiError::Unauthorized(format!("Invalid delegated identity: {e}")))?;
+    crate::middleware::set_user_context(user_info.user_principal);
+    Ok(user_info.user_principal)
+}
+
+/// [`require_user_wire`] for GET-style endpoints that carry the wire in the
+/// `x-delegated-identity` header
+pub async fn require_user(state: &AppState, headers: &HeaderMap) -> Result<Principal, ApiError> {
+    let wire = delegated_identity_wire_from_headers(headers)
+        .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+    require_user_wire(state, wire, headers).await
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub aud: String,
@@ -87,3 +172,17 @@ pub fn verify_jwt_from_header(
     let jwt = &jwt[7..];
     verify_jwt(public_key_pem, aud, jwt).map_err(|_| ("invalid JWT".to_string(), 401))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operator_token_matches() {
+        assert!(operator_token_matches("old, new", "new"));
+        assert!(operator_token_matches("old,new", "old"));
+        assert!(!operator_token_matches("old,new", "other"));
+        assert!(!operator_token_matches("", ""));
+        assert!(!operator_token_matches("old,,new", ""));
+    }
+}
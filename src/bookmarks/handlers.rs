@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+
+use super::{emit_event, validate_video_id, AddOutcome, Bookmark, MAX_BOOKMARKS};
+use crate::{
+    app_state::AppState,
+    auth::require_user,
+    error::{ApiError, ApiErrorBody},
+};
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListBookmarksParams {
+    /// Zero-based offset, most recent first
+    pub offset: Option<usize>,
+    /// Page size (max 100)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ListBookmarksResponse {
+    pub total: u64,
+    pub bookmarks: Vec<Bookmark>,
+    /// Offset for the next page, absent on the last page
+    pub next_offset: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BookmarkResponse {
+    pub video_id: String,
+    /// Whether the video is bookmarked after the call
+    pub bookmarked: bool,
+}
+
+/// List the caller's bookmarked videos, most recent first
+#[utoipa::path(
+    get,
+    path = "",
+    params(
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the user"),
+        ListBookmarksParams
+    ),
+    tag = "bookmarks",
+    responses(
+        (status = 200, description = "Bookmarks", body = ListBookmarksResponse),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn list_bookmarks(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ListBookmarksParams>,
+    headers: HeaderMap,
+) -> Result<Json<ListBookmarksResponse>, ApiError> {
+    let user = require_user(&state, &headers).await?;
+    let offset = params.offset.unwrap_or(0);
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let (total, bookmarks) =
+        super::list_bookmarks(&state.yral_redis_store_dragonfly, &user, offset, limit).await?;
+    let next_offset = ((offset + limit) < total as usize).then_some(offset + limit);
+
+    Ok(Json(ListBookmarksResponse {
+        total,
+        bookmarks,
+        next_offset,
+    }))
+}
+
+/// Bookmark a video; bookmarking it again is a no-op
+#[utoipa::path(
+    put,
+    path = "/{video_id}",
+    params(
+        ("video_id" = String, Path, description = "Video ID"),
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the user")
+    ),
+    tag = "bookmarks",
+    responses(
+        (status = 200, description = "Video bookmarked", body = BookmarkResponse),
+        (status = 400, description = "Invalid video ID or bookmark limit reached", body = ApiErrorBody),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn add_bookmark(
+    State(state): State<Arc<AppState>>,
+    Path(video_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<BookmarkResponse>, ApiError> {
+    let user = require_user(&state, &headers).await?;
+    validate_video_id(&video_id).map_err(ApiError::InvalidRequest)?;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    match super::add_bookmark(&state.yral_redis_store_dragonfly, &user, &video_id, now).await? {
+        AddOutcome::Added => emit_event(&state, "bookmark_added", &user, &video_id, now),
+        AddOutcome::AlreadyBookmarked => {}
+        AddOutcome::LimitReached => {
+            return Err(ApiError::InvalidRequest(format!(
+                "Cannot bookmark more than {MAX_BOOKMARKS} videos"
+            )))
+        }
+    }
+
+    Ok(Json(BookmarkResponse {
+        video_id,
+        bookmarked: true,
+    }))
+}
+
+/// Remove a bookmark; removing a missing bookmark is a no-op
+#[utoipa::path(
+    delete,
+    path = "/{video_id}",
+    params(
+        ("video_id" = String, Path, description = "Video ID"),
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the user")
+    ),
+    tag = "bookmarks",
+    responses(
+        (status = 200, description = "Bookmark removed", body = BookmarkResponse),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn remove_bookmark(
+    State(state): State<Arc<AppState>>,
+    Path(video_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<BookmarkResponse>, ApiError> {
+    let user = require_user(&state, &headers).await?;
+
+    if super::remove_bookmark(&state.yral_redis_store_dragonfly, &user, &video_id).await? {
+        let now = chrono::Utc::now().timestamp_millis();
+        emit_event(&state, "bookmark_removed", &user, &video_id, now);
+    }
+
+    Ok(Json(BookmarkResponse {
+        video_id,
+        bookmarked: false,
+    }))
+}
@@ -0,0 +1,188 @@
+//! Watch-later bookmarks.
+//!
+//! Each principal has a sorted set of bookmarked video ids scored by the time
+//! they were saved (ms), so pages come back most recent first. Adds and
+//! removes are streamed to the warehouse as `bookmark_added` /
+//! `bookmark_removed` events, which ML treats as a positive signal.
+
+pub mod handlers;
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use candid::Principal;
+use redis::AsyncCommands;
+use serde::Serialize;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    app_state::AppState,
+    events::{event::Event, warehouse_events::WarehouseEvent},
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const BOOKMARKS_KEY_PREFIX: &str = "offchain:bookmarks";
+
+pub const MAX_BOOKMARKS: u64 = 1000;
+const MAX_VIDEO_ID_LEN: usize = 128;
+
+pub fn bookmarks_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(handlers::list_bookmarks))
+        .routes(routes!(handlers::add_bookmark, handlers::remove_bookmark))
+        .with_state(state)
+}
+
+fn bookmarks_key(user: &Principal) -> String {
+    format!("{BOOKMARKS_KEY_PREFIX}:{}", user.to_text())
+}
+
+/// Rejects ids that can't be ours before they end up in the set
+pub fn validate_video_id(video_id: &str) -> Result<(), String> {
+    if video_id.is_empty() || video_id.len() > MAX_VIDEO_ID_LEN {
+        return Err(format!("video_id must be 1-{MAX_VIDEO_ID_LEN} characters"));
+    }
+    if !video_id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("video_id contains invalid characters".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Bookmark {
+    pub video_id: String,
+    /// Unix timestamp in milliseconds
+    pub bookmarked_at: i64,
+}
+
+/// Outcome of [`add_bookmark`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddOutcome {
+    Added,
+    AlreadyBookmarked,
+    LimitReached,
+}
+
+/// Bookmarks `video_id` unless the user is at [`MAX_BOOKMARKS`]. Re-adding an
+/// existing bookmark keeps its original timestamp.
+pub async fn add_bookmark(
+    pool: &Arc<DragonflyPool>,
+    user: &Principal,
+    video_id: &str,
+    bookmarked_at: i64,
+) -> Result<AddOutcome> {
+    let key = bookmarks_key(user);
+    let mut conn = pool.get().await?;
+    let existing: Option<f64> = conn.zscore(&key, video_id).await?;
+    if existing.is_some() {
+        return Ok(AddOutcome::AlreadyBookmarked);
+    }
+    let count: u64 = conn.zcard(&key).await?;
+    if count >= MAX_BOOKMARKS {
+        return Ok(AddOutcome::LimitReached);
+    }
+
+    let added: u64 = conn.zadd(&key, video_id, bookmarked_at).await?;
+    Ok(if added > 0 {
+        AddOutcome::Added
+    } else {
+        AddOutcome::AlreadyBookmarked
+    })
+}
+
+/// Returns whether the bookmark existed
+pub async fn remove_bookmark(
+    pool: &Arc<DragonflyPool>,
+    user: &Principal,
+    video_id: &str,
+) -> Result<bool> {
+    let mut conn = pool.get().await?;
+    let removed: u64 = conn.zrem(bookmarks_key(user), video_id).await?;
+    Ok(removed > 0)
+}
+
+/// Moves `from`'s bookmarks onto `into`. Videos both saved keep the earlier
+/// timestamp, and only the newest [`MAX_BOOKMARKS`] survive. Returns how many
+/// bookmarks `from` had.
+pub async fn merge_bookmarks(
+    pool: &Arc<DragonflyPool>,
+    from: &Principal,
+    into: &Principal,
+) -> Result<u64> {
+    let (from_key, into_key) = (bookmarks_key(from), bookmarks_key(into));
+    let mut conn = pool.get().await?;
+    let count: u64 = conn.zcard(&from_key).await?;
+    if count == 0 {
+        return Ok(0);
+    }
+
+    redis::pipe()
+        .atomic()
+        .zunionstore_min(&into_key, &[&into_key, &from_key])
+        .ignore()
+        .zremrangebyrank(&into_key, 0, -(MAX_BOOKMARKS as isize) - 1)
+        .ignore()
+        .del(&from_key)
+        .ignore()
+        .query_async::<()>(&mut conn)
+        .await?;
+    Ok(count)
+}
+
+/// Page of a user's bookmarks, most recent first, plus the total count
+pub async fn list_bookmarks(
+    pool: &Arc<DragonflyPool>,
+    user: &Principal,
+    offset: usize,
+    limit: usize,
+) -> Result<(u64, Vec<Bookmark>)> {
+    let key = bookmarks_key(user);
+    let mut conn = pool.get().await?;
+    let total: u64 = conn.zcard(&key).await?;
+    let entries: Vec<(String, f64)> = conn
+        .zrevrange_withscores(&key, offset as isize, (offset + limit) as isize - 1)
+        .await?;
+
+    let bookmarks = entries
+        .into_iter()
+        .map(|(video_id, score)| Bookmark {
+            video_id,
+            bookmarked_at: score as i64,
+        })
+        .collect();
+    Ok((total, bookmarks))
+}
+
+fn emit_event(state: &AppState, event: &str, user: &Principal, video_id: &str, timestamp: i64) {
+    let params = serde_json::json!({
+        "user_id": user.to_text(),
+        "video_id": video_id,
+        "timestamp": timestamp,
+    });
+    Event::new(WarehouseEvent {
+        event: event.to_string(),
+        params: params.to_string(),
+    })
+    .stream_to_bigquery(state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_video_id() {
+        assert!(validate_video_id("a1b2c3d4e5f64a7b8c9d0e1f2a3b4c5d").is_ok());
+        assert!(validate_video_id("").is_err());
+        assert!(validate_video_id(&"a".repeat(MAX_VIDEO_ID_LEN + 1)).is_err());
+        assert!(validate_video_id("../etc").is_err());
+        assert_eq!(
+            bookmarks_key(&Principal::anonymous()),
+            "offchain:bookmarks:2vxsx-fae"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Shared wrapper for IC canister calls.
+//!
+//! Every call goes through a per-canister semaphore so a burst of events
+//! cannot flood one subnet, is bounded by a timeout, and is counted in
+//! in-process metrics served to operators at `/canister-metrics`. Queries
+//! are retried on transient failures with jittered exponential backoff;
+//! updates are never retried since most of them are not idempotent (view
+//! counts, follows).
+//!
+//! Agents other than the admin agent in `AppState` are built here too: a
+//! shared anonymous agent for third-party canisters and per-request agents
+//! acting as a signed-in user.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{http::HeaderMap, Json};
+use candid::Principal;
+use ic_agent::{Agent, AgentError, Identity};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::Serialize;
+use tokio::sync::Semaphore;
+use utoipa::ToSchema;
+
+use crate::{
+    auth::require_operator,
+    consts::{
+        RATE_LIMITS_CANISTER_ID, USER_INFO_SERVICE_CANISTER_ID, USER_POST_SERVICE_CANISTER_ID,
+    },
+    error::ApiError,
+};
+
+const IC_URL: &str = "https://ic0.app";
+
+const DEFAULT_CONCURRENCY: usize = 32;
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const MAX_QUERY_ATTEMPTS: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_millis(200);
+const SLOW_CALL: Duration = Duration::from_secs(5);
+
+static CONCURRENCY: Lazy<usize> = Lazy::new(|| {
+    std::env::var("CANISTER_CALL_CONCURRENCY")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|&limit| limit > 0)
+        .unwrap_or(DEFAULT_CONCURRENCY)
+});
+
+static TIMEOUT: Lazy<Duration> = Lazy::new(|| {
+    Duration::from_secs(
+        std::env::var("CANISTER_CALL_TIMEOUT_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS),
+    )
+});
+
+static ANONYMOUS_AGENT: Lazy<Agent> = Lazy::new(|| {
+    Agent::builder()
+        .with_url(IC_URL)
+        .build()
+        .expect("static IC URL is valid")
+});
+
+/// Unauthenticated agent for read-only calls, such as ICPSwap quotes
+pub fn anonymous_agent() -> Agent {
+    ANONYMOUS_AGENT.clone()
+}
+
+/// Agent that signs calls as the given user
+pub fn user_agent(identity: impl Identity + 'static) -> Result<Agent, AgentError> {
+    Agent::builder()
+        .with_identity(identity)
+        .with_url(IC_URL)
+        .build()
+}
+
+static SEMAPHORES: Lazy<Mutex<HashMap<Principal, Arc<Semaphore>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+static METRICS: Lazy<Mutex<BTreeMap<String, CallStats>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct CallStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    pub retries: u64,
+    pub total_latency_ms: u64,
+    pub max_latency_ms: u64,
+}
+
+fn canister_label(canister_id: &Principal) -> String {
+    if *canister_id == *USER_INFO_SERVICE_CANISTER_ID {
+        "user_info_service".to_string()
+    } else if *canister_id == *USER_POST_SERVICE_CANISTER_ID {
+        "user_post_service".to_string()
+    } else if *canister_id == *RATE_LIMITS_CANISTER_ID {
+        "rate_limits".to_string()
+    } else {
+        canister_id.to_text()
+    }
+}
+
+fn semaphore(canister_id: Principal) -> Arc<Semaphore> {
+    let mut semaphores = SEMAPHORES.lock().unwrap_or_else(|e| e.into_inner());
+    semaphores
+        .entry(canister_id)
+        .or_insert_with(|| Arc::new(Semaphore::new(*CONCURRENCY)))
+        .clone()
+}
+
+fn record(canister_id: &Principal, method: &str, f: impl FnOnce(&mut CallStats)) {
+    let key = format!("{}.{method}", canister_label(canister_id));
+    let mut metrics = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+    f(metrics.entry(key).or_default());
+}
+
+/// Failures worth retrying: the call may not have reached the replica, or
+/// the replica did not answer in time
+fn is_transient(error: &AgentError) -> bool {
+    matches!(
+        error,
+        AgentError::TimeoutWaitingForResponse() | AgentError::TransportError(_)
+    )
+}
+
+fn backoff(attempt: u32) -> Duration {
+    let base = BASE_BACKOFF * 2u32.pow(attempt.saturating_sub(1));
+    let jitter = rand::rng().random_range(0..=base.as_millis() as u64);
+    base + Duration::from_millis(jitter)
+}
+
+async fn call_once<T, Fut>(canister_id: Principal, method: &str, call: Fut) -> Result<T, AgentError>
+where
+    Fut: Future<Output = Result<T, AgentError>>,
+{
+    let semaphore = semaphore(canister_id);
+    // The semaphore is never closed
+    let _permit = semaphore.acquire().await.expect("semaphore closed");
+
+    let started = Instant::now();
+    let result = match tokio::time::timeout(*TIMEOUT, call).await {
+        Ok(result) => result,
+        Err(_) => Err(AgentError::TimeoutWaitingForResponse()),
+    };
+    let elapsed = started.elapsed();
+
+    if elapsed > SLOW_CALL {
+        log::warn!(
+            "Slow canister call {}.{method}: {}ms",
+            canister_label(&canister_id),
+            elapsed.as_millis()
+        );
+    }
+    record(&canister_id, method, |stats| {
+        let latency_ms = elapsed.as_millis() as u64;
+        stats.calls += 1;
+        stats.total_latency_ms += latency_ms;
+        stats.max_latency_ms = stats.max_latency_ms.max(latency_ms);
+        match &result {
+            Err(AgentError::TimeoutWaitingForResponse()) => {
+                stats.errors += 1;
+                stats.timeouts += 1;
+            }
+            Err(_) => stats.errors += 1,
+            Ok(_) => {}
+        }
+    });
+
+    result
+}
+
+/// Runs a query call, retrying transient failures
+pub async fn query<T, F, Fut>(
+    canister_id: Principal,
+    method: &str,
+    call: F,
+) -> Result<T, AgentError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, AgentError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match call_once(canister_id, method, call()).await {
+            Err(e) if is_transient(&e) && attempt < MAX_QUERY_ATTEMPTS => {
+                log::debug!(
+                    "Retrying {}.{method} after attempt {attempt}: {e}",
+                    canister_label(&canister_id)
+                );
+                record(&canister_id, method, |stats| stats.retries += 1);
+                tokio::time::sleep(backoff(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Runs an update call once
+pub async fn update<T, Fut>(
+    canister_id: Principal,
+    method: &str,
+    call: Fut,
+) -> Result<T, AgentError>
+where
+    Fut: Future<Output = Result<T, AgentError>>,
+{
+    call_once(canister_id, method, call).await
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CanisterMetricsResponse {
+    /// Concurrent calls allowed per canister
+    pub concurrency_limit: usize,
+    pub timeout_secs: u64,
+    /// Keyed by `<canister>.<method>`
+    pub calls: BTreeMap<String, CallStats>,
+}
+
+/// Per-method canister call counters since process start; operators only
+pub async fn canister_metrics_handler(
+    headers: HeaderMap,
+) -> Result<Json<CanisterMetricsResponse>, ApiError> {
+    require_operator(&headers)?;
+    let calls = METRICS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    Ok(Json(CanisterMetricsResponse {
+        concurrency_limit: *CONCURRENCY,
+        timeout_secs: TIMEOUT.as_secs(),
+        calls,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_query_retries_transient_errors_only() {
+        let canister = Principal::anonymous();
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let result: Result<u32, AgentError> = query(canister, "flaky", || async {
+            let n = attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if n == 0 {
+                Err(AgentError::TimeoutWaitingForResponse())
+            } else {
+                Ok(n)
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 1);
+
+        let attempts = std::sync::atomic::AtomicU32::new(0);
+        let result: Result<(), AgentError> = query(canister, "rejected", || async {
+            attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(AgentError::MessageError("bad".to_string()))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let stats = METRICS
+            .lock()
+            .unwrap()
+            .get(&format!("{}.flaky", canister.to_text()))
+            .cloned()
+            .unwrap();
+        assert_eq!((stats.calls, stats.errors, stats.retries), (2, 1, 1));
+    }
+}
@@ -0,0 +1,479 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::{extract::State, response::IntoResponse, Json};
+use candid::{CandidType, Nat, Principal};
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use google_cloud_bigquery::http::tabledata::insert_all::{InsertAllRequest, Row};
+use http::StatusCode;
+use ic_agent::Agent;
+use ic_utils::{call::AsyncCall, interfaces::ManagementCanister};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    canister::{agent_pool, utils::get_user_principal_canister_list_v2},
+    consts::{
+        RATE_LIMITS_CANISTER_ID, USER_INFO_SERVICE_CANISTER_ID, USER_POST_SERVICE_CANISTER_ID,
+    },
+    offchain_service::send_message_gchat_webhook,
+};
+
+const T_CYCLES: u64 = 1_000_000_000_000;
+
+// Alert when a canister drops below this many cycles
+const DEFAULT_MIN_CYCLES: u64 = 5 * T_CYCLES;
+// Top-ups refill a canister back up to this balance
+const DEFAULT_TOP_UP_TARGET_CYCLES: u64 = 10 * T_CYCLES;
+// Never spend more than this per monitoring run
+const DEFAULT_MAX_TOP_UP_CYCLES_PER_RUN: u64 = 20 * T_CYCLES;
+// Alert when total memory (heap + stable) crosses this size
+const DEFAULT_MAX_MEMORY_BYTES: u64 = 400 * 1024 * 1024 * 1024;
+
+const SCAN_CONCURRENCY: usize = 10;
+
+// Cycles ledger used to withdraw admin cycles into canisters
+const CYCLES_LEDGER_CANISTER_ID: &str = "um5iw-rqaaa-aaaaq-qaaba-cai";
+
+static CANISTER_ALERTS_WEBHOOK_URL: Lazy<Option<String>> =
+    Lazy::new(|| std::env::var("GCHAT_CANISTER_ALERTS_WEBHOOK_URL").ok());
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct CyclesMonitorRequest {
+    #[serde(default)]
+    pub auto_top_up: bool,
+    pub min_cycles: Option<u64>,
+    pub top_up_target_cycles: Option<u64>,
+    pub max_top_up_cycles_per_run: Option<u64>,
+    pub max_memory_bytes: Option<u64>,
+}
+
+/// Time-series row persisted to BigQuery for every scanned canister
+#[derive(Debug, Clone, Serialize)]
+pub struct CanisterResourceSnapshot {
+    pub canister_id: String,
+    pub name: String,
+    pub status: Option<String>,
+    pub cycles: Option<u64>,
+    pub memory_bytes: Option<u64>,
+    pub idle_cycles_burned_per_day: Option<u64>,
+    pub low_cycles: bool,
+    pub high_memory: bool,
+    pub topped_up_cycles: u64,
+    pub error: Option<String>,
+    pub checked_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CyclesMonitorSummary {
+    pub scanned: usize,
+    pub failed: usize,
+    pub low_cycles: usize,
+    pub high_memory: usize,
+    pub topped_up: usize,
+    pub total_top_up_cycles: u64,
+}
+
+#[derive(CandidType)]
+struct WithdrawArgs {
+    amount: Nat,
+    from_subaccount: Option<Vec<u8>>,
+    to: Principal,
+    created_at_time: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+enum RejectionCode {
+    NoError,
+    CanisterError,
+    SysTransient,
+    DestinationInvalid,
+    Unknown,
+    SysFatal,
+    CanisterReject,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+enum WithdrawError {
+    GenericError {
+        message: String,
+        error_code: Nat,
+    },
+    TemporarilyUnavailable,
+    FailedToWithdraw {
+        fee_block: Option<Nat>,
+        rejection_code: RejectionCode,
+        rejection_reason: String,
+    },
+    Duplicate {
+        duplicate_of: Nat,
+    },
+    BadFee {
+        expected_fee: Nat,
+    },
+    InvalidReceiver {
+        receiver: Principal,
+    },
+    CreatedInFuture {
+        ledger_time: u64,
+    },
+    TooOld,
+    InsufficientFunds {
+        balance: Nat,
+    },
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+enum WithdrawResult {
+    Ok(Nat),
+    Err(WithdrawError),
+}
+
+fn monitored_service_canisters() -> Vec<(Principal, String)> {
+    vec![
+        (*RATE_LIMITS_CANISTER_ID, "rate_limits".to_string()),
+        (
+            *USER_INFO_SERVICE_CANISTER_ID,
+            "user_info_service".to_string(),
+        ),
+        (
+            *USER_POST_SERVICE_CANISTER_ID,
+            "user_post_service".to_string(),
+        ),
+    ]
+}
+
+fn nat_to_u64(value: &Nat) -> u64 {
+    value.0.clone().try_into().unwrap_or(u64::MAX)
+}
+
+/// Cycles needed to bring a canister back to the target, bounded by what's left of the run budget
+fn plan_top_up(cycles: u64, min_cycles: u64, target_cycles: u64, remaining_budget: u64) -> u64 {
+    if cycles >= min_cycles {
+        return 0;
+    }
+    target_cycles.saturating_sub(cycles).min(remaining_budget)
+}
+
+async fn scan_canister(
+    agent: &Agent,
+    canister_id: Principal,
+    name: String,
+    min_cycles: u64,
+    max_memory_bytes: u64,
+) -> CanisterResourceSnapshot {
+    let management_canister = ManagementCanister::create(agent);
+    let checked_at = Utc::now().to_rfc3339();
+
+    match agent_pool::update(
+        Principal::management_canister(),
+        "canister_status",
+        management_canister
+            .canister_status(&canister_id)
+            .call_and_wait(),
+    )
+    .await
+    {
+        Ok((status,)) => {
+            let cycles = nat_to_u64(&status.cycles);
+            let memory_bytes = nat_to_u64(&status.memory_size);
+            CanisterResourceSnapshot {
+                canister_id: canister_id.to_text(),
+                name,
+                status: Some(format!("{:?}", status.status)),
+                cycles: Some(cycles),
+                memory_bytes: Some(memory_bytes),
+                idle_cycles_burned_per_day: Some(nat_to_u64(&status.idle_cycles_burned_per_day)),
+                low_cycles: cycles < min_cycles,
+                high_memory: memory_bytes > max_memory_bytes,
+                topped_up_cycles: 0,
+                error: None,
+                checked_at,
+            }
+        }
+        Err(e) => CanisterResourceSnapshot {
+            canister_id: canister_id.to_text(),
+            name,
+            status: None,
+            cycles: None,
+            memory_bytes: None,
+            idle_cycles_burned_per_day: None,
+            low_cycles: false,
+            high_memory: false,
+            topped_up_cycles: 0,
+            error: Some(e.to_string()),
+            checked_at,
+        },
+    }
+}
+
+/// Withdraw cycles from the admin's cycles ledger balance into the canister
+async fn top_up_canister(agent: &Agent, canister_id: Principal, amount: u64) -> Result<Nat> {
+    let ledger = Principal::from_text(CYCLES_LEDGER_CANISTER_ID)
+        .context("Invalid cycles ledger canister ID")?;
+
+    let args = WithdrawArgs {
+        amount: Nat::from(amount),
+        from_subaccount: None,
+        to: canister_id,
+        created_at_time: Some(Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64),
+    };
+
+    let arg = candid::encode_one(&args).context("Failed to encode withdraw args")?;
+    let response = agent_pool::update(
+        ledger,
+        "withdraw",
+        agent
+            .update(&ledger, "withdraw")
+            .with_arg(arg)
+            .call_and_wait(),
+    )
+    .await
+    .context("Failed to call cycles ledger withdraw")?;
+
+    match candid::decode_one::<WithdrawResult>(&response)
+        .context("Failed to decode withdraw response")?
+    {
+        WithdrawResult::Ok(block_index) => Ok(block_index),
+        WithdrawResult::Err(e) => Err(anyhow::anyhow!("Cycles withdraw failed: {:?}", e)),
+    }
+}
+
+async fn insert_snapshots_to_bigquery(
+    bq_client: &google_cloud_bigquery::client::Client,
+    snapshots: &[CanisterResourceSnapshot],
+) -> Result<()> {
+    let rows: Vec<Row<CanisterResourceSnapshot>> = snapshots
+        .iter()
+        .map(|snapshot| Row {
+            insert_id: None,
+            json: snapshot.clone(),
+        })
+        .collect();
+
+    let request = InsertAllRequest {
+        rows,
+        ..Default::default()
+    };
+
+    let res = bq_client
+        .tabledata()
+        .insert(
+            "hot-or-not-feed-intelligence",
+            "yral_ds",
+            "canister_health_snapshots",
+            &request,
+        )
+        .await?;
+
+    if let Some(errors) = res.insert_errors {
+        if !errors.is_empty() {
+            log::error!("canister_health_snapshots insert errors: {errors:?}");
+            return Err(anyhow::anyhow!(
+                "Failed to insert canister health snapshots to bigquery"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_canister_alerts(snapshots: &[CanisterResourceSnapshot]) {
+    let alert_lines: Vec<String> = snapshots
+        .iter()
+        .filter_map(|s| {
+            if let Some(error) = &s.error {
+                Some(format!(
+                    "❌ {} ({}): status check failed: {}",
+                    s.name, s.canister_id, error
+                ))
+            } else if s.low_cycles || s.high_memory {
+                Some(format!(
+                    "⚠️ {} ({}): cycles={} memory={}B{}{}",
+                    s.name,
+                    s.canister_id,
+                    s.cycles.unwrap_or_default(),
+                    s.memory_bytes.unwrap_or_default(),
+                    if s.low_cycles { " [LOW CYCLES]" } else { "" },
+                    if s.topped_up_cycles > 0 {
+                        format!(" [TOPPED UP {}]", s.topped_up_cycles)
+                    } else {
+                        String::new()
+                    }
+                ))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if alert_lines.is_empty() {
+        return;
+    }
+
+    log::error!(
+        "Canister health thresholds breached for {} canisters",
+        alert_lines.len()
+    );
+
+    let Some(webhook_url) = CANISTER_ALERTS_WEBHOOK_URL.as_ref() else {
+        log::warn!("GCHAT_CANISTER_ALERTS_WEBHOOK_URL not set, skipping chat alert");
+        return;
+    };
+
+    let message = json!({
+        "text": format!("*Canister health alert*\n{}", alert_lines.join("\n"))
+    });
+    if let Err(e) = send_message_gchat_webhook(webhook_url, message).await {
+        log::error!("Failed to send canister health alert: {e:?}");
+    }
+}
+
+/// Scan all canisters for cycles and memory, persist the snapshots, alert and optionally top up
+pub async fn run_cycles_monitor(
+    app_state: &Arc<AppState>,
+    request: &CyclesMonitorRequest,
+) -> Result<CyclesMonitorSummary> {
+    let min_cycles = request.min_cycles.unwrap_or(DEFAULT_MIN_CYCLES);
+    let target_cycles = request
+        .top_up_target_cycles
+        .unwrap_or(DEFAULT_TOP_UP_TARGET_CYCLES)
+        .max(min_cycles);
+    let max_memory_bytes = request.max_memory_bytes.unwrap_or(DEFAULT_MAX_MEMORY_BYTES);
+    let mut remaining_budget = request
+        .max_top_up_cycles_per_run
+        .unwrap_or(DEFAULT_MAX_TOP_UP_CYCLES_PER_RUN);
+
+    let mut canisters = monitored_service_canisters();
+    match get_user_principal_canister_list_v2(&app_state.agent).await {
+        Ok(list) => canisters.extend(list.into_iter().map(|(user_principal, canister_id)| {
+            (canister_id, format!("user_canister:{user_principal}"))
+        })),
+        Err(e) => log::warn!("Failed to fetch user canister list: {e:?}"),
+    }
+
+    let agent = &app_state.agent;
+    let mut snapshots: Vec<CanisterResourceSnapshot> = stream::iter(canisters)
+        .map(|(canister_id, name)| {
+            scan_canister(agent, canister_id, name, min_cycles, max_memory_bytes)
+        })
+        .buffer_unordered(SCAN_CONCURRENCY)
+        .collect()
+        .await;
+
+    if request.auto_top_up {
+        // Lowest balances first so the per-run cap goes where it's needed most
+        snapshots.sort_by_key(|s| s.cycles.unwrap_or(u64::MAX));
+
+        for snapshot in snapshots.iter_mut() {
+            let Some(cycles) = snapshot.cycles else {
+                continue;
+            };
+            let amount = plan_top_up(cycles, min_cycles, target_cycles, remaining_budget);
+            if amount == 0 {
+                continue;
+            }
+
+            let Ok(canister_id) = Principal::from_text(&snapshot.canister_id) else {
+                continue;
+            };
+            match top_up_canister(agent, canister_id, amount).await {
+                Ok(block_index) => {
+                    log::info!(
+                        "Topped up {} with {} cycles (block {})",
+                        snapshot.canister_id,
+                        amount,
+                        block_index
+                    );
+                    snapshot.topped_up_cycles = amount;
+                    remaining_budget -= amount;
+                }
+                Err(e) => {
+                    log::error!("Failed to top up {}: {e:?}", snapshot.canister_id);
+                }
+            }
+
+            if remaining_budget == 0 {
+                log::warn!("Cycles top-up budget for this run exhausted");
+                break;
+            }
+        }
+    }
+
+    if let Err(e) = insert_snapshots_to_bigquery(&app_state.bigquery_client, &snapshots).await {
+        log::error!("Failed to persist canister health snapshots: {e:?}");
+    }
+
+    send_canister_alerts(&snapshots).await;
+
+    Ok(CyclesMonitorSummary {
+        scanned: snapshots.len(),
+        failed: snapshots.iter().filter(|s| s.error.is_some()).count(),
+        low_cycles: snapshots.iter().filter(|s| s.low_cycles).count(),
+        high_memory: snapshots.iter().filter(|s| s.high_memory).count(),
+        topped_up: snapshots.iter().filter(|s| s.topped_up_cycles > 0).count(),
+        total_top_up_cycles: snapshots.iter().map(|s| s.topped_up_cycles).sum(),
+    })
+}
+
+/// QStash scheduled job: canister cycles and memory monitoring
+#[utoipa::path(
+    post,
+    path = "/canister_cycles_monitor",
+    request_body = CyclesMonitorRequest,
+    responses(
+        (status = 200, description = "Scan summary", body = CyclesMonitorSummary),
+        (status = 500, description = "Monitor failed", body = serde_json::Value)
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state))]
+pub async fn cycles_monitor_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CyclesMonitorRequest>,
+) -> impl IntoResponse {
+    match run_cycles_monitor(&state, &request).await {
+        Ok(summary) => {
+            log::info!("Canister cycles monitor completed: {:?}", summary);
+            (StatusCode::OK, Json(json!(summary))).into_response()
+        }
+        Err(e) => {
+            log::error!("Canister cycles monitor failed: {e:?}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": format!("Cycles monitor failed: {}", e) })),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_top_up() {
+        // Healthy canister is left alone
+        assert_eq!(
+            plan_top_up(6 * T_CYCLES, 5 * T_CYCLES, 10 * T_CYCLES, 20 * T_CYCLES),
+            0
+        );
+        // Low canister is refilled to target
+        assert_eq!(
+            plan_top_up(2 * T_CYCLES, 5 * T_CYCLES, 10 * T_CYCLES, 20 * T_CYCLES),
+            8 * T_CYCLES
+        );
+        // Per-run cap bounds the top-up
+        assert_eq!(
+            plan_top_up(2 * T_CYCLES, 5 * T_CYCLES, 10 * T_CYCLES, 3 * T_CYCLES),
+            3 * T_CYCLES
+        );
+        assert_eq!(plan_top_up(0, 5 * T_CYCLES, 10 * T_CYCLES, 0), 0);
+    }
+}
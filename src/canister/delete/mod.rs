@@ -12,6 +12,7 @@ use yral_canisters_client::{
 use crate::yral_auth::dragonfly::{format_to_dragonfly_key, YRAL_AUTH_REDIS_KEY_PREFIX};
 use crate::{
     app_state::AppState,
+    canister::agent_pool,
     consts::{USER_INFO_SERVICE_CANISTER_ID, USER_POST_SERVICE_CANISTER_ID},
     posts::{delete_post::bulk_insert_video_delete_rows_v2, types::UserPostV2},
 };
@@ -64,9 +65,12 @@ pub async fn delete_canister_data(
             None => {
                 let user_info_service =
                     UserInfoService(*USER_INFO_SERVICE_CANISTER_ID, &state.agent);
-                match user_info_service
-                    .get_user_profile_details_v_7(user_principal)
-                    .await
+                match agent_pool::query(
+                    *USER_INFO_SERVICE_CANISTER_ID,
+                    "get_user_profile_details_v_7",
+                    || user_info_service.get_user_profile_details_v_7(user_principal),
+                )
+                .await
                 {
                     Ok(yral_canisters_client::user_info_service::Result7::Ok(profile)) => {
                         match profile.account_type {
@@ -211,14 +215,26 @@ pub async fn delete_canister_data(
     // 2. Delete user info from UserInfoService (including bots if this is a main account)
     let user_info_service = UserInfoService(*USER_INFO_SERVICE_CANISTER_ID, &state.agent);
     for bot_principal in &bot_principals {
-        if let Err(e) = user_info_service.delete_user_info(*bot_principal).await {
+        if let Err(e) = agent_pool::update(
+            *USER_INFO_SERVICE_CANISTER_ID,
+            "delete_user_info",
+            user_info_service.delete_user_info(*bot_principal),
+        )
+        .await
+        {
             log::error!(
                 "Failed to delete bot {} from canister: {e}",
                 bot_principal.to_text()
             );
         }
     }
-    if let Err(e) = user_info_service.delete_user_info(user_principal).await {
+    if let Err(e) = agent_pool::update(
+        *USER_INFO_SERVICE_CANISTER_ID,
+        "delete_user_info",
+        user_info_service.delete_user_info(user_principal),
+    )
+    .await
+    {
         log::error!("Failed to delete user info for user {user_principal}: {e}");
     }
 
@@ -278,9 +294,19 @@ async fn get_canister_posts(
         loop {
             let end = start + batch_size;
 
-            let posts = UserPostService(*USER_POST_SERVICE_CANISTER_ID, agent)
-                .get_posts_of_this_user_profile_with_pagination_cursor(user_principal, start, end)
-                .await?;
+            let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, agent);
+            let posts = agent_pool::query(
+                *USER_POST_SERVICE_CANISTER_ID,
+                "get_posts_of_this_user_profile_with_pagination_cursor",
+                || {
+                    user_post_service.get_posts_of_this_user_profile_with_pagination_cursor(
+                        user_principal,
+                        start,
+                        end,
+                    )
+                },
+            )
+            .await?;
 
             let result_len = posts.len();
             all_posts.extend(posts.into_iter().map(|p| UserPostV2 {
@@ -322,9 +348,12 @@ async fn delete_posts_from_canister(agent: &Agent, posts: Vec<UserPostV2>) {
                     // Use UserPostService for USER_INFO_SERVICE users
                     let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &agent);
 
-                    match user_post_service
-                        .delete_post(post.post_id.clone()) // post_id is already String
-                        .await
+                    match agent_pool::update(
+                        *USER_POST_SERVICE_CANISTER_ID,
+                        "delete_post",
+                        user_post_service.delete_post(post.post_id.clone()), // post_id is already String
+                    )
+                    .await
                     {
                         Ok(yral_canisters_client::user_post_service::Result_::Ok) => Ok(()),
                         Ok(yral_canisters_client::user_post_service::Result_::Err(_)) => {
@@ -7,6 +7,7 @@ use utoipa::ToSchema;
 
 use crate::{
     app_state::AppState,
+    canister::agent_pool,
     consts::{
         RATE_LIMITS_CANISTER_ID, USER_INFO_SERVICE_CANISTER_ID, USER_POST_SERVICE_CANISTER_ID,
     },
@@ -36,7 +37,7 @@ async fn check_rate_limits(app_state: &AppState) -> CanisterStatus {
     let canister_id = *RATE_LIMITS_CANISTER_ID;
     let client = RateLimits(canister_id, &app_state.agent);
 
-    match client.get_version().await {
+    match agent_pool::query(canister_id, "get_version", || client.get_version()).await {
         Ok(version) => CanisterStatus {
             canister_id: canister_id.to_text(),
             name: "rate_limits".to_string(),
@@ -58,7 +59,7 @@ async fn check_user_info_service(app_state: &AppState) -> CanisterStatus {
     let canister_id = *USER_INFO_SERVICE_CANISTER_ID;
     let client = UserInfoService(canister_id, &app_state.agent);
 
-    match client.get_version().await {
+    match agent_pool::query(canister_id, "get_version", || client.get_version()).await {
         Ok(version) => CanisterStatus {
             canister_id: canister_id.to_text(),
             name: "user_info_service".to_string(),
@@ -80,7 +81,7 @@ async fn check_user_post_service(app_state: &AppState) -> CanisterStatus {
     let canister_id = *USER_POST_SERVICE_CANISTER_ID;
     let client = UserPostService(canister_id, &app_state.agent);
 
-    match client.get_version().await {
+    match agent_pool::query(canister_id, "get_version", || client.get_version()).await {
         Ok(version) => CanisterStatus {
             canister_id: canister_id.to_text(),
             name: "user_post_service".to_string(),
@@ -1,3 +1,6 @@
+pub mod agent_pool;
+#[cfg(not(feature = "local-bin"))]
+pub mod cycles_monitor;
 pub mod delete;
 pub mod health;
 pub mod queries;
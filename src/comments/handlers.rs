@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use candid::Principal;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+use yral_canisters_client::user_post_service::{Post, Result2, UserPostService};
+
+use super::{
+    emit_event, list_post_comments, load_comment, remove_comment, store_comment, validate_text,
+    Comment,
+};
+use crate::{
+    app_state::AppState,
+    auth::require_user,
+    canister::agent_pool,
+    consts::USER_POST_SERVICE_CANISTER_ID,
+    error::{ApiError, ApiErrorBody},
+    text_moderation::{moderate_text, TextSurface},
+};
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+async fn post_creator(state: &AppState, post_id: &str) -> Result<Principal, ApiError> {
+    let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &state.agent);
+    let Result2::Ok(Post {
+        creator_principal, ..
+    }) = agent_pool::query(
+        *USER_POST_SERVICE_CANISTER_ID,
+        "get_individual_post_details_by_id",
+        || user_post_service.get_individual_post_details_by_id(post_id.to_string()),
+    )
+    .await?
+    else {
+        return Err(ApiError::NotFound(format!("Post {post_id} not found")));
+    };
+    Ok(creator_principal)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListCommentsParams {
+    /// Zero-based offset, newest first
+    pub offset: Option<usize>,
+    /// Page size (max 100)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ListCommentsResponse {
+    pub post_id: String,
+    pub total: u64,
+    pub comments: Vec<Comment>,
+    /// Offset for the next page, absent on the last page
+    pub next_offset: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateCommentRequest {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeleteCommentResponse {
+    pub success: bool,
+}
+
+/// List a post's comments, newest first
+#[utoipa::path(
+    get,
+    path = "/{post_id}",
+    params(
+        ("post_id" = String, Path, description = "Post ID"),
+        ListCommentsParams
+    ),
+    tag = "comments",
+    responses(
+        (status = 200, description = "Comments", body = ListCommentsResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn list_comments(
+    State(state): State<Arc<AppState>>,
+    Path(post_id): Path<String>,
+    Query(params): Query<ListCommentsParams>,
+) -> Result<Json<ListCommentsResponse>, ApiError> {
+    let offset = params.offset.unwrap_or(0);
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let (total, comments) =
+        list_post_comments(&state.yral_redis_store_dragonfly, &post_id, offset, limit).await?;
+    let next_offset = ((offset + limit) < total as usize).then_some(offset + limit);
+
+    Ok(Json(ListCommentsResponse {
+        post_id,
+        total,
+        comments,
+        next_offset,
+    }))
+}
+
+/// Comment on a post
+#[utoipa::path(
+    post,
+    path = "/{post_id}",
+    params(
+        ("post_id" = String, Path, description = "Post ID"),
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the commenter")
+    ),
+    request_body = CreateCommentRequest,
+    tag = "comments",
+    responses(
+        (status = 200, description = "Created comment", body = Comment),
+        (status = 400, description = "Empty, overlong or blocked by the text filter", body = ApiErrorBody),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 404, description = "Post not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers, request))]
+pub async fn create_comment(
+    State(state): State<Arc<AppState>>,
+    Path(post_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<CreateCommentRequest>,
+) -> Result<Json<Comment>, ApiError> {
+    let author = require_user(&state, &headers).await?;
+    let text = validate_text(&request.text).map_err(ApiError::InvalidRequest)?;
+    // Only checks the post exists
+    post_creator(&state, &post_id).await?;
+
+    let comment_id = uuid::Uuid::new_v4().to_string();
+    let moderation = moderate_text(&state, TextSurface::Comment, &comment_id, &text).await;
+    if moderation.is_blocked() {
+        return Err(ApiError::InvalidRequest(
+            "Comment violates the content guidelines".to_string(),
+        ));
+    }
+
+    let comment = Comment {
+        comment_id,
+        post_id,
+        author_principal: author.to_text(),
+        text: moderation.text,
+        created_at: chrono::Utc::now().timestamp_millis(),
+        flagged: moderation.flagged,
+    };
+    store_comment(&state.yral_redis_store_dragonfly, &comment).await?;
+    emit_event(&state, "comment_created", &comment);
+
+    Ok(Json(comment))
+}
+
+/// Delete a comment; allowed for its author and the post's creator
+#[utoipa::path(
+    delete,
+    path = "/{post_id}/{comment_id}",
+    params(
+        ("post_id" = String, Path, description = "Post ID"),
+        ("comment_id" = String, Path, description = "Comment ID"),
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the caller")
+    ),
+    tag = "comments",
+    responses(
+        (status = 200, description = "Comment deleted", body = DeleteCommentResponse),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Caller may not delete this comment", body = ApiErrorBody),
+        (status = 404, description = "Comment not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn delete_comment(
+    State(state): State<Arc<AppState>>,
+    Path((post_id, comment_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Json<DeleteCommentResponse>, ApiError> {
+    let caller = require_user(&state, &headers).await?;
+    let pool = &state.yral_redis_store_dragonfly;
+
+    let comment = load_comment(pool, &comment_id)
+        .await?
+        .filter(|comment| comment.post_id == post_id)
+        .ok_or_else(|| ApiError::NotFound(format!("Comment {comment_id} not found")))?;
+
+    if comment.author_principal != caller.to_text()
+        && post_creator(&state, &post_id).await? != caller
+    {
+        return Err(ApiError::Forbidden(
+            "Only the author or the post's creator can delete a comment".to_string(),
+        ));
+    }
+
+    remove_comment(pool, &comment).await?;
+    emit_event(&state, "comment_deleted", &comment);
+
+    Ok(Json(DeleteCommentResponse { success: true }))
+}
@@ -0,0 +1,180 @@
+//! Off-chain comments on posts.
+//!
+//! Comments are stored in Dragonfly: a sorted set of comment ids per post
+//! (scored by creation time, so pages come back newest first) and a JSON
+//! document per comment. Creates and deletes are streamed to the warehouse
+//! as `comment_created` / `comment_deleted` events.
+
+pub mod handlers;
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    app_state::AppState,
+    events::{event::Event, warehouse_events::WarehouseEvent},
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const POST_COMMENTS_KEY_PREFIX: &str = "offchain:comments:post";
+const COMMENT_KEY_PREFIX: &str = "offchain:comments:doc";
+
+pub const MAX_COMMENT_LEN: usize = 1000;
+
+pub fn comments_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(handlers::list_comments, handlers::create_comment))
+        .routes(routes!(handlers::delete_comment))
+        .with_state(state)
+}
+
+fn post_comments_key(post_id: &str) -> String {
+    format!("{POST_COMMENTS_KEY_PREFIX}:{post_id}")
+}
+
+fn comment_key(comment_id: &str) -> String {
+    format!("{COMMENT_KEY_PREFIX}:{comment_id}")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Comment {
+    pub comment_id: String,
+    pub post_id: String,
+    pub author_principal: String,
+    pub text: String,
+    /// Unix timestamp in milliseconds
+    pub created_at: i64,
+    /// Matched the text moderation filter but was kept (flag or mask mode)
+    #[serde(default)]
+    pub flagged: bool,
+}
+
+/// Trims the comment and rejects empty or overlong text
+pub fn validate_text(text: &str) -> Result<String, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Comment must not be empty".to_string());
+    }
+    if text.chars().count() > MAX_COMMENT_LEN {
+        return Err(format!(
+            "Comment must be at most {MAX_COMMENT_LEN} characters"
+        ));
+    }
+    Ok(text.to_string())
+}
+
+pub async fn store_comment(pool: &Arc<DragonflyPool>, comment: &Comment) -> Result<()> {
+    let payload = serde_json::to_string(comment)?;
+    let mut conn = pool.get().await?;
+    let _: () = redis::pipe()
+        .set(comment_key(&comment.comment_id), payload)
+        .zadd(
+            post_comments_key(&comment.post_id),
+            &comment.comment_id,
+            comment.created_at,
+        )
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn load_comment(pool: &Arc<DragonflyPool>, comment_id: &str) -> Result<Option<Comment>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.get(comment_key(comment_id)).await?;
+    payload
+        .map(|payload| serde_json::from_str(&payload).map_err(Into::into))
+        .transpose()
+}
+
+pub async fn remove_comment(pool: &Arc<DragonflyPool>, comment: &Comment) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = redis::pipe()
+        .del(comment_key(&comment.comment_id))
+        .zrem(post_comments_key(&comment.post_id), &comment.comment_id)
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+/// Drops every comment on a deleted post; returns how many there were
+pub async fn remove_post_comments(pool: &Arc<DragonflyPool>, post_id: &str) -> Result<u64> {
+    let mut conn = pool.get().await?;
+    let key = post_comments_key(post_id);
+    let ids: Vec<String> = conn.zrange(&key, 0, -1).await?;
+
+    let mut keys: Vec<String> = ids.iter().map(|id| comment_key(id)).collect();
+    keys.push(key);
+    let _: () = conn.del(keys).await?;
+    Ok(ids.len() as u64)
+}
+
+/// Page of a post's comments, newest first, plus the post's total count
+pub async fn list_post_comments(
+    pool: &Arc<DragonflyPool>,
+    post_id: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<(u64, Vec<Comment>)> {
+    let mut conn = pool.get().await?;
+    let key = post_comments_key(post_id);
+    let total: u64 = conn.zcard(&key).await?;
+    let ids: Vec<String> = conn
+        .zrevrange(&key, offset as isize, (offset + limit) as isize - 1)
+        .await?;
+    if ids.is_empty() {
+        return Ok((total, Vec::new()));
+    }
+
+    let doc_keys: Vec<String> = ids.iter().map(|id| comment_key(id)).collect();
+    let docs: Vec<Option<String>> = redis::cmd("MGET")
+        .arg(&doc_keys)
+        .query_async(&mut conn)
+        .await?;
+    let comments = docs
+        .into_iter()
+        .flatten()
+        .filter_map(|payload| serde_json::from_str(&payload).ok())
+        .collect();
+
+    Ok((total, comments))
+}
+
+/// Number of comments on a post
+pub async fn comment_count(pool: &Arc<DragonflyPool>, post_id: &str) -> Result<u64> {
+    let mut conn = pool.get().await?;
+    Ok(conn.zcard(post_comments_key(post_id)).await?)
+}
+
+fn emit_event(state: &AppState, event: &str, comment: &Comment) {
+    let params = serde_json::json!({
+        "comment_id": comment.comment_id,
+        "post_id": comment.post_id,
+        "user_id": comment.author_principal,
+        "comment_length": comment.text.chars().count(),
+        "flagged": comment.flagged,
+        "created_at": comment.created_at,
+    });
+    Event::new(WarehouseEvent {
+        event: event.to_string(),
+        params: params.to_string(),
+    })
+    .stream_to_bigquery(state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_text() {
+        assert_eq!(validate_text("  nice one  ").unwrap(), "nice one");
+        assert!(validate_text("   ").is_err());
+        assert!(validate_text(&"a".repeat(MAX_COMMENT_LEN)).is_ok());
+        assert!(validate_text(&"a".repeat(MAX_COMMENT_LEN + 1)).is_err());
+    }
+}
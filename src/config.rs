@@ -1,4 +1,15 @@
+//! Service configuration.
+//!
+//! Secrets and endpoints are flat top-level keys. Module tunables live in
+//! typed sections with defaults, set in `config.toml` (`[dedup]`) or through
+//! env with a `__` separator (`DEDUP__HAMMING_THRESHOLD=25`), and are
+//! validated at startup. Selected values can be changed at runtime through
+//! the Redis overlay in [`runtime`].
+
+pub mod runtime;
+
 use std::{
+    collections::BTreeMap,
     env,
     fs::OpenOptions,
     io::{BufWriter, Write},
@@ -6,8 +17,9 @@ use std::{
 
 use config::{Config, ConfigError, Environment, File};
 use once_cell::sync::Lazy;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
+use utoipa::ToSchema;
 
 use crate::consts::STORJ_INTERFACE_TOKEN;
 
@@ -19,6 +31,397 @@ pub struct AppConfig {
     #[cfg(not(feature = "local-bin"))]
     pub milvus_url: Option<String>,
     pub naitik_multi_service_api_jwt_token: String,
+    #[serde(default)]
+    pub rewards: RewardsSection,
+    #[serde(default)]
+    pub dedup: DedupSection,
+    #[serde(default)]
+    pub leaderboard: LeaderboardSection,
+    #[serde(default)]
+    pub videogen: VideogenSection,
+    #[serde(default)]
+    pub rates: RatesSection,
+    #[serde(default)]
+    pub redis: RedisSection,
+    #[serde(default)]
+    pub event_sink: EventSinkSection,
+    #[serde(default)]
+    pub delegated_identity: DelegatedIdentitySection,
+    #[serde(default)]
+    pub event_velocity: EventVelocitySection,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RewardsSection {
+    /// Largest single view-milestone payout
+    pub max_view_reward_e8s: u64,
+    /// Largest single mission payout
+    pub max_mission_reward_e8s: u64,
+    /// Signups per referrer per hour before referrals are flagged
+    pub referral_fraud_threshold: usize,
+    /// Reward notifications are batched into one digest per window; 0 sends
+    /// one per reward
+    pub digest_window_secs: u64,
+    /// Views of a video by the same user, on any device, within this window
+    /// of their last view count as replays
+    pub view_dedup_window_secs: u64,
+}
+
+impl Default for RewardsSection {
+    fn default() -> Self {
+        Self {
+            max_view_reward_e8s: 10_000_000_000,
+            max_mission_reward_e8s: 1_000_000_000,
+            referral_fraud_threshold: 20,
+            digest_window_secs: 60 * 60,
+            view_dedup_window_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+impl RewardsSection {
+    fn validate(&self) -> Result<(), String> {
+        if self.max_view_reward_e8s == 0 || self.max_mission_reward_e8s == 0 {
+            return Err("rewards payout caps must be positive".to_string());
+        }
+        if self.referral_fraud_threshold == 0 {
+            return Err("rewards.referral_fraud_threshold must be positive".to_string());
+        }
+        if self.view_dedup_window_secs == 0 {
+            return Err("rewards.view_dedup_window_secs must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Defaults for the dedup config until one is stored through
+/// `/api/v1/dedup-config`, which is how dedup is tuned at runtime
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct DedupSection {
+    pub hamming_threshold: u32,
+    pub backfill_hamming_threshold: u32,
+    pub top_k: u32,
+}
+
+impl Default for DedupSection {
+    fn default() -> Self {
+        Self {
+            hamming_threshold: 30,
+            backfill_hamming_threshold: 20,
+            top_k: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeaderboardSection {
+    pub max_tournament_duration_secs: i64,
+    /// Tournaments can't be scheduled further out than this
+    pub max_start_lead_secs: i64,
+    pub max_yral_prize_pool: f64,
+    /// Window over which score gains are summed for anomaly detection
+    pub velocity_window_secs: u64,
+    /// Score gain within one window that freezes the participant for review
+    pub max_score_per_window: f64,
+}
+
+impl Default for LeaderboardSection {
+    fn default() -> Self {
+        Self {
+            max_tournament_duration_secs: 30 * 24 * 60 * 60,
+            max_start_lead_secs: 90 * 24 * 60 * 60,
+            max_yral_prize_pool: 10_000_000.0,
+            velocity_window_secs: 60,
+            max_score_per_window: 500.0,
+        }
+    }
+}
+
+impl LeaderboardSection {
+    fn validate(&self) -> Result<(), String> {
+        if self.max_tournament_duration_secs <= 0 || self.max_start_lead_secs < 0 {
+            return Err("leaderboard durations must be positive".to_string());
+        }
+        if !self.max_yral_prize_pool.is_finite() || self.max_yral_prize_pool <= 0.0 {
+            return Err("leaderboard.max_yral_prize_pool must be positive".to_string());
+        }
+        if self.velocity_window_secs == 0 {
+            return Err("leaderboard.velocity_window_secs must be positive".to_string());
+        }
+        if !self.max_score_per_window.is_finite() || self.max_score_per_window <= 0.0 {
+            return Err("leaderboard.max_score_per_window must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct VideogenSection {
+    /// Daily spend that triggers a GChat alert; no alerts when unset
+    pub daily_budget_usd: Option<f64>,
+}
+
+impl Default for VideogenSection {
+    fn default() -> Self {
+        Self {
+            // Predates the section
+            daily_budget_usd: env::var("VIDEOGEN_DAILY_BUDGET_USD")
+                .ok()
+                .and_then(|budget| budget.parse().ok()),
+        }
+    }
+}
+
+impl VideogenSection {
+    fn validate(&self) -> Result<(), String> {
+        match self.daily_budget_usd {
+            Some(budget) if !budget.is_finite() || budget <= 0.0 => {
+                Err("videogen.daily_budget_usd must be positive".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Exchange rates, see [`crate::rates`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RatesSection {
+    /// How long a fetched rate is served before refreshing
+    pub ttl_secs: u64,
+    /// Alert once the rate in use is older than this
+    pub stale_alert_secs: u64,
+    /// Used while no provider has answered since startup
+    pub fallback_btc_usd: f64,
+    pub fallback_usd_inr: f64,
+}
+
+impl Default for RatesSection {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 5 * 60,
+            stale_alert_secs: 30 * 60,
+            fallback_btc_usd: 100_000.0,
+            fallback_usd_inr: 88.0,
+        }
+    }
+}
+
+impl RatesSection {
+    fn validate(&self) -> Result<(), String> {
+        if self.ttl_secs == 0 || self.stale_alert_secs < self.ttl_secs {
+            return Err(
+                "rates.ttl_secs must be positive and at most rates.stale_alert_secs".to_string(),
+            );
+        }
+        if !self.fallback_btc_usd.is_finite()
+            || self.fallback_btc_usd <= 0.0
+            || !self.fallback_usd_inr.is_finite()
+            || self.fallback_usd_inr <= 0.0
+        {
+            return Err("rates fallback rates must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Redis read routing, see [`crate::utils::read_replicas`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedisSection {
+    /// Send staleness-tolerant reads to replicas, when any are configured
+    pub replica_reads: bool,
+    /// Replicas lagging the primary by more than this are skipped
+    pub replica_max_staleness_ms: u64,
+}
+
+impl Default for RedisSection {
+    fn default() -> Self {
+        Self {
+            replica_reads: true,
+            replica_max_staleness_ms: 2_000,
+        }
+    }
+}
+
+impl RedisSection {
+    fn validate(&self) -> Result<(), String> {
+        if self.replica_max_staleness_ms == 0 {
+            return Err("redis.replica_max_staleness_ms must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSinkBackend {
+    Nats,
+}
+
+/// Live fan-out of processed warehouse events, off unless enabled per
+/// deployment (`EVENT_SINK__ENABLED=true`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventSinkSection {
+    pub enabled: bool,
+    pub backend: EventSinkBackend,
+    pub nats_url: String,
+    /// Events go to `{subject_prefix}.{event}`
+    pub subject_prefix: String,
+    /// JetStream stream created over `{subject_prefix}.>` if missing
+    pub stream: String,
+    /// Events waiting to be published; more are dropped and counted
+    pub buffer_size: usize,
+}
+
+impl Default for EventSinkSection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backend: EventSinkBackend::Nats,
+            nats_url: "nats://localhost:4222".to_string(),
+            subject_prefix: "warehouse.events".to_string(),
+            stream: "WAREHOUSE_EVENTS".to_string(),
+            buffer_size: 10_000,
+        }
+    }
+}
+
+impl EventSinkSection {
+    fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.nats_url.is_empty() || self.subject_prefix.is_empty() || self.stream.is_empty() {
+            return Err("event_sink.nats_url, subject_prefix and stream are required".to_string());
+        }
+        if self.buffer_size == 0 {
+            return Err("event_sink.buffer_size must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Replay protection for delegated identity wires, see
+/// [`crate::utils::delegated_identity`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct DelegatedIdentitySection {
+    /// How old a request nonce may be; also how long used nonces are kept
+    pub max_age_secs: u64,
+    /// Reject requests without a signed nonce. Off until clients send one;
+    /// meanwhile only requests that carry a nonce are checked
+    pub require_nonce: bool,
+    /// Reject delegations valid for longer than this from now; unset allows
+    /// any lifetime
+    pub max_delegation_lifetime_secs: Option<u64>,
+}
+
+impl Default for DelegatedIdentitySection {
+    fn default() -> Self {
+        Self {
+            max_age_secs: 300,
+            require_nonce: false,
+            max_delegation_lifetime_secs: None,
+        }
+    }
+}
+
+impl DelegatedIdentitySection {
+    fn validate(&self) -> Result<(), String> {
+        if self.max_age_secs == 0 || self.max_age_secs > 24 * 60 * 60 {
+            return Err("delegated_identity.max_age_secs must be between 1 and 86400".to_string());
+        }
+        if self.max_delegation_lifetime_secs == Some(0) {
+            return Err(
+                "delegated_identity.max_delegation_lifetime_secs must be positive".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Token bucket for one event type: `burst` events at once, refilled at
+/// `per_minute`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EventRateLimit {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+/// Per-principal event velocity limits, see [`crate::events::velocity`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventVelocitySection {
+    pub enabled: bool,
+    /// Drop events over the limit; otherwise they are processed with
+    /// `rate_limited: true` in their params
+    pub drop_excess: bool,
+    /// Limit for event types without an entry in `limits`
+    pub default_limit: EventRateLimit,
+    /// Keyed by snake_case event name
+    pub limits: BTreeMap<String, EventRateLimit>,
+    /// Limit across all of a sender's events, whatever their type
+    pub sender_limit: EventRateLimit,
+    /// Rate-limited events within `abuse_window_secs` after which the
+    /// principal is reported to fraud detection
+    pub abuse_threshold: u64,
+    pub abuse_window_secs: u64,
+}
+
+impl Default for EventVelocitySection {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            drop_excess: true,
+            default_limit: EventRateLimit {
+                burst: 120,
+                per_minute: 120,
+            },
+            limits: BTreeMap::from([(
+                "like_video".to_string(),
+                EventRateLimit {
+                    burst: 30,
+                    per_minute: 30,
+                },
+            )]),
+            sender_limit: EventRateLimit {
+                burst: 600,
+                per_minute: 600,
+            },
+            abuse_threshold: 500,
+            abuse_window_secs: 60 * 60,
+        }
+    }
+}
+
+impl EventVelocitySection {
+    pub fn limit_for(&self, event: &str) -> &EventRateLimit {
+        self.limits.get(event).unwrap_or(&self.default_limit)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let limits = [&self.default_limit, &self.sender_limit]
+            .into_iter()
+            .chain(self.limits.values());
+        for limit in limits {
+            if limit.burst == 0 || limit.per_minute == 0 {
+                return Err("event_velocity limits must be positive".to_string());
+            }
+        }
+        if self.abuse_threshold == 0 || self.abuse_window_secs == 0 {
+            return Err(
+                "event_velocity.abuse_threshold and abuse_window_secs must be positive".to_string(),
+            );
+        }
+        Ok(())
+    }
 }
 
 impl AppConfig {
@@ -47,9 +450,23 @@ impl AppConfig {
         let conf = Config::builder()
             .add_source(File::with_name("config.toml").required(false))
             .add_source(File::with_name(".env").required(false))
-            .add_source(Environment::default())
+            .add_source(Environment::default().separator("__"))
             .build()?;
 
-        conf.try_deserialize()
+        let app_config: Self = conf.try_deserialize()?;
+        app_config.validate().map_err(ConfigError::Message)?;
+        Ok(app_config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        self.rewards.validate()?;
+        crate::qstash::dedup_config::DedupConfig::from(self.dedup.clone()).validate()?;
+        self.leaderboard.validate()?;
+        self.videogen.validate()?;
+        self.rates.validate()?;
+        self.redis.validate()?;
+        self.event_sink.validate()?;
+        self.delegated_identity.validate()?;
+        self.event_velocity.validate()
     }
 }
@@ -0,0 +1,243 @@
+//! Config values that can change without a restart.
+//!
+//! The base is the typed sections of [`AppConfig`] as loaded at startup. On
+//! top of it sits an overlay in Redis, the hash `offchain:config:overlay`
+//! with fields like `rewards.referral_fraud_threshold` holding JSON values.
+//! Only the keys in [`HOT_RELOADABLE`] may be overlaid. A watcher task polls
+//! the overlay every [`POLL_INTERVAL`] and publishes the merged config on a
+//! watch channel: [`runtime`] reads the latest value and [`subscribe`] lets a
+//! module react to changes. An overlay that fails validation is ignored and
+//! the previous config stays in effect.
+//!
+//! Dedup thresholds are not overlaid here; they are tuned at runtime through
+//! `/admin/dedup_config`, and the `dedup` section only provides its defaults.
+
+#[cfg(not(feature = "local-bin"))]
+pub mod handlers;
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use once_cell::sync::{Lazy, OnceCell};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::watch;
+use utoipa::ToSchema;
+
+use super::{
+    AppConfig, DedupSection, EventVelocitySection, LeaderboardSection, RatesSection, RedisSection,
+    RewardsSection, VideogenSection,
+};
+use crate::yral_auth::dragonfly::DragonflyPool;
+
+#[cfg(not(feature = "local-bin"))]
+pub use handlers::runtime_config_router;
+
+const OVERLAY_KEY: &str = "offchain:config:overlay";
+pub const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Keys that can be overlaid, as `section.field`
+pub const HOT_RELOADABLE: &[&str] = &[
+    "rewards.max_view_reward_e8s",
+    "rewards.max_mission_reward_e8s",
+    "rewards.referral_fraud_threshold",
+    "rewards.digest_window_secs",
+    "rewards.view_dedup_window_secs",
+    "leaderboard.max_tournament_duration_secs",
+    "leaderboard.max_start_lead_secs",
+    "leaderboard.max_yral_prize_pool",
+    "leaderboard.velocity_window_secs",
+    "leaderboard.max_score_per_window",
+    "videogen.daily_budget_usd",
+    "rates.ttl_secs",
+    "rates.stale_alert_secs",
+    "rates.fallback_btc_usd",
+    "rates.fallback_usd_inr",
+    "redis.replica_reads",
+    "redis.replica_max_staleness_ms",
+    "event_velocity.enabled",
+    "event_velocity.drop_excess",
+    "event_velocity.default_limit",
+    "event_velocity.limits",
+    "event_velocity.sender_limit",
+    "event_velocity.abuse_threshold",
+    "event_velocity.abuse_window_secs",
+];
+
+static BASE: OnceCell<RuntimeConfig> = OnceCell::new();
+static CURRENT: Lazy<watch::Sender<Arc<RuntimeConfig>>> =
+    Lazy::new(|| watch::channel(Arc::new(RuntimeConfig::default())).0);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RuntimeConfig {
+    pub rewards: RewardsSection,
+    pub dedup: DedupSection,
+    pub leaderboard: LeaderboardSection,
+    pub videogen: VideogenSection,
+    pub rates: RatesSection,
+    pub redis: RedisSection,
+    pub event_velocity: EventVelocitySection,
+}
+
+impl From<&AppConfig> for RuntimeConfig {
+    fn from(config: &AppConfig) -> Self {
+        Self {
+            rewards: config.rewards.clone(),
+            dedup: config.dedup.clone(),
+            leaderboard: config.leaderboard.clone(),
+            videogen: config.videogen.clone(),
+            rates: config.rates.clone(),
+            redis: config.redis.clone(),
+            event_velocity: config.event_velocity.clone(),
+        }
+    }
+}
+
+impl RuntimeConfig {
+    /// `self` with the overlay applied, validated
+    pub fn with_overlay(&self, overlay: &HashMap<String, Value>) -> Result<Self, String> {
+        let mut merged = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        for (key, value) in overlay {
+            let (section, field) = overlay_path(key)?;
+            merged[section][field] = value.clone();
+        }
+
+        let config: Self = serde_json::from_value(merged).map_err(|e| e.to_string())?;
+        config.rewards.validate()?;
+        config.leaderboard.validate()?;
+        config.videogen.validate()?;
+        config.rates.validate()?;
+        config.redis.validate()?;
+        config.event_velocity.validate()?;
+        Ok(config)
+    }
+}
+
+fn overlay_path(key: &str) -> Result<(&str, &str), String> {
+    if !HOT_RELOADABLE.contains(&key) {
+        return Err(format!("{key} is not hot-reloadable"));
+    }
+    key.split_once('.')
+        .ok_or_else(|| format!("{key} is not a section.field key"))
+}
+
+/// Sets the base config. Called once at startup, before any module reads it.
+pub fn init(config: &AppConfig) {
+    let base = RuntimeConfig::from(config);
+    if BASE.set(base.clone()).is_err() {
+        log::warn!("Runtime config already initialized");
+        return;
+    }
+    CURRENT.send_replace(Arc::new(base));
+}
+
+/// The config currently in effect
+pub fn runtime() -> Arc<RuntimeConfig> {
+    CURRENT.borrow().clone()
+}
+
+/// Notified whenever the config in effect changes
+pub fn subscribe() -> watch::Receiver<Arc<RuntimeConfig>> {
+    CURRENT.subscribe()
+}
+
+pub fn base() -> RuntimeConfig {
+    BASE.get().cloned().unwrap_or_default()
+}
+
+pub async fn load_overlay(pool: &DragonflyPool) -> Result<HashMap<String, Value>> {
+    let mut conn = pool.get().await?;
+    let entries: HashMap<String, String> = conn.hgetall(OVERLAY_KEY).await?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|(key, payload)| match serde_json::from_str(&payload) {
+            Ok(value) => Some((key, value)),
+            Err(e) => {
+                log::warn!("Ignoring unreadable config overlay {key}: {e}");
+                None
+            }
+        })
+        .collect())
+}
+
+pub async fn store_overlay_value(pool: &DragonflyPool, key: &str, value: &Value) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = conn
+        .hset(OVERLAY_KEY, key, serde_json::to_string(value)?)
+        .await?;
+    Ok(())
+}
+
+/// Returns whether the key was overlaid
+pub async fn delete_overlay_value(pool: &DragonflyPool, key: &str) -> Result<bool> {
+    let mut conn = pool.get().await?;
+    let removed: u64 = conn.hdel(OVERLAY_KEY, key).await?;
+    Ok(removed > 0)
+}
+
+/// Applies the overlay to the base config. Returns whether the config in
+/// effect changed, in which case subscribers are notified.
+pub fn apply_overlay(overlay: &HashMap<String, Value>) -> Result<bool, String> {
+    let next = base().with_overlay(overlay)?;
+    Ok(CURRENT.send_if_modified(|current| {
+        if **current == next {
+            return false;
+        }
+        *current = Arc::new(next);
+        true
+    }))
+}
+
+pub async fn reload(pool: &DragonflyPool) -> Result<bool> {
+    let overlay = load_overlay(pool).await?;
+    apply_overlay(&overlay).map_err(|e| anyhow::anyhow!("Invalid config overlay: {e}"))
+}
+
+pub fn spawn_watcher(pool: Arc<DragonflyPool>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            match reload(&pool).await {
+                Ok(true) => log::info!("Runtime config reloaded: {:?}", runtime()),
+                Ok(false) => {}
+                Err(e) => log::warn!("Keeping current runtime config: {e}"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay_merge_and_validation() {
+        let base = RuntimeConfig::default();
+        let overlay = HashMap::from([
+            (
+                "rewards.referral_fraud_threshold".to_string(),
+                Value::from(50),
+            ),
+            ("videogen.daily_budget_usd".to_string(), Value::from(250.0)),
+        ]);
+        let merged = base.with_overlay(&overlay).unwrap();
+        assert_eq!(merged.rewards.referral_fraud_threshold, 50);
+        assert_eq!(merged.videogen.daily_budget_usd, Some(250.0));
+        assert_eq!(merged.leaderboard, base.leaderboard);
+
+        let not_reloadable = HashMap::from([("dedup.top_k".to_string(), Value::from(4))]);
+        assert!(base.with_overlay(&not_reloadable).is_err());
+        let invalid = HashMap::from([(
+            "leaderboard.max_yral_prize_pool".to_string(),
+            Value::from(-1.0),
+        )]);
+        assert!(base.with_overlay(&invalid).is_err());
+        let wrong_type = HashMap::from([(
+            "rewards.max_view_reward_e8s".to_string(),
+            Value::from("lots"),
+        )]);
+        assert!(base.with_overlay(&wrong_type).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::instrument;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{
+    apply_overlay, base, delete_overlay_value, load_overlay, overlay_path, runtime,
+    store_overlay_value, RuntimeConfig, HOT_RELOADABLE,
+};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+};
+
+pub fn runtime_config_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(get_runtime_config))
+        .routes(routes!(put_overlay_value, delete_overlay))
+        .with_state(state)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RuntimeConfigResponse {
+    /// Config in effect on the instance that served the request
+    pub effective: RuntimeConfig,
+    /// Config loaded at startup
+    pub base: RuntimeConfig,
+    /// Stored overlay values, by `section.field`
+    #[schema(value_type = Object)]
+    pub overlay: HashMap<String, Value>,
+    pub hot_reloadable: Vec<String>,
+}
+
+/// The runtime config, its startup base and the stored overlay
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "config",
+    responses(
+        (status = 200, description = "Runtime config", body = RuntimeConfigResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_runtime_config(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<RuntimeConfigResponse>, ApiError> {
+    require_operator(&headers)?;
+    let overlay = load_overlay(&state.yral_redis_store_dragonfly).await?;
+
+    Ok(Json(RuntimeConfigResponse {
+        effective: (*runtime()).clone(),
+        base: base(),
+        overlay,
+        hot_reloadable: HOT_RELOADABLE.iter().map(|key| key.to_string()).collect(),
+    }))
+}
+
+/// Overlay a hot-reloadable value. Takes effect here immediately and on
+/// other instances within the watcher's poll interval.
+#[utoipa::path(
+    put,
+    path = "/overlay/{key}",
+    params(
+        ("key" = String, Path, description = "`section.field`, e.g. `rewards.referral_fraud_threshold`")
+    ),
+    request_body(content = Object, description = "JSON value"),
+    tag = "config",
+    responses(
+        (status = 200, description = "Value stored; the config now in effect", body = RuntimeConfig),
+        (status = 400, description = "Key not hot-reloadable or value invalid", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn put_overlay_value(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    headers: HeaderMap,
+    Json(value): Json<Value>,
+) -> Result<Json<RuntimeConfig>, ApiError> {
+    require_operator(&headers)?;
+    overlay_path(&key).map_err(ApiError::InvalidRequest)?;
+
+    let pool = &state.yral_redis_store_dragonfly;
+    let mut overlay = load_overlay(pool).await?;
+    overlay.insert(key.clone(), value.clone());
+    base()
+        .with_overlay(&overlay)
+        .map_err(ApiError::InvalidRequest)?;
+
+    store_overlay_value(pool, &key, &value).await?;
+    apply_overlay(&overlay).map_err(ApiError::Internal)?;
+    log::warn!("Runtime config overlay {key} set to {value}");
+    Ok(Json((*runtime()).clone()))
+}
+
+/// Remove an overlaid value, reverting it to the startup config
+#[utoipa::path(
+    delete,
+    path = "/overlay/{key}",
+    params(
+        ("key" = String, Path, description = "`section.field`")
+    ),
+    tag = "config",
+    responses(
+        (status = 204, description = "Value removed"),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Key not overlaid", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn delete_overlay(
+    State(state): State<Arc<AppState>>,
+    Path(key): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiError> {
+    require_operator(&headers)?;
+    let pool = &state.yral_redis_store_dragonfly;
+    if !delete_overlay_value(pool, &key).await? {
+        return Err(ApiError::NotFound(format!("{key} is not overlaid")));
+    }
+
+    let overlay = load_overlay(pool).await?;
+    if let Err(e) = apply_overlay(&overlay) {
+        log::warn!("Remaining config overlay is invalid, keeping current config: {e}");
+    }
+    log::warn!("Runtime config overlay {key} removed");
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -10,6 +10,10 @@ pub static BIGQUERY_INGESTION_URL: Lazy<Url> = Lazy::new(|| {
     Url::parse("https://bigquery.googleapis.com/bigquery/v2/projects/hot-or-not-feed-intelligence/datasets/analytics_335143420/tables/test_events_analytics/insertAll").unwrap()
 });
 
+/// Same table as [`BIGQUERY_INGESTION_URL`], for the Storage Write API
+pub const BIGQUERY_EVENTS_TABLE: &str =
+    "projects/hot-or-not-feed-intelligence/datasets/analytics_335143420/tables/test_events_analytics";
+
 pub static YRAL_UPLOAD_SERVICE: Lazy<Url> =
     Lazy::new(|| Url::parse("https://upload.yral.com").unwrap());
 
@@ -0,0 +1,259 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use super::{is_blocked_for, normalize_country, resolve_country};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+    kvrocks::{tables, RegionBlocklist},
+};
+
+const MAX_LOOKUP_VIDEOS: usize = 500;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RegionBlocklistResponse {
+    pub video_id: String,
+    pub blocked_countries: BTreeSet<String>,
+    pub reference: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+impl RegionBlocklistResponse {
+    fn unrestricted(video_id: String) -> Self {
+        Self {
+            video_id,
+            blocked_countries: BTreeSet::new(),
+            reference: None,
+            updated_at: None,
+        }
+    }
+}
+
+impl From<RegionBlocklist> for RegionBlocklistResponse {
+    fn from(blocklist: RegionBlocklist) -> Self {
+        Self {
+            video_id: blocklist.video_id,
+            blocked_countries: blocklist.blocked_countries,
+            reference: blocklist.reference,
+            updated_at: Some(blocklist.updated_at),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetRegionBlocklistRequest {
+    /// ISO 3166-1 alpha-2 codes; replaces the current list
+    pub blocked_countries: Vec<String>,
+    /// Legal request or ticket this takedown fulfils
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LookupRequest {
+    pub video_ids: Vec<String>,
+    /// Requester country; defaults to the edge-resolved `CF-IPCountry` header
+    pub country: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LookupResponse {
+    /// Country the lookup was evaluated for; null when it could not be resolved
+    pub country: Option<String>,
+    /// Subset of the requested videos that must not be served
+    pub blocked_video_ids: Vec<String>,
+}
+
+/// Region blocklist of a video
+#[utoipa::path(
+    get,
+    path = "/videos/{video_id}",
+    params(("video_id" = String, Path, description = "Video ID")),
+    tag = "content_gating",
+    responses(
+        (status = 200, description = "Blocklist, empty when unrestricted", body = RegionBlocklistResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_region_blocklist(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(video_id): Path<String>,
+) -> Result<Json<RegionBlocklistResponse>, ApiError> {
+    require_operator(&headers)?;
+
+    let blocklist = state
+        .kvrocks_client
+        .get(&tables::REGION_BLOCKLIST, &video_id)
+        .await?;
+
+    Ok(Json(blocklist.map(Into::into).unwrap_or_else(|| {
+        RegionBlocklistResponse::unrestricted(video_id)
+    })))
+}
+
+/// Replace the countries a video is blocked in
+#[utoipa::path(
+    put,
+    path = "/videos/{video_id}",
+    params(("video_id" = String, Path, description = "Video ID")),
+    request_body = SetRegionBlocklistRequest,
+    tag = "content_gating",
+    responses(
+        (status = 200, description = "Updated blocklist", body = RegionBlocklistResponse),
+        (status = 400, description = "Invalid country code", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn set_region_blocklist(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(video_id): Path<String>,
+    Json(request): Json<SetRegionBlocklistRequest>,
+) -> Result<Json<RegionBlocklistResponse>, ApiError> {
+    require_operator(&headers)?;
+
+    let blocked_countries = request
+        .blocked_countries
+        .iter()
+        .map(|code| {
+            normalize_country(code)
+                .ok_or_else(|| ApiError::InvalidRequest(format!("Invalid country code: {code}")))
+        })
+        .collect::<Result<BTreeSet<_>, _>>()?;
+
+    if blocked_countries.is_empty() {
+        state
+            .kvrocks_client
+            .remove(&tables::REGION_BLOCKLIST, &video_id)
+            .await?;
+        log::info!("Cleared region blocklist for video {video_id}");
+        return Ok(Json(RegionBlocklistResponse::unrestricted(video_id)));
+    }
+
+    let blocklist = RegionBlocklist {
+        video_id: video_id.clone(),
+        blocked_countries,
+        reference: request.reference,
+        updated_at: chrono::Utc::now().to_rfc3339(),
+    };
+    state
+        .kvrocks_client
+        .put(&tables::REGION_BLOCKLIST, &video_id, &blocklist)
+        .await?;
+
+    log::warn!(
+        "Region blocklist for video {} set to {:?} (reference: {:?})",
+        video_id,
+        blocklist.blocked_countries,
+        blocklist.reference
+    );
+
+    Ok(Json(blocklist.into()))
+}
+
+/// Lift all regional restrictions on a video
+#[utoipa::path(
+    delete,
+    path = "/videos/{video_id}",
+    params(("video_id" = String, Path, description = "Video ID")),
+    tag = "content_gating",
+    responses(
+        (status = 200, description = "Restrictions removed", body = RegionBlocklistResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn delete_region_blocklist(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(video_id): Path<String>,
+) -> Result<Json<RegionBlocklistResponse>, ApiError> {
+    require_operator(&headers)?;
+
+    state
+        .kvrocks_client
+        .remove(&tables::REGION_BLOCKLIST, &video_id)
+        .await?;
+    log::info!("Cleared region blocklist for video {video_id}");
+
+    Ok(Json(RegionBlocklistResponse::unrestricted(video_id)))
+}
+
+/// Which of a batch of videos are blocked for the requester's country.
+/// Internal: called by feed services while assembling feeds.
+#[utoipa::path(
+    post,
+    path = "/lookup",
+    request_body = LookupRequest,
+    tag = "content_gating",
+    responses(
+        (status = 200, description = "Blocked videos", body = LookupResponse),
+        (status = 400, description = "Too many videos", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers, request), fields(num_videos = request.video_ids.len()))]
+pub async fn lookup_blocked_videos(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<LookupRequest>,
+) -> Result<Json<LookupResponse>, ApiError> {
+    require_operator(&headers)?;
+
+    if request.video_ids.len() > MAX_LOOKUP_VIDEOS {
+        return Err(ApiError::InvalidRequest(format!(
+            "At most {MAX_LOOKUP_VIDEOS} videos per lookup"
+        )));
+    }
+
+    let country = resolve_country(request.country.as_deref(), &headers);
+
+    let blocklists = try_join_all(request.video_ids.iter().map(|video_id| {
+        state
+            .kvrocks_client
+            .get(&tables::REGION_BLOCKLIST, video_id)
+    }))
+    .await?;
+
+    let blocked_video_ids = request
+        .video_ids
+        .into_iter()
+        .zip(blocklists)
+        .filter(|(_, blocklist)| {
+            blocklist
+                .as_ref()
+                .is_some_and(|b| is_blocked_for(&b.blocked_countries, country.as_deref()))
+        })
+        .map(|(video_id, _)| video_id)
+        .collect();
+
+    Ok(Json(LookupResponse {
+        country,
+        blocked_video_ids,
+    }))
+}
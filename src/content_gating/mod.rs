@@ -0,0 +1,85 @@
+//! Per-region takedowns.
+//!
+//! Legal requests block a video in specific countries without removing it
+//! globally. Blocklists live in kvrocks; feed services call the lookup
+//! endpoint with the requester's country and drop anything it returns.
+
+pub mod handlers;
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use axum::http::HeaderMap;
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+use crate::app_state::AppState;
+
+/// Country of the client as resolved by Cloudflare at the edge
+const EDGE_COUNTRY_HEADER: &str = "cf-ipcountry";
+
+pub fn content_gating_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(handlers::lookup_blocked_videos))
+        .routes(routes!(
+            handlers::get_region_blocklist,
+            handlers::set_region_blocklist,
+            handlers::delete_region_blocklist
+        ))
+        .with_state(state)
+}
+
+/// Normalizes to an uppercase ISO 3166-1 alpha-2 code. Cloudflare's
+/// placeholders for unknown origin (`XX`) and Tor (`T1`) resolve to None.
+pub fn normalize_country(code: &str) -> Option<String> {
+    let code = code.trim().to_ascii_uppercase();
+    let is_alpha2 = code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic());
+    (is_alpha2 && code != "XX").then_some(code)
+}
+
+/// Requester country from an explicit value, falling back to the edge header
+pub fn resolve_country(explicit: Option<&str>, headers: &HeaderMap) -> Option<String> {
+    explicit
+        .or_else(|| {
+            headers
+                .get(EDGE_COUNTRY_HEADER)
+                .and_then(|value| value.to_str().ok())
+        })
+        .and_then(normalize_country)
+}
+
+/// Whether a video with `blocked_countries` may be shown to `country`.
+/// Unknown origins fail closed: any regional restriction hides the video.
+pub fn is_blocked_for(blocked_countries: &BTreeSet<String>, country: Option<&str>) -> bool {
+    match country {
+        Some(country) => blocked_countries.contains(country),
+        None => !blocked_countries.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_country_resolution_and_blocking() {
+        assert_eq!(normalize_country(" in "), Some("IN".to_string()));
+        assert_eq!(normalize_country("XX"), None);
+        assert_eq!(normalize_country("IND"), None);
+        assert_eq!(normalize_country("T1"), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(EDGE_COUNTRY_HEADER, "de".parse().unwrap());
+        assert_eq!(resolve_country(None, &headers), Some("DE".to_string()));
+        assert_eq!(
+            resolve_country(Some("fr"), &headers),
+            Some("FR".to_string())
+        );
+
+        let blocked = BTreeSet::from(["DE".to_string()]);
+        assert!(is_blocked_for(&blocked, Some("DE")));
+        assert!(!is_blocked_for(&blocked, Some("FR")));
+        assert!(is_blocked_for(&blocked, None));
+        assert!(!is_blocked_for(&BTreeSet::new(), None));
+    }
+}
@@ -0,0 +1,322 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use candid::Principal;
+use chrono::Utc;
+use chrono_tz::Tz;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+
+use super::missions::{
+    claimed_key, get_user_timezone, load_definitions, mission_day, progress_key, set_user_timezone,
+    store_definitions, validate_definitions, MissionDefinition,
+};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+    rewards::config::RewardTokenType,
+    tokens::TransferError,
+    types::DelegatedIdentityWire,
+    utils::delegated_identity::verify_delegated_identity_request,
+};
+
+/// Claims are kept a little past the progress they refer to
+const CLAIMED_TTL_SECS: u64 = 3 * 24 * 60 * 60;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ProgressParams {
+    /// IANA timezone, e.g. `Asia/Kolkata`. The first one sent is kept for the user.
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MissionProgress {
+    pub mission_id: String,
+    pub title: String,
+    pub description: String,
+    pub target: u32,
+    pub progress: u32,
+    pub completed: bool,
+    pub claimed: bool,
+    pub reward_e8s: u64,
+    pub reward_token: RewardTokenType,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DailyMissionsProgressResponse {
+    pub principal: String,
+    /// Local day in YYYY-MM-DD
+    pub day: String,
+    pub timezone: String,
+    /// Unix timestamp of the user's next local midnight
+    pub expires_at: i64,
+    pub missions: Vec<MissionProgress>,
+}
+
+/// Today's mission progress for a user
+#[utoipa::path(
+    get,
+    path = "/progress/{principal}",
+    params(
+        ("principal" = String, Path, description = "User principal"),
+        ProgressParams
+    ),
+    tag = "daily-missions",
+    responses(
+        (status = 200, description = "Mission progress", body = DailyMissionsProgressResponse),
+        (status = 400, description = "Invalid principal or timezone", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+pub async fn get_progress(
+    State(state): State<Arc<AppState>>,
+    Path(principal): Path<String>,
+    Query(params): Query<ProgressParams>,
+) -> Result<Json<DailyMissionsProgressResponse>, ApiError> {
+    let principal = Principal::from_text(&principal)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid principal: {e}")))?;
+    let pool = &state.yral_redis_store_dragonfly;
+
+    let tz = match params.timezone {
+        Some(name) => {
+            let tz: Tz = name
+                .parse()
+                .map_err(|_| ApiError::InvalidRequest(format!("Unknown timezone {name}")))?;
+            set_user_timezone(pool, &principal, tz).await?
+        }
+        None => get_user_timezone(pool, &principal).await?,
+    };
+    let (day, expires_at) = mission_day(tz, Utc::now());
+
+    let definitions: Vec<MissionDefinition> = load_definitions(pool)
+        .await?
+        .into_iter()
+        .filter(|definition| definition.enabled)
+        .collect();
+    let mut conn = pool.get().await?;
+    let progress: HashMap<String, u32> = conn.hgetall(progress_key(&principal, &day)).await?;
+    let claimed: Vec<bool> = if definitions.is_empty() {
+        Vec::new()
+    } else {
+        let keys: Vec<String> = definitions
+            .iter()
+            .map(|definition| claimed_key(&principal, &day, &definition.id))
+            .collect();
+        let values: Vec<Option<String>> = conn.mget(keys).await?;
+        values.iter().map(Option::is_some).collect()
+    };
+
+    let missions = definitions
+        .into_iter()
+        .zip(claimed)
+        .map(|(definition, claimed)| {
+            let done = progress.get(&definition.id).copied().unwrap_or(0);
+            MissionProgress {
+                claimed,
+                completed: done >= definition.target,
+                progress: done.min(definition.target),
+                mission_id: definition.id,
+                title: definition.title,
+                description: definition.description,
+                target: definition.target,
+                reward_e8s: definition.reward_e8s,
+                reward_token: definition.reward_token,
+            }
+        })
+        .collect();
+
+    Ok(Json(DailyMissionsProgressResponse {
+        principal: principal.to_text(),
+        day: day.to_string(),
+        timezone: tz.name().to_string(),
+        expires_at,
+        missions,
+    }))
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ClaimMissionRequest {
+    pub delegated_identity_wire: DelegatedIdentityWire,
+    pub mission_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ClaimMissionResponse {
+    pub mission_id: String,
+    pub day: String,
+    pub reward_e8s: u64,
+    pub reward_token: RewardTokenType,
+    pub tx_id: String,
+}
+
+/// Claim the reward for a completed mission
+#[utoipa::path(
+    post,
+    path = "/claim",
+    request_body = ClaimMissionRequest,
+    tag = "daily-missions",
+    responses(
+        (status = 200, description = "Reward credited", body = ClaimMissionResponse),
+        (status = 400, description = "Mission not completed", body = ApiErrorBody),
+        (status = 401, description = "Invalid delegated identity", body = ApiErrorBody),
+        (status = 404, description = "Unknown mission", body = ApiErrorBody),
+        (status = 409, description = "Already claimed today", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+pub async fn claim_mission(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<ClaimMissionRequest>,
+) -> Result<Json<ClaimMissionResponse>, ApiError> {
+    let user_info =
+        verify_delegated_identity_request(&state, request.delegated_identity_wire, &headers)
+            .await
+            .map_err(|e| ApiError::Unauthorized(format!("Invalid delegated identity: {e}")))?;
+    let principal = user_info.user_principal;
+    crate::middleware::set_user_context(principal);
+
+    let pool = &state.yral_redis_store_dragonfly;
+    let definition = load_definitions(pool)
+        .await?
+        .into_iter()
+        .find(|definition| definition.enabled && definition.id == request.mission_id)
+        .ok_or_else(|| ApiError::NotFound(format!("Unknown mission {}", request.mission_id)))?;
+
+    let tz = get_user_timezone(pool, &principal).await?;
+    let (day, _) = mission_day(tz, Utc::now());
+    let mut conn = pool.get().await?;
+
+    let progress: Option<u32> = conn
+        .hget(progress_key(&principal, &day), &definition.id)
+        .await?;
+    if progress.unwrap_or(0) < definition.target {
+        return Err(ApiError::InvalidRequest(format!(
+            "Mission {} is not completed yet",
+            definition.id
+        )));
+    }
+
+    // SET NX is the claim lock: only the first request for the day gets through
+    let claimed_key = claimed_key(&principal, &day, &definition.id);
+    let claimed: Option<String> = redis::cmd("SET")
+        .arg(&claimed_key)
+        .arg(Utc::now().timestamp())
+        .arg("NX")
+        .arg("EX")
+        .arg(CLAIMED_TTL_SECS)
+        .query_async(&mut conn)
+        .await?;
+    if claimed.is_none() {
+        return Err(ApiError::Conflict(format!(
+            "Mission {} already claimed today",
+            definition.id
+        )));
+    }
+
+    let tx_id = match state
+        .rewards_module
+        .reward_engine
+        .credit_mission_reward(
+            principal,
+            &definition.id,
+            &day.to_string(),
+            definition.reward_e8s,
+            definition.reward_token,
+        )
+        .await
+    {
+        Ok(tx_id) => tx_id,
+        Err(e)
+            if matches!(
+                e.downcast_ref::<TransferError>(),
+                Some(TransferError::Unknown(..))
+            ) =>
+        {
+            // The credit may have landed, so the claim stays taken
+            log::error!(
+                "Mission {} credit for {principal} has an unknown outcome; reconcile manually: {e:?}",
+                definition.id
+            );
+            return Err(ApiError::Internal(format!("Failed to credit reward: {e}")));
+        }
+        Err(e) => {
+            // Nothing was credited, so release the claim and let the user retry
+            if let Err(release_err) = conn.del::<_, ()>(&claimed_key).await {
+                log::error!(
+                    "Failed to release mission claim {} for {principal}: {release_err:?}",
+                    definition.id
+                );
+            }
+            return Err(ApiError::Internal(format!("Failed to credit reward: {e}")));
+        }
+    };
+
+    Ok(Json(ClaimMissionResponse {
+        mission_id: definition.id,
+        day: day.to_string(),
+        reward_e8s: definition.reward_e8s,
+        reward_token: definition.reward_token,
+        tx_id,
+    }))
+}
+
+/// Current mission definitions
+#[utoipa::path(
+    get,
+    path = "/definitions",
+    tag = "daily-missions",
+    responses(
+        (status = 200, description = "Mission definitions", body = Vec<MissionDefinition>),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    )
+)]
+pub async fn get_definitions(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<MissionDefinition>>, ApiError> {
+    Ok(Json(
+        load_definitions(&state.yral_redis_store_dragonfly).await?,
+    ))
+}
+
+/// Replace the mission definitions
+#[utoipa::path(
+    put,
+    path = "/definitions",
+    request_body = Vec<MissionDefinition>,
+    tag = "daily-missions",
+    responses(
+        (status = 200, description = "Definitions stored", body = Vec<MissionDefinition>),
+        (status = 400, description = "Invalid definitions", body = ApiErrorBody),
+        (status = 401, description = "Authentication failed", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn put_definitions(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(definitions): Json<Vec<MissionDefinition>>,
+) -> Result<Json<Vec<MissionDefinition>>, ApiError> {
+    require_operator(&headers)?;
+    validate_definitions(&definitions).map_err(ApiError::InvalidRequest)?;
+
+    store_definitions(&state.yral_redis_store_dragonfly, &definitions).await?;
+    log::info!("Stored {} daily mission definitions", definitions.len());
+
+    Ok(Json(definitions))
+}
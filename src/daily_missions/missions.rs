@@ -0,0 +1,270 @@
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use candid::Principal;
+use chrono::{DateTime, Days, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::{rewards::config::RewardTokenType, yral_auth::dragonfly::DragonflyPool};
+
+const KEY_PREFIX: &str = "offchain:daily_missions";
+/// Progress outlives the local day it belongs to by enough to cover any timezone
+const PROGRESS_TTL_SECS: i64 = 2 * 24 * 60 * 60;
+const DEFINITIONS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+fn default_principal_param() -> String {
+    "user_id".to_string()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MissionDefinition {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    /// snake_case warehouse event that advances the mission
+    pub event: String,
+    /// Param holding the principal whose progress advances
+    #[serde(default = "default_principal_param")]
+    pub principal_param: String,
+    /// When set, each distinct value of this param counts once per day
+    pub unique_param: Option<String>,
+    pub target: u32,
+    pub reward_e8s: u64,
+    #[serde(default)]
+    pub reward_token: RewardTokenType,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+pub fn default_definitions() -> Vec<MissionDefinition> {
+    vec![
+        MissionDefinition {
+            id: "watch_videos".to_string(),
+            title: "Watch 10 videos".to_string(),
+            description: "Watch 10 different videos today".to_string(),
+            event: "video_duration_watched".to_string(),
+            principal_param: default_principal_param(),
+            unique_param: Some("video_id".to_string()),
+            target: 10,
+            reward_e8s: 100,
+            reward_token: RewardTokenType::Btc,
+            enabled: true,
+        },
+        MissionDefinition {
+            id: "upload_video".to_string(),
+            title: "Upload a video".to_string(),
+            description: "Post one video today".to_string(),
+            event: "video_upload_successful".to_string(),
+            principal_param: default_principal_param(),
+            unique_param: Some("video_id".to_string()),
+            target: 1,
+            reward_e8s: 200,
+            reward_token: RewardTokenType::Btc,
+            enabled: true,
+        },
+        MissionDefinition {
+            id: "refer_friend".to_string(),
+            title: "Refer a friend".to_string(),
+            description: "Get a friend to sign up with your referral code".to_string(),
+            event: "referral_attributed".to_string(),
+            principal_param: "referrer_id".to_string(),
+            unique_param: None,
+            target: 1,
+            reward_e8s: 100,
+            reward_token: RewardTokenType::Btc,
+            enabled: true,
+        },
+    ]
+}
+
+fn definitions_key() -> String {
+    format!("{KEY_PREFIX}:definitions")
+}
+
+fn timezone_key(principal: &Principal) -> String {
+    format!("{KEY_PREFIX}:tz:{principal}")
+}
+
+pub(crate) fn progress_key(principal: &Principal, day: &NaiveDate) -> String {
+    format!("{KEY_PREFIX}:progress:{principal}:{day}")
+}
+
+pub(crate) fn claimed_key(principal: &Principal, day: &NaiveDate, mission_id: &str) -> String {
+    format!("{KEY_PREFIX}:claimed:{principal}:{day}:{mission_id}")
+}
+
+fn seen_key(principal: &Principal, day: &NaiveDate, mission_id: &str) -> String {
+    format!("{KEY_PREFIX}:seen:{principal}:{day}:{mission_id}")
+}
+
+static DEFINITIONS_CACHE: Lazy<RwLock<Option<(Instant, Vec<MissionDefinition>)>>> =
+    Lazy::new(|| RwLock::new(None));
+
+/// Mission definitions from Redis, falling back to the built-in set
+pub async fn load_definitions(pool: &DragonflyPool) -> Result<Vec<MissionDefinition>> {
+    if let Ok(cached) = DEFINITIONS_CACHE.read() {
+        if let Some((fetched_at, definitions)) = cached.as_ref() {
+            if fetched_at.elapsed() < DEFINITIONS_CACHE_TTL {
+                return Ok(definitions.clone());
+            }
+        }
+    }
+
+    let mut conn = pool.get().await?;
+    let stored: Option<String> = conn.get(definitions_key()).await?;
+    let definitions = match stored {
+        Some(json) => serde_json::from_str(&json)?,
+        None => default_definitions(),
+    };
+
+    if let Ok(mut cached) = DEFINITIONS_CACHE.write() {
+        *cached = Some((Instant::now(), definitions.clone()));
+    }
+    Ok(definitions)
+}
+
+pub async fn store_definitions(
+    pool: &DragonflyPool,
+    definitions: &[MissionDefinition],
+) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let json = serde_json::to_string(definitions)?;
+    conn.set::<_, _, ()>(definitions_key(), json).await?;
+
+    if let Ok(mut cached) = DEFINITIONS_CACHE.write() {
+        *cached = Some((Instant::now(), definitions.to_vec()));
+    }
+    Ok(())
+}
+
+pub fn validate_definitions(definitions: &[MissionDefinition]) -> Result<(), String> {
+    let mut ids = std::collections::HashSet::new();
+    for definition in definitions {
+        if definition.id.trim().is_empty() || definition.event.trim().is_empty() {
+            return Err("missions need an id and an event".to_string());
+        }
+        if !ids.insert(definition.id.as_str()) {
+            return Err(format!("duplicate mission id {}", definition.id));
+        }
+        if definition.target == 0 {
+            return Err(format!("mission {} needs a positive target", definition.id));
+        }
+    }
+    Ok(())
+}
+
+pub async fn get_user_timezone(pool: &DragonflyPool, principal: &Principal) -> Result<Tz> {
+    let mut conn = pool.get().await?;
+    let stored: Option<String> = conn.get(timezone_key(principal)).await?;
+    Ok(stored
+        .and_then(|name| name.parse().ok())
+        .unwrap_or(chrono_tz::UTC))
+}
+
+/// Records the user's timezone unless one is already set; letting it change
+/// would let users replay a mission day by hopping across midnight
+pub async fn set_user_timezone(pool: &DragonflyPool, principal: &Principal, tz: Tz) -> Result<Tz> {
+    let mut conn = pool.get().await?;
+    let _: bool = conn.set_nx(timezone_key(principal), tz.name()).await?;
+    drop(conn);
+    get_user_timezone(pool, principal).await
+}
+
+/// The user's local mission day and the UTC timestamp it ends at
+pub fn mission_day(tz: Tz, now: DateTime<Utc>) -> (NaiveDate, i64) {
+    let day = now.with_timezone(&tz).date_naive();
+    let next_midnight = day
+        .checked_add_days(Days::new(1))
+        .and_then(|next| next.and_hms_opt(0, 0, 0))
+        .and_then(|midnight| tz.from_local_datetime(&midnight).earliest())
+        .map(|midnight| midnight.timestamp())
+        .unwrap_or_else(|| now.timestamp() + 24 * 60 * 60);
+    (day, next_midnight)
+}
+
+/// Advances every enabled mission the event counts towards
+pub async fn track_event(pool: &DragonflyPool, event_name: &str, raw_params: &str) -> Result<()> {
+    let definitions = load_definitions(pool).await?;
+    let matching: Vec<&MissionDefinition> = definitions
+        .iter()
+        .filter(|definition| definition.enabled && definition.event == event_name)
+        .collect();
+    if matching.is_empty() {
+        return Ok(());
+    }
+
+    let params: Value = serde_json::from_str(raw_params)?;
+    for definition in matching {
+        let Some(principal) = params
+            .get(&definition.principal_param)
+            .and_then(Value::as_str)
+            .and_then(|text| Principal::from_text(text).ok())
+        else {
+            continue;
+        };
+
+        let tz = get_user_timezone(pool, &principal).await?;
+        let (day, _) = mission_day(tz, Utc::now());
+        let mut conn = pool.get().await?;
+
+        if let Some(unique_param) = &definition.unique_param {
+            let Some(value) = params.get(unique_param).map(|value| match value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            }) else {
+                continue;
+            };
+            let seen_key = seen_key(&principal, &day, &definition.id);
+            let added: i64 = conn.sadd(&seen_key, value).await?;
+            conn.expire::<_, ()>(&seen_key, PROGRESS_TTL_SECS).await?;
+            if added == 0 {
+                continue;
+            }
+        }
+
+        let progress_key = progress_key(&principal, &day);
+        redis::pipe()
+            .atomic()
+            .hincr(&progress_key, &definition.id, 1)
+            .ignore()
+            .expire(&progress_key, PROGRESS_TTL_SECS)
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mission_day_expires_at_local_midnight() {
+        // 20:00 UTC is already the next day in Kolkata (UTC+5:30)
+        let now = Utc.with_ymd_and_hms(2025, 3, 10, 20, 0, 0).unwrap();
+        let (day, expires_at) = mission_day(chrono_tz::Asia::Kolkata, now);
+        assert_eq!(day, NaiveDate::from_ymd_opt(2025, 3, 11).unwrap());
+        assert_eq!(
+            expires_at,
+            Utc.with_ymd_and_hms(2025, 3, 11, 18, 30, 0)
+                .unwrap()
+                .timestamp()
+        );
+
+        let (day, _) = mission_day(chrono_tz::UTC, now);
+        assert_eq!(day, NaiveDate::from_ymd_opt(2025, 3, 10).unwrap());
+        assert!(validate_definitions(&default_definitions()).is_ok());
+    }
+}
@@ -0,0 +1,31 @@
+pub mod handlers;
+pub mod missions;
+
+use std::sync::Arc;
+
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+use crate::app_state::AppState;
+
+pub fn daily_missions_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(handlers::get_progress))
+        .routes(routes!(handlers::claim_mission))
+        .routes(routes!(
+            handlers::get_definitions,
+            handlers::put_definitions
+        ))
+        .with_state(state)
+}
+
+/// Spawned from the event pipeline so mission tracking never slows ingestion
+pub fn spawn_track_event(state: Arc<AppState>, event_name: String, raw_params: String) {
+    tokio::spawn(async move {
+        if let Err(e) =
+            missions::track_event(&state.yral_redis_store_dragonfly, &event_name, &raw_params).await
+        {
+            log::warn!("Failed to track daily missions for {event_name} event: {e:?}");
+        }
+    });
+}
@@ -0,0 +1,146 @@
+//! FCM HTTP v1 sends to individual device tokens.
+
+use hyper_util::client::legacy::connect::HttpConnector;
+use reqwest::StatusCode;
+use serde_json::Value;
+use yral_metadata_types::SendNotificationReq;
+use yup_oauth2::{authenticator::Authenticator, hyper_rustls::HttpsConnector};
+
+const FCM_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendOutcome {
+    Delivered,
+    /// FCM no longer knows the token; it should be unregistered
+    Stale,
+    Failed(String),
+}
+
+#[derive(Clone)]
+pub struct FcmClient {
+    project_id: String,
+    auth: Authenticator<HttpsConnector<HttpConnector>>,
+    client: reqwest::Client,
+}
+
+impl FcmClient {
+    pub fn new(project_id: String, auth: Authenticator<HttpsConnector<HttpConnector>>) -> Self {
+        Self {
+            project_id,
+            auth,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn send(&self, token: &str, data: &SendNotificationReq) -> SendOutcome {
+        let access_token = match self.auth.token(&[FCM_SCOPE]).await {
+            Ok(access_token) => match access_token.token() {
+                Some(access_token) => access_token.to_string(),
+                None => return SendOutcome::Failed("No FCM access token".to_string()),
+            },
+            Err(e) => return SendOutcome::Failed(format!("Failed to get FCM access token: {e}")),
+        };
+        let body = match message_body(token, data) {
+            Ok(body) => body,
+            Err(e) => return SendOutcome::Failed(e.to_string()),
+        };
+
+        let url = format!(
+            "https://fcm.googleapis.com/v1/projects/{}/messages:send",
+            self.project_id
+        );
+        match self
+            .client
+            .post(&url)
+            .bearer_auth(access_token)
+            .json(&body)
+            .send()
+            .await
+        {
+            Ok(res) => {
+                let status = res.status();
+                let text = res.text().await.unwrap_or_default();
+                classify_response(status, &text)
+            }
+            Err(e) => SendOutcome::Failed(e.to_string()),
+        }
+    }
+}
+
+/// `SendNotificationReq` mirrors the FCM message, minus the target
+fn message_body(token: &str, data: &SendNotificationReq) -> serde_json::Result<Value> {
+    let mut message = serde_json::to_value(data)?;
+    strip_nulls(&mut message);
+    message["token"] = Value::String(token.to_string());
+    Ok(serde_json::json!({ "message": message }))
+}
+
+/// FCM rejects explicit nulls for fields it types as objects or maps
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, value| !value.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+fn classify_response(status: StatusCode, body: &str) -> SendOutcome {
+    if status.is_success() {
+        return SendOutcome::Delivered;
+    }
+    // https://firebase.google.com/docs/cloud-messaging/manage-tokens#detect-invalid-token-responses-from-the-fcm-backend
+    let stale = status == StatusCode::NOT_FOUND
+        || body.contains("UNREGISTERED")
+        || (status == StatusCode::BAD_REQUEST
+            && body.contains("not a valid FCM registration token"));
+    if stale {
+        SendOutcome::Stale
+    } else {
+        SendOutcome::Failed(format!("FCM returned {status}: {body}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yral_metadata_types::NotificationPayload;
+
+    #[test]
+    fn test_fcm_message_and_feedback() {
+        let data = SendNotificationReq {
+            notification: Some(NotificationPayload {
+                title: Some("Hi".to_string()),
+                body: None,
+                image: None,
+            }),
+            ..Default::default()
+        };
+        let body = message_body("tok", &data).unwrap();
+        assert_eq!(body["message"]["token"], "tok");
+        assert_eq!(body["message"]["notification"]["title"], "Hi");
+        assert!(body["message"]["notification"].get("body").is_none());
+
+        assert_eq!(
+            classify_response(StatusCode::OK, "{}"),
+            SendOutcome::Delivered
+        );
+        assert_eq!(
+            classify_response(StatusCode::NOT_FOUND, r#"{"error":{"status":"NOT_FOUND"}}"#),
+            SendOutcome::Stale
+        );
+        assert_eq!(
+            classify_response(
+                StatusCode::BAD_REQUEST,
+                "The registration token is not a valid FCM registration token"
+            ),
+            SendOutcome::Stale
+        );
+        assert!(matches!(
+            classify_response(StatusCode::SERVICE_UNAVAILABLE, ""),
+            SendOutcome::Failed(_)
+        ));
+    }
+}
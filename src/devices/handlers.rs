@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::HeaderMap, Json};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{validate_locale, validate_token, Device, DeviceRegistry, Platform};
+use crate::{
+    app_state::AppState,
+    auth::require_user,
+    error::{ApiError, ApiErrorBody},
+};
+
+pub fn devices_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(list_devices, register_device, unregister_device))
+        .with_state(state)
+}
+
+fn registry(state: &AppState) -> DeviceRegistry {
+    DeviceRegistry::new(state.yral_redis_store_dragonfly.clone())
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RegisterDeviceRequest {
+    /// FCM registration token
+    pub token: String,
+    pub platform: Platform,
+    /// BCP 47 language tag used to localize notifications, e.g. `en-US`
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UnregisterDeviceRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UnregisterDeviceResponse {
+    /// Whether the token was registered to the caller
+    pub removed: bool,
+}
+
+/// List the caller's registered devices, most recently seen first
+#[utoipa::path(
+    get,
+    path = "",
+    params(
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the user")
+    ),
+    tag = "devices",
+    responses(
+        (status = 200, description = "Devices", body = Vec<Device>),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn list_devices(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<Device>>, ApiError> {
+    let user = require_user(&state, &headers).await?;
+    Ok(Json(registry(&state).list(&user).await?))
+}
+
+/// Register the device for push notifications, or refresh its registration.
+/// Apps call this on every start while signed in.
+#[utoipa::path(
+    post,
+    path = "",
+    params(
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the user")
+    ),
+    request_body = RegisterDeviceRequest,
+    tag = "devices",
+    responses(
+        (status = 200, description = "Device registered", body = Device),
+        (status = 400, description = "Invalid token or locale", body = ApiErrorBody),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers, request))]
+pub async fn register_device(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<RegisterDeviceRequest>,
+) -> Result<Json<Device>, ApiError> {
+    let user = require_user(&state, &headers).await?;
+    validate_token(&request.token).map_err(ApiError::InvalidRequest)?;
+    if let Some(locale) = &request.locale {
+        validate_locale(locale).map_err(ApiError::InvalidRequest)?;
+    }
+
+    let device = registry(&state)
+        .register(&user, &request.token, request.platform, request.locale)
+        .await?;
+    Ok(Json(device))
+}
+
+/// Unregister the device, e.g. on sign-out. Unknown tokens are a no-op.
+#[utoipa::path(
+    delete,
+    path = "",
+    params(
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the user")
+    ),
+    request_body = UnregisterDeviceRequest,
+    tag = "devices",
+    responses(
+        (status = 200, description = "Device unregistered", body = UnregisterDeviceResponse),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers, request))]
+pub async fn unregister_device(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<UnregisterDeviceRequest>,
+) -> Result<Json<UnregisterDeviceResponse>, ApiError> {
+    let user = require_user(&state, &headers).await?;
+    let removed = registry(&state).unregister(&user, &request.token).await?;
+    Ok(Json(UnregisterDeviceResponse { removed }))
+}
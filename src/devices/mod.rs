@@ -0,0 +1,226 @@
+//! Push notification devices.
+//!
+//! Each principal has a hash of its devices at `offchain:devices:{principal}`,
+//! keyed by FCM registration token. Registrations follow the sign-in session:
+//! apps register on every start, which refreshes `last_seen_at`, and
+//! unregister on sign-out. A token registered by another principal (account
+//! switch on the same phone) moves to it. Devices not seen for
+//! [`DEVICE_TTL_SECS`], and tokens FCM reports as unregistered, are pruned on
+//! the next send.
+
+pub mod fcm;
+#[cfg(not(feature = "local-bin"))]
+pub mod handlers;
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use candid::Principal;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::yral_auth::dragonfly::DragonflyPool;
+
+#[cfg(not(feature = "local-bin"))]
+pub use handlers::devices_router;
+
+const DEVICES_KEY_PREFIX: &str = "offchain:devices";
+const TOKEN_OWNER_KEY_PREFIX: &str = "offchain:devices:owner";
+
+pub const DEVICE_TTL_SECS: i64 = 60 * 24 * 60 * 60;
+/// Least recently seen devices are dropped past this
+pub const MAX_DEVICES: usize = 10;
+const MAX_TOKEN_LEN: usize = 4096;
+const MAX_LOCALE_LEN: usize = 35;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Platform {
+    Android,
+    Ios,
+    Web,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Device {
+    pub token: String,
+    pub platform: Platform,
+    /// BCP 47 language tag, e.g. `en-US`
+    pub locale: Option<String>,
+    /// Unix timestamps in seconds
+    pub registered_at: i64,
+    pub last_seen_at: i64,
+}
+
+impl Device {
+    pub fn is_active(&self, now: i64) -> bool {
+        now - self.last_seen_at < DEVICE_TTL_SECS
+    }
+}
+
+pub fn validate_token(token: &str) -> Result<(), String> {
+    if token.is_empty() || token.len() > MAX_TOKEN_LEN {
+        return Err(format!("token must be 1-{MAX_TOKEN_LEN} characters"));
+    }
+    if token.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err("token contains invalid characters".to_string());
+    }
+    Ok(())
+}
+
+pub fn validate_locale(locale: &str) -> Result<(), String> {
+    let valid = !locale.is_empty()
+        && locale.len() <= MAX_LOCALE_LEN
+        && locale
+            .split('-')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid locale {locale:?}"))
+    }
+}
+
+fn devices_key(user: &Principal) -> String {
+    format!("{DEVICES_KEY_PREFIX}:{}", user.to_text())
+}
+
+/// Tokens are long and opaque, so the owner index is keyed by their hash
+fn token_owner_key(token: &str) -> String {
+    format!(
+        "{TOKEN_OWNER_KEY_PREFIX}:{}",
+        hex::encode(Sha256::digest(token.as_bytes()))
+    )
+}
+
+#[derive(Clone)]
+pub struct DeviceRegistry {
+    pool: Arc<DragonflyPool>,
+}
+
+impl DeviceRegistry {
+    pub fn new(pool: Arc<DragonflyPool>) -> Self {
+        Self { pool }
+    }
+
+    /// Registers the device, or refreshes it if already registered
+    pub async fn register(
+        &self,
+        user: &Principal,
+        token: &str,
+        platform: Platform,
+        locale: Option<String>,
+    ) -> Result<Device> {
+        let key = devices_key(user);
+        let owner_key = token_owner_key(token);
+        let now = chrono::Utc::now().timestamp();
+        let mut conn = self.pool.get().await?;
+
+        let previous_owner: Option<String> = conn.get(&owner_key).await?;
+        if let Some(previous_owner) = previous_owner.filter(|owner| *owner != user.to_text()) {
+            let _: () = conn
+                .hdel(format!("{DEVICES_KEY_PREFIX}:{previous_owner}"), token)
+                .await?;
+        }
+
+        let existing: Option<String> = conn.hget(&key, token).await?;
+        let registered_at = existing
+            .and_then(|payload| serde_json::from_str::<Device>(&payload).ok())
+            .map_or(now, |device| device.registered_at);
+        let device = Device {
+            token: token.to_string(),
+            platform,
+            locale,
+            registered_at,
+            last_seen_at: now,
+        };
+
+        let _: () = conn
+            .hset(&key, token, serde_json::to_string(&device)?)
+            .await?;
+        let _: () = conn.set(&owner_key, user.to_text()).await?;
+        drop(conn);
+
+        let devices = self.list(user).await?;
+        for stale in devices.iter().skip(MAX_DEVICES) {
+            self.unregister(user, &stale.token).await?;
+        }
+        Ok(device)
+    }
+
+    /// Returns whether the device was registered
+    pub async fn unregister(&self, user: &Principal, token: &str) -> Result<bool> {
+        let owner_key = token_owner_key(token);
+        let mut conn = self.pool.get().await?;
+        let removed: u64 = conn.hdel(devices_key(user), token).await?;
+
+        let owner: Option<String> = conn.get(&owner_key).await?;
+        if owner.as_deref() == Some(user.to_text().as_str()) {
+            let _: () = conn.del(&owner_key).await?;
+        }
+        Ok(removed > 0)
+    }
+
+    /// Every registered device, most recently seen first
+    pub async fn list(&self, user: &Principal) -> Result<Vec<Device>> {
+        let mut conn = self.pool.get().await?;
+        let entries: Vec<(String, String)> = conn.hgetall(devices_key(user)).await?;
+
+        let mut devices: Vec<Device> = entries
+            .into_iter()
+            .filter_map(|(token, payload)| match serde_json::from_str(&payload) {
+                Ok(device) => Some(device),
+                Err(e) => {
+                    log::warn!("Dropping unreadable device {token} for {user}: {e}");
+                    None
+                }
+            })
+            .collect();
+        devices.sort_by_key(|device| std::cmp::Reverse(device.last_seen_at));
+        Ok(devices)
+    }
+
+    /// Devices to send to; inactive ones are unregistered on the way
+    pub async fn active_devices(&self, user: &Principal) -> Result<Vec<Device>> {
+        let now = chrono::Utc::now().timestamp();
+        let (active, inactive): (Vec<_>, Vec<_>) = self
+            .list(user)
+            .await?
+            .into_iter()
+            .partition(|device| device.is_active(now));
+
+        for device in inactive {
+            self.unregister(user, &device.token).await?;
+        }
+        Ok(active)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_validation() {
+        assert!(validate_token("dQw4w9WgXcQ:APA91bH-x_y").is_ok());
+        assert!(validate_token("").is_err());
+        assert!(validate_token("a b").is_err());
+        assert!(validate_locale("en-US").is_ok());
+        assert!(validate_locale("hi").is_ok());
+        assert!(validate_locale("en--US").is_err());
+        assert!(validate_locale("en_US").is_err());
+
+        let device = Device {
+            token: "t".to_string(),
+            platform: Platform::Ios,
+            locale: None,
+            registered_at: 0,
+            last_seen_at: 100,
+        };
+        assert!(device.is_active(100 + DEVICE_TTL_SECS - 1));
+        assert!(!device.is_active(100 + DEVICE_TTL_SECS));
+        assert_ne!(token_owner_key("a"), token_owner_key("b"));
+    }
+}
@@ -0,0 +1,291 @@
+//! Explains a dedup decision to moderators reviewing a duplicate report.
+//!
+//! The decision comes from the video's `VideoDedupStatus`. The threshold
+//! table lists the thresholds in force now, plus the one the decision was
+//! judged against when it was recorded, and whether the match distance is
+//! within each. Side-by-side frames of the video and its match are rendered
+//! on the first request and cached in `DEDUP_EXPLANATION_FRAMES`.
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::frame_diff::{compare_videos, upload_side_by_side_frame_to_gcs};
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    kvrocks::{tables, DedupExplanationFrame, DedupExplanationFrames, VideoDedupStatus},
+    moderation::verify_moderator,
+    qstash::dedup_config::{get_dedup_config, DedupConfig},
+};
+
+/// Moderator-only routes, merged into the video router before its state is set
+pub fn dedup_explanation_router(state: Arc<AppState>) -> OpenApiRouter<Arc<AppState>> {
+    OpenApiRouter::new()
+        .routes(routes!(get_dedup_explanation))
+        .layer(axum::middleware::from_fn_with_state(
+            state,
+            verify_moderator,
+        ))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DedupExplanationQuery {
+    /// Render side-by-side frames when not cached yet (default true)
+    pub frames: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ThresholdRow {
+    /// `exact`, `upload`, `backfill` or `at_decision`
+    pub name: String,
+    /// Max Hamming distance that counts as a match
+    pub max_hamming_distance: u32,
+    pub matched: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExplanationFrame {
+    pub frame_index: usize,
+    /// Between the two frames' hashes; 0 when identical
+    pub hamming_distance: u32,
+    /// PNG of the video's frame (left) next to the canonical's (right)
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DedupExplanationResponse {
+    pub video_id: String,
+    pub is_duplicate: bool,
+    /// Canonical video the upload matched
+    pub duplicate_of: Option<String>,
+    pub hamming_distance: Option<u32>,
+    pub thresholds: Vec<ThresholdRow>,
+    /// Only recorded for upload-time checks
+    pub auto_blocked: Option<bool>,
+    /// RFC 3339
+    pub decided_at: String,
+    /// Empty when not a duplicate, not rendered, or rendering failed
+    pub frames: Vec<ExplanationFrame>,
+    pub frames_error: Option<String>,
+}
+
+fn threshold_rows(
+    hamming_distance: Option<u32>,
+    config: &DedupConfig,
+    at_decision: Option<u32>,
+) -> Vec<ThresholdRow> {
+    let mut rows = vec![
+        ("exact", 0),
+        ("upload", config.hamming_threshold),
+        ("backfill", config.backfill_hamming_threshold),
+    ];
+    if let Some(threshold) = at_decision {
+        rows.push(("at_decision", threshold));
+    }
+
+    rows.into_iter()
+        .map(|(name, max_hamming_distance)| ThresholdRow {
+            name: name.to_string(),
+            max_hamming_distance,
+            matched: hamming_distance.is_some_and(|distance| distance <= max_hamming_distance),
+        })
+        .collect()
+}
+
+async fn publisher_of(state: &AppState, video_id: &str) -> anyhow::Result<String> {
+    state
+        .kvrocks_client
+        .get(&tables::VIDEO_METADATA, video_id)
+        .await?
+        .map(|metadata| metadata.publisher_user_id)
+        .ok_or_else(|| anyhow::anyhow!("No metadata for video {video_id}"))
+}
+
+async fn render_frames(
+    state: &AppState,
+    video_id: &str,
+    duplicate_of: &str,
+) -> anyhow::Result<DedupExplanationFrames> {
+    let (publisher, canonical_publisher) = tokio::try_join!(
+        publisher_of(state, video_id),
+        publisher_of(state, duplicate_of)
+    )?;
+    let (differing_frames, frames, canonical_frames, _, _) =
+        compare_videos(&publisher, video_id, &canonical_publisher, duplicate_of).await?;
+
+    let distances: HashMap<usize, u32> = differing_frames.into_iter().collect();
+    let distances = &distances;
+    let uploads = frames.iter().zip(canonical_frames.iter()).enumerate().map(
+        |(frame_index, (frame, canonical_frame))| async move {
+            let url = upload_side_by_side_frame_to_gcs(
+                state.gcs_client.clone(),
+                frame,
+                canonical_frame,
+                video_id,
+                duplicate_of,
+                frame_index,
+            )
+            .await?;
+            Ok::<_, anyhow::Error>(DedupExplanationFrame {
+                frame_index,
+                hamming_distance: distances.get(&frame_index).copied().unwrap_or(0),
+                url,
+            })
+        },
+    );
+
+    Ok(DedupExplanationFrames {
+        video_id: video_id.to_string(),
+        duplicate_of: duplicate_of.to_string(),
+        frames: futures::future::try_join_all(uploads).await?,
+        generated_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+/// Cached frames, rendered and cached first when `render` is set
+async fn load_frames(
+    state: &AppState,
+    video_id: &str,
+    duplicate_of: &str,
+    render: bool,
+) -> anyhow::Result<Option<DedupExplanationFrames>> {
+    let id = format!("{video_id}:{duplicate_of}");
+    if let Some(frames) = state
+        .kvrocks_client
+        .get(&tables::DEDUP_EXPLANATION_FRAMES, &id)
+        .await?
+    {
+        return Ok(Some(frames));
+    }
+    if !render {
+        return Ok(None);
+    }
+
+    let frames = render_frames(state, video_id, duplicate_of).await?;
+    if let Err(e) = state
+        .kvrocks_client
+        .put(&tables::DEDUP_EXPLANATION_FRAMES, &id, &frames)
+        .await
+    {
+        log::warn!("Failed to cache dedup explanation frames for {id}: {e}");
+    }
+    Ok(Some(frames))
+}
+
+/// Why a video was or wasn't flagged as a duplicate
+#[utoipa::path(
+    get,
+    path = "/{video_id}/dedup-explanation",
+    params(
+        ("video_id" = String, Path, description = "Video ID"),
+        DedupExplanationQuery
+    ),
+    tag = "videos",
+    responses(
+        (status = 200, description = "Dedup decision for the video", body = DedupExplanationResponse),
+        (status = 401, description = "Unauthorized - invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Forbidden - not a moderator", body = ApiErrorBody),
+        (status = 404, description = "No dedup decision recorded for the video", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("moderator_identity" = [])
+    )
+)]
+#[instrument(skip(state))]
+pub async fn get_dedup_explanation(
+    State(state): State<Arc<AppState>>,
+    Path(video_id): Path<String>,
+    Query(query): Query<DedupExplanationQuery>,
+) -> Result<Json<DedupExplanationResponse>, ApiError> {
+    let status: VideoDedupStatus = state
+        .kvrocks_client
+        .get(&tables::VIDEO_DEDUP_STATUS, &video_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("No dedup decision for video {video_id}")))?;
+
+    let config = get_dedup_config(&state.rewards_module.dragonfly_pool).await;
+    let thresholds = threshold_rows(status.hamming_distance, &config, status.hamming_threshold);
+
+    let (frames, frames_error) = match status.duplicate_of.as_deref() {
+        Some(duplicate_of) => {
+            match load_frames(
+                &state,
+                &video_id,
+                duplicate_of,
+                query.frames.unwrap_or(true),
+            )
+            .await
+            {
+                Ok(frames) => (frames.map(|frames| frames.frames).unwrap_or_default(), None),
+                Err(e) => {
+                    log::warn!(
+                        "Failed to render dedup frames for {video_id} vs {duplicate_of}: {e}"
+                    );
+                    (Vec::new(), Some(e.to_string()))
+                }
+            }
+        }
+        None => (Vec::new(), None),
+    };
+
+    Ok(Json(DedupExplanationResponse {
+        video_id: status.video_id,
+        is_duplicate: status.is_duplicate,
+        duplicate_of: status.duplicate_of,
+        hamming_distance: status.hamming_distance,
+        thresholds,
+        auto_blocked: status.auto_blocked,
+        decided_at: status.ingested_at,
+        frames: frames
+            .into_iter()
+            .map(|frame| ExplanationFrame {
+                frame_index: frame.frame_index,
+                hamming_distance: frame.hamming_distance,
+                url: frame.url,
+            })
+            .collect(),
+        frames_error,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_rows() {
+        let config = DedupConfig {
+            hamming_threshold: 10,
+            backfill_hamming_threshold: 3,
+            top_k: 5,
+        };
+
+        let rows = threshold_rows(Some(6), &config, Some(8));
+        let matched: Vec<_> = rows
+            .iter()
+            .map(|row| (row.name.as_str(), row.matched))
+            .collect();
+        assert_eq!(
+            matched,
+            vec![
+                ("exact", false),
+                ("upload", true),
+                ("backfill", false),
+                ("at_decision", true)
+            ]
+        );
+
+        let rows = threshold_rows(None, &config, None);
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().all(|row| !row.matched));
+    }
+}
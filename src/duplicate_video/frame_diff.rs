@@ -178,8 +178,7 @@ impl PHasherWithFrames {
     }
 }
 
-/// Join two frames horizontally for visual comparison (currently unused, kept for future use)
-#[allow(dead_code)]
+/// Join two frames horizontally for visual comparison
 pub fn join_frames_horizontally(frame1: &DynamicImage, frame2: &DynamicImage) -> DynamicImage {
     let (w1, h1) = (frame1.width(), frame1.height());
     let (w2, h2) = (frame2.width(), frame2.height());
@@ -301,6 +300,37 @@ pub async fn upload_frame_to_gcs(
     video_id_2: &str,
     frame_index: usize,
     video_num: u8, // 1 or 2
+) -> Result<String> {
+    let object_name = format!(
+        "frame-diff/{}-vs-{}/frame-{}-video{}.png",
+        video_id_1, video_id_2, frame_index, video_num
+    );
+
+    upload_png_to_gcs(client, frame, object_name).await
+}
+
+/// Upload a frame of each video joined side by side (video 1 on the left) to GCS
+pub async fn upload_side_by_side_frame_to_gcs(
+    client: Arc<Client>,
+    frame1: &DynamicImage,
+    frame2: &DynamicImage,
+    video_id_1: &str,
+    video_id_2: &str,
+    frame_index: usize,
+) -> Result<String> {
+    let combined = join_frames_horizontally(frame1, frame2);
+    let object_name = format!(
+        "frame-diff/{}-vs-{}/frame-{}-side-by-side.png",
+        video_id_1, video_id_2, frame_index
+    );
+
+    upload_png_to_gcs(client, &combined, object_name).await
+}
+
+async fn upload_png_to_gcs(
+    client: Arc<Client>,
+    frame: &DynamicImage,
+    object_name: String,
 ) -> Result<String> {
     // Convert image to PNG bytes
     let mut png_bytes = Vec::new();
@@ -308,11 +338,6 @@ pub async fn upload_frame_to_gcs(
         .write_to(&mut Cursor::new(&mut png_bytes), image::ImageFormat::Png)
         .context("Failed to encode image as PNG")?;
 
-    let object_name = format!(
-        "frame-diff/{}-vs-{}/frame-{}-video{}.png",
-        video_id_1, video_id_2, frame_index, video_num
-    );
-
     log::info!("Uploading frame to GCS: {}/{}", GCS_BUCKET, object_name);
 
     // Upload to GCS
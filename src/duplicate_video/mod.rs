@@ -1,3 +1,5 @@
+#[cfg(not(feature = "local-bin"))]
+pub mod dedup_explanation_api;
 pub mod frame_diff;
 pub mod frame_diff_api;
 pub mod phash;
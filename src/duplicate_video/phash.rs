@@ -2,6 +2,7 @@ use anyhow::{Context, Result};
 use image::DynamicImage;
 use image_hasher::{HasherConfig, ImageHash};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::path::Path;
 
 /// Perceptual hash implementation that extracts frames and concatenates their phashes
@@ -237,6 +238,9 @@ pub struct VideoMetadata {
     pub width: u32,
     pub height: u32,
     pub fps: f64,
+    /// Hex SHA-256 of the file, set when it was downloaded for hashing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_sha256: Option<String>,
 }
 
 /// Extract video metadata using ffmpeg
@@ -265,9 +269,18 @@ pub fn extract_metadata(video_path: &Path, video_id: String) -> Result<VideoMeta
         width: decoder.width(),
         height: decoder.height(),
         fps,
+        content_sha256: None,
     })
 }
 
+/// Hex SHA-256 of a file's bytes
+pub fn file_sha256(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path).context("Failed to open video file")?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).context("Failed to read video file")?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
 /// Download video from Storj (defaults to SFW bucket)
 /// Note: At deduplication time, NSFW status is not yet known, so we always use SFW bucket
 pub async fn download_video_from_storj(
@@ -417,7 +430,10 @@ pub async fn compute_phash_from_storj(
     let video_id_clone = video_id.to_string();
     let result = tokio::task::spawn_blocking(move || {
         let phash = PHasher::new().compute_hash(&video_path_clone)?;
-        let metadata = extract_metadata(&video_path_clone, video_id_clone)?;
+        let metadata = VideoMetadata {
+            content_sha256: Some(file_sha256(&video_path_clone)?),
+            ..extract_metadata(&video_path_clone, video_id_clone)?
+        };
         Ok::<_, anyhow::Error>((phash, metadata))
     })
     .await;
@@ -5,8 +5,21 @@ use utoipa_axum::router::OpenApiRouter;
 use utoipa_axum::routes;
 
 pub fn video_router(app_state: Arc<AppState>) -> OpenApiRouter {
-    OpenApiRouter::new()
+    let router = OpenApiRouter::new()
         .routes(routes!(phash_api::compute_phash_api))
-        .routes(routes!(frame_diff_api::compare_videos_api))
-        .with_state(app_state)
+        .routes(routes!(frame_diff_api::compare_videos_api));
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router
+        .routes(routes!(
+            crate::video_processing::thumbnails::get_video_thumbnails_handler
+        ))
+        .routes(routes!(
+            crate::video_processing::transcode::get_video_renditions_handler
+        ))
+        .merge(super::dedup_explanation_api::dedup_explanation_router(
+            app_state.clone(),
+        ));
+
+    router.with_state(app_state)
 }
@@ -30,3 +30,216 @@ where
         Self(err.into())
     }
 }
+
+/// Crate-wide handler error with a stable, machine-readable code.
+///
+/// Serialized as `{"code": "...", "message": "...", "details": ...}` so clients
+/// can branch on `code` instead of parsing free-form messages.
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    InvalidRequest(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    Expired(String),
+    #[error("{0}")]
+    InsufficientBalance(String),
+    #[error("{0}")]
+    RateLimited(String),
+    #[error("{0}")]
+    UnsupportedMediaType(String),
+    #[error("{0}")]
+    ServiceUnavailable(String),
+    #[error("BigQuery error: {0}")]
+    BigQuery(String),
+    #[error("Redis error: {0}")]
+    Redis(String),
+    #[error("Canister call failed: {0}")]
+    Canister(String),
+    #[error("Upstream service error: {0}")]
+    Upstream(String),
+    /// Video generation failures keep the provider-facing `VideoGenError` in `details`
+    #[error("Video generation failed")]
+    VideoGen {
+        status: StatusCode,
+        error: videogen_common::VideoGenError,
+    },
+    #[error("{0}")]
+    Internal(String),
+}
+
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct ApiErrorBody {
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidRequest(_) => "INVALID_REQUEST",
+            ApiError::Unauthorized(_) => "UNAUTHORIZED",
+            ApiError::Forbidden(_) => "FORBIDDEN",
+            ApiError::NotFound(_) => "NOT_FOUND",
+            ApiError::Conflict(_) => "CONFLICT",
+            ApiError::Expired(_) => "EXPIRED",
+            ApiError::InsufficientBalance(_) => "INSUFFICIENT_BALANCE",
+            ApiError::RateLimited(_) => "RATE_LIMITED",
+            ApiError::UnsupportedMediaType(_) => "UNSUPPORTED_MEDIA_TYPE",
+            ApiError::ServiceUnavailable(_) => "SERVICE_UNAVAILABLE",
+            ApiError::BigQuery(_) => "BIGQUERY_ERROR",
+            ApiError::Redis(_) => "REDIS_ERROR",
+            ApiError::Canister(_) => "CANISTER_ERROR",
+            ApiError::Upstream(_) => "UPSTREAM_ERROR",
+            ApiError::VideoGen { .. } => "VIDEOGEN_ERROR",
+            ApiError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Expired(_) => StatusCode::GONE,
+            ApiError::InsufficientBalance(_) => StatusCode::PAYMENT_REQUIRED,
+            ApiError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Canister(_) | ApiError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            ApiError::VideoGen { status, .. } => *status,
+            ApiError::BigQuery(_) | ApiError::Redis(_) | ApiError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            ApiError::VideoGen { error, .. } => serde_json::to_value(error).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn body(&self) -> ApiErrorBody {
+        ApiErrorBody {
+            code: self.code().to_string(),
+            message: self.to_string(),
+            details: self.details(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            log::error!("{} ({}): {}", status, self.code(), self);
+        }
+        (status, axum::Json(self.body())).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        if let Some(e) = err.downcast_ref::<redis::RedisError>() {
+            return ApiError::Redis(e.to_string());
+        }
+        if let Some(e) = err.downcast_ref::<ic_agent::AgentError>() {
+            return ApiError::Canister(e.to_string());
+        }
+        if let Some(e) = err.downcast_ref::<google_cloud_bigquery::http::error::Error>() {
+            return ApiError::BigQuery(e.to_string());
+        }
+        ApiError::Internal(format!("{err:#}"))
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(err: AppError) -> Self {
+        err.0.into()
+    }
+}
+
+impl From<redis::RedisError> for ApiError {
+    fn from(err: redis::RedisError) -> Self {
+        ApiError::Redis(err.to_string())
+    }
+}
+
+impl From<bb8::RunError<redis::RedisError>> for ApiError {
+    fn from(err: bb8::RunError<redis::RedisError>) -> Self {
+        ApiError::Redis(err.to_string())
+    }
+}
+
+impl From<google_cloud_bigquery::http::error::Error> for ApiError {
+    fn from(err: google_cloud_bigquery::http::error::Error) -> Self {
+        ApiError::BigQuery(err.to_string())
+    }
+}
+
+impl From<ic_agent::AgentError> for ApiError {
+    fn from(err: ic_agent::AgentError) -> Self {
+        ApiError::Canister(err.to_string())
+    }
+}
+
+impl From<crate::leaderboard::types::LeaderboardError> for ApiError {
+    fn from(err: crate::leaderboard::types::LeaderboardError) -> Self {
+        use crate::leaderboard::types::LeaderboardError;
+
+        match err {
+            LeaderboardError::TournamentNotFound | LeaderboardError::UserNotFound => {
+                ApiError::NotFound(err.to_string())
+            }
+            LeaderboardError::InvalidTournamentStatus => ApiError::Conflict(err.to_string()),
+            LeaderboardError::RedisError(e) => ApiError::Redis(e),
+            LeaderboardError::MetadataServiceError(e) => ApiError::Upstream(e),
+            LeaderboardError::InvalidRequest(e) => ApiError::InvalidRequest(e),
+        }
+    }
+}
+
+impl From<(StatusCode, axum::Json<videogen_common::VideoGenError>)> for ApiError {
+    fn from(
+        (status, axum::Json(error)): (StatusCode, axum::Json<videogen_common::VideoGenError>),
+    ) -> Self {
+        ApiError::VideoGen { status, error }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_error_body() {
+        let err = ApiError::NotFound("Tournament not found".to_string());
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+
+        let body = serde_json::to_value(err.body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"code": "NOT_FOUND", "message": "Tournament not found"})
+        );
+
+        let err: ApiError = anyhow::Error::from(redis::RedisError::from((
+            redis::ErrorKind::IoError,
+            "connection reset",
+        )))
+        .into();
+        assert_eq!(err.code(), "REDIS_ERROR");
+    }
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+
+use super::{
+    export_day, load_manifest, load_manifests, partition_name, ExportFile, ExportManifest,
+};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+};
+
+fn yesterday() -> NaiveDate {
+    Utc::now().date_naive() - Days::new(1)
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct RunEventExportRequest {
+    /// UTC day to export; defaults to yesterday
+    #[schema(value_type = Option<String>, format = Date)]
+    pub date: Option<NaiveDate>,
+    /// Re-export a day that already has a manifest
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// QStash scheduled job: exports a day of events to Parquet on GCS
+#[utoipa::path(
+    post,
+    path = "/event_export/run",
+    request_body = RunEventExportRequest,
+    responses(
+        (status = 200, description = "Day exported, or already exported", body = ExportManifest),
+        (status = 400, description = "Date is not in the past", body = ApiErrorBody),
+        (status = 500, description = "Export failed", body = ApiErrorBody)
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state))]
+pub async fn run_event_export_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<RunEventExportRequest>,
+) -> Result<Json<ExportManifest>, ApiError> {
+    let date = request.date.unwrap_or_else(yesterday);
+    if date >= Utc::now().date_naive() {
+        return Err(ApiError::InvalidRequest(format!("{date} is not over yet")));
+    }
+
+    if !request.force {
+        if let Some(manifest) = load_manifest(&state.yral_redis_store_dragonfly, date).await? {
+            log::info!("Events for {date} already exported, skipping");
+            return Ok(Json(manifest));
+        }
+    }
+
+    Ok(Json(export_day(&state, date).await?))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListPartitionsParams {
+    /// Only this event type
+    pub event: Option<String>,
+    /// First day, inclusive
+    #[param(value_type = Option<String>, format = Date)]
+    pub from: Option<NaiveDate>,
+    /// Last day, inclusive
+    #[param(value_type = Option<String>, format = Date)]
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PartitionEntry {
+    #[schema(value_type = String, format = Date)]
+    pub date: NaiveDate,
+    pub event: String,
+    pub rows: u64,
+    /// `gs://` URIs of the partition's Parquet files
+    pub uris: Vec<String>,
+    pub files: Vec<ExportFile>,
+}
+
+/// Exported partitions, newest day first
+#[utoipa::path(
+    get,
+    path = "/partitions",
+    params(ListPartitionsParams),
+    tag = "event-export",
+    responses(
+        (status = 200, description = "Partitions", body = Vec<PartitionEntry>),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn list_partitions(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<ListPartitionsParams>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<PartitionEntry>>, ApiError> {
+    require_operator(&headers)?;
+    let event = params.event.as_deref().map(partition_name);
+
+    let mut manifests: Vec<ExportManifest> = load_manifests(&state.yral_redis_store_dragonfly)
+        .await?
+        .into_values()
+        .filter(|manifest| params.from.is_none_or(|from| manifest.date >= from))
+        .filter(|manifest| params.to.is_none_or(|to| manifest.date <= to))
+        .collect();
+    manifests.sort_by(|a, b| b.date.cmp(&a.date));
+
+    Ok(Json(
+        manifests
+            .into_iter()
+            .flat_map(|manifest| {
+                let date = manifest.date;
+                let bucket = manifest.bucket;
+                manifest
+                    .partitions
+                    .into_iter()
+                    .map(move |partition| PartitionEntry {
+                        date,
+                        uris: partition
+                            .files
+                            .iter()
+                            .map(|file| format!("gs://{bucket}/{}", file.path))
+                            .collect(),
+                        event: partition.event,
+                        rows: partition.rows,
+                        files: partition.files,
+                    })
+            })
+            .filter(|entry| {
+                event
+                    .as_deref()
+                    .is_none_or(|event| partition_name(&entry.event) == event)
+            })
+            .collect(),
+    ))
+}
@@ -0,0 +1,366 @@
+//! Daily Parquet export of raw events for ML training.
+//!
+//! The `/qstash/event_export/run` job reads one UTC day of the events table
+//! and writes it to `EVENT_EXPORT_BUCKET` as Snappy-compressed Parquet, one
+//! partition per event type:
+//!
+//! ```text
+//! events/dt=2025-01-31/event=like_video/part-00000.parquet
+//! events/dt=2025-01-31/_manifest.json
+//! ```
+//!
+//! Rows are streamed ordered by event type, so only one partition is held in
+//! memory, split into parts of at most `EVENT_EXPORT_ROWS_PER_FILE` rows. The
+//! manifest lists every part with its row count and is written last; a day
+//! without a manifest is incomplete. Manifests are also indexed in Dragonfly
+//! for the `/api/v1/event-export/partitions` catalog. Re-exporting a day
+//! rewrites its parts and manifest; parts not in the manifest are stale.
+
+pub mod handlers;
+
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{Context, Result};
+use arrow_array::{ArrayRef, RecordBatch, StringArray, TimestampMicrosecondArray};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::NaiveDate;
+use google_cloud_bigquery::{http::job::query::QueryRequest, query::row::Row as QueryRow};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{app_state::AppState, yral_auth::dragonfly::DragonflyPool};
+
+const PROJECT_ID: &str = "hot-or-not-feed-intelligence";
+const EVENTS_TABLE: &str =
+    "`hot-or-not-feed-intelligence.analytics_335143420.test_events_analytics`";
+const MANIFESTS_KEY: &str = "offchain:event_export:manifests";
+const PARQUET_CONTENT_TYPE: &str = "application/vnd.apache.parquet";
+
+fn export_bucket() -> String {
+    std::env::var("EVENT_EXPORT_BUCKET").unwrap_or_else(|_| "yral-event-exports".to_string())
+}
+
+fn rows_per_file() -> usize {
+    std::env::var("EVENT_EXPORT_ROWS_PER_FILE")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(500_000)
+}
+
+pub fn event_export_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(handlers::list_partitions))
+        .with_state(state)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportFile {
+    /// Object name in the export bucket
+    pub path: String,
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportPartition {
+    pub event: String,
+    pub rows: u64,
+    pub files: Vec<ExportFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportManifest {
+    #[schema(value_type = String, format = Date)]
+    pub date: NaiveDate,
+    pub bucket: String,
+    /// Unix timestamp in seconds
+    pub exported_at: i64,
+    pub total_rows: u64,
+    pub partitions: Vec<ExportPartition>,
+}
+
+struct ExportRow {
+    event: String,
+    params: String,
+    /// Microseconds since the epoch
+    timestamp: i64,
+}
+
+fn export_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("event", DataType::Utf8, false),
+        Field::new("params", DataType::Utf8, true),
+        Field::new(
+            "timestamp",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            false,
+        ),
+    ])
+}
+
+fn encode_parquet(rows: &[ExportRow]) -> Result<Vec<u8>> {
+    let schema = Arc::new(export_schema());
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|row| row.event.as_str()),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|row| row.params.as_str()),
+        )),
+        Arc::new(
+            TimestampMicrosecondArray::from_iter_values(rows.iter().map(|row| row.timestamp))
+                .with_timezone("UTC"),
+        ),
+    ];
+    let batch = RecordBatch::try_new(schema.clone(), columns)?;
+
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut buffer = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buffer, schema, Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(buffer)
+}
+
+/// Event names as they appear in partition paths
+fn partition_name(event: &str) -> String {
+    let name: String = event
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "_unknown".to_string()
+    } else {
+        name
+    }
+}
+
+fn date_prefix(date: NaiveDate) -> String {
+    format!("events/dt={date}")
+}
+
+fn part_path(date: NaiveDate, event: &str, part: usize) -> String {
+    format!(
+        "{}/event={}/part-{part:05}.parquet",
+        date_prefix(date),
+        partition_name(event)
+    )
+}
+
+fn manifest_path(date: NaiveDate) -> String {
+    format!("{}/_manifest.json", date_prefix(date))
+}
+
+fn export_query(date: NaiveDate) -> String {
+    format!(
+        "SELECT event, params, UNIX_MICROS(timestamp) AS timestamp
+        FROM {EVENTS_TABLE}
+        WHERE DATE(timestamp) = '{date}'
+        ORDER BY event, timestamp"
+    )
+}
+
+/// Collects one event type's rows and uploads them in parts
+struct PartitionWriter {
+    date: NaiveDate,
+    bucket: String,
+    rows_per_file: usize,
+    partition: ExportPartition,
+    pending: Vec<ExportRow>,
+}
+
+impl PartitionWriter {
+    fn new(date: NaiveDate, bucket: &str, rows_per_file: usize, event: String) -> Self {
+        Self {
+            date,
+            bucket: bucket.to_string(),
+            rows_per_file,
+            partition: ExportPartition {
+                event,
+                rows: 0,
+                files: Vec::new(),
+            },
+            pending: Vec::new(),
+        }
+    }
+
+    async fn push(&mut self, state: &AppState, row: ExportRow) -> Result<()> {
+        self.pending.push(row);
+        if self.pending.len() >= self.rows_per_file {
+            self.flush(state).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self, state: &AppState) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let path = part_path(self.date, &self.partition.event, self.partition.files.len());
+        let bytes = encode_parquet(&self.pending)?;
+        let size = bytes.len() as u64;
+        state
+            .gcs_client
+            .object()
+            .create(&self.bucket, bytes, &path, PARQUET_CONTENT_TYPE)
+            .await
+            .with_context(|| format!("Failed to upload {path}"))?;
+
+        let rows = self.pending.len() as u64;
+        self.partition.rows += rows;
+        self.partition.files.push(ExportFile {
+            path,
+            rows,
+            bytes: size,
+        });
+        self.pending.clear();
+        Ok(())
+    }
+
+    async fn finish(mut self, state: &AppState) -> Result<ExportPartition> {
+        self.flush(state).await?;
+        Ok(self.partition)
+    }
+}
+
+/// Exports one day of events and records its manifest
+pub async fn export_day(state: &AppState, date: NaiveDate) -> Result<ExportManifest> {
+    let bucket = export_bucket();
+    let rows_per_file = rows_per_file().max(1);
+
+    let request = QueryRequest {
+        query: export_query(date),
+        ..Default::default()
+    };
+    let mut rows = state
+        .bigquery_client
+        .query::<QueryRow>(PROJECT_ID, request)
+        .await
+        .context("Failed to query events")?;
+
+    let mut partitions = Vec::new();
+    let mut current: Option<PartitionWriter> = None;
+    while let Some(row) = rows.next().await? {
+        let row = ExportRow {
+            event: row.column::<String>(0)?,
+            params: row.column::<Option<String>>(1)?.unwrap_or_default(),
+            timestamp: row.column::<i64>(2)?,
+        };
+
+        if current
+            .as_ref()
+            .is_some_and(|writer| writer.partition.event != row.event)
+        {
+            if let Some(writer) = current.take() {
+                partitions.push(writer.finish(state).await?);
+            }
+        }
+        let writer = current.get_or_insert_with(|| {
+            PartitionWriter::new(date, &bucket, rows_per_file, row.event.clone())
+        });
+        writer.push(state, row).await?;
+    }
+    if let Some(writer) = current {
+        partitions.push(writer.finish(state).await?);
+    }
+
+    let manifest = ExportManifest {
+        date,
+        bucket: bucket.clone(),
+        exported_at: chrono::Utc::now().timestamp(),
+        total_rows: partitions.iter().map(|partition| partition.rows).sum(),
+        partitions,
+    };
+    state
+        .gcs_client
+        .object()
+        .create(
+            &bucket,
+            serde_json::to_vec_pretty(&manifest)?,
+            &manifest_path(date),
+            "application/json",
+        )
+        .await
+        .context("Failed to upload manifest")?;
+    store_manifest(&state.yral_redis_store_dragonfly, &manifest).await?;
+
+    log::info!(
+        "Exported {} events for {date} in {} partitions",
+        manifest.total_rows,
+        manifest.partitions.len()
+    );
+    Ok(manifest)
+}
+
+async fn store_manifest(pool: &DragonflyPool, manifest: &ExportManifest) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = conn
+        .hset(
+            MANIFESTS_KEY,
+            manifest.date.to_string(),
+            serde_json::to_string(manifest)?,
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn load_manifest(
+    pool: &DragonflyPool,
+    date: NaiveDate,
+) -> Result<Option<ExportManifest>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.hget(MANIFESTS_KEY, date.to_string()).await?;
+    Ok(payload.and_then(|payload| serde_json::from_str(&payload).ok()))
+}
+
+/// Every recorded manifest, by date
+pub async fn load_manifests(pool: &DragonflyPool) -> Result<HashMap<NaiveDate, ExportManifest>> {
+    let mut conn = pool.get().await?;
+    let entries: HashMap<String, String> = conn.hgetall(MANIFESTS_KEY).await?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|(date, payload)| {
+            Some((date.parse().ok()?, serde_json::from_str(&payload).ok()?))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_and_encoding() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 31).unwrap();
+        assert_eq!(
+            part_path(date, "like_video", 3),
+            "events/dt=2025-01-31/event=like_video/part-00003.parquet"
+        );
+        assert_eq!(
+            part_path(date, "a/b c", 0),
+            "events/dt=2025-01-31/event=a_b_c/part-00000.parquet"
+        );
+        assert_eq!(partition_name(""), "_unknown");
+        assert_eq!(manifest_path(date), "events/dt=2025-01-31/_manifest.json");
+        assert!(export_query(date).contains("DATE(timestamp) = '2025-01-31'"));
+
+        let rows = vec![ExportRow {
+            event: "like_video".to_string(),
+            params: r#"{"video_id":"v1"}"#.to_string(),
+            timestamp: 1_738_281_600_000_000,
+        }];
+        let bytes = encode_parquet(&rows).unwrap();
+        assert!(bytes.starts_with(b"PAR1") && bytes.ends_with(b"PAR1"));
+    }
+}
@@ -0,0 +1,351 @@
+//! Event ingestion into BigQuery.
+//!
+//! Events are buffered in memory and written in batches every
+//! `BQ_EVENTS_FLUSH_INTERVAL_MS`, or early once `BQ_EVENTS_BATCH_SIZE` rows
+//! are waiting. [`EventWriter`] picks the transport with `BQ_EVENTS_WRITER`:
+//!
+//! - `storage_write` (default): the Storage Write API. Each batch goes to its
+//!   own pending stream at offset 0 and becomes visible only when the stream
+//!   is committed, so a batch lands entirely or not at all. A retried append
+//!   is rejected as `ALREADY_EXISTS` rather than duplicated, and a retried
+//!   commit reports the stream as already committed.
+//! - `insert_all`: the legacy `tabledata.insertAll` REST endpoint.
+//!
+//! A batch that still fails after retries is logged and dropped, as single
+//! events were before. The buffer is drained on graceful shutdown; events
+//! still buffered when the process dies are lost.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use futures::StreamExt;
+use google_cloud_bigquery::{client::Client, storage_write::AppendRowsRequestBuilder};
+use google_cloud_googleapis::cloud::bigquery::storage::v1::{
+    append_rows_response, storage_error::StorageErrorCode,
+};
+use once_cell::sync::{Lazy, OnceCell};
+use prost::Message;
+use prost_types::{field_descriptor_proto, DescriptorProto, FieldDescriptorProto};
+use tokio::sync::Notify;
+
+use crate::{
+    app_state::AppState,
+    consts::{BIGQUERY_EVENTS_TABLE, BIGQUERY_INGESTION_URL},
+    utils::env_parse,
+};
+
+const MAX_ATTEMPTS: u32 = 3;
+/// gRPC `ALREADY_EXISTS`, returned for an append at an offset already written
+const ALREADY_EXISTS: i32 = 6;
+
+static BUFFER: Lazy<Mutex<Vec<EventRow>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static FLUSH_NOW: Lazy<Notify> = Lazy::new(Notify::new);
+static WRITER: OnceCell<EventWriter> = OnceCell::new();
+
+#[derive(Debug, Clone)]
+pub struct EventWriterConfig {
+    pub flush_interval: Duration,
+    pub batch_size: usize,
+    /// Rows beyond this are dropped until a flush catches up
+    pub max_buffered: usize,
+}
+
+impl EventWriterConfig {
+    pub fn from_env() -> Self {
+        Self {
+            flush_interval: Duration::from_millis(env_parse("BQ_EVENTS_FLUSH_INTERVAL_MS", 1000)),
+            batch_size: env_parse("BQ_EVENTS_BATCH_SIZE", 500),
+            max_buffered: env_parse("BQ_EVENTS_MAX_BUFFERED", 50_000),
+        }
+    }
+}
+
+/// A row of the events table
+#[derive(Clone, PartialEq, Message)]
+pub struct EventRow {
+    #[prost(string, tag = "1")]
+    pub event: String,
+    /// JSON-encoded params
+    #[prost(string, tag = "2")]
+    pub params: String,
+    /// Microseconds since the epoch
+    #[prost(int64, tag = "3")]
+    pub timestamp: i64,
+}
+
+impl EventRow {
+    pub fn new(event: String, params: String) -> Self {
+        Self {
+            event,
+            params,
+            timestamp: chrono::Utc::now().timestamp_micros(),
+        }
+    }
+
+    fn to_insert_all_row(&self) -> serde_json::Value {
+        let timestamp = chrono::DateTime::from_timestamp_micros(self.timestamp)
+            .unwrap_or_default()
+            .to_rfc3339();
+        serde_json::json!({
+            "json": {
+                "event": self.event,
+                "params": self.params,
+                "timestamp": timestamp,
+            }
+        })
+    }
+}
+
+fn event_row_descriptor() -> DescriptorProto {
+    let field =
+        |name: &str, number: i32, r#type: field_descriptor_proto::Type| FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(field_descriptor_proto::Label::Optional.into()),
+            r#type: Some(r#type.into()),
+            ..Default::default()
+        };
+
+    DescriptorProto {
+        name: Some("EventRow".to_string()),
+        field: vec![
+            field("event", 1, field_descriptor_proto::Type::String),
+            field("params", 2, field_descriptor_proto::Type::String),
+            field("timestamp", 3, field_descriptor_proto::Type::Int64),
+        ],
+        ..Default::default()
+    }
+}
+
+#[derive(Clone)]
+pub enum EventWriter {
+    StorageWrite { client: Client, table: String },
+    InsertAll { app_state: Arc<AppState> },
+}
+
+impl EventWriter {
+    pub fn from_env(app_state: &AppState) -> Self {
+        match std::env::var("BQ_EVENTS_WRITER").as_deref() {
+            Ok("insert_all") => Self::InsertAll {
+                app_state: Arc::new(app_state.clone()),
+            },
+            _ => Self::StorageWrite {
+                client: app_state.bigquery_client.clone(),
+                table: BIGQUERY_EVENTS_TABLE.to_string(),
+            },
+        }
+    }
+
+    pub async fn write(&self, rows: &[EventRow]) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        match self {
+            Self::StorageWrite { client, table } => write_pending_stream(client, table, rows).await,
+            Self::InsertAll { app_state } => write_insert_all(app_state, rows).await,
+        }
+    }
+}
+
+async fn write_pending_stream(client: &Client, table: &str, rows: &[EventRow]) -> Result<()> {
+    let mut writer = client.pending_storage_writer(table);
+    let stream = writer.create_write_stream().await?;
+    let data: Vec<Vec<u8>> = rows.iter().map(Message::encode_to_vec).collect();
+
+    let mut attempt = 1;
+    loop {
+        let request =
+            AppendRowsRequestBuilder::new(event_row_descriptor(), data.clone()).with_offset(0);
+        let result = async {
+            let mut responses = stream.append_rows(vec![request]).await?;
+            while let Some(response) = responses.next().await {
+                let response = response?;
+                if !response.row_errors.is_empty() {
+                    bail!(
+                        "{} rows rejected: {:?}",
+                        response.row_errors.len(),
+                        response.row_errors
+                    );
+                }
+                if let Some(append_rows_response::Response::Error(status)) = response.response {
+                    if status.code != ALREADY_EXISTS {
+                        bail!("Append failed: {}", status.message);
+                    }
+                }
+            }
+            Ok(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => break,
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                log::warn!("Retrying event append (attempt {attempt}): {e:?}");
+                tokio::time::sleep(Duration::from_millis(200 * attempt as u64)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    stream.finalize().await?;
+
+    let mut attempt = 1;
+    loop {
+        let result = writer
+            .commit()
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|response| {
+                let errors: Vec<_> = response
+                    .stream_errors
+                    .iter()
+                    .filter(|error| error.code != StorageErrorCode::StreamAlreadyCommitted as i32)
+                    .collect();
+                if !errors.is_empty() {
+                    bail!("Commit failed: {errors:?}");
+                }
+                Ok(())
+            });
+
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                log::warn!("Retrying event commit (attempt {attempt}): {e:?}");
+                tokio::time::sleep(Duration::from_millis(200 * attempt as u64)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn write_insert_all(app_state: &AppState, rows: &[EventRow]) -> Result<()> {
+    let token = app_state
+        .get_access_token(&["https://www.googleapis.com/auth/bigquery.insertdata"])
+        .await;
+    let data = serde_json::json!({
+        "kind": "bigquery#tableDataInsertAllRequest",
+        "rows": rows.iter().map(EventRow::to_insert_all_row).collect::<Vec<_>>(),
+    });
+
+    let response = reqwest::Client::new()
+        .post(BIGQUERY_INGESTION_URL.to_string())
+        .bearer_auth(token)
+        .json(&data)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        bail!("Failed to stream data - {:?}", response.text().await?);
+    }
+    Ok(())
+}
+
+/// Buffers an event for the next flush. Before [`spawn_event_writer`] has
+/// run, the event is written on its own.
+pub fn enqueue(app_state: &AppState, row: EventRow) {
+    if WRITER.get().is_none() {
+        let writer = EventWriter::from_env(app_state);
+        tokio::spawn(async move {
+            if let Err(e) = writer.write(&[row]).await {
+                log::error!("Error sending data to BigQuery: {e:?}");
+            }
+        });
+        return;
+    }
+
+    let config = EventWriterConfig::from_env();
+    let buffered = {
+        let mut buffer = BUFFER.lock().unwrap_or_else(|e| e.into_inner());
+        if buffer.len() >= config.max_buffered {
+            drop(buffer);
+            log::warn!("Event buffer full, dropping {} event", row.event);
+            return;
+        }
+        buffer.push(row);
+        buffer.len()
+    };
+
+    if buffered >= config.batch_size {
+        FLUSH_NOW.notify_one();
+    }
+}
+
+/// Writes everything buffered, one batch at a time. Returns the number of
+/// rows written.
+pub async fn flush_events() -> usize {
+    let Some(writer) = WRITER.get() else {
+        return 0;
+    };
+    let batch_size = EventWriterConfig::from_env().batch_size.max(1);
+
+    let mut written = 0;
+    loop {
+        let batch: Vec<EventRow> = {
+            let mut buffer = BUFFER.lock().unwrap_or_else(|e| e.into_inner());
+            let take = buffer.len().min(batch_size);
+            buffer.drain(..take).collect()
+        };
+        if batch.is_empty() {
+            return written;
+        }
+
+        match writer.write(&batch).await {
+            Ok(()) => written += batch.len(),
+            Err(e) => log::error!("Dropping {} events after failed write: {e:?}", batch.len()),
+        }
+    }
+}
+
+pub fn spawn_event_writer(app_state: Arc<AppState>) {
+    if WRITER.set(EventWriter::from_env(&app_state)).is_err() {
+        log::warn!("Event writer already running");
+        return;
+    }
+    let config = EventWriterConfig::from_env();
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.flush_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = FLUSH_NOW.notified() => {}
+            }
+            flush_events().await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_row_encoding() {
+        let row = EventRow {
+            event: "like_video".to_string(),
+            params: r#"{"video_id":"v1"}"#.to_string(),
+            timestamp: 1_700_000_000_123_456,
+        };
+        assert_eq!(
+            EventRow::decode(row.encode_to_vec().as_slice()).unwrap(),
+            row
+        );
+
+        let descriptor = event_row_descriptor();
+        let fields: Vec<_> = descriptor
+            .field
+            .iter()
+            .map(|field| (field.name(), field.number()))
+            .collect();
+        assert_eq!(fields, vec![("event", 1), ("params", 2), ("timestamp", 3)]);
+
+        assert_eq!(
+            row.to_insert_all_row()["json"]["timestamp"],
+            "2023-11-14T22:13:20.123456+00:00"
+        );
+    }
+}
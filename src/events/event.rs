@@ -1,3 +1,4 @@
+use crate::canister::agent_pool;
 use crate::consts::{USER_INFO_SERVICE_CANISTER_ID, USER_POST_SERVICE_CANISTER_ID};
 use crate::events::types::{
     string_or_number, VideoDurationWatchedPayload, VideoDurationWatchedPayloadV2,
@@ -6,18 +7,23 @@ use crate::events::types::{
 use crate::pipeline::Step;
 use crate::setup_context;
 use crate::{
-    app_state::AppState, consts::BIGQUERY_INGESTION_URL, events::warehouse_events::WarehouseEvent,
+    app_state::AppState,
+    events::bigquery_writer::{self, EventRow},
+    events::warehouse_events::WarehouseEvent,
+    qstash::job::ExtractFramesJob,
+    utils::gcs_upload::ResumableUpload,
     AppError,
 };
 use axum::{extract::State, Json};
 use log::{debug, error};
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
 use tracing::instrument;
 
 pub mod storj;
+#[cfg(not(feature = "local-bin"))]
+pub mod storj_backfill;
 
 /// Flat event for Mixpanel - event name + all params at same level
 #[allow(dead_code)]
@@ -77,33 +83,13 @@ impl Event {
         })
     }
 
-    /// BigQuery format: {event: string, params: string (JSON), timestamp: string}
+    /// BigQuery format: {event: string, params: string (JSON), timestamp}
     pub fn stream_to_bigquery(&self, app_state: &AppState) {
-        let event_str = self.event.event.clone();
-        let params_str = self.event.params.clone();
-        let app_state = app_state.clone();
-
-        tokio::spawn(async move {
-            let timestamp = chrono::Utc::now().to_rfc3339();
-
-            let data = serde_json::json!({
-                "kind": "bigquery#tableDataInsertAllRequest",
-                "rows": [
-                    {
-                        "json": {
-                            "event": event_str,
-                            "params": params_str,
-                            "timestamp": timestamp,
-                        }
-                    }
-                ]
-            });
-
-            // Push to BigQuery (events stay in analytical DB only, not kvrocks)
-            if let Err(e) = stream_to_bigquery(&app_state, data).await {
-                error!("Error sending data to BigQuery: {}", e);
-            }
-        });
+        // Events stay in the analytical DB only, not kvrocks
+        bigquery_writer::enqueue(
+            app_state,
+            EventRow::new(self.event.event.clone(), self.event.params.clone()),
+        );
     }
 
     /// Mixpanel format: {event: string, user_id: string, video_id: string, ...} (flat)
@@ -184,6 +170,7 @@ impl Event {
             #[cfg(not(feature = "local-bin"))]
             {
                 let video_processing_pool = app_state.yral_redis_store_dragonfly.clone();
+                let upload_params = params.clone();
                 let video_id = params.video_id;
                 let post_id = params.post_id.clone();
                 let publisher_user_id = params.publisher_user_id.to_text();
@@ -203,6 +190,42 @@ impl Event {
                 )
                 .await?;
                 log::info!("Durable video processing job queued for video_id: {video_id}");
+
+                // Hide before the feed can pick it up; a failure here must not publish early
+                if let Some(publish_at) = upload_params.publish_at {
+                    crate::scheduled_publish::schedule_upload(
+                        app_state,
+                        &upload_params,
+                        publish_at,
+                    )
+                    .await?;
+                }
+
+                // Search indexing is best-effort and never fails the upload
+                let mut raw_params: Value =
+                    serde_json::from_str(&self.event.params).unwrap_or(Value::Null);
+                crate::text_moderation::moderate_caption(app_state, &video_id, &mut raw_params)
+                    .await;
+                if let Err(e) = crate::posts::search::index_upload(
+                    &app_state.yral_redis_store_dragonfly,
+                    &upload_params,
+                    &raw_params,
+                )
+                .await
+                {
+                    log::error!("Failed to index video {video_id} for search: {e:?}");
+                }
+
+                if let Err(e) = crate::posts::hashtags::record_upload_hashtags(
+                    &app_state.yral_redis_store_dragonfly,
+                    &video_id,
+                    &raw_params,
+                    chrono::Utc::now().timestamp(),
+                )
+                .await
+                {
+                    log::error!("Failed to record hashtags for video {video_id}: {e:?}");
+                }
             }
         }
 
@@ -211,6 +234,25 @@ impl Event {
 
     // TODO: canister_id being used
     pub fn update_view_count_canister(&self, app_state: &AppState) {
+        #[cfg(not(feature = "local-bin"))]
+        if self.event.event == "video_duration_watched"
+            && crate::events::view_aggregator::aggregation_enabled()
+        {
+            use crate::events::view_aggregator::{parse_watch_event, record_view};
+
+            let Some((post_id, percentage_watched)) = parse_watch_event(&self.event.params) else {
+                debug!("Skipping video_duration_watched without a valid post_id/percentage");
+                return;
+            };
+            let app_state = app_state.clone();
+            tokio::spawn(async move {
+                if let Err(e) = record_view(&app_state, &post_id, percentage_watched).await {
+                    error!("Failed to record view for post {post_id}: {e:?}");
+                }
+            });
+            return;
+        }
+
         if self.event.event == "video_duration_watched" {
             // Try V3 first (new format with publisher_user_id)
             let params_v3: Result<VideoDurationWatchedPayloadV2, _> =
@@ -235,6 +277,43 @@ impl Event {
                         let post_id = params.post_id; // Already a String
                         let watch_count = 1u8;
 
+                        #[cfg(not(feature = "local-bin"))]
+                        let v3_enabled = crate::feature_flags::WATCH_HISTORY_V3
+                            .get_for(&app_state.yral_redis_store_dragonfly, &params.user_id)
+                            .await;
+                        #[cfg(feature = "local-bin")]
+                        let v3_enabled = true;
+
+                        if !v3_enabled {
+                            // Flag off: skip the publisher lookup, same as V2 payloads
+                            let payload = match percentage_watched.cmp(&95) {
+                                Ordering::Less => {
+                                    UserPostViewDetails::WatchedPartially { percentage_watched }
+                                }
+                                _ => UserPostViewDetails::WatchedMultipleTimes {
+                                    percentage_watched,
+                                    watch_count,
+                                },
+                            };
+
+                            let user_post_service =
+                                UserPostService(*USER_POST_SERVICE_CANISTER_ID, &app_state.agent);
+
+                            if let Err(e) = agent_pool::update(
+                                *USER_POST_SERVICE_CANISTER_ID,
+                                "update_post_add_view_details",
+                                user_post_service
+                                    .update_post_add_view_details(post_id.clone(), payload),
+                            )
+                            .await
+                            {
+                                error!(
+                                    "Failed to update view details for post {post_id} in UserPostService canister (v3 disabled): {e:?}"
+                                );
+                            }
+                            return;
+                        }
+
                         // Get publisher user ID
                         let publisher_user_id = match params.publisher_user_id {
                             Some(id) => id,
@@ -268,9 +347,13 @@ impl Event {
                                         &app_state.agent,
                                     );
 
-                                    if let Err(e) = user_post_service
-                                        .update_post_add_view_details(post_id.clone(), payload)
-                                        .await
+                                    if let Err(e) = agent_pool::update(
+                                        *USER_POST_SERVICE_CANISTER_ID,
+                                        "update_post_add_view_details",
+                                        user_post_service
+                                            .update_post_add_view_details(post_id.clone(), payload),
+                                    )
+                                    .await
                                     {
                                         error!(
                                             "Failed to update view details for post {post_id} in UserPostService canister: {e:?}"
@@ -295,9 +378,13 @@ impl Event {
                                         &app_state.agent,
                                     );
 
-                                    if let Err(e) = user_post_service
-                                        .update_post_add_view_details(post_id.clone(), payload)
-                                        .await
+                                    if let Err(e) = agent_pool::update(
+                                        *USER_POST_SERVICE_CANISTER_ID,
+                                        "update_post_add_view_details",
+                                        user_post_service
+                                            .update_post_add_view_details(post_id.clone(), payload),
+                                    )
+                                    .await
                                     {
                                         error!(
                                             "Failed to update view details for post {post_id} in UserPostService canister (legacy user): {e:?}"
@@ -321,9 +408,7 @@ impl Event {
                                     &app_state.agent,
                                 );
 
-                                let _ = user_post_service
-                                    .update_post_add_view_details(post_id.clone(), payload)
-                                    .await
+                                let _ = agent_pool::update(*USER_POST_SERVICE_CANISTER_ID, "update_post_add_view_details", user_post_service.update_post_add_view_details(post_id.clone(), payload)).await
                                     .map_err(|e| error!("Failed to update view details for post {post_id} in UserPostService canister (fallback): {e:?}"));
                             }
                         }
@@ -371,9 +456,13 @@ impl Event {
                                     &app_state.agent,
                                 );
 
-                                if let Err(e) = user_post_service
-                                    .update_post_add_view_details(post_id.clone(), payload)
-                                    .await
+                                if let Err(e) = agent_pool::update(
+                                    *USER_POST_SERVICE_CANISTER_ID,
+                                    "update_post_add_view_details",
+                                    user_post_service
+                                        .update_post_add_view_details(post_id.clone(), payload),
+                                )
+                                .await
                                 {
                                     error!(
                                         "Failed to update view details for post {post_id} in UserPostService canister (V2 legacy): {e:?}"
@@ -409,6 +498,17 @@ impl Event {
             }
         };
 
+        #[cfg(not(feature = "local-bin"))]
+        if let Err(e) = crate::posts::search::record_engagement(
+            &app_state.yral_redis_store_dragonfly,
+            &params.video_id,
+            1.0,
+        )
+        .await
+        {
+            log::error!("Failed to record search engagement: {e:?}");
+        }
+
         // Initialize reward engine
         let reward_engine = app_state.rewards_module.reward_engine.clone();
 
@@ -438,6 +538,17 @@ impl Event {
             }
         };
 
+        #[cfg(not(feature = "local-bin"))]
+        if let Err(e) = crate::posts::search::record_engagement(
+            &app_state.yral_redis_store_dragonfly,
+            &params.video_id,
+            1.0,
+        )
+        .await
+        {
+            log::error!("Failed to record search engagement: {e:?}");
+        }
+
         // Initialize reward engine
         let reward_engine = app_state.rewards_module.reward_engine.clone();
 
@@ -448,28 +559,6 @@ impl Event {
     }
 }
 
-async fn stream_to_bigquery(
-    app_state: &AppState,
-    data: Value,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let token = app_state
-        .get_access_token(&["https://www.googleapis.com/auth/bigquery.insertdata"])
-        .await;
-    let client = Client::new();
-    let request_url = BIGQUERY_INGESTION_URL.to_string();
-    let response = client
-        .post(request_url)
-        .bearer_auth(token)
-        .json(&data)
-        .send()
-        .await?;
-
-    match response.status().is_success() {
-        true => Ok(()),
-        false => Err(format!("Failed to stream data - {:?}", response.text().await?).into()),
-    }
-}
-
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct UploadVideoInfoV2 {
     pub video_id: String,
@@ -491,6 +580,7 @@ pub async fn upload_video_gcs(
     });
 
     upload_gcs_impl(
+        &state,
         &payload.video_id,
         &payload.publisher_user_id,
         payload.post_id.clone(),
@@ -500,7 +590,10 @@ pub async fn upload_video_gcs(
 
     let qstash_client = state.qstash_client.clone();
     qstash_client
-        .publish_video_frames(&payload.video_id, &payload)
+        .publish_job(&ExtractFramesJob {
+            video_id: &payload.video_id,
+            video_info: &payload,
+        })
         .await?;
 
     Ok(Json(
@@ -508,16 +601,29 @@ pub async fn upload_video_gcs(
     ))
 }
 
-#[instrument(skip(uid))]
+#[instrument(skip(state, uid))]
 #[allow(dead_code)]
 pub async fn upload_gcs_impl(
+    state: &AppState,
+    uid: &str,
+    publisher_user_id: &str,
+    post_id: String,
+    timestamp_str: &str,
+) -> Result<(), anyhow::Error> {
+    // SFW bucket - NSFW status not yet determined at this stage
+    upload_gcs_from_storj(state, uid, publisher_user_id, post_id, timestamp_str, false).await
+}
+
+/// Copies a video from the Storj bucket its NSFW verdict points at to GCS
+pub async fn upload_gcs_from_storj(
+    state: &AppState,
     uid: &str,
     publisher_user_id: &str,
     post_id: String,
     timestamp_str: &str,
+    is_nsfw: bool,
 ) -> Result<(), anyhow::Error> {
-    // Download from Storj (SFW bucket - NSFW status not yet determined at this stage)
-    let url = crate::consts::get_storj_video_url(publisher_user_id, uid, false);
+    let url = crate::consts::get_storj_video_url(publisher_user_id, uid, is_nsfw);
     let name = format!("{uid}.mp4");
 
     log::info!("Downloading video from Storj: {}", url);
@@ -537,31 +643,12 @@ pub async fn upload_gcs_impl(
 
     log::info!("Uploading video to GCS bucket 'yral-videos' as {}", name);
 
-    // Upload to GCS
-    let gcs_client = cloud_storage::Client::default();
-    gcs_client
-        .object()
-        .create("yral-videos", video_bytes.to_vec(), &name, "video/mp4")
-        .await
-        .map_err(|e| {
-            log::error!("Failed to upload to GCS: {}", e);
-            anyhow::anyhow!("GCS upload failed: {}", e)
-        })?;
-
-    log::info!("Reading object from GCS to update metadata");
-
-    // Now update metadata since create doesn't accept it
-    let mut obj = gcs_client
-        .object()
-        .read("yral-videos", &name)
-        .await
-        .map_err(|e| {
-            log::error!("Failed to read object from GCS: {}", e);
-            anyhow::anyhow!("Failed to read GCS object for metadata update: {}", e)
-        })?;
-
-    obj.metadata = Some(
-        [
+    // Resumable, so a flaky connection resumes mid-video instead of failing it
+    ResumableUpload {
+        bucket: "yral-videos",
+        name: &name,
+        content_type: "video/mp4",
+        metadata: [
             (
                 "publisher_user_id".to_string(),
                 publisher_user_id.to_string(),
@@ -571,13 +658,12 @@ pub async fn upload_gcs_impl(
         ]
         .into_iter()
         .collect(),
-    );
-
-    log::info!("Updating GCS object metadata");
-
-    gcs_client.object().update(&obj).await.map_err(|e| {
-        log::error!("Failed to update GCS metadata: {}", e);
-        anyhow::anyhow!("GCS metadata update failed: {}", e)
+    }
+    .upload(state, video_bytes)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to upload to GCS: {e:#}");
+        anyhow::anyhow!("GCS upload failed: {e:#}")
     })?;
 
     log::info!("Successfully uploaded video {} to GCS with metadata", uid);
@@ -1,46 +1,158 @@
-use std::sync::Arc;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use axum::{extract::State, Json};
+use axum::{extract::State, http::HeaderMap, Json};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use tracing::instrument;
+use utoipa::ToSchema;
 
 use crate::{
     app_state::AppState,
+    auth::require_operator,
     consts::{STORJ_INTERFACE_TOKEN, STORJ_INTERFACE_URL},
-    pipeline::Step,
+    error::ApiError,
+    pipeline::{telemetry, Step},
+    qstash::job::{Lane, PublishOptions},
     setup_context, AppError,
 };
 
-#[instrument]
+/// Copies an uploaded video to Storj
+#[utoipa::path(
+    post,
+    path = "/storj_ingest",
+    request_body(content = serde_json::Value, description = "`storj_interface::duplicate::Args`"),
+    responses(
+        (status = 200, description = "Video copied to Storj"),
+        (status = 500, description = "Storj interface request failed")
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state))]
 pub async fn storj_ingest(
+    State(state): State<Arc<AppState>>,
     Json(payload): Json<storj_interface::duplicate::Args>,
 ) -> Result<(), AppError> {
     setup_context!(&payload.video_id, Step::StorjIngest, {
         "args": &payload
     });
 
-    let client = reqwest::Client::new();
-    client
-        .post(
-            STORJ_INTERFACE_URL
-                .join("/duplicate")
-                .expect("url to be valid"),
-        )
-        .json(&payload)
-        .bearer_auth(STORJ_INTERFACE_TOKEN.as_str())
-        .send()
-        .await?
-        .error_for_status()?;
+    telemetry::measure(
+        &state,
+        &payload.video_id,
+        Step::StorjIngest,
+        duplicate_via_storj_interface(&payload),
+    )
+    .await?;
 
     Ok(())
 }
 
+/// Duplicate calls retried on network errors and 5xx before giving up
+const DUPLICATE_ATTEMPTS: u32 = 3;
+const DUPLICATE_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+static METRICS: Lazy<Mutex<StorjIngestStats>> =
+    Lazy::new(|| Mutex::new(StorjIngestStats::default()));
+
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct StorjIngestStats {
+    pub succeeded: u64,
+    pub failed: u64,
+    /// Attempts beyond the first, across all calls
+    pub retries: u64,
+    /// Wall time of successful calls, retries included
+    pub total_latency_ms: u64,
+    pub max_latency_ms: u64,
+}
+
+fn record(f: impl FnOnce(&mut StorjIngestStats)) {
+    f(&mut METRICS.lock().unwrap_or_else(|e| e.into_inner()));
+}
+
+/// Whether a failed duplicate call may succeed when repeated
+fn is_retryable(error: &reqwest::Error) -> bool {
+    error
+        .status()
+        .map_or(true, |status| status.is_server_error())
+}
+
+pub async fn duplicate_via_storj_interface(
+    payload: &storj_interface::duplicate::Args,
+) -> anyhow::Result<()> {
+    let client = reqwest::Client::new();
+    let started = Instant::now();
+    let mut attempt = 1;
+    loop {
+        telemetry::count_provider_call();
+        let result = client
+            .post(
+                STORJ_INTERFACE_URL
+                    .join("/duplicate")
+                    .expect("url to be valid"),
+            )
+            .json(payload)
+            .bearer_auth(STORJ_INTERFACE_TOKEN.as_str())
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+
+        match result {
+            Ok(_) => {
+                let latency_ms = started.elapsed().as_millis() as u64;
+                record(|stats| {
+                    stats.succeeded += 1;
+                    stats.total_latency_ms += latency_ms;
+                    stats.max_latency_ms = stats.max_latency_ms.max(latency_ms);
+                });
+                return Ok(());
+            }
+            Err(e) if attempt < DUPLICATE_ATTEMPTS && is_retryable(&e) => {
+                log::warn!(
+                    "Storj duplicate attempt {attempt} for video {} failed, retrying: {e}",
+                    payload.video_id
+                );
+                record(|stats| stats.retries += 1);
+                tokio::time::sleep(DUPLICATE_RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                record(|stats| stats.failed += 1);
+                return Err(e.into());
+            }
+        }
+    }
+}
+
+/// Storj duplicate call counters since process start; operators only
+pub async fn storj_ingest_metrics_handler(
+    headers: HeaderMap,
+) -> Result<Json<StorjIngestStats>, ApiError> {
+    require_operator(&headers)?;
+    Ok(Json(
+        METRICS.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+    ))
+}
+
+/// A slice of a bulk backfill, processed by a single QStash delivery
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StorjBackfillChunk {
+    pub backfill_id: String,
+    pub video_ids: Vec<String>,
+}
+
 /// for the purpose of backfilling, can be removed once there are no more items
 /// to be filled
 pub async fn enqueue_storj_backfill_item(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<storj_interface::duplicate::Args>,
 ) -> Result<(), AppError> {
-    state.qstash_client.duplicate_to_storj(payload).await?;
+    state
+        .qstash_client
+        .publish_job_with(&payload, PublishOptions::in_lane(Lane::Bulk))
+        .await?;
 
     Ok(())
 }
@@ -0,0 +1,727 @@
+//! Bulk Storj backfill driven by a manifest.
+//!
+//! A manifest is an explicit list of duplicate args, uploads selected by a
+//! structured filter (upload date range, optionally one publisher), or both.
+//! The filter is turned into a fixed BigQuery query here; callers never
+//! supply SQL. Endpoints require the operator admin token. Items are deduplicated
+//! by video id, split into chunks and published to QStash. Every item's
+//! status lives in Redis so operators can follow progress and retry failures
+//! without re-submitting the whole manifest.
+
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Context;
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    routing::{get, post},
+    Json, Router,
+};
+use candid::Principal;
+use chrono::{Days, NaiveDate};
+use futures::StreamExt;
+use google_cloud_bigquery::http::{
+    job::query::QueryRequest,
+    tabledata::list::{Tuple, Value},
+};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::instrument;
+
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+    events::event::storj::{duplicate_via_storj_interface, StorjBackfillChunk},
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const KEY_PREFIX: &str = "offchain:storj_backfill";
+/// Backfill bookkeeping is kept for two weeks after the last write
+const BACKFILL_TTL_SECS: i64 = 14 * 24 * 60 * 60;
+
+const DEFAULT_CHUNK_SIZE: usize = 25;
+const MAX_CHUNK_SIZE: usize = 100;
+const MAX_MANIFEST_ITEMS: usize = 100_000;
+const DEFAULT_FAILED_LIMIT: usize = 100;
+/// Items of one chunk copied at a time
+const CHUNK_CONCURRENCY: usize = 4;
+
+type DuplicateArgs = storj_interface::duplicate::Args;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackfillItemStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+impl BackfillItemStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BackfillItemStatus::Pending => "pending",
+            BackfillItemStatus::Succeeded => "succeeded",
+            BackfillItemStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(BackfillItemStatus::Pending),
+            "succeeded" => Some(BackfillItemStatus::Succeeded),
+            "failed" => Some(BackfillItemStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackfillMeta {
+    pub backfill_id: String,
+    pub created_at: i64,
+    pub total: usize,
+    pub chunk_size: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkBackfillRequest {
+    /// Explicit manifest entries
+    #[serde(default)]
+    pub items: Vec<DuplicateArgs>,
+    /// Adds every upload matching the filter
+    pub filter: Option<BackfillFilter>,
+    pub chunk_size: Option<usize>,
+}
+
+/// Uploads to backfill, selected from the `video_upload_successful` events
+#[derive(Debug, Clone, Deserialize)]
+pub struct BackfillFilter {
+    /// First upload day included (UTC)
+    pub uploaded_from: NaiveDate,
+    /// Last upload day included (UTC), today when absent
+    pub uploaded_to: Option<NaiveDate>,
+    /// Only this publisher's uploads
+    pub publisher_user_id: Option<String>,
+    /// At most this many videos, oldest first (max 100000)
+    pub limit: Option<usize>,
+}
+
+impl BackfillFilter {
+    /// The fixed manifest query for this filter. Every interpolated value is
+    /// a date, a re-encoded principal or a number.
+    fn to_query(&self) -> Result<String, ApiError> {
+        let uploaded_to = self
+            .uploaded_to
+            .unwrap_or_else(|| chrono::Utc::now().date_naive());
+        if uploaded_to < self.uploaded_from {
+            return Err(ApiError::InvalidRequest(
+                "uploaded_to is before uploaded_from".to_string(),
+            ));
+        }
+        let end = uploaded_to + Days::new(1);
+        let publisher_clause = match self.publisher_user_id.as_deref() {
+            Some(publisher) => {
+                let principal = Principal::from_text(publisher).map_err(|e| {
+                    ApiError::InvalidRequest(format!("Invalid publisher_user_id: {e}"))
+                })?;
+                format!(
+                    "AND JSON_EXTRACT_SCALAR(e.params, '$.publisher_user_id') = '{}'",
+                    principal.to_text()
+                )
+            }
+            None => String::new(),
+        };
+        let limit = self
+            .limit
+            .unwrap_or(MAX_MANIFEST_ITEMS)
+            .clamp(1, MAX_MANIFEST_ITEMS);
+
+        Ok(format!(
+            "SELECT u.video_id, u.publisher_user_id, COALESCE(n.is_nsfw, FALSE)
+             FROM (
+               SELECT JSON_EXTRACT_SCALAR(e.params, '$.video_id') AS video_id,
+                      ANY_VALUE(JSON_EXTRACT_SCALAR(e.params, '$.publisher_user_id')) AS publisher_user_id,
+                      MIN(e.timestamp) AS uploaded_at
+               FROM `hot-or-not-feed-intelligence.analytics_335143420.test_events_analytics` e
+               WHERE e.event = 'video_upload_successful'
+                 AND e.timestamp >= TIMESTAMP('{}')
+                 AND e.timestamp < TIMESTAMP('{end}')
+                 {publisher_clause}
+               GROUP BY video_id
+             ) u
+             LEFT JOIN `hot-or-not-feed-intelligence.yral_ds.video_nsfw` n
+               ON n.video_id = u.video_id
+             WHERE u.video_id IS NOT NULL AND u.publisher_user_id IS NOT NULL
+             ORDER BY u.uploaded_at
+             LIMIT {limit}",
+            self.uploaded_from
+        ))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkBackfillResponse {
+    pub backfill_id: String,
+    pub total: usize,
+    pub chunks_queued: usize,
+    pub chunks_failed: usize,
+    /// Filter rows that could not be turned into duplicate args
+    pub rejected_rows: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProgressParams {
+    pub failed_limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailedBackfillItem {
+    pub video_id: String,
+    pub error: Option<String>,
+    pub attempts: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackfillProgress {
+    pub backfill_id: String,
+    pub created_at: i64,
+    pub total: usize,
+    pub pending: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub failed_items: Vec<FailedBackfillItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetryBackfillRequest {
+    /// Items to retry; every failed item when omitted (send `{}`)
+    pub video_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetryBackfillResponse {
+    pub retried: usize,
+    pub chunks_queued: usize,
+    pub chunks_failed: usize,
+}
+
+fn meta_key(backfill_id: &str) -> String {
+    format!("{KEY_PREFIX}:{backfill_id}:meta")
+}
+
+fn items_key(backfill_id: &str) -> String {
+    format!("{KEY_PREFIX}:{backfill_id}:items")
+}
+
+fn status_key(backfill_id: &str) -> String {
+    format!("{KEY_PREFIX}:{backfill_id}:status")
+}
+
+fn errors_key(backfill_id: &str) -> String {
+    format!("{KEY_PREFIX}:{backfill_id}:errors")
+}
+
+fn attempts_key(backfill_id: &str) -> String {
+    format!("{KEY_PREFIX}:{backfill_id}:attempts")
+}
+
+/// Keeps the last occurrence of each video id
+fn dedup_manifest(items: Vec<DuplicateArgs>) -> Vec<DuplicateArgs> {
+    let mut by_video_id: HashMap<String, usize> = HashMap::new();
+    let mut deduped: Vec<DuplicateArgs> = Vec::with_capacity(items.len());
+    for item in items {
+        match by_video_id.get(&item.video_id) {
+            Some(&idx) => deduped[idx] = item,
+            None => {
+                by_video_id.insert(item.video_id.clone(), deduped.len());
+                deduped.push(item);
+            }
+        }
+    }
+    deduped
+}
+
+fn chunk_video_ids(
+    backfill_id: &str,
+    video_ids: &[String],
+    chunk_size: usize,
+) -> Vec<StorjBackfillChunk> {
+    video_ids
+        .chunks(chunk_size.max(1))
+        .map(|chunk| StorjBackfillChunk {
+            backfill_id: backfill_id.to_string(),
+            video_ids: chunk.to_vec(),
+        })
+        .collect()
+}
+
+#[instrument(skip(bigquery_client))]
+async fn load_manifest_from_filter(
+    bigquery_client: &google_cloud_bigquery::client::Client,
+    filter: &BackfillFilter,
+) -> Result<(Vec<DuplicateArgs>, usize), ApiError> {
+    let request = QueryRequest {
+        query: filter.to_query()?,
+        ..Default::default()
+    };
+
+    let result = bigquery_client
+        .job()
+        .query("hot-or-not-feed-intelligence", &request)
+        .await
+        .context("Failed to load storj backfill manifest")?;
+
+    let mut items = Vec::new();
+    let mut rejected = 0;
+    for row in result.rows.unwrap_or_default() {
+        match duplicate_args_from_row(&row) {
+            Some(args) => items.push(args),
+            None => rejected += 1,
+        }
+    }
+
+    Ok((items, rejected))
+}
+
+fn duplicate_args_from_row(row: &Tuple) -> Option<DuplicateArgs> {
+    let cell = |i: usize| match row.f.get(i).map(|cell| &cell.v) {
+        Some(Value::String(value)) => Some(value.as_str()),
+        _ => None,
+    };
+    serde_json::from_value(json!({
+        "publisher_user_id": cell(1)?,
+        "video_id": cell(0)?,
+        "is_nsfw": cell(2)? == "true",
+        "metadata": {},
+    }))
+    .ok()
+}
+
+async fn refresh_ttl(
+    conn: &mut redis::aio::MultiplexedConnection,
+    backfill_id: &str,
+) -> anyhow::Result<()> {
+    for key in [
+        meta_key(backfill_id),
+        items_key(backfill_id),
+        status_key(backfill_id),
+        errors_key(backfill_id),
+        attempts_key(backfill_id),
+    ] {
+        let _: () = conn.expire(key, BACKFILL_TTL_SECS).await?;
+    }
+    Ok(())
+}
+
+async fn get_meta(
+    pool: &Arc<DragonflyPool>,
+    backfill_id: &str,
+) -> anyhow::Result<Option<BackfillMeta>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.get(meta_key(backfill_id)).await?;
+    payload
+        .map(|p| serde_json::from_str(&p).context("Corrupt storj backfill meta"))
+        .transpose()
+}
+
+async fn set_item_status(
+    pool: &Arc<DragonflyPool>,
+    backfill_id: &str,
+    video_id: &str,
+    status: BackfillItemStatus,
+    error: Option<&str>,
+) -> anyhow::Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = conn
+        .hset(status_key(backfill_id), video_id, status.as_str())
+        .await?;
+    match error {
+        Some(error) => {
+            let _: () = conn.hset(errors_key(backfill_id), video_id, error).await?;
+        }
+        None => {
+            let _: () = conn.hdel(errors_key(backfill_id), video_id).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Publishes chunks, returning `(queued, failed)`. Items of chunks that fail
+/// to publish stay pending and can be re-queued through the retry endpoint.
+async fn publish_chunks(state: &AppState, chunks: Vec<StorjBackfillChunk>) -> (usize, usize) {
+    let mut queued = 0;
+    let mut failed = 0;
+    for chunk in chunks {
+        match state.qstash_client.publish_job(&chunk).await {
+            Ok(()) => queued += 1,
+            Err(e) => {
+                log::error!(
+                    "Failed to queue storj backfill chunk for {} ({} items): {e:?}",
+                    chunk.backfill_id,
+                    chunk.video_ids.len()
+                );
+                failed += 1;
+            }
+        }
+    }
+    (queued, failed)
+}
+
+async fn start_bulk_backfill_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<BulkBackfillRequest>,
+) -> Result<Json<BulkBackfillResponse>, ApiError> {
+    require_operator(&headers)?;
+
+    let mut items = request.items;
+    let mut rejected_rows = 0;
+    if let Some(filter) = request.filter.as_ref() {
+        let (filter_items, rejected) =
+            load_manifest_from_filter(&state.bigquery_client, filter).await?;
+        items.extend(filter_items);
+        rejected_rows = rejected;
+    }
+
+    let items = dedup_manifest(items);
+    if items.is_empty() {
+        return Err(ApiError::InvalidRequest(
+            "Manifest is empty; provide items or a filter matching uploads".to_string(),
+        ));
+    }
+    if items.len() > MAX_MANIFEST_ITEMS {
+        return Err(ApiError::InvalidRequest(format!(
+            "Manifest has {} items, the limit is {MAX_MANIFEST_ITEMS}",
+            items.len()
+        )));
+    }
+
+    let chunk_size = request
+        .chunk_size
+        .unwrap_or(DEFAULT_CHUNK_SIZE)
+        .clamp(1, MAX_CHUNK_SIZE);
+    let backfill_id = uuid::Uuid::new_v4().to_string();
+    let meta = BackfillMeta {
+        backfill_id: backfill_id.clone(),
+        created_at: chrono::Utc::now().timestamp(),
+        total: items.len(),
+        chunk_size,
+    };
+
+    let mut item_entries = Vec::with_capacity(items.len());
+    for item in &items {
+        let payload = serde_json::to_string(item).map_err(|e| ApiError::Internal(e.to_string()))?;
+        item_entries.push((item.video_id.clone(), payload));
+    }
+    let video_ids: Vec<String> = items.iter().map(|item| item.video_id.clone()).collect();
+    let status_entries: Vec<(&str, &str)> = video_ids
+        .iter()
+        .map(|video_id| (video_id.as_str(), BackfillItemStatus::Pending.as_str()))
+        .collect();
+
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+    let meta_payload =
+        serde_json::to_string(&meta).map_err(|e| ApiError::Internal(e.to_string()))?;
+    let _: () = conn.set(meta_key(&backfill_id), meta_payload).await?;
+    for entries in item_entries.chunks(1000) {
+        let _: () = conn.hset_multiple(items_key(&backfill_id), entries).await?;
+    }
+    for entries in status_entries.chunks(1000) {
+        let _: () = conn
+            .hset_multiple(status_key(&backfill_id), entries)
+            .await?;
+    }
+    refresh_ttl(&mut conn, &backfill_id).await?;
+
+    let (chunks_queued, chunks_failed) = publish_chunks(
+        &state,
+        chunk_video_ids(&backfill_id, &video_ids, chunk_size),
+    )
+    .await;
+
+    log::info!(
+        "Storj backfill {backfill_id} started: {} items, {chunks_queued} chunks queued, {chunks_failed} failed",
+        meta.total
+    );
+
+    Ok(Json(BulkBackfillResponse {
+        backfill_id,
+        total: meta.total,
+        chunks_queued,
+        chunks_failed,
+        rejected_rows,
+    }))
+}
+
+async fn backfill_progress_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(backfill_id): Path<String>,
+    Query(params): Query<ProgressParams>,
+) -> Result<Json<BackfillProgress>, ApiError> {
+    require_operator(&headers)?;
+
+    let pool = &state.yral_redis_store_dragonfly;
+    let meta = get_meta(pool, &backfill_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Storj backfill {backfill_id} not found")))?;
+
+    let mut conn = pool.get().await?;
+    let statuses: HashMap<String, String> = conn.hgetall(status_key(&backfill_id)).await?;
+
+    let mut progress = BackfillProgress {
+        backfill_id: backfill_id.clone(),
+        created_at: meta.created_at,
+        total: meta.total,
+        pending: 0,
+        succeeded: 0,
+        failed: 0,
+        failed_items: Vec::new(),
+    };
+    let mut failed_ids = Vec::new();
+    for (video_id, status) in statuses {
+        match BackfillItemStatus::parse(&status) {
+            Some(BackfillItemStatus::Pending) | None => progress.pending += 1,
+            Some(BackfillItemStatus::Succeeded) => progress.succeeded += 1,
+            Some(BackfillItemStatus::Failed) => {
+                progress.failed += 1;
+                failed_ids.push(video_id);
+            }
+        }
+    }
+
+    failed_ids.sort();
+    failed_ids.truncate(params.failed_limit.unwrap_or(DEFAULT_FAILED_LIMIT));
+    if !failed_ids.is_empty() {
+        let errors: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(errors_key(&backfill_id))
+            .arg(&failed_ids)
+            .query_async(&mut conn)
+            .await?;
+        let attempts: Vec<Option<u32>> = redis::cmd("HMGET")
+            .arg(attempts_key(&backfill_id))
+            .arg(&failed_ids)
+            .query_async(&mut conn)
+            .await?;
+
+        progress.failed_items = failed_ids
+            .into_iter()
+            .zip(errors)
+            .zip(attempts)
+            .map(|((video_id, error), attempts)| FailedBackfillItem {
+                video_id,
+                error,
+                attempts: attempts.unwrap_or(0),
+            })
+            .collect();
+    }
+
+    Ok(Json(progress))
+}
+
+async fn retry_backfill_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(backfill_id): Path<String>,
+    Json(request): Json<RetryBackfillRequest>,
+) -> Result<Json<RetryBackfillResponse>, ApiError> {
+    require_operator(&headers)?;
+
+    let pool = &state.yral_redis_store_dragonfly;
+    let meta = get_meta(pool, &backfill_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Storj backfill {backfill_id} not found")))?;
+
+    let mut conn = pool.get().await?;
+    let statuses: HashMap<String, String> = conn.hgetall(status_key(&backfill_id)).await?;
+
+    let mut video_ids: Vec<String> = match request.video_ids {
+        Some(video_ids) => {
+            if let Some(unknown) = video_ids.iter().find(|id| !statuses.contains_key(*id)) {
+                return Err(ApiError::InvalidRequest(format!(
+                    "Video {unknown} is not part of storj backfill {backfill_id}"
+                )));
+            }
+            video_ids
+        }
+        None => statuses
+            .into_iter()
+            .filter(|(_, status)| {
+                BackfillItemStatus::parse(status) == Some(BackfillItemStatus::Failed)
+            })
+            .map(|(video_id, _)| video_id)
+            .collect(),
+    };
+    video_ids.sort();
+    video_ids.dedup();
+
+    if video_ids.is_empty() {
+        return Ok(Json(RetryBackfillResponse {
+            retried: 0,
+            chunks_queued: 0,
+            chunks_failed: 0,
+        }));
+    }
+
+    for video_id in &video_ids {
+        set_item_status(
+            pool,
+            &backfill_id,
+            video_id,
+            BackfillItemStatus::Pending,
+            None,
+        )
+        .await?;
+    }
+    refresh_ttl(&mut conn, &backfill_id).await?;
+
+    let (chunks_queued, chunks_failed) = publish_chunks(
+        &state,
+        chunk_video_ids(&backfill_id, &video_ids, meta.chunk_size),
+    )
+    .await;
+
+    log::info!(
+        "Storj backfill {backfill_id}: retrying {} items in {chunks_queued} chunks",
+        video_ids.len()
+    );
+
+    Ok(Json(RetryBackfillResponse {
+        retried: video_ids.len(),
+        chunks_queued,
+        chunks_failed,
+    }))
+}
+
+/// Processes one chunk. Per-item failures are recorded rather than returned so
+/// QStash does not redeliver items that already succeeded.
+#[utoipa::path(
+    post,
+    path = "/storj_backfill_chunk",
+    request_body = StorjBackfillChunk,
+    responses(
+        (status = 200, description = "Chunk processed"),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state), fields(backfill_id = %chunk.backfill_id))]
+pub async fn storj_backfill_chunk_handler(
+    State(state): State<Arc<AppState>>,
+    Json(chunk): Json<StorjBackfillChunk>,
+) -> Result<(), ApiError> {
+    let backfill_id = &chunk.backfill_id;
+
+    let results: Vec<Result<(), ApiError>> = futures::stream::iter(&chunk.video_ids)
+        .map(|video_id| backfill_item(&state.yral_redis_store_dragonfly, backfill_id, video_id))
+        .buffer_unordered(CHUNK_CONCURRENCY)
+        .collect()
+        .await;
+    results.into_iter().collect()
+}
+
+async fn backfill_item(
+    pool: &Arc<DragonflyPool>,
+    backfill_id: &str,
+    video_id: &str,
+) -> Result<(), ApiError> {
+    let mut conn = pool.get().await?;
+    let status: Option<String> = conn.hget(status_key(backfill_id), video_id).await?;
+    if status.as_deref().and_then(BackfillItemStatus::parse) == Some(BackfillItemStatus::Succeeded)
+    {
+        return Ok(());
+    }
+
+    let args: Option<String> = conn.hget(items_key(backfill_id), video_id).await?;
+    let _: () = conn.hincr(attempts_key(backfill_id), video_id, 1).await?;
+
+    let result = match args.map(|args| serde_json::from_str::<DuplicateArgs>(&args)) {
+        Some(Ok(args)) => duplicate_via_storj_interface(&args).await,
+        Some(Err(e)) => Err(anyhow::anyhow!("Corrupt manifest entry: {e}")),
+        None => Err(anyhow::anyhow!("Manifest entry missing")),
+    };
+
+    match result {
+        Ok(()) => {
+            set_item_status(
+                pool,
+                backfill_id,
+                video_id,
+                BackfillItemStatus::Succeeded,
+                None,
+            )
+            .await?
+        }
+        Err(e) => {
+            log::warn!("Storj backfill {backfill_id} failed for video {video_id}: {e:?}");
+            set_item_status(
+                pool,
+                backfill_id,
+                video_id,
+                BackfillItemStatus::Failed,
+                Some(&e.to_string()),
+            )
+            .await?
+        }
+    }
+
+    Ok(())
+}
+
+/// `POST /` starts a backfill, `GET /{id}` reports progress and
+/// `POST /{id}/retry` re-queues failed (or selected) items
+pub fn storj_backfill_router<S>(app_state: Arc<AppState>) -> Router<S> {
+    Router::new()
+        .route("/", post(start_bulk_backfill_handler))
+        .route("/{backfill_id}", get(backfill_progress_handler))
+        .route("/{backfill_id}/retry", post(retry_backfill_handler))
+        .with_state(app_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunking_covers_every_item_once() {
+        let video_ids: Vec<String> = (0..53).map(|i| format!("video_{i}")).collect();
+        let chunks = chunk_video_ids("bf", &video_ids, 25);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2].video_ids.len(), 3);
+        let flattened: Vec<String> = chunks.into_iter().flat_map(|c| c.video_ids).collect();
+        assert_eq!(flattened, video_ids);
+
+        for status in [
+            BackfillItemStatus::Pending,
+            BackfillItemStatus::Succeeded,
+            BackfillItemStatus::Failed,
+        ] {
+            assert_eq!(BackfillItemStatus::parse(status.as_str()), Some(status));
+        }
+    }
+
+    #[test]
+    fn test_filter_query_only_interpolates_typed_values() {
+        let mut filter = BackfillFilter {
+            uploaded_from: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            uploaded_to: Some(NaiveDate::from_ymd_opt(2025, 1, 31).unwrap()),
+            publisher_user_id: Some("2vxsx-fae".to_string()),
+            limit: Some(500),
+        };
+        let query = filter.to_query().unwrap();
+        assert!(query.contains("TIMESTAMP('2025-01-01')"));
+        assert!(query.contains("TIMESTAMP('2025-02-01')"));
+        assert!(query.contains("= '2vxsx-fae'"));
+        assert!(query.contains("LIMIT 500"));
+
+        filter.publisher_user_id = Some("x' OR '1'='1".to_string());
+        assert!(filter.to_query().is_err());
+
+        filter.publisher_user_id = None;
+        filter.uploaded_to = NaiveDate::from_ymd_opt(2024, 12, 31);
+        assert!(filter.to_query().is_err());
+    }
+}
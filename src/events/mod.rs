@@ -1,10 +1,15 @@
-use axum::extract::State;
+use axum::extract::{ConnectInfo, State};
 use axum::response::IntoResponse;
 use axum::{middleware, Json};
+use candid::Principal;
 use event::Event;
+use futures::StreamExt;
 use http::{header, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use types::AnalyticsEvent;
 use utoipa::ToSchema;
@@ -18,7 +23,9 @@ use warehouse_events::warehouse_events_server::WarehouseEvents;
 use crate::auth::check_auth_events;
 use crate::events::verify::verify_event_bulk_request_v3;
 use crate::events::warehouse_events::{Empty, WarehouseEvent};
+use crate::middleware::body_guard::{body_limit, SMALL_JSON_LIMIT};
 use crate::types::DelegatedIdentityWire;
+use crate::utils::delegated_identity::verify_delegated_identity_request;
 use crate::AppState;
 
 pub mod warehouse_events {
@@ -27,15 +34,21 @@ pub mod warehouse_events {
         tonic::include_file_descriptor_set!("warehouse_events_descriptor");
 }
 
+pub mod bigquery_writer;
 pub mod event;
+pub mod notification_templates;
 // Retired QStash NSFW handlers are kept for rollback/cleanup context, but are not mounted.
 #[allow(dead_code)]
 pub mod nsfw;
 pub mod push_notifications;
 pub mod queries;
+pub mod sink;
 pub mod types;
 pub mod utils;
+pub mod velocity;
 pub mod verify;
+#[cfg(not(feature = "local-bin"))]
+pub mod view_aggregator;
 
 /// Convert PascalCase to snake_case (e.g., "VideoDurationWatched" -> "video_duration_watched")
 fn to_snake_case(s: &str) -> String {
@@ -53,6 +66,13 @@ fn to_snake_case(s: &str) -> String {
     result
 }
 
+/// Most events accepted in one REST bulk request
+const MAX_BULK_EVENTS: usize = 500;
+/// Users whose bulk events are processed at a time
+const BULK_CONCURRENCY: usize = 16;
+/// Sent back for events that failed processing; the cause is only logged
+const PROCESSING_FAILED_MESSAGE: &str = "Failed to process event, retry it";
+
 pub struct WarehouseEventsService {
     pub shared_state: Arc<AppState>,
 }
@@ -79,7 +99,7 @@ impl WarehouseEvents for WarehouseEventsService {
 
 pub fn events_router(state: Arc<AppState>) -> OpenApiRouter {
     OpenApiRouter::new()
-        .routes(routes!(post_event))
+        .routes(routes!(post_event).layer(body_limit(SMALL_JSON_LIMIT)))
         .routes(
             routes!(handle_bulk_events).layer(middleware::from_fn_with_state(
                 state.clone(),
@@ -93,6 +113,61 @@ pub fn events_router(state: Arc<AppState>) -> OpenApiRouter {
 pub struct EventRequest {
     event: String,
     params: String,
+    /// Authenticates the event as the user instead of with the bearer
+    /// token; the verified principal is stamped into `params.user_id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    delegated_identity_wire: Option<DelegatedIdentityWire>,
+}
+
+/// Sets `user_id` in the event params to the verified principal. A
+/// `user_id` the client sent must already match it.
+fn stamp_user_id(params: &str, user_principal: Principal) -> Result<String, (StatusCode, String)> {
+    let mut params: Value = serde_json::from_str(params).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Event params are not valid JSON: {e}"),
+        )
+    })?;
+    let Value::Object(ref mut map) = params else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Event params must be a JSON object".to_string(),
+        ));
+    };
+
+    let user_principal = user_principal.to_string();
+    if let Some(user_id) = map.get("user_id") {
+        if user_id.as_str() != Some(user_principal.as_str()) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Invalid user_id: does not match authenticated principal".to_string(),
+            ));
+        }
+    }
+    map.insert("user_id".to_string(), Value::String(user_principal));
+
+    Ok(params.to_string())
+}
+
+/// Verifies the event's delegated identity and returns its params stamped
+/// with the user's principal, along with the principal
+async fn verify_event_identity(
+    state: &AppState,
+    delegated_identity_wire: DelegatedIdentityWire,
+    headers: &axum::http::HeaderMap,
+    params: &str,
+) -> Result<(String, Principal), (StatusCode, String)> {
+    let user_info = verify_delegated_identity_request(state, delegated_identity_wire, headers)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::UNAUTHORIZED,
+                format!("Failed to get user info: {e}"),
+            )
+        })?;
+    crate::middleware::set_user_context(user_info.user_principal);
+    let params = stamp_user_id(params, user_info.user_principal)?;
+    Ok((params, user_info.user_principal))
 }
 
 #[utoipa::path(
@@ -102,21 +177,35 @@ pub struct EventRequest {
     tag = "events",
     responses(
         (status = 200, description = "Event sent successfully"),
+        (status = 400, description = "Params don't match the delegated identity"),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal server error"),
+    ),
+    security(
+        ("bearer" = []),
+        ("delegated_identity" = [])
     )
 )]
 async fn post_event(
     State(state): State<Arc<AppState>>,
     headers: axum::http::HeaderMap,
-    Json(payload): Json<EventRequest>,
+    Json(mut payload): Json<EventRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let auth_token = headers
-        .get(header::AUTHORIZATION)
-        .and_then(|value| value.to_str().ok())
-        .map(|value| value.trim_start_matches("Bearer ").to_string());
+    match payload.delegated_identity_wire.take() {
+        Some(delegated_identity_wire) => {
+            (payload.params, _) =
+                verify_event_identity(&state, delegated_identity_wire, &headers, &payload.params)
+                    .await?;
+        }
+        None => {
+            let auth_token = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim_start_matches("Bearer ").to_string());
 
-    check_auth_events(auth_token).map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()))?;
+            check_auth_events(auth_token).map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()))?;
+        }
+    }
 
     let warehouse_event = WarehouseEvent {
         event: payload.event.clone(),
@@ -149,6 +238,7 @@ async fn process_event_impl(
 ) -> Result<(), anyhow::Error> {
     #[cfg(not(feature = "local-bin"))]
     event.stream_to_bigquery(&shared_state.clone());
+    sink::publish(&event.event);
 
     // event.forward_to_mixpanel(&shared_state);
 
@@ -189,11 +279,17 @@ async fn process_event_impl(
 }
 
 async fn process_event_impl_v2(
-    event: Event,
+    mut event: Event,
     shared_state: Arc<AppState>,
+    sender: Option<&velocity::Sender>,
 ) -> Result<(), anyhow::Error> {
+    if !velocity::enforce(&shared_state, &mut event.event, sender).await {
+        return Ok(());
+    }
+
     #[cfg(not(feature = "local-bin"))]
     event.stream_to_bigquery(&shared_state.clone());
+    sink::publish(&event.event);
 
     // event.forward_to_mixpanel(&shared_state);
 
@@ -203,6 +299,33 @@ async fn process_event_impl_v2(
 
     event.update_view_count_canister(&shared_state.clone());
 
+    crate::leaderboard::ingest::spawn_ingest_event_score(
+        shared_state.clone(),
+        event.event.event.clone(),
+        event.event.params.clone(),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    crate::daily_missions::spawn_track_event(
+        shared_state.clone(),
+        event.event.event.clone(),
+        event.event.params.clone(),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    crate::referrals::spawn_track_event(
+        shared_state.clone(),
+        event.event.event.clone(),
+        event.event.params.clone(),
+        sender.and_then(velocity::Sender::principal),
+    );
+    #[cfg(not(feature = "local-bin"))]
+    crate::streaks::spawn_track_event(
+        shared_state.clone(),
+        event.event.event.clone(),
+        event.event.params.clone(),
+    );
+
     // #[cfg(not(feature = "local-bin"))]
     // {
     //     use crate::events::push_notifications::dispatch_notif;
@@ -233,6 +356,182 @@ async fn process_event_impl_v2(
     Ok(())
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkEventStatus {
+    Accepted,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkEventErrorCode {
+    /// The event is malformed; retrying it won't help
+    InvalidEvent,
+    /// Processing failed; the event can be retried
+    ProcessingFailed,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BulkEventResult {
+    /// Position of the event in the request
+    pub index: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
+    pub status: BulkEventStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<BulkEventErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of every event of a bulk request, in request order
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BulkEventsResponse {
+    pub accepted: u32,
+    pub failed: u32,
+    pub results: Vec<BulkEventResult>,
+}
+
+impl BulkEventsResponse {
+    /// Whether the event at `index` of the request was accepted
+    fn is_accepted(&self, index: usize) -> bool {
+        self.results
+            .get(index)
+            .is_some_and(|result| result.status == BulkEventStatus::Accepted)
+    }
+
+    fn from_results(results: Vec<BulkEventResult>) -> Self {
+        let accepted = results
+            .iter()
+            .filter(|result| result.status == BulkEventStatus::Accepted)
+            .count() as u32;
+        Self {
+            accepted,
+            failed: results.len() as u32 - accepted,
+            results,
+        }
+    }
+
+    /// 200 when every event was accepted, 207 when some were; when none
+    /// were, 500 if any is worth retrying and 400 otherwise
+    fn status_code(&self) -> StatusCode {
+        if self.failed == 0 {
+            StatusCode::OK
+        } else if self.accepted > 0 {
+            StatusCode::MULTI_STATUS
+        } else if self
+            .results
+            .iter()
+            .any(|result| result.error_code == Some(BulkEventErrorCode::ProcessingFailed))
+        {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::BAD_REQUEST
+        }
+    }
+}
+
+fn check_bulk_size(len: usize) -> Result<(), (StatusCode, String)> {
+    if len > MAX_BULK_EVENTS {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("At most {MAX_BULK_EVENTS} events per bulk request, got {len}"),
+        ));
+    }
+    Ok(())
+}
+
+/// User an event belongs to, from its `user_id` param
+fn event_user(event: &WarehouseEvent) -> Option<String> {
+    let params: Value = serde_json::from_str(&event.params).ok()?;
+    params.get("user_id")?.as_str().map(str::to_string)
+}
+
+async fn process_bulk_event<F, Fut>(
+    index: u32,
+    event: Result<WarehouseEvent, String>,
+    process: &F,
+) -> BulkEventResult
+where
+    F: Fn(Event) -> Fut,
+    Fut: Future<Output = Result<(), anyhow::Error>>,
+{
+    let warehouse_event = match event {
+        Ok(warehouse_event) => warehouse_event,
+        Err(error) => {
+            return BulkEventResult {
+                index,
+                event: None,
+                status: BulkEventStatus::Failed,
+                error_code: Some(BulkEventErrorCode::InvalidEvent),
+                error: Some(error),
+            }
+        }
+    };
+
+    let event_name = warehouse_event.event.clone();
+    match process(Event::new(warehouse_event)).await {
+        Ok(()) => BulkEventResult {
+            index,
+            event: Some(event_name),
+            status: BulkEventStatus::Accepted,
+            error_code: None,
+            error: None,
+        },
+        Err(e) => {
+            log::error!("Failed to process bulk event {index} ({event_name}): {e}");
+            BulkEventResult {
+                index,
+                event: Some(event_name),
+                status: BulkEventStatus::Failed,
+                error_code: Some(BulkEventErrorCode::ProcessingFailed),
+                error: Some(PROCESSING_FAILED_MESSAGE.to_string()),
+            }
+        }
+    }
+}
+
+/// Processes bulk events. One user's events run in request order, since
+/// some depend on earlier ones (`video_started` before
+/// `video_duration_watched`); [`BULK_CONCURRENCY`] users run at a time.
+/// `Err` entries are events that couldn't be parsed, reported without being
+/// processed.
+async fn process_bulk<F, Fut>(
+    events: Vec<Result<WarehouseEvent, String>>,
+    process: F,
+) -> BulkEventsResponse
+where
+    F: Fn(Event) -> Fut,
+    Fut: Future<Output = Result<(), anyhow::Error>>,
+{
+    let mut by_user: BTreeMap<Option<String>, Vec<(u32, Result<WarehouseEvent, String>)>> =
+        BTreeMap::new();
+    for (index, event) in events.into_iter().enumerate() {
+        let user = event.as_ref().ok().and_then(event_user);
+        by_user.entry(user).or_default().push((index as u32, event));
+    }
+
+    let process = &process;
+    let mut results: Vec<BulkEventResult> = futures::stream::iter(by_user.into_values())
+        .map(|user_events| async move {
+            let mut results = Vec::with_capacity(user_events.len());
+            for (index, event) in user_events {
+                results.push(process_bulk_event(index, event, process).await);
+            }
+            results
+        })
+        .buffer_unordered(BULK_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+    results.sort_by_key(|result| result.index);
+
+    BulkEventsResponse::from_results(results)
+}
+
 #[derive(Serialize, Deserialize, Clone, ToSchema)]
 pub struct EventBulkRequest {
     pub delegated_identity_wire: DelegatedIdentityWire,
@@ -250,33 +549,53 @@ pub struct VerifiedEventBulkRequest {
     request_body = EventBulkRequest,
     tag = "events",
     responses(
-        (status = 200, description = "Bulk event success"),
-        (status = 400, description = "Bulk event failed"),
-        (status = 500, description = "Internal server error"),
+        (status = 200, description = "Every event accepted", body = BulkEventsResponse),
+        (status = 207, description = "Some events failed; see results", body = BulkEventsResponse),
+        (status = 400, description = "No event was valid", body = BulkEventsResponse),
+        (status = 401, description = "Invalid delegated identity"),
         (status = 403, description = "Forbidden"),
+        (status = 413, description = "Too many events in one request"),
+        (status = 500, description = "Every event failed processing", body = BulkEventsResponse),
+    ),
+    security(
+        ("delegated_identity" = [])
     )
 )]
 async fn handle_bulk_events(
     State(state): State<Arc<AppState>>,
     Json(request): Json<VerifiedEventBulkRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    for req_event in &request.events {
-        let event = Event::new(WarehouseEvent {
-            event: req_event.tag(),
-            params: req_event.params().to_string(),
-        });
-
-        if let Err(e) = process_event_impl(event, state.clone()).await {
-            log::error!("Failed to process event rest: {e}"); // not sending any error to the client as it is a bulk request
-        }
+    check_bulk_size(request.events.len())?;
+
+    let events = request
+        .events
+        .iter()
+        .map(|req_event| {
+            Ok(WarehouseEvent {
+                event: req_event.tag(),
+                params: req_event.params().to_string(),
+            })
+        })
+        .collect();
+    let response = process_bulk(events, |event| process_event_impl(event, state.clone())).await;
+
+    // Only accepted events go on to naitik, so retried failures aren't seen twice
+    let accepted = VerifiedEventBulkRequest {
+        events: request
+            .events
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| response.is_accepted(*index))
+            .map(|(_, event)| event)
+            .collect(),
+    };
+    if !accepted.events.is_empty() {
+        state
+            .naitik_multi_service_client
+            .send_bulk_events_v1_to_naitik_multi_services(accepted);
     }
 
-    // After processing all events, we send events to naitik multi services in bulk
-    state
-        .naitik_multi_service_client
-        .send_bulk_events_v1_to_naitik_multi_services(request);
-
-    Ok((StatusCode::OK, "Events processed".to_string()))
+    Ok((response.status_code(), Json(response)))
 }
 
 /// V2 bulk event request with delegated identity auth and arbitrary payloads
@@ -301,57 +620,82 @@ pub struct VerifiedEventBulkRequestV2 {
     request_body = EventBulkRequestV2,
     tag = "events",
     responses(
-        (status = 200, description = "Bulk event success"),
-        (status = 400, description = "Bulk event failed"),
-        (status = 500, description = "Internal server error"),
+        (status = 200, description = "Every event accepted", body = BulkEventsResponse),
+        (status = 207, description = "Some events failed; see results", body = BulkEventsResponse),
+        (status = 400, description = "No event was valid", body = BulkEventsResponse),
+        (status = 401, description = "Invalid delegated identity"),
         (status = 403, description = "Forbidden"),
+        (status = 413, description = "Too many events in one request"),
+        (status = 500, description = "Every event failed processing", body = BulkEventsResponse),
+    ),
+    security(
+        ("delegated_identity" = [])
     )
 )]
 async fn handle_bulk_events_v2(
     State(state): State<Arc<AppState>>,
     Json(request): Json<VerifiedEventBulkRequestV2>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let events_payload = request.clone();
-    for mut payload in request.events {
-        // Extract event name and convert PascalCase to snake_case for backwards compat
-        let event_name = payload
-            .get("event")
-            .and_then(|v| v.as_str())
-            .map(to_snake_case)
-            .unwrap_or_else(|| "unknown".to_string());
-
-        if event_name == "video_started" {
-            if let Value::Object(ref mut map) = payload {
-                if !map.contains_key("user_id") {
-                    map.insert(
-                        "user_id".to_string(),
-                        Value::String(request.user_id.clone()),
-                    );
+    check_bulk_size(request.events.len())?;
+
+    let mut events_payload = request.clone();
+    let events = request
+        .events
+        .into_iter()
+        .map(|mut payload| {
+            // Event name comes as PascalCase from older clients; snake_case it for backwards compat
+            let event_name = payload
+                .get("event")
+                .and_then(|v| v.as_str())
+                .map(to_snake_case)
+                .ok_or_else(|| "Missing \"event\" field".to_string())?;
+
+            if event_name == "video_started" {
+                if let Value::Object(ref mut map) = payload {
+                    if !map.contains_key("user_id") {
+                        map.insert(
+                            "user_id".to_string(),
+                            Value::String(request.user_id.clone()),
+                        );
+                    }
                 }
             }
-        }
 
-        // Remove "event" field from params (old AnalyticsEventV3.params() didn't include it)
-        if let Value::Object(ref mut map) = payload {
-            map.remove("event");
-        }
-
-        let event = Event::new(WarehouseEvent {
-            event: event_name,
-            params: payload.to_string(),
-        });
+            // Remove "event" field from params (old AnalyticsEventV3.params() didn't include it)
+            if let Value::Object(ref mut map) = payload {
+                map.remove("event");
+            }
 
-        if let Err(e) = process_event_impl_v2(event, state.clone()).await {
-            log::error!("Failed to process event rest: {e}"); // not sending any error to the client as it is a bulk request
-        }
+            Ok(WarehouseEvent {
+                event: event_name,
+                params: payload.to_string(),
+            })
+        })
+        .collect();
+    // The middleware verified the delegated identity behind `user_id`
+    let sender = Principal::from_text(&request.user_id)
+        .ok()
+        .map(velocity::Sender::Principal);
+    let response = process_bulk(events, |event| {
+        process_event_impl_v2(event, state.clone(), sender.as_ref())
+    })
+    .await;
+
+    // Only accepted events go on to naitik, so retried failures aren't seen twice
+    events_payload.events = events_payload
+        .events
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| response.is_accepted(*index))
+        .map(|(_, event)| event)
+        .collect();
+    if !events_payload.events.is_empty() {
+        state
+            .naitik_multi_service_client
+            .send_bulk_events_v2_to_naitik_multi_services(events_payload);
     }
 
-    // After processing all events, we can send events to naitik multi services in bulk
-    state
-        .naitik_multi_service_client
-        .send_bulk_events_v2_to_naitik_multi_services(events_payload);
-
-    Ok((StatusCode::OK, "Events processed".to_string()))
+    Ok((response.status_code(), Json(response)))
 }
 
 #[utoipa::path(
@@ -361,14 +705,32 @@ async fn handle_bulk_events_v2(
     tag = "events",
     responses(
         (status = 200, description = "Event sent successfully"),
+        (status = 400, description = "Params don't match the delegated identity"),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal server error"),
+    ),
+    security(
+        (),
+        ("delegated_identity" = [])
     )
 )]
 async fn post_event_v2(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<EventRequest>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: axum::http::HeaderMap,
+    Json(mut payload): Json<EventRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let sender = match payload.delegated_identity_wire.take() {
+        Some(delegated_identity_wire) => {
+            let (params, principal) =
+                verify_event_identity(&state, delegated_identity_wire, &headers, &payload.params)
+                    .await?;
+            payload.params = params;
+            velocity::Sender::Principal(principal)
+        }
+        None => velocity::Sender::unverified(&headers, peer),
+    };
+
     // Convert event name to snake_case for backwards compat with mobile sending PascalCase
     let event_name = to_snake_case(&payload.event);
 
@@ -379,7 +741,7 @@ async fn post_event_v2(
 
     let event = Event::new(warehouse_event);
 
-    process_event_impl_v2(event, state.clone())
+    process_event_impl_v2(event, state.clone(), Some(&sender))
         .await
         .map_err(|e| {
             log::error!("Failed to process event rest: {e}");
@@ -399,7 +761,7 @@ async fn post_event_v2(
 
 pub fn events_router_v2(state: Arc<AppState>) -> OpenApiRouter {
     OpenApiRouter::new()
-        .routes(routes!(post_event_v2))
+        .routes(routes!(post_event_v2).layer(body_limit(SMALL_JSON_LIMIT)))
         .routes(
             routes!(handle_bulk_events_v2).layer(middleware::from_fn_with_state(
                 state.clone(),
@@ -408,3 +770,110 @@ pub fn events_router_v2(state: Arc<AppState>) -> OpenApiRouter {
         )
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bulk_status_code() {
+        let result = |status, error_code| BulkEventResult {
+            index: 0,
+            event: None,
+            status,
+            error_code,
+            error: None,
+        };
+        let accepted = || result(BulkEventStatus::Accepted, None);
+        let invalid = || {
+            result(
+                BulkEventStatus::Failed,
+                Some(BulkEventErrorCode::InvalidEvent),
+            )
+        };
+        let failed = || {
+            result(
+                BulkEventStatus::Failed,
+                Some(BulkEventErrorCode::ProcessingFailed),
+            )
+        };
+        let status =
+            |results: Vec<BulkEventResult>| BulkEventsResponse::from_results(results).status_code();
+
+        assert_eq!(status(vec![accepted(), accepted()]), StatusCode::OK);
+        assert_eq!(status(vec![accepted(), failed()]), StatusCode::MULTI_STATUS);
+        assert_eq!(
+            status(vec![invalid(), failed()]),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(status(vec![invalid()]), StatusCode::BAD_REQUEST);
+
+        let response = BulkEventsResponse::from_results(vec![accepted(), invalid(), failed()]);
+        assert_eq!((response.accepted, response.failed), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn test_process_bulk_keeps_user_order() {
+        let event = |name: &str, user: &str| {
+            Ok(WarehouseEvent {
+                event: name.to_string(),
+                params: serde_json::json!({ "user_id": user }).to_string(),
+            })
+        };
+        let events = vec![
+            event("video_started", "a"),
+            event("video_started", "b"),
+            event("video_duration_watched", "a"),
+            Err("Missing \"event\" field".to_string()),
+        ];
+
+        let seen = std::sync::Mutex::new(Vec::new());
+        let response = process_bulk(events, |event| {
+            let seen = &seen;
+            async move {
+                if event.event.event == "video_started" {
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                }
+                seen.lock()
+                    .unwrap()
+                    .push((event.event.event.clone(), event.event.params.clone()));
+                if event.event.params.contains("\"b\"") {
+                    anyhow::bail!("internal detail");
+                }
+                Ok(())
+            }
+        })
+        .await;
+
+        let seen = seen.into_inner().unwrap();
+        let a_events: Vec<&str> = seen
+            .iter()
+            .filter(|(_, params)| params.contains("\"a\""))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(a_events, vec!["video_started", "video_duration_watched"]);
+        let indices: Vec<u32> = response.results.iter().map(|result| result.index).collect();
+        assert_eq!(indices, vec![0, 1, 2, 3]);
+        assert_eq!(
+            response.results[1].error.as_deref(),
+            Some(PROCESSING_FAILED_MESSAGE)
+        );
+        assert!(response.is_accepted(0) && response.is_accepted(2));
+        assert!(!response.is_accepted(1) && !response.is_accepted(3));
+    }
+
+    #[test]
+    fn test_stamp_user_id() {
+        let user = Principal::from_text("2vxsx-fae").unwrap();
+
+        let stamped: Value =
+            serde_json::from_str(&stamp_user_id(r#"{"video_id":"v1"}"#, user).unwrap()).unwrap();
+        assert_eq!(stamped["user_id"], "2vxsx-fae");
+        assert_eq!(stamped["video_id"], "v1");
+
+        assert!(stamp_user_id(r#"{"user_id":"2vxsx-fae"}"#, user).is_ok());
+        let (status, _) = stamp_user_id(r#"{"user_id":"aaaaa-aa"}"#, user).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(stamp_user_id("[]", user).is_err());
+    }
+}
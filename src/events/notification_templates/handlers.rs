@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{builtin_templates, override_field, NotificationTemplate, TemplateRegistry};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    devices::validate_locale,
+    error::{ApiError, ApiErrorBody},
+};
+
+pub fn notification_templates_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(list_templates))
+        .routes(routes!(put_template, delete_template))
+        .with_state(state)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateSource {
+    Builtin,
+    Override,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TemplateEntry {
+    pub template_id: String,
+    pub locale: String,
+    pub source: TemplateSource,
+    #[serde(flatten)]
+    pub template: NotificationTemplate,
+}
+
+/// Every built-in template and stored override
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "notification-templates",
+    responses(
+        (status = 200, description = "Templates", body = Vec<TemplateEntry>),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn list_templates(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<TemplateEntry>>, ApiError> {
+    require_operator(&headers)?;
+    let overrides = TemplateRegistry::load_overrides(&state.yral_redis_store_dragonfly).await?;
+
+    let mut entries: Vec<TemplateEntry> = builtin_templates()
+        .iter()
+        .flat_map(|(template_id, locales)| {
+            locales.iter().map(|(locale, template)| TemplateEntry {
+                template_id: template_id.clone(),
+                locale: locale.clone(),
+                source: TemplateSource::Builtin,
+                template: template.clone(),
+            })
+        })
+        .collect();
+    entries.extend(overrides.into_iter().filter_map(|(field, template)| {
+        let (template_id, locale) = field.split_once(':')?;
+        Some(TemplateEntry {
+            template_id: template_id.to_string(),
+            locale: locale.to_string(),
+            source: TemplateSource::Override,
+            template,
+        })
+    }));
+    entries.sort_by(|a, b| {
+        (&a.template_id, &a.locale, a.source).cmp(&(&b.template_id, &b.locale, b.source))
+    });
+    Ok(Json(entries))
+}
+
+/// Checks an override against the built-in copy it replaces or translates
+fn validate_override(
+    template_id: &str,
+    locale: &str,
+    template: &NotificationTemplate,
+) -> Result<(), String> {
+    validate_locale(locale)?;
+    let reference = builtin_templates()
+        .get(template_id)
+        .and_then(|locales| locales.get(super::DEFAULT_LOCALE))
+        .ok_or_else(|| format!("Unknown template {template_id}"))?;
+    if template.title.trim().is_empty() {
+        return Err("title must not be empty".to_string());
+    }
+
+    let allowed = reference.placeholders();
+    let unknown: Vec<String> = template
+        .placeholders()
+        .into_iter()
+        .filter(|name| !allowed.contains(name))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(format!(
+            "Unknown placeholders {unknown:?}; {template_id} has {allowed:?}"
+        ));
+    }
+    Ok(())
+}
+
+/// Override a template's copy for a locale, or add a language
+#[utoipa::path(
+    put,
+    path = "/{template_id}/{locale}",
+    params(
+        ("template_id" = String, Path, description = "Template ID"),
+        ("locale" = String, Path, description = "BCP 47 language tag, e.g. `hi` or `pt-BR`")
+    ),
+    request_body = NotificationTemplate,
+    tag = "notification-templates",
+    responses(
+        (status = 200, description = "Override stored", body = NotificationTemplate),
+        (status = 400, description = "Unknown template, invalid locale or placeholders", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers, template))]
+pub async fn put_template(
+    State(state): State<Arc<AppState>>,
+    Path((template_id, locale)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(template): Json<NotificationTemplate>,
+) -> Result<Json<NotificationTemplate>, ApiError> {
+    require_operator(&headers)?;
+    validate_override(&template_id, &locale, &template).map_err(ApiError::InvalidRequest)?;
+
+    TemplateRegistry::store_override(
+        &state.yral_redis_store_dragonfly,
+        &template_id,
+        &locale,
+        &template,
+    )
+    .await?;
+    state.notification_client.templates().invalidate().await;
+    log::info!(
+        "Stored notification template override {}",
+        override_field(&template_id, &locale)
+    );
+    Ok(Json(template))
+}
+
+/// Remove an override, falling back to the built-in copy
+#[utoipa::path(
+    delete,
+    path = "/{template_id}/{locale}",
+    params(
+        ("template_id" = String, Path, description = "Template ID"),
+        ("locale" = String, Path, description = "BCP 47 language tag")
+    ),
+    tag = "notification-templates",
+    responses(
+        (status = 204, description = "Override removed"),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "No override for this template and locale", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn delete_template(
+    State(state): State<Arc<AppState>>,
+    Path((template_id, locale)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<axum::http::StatusCode, ApiError> {
+    require_operator(&headers)?;
+    if !TemplateRegistry::delete_override(&state.yral_redis_store_dragonfly, &template_id, &locale)
+        .await?
+    {
+        return Err(ApiError::NotFound(format!(
+            "No override {}",
+            override_field(&template_id, &locale)
+        )));
+    }
+    state.notification_client.templates().invalidate().await;
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
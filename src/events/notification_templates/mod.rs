@@ -0,0 +1,291 @@
+//! Notification copy, per template and locale.
+//!
+//! Built-in copy ships in `templates.json` (template id -> locale -> title
+//! and body). Overrides and new languages are stored in Redis at
+//! `offchain:notification_templates`, field `{template_id}:{locale}`, and
+//! managed through `/api/v1/notification-templates`, so copy changes need no
+//! deploy. Instances pick overrides up within [`OVERRIDES_TTL`].
+//!
+//! A locale like `pt-BR` falls back to `pt`, then to [`DEFAULT_LOCALE`].
+//! `{name}` placeholders are filled from the [`NotificationCopy`] variables.
+
+#[cfg(not(feature = "local-bin"))]
+pub mod handlers;
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use yral_metadata_types::SendNotificationReq;
+
+use crate::yral_auth::dragonfly::DragonflyPool;
+
+#[cfg(not(feature = "local-bin"))]
+pub use handlers::notification_templates_router;
+
+pub const DEFAULT_LOCALE: &str = "en";
+const OVERRIDES_KEY: &str = "offchain:notification_templates";
+const OVERRIDES_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct NotificationTemplate {
+    pub title: String,
+    pub body: String,
+}
+
+impl NotificationTemplate {
+    /// Placeholder names used in the title and body
+    pub fn placeholders(&self) -> BTreeSet<String> {
+        [&self.title, &self.body]
+            .into_iter()
+            .flat_map(|text| {
+                text.split('{')
+                    .skip(1)
+                    .filter_map(|part| part.split_once('}').map(|(name, _)| name.to_string()))
+            })
+            .collect()
+    }
+}
+
+type Templates = HashMap<String, HashMap<String, NotificationTemplate>>;
+
+static BUILTIN: Lazy<Templates> = Lazy::new(|| {
+    serde_json::from_str(include_str!("templates.json"))
+        .expect("notification_templates/templates.json is invalid")
+});
+
+pub fn builtin_templates() -> &'static Templates {
+    &BUILTIN
+}
+
+/// A template id plus the variables to fill it with
+#[derive(Debug, Clone)]
+pub struct NotificationCopy {
+    template_id: &'static str,
+    vars: Vec<(&'static str, String)>,
+}
+
+impl NotificationCopy {
+    pub fn new(template_id: &'static str) -> Self {
+        Self {
+            template_id,
+            vars: Vec::new(),
+        }
+    }
+
+    pub fn var(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.vars.push((name, value.to_string()));
+        self
+    }
+
+    fn fill(&self, text: &str) -> String {
+        self.vars
+            .iter()
+            .fold(text.to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), value)
+            })
+    }
+}
+
+/// `pt-BR` -> `["pt-br", "pt", "en"]`
+fn locale_chain(locale: Option<&str>) -> Vec<String> {
+    let mut chain = Vec::new();
+    if let Some(locale) = locale.map(str::to_ascii_lowercase) {
+        let mut parts: Vec<&str> = locale.split('-').collect();
+        while !parts.is_empty() {
+            chain.push(parts.join("-"));
+            parts.pop();
+        }
+    }
+    if !chain.iter().any(|locale| locale == DEFAULT_LOCALE) {
+        chain.push(DEFAULT_LOCALE.to_string());
+    }
+    chain
+}
+
+pub fn override_field(template_id: &str, locale: &str) -> String {
+    format!("{template_id}:{}", locale.to_ascii_lowercase())
+}
+
+#[derive(Clone, Default)]
+pub struct TemplateRegistry {
+    pool: Option<Arc<DragonflyPool>>,
+    overrides: Arc<RwLock<Option<(Instant, HashMap<String, NotificationTemplate>)>>>,
+}
+
+impl TemplateRegistry {
+    /// Built-in copy only
+    pub fn builtin() -> Self {
+        Self::default()
+    }
+
+    pub fn with_overrides(pool: Arc<DragonflyPool>) -> Self {
+        Self {
+            pool: Some(pool),
+            overrides: Default::default(),
+        }
+    }
+
+    pub async fn load_overrides(
+        pool: &DragonflyPool,
+    ) -> Result<HashMap<String, NotificationTemplate>> {
+        let mut conn = pool.get().await?;
+        let entries: HashMap<String, String> = conn.hgetall(OVERRIDES_KEY).await?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|(field, payload)| match serde_json::from_str(&payload) {
+                Ok(template) => Some((field, template)),
+                Err(e) => {
+                    log::warn!("Ignoring unreadable notification template {field}: {e}");
+                    None
+                }
+            })
+            .collect())
+    }
+
+    pub async fn store_override(
+        pool: &DragonflyPool,
+        template_id: &str,
+        locale: &str,
+        template: &NotificationTemplate,
+    ) -> Result<()> {
+        let mut conn = pool.get().await?;
+        let _: () = conn
+            .hset(
+                OVERRIDES_KEY,
+                override_field(template_id, locale),
+                serde_json::to_string(template)?,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns whether an override existed
+    pub async fn delete_override(
+        pool: &DragonflyPool,
+        template_id: &str,
+        locale: &str,
+    ) -> Result<bool> {
+        let mut conn = pool.get().await?;
+        let removed: u64 = conn
+            .hdel(OVERRIDES_KEY, override_field(template_id, locale))
+            .await?;
+        Ok(removed > 0)
+    }
+
+    /// Drops this instance's cached overrides
+    pub async fn invalidate(&self) {
+        *self.overrides.write().await = None;
+    }
+
+    /// Cached overrides; on a Redis error the stale copy, or none, is used
+    async fn overrides(&self) -> HashMap<String, NotificationTemplate> {
+        let Some(pool) = &self.pool else {
+            return HashMap::new();
+        };
+        if let Some((loaded_at, overrides)) = &*self.overrides.read().await {
+            if loaded_at.elapsed() < OVERRIDES_TTL {
+                return overrides.clone();
+            }
+        }
+
+        let mut cached = self.overrides.write().await;
+        match Self::load_overrides(pool).await {
+            Ok(overrides) => {
+                *cached = Some((Instant::now(), overrides.clone()));
+                overrides
+            }
+            Err(e) => {
+                log::warn!("Failed to load notification template overrides: {e}");
+                cached
+                    .as_ref()
+                    .map(|(_, overrides)| overrides.clone())
+                    .unwrap_or_default()
+            }
+        }
+    }
+
+    /// Title and body of `copy` in `locale`, or the closest fallback
+    pub async fn render(&self, copy: &NotificationCopy, locale: Option<&str>) -> (String, String) {
+        let overrides = self.overrides().await;
+        match resolve(&overrides, copy.template_id, locale) {
+            Some(template) => (copy.fill(&template.title), copy.fill(&template.body)),
+            None => {
+                log::error!("No notification template {}", copy.template_id);
+                (copy.template_id.to_string(), String::new())
+            }
+        }
+    }
+}
+
+fn resolve<'a>(
+    overrides: &'a HashMap<String, NotificationTemplate>,
+    template_id: &str,
+    locale: Option<&str>,
+) -> Option<&'a NotificationTemplate> {
+    let builtin = BUILTIN.get(template_id);
+    locale_chain(locale).into_iter().find_map(|locale| {
+        overrides
+            .get(&override_field(template_id, &locale))
+            .or_else(|| builtin.and_then(|locales| locales.get(&locale)))
+    })
+}
+
+/// Puts rendered copy everywhere a payload carries it
+pub fn apply_copy(data: &mut SendNotificationReq, title: &str, body: &str) {
+    if let Some(notification) = data.notification.as_mut() {
+        notification.title = Some(title.to_string());
+        notification.body = Some(body.to_string());
+    }
+    if let Some(alert) = data
+        .apns
+        .as_mut()
+        .and_then(|apns| apns.payload.as_mut())
+        .and_then(|payload| payload.pointer_mut("/aps/alert"))
+    {
+        alert["title"] = title.into();
+        alert["body"] = body.into();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_resolution() {
+        assert_eq!(locale_chain(Some("pt-BR")), vec!["pt-br", "pt", "en"]);
+        assert_eq!(locale_chain(Some("en")), vec!["en"]);
+        assert_eq!(locale_chain(None), vec!["en"]);
+
+        let copy = NotificationCopy::new("streak_milestone").var("days", 7);
+        let mut overrides = HashMap::new();
+        let template = resolve(&overrides, "streak_milestone", Some("hi-IN")).unwrap();
+        assert_eq!(copy.fill(&template.title), "7-day streak!");
+
+        overrides.insert(
+            override_field("streak_milestone", "hi"),
+            NotificationTemplate {
+                title: "{days} दिन की स्ट्रीक!".to_string(),
+                body: String::new(),
+            },
+        );
+        let template = resolve(&overrides, "streak_milestone", Some("hi-IN")).unwrap();
+        assert_eq!(copy.fill(&template.title), "7 दिन की स्ट्रीक!");
+        assert!(resolve(&overrides, "missing", None).is_none());
+
+        let template = &BUILTIN["tournament_ended_winner"]["en"];
+        assert_eq!(
+            template.placeholders().into_iter().collect::<Vec<_>>(),
+            vec!["prize_amount", "prize_token", "rank"]
+        );
+    }
+}
@@ -1,15 +1,11 @@
-use std::{
-    env, fs,
-    path::{Path, PathBuf},
-    process::Command,
-    sync::Arc,
-};
+use std::{env, sync::Arc};
 
 use crate::{
-    consts::{NSFW_SERVER_URL, NSFW_THRESHOLD},
+    consts::NSFW_SERVER_URL,
     events::event::UploadVideoInfoV2,
-    kvrocks::VideoNsfw,
+    kvrocks::{tables, VideoNsfw},
     pipeline::Step,
+    qstash::job::{NsfwDetectionJob, NsfwDetectionV2Job},
     scratchpad::{PendingNsfwV2Item, ScratchpadClient},
     setup_context,
 };
@@ -28,64 +24,15 @@ use tonic::{metadata::MetadataValue, Request};
 use tracing::instrument;
 
 use crate::{app_state::AppState, AppError};
+use frame_sampling::FrameSamplingConfig;
+
+pub mod ensemble;
+pub mod frame_sampling;
 
 pub mod nsfw_detector {
     tonic::include_proto!("nsfw_detector");
 }
 
-fn create_output_directory(video_id: &str) -> Result<PathBuf, Error> {
-    let video_name = Path::new(video_id)
-        .file_stem()
-        .ok_or(anyhow::anyhow!("Failed to get file stem"))?
-        .to_str()
-        .ok_or(anyhow::anyhow!("Failed to convert file stem to string"))?;
-    let output_dir = Path::new(".").join(video_name);
-
-    if !output_dir.exists() {
-        fs::create_dir(&output_dir)?;
-    }
-
-    Ok(output_dir)
-}
-
-#[instrument]
-pub async fn extract_frames(video_path: &str, output_dir: PathBuf) -> Result<Vec<Vec<u8>>, Error> {
-    let output_pattern = output_dir.join("output-%04d.jpg");
-    let video_path_clone = video_path.to_string();
-    let output_pattern_str = output_pattern.to_string_lossy().to_string();
-
-    let status = tokio::task::spawn_blocking(move || {
-        Command::new("ffmpeg")
-            .arg("-loglevel")
-            .arg("error")
-            .arg("-i")
-            .arg(&video_path_clone)
-            .arg("-vf")
-            .arg("fps=1")
-            .arg("-pix_fmt")
-            .arg("rgb24")
-            .arg(&output_pattern_str)
-            .status()
-    })
-    .await??;
-
-    if !status.success() {
-        return Err(anyhow::anyhow!("Failed to extract frames"));
-    }
-
-    let mut frames = Vec::new();
-    for entry in fs::read_dir(output_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_file() {
-            let frame = fs::read(&path)?;
-            frames.push(frame);
-        }
-    }
-
-    Ok(frames)
-}
-
 #[instrument(skip(gcs_client, frames))]
 pub async fn upload_frames_to_gcs(
     gcs_client: &cloud_storage::Client,
@@ -137,17 +84,31 @@ pub async fn extract_frames_and_upload(
     let video_id = payload.video_id;
     let publisher_user_id = &payload.video_info.publisher_user_id;
     let video_path = crate::consts::get_storj_video_url(publisher_user_id, &video_id, false);
-    let output_dir = create_output_directory(&video_id)?;
-    let frames = extract_frames(&video_path, output_dir.clone()).await?;
+    let (frames, samples) =
+        frame_sampling::sample_frames(&video_id, &video_path, &FrameSamplingConfig::from_env())
+            .await?;
     #[cfg(not(feature = "local-bin"))]
     upload_frames_to_gcs(&state.gcs_client, frames, &video_id).await?;
-    // delete output directory
-    fs::remove_dir_all(output_dir)?;
+    // Downstream detectors read the timestamps alongside the frames
+    if let Err(e) = state
+        .kvrocks_client
+        .put(&tables::VIDEO_FRAME_SAMPLES, &video_id, &samples)
+        .await
+    {
+        log::error!(
+            "Error pushing frame samples to kvrocks for {}: {}",
+            video_id,
+            e
+        );
+    }
 
     // enqueue qstash job to detect nsfw
     let qstash_client = state.qstash_client.clone();
     qstash_client
-        .publish_video_nsfw_detection(&video_id, &payload.video_info)
+        .publish_job(&NsfwDetectionJob {
+            video_id: &video_id,
+            video_info: &payload.video_info,
+        })
         .await?;
 
     Ok(Json(
@@ -240,7 +201,10 @@ pub async fn nsfw_job(
     // enqueue qstash job to detect nsfw v2
     let qstash_client = state.qstash_client.clone();
     qstash_client
-        .publish_video_nsfw_detection_v2(&video_id, video_info)
+        .publish_job(&NsfwDetectionV2Job {
+            video_id: &video_id,
+            video_info: &video_info,
+        })
         .await?;
 
     Ok(Json(serde_json::json!({ "message": "NSFW job completed" })))
@@ -351,6 +315,11 @@ pub async fn nsfw_job_v2(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<VideoRequest>,
 ) -> Result<Json<serde_json::Value>, AppError> {
+    use crate::{
+        kvrocks::UserUploadedContentApproval,
+        verdict_cache::{self, VerdictCachePolicy},
+    };
+    use ensemble::EnsembleDecision;
     use sentry_anyhow::capture_anyhow;
 
     setup_context!(&payload.video_id, Step::NsfwDetectionV2, {
@@ -358,13 +327,95 @@ pub async fn nsfw_job_v2(
     });
 
     let video_id = payload.video_id;
+    let ensemble_flag = &crate::feature_flags::NSFW_ENSEMBLE;
+    let pool = &state.rewards_module.dragonfly_pool;
+    let ensemble_config = match candid::Principal::from_text(&payload.video_info.publisher_user_id)
+    {
+        Ok(publisher) => ensemble_flag.get_for(pool, &publisher).await,
+        Err(_) => ensemble_flag.get(pool).await,
+    };
 
-    let nsfw_prob = get_video_nsfw_info_v2(video_id.clone())
+    // Byte-identical re-uploads share a checksum, so reuse the verdict scored
+    // for the first copy; near-duplicates are scored on their own
+    let verdict_cache = VerdictCachePolicy::from_env(verdict_cache::NSFW_V2);
+    let content_hash = match state
+        .kvrocks_client
+        .get(&tables::VIDEO_CONTENT_HASH, &video_id)
         .await
-        .inspect_err(|err| {
-            capture_anyhow(err);
-        })?;
-    let is_nsfw = nsfw_prob >= NSFW_THRESHOLD;
+    {
+        Ok(hash) => hash.map(|h| h.sha256),
+        Err(e) => {
+            log::warn!("Failed to read content hash for {}: {}", video_id, e);
+            None
+        }
+    };
+    let cached_prob = match &content_hash {
+        Some(hash) => verdict_cache
+            .lookup::<f32>(&state.kvrocks_client, hash)
+            .await
+            .map(|(prob, _)| prob),
+        None => None,
+    };
+
+    let (nsfw_prob, needs_review) = match cached_prob {
+        Some(prob) => {
+            log::info!("Reusing cached NSFW v2 probability for video {}", video_id);
+            (prob, false)
+        }
+        None => {
+            let verdict = ensemble::score_video(
+                &ensemble_config,
+                &video_id,
+                &payload.video_info.publisher_user_id,
+            )
+            .await
+            .inspect_err(|err| {
+                capture_anyhow(err);
+            })?;
+            if let Err(e) =
+                ensemble::push_model_scores_bigquery(&state.bigquery_client, &video_id, &verdict)
+                    .await
+            {
+                log::error!("Failed to push NSFW model scores for {}: {}", video_id, e);
+            }
+
+            let needs_review = verdict.decision == EnsembleDecision::ManualReview;
+            // Contested verdicts are scored afresh for every re-upload
+            if let Some(hash) = content_hash.as_ref().filter(|_| !needs_review) {
+                verdict_cache
+                    .store(
+                        &state.kvrocks_client,
+                        hash,
+                        &video_id,
+                        nsfw_v2_confidence(verdict.score, ensemble_config.threshold),
+                        &verdict.score,
+                    )
+                    .await;
+            }
+            (verdict.score, needs_review)
+        }
+    };
+    let is_nsfw = nsfw_prob >= ensemble_config.threshold;
+
+    if needs_review {
+        log::info!(
+            "NSFW models disagree on video {}, holding it for manual review",
+            video_id
+        );
+        // Written in full in case ingest hasn't created the row yet; ingest
+        // keeps the hold when it does
+        let row = UserUploadedContentApproval {
+            video_id: video_id.clone(),
+            post_id: payload.video_info.post_id.clone(),
+            canister_id: String::new(),
+            user_id: payload.video_info.publisher_user_id.clone(),
+            is_approved: false,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = state.kvrocks_client.hold_for_manual_review(row).await {
+            log::error!("Failed to hold video {} for manual review: {}", video_id, e);
+        }
+    }
 
     // push nsfw info to bigquery table and scratchpad
     let bigquery_client = state.bigquery_client.clone();
@@ -392,6 +443,16 @@ pub async fn nsfw_job_v2(
     ))
 }
 
+/// Confidence in the thresholded verdict, not in the positive class
+#[cfg(not(feature = "local-bin"))]
+fn nsfw_v2_confidence(nsfw_prob: f32, threshold: f32) -> f64 {
+    if nsfw_prob >= threshold {
+        nsfw_prob as f64
+    } else {
+        1.0 - nsfw_prob as f64
+    }
+}
+
 #[allow(clippy::result_large_err)]
 #[instrument]
 pub async fn get_video_nsfw_info_v2(video_id: String) -> Result<f32, Error> {
@@ -608,7 +669,10 @@ pub async fn push_nsfw_data_bigquery_v2(
             nsfw_gore,
             probability: Some(pending_item.nsfw_prob),
         };
-        if let Err(e) = kvrocks_client.store_video_nsfw(&nsfw_data).await {
+        if let Err(e) = kvrocks_client
+            .put(&tables::VIDEO_NSFW, &nsfw_data.video_id, &nsfw_data)
+            .await
+        {
             log::error!("Error pushing NSFW data to kvrocks for {}: {}", vid, e);
         }
     }
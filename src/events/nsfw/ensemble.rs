@@ -0,0 +1,409 @@
+//! Weighted ensemble of NSFW models for `nsfw_job_v2`.
+//!
+//! The embedding detector and, when it has a weight, a secondary provider
+//! each score the video. The weighted mean of the scores that came back is
+//! compared against the threshold. When the models disagree by more than the
+//! configured band the video is held for manual review instead of trusting
+//! the mean. Weights, threshold and band come from the `nsfw_ensemble`
+//! feature flag; every model's score is written to BigQuery for calibration.
+
+use anyhow::{anyhow, Context, Error};
+use google_cloud_bigquery::http::tabledata::insert_all::{InsertAllRequest, Row};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::{consts::NSFW_THRESHOLD, pipeline::telemetry};
+
+const MODEL_SCORES_TABLE: &str = "nsfw_model_scores";
+
+static SECONDARY_NSFW_API_URL: Lazy<Option<String>> =
+    Lazy::new(|| std::env::var("SECONDARY_NSFW_API_URL").ok());
+static SECONDARY_NSFW_API_TOKEN: Lazy<Option<String>> =
+    Lazy::new(|| std::env::var("SECONDARY_NSFW_API_TOKEN").ok());
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NsfwEnsembleConfig {
+    /// Weight of the embedding detector
+    pub embedding_weight: f32,
+    /// Weight of the secondary provider; 0 skips calling it
+    pub secondary_weight: f32,
+    /// Weighted score at or above which a video is NSFW
+    pub threshold: f32,
+    /// Scores further apart than this send the video to manual review,
+    /// keeping the thresholded flag; unset never does
+    pub disagreement_band: Option<f32>,
+}
+
+impl NsfwEnsembleConfig {
+    /// The embedding detector alone, as before the ensemble
+    pub const DEFAULT: Self = Self {
+        embedding_weight: 1.0,
+        secondary_weight: 0.0,
+        threshold: NSFW_THRESHOLD,
+        disagreement_band: None,
+    };
+
+    /// Rejects configs the ensemble can't score with: negative or
+    /// non-finite weights, no weight at all, or a threshold or band outside
+    /// the probability range
+    pub fn validate(&self) -> Result<(), String> {
+        let weights = [self.embedding_weight, self.secondary_weight];
+        if weights
+            .iter()
+            .any(|weight| !weight.is_finite() || *weight < 0.0)
+        {
+            return Err("weights must be finite and non-negative".to_string());
+        }
+        if self.embedding_weight + self.secondary_weight <= 0.0 {
+            return Err("at least one weight must be positive".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.threshold) {
+            return Err("threshold must be between 0 and 1".to_string());
+        }
+        if self
+            .disagreement_band
+            .is_some_and(|band| !(f32::EPSILON..=1.0).contains(&band))
+        {
+            return Err("disagreement_band must be in (0, 1]".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for NsfwEnsembleConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NsfwModel {
+    Embedding,
+    Secondary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnsembleDecision {
+    Safe,
+    Nsfw,
+    ManualReview,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModelScore {
+    pub model: NsfwModel,
+    pub weight: f32,
+    /// None when the model failed
+    pub probability: Option<f32>,
+    pub error: Option<String>,
+}
+
+impl ModelScore {
+    pub fn from_result(model: NsfwModel, weight: f32, result: Result<f32, Error>) -> Self {
+        match result {
+            Ok(probability) => Self {
+                model,
+                weight,
+                probability: Some(probability),
+                error: None,
+            },
+            Err(e) => Self {
+                model,
+                weight,
+                probability: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnsembleVerdict {
+    /// Weighted mean of the scores that came back
+    pub score: f32,
+    pub decision: EnsembleDecision,
+    pub scores: Vec<ModelScore>,
+}
+
+/// Combines model scores; fails when no weighted model returned a score
+pub fn combine(
+    config: &NsfwEnsembleConfig,
+    scores: Vec<ModelScore>,
+) -> Result<EnsembleVerdict, Error> {
+    let scored: Vec<(f32, f32)> = scores
+        .iter()
+        .filter(|score| score.weight > 0.0)
+        .filter_map(|score| {
+            score
+                .probability
+                .map(|probability| (probability, score.weight))
+        })
+        .collect();
+    let total_weight: f32 = scored.iter().map(|(_, weight)| weight).sum();
+    if total_weight <= 0.0 {
+        let errors: Vec<_> = scores
+            .iter()
+            .filter_map(|score| score.error.as_deref())
+            .collect();
+        return Err(anyhow!(
+            "No NSFW model returned a score: {}",
+            errors.join("; ")
+        ));
+    }
+
+    let score = scored
+        .iter()
+        .map(|(probability, weight)| probability * weight)
+        .sum::<f32>()
+        / total_weight;
+    let spread = scored
+        .iter()
+        .map(|(probability, _)| *probability)
+        .fold(None, |range: Option<(f32, f32)>, probability| {
+            Some(match range {
+                Some((min, max)) => (min.min(probability), max.max(probability)),
+                None => (probability, probability),
+            })
+        })
+        .map_or(0.0, |(min, max)| max - min);
+
+    let decision = if config.disagreement_band.is_some_and(|band| spread > band) {
+        EnsembleDecision::ManualReview
+    } else if score >= config.threshold {
+        EnsembleDecision::Nsfw
+    } else {
+        EnsembleDecision::Safe
+    };
+
+    Ok(EnsembleVerdict {
+        score,
+        decision,
+        scores,
+    })
+}
+
+/// Scores the video with every model that has a weight and combines them
+pub async fn score_video(
+    config: &NsfwEnsembleConfig,
+    video_id: &str,
+    publisher_user_id: &str,
+) -> Result<EnsembleVerdict, Error> {
+    let video_url = crate::consts::get_storj_video_url(publisher_user_id, video_id, false);
+    let secondary = async {
+        if config.secondary_weight > 0.0 {
+            Some(secondary_nsfw_probability(video_id, &video_url).await)
+        } else {
+            None
+        }
+    };
+    let (embedding, secondary) = tokio::join!(
+        super::get_video_nsfw_info_v2(video_id.to_string()),
+        secondary
+    );
+
+    let mut scores = vec![ModelScore::from_result(
+        NsfwModel::Embedding,
+        config.embedding_weight,
+        embedding,
+    )];
+    if let Some(secondary) = secondary {
+        scores.push(ModelScore::from_result(
+            NsfwModel::Secondary,
+            config.secondary_weight,
+            secondary,
+        ));
+    }
+    for score in &scores {
+        if let Some(error) = &score.error {
+            log::warn!(
+                "NSFW model {:?} failed for video {video_id}: {error}",
+                score.model
+            );
+        }
+    }
+
+    combine(config, scores)
+}
+
+#[derive(Serialize)]
+struct SecondaryRequest<'a> {
+    video_id: &'a str,
+    video_url: &'a str,
+}
+
+#[derive(Deserialize)]
+struct SecondaryResponse {
+    probability: f32,
+}
+
+/// NSFW probability from the secondary provider
+pub async fn secondary_nsfw_probability(video_id: &str, video_url: &str) -> Result<f32, Error> {
+    let url = SECONDARY_NSFW_API_URL
+        .as_deref()
+        .context("SECONDARY_NSFW_API_URL is not set")?;
+
+    telemetry::count_provider_call();
+    let mut request = reqwest::Client::new().post(url).json(&SecondaryRequest {
+        video_id,
+        video_url,
+    });
+    if let Some(token) = SECONDARY_NSFW_API_TOKEN.as_deref() {
+        request = request.bearer_auth(token);
+    }
+    let response: SecondaryResponse = request
+        .send()
+        .await?
+        .error_for_status()
+        .context("Secondary NSFW provider rejected the request")?
+        .json()
+        .await?;
+
+    Ok(response.probability)
+}
+
+#[derive(Serialize)]
+struct ModelScoreRow {
+    video_id: String,
+    model: NsfwModel,
+    weight: f32,
+    probability: Option<f32>,
+    error: Option<String>,
+    ensemble_score: f32,
+    decision: EnsembleDecision,
+    scored_at: String,
+}
+
+/// Writes one row per model to `yral_ds.nsfw_model_scores`
+pub async fn push_model_scores_bigquery(
+    bigquery_client: &google_cloud_bigquery::client::Client,
+    video_id: &str,
+    verdict: &EnsembleVerdict,
+) -> Result<(), Error> {
+    let scored_at = chrono::Utc::now().to_rfc3339();
+    let rows = verdict
+        .scores
+        .iter()
+        .map(|score| Row {
+            insert_id: None,
+            json: ModelScoreRow {
+                video_id: video_id.to_string(),
+                model: score.model,
+                weight: score.weight,
+                probability: score.probability,
+                error: score.error.clone(),
+                ensemble_score: verdict.score,
+                decision: verdict.decision,
+                scored_at: scored_at.clone(),
+            },
+        })
+        .collect();
+
+    let request = InsertAllRequest {
+        rows,
+        ..Default::default()
+    };
+    let result = bigquery_client
+        .tabledata()
+        .insert(
+            "hot-or-not-feed-intelligence",
+            "yral_ds",
+            MODEL_SCORES_TABLE,
+            &request,
+        )
+        .await?;
+    if let Some(errors) = result.insert_errors.filter(|errors| !errors.is_empty()) {
+        return Err(anyhow!("BigQuery insert errors: {:?}", errors));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(model: NsfwModel, weight: f32, probability: Option<f32>) -> ModelScore {
+        ModelScore {
+            model,
+            weight,
+            probability,
+            error: probability.is_none().then(|| "timeout".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_combine() {
+        let config = NsfwEnsembleConfig {
+            embedding_weight: 3.0,
+            secondary_weight: 1.0,
+            threshold: 0.5,
+            disagreement_band: Some(0.5),
+        };
+
+        let verdict = combine(
+            &config,
+            vec![
+                score(NsfwModel::Embedding, 3.0, Some(0.6)),
+                score(NsfwModel::Secondary, 1.0, Some(0.2)),
+            ],
+        )
+        .unwrap();
+        assert!((verdict.score - 0.5).abs() < 1e-6);
+        assert_eq!(verdict.decision, EnsembleDecision::Nsfw);
+
+        let verdict = combine(
+            &config,
+            vec![
+                score(NsfwModel::Embedding, 3.0, Some(0.1)),
+                score(NsfwModel::Secondary, 1.0, Some(0.9)),
+            ],
+        )
+        .unwrap();
+        assert_eq!(verdict.decision, EnsembleDecision::ManualReview);
+
+        // A failed model is left out of the mean
+        let verdict = combine(
+            &config,
+            vec![
+                score(NsfwModel::Embedding, 3.0, Some(0.1)),
+                score(NsfwModel::Secondary, 1.0, None),
+            ],
+        )
+        .unwrap();
+        assert_eq!(verdict.decision, EnsembleDecision::Safe);
+        assert_eq!(verdict.scores.len(), 2);
+
+        assert!(combine(&config, vec![score(NsfwModel::Embedding, 3.0, None)]).is_err());
+        assert_eq!(
+            serde_json::from_value::<NsfwEnsembleConfig>(serde_json::json!({})).unwrap(),
+            NsfwEnsembleConfig::DEFAULT
+        );
+
+        assert!(config.validate().is_ok());
+        assert!(NsfwEnsembleConfig::DEFAULT.validate().is_ok());
+        for invalid in [
+            NsfwEnsembleConfig {
+                secondary_weight: -1.0,
+                ..config
+            },
+            NsfwEnsembleConfig {
+                embedding_weight: 0.0,
+                secondary_weight: 0.0,
+                ..config
+            },
+            NsfwEnsembleConfig {
+                threshold: 1.5,
+                ..config
+            },
+            NsfwEnsembleConfig {
+                disagreement_band: Some(0.0),
+                ..config
+            },
+        ] {
+            assert!(invalid.validate().is_err(), "{invalid:?}");
+        }
+    }
+}
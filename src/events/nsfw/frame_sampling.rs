@@ -0,0 +1,338 @@
+//! Adaptive frame sampling for the NSFW frame pipeline.
+//!
+//! A remote video is copied to a temp file once, without re-encoding, so
+//! the scene pass and every frame seek read local disk. One ffmpeg pass with the `scene` filter finds the video's duration and
+//! its scene cuts. Frames are then taken at every cut plus one every
+//! `FRAME_SAMPLING_STATIC_INTERVAL_SECS` of footage without a cut, so
+//! fast-cut videos get more frames and static ones fewer than the old one
+//! frame per second. The count is kept between `FRAME_SAMPLING_MIN_FRAMES`
+//! and `FRAME_SAMPLING_MAX_FRAMES`. The chosen timestamps are stored in
+//! kvrocks so downstream detectors know where each frame came from.
+
+use std::{path::Path, process::Command};
+
+use anyhow::{anyhow, Error};
+use uuid::Uuid;
+
+use crate::{
+    kvrocks::{FrameSample, VideoFrameSamples},
+    utils::env_parse,
+};
+
+#[derive(Debug, Clone)]
+pub struct FrameSamplingConfig {
+    /// Scene score (0-1) above which a frame starts a new scene
+    pub scene_threshold: f64,
+    /// One frame per this many seconds of footage without a cut
+    pub static_interval_secs: f64,
+    pub min_frames: usize,
+    pub max_frames: usize,
+}
+
+impl FrameSamplingConfig {
+    pub fn from_env() -> Self {
+        let min_frames = env_parse("FRAME_SAMPLING_MIN_FRAMES", 3usize).max(1);
+        Self {
+            scene_threshold: env_parse("FRAME_SAMPLING_SCENE_THRESHOLD", 0.3),
+            static_interval_secs: env_parse("FRAME_SAMPLING_STATIC_INTERVAL_SECS", 4.0f64).max(0.5),
+            min_frames,
+            max_frames: env_parse("FRAME_SAMPLING_MAX_FRAMES", 24usize).max(min_frames),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SceneAnalysis {
+    /// 0 when ffmpeg didn't report it
+    pub duration_secs: f64,
+    /// Start of each new scene, ascending
+    pub scene_cuts: Vec<f64>,
+}
+
+/// Reads `Duration:` and the `showinfo` timestamps of selected frames from
+/// ffmpeg's log
+fn parse_scene_analysis(log: &str) -> SceneAnalysis {
+    let duration_secs = log
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Duration: "))
+        .and_then(|rest| rest.split(',').next())
+        .and_then(|hms| {
+            let mut parts = hms.split(':').map(|part| part.parse::<f64>().ok());
+            Some(parts.next()?? * 3600.0 + parts.next()?? * 60.0 + parts.next()??)
+        })
+        .unwrap_or_default();
+
+    let mut scene_cuts: Vec<f64> = log
+        .lines()
+        .filter(|line| line.contains("Parsed_showinfo"))
+        .filter_map(|line| line.split("pts_time:").nth(1))
+        .filter_map(|rest| rest.split_whitespace().next()?.parse::<f64>().ok())
+        .filter(|ts| ts.is_finite() && *ts >= 0.0)
+        .collect();
+    scene_cuts.sort_by(|a, b| a.total_cmp(b));
+    scene_cuts.dedup();
+
+    SceneAnalysis {
+        duration_secs,
+        scene_cuts,
+    }
+}
+
+async fn analyze_scenes(video_path: &str, scene_threshold: f64) -> Result<SceneAnalysis, Error> {
+    let video_path = video_path.to_string();
+    let output = tokio::task::spawn_blocking(move || {
+        Command::new("ffmpeg")
+            .arg("-hide_banner")
+            .arg("-loglevel")
+            .arg("info")
+            .arg("-i")
+            .arg(&video_path)
+            .arg("-an")
+            .arg("-vf")
+            .arg(format!("select='gt(scene,{scene_threshold})',showinfo"))
+            .arg("-f")
+            .arg("null")
+            .arg("-")
+            .output()
+    })
+    .await??;
+
+    let log = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(anyhow!("ffmpeg scene detection failed: {log}"));
+    }
+    Ok(parse_scene_analysis(&log))
+}
+
+/// Timestamps to sample: every scene cut plus one per static interval of
+/// footage away from a cut, evened out to the frame budget
+pub fn choose_timestamps(
+    analysis: &SceneAnalysis,
+    config: &FrameSamplingConfig,
+) -> Vec<(f64, bool)> {
+    let duration = analysis.duration_secs;
+    let interval = config.static_interval_secs;
+    let in_video = |ts: f64| duration <= 0.0 || ts < duration;
+
+    let mut samples: Vec<(f64, bool)> = analysis
+        .scene_cuts
+        .iter()
+        .copied()
+        .filter(|ts| in_video(*ts))
+        .map(|ts| (ts, true))
+        .collect();
+    let near_cut = |ts: f64| {
+        analysis
+            .scene_cuts
+            .iter()
+            .any(|cut| (cut - ts).abs() < interval / 2.0)
+    };
+    let mut ts = 0.0;
+    while in_video(ts) && (duration > 0.0 || ts == 0.0) {
+        if !near_cut(ts) {
+            samples.push((ts, false));
+        }
+        ts += interval;
+    }
+
+    // Too few: keep the cuts and spread the minimum evenly instead
+    if samples.len() < config.min_frames && duration > 0.0 {
+        let n = config.min_frames;
+        samples.retain(|(_, scene_cut)| *scene_cut);
+        let spread: Vec<_> = (0..n)
+            .map(|i| duration * (i as f64 + 0.5) / n as f64)
+            .filter(|ts| {
+                !samples
+                    .iter()
+                    .any(|(sampled, _)| (sampled - ts).abs() < 0.1)
+            })
+            .map(|ts| (ts, false))
+            .collect();
+        samples.extend(spread);
+    }
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    if samples.len() > config.max_frames {
+        let last = samples.len() - 1;
+        let keep = config.max_frames;
+        samples = (0..keep)
+            .map(|i| samples[if keep == 1 { 0 } else { i * last / (keep - 1) }])
+            .collect();
+    }
+    samples
+}
+
+/// One JPEG frame at `timestamp_secs`; `None` past the end of the video
+async fn extract_frame_at(video_path: &str, timestamp_secs: f64) -> Result<Option<Vec<u8>>, Error> {
+    let video_path = video_path.to_string();
+    let output = tokio::task::spawn_blocking(move || {
+        Command::new("ffmpeg")
+            .arg("-loglevel")
+            .arg("error")
+            .arg("-ss")
+            .arg(format!("{timestamp_secs:.3}"))
+            .arg("-i")
+            .arg(&video_path)
+            .arg("-frames:v")
+            .arg("1")
+            .arg("-f")
+            .arg("image2")
+            .arg("-c:v")
+            .arg("mjpeg")
+            .arg("pipe:1")
+            .output()
+    })
+    .await??;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffmpeg failed to extract frame at {timestamp_secs}s: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok((!output.stdout.is_empty()).then_some(output.stdout))
+}
+
+/// Copies the video at `url` to `local_path` without re-encoding
+async fn download_video(url: &str, local_path: &Path) -> Result<(), Error> {
+    let url = url.to_string();
+    let local_path = local_path.to_path_buf();
+    let output = tokio::task::spawn_blocking(move || {
+        Command::new("ffmpeg")
+            .arg("-y")
+            .arg("-loglevel")
+            .arg("error")
+            .arg("-i")
+            .arg(&url)
+            .arg("-c")
+            .arg("copy")
+            .arg(&local_path)
+            .output()
+    })
+    .await??;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "ffmpeg failed to download video: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+/// Samples frames from the video; frame `i` of the result is `frames[i]`
+/// of the returned metadata
+pub async fn sample_frames(
+    video_id: &str,
+    video_path: &str,
+    config: &FrameSamplingConfig,
+) -> Result<(Vec<Vec<u8>>, VideoFrameSamples), Error> {
+    if !video_path.starts_with("http://") && !video_path.starts_with("https://") {
+        return sample_local_frames(video_id, video_path, config).await;
+    }
+
+    let work_dir = std::env::temp_dir().join(format!("frame_sampling_{}", Uuid::new_v4()));
+    tokio::fs::create_dir_all(&work_dir).await?;
+    let local_path = work_dir.join("video.mp4");
+    let result = match download_video(video_path, &local_path).await {
+        Ok(()) => sample_local_frames(video_id, &local_path.to_string_lossy(), config).await,
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_dir_all(&work_dir).await;
+    result
+}
+
+async fn sample_local_frames(
+    video_id: &str,
+    video_path: &str,
+    config: &FrameSamplingConfig,
+) -> Result<(Vec<Vec<u8>>, VideoFrameSamples), Error> {
+    let analysis = analyze_scenes(video_path, config.scene_threshold).await?;
+
+    let mut frames = Vec::new();
+    let mut samples = Vec::new();
+    for (timestamp_secs, scene_cut) in choose_timestamps(&analysis, config) {
+        let Some(frame) = extract_frame_at(video_path, timestamp_secs).await? else {
+            continue;
+        };
+        samples.push(FrameSample {
+            index: frames.len(),
+            timestamp_secs,
+            scene_cut,
+        });
+        frames.push(frame);
+    }
+    if frames.is_empty() {
+        return Err(anyhow!("No frames extracted for {video_id}"));
+    }
+
+    log::info!(
+        "Sampled {} frames for {video_id} ({} scene cuts in {:.1}s)",
+        frames.len(),
+        analysis.scene_cuts.len(),
+        analysis.duration_secs
+    );
+    Ok((
+        frames,
+        VideoFrameSamples {
+            video_id: video_id.to_string(),
+            duration_secs: analysis.duration_secs,
+            scene_cut_count: analysis.scene_cuts.len(),
+            frames: samples,
+            sampled_at: chrono::Utc::now().timestamp(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> FrameSamplingConfig {
+        FrameSamplingConfig {
+            scene_threshold: 0.3,
+            static_interval_secs: 4.0,
+            min_frames: 3,
+            max_frames: 6,
+        }
+    }
+
+    #[test]
+    fn test_parse_and_choose() {
+        let log = "  Duration: 00:00:12.50, start: 0.000000, bitrate: 900 kb/s\n\
+            [Parsed_showinfo_1 @ 0x1] n:   0 pts:  98304 pts_time:6.4     duration:512\n\
+            [Parsed_showinfo_1 @ 0x1] n:   1 pts: 122880 pts_time:8      duration:512\n";
+        let analysis = parse_scene_analysis(log);
+        assert_eq!(analysis.duration_secs, 12.5);
+        assert_eq!(analysis.scene_cuts, vec![6.4, 8.0]);
+
+        // Uniform 0/4/8/12 minus the ones next to a cut, plus both cuts
+        let chosen: Vec<_> = choose_timestamps(&analysis, &config());
+        assert_eq!(
+            chosen,
+            vec![
+                (0.0, false),
+                (4.0, false),
+                (6.4, true),
+                (8.0, true),
+                (12.0, false)
+            ]
+        );
+
+        // A static clip gets the minimum, spread out
+        let still = SceneAnalysis {
+            duration_secs: 3.0,
+            scene_cuts: vec![],
+        };
+        assert_eq!(choose_timestamps(&still, &config()).len(), 3);
+
+        // A fast-cut clip is capped at the budget
+        let fast = SceneAnalysis {
+            duration_secs: 20.0,
+            scene_cuts: (1..40).map(|i| i as f64 * 0.5).collect(),
+        };
+        let chosen = choose_timestamps(&fast, &config());
+        assert_eq!(chosen.len(), 6);
+        assert!(chosen.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+}
@@ -1,22 +1,131 @@
+use std::collections::HashMap;
+
 use candid::Principal;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use yral_metadata_types::SendNotificationReq;
 
-use crate::{app_state::AppState, events::types::deserialize_event_payload};
+use crate::{
+    app_state::AppState,
+    devices::{
+        fcm::{FcmClient, SendOutcome},
+        Device, DeviceRegistry,
+    },
+    events::{
+        notification_templates::{apply_copy, NotificationCopy, TemplateRegistry},
+        types::deserialize_event_payload,
+    },
+};
 
 const METADATA_SERVER_URL: &str = "https://metadata.yral.com";
 
+// FCM multicast accepts at most 500 registration tokens per send
+const MULTICAST_BATCH_SIZE: usize = 500;
+
+// Concurrency for per-recipient fallback when multicast isn't available
+const FALLBACK_SEND_CONCURRENCY: usize = 50;
+
+// Concurrency for per-device sends to a single user
+const DEVICE_SEND_CONCURRENCY: usize = 10;
+
+/// Delivery outcome for a single recipient of a batched send
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationDeliveryStatus {
+    pub user_id: Principal,
+    pub delivered: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct MulticastNotificationReq<'a> {
+    user_ids: Vec<String>,
+    data: &'a SendNotificationReq,
+}
+
+#[derive(Deserialize)]
+struct MulticastNotificationRes {
+    results: Vec<MulticastRecipientResult>,
+}
+
+#[derive(Deserialize)]
+struct MulticastRecipientResult {
+    user_id: String,
+    success: bool,
+    error: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct NotificationClient {
     api_key: String,
+    /// Per-device delivery; users without registered devices, or every user
+    /// when unset, are sent to through the metadata server
+    devices: Option<(DeviceRegistry, FcmClient)>,
+    templates: TemplateRegistry,
 }
 
 impl NotificationClient {
     pub fn new(api_key: String) -> Self {
-        Self { api_key }
+        Self {
+            api_key,
+            devices: None,
+            templates: TemplateRegistry::builtin(),
+        }
+    }
+
+    pub fn with_templates(mut self, templates: TemplateRegistry) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    pub fn templates(&self) -> &TemplateRegistry {
+        &self.templates
+    }
+
+    pub fn with_devices(mut self, registry: DeviceRegistry, fcm: FcmClient) -> Self {
+        self.devices = Some((registry, fcm));
+        self
     }
 
     pub async fn send_notification(&self, data: SendNotificationReq, user_id: Principal) {
+        self.deliver(data, None, user_id).await;
+    }
+
+    /// Sends `data` with its title and body rendered from `copy` in each
+    /// device's locale
+    pub async fn send_localized(
+        &self,
+        copy: &NotificationCopy,
+        data: SendNotificationReq,
+        user_id: Principal,
+    ) {
+        self.deliver(data, Some(copy), user_id).await;
+    }
+
+    async fn deliver(
+        &self,
+        mut data: SendNotificationReq,
+        copy: Option<&NotificationCopy>,
+        user_id: Principal,
+    ) {
+        if let Some((registry, fcm)) = &self.devices {
+            match registry.active_devices(&user_id).await {
+                Ok(devices) if !devices.is_empty() => {
+                    let copy = copy.map(|copy| (&self.templates, copy));
+                    send_to_devices(registry, fcm, copy, &data, user_id, devices).await;
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to load devices for {user_id}, sending by user: {e}"),
+            }
+        }
+
+        // The metadata server doesn't know the user's locale
+        if let Some(copy) = copy {
+            let (title, body) = self.templates.render(copy, None).await;
+            apply_copy(&mut data, &title, &body);
+        }
+
         let client = reqwest::Client::new();
         let url = format!(
             "{}/notifications/{}/send",
@@ -35,6 +144,293 @@ impl NotificationClient {
             log::error!("Error sending notification: {e:?}");
         }
     }
+
+    async fn send_notification_with_status(
+        &self,
+        data: &SendNotificationReq,
+        user_id: Principal,
+    ) -> NotificationDeliveryStatus {
+        let client = reqwest::Client::new();
+        let url = format!(
+            "{}/notifications/{}/send",
+            METADATA_SERVER_URL,
+            user_id.to_text()
+        );
+
+        let error = match client
+            .post(&url)
+            .bearer_auth(&self.api_key)
+            .json(data)
+            .send()
+            .await
+        {
+            Ok(res) if res.status().is_success() => None,
+            Ok(res) => Some(format!("Provider returned {}", res.status())),
+            Err(e) => Some(e.to_string()),
+        };
+
+        NotificationDeliveryStatus {
+            user_id,
+            delivered: error.is_none(),
+            error,
+        }
+    }
+
+    /// Send the same notification to many users using provider-side multicast.
+    /// Users with registered devices are sent to per device, as in
+    /// [`Self::send_notification`]; the rest go through the metadata server,
+    /// falling back to per-recipient sends if the multicast endpoint is
+    /// unavailable.
+    pub async fn send_multicast(
+        &self,
+        data: &SendNotificationReq,
+        user_ids: &[Principal],
+    ) -> Vec<NotificationDeliveryStatus> {
+        let mut statuses = Vec::with_capacity(user_ids.len());
+        let mut by_user = Vec::with_capacity(user_ids.len());
+
+        match &self.devices {
+            Some((registry, fcm)) => {
+                let lookups: Vec<(Principal, Vec<Device>)> = stream::iter(user_ids.iter().copied())
+                    .map(|user_id| async move {
+                        let devices = registry.active_devices(&user_id).await.unwrap_or_else(|e| {
+                            log::warn!(
+                                "Failed to load devices for {user_id}, sending by user: {e}"
+                            );
+                            Vec::new()
+                        });
+                        (user_id, devices)
+                    })
+                    .buffer_unordered(FALLBACK_SEND_CONCURRENCY)
+                    .collect()
+                    .await;
+
+                let mut with_devices = Vec::new();
+                for (user_id, devices) in lookups {
+                    if devices.is_empty() {
+                        by_user.push(user_id);
+                    } else {
+                        with_devices.push((user_id, devices));
+                    }
+                }
+
+                let device_statuses: Vec<NotificationDeliveryStatus> = stream::iter(with_devices)
+                    .map(|(user_id, devices)| {
+                        send_to_devices(registry, fcm, None, data, user_id, devices)
+                    })
+                    .buffer_unordered(FALLBACK_SEND_CONCURRENCY)
+                    .collect()
+                    .await;
+                statuses.extend(device_statuses);
+            }
+            None => by_user.extend_from_slice(user_ids),
+        }
+
+        statuses.extend(self.send_batch(data, &by_user).await);
+
+        let delivered = statuses.iter().filter(|s| s.delivered).count();
+        log::info!(
+            "Multicast notification delivered to {}/{} recipients",
+            delivered,
+            statuses.len()
+        );
+
+        statuses
+    }
+
+    /// Sends through the metadata server's `send_batch` endpoint in chunks
+    async fn send_batch(
+        &self,
+        data: &SendNotificationReq,
+        user_ids: &[Principal],
+    ) -> Vec<NotificationDeliveryStatus> {
+        let client = reqwest::Client::new();
+        let url = format!("{}/notifications/send_batch", METADATA_SERVER_URL);
+
+        let mut statuses = Vec::with_capacity(user_ids.len());
+
+        for chunk in user_ids.chunks(MULTICAST_BATCH_SIZE) {
+            let req = MulticastNotificationReq {
+                user_ids: chunk.iter().map(|p| p.to_text()).collect(),
+                data,
+            };
+
+            let res = client
+                .post(&url)
+                .bearer_auth(&self.api_key)
+                .json(&req)
+                .send()
+                .await;
+
+            let chunk_statuses = match res {
+                Ok(res) if res.status().is_success() => {
+                    match res.json::<MulticastNotificationRes>().await {
+                        Ok(body) => fan_in_multicast_results(chunk, body.results),
+                        Err(e) => {
+                            log::error!("Failed to parse multicast response: {e:?}");
+                            failed_statuses(chunk, &e.to_string())
+                        }
+                    }
+                }
+                Ok(res) if res.status() == reqwest::StatusCode::NOT_FOUND => {
+                    log::warn!("Multicast endpoint unavailable, sending individually");
+                    stream::iter(chunk.iter().copied())
+                        .map(|user_id| self.send_notification_with_status(data, user_id))
+                        .buffer_unordered(FALLBACK_SEND_CONCURRENCY)
+                        .collect()
+                        .await
+                }
+                Ok(res) => {
+                    log::error!("Multicast send failed with status {}", res.status());
+                    failed_statuses(chunk, &format!("Provider returned {}", res.status()))
+                }
+                Err(e) => {
+                    log::error!("Error sending multicast notification: {e:?}");
+                    failed_statuses(chunk, &e.to_string())
+                }
+            };
+
+            statuses.extend(chunk_statuses);
+        }
+
+        statuses
+    }
+}
+
+/// Fans `data` out to each device, localized when there is copy to render,
+/// unregistering tokens FCM reports stale. The user counts as delivered when
+/// any device accepted the notification.
+async fn send_to_devices(
+    registry: &DeviceRegistry,
+    fcm: &FcmClient,
+    copy: Option<(&TemplateRegistry, &NotificationCopy)>,
+    data: &SendNotificationReq,
+    user_id: Principal,
+    devices: Vec<Device>,
+) -> NotificationDeliveryStatus {
+    let outcomes: Vec<(Device, SendOutcome)> = stream::iter(devices)
+        .map(|device| async move {
+            let outcome = match copy {
+                Some((templates, copy)) => {
+                    let (title, body) = templates.render(copy, device.locale.as_deref()).await;
+                    let mut data = data.clone();
+                    apply_copy(&mut data, &title, &body);
+                    fcm.send(&device.token, &data).await
+                }
+                None => fcm.send(&device.token, data).await,
+            };
+            (device, outcome)
+        })
+        .buffer_unordered(DEVICE_SEND_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut delivered = false;
+    let mut error = None;
+    for (device, outcome) in outcomes {
+        match outcome {
+            SendOutcome::Delivered => delivered = true,
+            SendOutcome::Stale => {
+                error.get_or_insert_with(|| "Device token is stale".to_string());
+                log::info!(
+                    "Unregistering stale {:?} device of {user_id}",
+                    device.platform
+                );
+                if let Err(e) = registry.unregister(&user_id, &device.token).await {
+                    log::warn!("Failed to unregister stale device of {user_id}: {e}");
+                }
+            }
+            SendOutcome::Failed(e) => {
+                log::error!(
+                    "Error sending notification to {:?} device of {user_id}: {e}",
+                    device.platform
+                );
+                error.get_or_insert(e);
+            }
+        }
+    }
+
+    NotificationDeliveryStatus {
+        user_id,
+        delivered,
+        error: if delivered { None } else { error },
+    }
+}
+
+fn failed_statuses(user_ids: &[Principal], error: &str) -> Vec<NotificationDeliveryStatus> {
+    user_ids
+        .iter()
+        .map(|user_id| NotificationDeliveryStatus {
+            user_id: *user_id,
+            delivered: false,
+            error: Some(error.to_string()),
+        })
+        .collect()
+}
+
+/// Map provider results back onto the requested recipients; anyone missing is undelivered
+fn fan_in_multicast_results(
+    user_ids: &[Principal],
+    results: Vec<MulticastRecipientResult>,
+) -> Vec<NotificationDeliveryStatus> {
+    let mut by_user: HashMap<String, MulticastRecipientResult> = results
+        .into_iter()
+        .map(|result| (result.user_id.clone(), result))
+        .collect();
+
+    user_ids
+        .iter()
+        .map(|user_id| match by_user.remove(&user_id.to_text()) {
+            Some(result) => NotificationDeliveryStatus {
+                user_id: *user_id,
+                delivered: result.success,
+                error: result.error,
+            },
+            None => NotificationDeliveryStatus {
+                user_id: *user_id,
+                delivered: false,
+                error: Some("No delivery result from provider".to_string()),
+            },
+        })
+        .collect()
+}
+
+/// Collects notifications and groups identical payloads so each template is
+/// sent once per multicast batch instead of once per recipient.
+#[derive(Default)]
+pub struct NotificationBatch {
+    groups: HashMap<String, (SendNotificationReq, Vec<Principal>)>,
+}
+
+impl NotificationBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, data: SendNotificationReq, user_id: Principal) {
+        let key = serde_json::to_string(&data).unwrap_or_default();
+        self.groups
+            .entry(key)
+            .or_insert_with(|| (data, Vec::new()))
+            .1
+            .push(user_id);
+    }
+
+    pub fn num_templates(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn num_recipients(&self) -> usize {
+        self.groups.values().map(|(_, users)| users.len()).sum()
+    }
+
+    pub async fn dispatch(self, client: &NotificationClient) -> Vec<NotificationDeliveryStatus> {
+        let mut statuses = Vec::new();
+        for (data, user_ids) in self.groups.into_values() {
+            statuses.extend(client.send_multicast(&data, &user_ids).await);
+        }
+        statuses
+    }
 }
 
 const NOTIFICATION_EVENTS: &[&str] = &[
@@ -46,6 +442,8 @@ const NOTIFICATION_EVENTS: &[&str] = &[
     "tournament_ended_winner",
     "reward_earned",
     "follow_user",
+    "streak_milestone",
+    "video_hidden_pending_review",
 ];
 
 pub async fn dispatch_notif(
@@ -61,3 +459,46 @@ pub async fn dispatch_notif(
     event.send_notification(app_state).await;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yral_metadata_types::NotificationPayload;
+
+    fn notif(title: &str) -> SendNotificationReq {
+        SendNotificationReq {
+            notification: Some(NotificationPayload {
+                title: Some(title.to_string()),
+                body: None,
+                image: None,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_notification_batch_groups_by_template() {
+        let mut batch = NotificationBatch::new();
+        batch.add(notif("a"), Principal::from_slice(&[1]));
+        batch.add(notif("a"), Principal::from_slice(&[2]));
+        batch.add(notif("b"), Principal::from_slice(&[3]));
+
+        assert_eq!(batch.num_templates(), 2);
+        assert_eq!(batch.num_recipients(), 3);
+    }
+
+    #[test]
+    fn test_fan_in_marks_missing_recipients_undelivered() {
+        let users = vec![Principal::from_slice(&[1]), Principal::from_slice(&[2])];
+        let results = vec![MulticastRecipientResult {
+            user_id: users[0].to_text(),
+            success: true,
+            error: None,
+        }];
+
+        let statuses = fan_in_multicast_results(&users, results);
+        assert!(statuses[0].delivered);
+        assert!(!statuses[1].delivered);
+        assert!(statuses[1].error.is_some());
+    }
+}
@@ -0,0 +1,207 @@
+//! Live fan-out of processed warehouse events.
+//!
+//! Every event that reaches `process_event_impl` is handed to [`publish`],
+//! which queues it for the configured [`EventSink`] without waiting on the
+//! broker. A background task drains the queue; when it falls behind by more
+//! than `event_sink.buffer_size` events, new events are dropped and counted
+//! rather than slowing ingestion. Delivery is at most once per process:
+//! events still queued at shutdown or rejected by the broker are not retried.
+//!
+//! The sink is off unless `event_sink.enabled` is set for the deployment.
+//! Per-event-type counters are served at `/event-sink-metrics`.
+
+pub mod nats;
+
+use std::{collections::BTreeMap, sync::Mutex, time::Instant};
+
+use anyhow::Result;
+use axum::{http::HeaderMap, Json};
+use futures::future::BoxFuture;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
+
+use crate::{
+    auth::require_operator,
+    config::{EventSinkBackend, EventSinkSection},
+    error::ApiError,
+};
+
+use super::warehouse_events::WarehouseEvent;
+
+static QUEUE: OnceCell<mpsc::Sender<SinkEvent>> = OnceCell::new();
+static SINK_NAME: OnceCell<&'static str> = OnceCell::new();
+static METRICS: Lazy<Mutex<BTreeMap<String, DeliveryStats>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// An event as published to the sink
+#[derive(Debug, Clone, Serialize)]
+pub struct SinkEvent {
+    pub event: String,
+    /// JSON-encoded params, as received
+    pub params: String,
+    /// Microseconds since the epoch, when the event was processed
+    pub timestamp: i64,
+}
+
+impl SinkEvent {
+    pub fn from_warehouse_event(event: &WarehouseEvent) -> Self {
+        Self {
+            event: event.event.clone(),
+            params: event.params.clone(),
+            timestamp: chrono::Utc::now().timestamp_micros(),
+        }
+    }
+}
+
+/// A destination for processed events. Implementations publish one event
+/// and resolve once the broker has accepted it.
+pub trait EventSink: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn publish<'a>(&'a self, event: &'a SinkEvent) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Topic suffix for an event type. Characters brokers treat specially
+/// (separators, wildcards, whitespace) are replaced with `_`.
+pub fn topic_token(event: &str) -> String {
+    let token: String = event
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if token.is_empty() {
+        "_unknown".to_string()
+    } else {
+        token
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct DeliveryStats {
+    pub published: u64,
+    pub failed: u64,
+    /// Dropped because the queue was full
+    pub dropped: u64,
+    pub total_latency_ms: u64,
+    pub max_latency_ms: u64,
+}
+
+fn record(event: &str, f: impl FnOnce(&mut DeliveryStats)) {
+    let mut metrics = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+    f(metrics.entry(topic_token(event)).or_default());
+}
+
+/// Queues a processed event for the sink. Does nothing when the sink is
+/// disabled.
+pub fn publish(event: &WarehouseEvent) {
+    let Some(queue) = QUEUE.get() else {
+        return;
+    };
+    if let Err(e) = queue.try_send(SinkEvent::from_warehouse_event(event)) {
+        let event = match e {
+            mpsc::error::TrySendError::Full(event) | mpsc::error::TrySendError::Closed(event) => {
+                event
+            }
+        };
+        record(&event.event, |stats| stats.dropped += 1);
+    }
+}
+
+async fn run(sink: Box<dyn EventSink>, mut events: mpsc::Receiver<SinkEvent>) {
+    while let Some(event) = events.recv().await {
+        let started = Instant::now();
+        let result = sink.publish(&event).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        match result {
+            Ok(()) => record(&event.event, |stats| {
+                stats.published += 1;
+                stats.total_latency_ms += latency_ms;
+                stats.max_latency_ms = stats.max_latency_ms.max(latency_ms);
+            }),
+            Err(e) => {
+                log::warn!(
+                    "Failed to publish {} event to {}: {e:?}",
+                    event.event,
+                    sink.name()
+                );
+                record(&event.event, |stats| stats.failed += 1);
+            }
+        }
+    }
+}
+
+/// Connects the configured sink and starts publishing queued events
+pub async fn spawn_event_sink(config: &EventSinkSection) -> Result<()> {
+    if !config.enabled {
+        log::info!("Event sink disabled");
+        return Ok(());
+    }
+
+    let sink: Box<dyn EventSink> = match config.backend {
+        EventSinkBackend::Nats => Box::new(nats::NatsSink::connect(config).await?),
+    };
+    let (sender, receiver) = mpsc::channel(config.buffer_size);
+    if QUEUE.set(sender).is_err() {
+        log::warn!("Event sink already running");
+        return Ok(());
+    }
+    let _ = SINK_NAME.set(sink.name());
+
+    log::info!("Publishing events to {}", sink.name());
+    tokio::spawn(run(sink, receiver));
+    Ok(())
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EventSinkMetricsResponse {
+    /// `None` when the sink is disabled
+    pub sink: Option<String>,
+    /// Events waiting to be published
+    pub queued: usize,
+    /// Keyed by event type
+    pub events: BTreeMap<String, DeliveryStats>,
+}
+
+/// Per-event-type delivery counters since process start; operators only
+pub async fn event_sink_metrics_handler(
+    headers: HeaderMap,
+) -> Result<Json<EventSinkMetricsResponse>, ApiError> {
+    require_operator(&headers)?;
+    let events = METRICS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    Ok(Json(EventSinkMetricsResponse {
+        sink: SINK_NAME.get().map(|name| name.to_string()),
+        queued: QUEUE
+            .get()
+            .map(|queue| queue.max_capacity() - queue.capacity())
+            .unwrap_or_default(),
+        events,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_token_and_stats() {
+        assert_eq!(
+            topic_token("video_duration_watched"),
+            "video_duration_watched"
+        );
+        assert_eq!(topic_token("a.b *>c"), "a_b___c");
+        assert_eq!(topic_token(""), "_unknown");
+
+        record("sink.test", |stats| stats.dropped += 1);
+        record("sink_test", |stats| stats.published += 1);
+        let stats = METRICS.lock().unwrap()["sink_test"].clone();
+        assert_eq!((stats.published, stats.dropped), (1, 1));
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use async_nats::jetstream::{self, stream};
+use futures::future::BoxFuture;
+
+use super::{topic_token, EventSink, SinkEvent};
+use crate::config::EventSinkSection;
+
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(5);
+const STREAM_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Publishes to JetStream on `{subject_prefix}.{event}`
+pub struct NatsSink {
+    jetstream: jetstream::Context,
+    subject_prefix: String,
+}
+
+impl NatsSink {
+    /// Connects and makes sure a stream captures `{subject_prefix}.>`. An
+    /// existing stream is used as is.
+    pub async fn connect(config: &EventSinkSection) -> Result<Self> {
+        let client = async_nats::ConnectOptions::new()
+            .name("off-chain-agent")
+            .retry_on_initial_connect()
+            .connect(&config.nats_url)
+            .await
+            .with_context(|| format!("Failed to connect to {}", config.nats_url))?;
+        let jetstream = jetstream::new(client);
+
+        jetstream
+            .get_or_create_stream(stream::Config {
+                name: config.stream.clone(),
+                subjects: vec![format!("{}.>", config.subject_prefix)],
+                max_age: STREAM_MAX_AGE,
+                ..Default::default()
+            })
+            .await
+            .with_context(|| format!("Failed to set up stream {}", config.stream))?;
+
+        Ok(Self {
+            jetstream,
+            subject_prefix: config.subject_prefix.clone(),
+        })
+    }
+
+    fn subject(&self, event: &str) -> String {
+        format!("{}.{}", self.subject_prefix, topic_token(event))
+    }
+}
+
+impl EventSink for NatsSink {
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+
+    fn publish<'a>(&'a self, event: &'a SinkEvent) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let payload = serde_json::to_vec(event)?;
+            let ack = self
+                .jetstream
+                .publish(self.subject(&event.event), payload.into())
+                .await?;
+            tokio::time::timeout(PUBLISH_TIMEOUT, ack)
+                .await
+                .context("Timed out waiting for JetStream ack")??;
+            Ok(())
+        })
+    }
+}
@@ -12,6 +12,7 @@ use yral_metrics::metrics::{
 };
 
 use crate::app_state::AppState;
+use crate::events::notification_templates::NotificationCopy;
 use crate::rewards::config::RewardTokenType;
 
 pub fn string_or_number<'de, D>(deserializer: D) -> Result<String, D::Error>
@@ -210,6 +211,9 @@ pub struct VideoDurationWatchedPayloadV2 {
     pub source: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub client_type: Option<String>,
+    /// Install or browser id, so one user's devices can be told apart
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -449,6 +453,9 @@ pub struct VideoUploadSuccessfulPayload {
     pub country: Option<String>,
     #[serde(rename = "internalUrl", skip_serializing_if = "Option::is_none")]
     pub internal_url: Option<String>,
+    /// Unix seconds; the video stays hidden until then
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish_at: Option<i64>,
 }
 
 // --------------------------------------------------
@@ -695,6 +702,14 @@ pub struct RewardEarnedPayload {
     pub reward_token: RewardTokenType,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreakMilestonePayload {
+    #[serde(rename = "user_id")]
+    pub user_id: Principal,
+    #[serde(rename = "streak_days")]
+    pub streak_days: u32,
+}
+
 // ----------------------------------------------------------------------------------
 // Unified wrapper enum so callers can work with a single return type
 // ----------------------------------------------------------------------------------
@@ -753,6 +768,8 @@ pub enum EventPayload {
     FollowUser(FollowUserPayload),
     VideoApproved(VideoApprovalPayload),
     VideoDisapproved(VideoApprovalPayload),
+    StreakMilestone(StreakMilestonePayload),
+    VideoHiddenPendingReview(VideoApprovalPayload),
 }
 
 fn serialize_reward_earned<S>(
@@ -882,11 +899,43 @@ where
 impl EventPayload {
     // TODO: canister_id is used
 
+    /// Actor and recipient of notifications about one user's action on another
+    fn interaction_parties(&self) -> Option<(Principal, Principal)> {
+        match self {
+            EventPayload::LikeVideo(payload) => Some((payload.user_id, payload.publisher_user_id)),
+            EventPayload::FollowUser(payload) => {
+                Some((payload.follower_principal_id, payload.followee_principal_id))
+            }
+            _ => None,
+        }
+    }
+
     pub async fn send_notification(&self, app_state: &AppState) {
+        if let Some((actor, recipient)) = self.interaction_parties() {
+            match crate::user::blocklist::is_blocked_between(
+                &app_state.yral_redis_store_dragonfly,
+                &actor,
+                &recipient,
+            )
+            .await
+            {
+                Ok(true) => {
+                    log::debug!("Skipping notification from {actor} to {recipient}: blocked");
+                    return;
+                }
+                Ok(false) => {}
+                Err(e) => log::warn!("Failed to check block list for notification: {e}"),
+            }
+        }
+
         match self {
             EventPayload::VideoUploadSuccessful(payload) => {
-                let title = "Video Uploaded";
-                let body = "Your video has been uploaded successfully";
+                let copy = NotificationCopy::new("video_upload_successful");
+                let (title, body) = app_state
+                    .notification_client
+                    .templates()
+                    .render(&copy, None)
+                    .await;
                 let publisher_user_id = payload.publisher_user_id;
                 let canister_id = match app_state
                     .get_individual_canister_by_user_principal(publisher_user_id)
@@ -965,12 +1014,16 @@ impl EventPayload {
 
                 app_state
                     .notification_client
-                    .send_notification(notif_payload, payload.publisher_user_id)
+                    .send_localized(&copy, notif_payload, payload.publisher_user_id)
                     .await;
             }
             EventPayload::LikeVideo(payload) => {
-                let title = "Video Liked";
-                let body = format!("{} liked your video", payload.user_id.to_text());
+                let copy = NotificationCopy::new("like_video").var("user", payload.user_id);
+                let (title, body) = app_state
+                    .notification_client
+                    .templates()
+                    .render(&copy, None)
+                    .await;
                 let publisher_user_id = payload.publisher_user_id;
                 let canister_id = match app_state
                     .get_individual_canister_by_user_principal(publisher_user_id)
@@ -1049,7 +1102,7 @@ impl EventPayload {
 
                 app_state
                     .notification_client
-                    .send_notification(notif_payload, payload.publisher_user_id)
+                    .send_localized(&copy, notif_payload, payload.publisher_user_id)
                     .await;
             }
 
@@ -1066,11 +1119,15 @@ impl EventPayload {
             }
 
             EventPayload::TournamentEndedWinner(payload) => {
-                let title = format!("Congratulations! You won rank #{}!", payload.rank);
-                let body = format!(
-                    "You ranked #{}! You’ve won {} {} in the tournament. Check the leaderboard now!",
-                    payload.rank, payload.prize_amount, payload.prize_token
-                );
+                let copy = NotificationCopy::new("tournament_ended_winner")
+                    .var("rank", payload.rank)
+                    .var("prize_amount", &payload.prize_amount)
+                    .var("prize_token", &payload.prize_token);
+                let (title, body) = app_state
+                    .notification_client
+                    .templates()
+                    .render(&copy, None)
+                    .await;
 
                 let notif_payload = SendNotificationReq {
                     notification: Some(NotificationPayload {
@@ -1100,26 +1157,38 @@ impl EventPayload {
 
                 app_state
                     .notification_client
-                    .send_notification(notif_payload, payload.user_id)
+                    .send_localized(&copy, notif_payload, payload.user_id)
                     .await;
             }
 
             EventPayload::RewardEarned(payload) => {
-                let (title, body) = match payload.reward_token {
-                    RewardTokenType::Dolr => (
-                        "DOLR Credited",
-                        "Congrats! Your video views have earned you DOLR. See your balance in the wallet."
-                    ),
-                    RewardTokenType::Btc => (
-                        "Bitcoin Credited",
-                        "Congrats! Your video views have earned you Bitcoin. See your balance in the wallet."
-                    ),
+                let token_label = match payload.reward_token {
+                    RewardTokenType::Dolr => "DOLR",
+                    RewardTokenType::Btc => "Bitcoin",
                 };
 
+                #[cfg(not(feature = "local-bin"))]
+                let show_amount =
+                    crate::experiments::shows_reward_amount(app_state, payload.creator_id).await;
+                #[cfg(feature = "local-bin")]
+                let show_amount = false;
+                let copy = if show_amount {
+                    NotificationCopy::new("reward_earned_amount")
+                        .var("amount", format!("{:.2}", payload.reward_inr))
+                } else {
+                    NotificationCopy::new("reward_earned")
+                }
+                .var("token", token_label);
+                let (title, body) = app_state
+                    .notification_client
+                    .templates()
+                    .render(&copy, None)
+                    .await;
+
                 let notif_payload = SendNotificationReq {
                     notification: Some(NotificationPayload {
                         title: Some(title.to_string()),
-                        body: Some(body.to_string()),
+                        body: Some(body.clone()),
                         image: Some(
                             "https://yral.com/img/yral/android-chrome-384x384.png".to_string(),
                         ),
@@ -1175,16 +1244,22 @@ impl EventPayload {
 
                 app_state
                     .notification_client
-                    .send_notification(notif_payload, payload.creator_id)
+                    .send_localized(&copy, notif_payload, payload.creator_id)
                     .await;
             }
 
             EventPayload::FollowUser(payload) => {
-                let title = "New Follower";
-                let body = match &payload.follower_username {
-                    Some(username) => format!("{} started following you", username),
-                    None => "Someone started following you".to_string(),
+                let copy = match &payload.follower_username {
+                    Some(username) => {
+                        NotificationCopy::new("follow_user").var("username", username)
+                    }
+                    None => NotificationCopy::new("follow_user_anonymous"),
                 };
+                let (title, body) = app_state
+                    .notification_client
+                    .templates()
+                    .render(&copy, None)
+                    .await;
                 let followee_principal_id = payload.followee_principal_id;
 
                 let profile_url = format!(
@@ -1260,13 +1335,17 @@ impl EventPayload {
 
                 app_state
                     .notification_client
-                    .send_notification(notif_payload, followee_principal_id)
+                    .send_localized(&copy, notif_payload, followee_principal_id)
                     .await;
             }
 
             EventPayload::VideoApproved(payload) => {
-                let title = "Video Approved";
-                let body = "Your video has been approved and is now live!";
+                let copy = NotificationCopy::new("video_approved");
+                let (title, body) = app_state
+                    .notification_client
+                    .templates()
+                    .render(&copy, None)
+                    .await;
 
                 let video_url = payload
                     .canister_id
@@ -1335,13 +1414,17 @@ impl EventPayload {
 
                 app_state
                     .notification_client
-                    .send_notification(notif_payload, payload.user_id)
+                    .send_localized(&copy, notif_payload, payload.user_id)
                     .await;
             }
 
             EventPayload::VideoDisapproved(payload) => {
-                let title = "Video Not Approved";
-                let body = "Your video was not approved for publication.";
+                let copy = NotificationCopy::new("video_disapproved");
+                let (title, body) = app_state
+                    .notification_client
+                    .templates()
+                    .render(&copy, None)
+                    .await;
 
                 let notif_payload = SendNotificationReq {
                     notification: Some(NotificationPayload {
@@ -1404,7 +1487,142 @@ impl EventPayload {
 
                 app_state
                     .notification_client
-                    .send_notification(notif_payload, payload.user_id)
+                    .send_localized(&copy, notif_payload, payload.user_id)
+                    .await;
+            }
+
+            EventPayload::VideoHiddenPendingReview(payload) => {
+                let copy = NotificationCopy::new("video_hidden_pending_review");
+                let (title, body) = app_state
+                    .notification_client
+                    .templates()
+                    .render(&copy, None)
+                    .await;
+
+                let notif_payload = SendNotificationReq {
+                    notification: Some(NotificationPayload {
+                        title: Some(title.to_string()),
+                        body: Some(body.to_string()),
+                        image: Some(
+                            "https://yral.com/img/yral/android-chrome-384x384.png".to_string(),
+                        ),
+                    }),
+                    data: Some(json!({
+                        "type": "video_hidden_pending_review",
+                        "video_id": payload.video_id,
+                        "post_id": payload.post_id
+                    })),
+                    android: Some(AndroidConfig {
+                        notification: Some(AndroidNotification {
+                            icon: Some(
+                                "https://yral.com/img/yral/android-chrome-384x384.png".to_string(),
+                            ),
+                            image: Some(
+                                "https://yral.com/img/yral/android-chrome-384x384.png".to_string(),
+                            ),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    webpush: Some(WebpushConfig {
+                        fcm_options: Some(WebpushFcmOptions {
+                            link: Some("https://yral.com".to_string()),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    apns: Some(ApnsConfig {
+                        headers: Some(json!({
+                            "apns-push-type": "alert",
+                            "apns-priority": "10",
+                        })),
+                        fcm_options: Some(ApnsFcmOptions {
+                            image: Some(
+                                "https://yral.com/img/yral/android-chrome-384x384.png".to_string(),
+                            ),
+                            ..Default::default()
+                        }),
+                        payload: Some(json!({
+                            "aps": {
+                                "alert": {
+                                    "title": title.to_string(),
+                                    "body": body.to_string(),
+                                },
+                                "sound": "default",
+                                "mutable-content": 1,
+                            },
+                            "url": "https://yral.com"
+                        })),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                };
+
+                app_state
+                    .notification_client
+                    .send_localized(&copy, notif_payload, payload.user_id)
+                    .await;
+            }
+
+            EventPayload::StreakMilestone(payload) => {
+                let copy =
+                    NotificationCopy::new("streak_milestone").var("days", payload.streak_days);
+                let (title, body) = app_state
+                    .notification_client
+                    .templates()
+                    .render(&copy, None)
+                    .await;
+
+                let notif_payload = SendNotificationReq {
+                    notification: Some(NotificationPayload {
+                        title: Some(title.clone()),
+                        body: Some(body.clone()),
+                        image: Some(
+                            "https://yral.com/img/yral/android-chrome-384x384.png".to_string(),
+                        ),
+                    }),
+                    data: Some(json!({
+                        "payload": serde_json::to_string(self).unwrap()
+                    })),
+                    android: Some(AndroidConfig {
+                        notification: Some(AndroidNotification {
+                            icon: Some(
+                                "https://yral.com/img/yral/android-chrome-384x384.png".to_string(),
+                            ),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    webpush: Some(WebpushConfig {
+                        fcm_options: Some(WebpushFcmOptions {
+                            link: Some("https://yral.com".to_string()),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    apns: Some(ApnsConfig {
+                        headers: Some(json!({
+                            "apns-push-type": "alert",
+                            "apns-priority": "10",
+                        })),
+                        payload: Some(json!({
+                            "aps": {
+                                "alert": {
+                                    "title": title,
+                                    "body": body,
+                                },
+                                "sound": "default",
+                            },
+                            "url": "https://yral.com"
+                        })),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                };
+
+                app_state
+                    .notification_client
+                    .send_localized(&copy, notif_payload, payload.user_id)
                     .await;
             }
 
@@ -1481,6 +1699,12 @@ pub fn deserialize_event_payload(
         "video_disapproved" => Ok(EventPayload::VideoDisapproved(serde_json::from_value(
             value,
         )?)),
+        "streak_milestone" => Ok(EventPayload::StreakMilestone(serde_json::from_value(
+            value,
+        )?)),
+        "video_hidden_pending_review" => Ok(EventPayload::VideoHiddenPendingReview(
+            serde_json::from_value(value)?,
+        )),
         _ => Err(serde_json::Error::unknown_field(event_name, &[])),
     }
 }
@@ -1501,6 +1725,7 @@ fn test_data_payload_serialization() {
         video_id: "test".to_string(),
         country: None,
         internal_url: None,
+        publish_at: None,
     };
 
     let data = EventPayload::VideoUploadSuccessful(payload.clone());
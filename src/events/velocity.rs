@@ -0,0 +1,288 @@
+//! Per-sender velocity limits on ingested events.
+//!
+//! Events are charged to the principal of a verified delegated identity, or,
+//! for events sent without one, to the client IP; the client-supplied
+//! `user_id` is never trusted for this. The IP is the right-most
+//! `x-forwarded-for` hop, which our edge appends, or the peer address when
+//! the header is missing. Every (sender, event type) pair has a token bucket
+//! in Dragonfly, sized by `event_velocity.limits` or `default_limit`, and
+//! each sender has one more bucket across all event types, sized by
+//! `sender_limit`, so rotating event names gains nothing. An event that
+//! finds either bucket empty is rate limited: dropped, or with `drop_excess` off,
+//! processed with `rate_limited: true` in its params so downstream consumers
+//! can discount it. Limited events are counted per type as
+//! `event_rate_limited`, served at `/event-rate-limit-metrics`.
+//!
+//! A verified principal rate limited `abuse_threshold` times within
+//! `abuse_window_secs` is reported to [`FraudDetector`], which shadow bans
+//! them from rewards. IP senders are limited but never reported. A Redis
+//! error lets the event through.
+
+use std::{collections::BTreeMap, net::SocketAddr, sync::Mutex};
+
+use anyhow::Result;
+use axum::{http::HeaderMap, Json};
+use candid::Principal;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use super::{sink::topic_token, warehouse_events::WarehouseEvent};
+use crate::{
+    app_state::AppState, auth::require_operator, config::runtime::runtime, config::EventRateLimit,
+    error::ApiError, rewards::fraud_detection::FraudDetector, yral_auth::dragonfly::DragonflyPool,
+};
+
+const BUCKET_KEY_PREFIX: &str = "offchain:event_velocity:bucket";
+const SENDER_BUCKET_KEY_PREFIX: &str = "offchain:event_velocity:sender_bucket";
+const STRIKES_KEY_PREFIX: &str = "offchain:event_velocity:strikes";
+
+/// Takes a token from both the event bucket in KEYS[1] and the sender bucket
+/// in KEYS[2], refilling each for the time since its last event first. When
+/// either is empty neither is charged, and a strike is counted for the sender
+/// in KEYS[3] instead. Returns `{allowed, strikes}`.
+static TOKEN_BUCKET_SCRIPT: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+        local now_ms = tonumber(ARGV[5])
+        local strike_window_secs = tonumber(ARGV[6])
+
+        local function refill(key, capacity, refill_per_ms)
+            local bucket = redis.call('HMGET', key, 'tokens', 'ts')
+            local tokens = tonumber(bucket[1]) or capacity
+            local ts = tonumber(bucket[2]) or now_ms
+            return math.min(capacity, tokens + math.max(0, now_ms - ts) * refill_per_ms)
+        end
+
+        local function store(key, tokens, capacity, refill_per_ms)
+            redis.call('HSET', key, 'tokens', tostring(tokens), 'ts', now_ms)
+            redis.call('PEXPIRE', key, math.ceil(capacity / refill_per_ms))
+        end
+
+        local event_capacity, event_refill = tonumber(ARGV[1]), tonumber(ARGV[2])
+        local sender_capacity, sender_refill = tonumber(ARGV[3]), tonumber(ARGV[4])
+        local event_tokens = refill(KEYS[1], event_capacity, event_refill)
+        local sender_tokens = refill(KEYS[2], sender_capacity, sender_refill)
+
+        local allowed = 0
+        if event_tokens >= 1 and sender_tokens >= 1 then
+            event_tokens = event_tokens - 1
+            sender_tokens = sender_tokens - 1
+            allowed = 1
+        end
+        store(KEYS[1], event_tokens, event_capacity, event_refill)
+        store(KEYS[2], sender_tokens, sender_capacity, sender_refill)
+
+        if allowed == 1 then return {1, 0} end
+        local strikes = redis.call('INCR', KEYS[3])
+        if strikes == 1 then
+            redis.call('EXPIRE', KEYS[3], strike_window_secs)
+        end
+        return {0, strikes}
+        "#,
+    )
+});
+
+static METRICS: Lazy<Mutex<BTreeMap<String, u64>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Allowed,
+    /// `strikes` is how often the sender was limited in the abuse window
+    Limited {
+        strikes: u64,
+    },
+}
+
+/// Who an event's velocity is charged to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sender {
+    /// Verified through a delegated identity; may be reported as an abuser
+    Principal(Principal),
+    /// Unverified event, bucketed by client IP
+    Ip(String),
+}
+
+impl Sender {
+    /// Sender of an event that carried no delegated identity
+    pub fn unverified(headers: &HeaderMap, peer: SocketAddr) -> Self {
+        Self::Ip(client_ip(headers).unwrap_or_else(|| peer.ip().to_string()))
+    }
+
+    /// The principal, when verified
+    pub fn principal(&self) -> Option<Principal> {
+        match self {
+            Self::Principal(principal) => Some(*principal),
+            Self::Ip(_) => None,
+        }
+    }
+
+    fn key(&self) -> String {
+        match self {
+            Self::Principal(principal) => principal.to_text(),
+            Self::Ip(ip) => format!("ip:{ip}"),
+        }
+    }
+}
+
+/// Last hop of `x-forwarded-for`. The edge appends the address it saw, so
+/// anything before it is client-supplied.
+fn client_ip(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit(',').next())
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .map(str::to_string)
+}
+
+fn record_limited(event: &str) {
+    let mut metrics = METRICS.lock().unwrap_or_else(|e| e.into_inner());
+    *metrics.entry(topic_token(event)).or_default() += 1;
+}
+
+/// Takes one token from the sender's `event` bucket and one from their
+/// bucket across all events
+pub async fn check(
+    pool: &DragonflyPool,
+    sender: &str,
+    event: &str,
+    limit: &EventRateLimit,
+    sender_limit: &EventRateLimit,
+    abuse_window_secs: u64,
+) -> Result<Verdict> {
+    let mut conn = pool.get().await?;
+    let (allowed, strikes): (i64, u64) = TOKEN_BUCKET_SCRIPT
+        .key(format!("{BUCKET_KEY_PREFIX}:{sender}:{event}"))
+        .key(format!("{SENDER_BUCKET_KEY_PREFIX}:{sender}"))
+        .key(format!("{STRIKES_KEY_PREFIX}:{sender}"))
+        .arg(limit.burst)
+        .arg(limit.per_minute as f64 / 60_000.0)
+        .arg(sender_limit.burst)
+        .arg(sender_limit.per_minute as f64 / 60_000.0)
+        .arg(Utc::now().timestamp_millis())
+        .arg(abuse_window_secs)
+        .invoke_async(&mut conn)
+        .await?;
+
+    Ok(if allowed == 1 {
+        Verdict::Allowed
+    } else {
+        Verdict::Limited { strikes }
+    })
+}
+
+/// Applies the velocity limit to `event` sent by `sender`. Returns `false`
+/// when the event should be dropped; a limited event that is kept is flagged
+/// in its params.
+pub async fn enforce(
+    state: &AppState,
+    event: &mut WarehouseEvent,
+    sender: Option<&Sender>,
+) -> bool {
+    let config = runtime();
+    let velocity = &config.event_velocity;
+    if !velocity.enabled {
+        return true;
+    }
+    let Some(sender) = sender else {
+        return true;
+    };
+    let Ok(mut params) = serde_json::from_str::<Value>(&event.params) else {
+        return true;
+    };
+    let sender_key = sender.key();
+
+    let verdict = match check(
+        &state.yral_redis_store_dragonfly,
+        &sender_key,
+        &event.event,
+        velocity.limit_for(&event.event),
+        &velocity.sender_limit,
+        velocity.abuse_window_secs,
+    )
+    .await
+    {
+        Ok(verdict) => verdict,
+        Err(e) => {
+            log::warn!("Failed to check event velocity for {sender_key}: {e:?}");
+            return true;
+        }
+    };
+    let Verdict::Limited { strikes } = verdict else {
+        return true;
+    };
+
+    record_limited(&event.event);
+    // Reported once per window, when a verified principal crosses the threshold
+    if let Sender::Principal(principal) = sender {
+        if strikes == velocity.abuse_threshold {
+            report_abuser(state, *principal, &event.event, strikes);
+        }
+    }
+
+    if velocity.drop_excess {
+        return false;
+    }
+    if let Value::Object(ref mut map) = params {
+        map.insert("rate_limited".to_string(), Value::Bool(true));
+    }
+    event.params = params.to_string();
+    true
+}
+
+fn report_abuser(state: &AppState, principal: Principal, event: &str, strikes: u64) {
+    let fraud_detector = FraudDetector::new(state.yral_redis_store_dragonfly.clone());
+    let event = event.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = fraud_detector
+            .report_event_spam(principal, &event, strikes)
+            .await
+        {
+            log::error!("Failed to report event spam from {principal}: {e:?}");
+        }
+    });
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EventRateLimitMetricsResponse {
+    /// Rate-limited events keyed by event type
+    pub event_rate_limited: BTreeMap<String, u64>,
+}
+
+/// Per-event-type rate limiting counters since process start; operators only
+pub async fn event_rate_limit_metrics_handler(
+    headers: HeaderMap,
+) -> Result<Json<EventRateLimitMetricsResponse>, ApiError> {
+    require_operator(&headers)?;
+    Ok(Json(EventRateLimitMetricsResponse {
+        event_rate_limited: METRICS.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sender_and_counters() {
+        let mut headers = HeaderMap::new();
+        let peer = SocketAddr::from(([10, 0, 0, 1], 443));
+        assert_eq!(
+            Sender::unverified(&headers, peer),
+            Sender::Ip("10.0.0.1".to_string())
+        );
+        headers.insert("x-forwarded-for", "1.2.3.4, 203.0.113.7".parse().unwrap());
+        let sender = Sender::unverified(&headers, peer);
+        assert_eq!(sender, Sender::Ip("203.0.113.7".to_string()));
+        assert_eq!(sender.key(), "ip:203.0.113.7");
+        assert_eq!(Sender::Principal(Principal::anonymous()).key(), "2vxsx-fae");
+
+        record_limited("velocity.test");
+        record_limited("velocity_test");
+        assert_eq!(METRICS.lock().unwrap()["velocity_test"], 2);
+    }
+}
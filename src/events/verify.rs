@@ -12,7 +12,7 @@ use yral_metrics::metrics::sealed_metric::SealedMetric;
 use crate::{
     app_state::AppState,
     events::{EventBulkRequestV2, VerifiedEventBulkRequestV2},
-    utils::delegated_identity::get_user_info_from_delegated_identity_wire,
+    utils::delegated_identity::verify_delegated_identity_request,
 };
 
 use super::{EventBulkRequest, VerifiedEventBulkRequest};
@@ -45,9 +45,10 @@ pub async fn verify_event_bulk_request(
         }
     };
 
-    let user_info = get_user_info_from_delegated_identity_wire(
+    let user_info = verify_delegated_identity_request(
         &state,
         event_bulk_request.delegated_identity_wire.clone(),
+        &parts.headers,
     )
     .await
     .map_err(|e| {
@@ -111,9 +112,10 @@ pub async fn verify_event_bulk_request_v3(
         }
     };
 
-    let user_info = get_user_info_from_delegated_identity_wire(
+    let user_info = verify_delegated_identity_request(
         &state,
         event_bulk_request.delegated_identity_wire.clone(),
+        &parts.headers,
     )
     .await
     .map_err(|e| {
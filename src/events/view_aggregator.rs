@@ -0,0 +1,410 @@
+//! Batches post view updates instead of one canister call per watch event.
+//!
+//! Watch events add deltas to a per-post hash in Dragonfly and mark the post
+//! dirty. A background flusher drains dirty posts every
+//! `VIEW_AGG_FLUSH_INTERVAL_SECS`, or early once `VIEW_AGG_FLUSH_MAX_VIEWS`
+//! views have accumulated. Partial and full watches are kept apart and each
+//! goes out as one counted `update_post_add_view_details` call per post.
+//! Deltas live in Redis until a call succeeds, so a crash delays them rather
+//! than losing them; each call takes its views off the inflight batch once it
+//! succeeds, so a retry only resends what did not go through.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use futures::StreamExt;
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use tokio::sync::Notify;
+use yral_canisters_client::user_post_service::{
+    PostViewDetailsFromFrontend as UserPostViewDetails, UserPostService,
+};
+
+use crate::{
+    app_state::AppState,
+    canister::agent_pool,
+    consts::USER_POST_SERVICE_CANISTER_ID,
+    events::types::{VideoDurationWatchedPayload, VideoDurationWatchedPayloadV2},
+    utils::env_parse,
+};
+
+const PENDING_KEY_PREFIX: &str = "offchain:view_agg:pending";
+const INFLIGHT_KEY_PREFIX: &str = "offchain:view_agg:inflight";
+const DIRTY_SET_KEY: &str = "offchain:view_agg:dirty";
+const INFLIGHT_SET_KEY: &str = "offchain:view_agg:inflight_posts";
+const VIEWS_SINCE_FLUSH_KEY: &str = "offchain:view_agg:views_since_flush";
+const FLUSH_LOCK_KEY: &str = "offchain:view_agg:flush_lock";
+
+/// Percentage at or above which a view counts as a full watch
+const FULL_WATCH_PERCENTAGE: u8 = 95;
+const FLUSH_BATCH_SIZE: usize = 500;
+const FLUSH_CONCURRENCY: usize = 16;
+/// Longer than any single flush, so a crashed holder cannot block others for long
+const FLUSH_LOCK_TTL_SECS: u64 = 120;
+
+/// Moves a post's pending deltas to its inflight key and tracks it for
+/// recovery. Skips posts whose previous batch is still inflight.
+static CLAIM_SCRIPT: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+        if redis.call('EXISTS', KEYS[2]) == 1 then return 0 end
+        if redis.call('EXISTS', KEYS[1]) == 0 then return 0 end
+        redis.call('RENAME', KEYS[1], KEYS[2])
+        redis.call('SADD', KEYS[3], ARGV[1])
+        return 1
+        "#,
+    )
+});
+
+static FLUSH_NOW: Lazy<Notify> = Lazy::new(Notify::new);
+
+#[derive(Debug, Clone)]
+pub struct ViewAggregatorConfig {
+    pub enabled: bool,
+    pub flush_interval: Duration,
+    pub flush_max_views: u64,
+}
+
+impl ViewAggregatorConfig {
+    pub fn from_env() -> Self {
+        Self {
+            enabled: env_parse("VIEW_AGG_ENABLED", true),
+            flush_interval: Duration::from_secs(env_parse("VIEW_AGG_FLUSH_INTERVAL_SECS", 30)),
+            flush_max_views: env_parse("VIEW_AGG_FLUSH_MAX_VIEWS", 500),
+        }
+    }
+}
+
+pub fn aggregation_enabled() -> bool {
+    env_parse("VIEW_AGG_ENABLED", true)
+}
+
+fn pending_key(post_id: &str) -> String {
+    format!("{PENDING_KEY_PREFIX}:{post_id}")
+}
+
+fn inflight_key(post_id: &str) -> String {
+    format!("{INFLIGHT_KEY_PREFIX}:{post_id}")
+}
+
+/// `(post_id, percentage_watched)` from a `video_duration_watched` payload in
+/// either the current or the legacy format
+pub fn parse_watch_event(params: &str) -> Option<(String, u8)> {
+    let (post_id, percentage) = serde_json::from_str::<VideoDurationWatchedPayloadV2>(params)
+        .map(|p| (p.post_id, p.percentage_watched))
+        .or_else(|_| {
+            serde_json::from_str::<VideoDurationWatchedPayload>(params)
+                .map(|p| (p.post_id, p.percentage_watched))
+        })
+        .ok()?;
+
+    let percentage = percentage as u8;
+    (percentage > 0 && percentage <= 100).then_some((post_id, percentage))
+}
+
+/// Accumulated deltas for one post
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ViewDeltas {
+    pub partial_count: u64,
+    pub partial_pct_sum: u64,
+    pub full_count: u64,
+    pub full_pct_sum: u64,
+}
+
+/// One canister call and the inflight counters it accounts for
+#[derive(Debug)]
+pub struct ViewBatch {
+    pub details: UserPostViewDetails,
+    /// `(field, amount)` pairs to take off the inflight hash once sent
+    pub consumed: [(&'static str, u64); 2],
+}
+
+/// Splits `count` views averaging `pct_sum / count` into counted calls of at
+/// most `u8::MAX` views. The last call takes the remainder of the sum.
+fn counted_batches(
+    count: u64,
+    pct_sum: u64,
+    fields: (&'static str, &'static str),
+) -> Vec<ViewBatch> {
+    let mut batches = Vec::new();
+    if count == 0 {
+        return batches;
+    }
+    let percentage_watched = (pct_sum / count).min(100) as u8;
+    let (mut remaining, mut remaining_sum) = (count, pct_sum);
+    while remaining > 0 {
+        let watch_count = remaining.min(u8::MAX as u64);
+        remaining -= watch_count;
+        let sum = if remaining == 0 {
+            remaining_sum
+        } else {
+            (pct_sum / count) * watch_count
+        };
+        remaining_sum -= sum;
+        batches.push(ViewBatch {
+            details: UserPostViewDetails::WatchedMultipleTimes {
+                percentage_watched,
+                watch_count: watch_count as u8,
+            },
+            consumed: [(fields.0, watch_count), (fields.1, sum)],
+        });
+    }
+    batches
+}
+
+impl ViewDeltas {
+    fn from_fields(fields: &[(String, u64)]) -> Self {
+        let mut deltas = Self::default();
+        for (field, value) in fields {
+            match field.as_str() {
+                "partial_count" => deltas.partial_count = *value,
+                "partial_pct_sum" => deltas.partial_pct_sum = *value,
+                "full_count" => deltas.full_count = *value,
+                "full_pct_sum" => deltas.full_pct_sum = *value,
+                _ => {}
+            }
+        }
+        deltas
+    }
+
+    /// The canister calls that apply these deltas. Partial and full watches
+    /// each keep their count through `watch_count` (split at `u8::MAX`) at
+    /// their own average percentage, so partial views stay below
+    /// `FULL_WATCH_PERCENTAGE` instead of being folded into full ones.
+    pub fn to_view_batches(&self) -> Vec<ViewBatch> {
+        let mut batches = counted_batches(
+            self.partial_count,
+            self.partial_pct_sum,
+            ("partial_count", "partial_pct_sum"),
+        );
+        batches.extend(counted_batches(
+            self.full_count,
+            self.full_pct_sum,
+            ("full_count", "full_pct_sum"),
+        ));
+        batches
+    }
+}
+
+/// Adds one view to the post's pending deltas
+pub async fn record_view(state: &AppState, post_id: &str, percentage_watched: u8) -> Result<()> {
+    let (count_field, pct_field) = if percentage_watched >= FULL_WATCH_PERCENTAGE {
+        ("full_count", "full_pct_sum")
+    } else {
+        ("partial_count", "partial_pct_sum")
+    };
+
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+    let (views_since_flush,): (u64,) = redis::pipe()
+        .atomic()
+        .hincr(pending_key(post_id), count_field, 1)
+        .ignore()
+        .hincr(pending_key(post_id), pct_field, percentage_watched as u64)
+        .ignore()
+        .sadd(DIRTY_SET_KEY, post_id)
+        .ignore()
+        .incr(VIEWS_SINCE_FLUSH_KEY, 1)
+        .query_async(&mut conn)
+        .await?;
+
+    if views_since_flush >= ViewAggregatorConfig::from_env().flush_max_views {
+        FLUSH_NOW.notify_one();
+    }
+
+    Ok(())
+}
+
+/// Sends the batches one by one, taking each off the inflight hash as soon
+/// as the canister has it
+async fn send_view_details(state: &AppState, post_id: &str, deltas: &ViewDeltas) -> Result<()> {
+    let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &state.agent);
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+    for batch in deltas.to_view_batches() {
+        if let Err(e) = agent_pool::update(
+            *USER_POST_SERVICE_CANISTER_ID,
+            "update_post_add_view_details",
+            user_post_service.update_post_add_view_details(post_id.to_string(), batch.details),
+        )
+        .await
+        {
+            return Err(anyhow::anyhow!(
+                "Failed to update view details for post {post_id}: {e:?}"
+            ));
+        }
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (field, amount) in batch.consumed {
+            pipe.hincr(inflight_key(post_id), field, -(amount as i64))
+                .ignore();
+        }
+        let _: () = pipe.query_async(&mut conn).await?;
+    }
+    Ok(())
+}
+
+/// Sends one post's inflight batch. On failure whatever is left inflight is
+/// merged back into pending so the next flush retries only that.
+async fn flush_inflight_post(state: &AppState, post_id: &str) -> Result<()> {
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+    let fields: Vec<(String, u64)> = conn.hgetall(inflight_key(post_id)).await?;
+    let deltas = ViewDeltas::from_fields(&fields);
+
+    let send_result = if deltas == ViewDeltas::default() {
+        Ok(())
+    } else {
+        send_view_details(state, post_id, &deltas).await
+    };
+
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    if let Err(e) = &send_result {
+        log::warn!("View flush for post {post_id} failed, requeueing the rest: {e:?}");
+        let unsent: Vec<(String, u64)> = conn.hgetall(inflight_key(post_id)).await?;
+        for (field, value) in unsent.iter().filter(|(_, value)| *value > 0) {
+            pipe.hincr(pending_key(post_id), field, *value).ignore();
+        }
+        pipe.sadd(DIRTY_SET_KEY, post_id).ignore();
+    }
+    pipe.del(inflight_key(post_id)).ignore();
+    pipe.srem(INFLIGHT_SET_KEY, post_id).ignore();
+    let _: () = pipe.query_async(&mut conn).await?;
+
+    send_result
+}
+
+/// Drains dirty posts in batches. Returns the number of posts flushed.
+pub async fn flush_views(state: &AppState) -> Result<usize> {
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+
+    // One flusher across all replicas, otherwise recovery could resend a
+    // batch another replica is still sending
+    let acquired: bool = redis::cmd("SET")
+        .arg(FLUSH_LOCK_KEY)
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(FLUSH_LOCK_TTL_SECS)
+        .query_async::<Option<String>>(&mut conn)
+        .await?
+        .is_some();
+    if !acquired {
+        return Ok(0);
+    }
+
+    let result = flush_views_locked(state, &mut conn).await;
+    let _: () = conn.del(FLUSH_LOCK_KEY).await?;
+    result
+}
+
+async fn flush_views_locked(
+    state: &AppState,
+    conn: &mut redis::aio::MultiplexedConnection,
+) -> Result<usize> {
+    let _: () = conn.set(VIEWS_SINCE_FLUSH_KEY, 0).await?;
+
+    // Batches claimed by a flusher that died before finishing
+    let mut post_ids: Vec<String> = conn.smembers(INFLIGHT_SET_KEY).await?;
+
+    let dirty: Vec<String> = redis::cmd("SPOP")
+        .arg(DIRTY_SET_KEY)
+        .arg(FLUSH_BATCH_SIZE)
+        .query_async(conn)
+        .await?;
+    for post_id in dirty {
+        let claimed: i64 = CLAIM_SCRIPT
+            .key(pending_key(&post_id))
+            .key(inflight_key(&post_id))
+            .key(INFLIGHT_SET_KEY)
+            .arg(&post_id)
+            .invoke_async(conn)
+            .await?;
+        if claimed == 1 {
+            post_ids.push(post_id);
+        } else {
+            // Still inflight from an earlier batch; keep it dirty for next time
+            let _: () = conn.sadd(DIRTY_SET_KEY, &post_id).await?;
+        }
+    }
+    post_ids.sort();
+    post_ids.dedup();
+
+    let flushed = futures::stream::iter(post_ids)
+        .map(|post_id| async move { flush_inflight_post(state, &post_id).await.is_ok() })
+        .buffer_unordered(FLUSH_CONCURRENCY)
+        .filter(|ok| futures::future::ready(*ok))
+        .count()
+        .await;
+
+    Ok(flushed)
+}
+
+/// Periodic flusher; also wakes early when the view threshold is reached
+pub fn spawn_view_flusher(state: Arc<AppState>) {
+    let config = ViewAggregatorConfig::from_env();
+    if !config.enabled {
+        log::info!("View aggregation disabled by VIEW_AGG_ENABLED=false");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.flush_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = FLUSH_NOW.notified() => {}
+            }
+            match flush_views(&state).await {
+                Ok(0) => {}
+                Ok(flushed) => log::info!("Flushed view details for {flushed} posts"),
+                Err(e) => log::error!("View flush failed: {e:?}"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_deltas_to_batches() {
+        let deltas = ViewDeltas::from_fields(&[
+            ("partial_count".to_string(), 3),
+            ("partial_pct_sum".to_string(), 120),
+            ("full_count".to_string(), 300),
+            ("full_pct_sum".to_string(), 29_950),
+        ]);
+
+        let batches = deltas.to_view_batches();
+        assert_eq!(batches.len(), 3);
+        assert!(matches!(
+            batches[0].details,
+            UserPostViewDetails::WatchedMultipleTimes {
+                percentage_watched: 40,
+                watch_count: 3
+            }
+        ));
+        assert_eq!(
+            batches[0].consumed,
+            [("partial_count", 3), ("partial_pct_sum", 120)]
+        );
+        assert!(matches!(
+            batches[1].details,
+            UserPostViewDetails::WatchedMultipleTimes {
+                percentage_watched: 99,
+                watch_count: 255
+            }
+        ));
+        assert_eq!(
+            batches[1].consumed,
+            [("full_count", 255), ("full_pct_sum", 255 * 99)]
+        );
+        assert_eq!(
+            batches[2].consumed,
+            [("full_count", 45), ("full_pct_sum", 29_950 - 255 * 99)]
+        );
+        assert!(ViewDeltas::default().to_view_batches().is_empty());
+    }
+}
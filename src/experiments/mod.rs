@@ -0,0 +1,251 @@
+//! Deterministic A/B experiment assignment.
+//!
+//! Experiments are declared in code with weighted variants. A principal's
+//! variant comes from the same salted hash as feature flag rollouts, so every
+//! instance agrees on it, and it is persisted on first assignment so later
+//! weight changes don't move users who were already assigned.
+//!
+//! Each experiment is switched on by its own feature flag; the flag's rollout
+//! percentage controls how many principals are enrolled. Unenrolled
+//! principals get the control variant. Code branches through [`variant`],
+//! which also streams an `experiment_exposure` event to the warehouse.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use candid::Principal;
+use redis::AsyncCommands;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    events::{event::Event, warehouse_events::WarehouseEvent},
+    feature_flags::{rollout_bucket, Flag},
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const ASSIGNMENTS_KEY_PREFIX: &str = "offchain:experiments:assignments";
+const ASSIGNMENTS_TTL_SECS: i64 = 180 * 24 * 60 * 60;
+pub const EXPOSURE_EVENT: &str = "experiment_exposure";
+pub const CONTROL: &str = "control";
+
+pub struct Experiment {
+    pub name: &'static str,
+    /// Variant names with weights summing to 100; the first is the control
+    pub variants: &'static [(&'static str, u8)],
+    /// Enrolment switch, rolled out by principal
+    pub enabled: Flag<bool>,
+}
+
+/// Multiplier applied to creator milestone rewards
+pub const REWARD_MULTIPLIER: Experiment = Experiment {
+    name: "reward_multiplier_v1",
+    variants: &[(CONTROL, 50), ("boost_1_5x", 25), ("boost_2x", 25)],
+    enabled: Flag::new("experiment_reward_multiplier_v1", false),
+};
+
+/// Reward notification body that names the amount earned
+pub const REWARD_NOTIFICATION_COPY: Experiment = Experiment {
+    name: "reward_notification_copy_v1",
+    variants: &[(CONTROL, 50), ("show_amount", 50)],
+    enabled: Flag::new("experiment_reward_notification_copy_v1", false),
+};
+
+pub static EXPERIMENTS: &[&Experiment] = &[&REWARD_MULTIPLIER, &REWARD_NOTIFICATION_COPY];
+
+impl Experiment {
+    /// Variant from the weights alone, ignoring any persisted assignment
+    fn bucket_variant(&self, principal: &Principal) -> &'static str {
+        let mut bucket = rollout_bucket(self.name, principal);
+        for (variant, weight) in self.variants {
+            if bucket < *weight {
+                return variant;
+            }
+            bucket -= weight;
+        }
+        CONTROL
+    }
+
+    fn declared_variant(&self, name: &str) -> Option<&'static str> {
+        self.variants
+            .iter()
+            .find(|(variant, _)| *variant == name)
+            .map(|(variant, _)| *variant)
+    }
+}
+
+fn assignments_key(principal: &Principal) -> String {
+    format!("{ASSIGNMENTS_KEY_PREFIX}:{principal}")
+}
+
+/// The persisted variant, assigning one first if the principal has none.
+/// A persisted variant that is no longer declared is replaced.
+async fn assign(
+    pool: &Arc<DragonflyPool>,
+    experiment: &Experiment,
+    principal: &Principal,
+) -> Result<&'static str> {
+    let mut conn = pool.get().await?;
+    let key = assignments_key(principal);
+    let computed = experiment.bucket_variant(principal);
+
+    let (stored,): (Option<String>,) = redis::pipe()
+        .hset_nx(&key, experiment.name, computed)
+        .ignore()
+        .hget(&key, experiment.name)
+        .expire(&key, ASSIGNMENTS_TTL_SECS)
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+
+    if let Some(variant) = stored
+        .as_deref()
+        .and_then(|stored| experiment.declared_variant(stored))
+    {
+        return Ok(variant);
+    }
+    let _: () = conn.hset(&key, experiment.name, computed).await?;
+    Ok(computed)
+}
+
+/// Variant to branch on for `principal`. Control when the principal isn't
+/// enrolled or Redis is unavailable; exposures are only logged for enrolled
+/// principals.
+pub async fn variant(
+    app_state: &AppState,
+    experiment: &Experiment,
+    principal: Principal,
+) -> &'static str {
+    let pool = &app_state.yral_redis_store_dragonfly;
+    if !experiment.enabled.get_for(pool, &principal).await {
+        return CONTROL;
+    }
+
+    let variant = match assign(pool, experiment, &principal).await {
+        Ok(variant) => variant,
+        Err(e) => {
+            log::warn!(
+                "Failed to assign {principal} to experiment {}: {e}",
+                experiment.name
+            );
+            return CONTROL;
+        }
+    };
+
+    Event::new(WarehouseEvent {
+        event: EXPOSURE_EVENT.to_string(),
+        params: serde_json::json!({
+            "experiment": experiment.name,
+            "variant": variant,
+            "principal": principal.to_text(),
+        })
+        .to_string(),
+    })
+    .stream_to_bigquery(app_state);
+
+    variant
+}
+
+/// Hook for `RewardEngine` milestone payouts
+pub async fn reward_multiplier(app_state: &AppState, creator: Principal) -> f64 {
+    match variant(app_state, &REWARD_MULTIPLIER, creator).await {
+        "boost_1_5x" => 1.5,
+        "boost_2x" => 2.0,
+        _ => 1.0,
+    }
+}
+
+/// Hook for the reward-earned notification copy
+pub async fn shows_reward_amount(app_state: &AppState, creator: Principal) -> bool {
+    variant(app_state, &REWARD_NOTIFICATION_COPY, creator).await == "show_amount"
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExperimentAssignment {
+    pub experiment: String,
+    pub variant: String,
+    /// False when the experiment is off for this principal and the variant
+    /// is the control
+    pub enrolled: bool,
+}
+
+/// Variants a principal is assigned to, assigning any that are missing
+#[utoipa::path(
+    get,
+    path = "/assignments/{principal}",
+    params(("principal" = String, Path, description = "User principal")),
+    tag = "experiments",
+    responses(
+        (status = 200, description = "Assignments", body = Vec<ExperimentAssignment>),
+        (status = 400, description = "Invalid principal", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn get_assignments(
+    State(state): State<Arc<AppState>>,
+    Path(principal): Path<String>,
+) -> Result<Json<Vec<ExperimentAssignment>>, ApiError> {
+    let principal = Principal::from_text(&principal)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid principal: {e}")))?;
+    let pool = &state.yral_redis_store_dragonfly;
+
+    let mut assignments = Vec::with_capacity(EXPERIMENTS.len());
+    for experiment in EXPERIMENTS {
+        let enrolled = experiment.enabled.get_for(pool, &principal).await;
+        let variant = if enrolled {
+            assign(pool, experiment, &principal).await?
+        } else {
+            CONTROL
+        };
+        assignments.push(ExperimentAssignment {
+            experiment: experiment.name.to_string(),
+            variant: variant.to_string(),
+            enrolled,
+        });
+    }
+
+    Ok(Json(assignments))
+}
+
+pub fn experiments_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(get_assignments))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_variant() {
+        for experiment in EXPERIMENTS {
+            let total: u32 = experiment.variants.iter().map(|(_, w)| *w as u32).sum();
+            assert_eq!(total, 100, "{} weights", experiment.name);
+            assert_eq!(experiment.variants[0].0, CONTROL);
+        }
+
+        let principals: Vec<Principal> = (0..200u8)
+            .map(|i| Principal::from_slice(&[i; 29]))
+            .collect();
+        for (variant, _) in REWARD_MULTIPLIER.variants {
+            assert!(principals
+                .iter()
+                .any(|p| REWARD_MULTIPLIER.bucket_variant(p) == *variant));
+        }
+        assert_eq!(
+            REWARD_MULTIPLIER.bucket_variant(&principals[0]),
+            REWARD_MULTIPLIER.bucket_variant(&principals[0])
+        );
+        assert_eq!(REWARD_MULTIPLIER.declared_variant("boost_3x"), None);
+    }
+}
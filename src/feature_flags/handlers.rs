@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use super::{
+    check_known_value, delete_flag as remove_flag, is_valid_name, load_all_flags, load_flag,
+    store_flag, FeatureFlag, DEFAULT_CACHE_TTL_SECS, KNOWN_FLAGS,
+};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FlagEntry {
+    pub name: String,
+    /// Whether code reads this flag
+    pub declared: bool,
+    /// Stored record; absent when the flag is unset and code uses its default
+    pub flag: Option<FeatureFlag>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PutFlagRequest {
+    pub value: serde_json::Value,
+    /// 0-100; omit to apply the value to everyone
+    pub rollout_percentage: Option<u8>,
+    /// Defaults to 30 seconds
+    pub cache_ttl_secs: Option<u64>,
+    pub description: Option<String>,
+}
+
+/// Stored and declared feature flags
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "feature_flags",
+    responses(
+        (status = 200, description = "Feature flags", body = Vec<FlagEntry>),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn list_flags(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<FlagEntry>>, ApiError> {
+    require_operator(&headers)?;
+
+    let mut stored = load_all_flags(&state.yral_redis_store_dragonfly).await?;
+    let mut entries: Vec<FlagEntry> = KNOWN_FLAGS
+        .iter()
+        .map(|name| FlagEntry {
+            name: name.to_string(),
+            declared: true,
+            flag: stored
+                .iter()
+                .position(|flag| flag.name == *name)
+                .map(|index| stored.remove(index)),
+        })
+        .collect();
+    entries.extend(stored.into_iter().map(|flag| FlagEntry {
+        name: flag.name.clone(),
+        declared: false,
+        flag: Some(flag),
+    }));
+
+    Ok(Json(entries))
+}
+
+/// One stored feature flag
+#[utoipa::path(
+    get,
+    path = "/{name}",
+    params(("name" = String, Path, description = "Flag name")),
+    tag = "feature_flags",
+    responses(
+        (status = 200, description = "Feature flag", body = FeatureFlag),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Flag is unset", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_flag(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<FeatureFlag>, ApiError> {
+    require_operator(&headers)?;
+
+    load_flag(&state.yral_redis_store_dragonfly, &name)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Feature flag {name} is not set")))
+}
+
+/// Create or replace a feature flag; applies on every instance within the
+/// previous cache TTL
+#[utoipa::path(
+    put,
+    path = "/{name}",
+    params(("name" = String, Path, description = "Flag name")),
+    request_body = PutFlagRequest,
+    tag = "feature_flags",
+    responses(
+        (status = 200, description = "Stored flag", body = FeatureFlag),
+        (status = 400, description = "Invalid flag", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn put_flag(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<PutFlagRequest>,
+) -> Result<Json<FeatureFlag>, ApiError> {
+    require_operator(&headers)?;
+
+    let flag = FeatureFlag {
+        name,
+        value: request.value,
+        rollout_percentage: request.rollout_percentage,
+        cache_ttl_secs: request.cache_ttl_secs.unwrap_or(DEFAULT_CACHE_TTL_SECS),
+        description: request.description,
+        updated_at: chrono::Utc::now().timestamp(),
+    };
+    flag.validate().map_err(ApiError::InvalidRequest)?;
+    check_known_value(&flag.name, &flag.value).map_err(ApiError::InvalidRequest)?;
+
+    store_flag(&state.yral_redis_store_dragonfly, &flag).await?;
+    log::warn!("Feature flag updated: {flag:?}");
+
+    Ok(Json(flag))
+}
+
+/// Unset a feature flag so code falls back to its default
+#[utoipa::path(
+    delete,
+    path = "/{name}",
+    params(("name" = String, Path, description = "Flag name")),
+    tag = "feature_flags",
+    responses(
+        (status = 200, description = "Flag removed"),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Flag is unset", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn delete_flag(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Result<(), ApiError> {
+    require_operator(&headers)?;
+
+    if !is_valid_name(&name) || !remove_flag(&state.yral_redis_store_dragonfly, &name).await? {
+        return Err(ApiError::NotFound(format!(
+            "Feature flag {name} is not set"
+        )));
+    }
+    log::warn!("Feature flag removed: {name}");
+
+    Ok(())
+}
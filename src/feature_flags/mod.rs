@@ -0,0 +1,345 @@
+//! Runtime feature flags shared across modules.
+//!
+//! Flags are JSON records in one Dragonfly hash, so every instance sees the
+//! same values. Code declares each flag once as a typed [`Flag`] constant
+//! with the default used when the flag is unset, malformed or Redis is
+//! unreachable. Reads are cached in-process for the flag's own TTL.
+//!
+//! A flag with a `rollout_percentage` only applies to principals whose hash
+//! (salted with the flag name) falls inside the percentage; everyone else
+//! gets the default. Callers without a principal always get the default
+//! while a rollout is in progress.
+
+pub mod handlers;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use candid::Principal;
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    app_state::AppState, events::nsfw::ensemble::NsfwEnsembleConfig, experiments,
+    qstash::dedup_config::AutoBlockPolicy, yral_auth::dragonfly::DragonflyPool,
+};
+
+const FLAGS_KEY: &str = "offchain:feature_flags";
+const DEFAULT_CACHE_TTL_SECS: u64 = 30;
+const MAX_CACHE_TTL_SECS: u64 = 3600;
+
+/// Watch events with a publisher principal resolve the publisher's canister
+/// before updating view details (the v3 path); off sends them straight to
+/// the user post service like legacy payloads. Rolled out by viewer.
+pub const WATCH_HISTORY_V3: Flag<bool> = Flag::new("watch_history_v3", true);
+
+/// Which duplicates stop an upload before NSFW processing. Rolled out by
+/// publisher.
+pub const DEDUP_AUTO_BLOCK: Flag<AutoBlockPolicy> =
+    Flag::new("dedup_auto_block", AutoBlockPolicy::Never);
+
+/// Model weights, threshold and disagreement band of the NSFW v2 ensemble.
+/// Rolled out by publisher.
+pub const NSFW_ENSEMBLE: Flag<NsfwEnsembleConfig> =
+    Flag::new("nsfw_ensemble", NsfwEnsembleConfig::DEFAULT);
+
+/// Flags declared in code, listed by the admin API even when unset
+pub static KNOWN_FLAGS: &[&str] = &[
+    WATCH_HISTORY_V3.name,
+    DEDUP_AUTO_BLOCK.name,
+    NSFW_ENSEMBLE.name,
+    experiments::REWARD_MULTIPLIER.enabled.name,
+    experiments::REWARD_NOTIFICATION_COPY.enabled.name,
+];
+
+/// Rejects values a declared flag could not be read as
+fn check_known_value(name: &str, value: &serde_json::Value) -> Result<(), String> {
+    let result = match name {
+        name if name == WATCH_HISTORY_V3.name
+            || experiments::EXPERIMENTS
+                .iter()
+                .any(|experiment| experiment.enabled.name == name) =>
+        {
+            serde_json::from_value::<bool>(value.clone())
+                .map(drop)
+                .map_err(|e| e.to_string())
+        }
+        name if name == DEDUP_AUTO_BLOCK.name => {
+            serde_json::from_value::<AutoBlockPolicy>(value.clone())
+                .map(drop)
+                .map_err(|e| e.to_string())
+        }
+        name if name == NSFW_ENSEMBLE.name => {
+            serde_json::from_value::<NsfwEnsembleConfig>(value.clone())
+                .map_err(|e| e.to_string())
+                .and_then(|config| config.validate())
+        }
+        _ => Ok(()),
+    };
+    result.map_err(|e| format!("Invalid value for {name}: {e}"))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FeatureFlag {
+    pub name: String,
+    /// Any JSON value; each flag's declaration decides how it is read
+    pub value: serde_json::Value,
+    /// 0-100; unset applies the value to everyone
+    pub rollout_percentage: Option<u8>,
+    /// How long instances may serve a cached copy
+    pub cache_ttl_secs: u64,
+    pub description: Option<String>,
+    /// Unix timestamp in seconds
+    pub updated_at: i64,
+}
+
+impl FeatureFlag {
+    fn validate(&self) -> Result<(), String> {
+        if !is_valid_name(&self.name) {
+            return Err("name must be 1-64 characters of a-z, 0-9 and _".to_string());
+        }
+        if self.rollout_percentage.is_some_and(|pct| pct > 100) {
+            return Err("rollout_percentage must be between 0 and 100".to_string());
+        }
+        if self.cache_ttl_secs > MAX_CACHE_TTL_SECS {
+            return Err(format!(
+                "cache_ttl_secs must be at most {MAX_CACHE_TTL_SECS}"
+            ));
+        }
+        Ok(())
+    }
+
+    fn applies_to(&self, principal: Option<&Principal>) -> bool {
+        match (self.rollout_percentage, principal) {
+            (None, _) => true,
+            (Some(pct), _) if pct >= 100 => true,
+            (Some(pct), Some(principal)) => rollout_bucket(&self.name, principal) < pct,
+            (Some(_), None) => false,
+        }
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit() || byte == b'_')
+}
+
+/// Stable bucket in 0..100 for a principal, salted with the flag name so
+/// each flag rolls out to a different cohort
+pub(crate) fn rollout_bucket(flag: &str, principal: &Principal) -> u8 {
+    let digest = Sha256::new()
+        .chain_update(flag.as_bytes())
+        .chain_update(b":")
+        .chain_update(principal.as_slice())
+        .finalize();
+    let prefix = u64::from_be_bytes(digest[..8].try_into().expect("sha256 is 32 bytes"));
+    (prefix % 100) as u8
+}
+
+/// A flag declared in code, read as `T`
+pub struct Flag<T> {
+    pub name: &'static str,
+    pub default: T,
+}
+
+impl<T> Flag<T> {
+    pub const fn new(name: &'static str, default: T) -> Self {
+        Self { name, default }
+    }
+}
+
+impl<T: DeserializeOwned + Clone> Flag<T> {
+    fn default_value(&self) -> T {
+        self.default.clone()
+    }
+
+    fn resolve(&self, flag: Option<FeatureFlag>, principal: Option<&Principal>) -> T {
+        let Some(flag) = flag.filter(|flag| flag.applies_to(principal)) else {
+            return self.default_value();
+        };
+        serde_json::from_value(flag.value).unwrap_or_else(|e| {
+            log::warn!("Feature flag {} has an invalid value: {e}", self.name);
+            self.default_value()
+        })
+    }
+
+    /// Value for callers without a principal
+    pub async fn get(&self, pool: &Arc<DragonflyPool>) -> T {
+        self.resolve(lookup(pool, self.name).await, None)
+    }
+
+    /// Value for `principal`, honouring the rollout percentage
+    pub async fn get_for(&self, pool: &Arc<DragonflyPool>, principal: &Principal) -> T {
+        self.resolve(lookup(pool, self.name).await, Some(principal))
+    }
+}
+
+struct CacheEntry {
+    fetched_at: Instant,
+    ttl: Duration,
+    flag: Option<FeatureFlag>,
+}
+
+static CACHE: Lazy<RwLock<HashMap<String, CacheEntry>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+fn cached(name: &str) -> Option<Option<FeatureFlag>> {
+    let cache = CACHE.read().ok()?;
+    cache
+        .get(name)
+        .filter(|entry| entry.fetched_at.elapsed() < entry.ttl)
+        .map(|entry| entry.flag.clone())
+}
+
+fn set_cached(name: &str, flag: Option<FeatureFlag>) {
+    let ttl = Duration::from_secs(
+        flag.as_ref()
+            .map_or(DEFAULT_CACHE_TTL_SECS, |flag| flag.cache_ttl_secs),
+    );
+    if let Ok(mut cache) = CACHE.write() {
+        cache.insert(
+            name.to_string(),
+            CacheEntry {
+                fetched_at: Instant::now(),
+                ttl,
+                flag,
+            },
+        );
+    }
+}
+
+/// Fails open: on a Redis error the last known record (or none) is used
+async fn lookup(pool: &Arc<DragonflyPool>, name: &str) -> Option<FeatureFlag> {
+    if let Some(flag) = cached(name) {
+        return flag;
+    }
+
+    match load_flag(pool, name).await {
+        Ok(flag) => {
+            set_cached(name, flag.clone());
+            flag
+        }
+        Err(e) => {
+            log::warn!("Failed to load feature flag {name}, using last known value: {e}");
+            CACHE
+                .read()
+                .ok()
+                .and_then(|cache| cache.get(name).and_then(|entry| entry.flag.clone()))
+        }
+    }
+}
+
+pub async fn load_flag(pool: &Arc<DragonflyPool>, name: &str) -> Result<Option<FeatureFlag>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.hget(FLAGS_KEY, name).await?;
+    payload
+        .map(|payload| serde_json::from_str(&payload).map_err(Into::into))
+        .transpose()
+}
+
+pub async fn load_all_flags(pool: &Arc<DragonflyPool>) -> Result<Vec<FeatureFlag>> {
+    let mut conn = pool.get().await?;
+    let payloads: HashMap<String, String> = conn.hgetall(FLAGS_KEY).await?;
+    let mut flags: Vec<FeatureFlag> = payloads
+        .values()
+        .filter_map(|payload| serde_json::from_str(payload).ok())
+        .collect();
+    flags.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(flags)
+}
+
+async fn store_flag(pool: &Arc<DragonflyPool>, flag: &FeatureFlag) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = conn
+        .hset(FLAGS_KEY, &flag.name, serde_json::to_string(flag)?)
+        .await?;
+    set_cached(&flag.name, Some(flag.clone()));
+    Ok(())
+}
+
+/// Returns whether the flag existed
+async fn delete_flag(pool: &Arc<DragonflyPool>, name: &str) -> Result<bool> {
+    let mut conn = pool.get().await?;
+    let removed: u32 = conn.hdel(FLAGS_KEY, name).await?;
+    set_cached(name, None);
+    Ok(removed > 0)
+}
+
+pub fn feature_flags_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(handlers::list_flags))
+        .routes(routes!(
+            handlers::get_flag,
+            handlers::put_flag,
+            handlers::delete_flag
+        ))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag(value: serde_json::Value, rollout_percentage: Option<u8>) -> FeatureFlag {
+        FeatureFlag {
+            name: WATCH_HISTORY_V3.name.to_string(),
+            value,
+            rollout_percentage,
+            cache_ttl_secs: DEFAULT_CACHE_TTL_SECS,
+            description: None,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_resolve_and_rollout() {
+        let principal = Principal::from_slice(&[7; 29]);
+        assert!(WATCH_HISTORY_V3.resolve(None, Some(&principal)));
+        assert!(!WATCH_HISTORY_V3.resolve(Some(flag(false.into(), None)), None));
+        // Malformed values fall back to the default
+        assert!(WATCH_HISTORY_V3.resolve(Some(flag("nope".into(), None)), None));
+
+        // A partial rollout never applies without a principal
+        assert!(!WATCH_HISTORY_V3.resolve(Some(flag(false.into(), Some(100))), None));
+        assert!(WATCH_HISTORY_V3.resolve(Some(flag(false.into(), Some(0))), Some(&principal)));
+        assert!(WATCH_HISTORY_V3.resolve(Some(flag(false.into(), Some(50))), None));
+
+        let bucket = rollout_bucket(WATCH_HISTORY_V3.name, &principal);
+        assert_eq!(bucket, rollout_bucket(WATCH_HISTORY_V3.name, &principal));
+        assert!(bucket < 100);
+        assert!(flag(false.into(), Some(bucket + 1)).applies_to(Some(&principal)));
+        assert!(!flag(false.into(), Some(bucket)).applies_to(Some(&principal)));
+
+        assert_eq!(
+            DEDUP_AUTO_BLOCK.resolve(
+                Some(FeatureFlag {
+                    name: DEDUP_AUTO_BLOCK.name.to_string(),
+                    ..flag("exact_only".into(), None)
+                }),
+                None
+            ),
+            AutoBlockPolicy::ExactOnly
+        );
+        assert!(flag(true.into(), Some(101)).validate().is_err());
+        assert!(check_known_value(DEDUP_AUTO_BLOCK.name, &"sometimes".into()).is_err());
+        assert!(check_known_value(
+            NSFW_ENSEMBLE.name,
+            &serde_json::json!({ "embedding_weight": -1.0 })
+        )
+        .is_err());
+        assert!(
+            check_known_value(NSFW_ENSEMBLE.name, &serde_json::json!({ "threshold": 0.6 })).is_ok()
+        );
+        assert!(!is_valid_name("Bad-Name"));
+    }
+}
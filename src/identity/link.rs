@@ -0,0 +1,658 @@
+//! Linking an anonymous principal into the authenticated one it signed in as.
+//!
+//! Both delegated identities are presented together, which proves the caller
+//! holds each of them. Everything this service keeps per principal is then
+//! moved across, one item at a time: a failed item is recorded and the rest
+//! still run, so the audit record says exactly what was carried over. A
+//! link with failed items can be retried into the same authenticated
+//! principal, which reruns only the failed items under the original policy;
+//! once every item has gone through, the principal can't be linked again.
+//!
+//! Watch and success histories belong to the ML feed cache service and the
+//! follow graph to the user info canister, neither of which this service
+//! can rewrite, so they are not part of a link.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use candid::Principal;
+use num_bigint::BigUint;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+use videogen_common::TokenType;
+use yral_canisters_client::user_info_service::{Result8, SessionType, UserInfoService};
+
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    bookmarks::merge_bookmarks,
+    canister::agent_pool,
+    consts::USER_INFO_SERVICE_CANISTER_ID,
+    error::{ApiError, ApiErrorBody},
+    events::{event::Event, warehouse_events::WarehouseEvent},
+    leaderboard::{redis_ops::LeaderboardRedis, types::ScoreOperation},
+    rewards::history::HistoryTracker,
+    types::DelegatedIdentityWire,
+    user::utils::get_agent_from_delegated_identity_wire,
+    utils::delegated_identity::{
+        get_user_info_from_delegated_identity_wire, verify_delegated_identity_request,
+    },
+    videogen::{
+        token_operations::{add_token_balance, deduct_token_balance, load_token_balance},
+        utils::get_hon_worker_jwt_token,
+    },
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+pub const ACCOUNTS_LINKED_EVENT: &str = "accounts_linked";
+const AUDIT_KEY_PREFIX: &str = "offchain:identity:link:audit";
+const LOCK_KEY_PREFIX: &str = "offchain:identity:link:lock";
+/// Long enough for every balance transfer to go through
+const LOCK_TTL_SECS: u64 = 300;
+
+/// How the anonymous principal's leaderboard score combines with the
+/// authenticated one's in the current tournament
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScorePolicy {
+    #[default]
+    Sum,
+    Max,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BalancePolicy {
+    /// Move the whole balance to the authenticated principal
+    #[default]
+    Transfer,
+    /// Leave balances where they are
+    Keep,
+}
+
+/// Conflict policies. History and bookmarks always merge: history appends
+/// the anonymous records behind the authenticated ones, and a video
+/// bookmarked by both keeps the earlier timestamp.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+pub struct LinkPolicy {
+    #[serde(default)]
+    pub leaderboard: ScorePolicy,
+    #[serde(default)]
+    pub balances: BalancePolicy,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct LinkAccountsRequest {
+    pub anonymous_identity: DelegatedIdentityWire,
+    pub authenticated_identity: DelegatedIdentityWire,
+    #[serde(default)]
+    pub policy: LinkPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkItem {
+    RewardsHistory,
+    Bookmarks,
+    LeaderboardScore,
+    SatsBalance,
+    DolrBalance,
+}
+
+impl LinkItem {
+    const ALL: [LinkItem; 5] = [
+        LinkItem::RewardsHistory,
+        LinkItem::Bookmarks,
+        LinkItem::LeaderboardScore,
+        LinkItem::SatsBalance,
+        LinkItem::DolrBalance,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeState {
+    Merged,
+    /// Nothing to move, or the policy said to leave it
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MergeOutcome {
+    pub item: LinkItem,
+    pub state: MergeState,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LinkAuditRecord {
+    #[schema(value_type = String)]
+    pub anonymous_principal: Principal,
+    #[schema(value_type = String)]
+    pub authenticated_principal: Principal,
+    pub policy: LinkPolicy,
+    pub outcomes: Vec<MergeOutcome>,
+    /// Unix timestamp in seconds of the latest attempt
+    pub linked_at: i64,
+    /// 1 for a link that went through first time
+    #[serde(default = "first_attempt")]
+    pub attempts: u32,
+}
+
+fn first_attempt() -> u32 {
+    1
+}
+
+impl LinkAuditRecord {
+    pub fn is_complete(&self) -> bool {
+        self.outcomes
+            .iter()
+            .all(|outcome| outcome.state != MergeState::Failed)
+    }
+
+    /// Items a retry has to run again
+    fn failed_items(&self) -> Vec<LinkItem> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.state == MergeState::Failed)
+            .map(|outcome| outcome.item)
+            .collect()
+    }
+
+    /// Replaces the outcomes of retried items
+    fn apply_retry(&mut self, retried: Vec<MergeOutcome>) {
+        for retry in retried {
+            match self
+                .outcomes
+                .iter_mut()
+                .find(|outcome| outcome.item == retry.item)
+            {
+                Some(outcome) => *outcome = retry,
+                None => self.outcomes.push(retry),
+            }
+        }
+    }
+}
+
+fn audit_key(anonymous: &Principal) -> String {
+    format!("{AUDIT_KEY_PREFIX}:{}", anonymous.to_text())
+}
+
+fn lock_key(anonymous: &Principal) -> String {
+    format!("{LOCK_KEY_PREFIX}:{}", anonymous.to_text())
+}
+
+async fn load_audit(
+    pool: &DragonflyPool,
+    anonymous: &Principal,
+) -> Result<Option<LinkAuditRecord>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.get(audit_key(anonymous)).await?;
+    payload
+        .map(|payload| serde_json::from_str(&payload).map_err(Into::into))
+        .transpose()
+}
+
+async fn release_lock(pool: &DragonflyPool, anonymous: &Principal) {
+    let result: Result<()> = async {
+        let mut conn = pool.get().await?;
+        let _: () = conn.del(lock_key(anonymous)).await?;
+        Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        log::warn!("Failed to release link lock on {anonymous}: {e:?}");
+    }
+}
+
+async fn is_registered(state: &AppState, principal: Principal) -> Result<bool, ApiError> {
+    let user_info_service = UserInfoService(*USER_INFO_SERVICE_CANISTER_ID, &state.agent);
+    let result = agent_pool::query(
+        *USER_INFO_SERVICE_CANISTER_ID,
+        "get_user_session_type",
+        || user_info_service.get_user_session_type(principal),
+    )
+    .await?;
+
+    Ok(match result {
+        Result8::Ok(session_type) => matches!(session_type, SessionType::RegisteredSession),
+        // Anonymous principals that never did anything aren't in the service
+        Result8::Err(e) if e.contains("User not found") => false,
+        Result8::Err(e) => return Err(ApiError::Canister(e)),
+    })
+}
+
+fn outcome(item: LinkItem, result: Result<(MergeState, String)>) -> MergeOutcome {
+    let (state, detail) = result.unwrap_or_else(|e| {
+        log::error!("Account link failed to merge {item:?}: {e:?}");
+        (MergeState::Failed, e.to_string())
+    });
+    MergeOutcome {
+        item,
+        state,
+        detail,
+    }
+}
+
+/// Applies `policy` to the current tournament's scores
+async fn merge_leaderboard_score(
+    state: &AppState,
+    anonymous: Principal,
+    authenticated: Principal,
+    policy: ScorePolicy,
+) -> Result<(MergeState, String)> {
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+    let Some(tournament_id) = redis.get_current_tournament().await? else {
+        return Ok((MergeState::Skipped, "No tournament running".to_string()));
+    };
+    let anonymous_score = redis
+        .get_user_score(&tournament_id, anonymous)
+        .await?
+        .unwrap_or(0.0);
+    if anonymous_score <= 0.0 {
+        return Ok((MergeState::Skipped, "No score to merge".to_string()));
+    }
+    let authenticated_score = redis
+        .get_user_score(&tournament_id, authenticated)
+        .await?
+        .unwrap_or(0.0);
+
+    let increment = match policy {
+        ScorePolicy::Sum => anonymous_score,
+        ScorePolicy::Max => (anonymous_score - authenticated_score).max(0.0),
+    };
+    let new_score = if increment > 0.0 {
+        redis
+            .update_user_score(
+                &tournament_id,
+                authenticated,
+                increment,
+                &ScoreOperation::Increment,
+            )
+            .await?
+    } else {
+        authenticated_score
+    };
+    redis
+        .remove_user_from_leaderboard(&tournament_id, anonymous)
+        .await?;
+
+    Ok((
+        MergeState::Merged,
+        format!("{tournament_id}: {authenticated_score} + {anonymous_score} -> {new_score}"),
+    ))
+}
+
+/// Deducts the anonymous balance, then credits it to the authenticated
+/// principal, refunding the anonymous one if the credit fails
+async fn transfer_balance(
+    state: &AppState,
+    request: &LinkAccountsRequest,
+    anonymous: Principal,
+    authenticated: Principal,
+    token_type: TokenType,
+) -> Result<(MergeState, String)> {
+    let (jwt_token, admin_agent, user_agent) = match token_type {
+        TokenType::Sats => {
+            let jwt_token = get_hon_worker_jwt_token()
+                .map_err(|(_, e)| anyhow::anyhow!("Failed to get JWT token: {:?}", e.0))?;
+            (Some(jwt_token), None, None)
+        }
+        _ => (
+            None,
+            Some(state.agent.clone()),
+            Some(get_agent_from_delegated_identity_wire(&request.anonymous_identity).await?),
+        ),
+    };
+
+    let balance = load_token_balance(
+        anonymous,
+        &token_type,
+        jwt_token.clone(),
+        admin_agent.clone(),
+        user_agent.clone(),
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+    if balance == BigUint::ZERO {
+        return Ok((MergeState::Skipped, "No balance to move".to_string()));
+    }
+    let amount = u64::try_from(&balance)
+        .map_err(|_| anyhow::anyhow!("Balance {balance} does not fit a single transfer"))?;
+
+    deduct_token_balance(
+        anonymous,
+        amount,
+        &token_type,
+        jwt_token.clone(),
+        admin_agent.clone(),
+        user_agent,
+    )
+    .await
+    .map_err(|e| anyhow::anyhow!("Failed to deduct from {anonymous}: {e:?}"))?;
+
+    if let Err(e) = add_token_balance(
+        authenticated,
+        amount,
+        &token_type,
+        jwt_token.clone(),
+        admin_agent.clone(),
+    )
+    .await
+    {
+        let refund =
+            add_token_balance(anonymous, amount, &token_type, jwt_token, admin_agent).await;
+        return Err(anyhow::anyhow!(
+            "Failed to credit {authenticated}: {e:?}; refund to {anonymous}: {}",
+            match refund {
+                Ok(()) => "done".to_string(),
+                Err(e) => format!("failed: {e:?}"),
+            }
+        ));
+    }
+
+    Ok((MergeState::Merged, format!("Moved {amount} {token_type:?}")))
+}
+
+async fn merge_item(
+    state: &AppState,
+    request: &LinkAccountsRequest,
+    policy: LinkPolicy,
+    anonymous: Principal,
+    authenticated: Principal,
+    item: LinkItem,
+) -> MergeOutcome {
+    let result = match item {
+        LinkItem::RewardsHistory => {
+            HistoryTracker::new(state.rewards_module.dragonfly_pool.clone())
+                .merge_user_history(&anonymous, &authenticated)
+                .await
+                .map(|moved| match moved {
+                    0 => (MergeState::Skipped, "No history".to_string()),
+                    moved => (MergeState::Merged, format!("Moved {moved} records")),
+                })
+        }
+        LinkItem::Bookmarks => merge_bookmarks(
+            &state.yral_redis_store_dragonfly,
+            &anonymous,
+            &authenticated,
+        )
+        .await
+        .map(|moved| match moved {
+            0 => (MergeState::Skipped, "No bookmarks".to_string()),
+            moved => (MergeState::Merged, format!("Merged {moved} bookmarks")),
+        }),
+        LinkItem::LeaderboardScore => {
+            merge_leaderboard_score(state, anonymous, authenticated, policy.leaderboard).await
+        }
+        LinkItem::SatsBalance | LinkItem::DolrBalance => {
+            let token_type = match item {
+                LinkItem::SatsBalance => TokenType::Sats,
+                _ => TokenType::Dolr,
+            };
+            match policy.balances {
+                BalancePolicy::Keep => Ok((MergeState::Skipped, "Kept by policy".to_string())),
+                BalancePolicy::Transfer => {
+                    transfer_balance(state, request, anonymous, authenticated, token_type).await
+                }
+            }
+        }
+    };
+    outcome(item, result)
+}
+
+async fn merge_items(
+    state: &AppState,
+    request: &LinkAccountsRequest,
+    policy: LinkPolicy,
+    anonymous: Principal,
+    authenticated: Principal,
+    items: &[LinkItem],
+) -> Vec<MergeOutcome> {
+    let mut outcomes = Vec::with_capacity(items.len());
+    for &item in items {
+        outcomes.push(merge_item(state, request, policy, anonymous, authenticated, item).await);
+    }
+    outcomes
+}
+
+/// Merge an anonymous principal's data into the authenticated principal
+/// it signed in as
+#[utoipa::path(
+    post,
+    path = "/link",
+    request_body = LinkAccountsRequest,
+    tag = "identity",
+    responses(
+        (status = 200, description = "Audit record of the link; check each outcome", body = LinkAuditRecord),
+        (status = 400, description = "Principals can't be linked", body = ApiErrorBody),
+        (status = 401, description = "Invalid delegated identity", body = ApiErrorBody),
+        (status = 409, description = "Anonymous principal already linked, linked elsewhere or being linked", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers, request))]
+pub async fn link_accounts(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<LinkAccountsRequest>,
+) -> Result<Json<LinkAuditRecord>, ApiError> {
+    let anonymous =
+        get_user_info_from_delegated_identity_wire(&state, request.anonymous_identity.clone())
+            .await
+            .map_err(|e| ApiError::Unauthorized(format!("Invalid anonymous identity: {e}")))?
+            .user_principal;
+    let authenticated =
+        verify_delegated_identity_request(&state, request.authenticated_identity.clone(), &headers)
+            .await
+            .map_err(|e| ApiError::Unauthorized(format!("Invalid authenticated identity: {e}")))?
+            .user_principal;
+    crate::middleware::set_user_context(authenticated);
+
+    if anonymous == authenticated {
+        return Err(ApiError::InvalidRequest(
+            "Cannot link a principal to itself".to_string(),
+        ));
+    }
+    if !is_registered(&state, authenticated).await? {
+        return Err(ApiError::InvalidRequest(format!(
+            "{authenticated} is not a registered user"
+        )));
+    }
+    if is_registered(&state, anonymous).await? {
+        return Err(ApiError::InvalidRequest(format!(
+            "{anonymous} is a registered user and can't be merged away"
+        )));
+    }
+
+    let pool = &state.yral_redis_store_dragonfly;
+    let previous = load_audit(pool, &anonymous).await?;
+    if let Some(record) = &previous {
+        if record.authenticated_principal != authenticated || record.is_complete() {
+            return Err(ApiError::Conflict(format!(
+                "{anonymous} was already linked to {}",
+                record.authenticated_principal
+            )));
+        }
+    }
+
+    let mut conn = pool.get().await?;
+    let locked: bool = redis::cmd("SET")
+        .arg(lock_key(&anonymous))
+        .arg(authenticated.to_text())
+        .arg("NX")
+        .arg("EX")
+        .arg(LOCK_TTL_SECS)
+        .query_async::<Option<String>>(&mut conn)
+        .await?
+        .is_some();
+    if !locked {
+        return Err(ApiError::Conflict(format!(
+            "{anonymous} is already being linked"
+        )));
+    }
+    drop(conn);
+
+    // Read again under the lock, so two retries can't both rerun an item
+    let record = match load_audit(pool, &anonymous).await {
+        Ok(Some(mut record))
+            if record.authenticated_principal == authenticated && !record.is_complete() =>
+        {
+            let retried = merge_items(
+                &state,
+                &request,
+                record.policy,
+                anonymous,
+                authenticated,
+                &record.failed_items(),
+            )
+            .await;
+            record.apply_retry(retried);
+            record.attempts += 1;
+            record.linked_at = chrono::Utc::now().timestamp();
+            record
+        }
+        Ok(None) if previous.is_none() => LinkAuditRecord {
+            anonymous_principal: anonymous,
+            authenticated_principal: authenticated,
+            policy: request.policy,
+            outcomes: merge_items(
+                &state,
+                &request,
+                request.policy,
+                anonymous,
+                authenticated,
+                &LinkItem::ALL,
+            )
+            .await,
+            linked_at: chrono::Utc::now().timestamp(),
+            attempts: 1,
+        },
+        result => {
+            release_lock(pool, &anonymous).await;
+            result?;
+            return Err(ApiError::Conflict(format!(
+                "{anonymous} was linked concurrently"
+            )));
+        }
+    };
+
+    // Kept forever: once complete it is what stops a second link of the
+    // same principal, and until then it says which items a retry reruns
+    let mut conn = pool.get().await?;
+    let _: () = conn
+        .set(audit_key(&anonymous), serde_json::to_string(&record)?)
+        .await?;
+    let _: () = conn.del(lock_key(&anonymous)).await?;
+
+    log::info!(
+        "Linked {anonymous} into {authenticated} (attempt {}), complete: {}",
+        record.attempts,
+        record.is_complete()
+    );
+    let params = serde_json::json!({
+        "anonymous_principal": anonymous.to_text(),
+        "authenticated_principal": authenticated.to_text(),
+        "complete": record.is_complete(),
+        "attempt": record.attempts,
+        "outcomes": record.outcomes,
+    });
+    Event::new(WarehouseEvent {
+        event: ACCOUNTS_LINKED_EVENT.to_string(),
+        params: params.to_string(),
+    })
+    .stream_to_bigquery(&state);
+
+    Ok(Json(record))
+}
+
+/// Audit record of the link that merged away an anonymous principal
+#[utoipa::path(
+    get,
+    path = "/link/{anonymous_principal}",
+    params(("anonymous_principal" = String, Path, description = "Anonymous principal")),
+    tag = "identity",
+    responses(
+        (status = 200, description = "Audit record", body = LinkAuditRecord),
+        (status = 400, description = "Invalid principal", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Principal was never linked", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_link_audit(
+    State(state): State<Arc<AppState>>,
+    Path(anonymous_principal): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<LinkAuditRecord>, ApiError> {
+    require_operator(&headers)?;
+    let anonymous = Principal::from_text(&anonymous_principal)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid principal: {e}")))?;
+
+    load_audit(&state.yral_redis_store_dragonfly, &anonymous)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("{anonymous} was never linked")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_policy_and_audit() {
+        let policy: LinkPolicy = serde_json::from_str(r#"{"leaderboard":"max"}"#).unwrap();
+        assert_eq!(policy.leaderboard, ScorePolicy::Max);
+        assert_eq!(policy.balances, BalancePolicy::Transfer);
+
+        let mut record = LinkAuditRecord {
+            anonymous_principal: Principal::anonymous(),
+            authenticated_principal: Principal::from_slice(&[1; 29]),
+            policy,
+            outcomes: vec![
+                outcome(LinkItem::Bookmarks, Ok((MergeState::Merged, String::new()))),
+                outcome(
+                    LinkItem::DolrBalance,
+                    Ok((MergeState::Skipped, String::new())),
+                ),
+            ],
+            linked_at: 0,
+            attempts: 1,
+        };
+        assert!(record.is_complete());
+
+        record.outcomes.push(outcome(
+            LinkItem::SatsBalance,
+            Err(anyhow::anyhow!("ledger down")),
+        ));
+        assert!(!record.is_complete());
+        assert_eq!(record.outcomes[2].detail, "ledger down");
+        assert_eq!(record.failed_items(), vec![LinkItem::SatsBalance]);
+
+        record.apply_retry(vec![outcome(
+            LinkItem::SatsBalance,
+            Ok((MergeState::Merged, "Moved 5 Sats".to_string())),
+        )]);
+        assert!(record.is_complete());
+        assert_eq!(record.outcomes.len(), 3);
+        assert_eq!(
+            audit_key(&Principal::anonymous()),
+            "offchain:identity:link:audit:2vxsx-fae"
+        );
+    }
+}
@@ -0,0 +1,16 @@
+//! Operations spanning more than one principal of the same person.
+
+pub mod link;
+
+use std::sync::Arc;
+
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::app_state::AppState;
+
+pub fn identity_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(link::link_accounts))
+        .routes(routes!(link::get_link_audit))
+        .with_state(state)
+}
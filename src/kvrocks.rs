@@ -1,23 +1,169 @@
 use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
 use redis::cluster::ClusterClient;
 use redis::cluster_async::ClusterConnection;
 use redis::AsyncCommands;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
+use std::marker::PhantomData;
 
 const KVROCKS_TLS_PORT: u16 = 6666;
 
+/// `VIDEO_HIDDEN` field prefix marking one active hide per reason
+const HIDE_HOLD_PREFIX: &str = "hold:";
+
+/// Drops one reason's hold and deletes the row once none is left, pointing
+/// `reason` at a remaining hold otherwise. Rows written before holds existed
+/// are cleared only by their own `reason`. Returns 1 if the row was deleted.
+static UNHIDE_SCRIPT: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+        local removed = redis.call('HDEL', KEYS[1], ARGV[1] .. ARGV[2])
+        for _, field in ipairs(redis.call('HKEYS', KEYS[1])) do
+            if string.sub(field, 1, string.len(ARGV[1])) == ARGV[1] then
+                redis.call('HSET', KEYS[1], 'reason', string.sub(field, string.len(ARGV[1]) + 1))
+                return 0
+            end
+        end
+        if removed == 1 or redis.call('HGET', KEYS[1], 'reason') == ARGV[2] then
+            return redis.call('DEL', KEYS[1])
+        end
+        return 0
+        "#,
+    )
+});
+
 pub mod keys {
     pub const VIDEO_NSFW: &str = "offchain:video_nsfw";
     pub const VIDEO_DELETED: &str = "offchain:video_deleted";
+    pub const VIDEO_HIDDEN: &str = "offchain:video_hidden";
     pub const VIDEO_UNIQUE_V2: &str = "offchain:video_unique_v2";
     pub const USER_UPLOADED_CONTENT_APPROVAL: &str = "offchain:user_uploaded_content_approval";
+    /// Sorted set of video ids awaiting moderation, scored by upload time (ms)
+    pub const PENDING_APPROVAL_QUEUE: &str = "offchain:moderation:pending_approval";
+    /// Set of video ids held for manual review by NSFW scoring; ingest keeps
+    /// them unapproved whatever AI detection says
+    pub const MANUAL_REVIEW_HOLDS: &str = "offchain:moderation:manual_review_holds";
+    /// Set once the queue has been reconciled against BigQuery at least once
+    pub const PENDING_APPROVAL_RECONCILED_AT: &str =
+        "offchain:moderation:pending_approval:reconciled_at";
     pub const BOT_UPLOADED_AI_CONTENT: &str = "offchain:bot_uploaded_ai_content";
     pub const VIDEO_DEDUP_STATUS: &str = "offchain:video_dedup_status";
     pub const VIDEOHASH_PHASH: &str = "offchain:videohash_phash";
     pub const VIDEOHASH_ORIGINAL: &str = "offchain:videohash_original";
+    pub const VIDEO_CONTENT_HASH: &str = "offchain:video_content_hash";
     pub const VIDEO_EMBEDDINGS: &str = "offchain:video_embeddings";
     pub const VIDEO_METADATA: &str = "offchain:metadata:video_details";
+    pub const DETECTOR_VERDICT_CACHE: &str = "offchain:detector_verdict_cache";
+    pub const VIDEO_THUMBNAILS: &str = "offchain:video_thumbnails";
+    pub const VIDEO_RENDITIONS: &str = "offchain:video_renditions";
+    pub const POST_ANALYTICS: &str = "offchain:post_analytics";
+    pub const REGION_BLOCKLIST: &str = "offchain:region_blocklist";
+    pub const VIDEO_AVAILABILITY: &str = "offchain:video_availability";
+    pub const DEDUP_EXPLANATION_FRAMES: &str = "offchain:dedup_explanation_frames";
+    pub const VIDEO_FRAME_SAMPLES: &str = "offchain:video_frame_samples";
+}
+
+/// How a table's values are laid out in kvrocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// One hash field per struct field, readable field by field from other services
+    Hash,
+    /// A single JSON string value
+    Json,
+}
+
+/// A namespaced, typed table: every row lives at `{prefix}:{id}`
+#[derive(Debug)]
+pub struct Table<T> {
+    prefix: &'static str,
+    encoding: Encoding,
+    ttl_secs: Option<u64>,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Table<T> {
+    pub const fn hash(prefix: &'static str) -> Self {
+        Self {
+            prefix,
+            encoding: Encoding::Hash,
+            ttl_secs: None,
+            _value: PhantomData,
+        }
+    }
+
+    pub const fn json(prefix: &'static str) -> Self {
+        Self {
+            prefix,
+            encoding: Encoding::Json,
+            ttl_secs: None,
+            _value: PhantomData,
+        }
+    }
+
+    /// Default expiry applied by [`KvrocksClient::put`]
+    pub const fn with_ttl(mut self, ttl_secs: u64) -> Self {
+        self.ttl_secs = Some(ttl_secs);
+        self
+    }
+
+    pub fn prefix(&self) -> &'static str {
+        self.prefix
+    }
+
+    pub fn key(&self, id: &str) -> String {
+        format!("{}:{}", self.prefix, id)
+    }
+
+    /// The row id of a key in this table, e.g. from a prefix scan
+    pub fn id_from_key<'a>(&self, key: &'a str) -> Option<&'a str> {
+        key.strip_prefix(self.prefix)?
+            .strip_prefix(':')
+            .filter(|id| !id.is_empty())
+    }
+}
+
+/// Typed tables for the namespaces in [`keys`]
+pub mod tables {
+    use super::*;
+
+    pub const VIDEO_NSFW: Table<VideoNsfw> = Table::hash(keys::VIDEO_NSFW);
+    pub const VIDEO_DELETED: Table<VideoDeleted> = Table::hash(keys::VIDEO_DELETED);
+    /// Feed services skip videos present here
+    pub const VIDEO_HIDDEN: Table<VideoHidden> = Table::hash(keys::VIDEO_HIDDEN);
+    pub const VIDEO_UNIQUE_V2: Table<VideoUniqueV2> = Table::hash(keys::VIDEO_UNIQUE_V2);
+    pub const USER_UPLOADED_CONTENT_APPROVAL: Table<UserUploadedContentApproval> =
+        Table::hash(keys::USER_UPLOADED_CONTENT_APPROVAL);
+    pub const BOT_UPLOADED_AI_CONTENT: Table<BotUploadedAiContent> =
+        Table::hash(keys::BOT_UPLOADED_AI_CONTENT);
+    pub const VIDEO_DEDUP_STATUS: Table<VideoDedupStatus> = Table::hash(keys::VIDEO_DEDUP_STATUS);
+    pub const VIDEOHASH_PHASH: Table<VideohashPhash> = Table::hash(keys::VIDEOHASH_PHASH);
+    pub const VIDEOHASH_ORIGINAL: Table<VideohashOriginal> = Table::hash(keys::VIDEOHASH_ORIGINAL);
+    pub const VIDEO_CONTENT_HASH: Table<VideoContentHash> = Table::hash(keys::VIDEO_CONTENT_HASH);
+    pub const VIDEO_EMBEDDINGS: Table<VideoEmbeddings> = Table::hash(keys::VIDEO_EMBEDDINGS);
+    pub const VIDEO_METADATA: Table<VideoMetadata> = Table::hash(keys::VIDEO_METADATA);
+    /// Rows are keyed `{detector}:{content_hash}`, see [`detector_verdict_id`]
+    pub const DETECTOR_VERDICT_CACHE: Table<CachedDetectorVerdict> =
+        Table::json(keys::DETECTOR_VERDICT_CACHE);
+    pub const VIDEO_THUMBNAILS: Table<VideoThumbnails> = Table::json(keys::VIDEO_THUMBNAILS);
+    pub const VIDEO_RENDITIONS: Table<VideoRenditions> = Table::json(keys::VIDEO_RENDITIONS);
+    pub const POST_ANALYTICS: Table<PostAnalytics> = Table::json(keys::POST_ANALYTICS);
+    pub const REGION_BLOCKLIST: Table<RegionBlocklist> = Table::json(keys::REGION_BLOCKLIST);
+    /// Last availability audit of a video, kept for 30 days
+    pub const VIDEO_AVAILABILITY: Table<VideoAvailability> =
+        Table::json(keys::VIDEO_AVAILABILITY).with_ttl(30 * 24 * 60 * 60);
+    /// Side-by-side frames of a duplicate and its match, keyed
+    /// `{video_id}:{duplicate_of}` and kept for 7 days
+    pub const DEDUP_EXPLANATION_FRAMES: Table<DedupExplanationFrames> =
+        Table::json(keys::DEDUP_EXPLANATION_FRAMES).with_ttl(7 * 24 * 60 * 60);
+    /// Where each frame in `yral-video-frames/{video_id}/` was taken from
+    pub const VIDEO_FRAME_SAMPLES: Table<VideoFrameSamples> =
+        Table::json(keys::VIDEO_FRAME_SAMPLES);
+
+    pub fn detector_verdict_id(detector: &str, content_hash: &str) -> String {
+        format!("{detector}:{content_hash}")
+    }
 }
 
 /// NSFW classification data for a video
@@ -54,6 +200,15 @@ pub struct VideohashOriginal {
     pub created_at: String,
 }
 
+/// Exact checksum of the uploaded file; equal only for byte-identical uploads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoContentHash {
+    pub video_id: String,
+    /// Hex SHA-256 of the file
+    pub sha256: String,
+    pub created_at: String,
+}
+
 /// Unique video marker (v2)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VideoUniqueV2 {
@@ -72,6 +227,28 @@ pub struct VideoDeleted {
     pub deleted_at: String,
 }
 
+/// Countries a video must not be served in, set by legal takedowns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionBlocklist {
+    pub video_id: String,
+    /// ISO 3166-1 alpha-2 codes, uppercase
+    pub blocked_countries: std::collections::BTreeSet<String>,
+    /// Legal request or ticket this takedown fulfils
+    pub reference: Option<String>,
+    pub updated_at: String,
+}
+
+/// Video temporarily hidden from feeds pending moderator review or its
+/// scheduled publish time. Written through `KvrocksClient::hide_video`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoHidden {
+    pub video_id: String,
+    /// Latest reason still holding the hide
+    pub reason: String,
+    pub unique_reporters: u64,
+    pub hidden_at: String,
+}
+
 /// Video deduplication status
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VideoDedupStatus {
@@ -83,6 +260,12 @@ pub struct VideoDedupStatus {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hamming_distance: Option<u32>,
     pub ingested_at: String,
+    /// Max distance the match was judged against, when recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hamming_threshold: Option<u32>,
+    /// Whether the upload was blocked for being a duplicate; upload-time checks only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_blocked: Option<bool>,
 }
 
 /// Video embeddings data
@@ -105,6 +288,19 @@ pub struct UserUploadedContentApproval {
     pub created_at: String,
 }
 
+impl UserUploadedContentApproval {
+    /// `created_at` in ms; accepts RFC 3339 (written at ingest) and BigQuery's
+    /// `CAST(timestamp AS STRING)` format, falling back to now
+    pub fn created_at_millis(&self) -> i64 {
+        chrono::DateTime::parse_from_rfc3339(&self.created_at)
+            .or_else(|_| {
+                chrono::DateTime::parse_from_str(&self.created_at, "%Y-%m-%d %H:%M:%S%.f%#z")
+            })
+            .map(|ts| ts.timestamp_millis())
+            .unwrap_or_else(|_| chrono::Utc::now().timestamp_millis())
+    }
+}
+
 /// Bot uploaded AI content marker
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BotUploadedAiContent {
@@ -120,17 +316,129 @@ pub struct VideoMetadata {
     pub publisher_user_id: String,
 }
 
+/// Detector verdict keyed by the file's SHA-256, reused across exact re-uploads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedDetectorVerdict {
+    pub content_hash: String,
+    pub detector: String,
+    pub model_version: String,
+    pub source_video_id: String,
+    pub confidence: f64,
+    pub result: serde_json::Value,
+    pub scored_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoThumbnail {
+    pub timestamp_secs: f64,
+    pub url: String,
+    pub width: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoThumbnails {
+    pub video_id: String,
+    pub thumbnails: Vec<VideoThumbnail>,
+    pub generated_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameSample {
+    /// `frame-{index}.jpg`
+    pub index: usize,
+    pub timestamp_secs: f64,
+    /// First frame of a new scene, rather than a fill-in for static footage
+    pub scene_cut: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoFrameSamples {
+    pub video_id: String,
+    pub duration_secs: f64,
+    pub scene_cut_count: usize,
+    pub frames: Vec<FrameSample>,
+    pub sampled_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupExplanationFrame {
+    pub frame_index: usize,
+    /// Between the two frames' hashes; 0 when identical
+    pub hamming_distance: u32,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupExplanationFrames {
+    pub video_id: String,
+    pub duplicate_of: String,
+    pub frames: Vec<DedupExplanationFrame>,
+    pub generated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoRendition {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub bitrate_kbps: u32,
+    pub playlist_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoRenditions {
+    pub video_id: String,
+    pub master_playlist_url: String,
+    pub renditions: Vec<VideoRendition>,
+    pub source_width: u32,
+    pub source_height: u32,
+    pub transcoded_at: i64,
+}
+
+/// Cached per-post creator analytics computed from BigQuery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostAnalytics {
+    pub post_id: String,
+    pub video_id: String,
+    pub views: u64,
+    pub avg_watch_percentage: f64,
+    pub likes: u64,
+    pub shares: u64,
+    pub reward_btc: f64,
+    pub reward_inr: f64,
+    pub computed_at: i64,
+}
+
+/// Outcome of checking one storage origin for a video
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OriginAvailability {
+    pub available: bool,
+    /// Status of the check; none when the request didn't complete
+    pub http_status: Option<u16>,
+    pub error: Option<String>,
+}
+
+/// Where a video could be fetched from when last audited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoAvailability {
+    pub video_id: String,
+    pub publisher_user_id: String,
+    /// Keyed by origin: `storj`, `cloudflare_stream` or `gcs`
+    pub origins: BTreeMap<String, OriginAvailability>,
+    pub checked_at: i64,
+    /// Last time missing copies were re-enqueued
+    pub healed_at: Option<i64>,
+}
+
 #[derive(Clone)]
 pub struct KvrocksClient {
-    client: ClusterClient,
+    /// Multiplexed and reconnecting, so one connection serves every caller
+    conn: ClusterConnection,
 }
 
 impl KvrocksClient {
     pub async fn get_connection(&self) -> Result<ClusterConnection> {
-        self.client
-            .get_async_connection()
-            .await
-            .context("Failed to get kvrocks cluster connection")
+        Ok(self.conn.clone())
     }
 
     pub async fn set_json<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
@@ -218,29 +526,85 @@ impl KvrocksClient {
         }
     }
 
-    /// Store a struct as a Redis HASH (each field becomes a hash field)
-    pub async fn set_hash<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+    pub async fn expire(&self, key: &str, ttl_secs: u64) -> Result<()> {
         let mut conn = self.get_connection().await?;
-        // Serialize to JSON Value to get field names
-        let json_value = serde_json::to_value(value)?;
-        let obj = json_value
-            .as_object()
-            .context("Value must serialize to a JSON object")?;
+        conn.expire::<_, ()>(key, ttl_secs as i64).await?;
+        Ok(())
+    }
 
-        let fields: Vec<(&str, String)> = obj
-            .iter()
-            .map(|(k, v)| {
-                // Store primitives directly, objects as JSON strings
-                let value_str = match v {
-                    serde_json::Value::String(s) => s.clone(),
-                    serde_json::Value::Null => String::new(),
-                    other => other.to_string(),
-                };
-                (k.as_str(), value_str)
-            })
-            .collect();
+    /// One SCAN page of keys under `prefix`; pass the returned cursor back in
+    /// until it is 0
+    pub async fn scan_prefix(
+        &self,
+        prefix: &str,
+        cursor: u64,
+        count: usize,
+    ) -> Result<(u64, Vec<String>)> {
+        let mut conn = self.get_connection().await?;
+        let pattern = format!("{}:*", prefix);
+        redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(&pattern)
+            .arg("COUNT")
+            .arg(count)
+            .query_async(&mut conn)
+            .await
+            .with_context(|| format!("Failed to scan {} keys", prefix))
+    }
 
+    pub async fn get<T: DeserializeOwned>(&self, table: &Table<T>, id: &str) -> Result<Option<T>> {
+        let key = table.key(id);
+        match table.encoding {
+            Encoding::Hash => self.get_hash(&key).await,
+            Encoding::Json => self.get_json(&key).await,
+        }
+    }
+
+    /// Writes a row, applying the table's default TTL if it has one
+    pub async fn put<T: Serialize>(&self, table: &Table<T>, id: &str, value: &T) -> Result<()> {
+        match table.ttl_secs {
+            Some(ttl_secs) => self.put_ex(table, id, value, ttl_secs).await,
+            None => {
+                let key = table.key(id);
+                match table.encoding {
+                    Encoding::Hash => self.set_hash(&key, value).await,
+                    Encoding::Json => self.set_json(&key, value).await,
+                }
+            }
+        }
+    }
+
+    pub async fn put_ex<T: Serialize>(
+        &self,
+        table: &Table<T>,
+        id: &str,
+        value: &T,
+        ttl_secs: u64,
+    ) -> Result<()> {
+        let key = table.key(id);
+        match table.encoding {
+            Encoding::Hash => {
+                self.set_hash(&key, value).await?;
+                self.expire(&key, ttl_secs).await
+            }
+            Encoding::Json => self.set_json_ex(&key, value, ttl_secs).await,
+        }
+    }
+
+    pub async fn remove<T>(&self, table: &Table<T>, id: &str) -> Result<()> {
+        self.del(&table.key(id)).await
+    }
+
+    pub async fn contains<T>(&self, table: &Table<T>, id: &str) -> Result<bool> {
+        self.exists(&table.key(id)).await
+    }
+
+    /// Store a struct as a Redis HASH (each field becomes a hash field)
+    pub async fn set_hash<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        let fields = hash_fields(value)?;
         if !fields.is_empty() {
+            let mut conn = self.get_connection().await?;
             conn.hset_multiple::<_, _, _, ()>(key, &fields).await?;
         }
         Ok(())
@@ -268,6 +632,28 @@ impl KvrocksClient {
     }
 }
 
+/// Hash fields of a struct, as written by `set_hash`
+fn hash_fields<T: Serialize>(value: &T) -> Result<Vec<(String, String)>> {
+    // Serialize to JSON Value to get field names
+    let json_value = serde_json::to_value(value)?;
+    let obj = json_value
+        .as_object()
+        .context("Value must serialize to a JSON object")?;
+
+    Ok(obj
+        .iter()
+        .map(|(k, v)| {
+            // Store primitives directly, objects as JSON strings
+            let value_str = match v {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Null => String::new(),
+                other => other.to_string(),
+            };
+            (k.clone(), value_str)
+        })
+        .collect())
+}
+
 pub async fn init_kvrocks_client() -> Result<KvrocksClient> {
     rustls::crypto::ring::default_provider()
         .install_default()
@@ -343,7 +729,7 @@ pub async fn init_kvrocks_client() -> Result<KvrocksClient> {
         hosts.len()
     );
 
-    Ok(KvrocksClient { client })
+    Ok(KvrocksClient { conn })
 }
 
 fn normalize_pem(pem: String) -> Vec<u8> {
@@ -381,78 +767,14 @@ fn get_client_key_pem() -> Result<Vec<u8>> {
 }
 
 impl KvrocksClient {
-    pub async fn store_video_nsfw(&self, data: &VideoNsfw) -> Result<()> {
-        let key = format!("{}:{}", keys::VIDEO_NSFW, data.video_id);
-        self.set_hash(&key, data).await
-    }
-
-    pub async fn get_video_nsfw(&self, video_id: &str) -> Result<Option<VideoNsfw>> {
-        let key = format!("{}:{}", keys::VIDEO_NSFW, video_id);
-        self.get_hash(&key).await
-    }
-
-    pub async fn store_videohash_phash(&self, data: &VideohashPhash) -> Result<()> {
-        let key = format!("{}:{}", keys::VIDEOHASH_PHASH, data.video_id);
-        self.set_hash(&key, data).await
-    }
-
-    pub async fn get_videohash_phash(&self, video_id: &str) -> Result<Option<VideohashPhash>> {
-        let key = format!("{}:{}", keys::VIDEOHASH_PHASH, video_id);
-        self.get_hash(&key).await
-    }
-
-    pub async fn store_videohash_original(&self, data: &VideohashOriginal) -> Result<()> {
-        let key = format!("{}:{}", keys::VIDEOHASH_ORIGINAL, data.video_id);
-        self.set_hash(&key, data).await
-    }
-
-    pub async fn get_videohash_original(
-        &self,
-        video_id: &str,
-    ) -> Result<Option<VideohashOriginal>> {
-        let key = format!("{}:{}", keys::VIDEOHASH_ORIGINAL, video_id);
-        self.get_hash(&key).await
-    }
-
-    pub async fn store_video_unique_v2(&self, data: &VideoUniqueV2) -> Result<()> {
-        let key = format!("{}:{}", keys::VIDEO_UNIQUE_V2, data.video_id);
-        self.set_hash(&key, data).await
-    }
-
-    pub async fn get_video_unique_v2(&self, video_id: &str) -> Result<Option<VideoUniqueV2>> {
-        let key = format!("{}:{}", keys::VIDEO_UNIQUE_V2, video_id);
-        self.get_hash(&key).await
-    }
-
-    pub async fn store_video_deleted(&self, data: &VideoDeleted) -> Result<()> {
-        let key = format!("{}:{}", keys::VIDEO_DELETED, data.video_id);
-        self.set_hash(&key, data).await
-    }
-
-    pub async fn get_video_deleted(&self, video_id: &str) -> Result<Option<VideoDeleted>> {
-        let key = format!("{}:{}", keys::VIDEO_DELETED, video_id);
-        self.get_hash(&key).await
-    }
-
-    pub async fn store_video_dedup_status(&self, data: &VideoDedupStatus) -> Result<()> {
-        let key = format!("{}:{}", keys::VIDEO_DEDUP_STATUS, data.video_id);
-        self.set_hash(&key, data).await
-    }
-
-    pub async fn get_video_dedup_status(&self, video_id: &str) -> Result<Option<VideoDedupStatus>> {
-        let key = format!("{}:{}", keys::VIDEO_DEDUP_STATUS, video_id);
-        self.get_hash(&key).await
-    }
-
     pub async fn push_video_embedding(
         &self,
         video_id: &str,
         embedding_vector: Vec<f32>,
         metadata: Option<serde_json::Value>,
     ) -> Result<()> {
-        let key = format!("{}:{}", keys::VIDEO_EMBEDDINGS, video_id);
-
-        let data = if let Some(mut existing) = self.get_video_embeddings(video_id).await? {
+        let data = if let Some(mut existing) = self.get(&tables::VIDEO_EMBEDDINGS, video_id).await?
+        {
             existing.embedding_vectors.push(embedding_vector);
             if let Some(new_metadata) = metadata {
                 existing.metadata = Some(new_metadata);
@@ -466,28 +788,7 @@ impl KvrocksClient {
             }
         };
 
-        self.set_hash(&key, &data).await
-    }
-
-    pub async fn get_video_embeddings(&self, video_id: &str) -> Result<Option<VideoEmbeddings>> {
-        let key = format!("{}:{}", keys::VIDEO_EMBEDDINGS, video_id);
-        self.get_hash(&key).await
-    }
-
-    pub async fn store_user_uploaded_content_approval(
-        &self,
-        data: &UserUploadedContentApproval,
-    ) -> Result<()> {
-        let key = format!("{}:{}", keys::USER_UPLOADED_CONTENT_APPROVAL, data.video_id);
-        self.set_hash(&key, data).await
-    }
-
-    pub async fn get_user_uploaded_content_approval(
-        &self,
-        video_id: &str,
-    ) -> Result<Option<UserUploadedContentApproval>> {
-        let key = format!("{}:{}", keys::USER_UPLOADED_CONTENT_APPROVAL, video_id);
-        self.get_hash(&key).await
+        self.put(&tables::VIDEO_EMBEDDINGS, video_id, &data).await
     }
 
     pub async fn update_user_uploaded_content_approval_status(
@@ -495,79 +796,141 @@ impl KvrocksClient {
         video_id: &str,
         is_approved: bool,
     ) -> Result<()> {
-        let key = format!("{}:{}", keys::USER_UPLOADED_CONTENT_APPROVAL, video_id);
-        if let Some(mut data) = self.get_user_uploaded_content_approval(video_id).await? {
+        let table = &tables::USER_UPLOADED_CONTENT_APPROVAL;
+        if is_approved {
+            // A moderator's decision ends any hold
+            let mut conn = self.get_connection().await?;
+            conn.srem::<_, _, ()>(keys::MANUAL_REVIEW_HOLDS, video_id)
+                .await?;
+        }
+        if let Some(mut data) = self.get(table, video_id).await? {
             data.is_approved = is_approved;
-            self.set_hash(&key, &data).await?;
+            self.put(table, video_id, &data).await?;
+            if is_approved {
+                self.dequeue_pending_approval(video_id).await?;
+            } else {
+                self.enqueue_pending_approval(video_id, data.created_at_millis())
+                    .await?;
+            }
         }
         Ok(())
     }
 
-    pub async fn delete_user_uploaded_content_approval(&self, video_id: &str) -> Result<()> {
-        let key = format!("{}:{}", keys::USER_UPLOADED_CONTENT_APPROVAL, video_id);
-        self.del(&key).await
+    /// Holds a video for moderators: records the hold, writes the approval
+    /// row as not approved (from `row` when ingest hasn't written one yet)
+    /// and queues it
+    pub async fn hold_for_manual_review(&self, row: UserUploadedContentApproval) -> Result<()> {
+        {
+            let mut conn = self.get_connection().await?;
+            conn.sadd::<_, _, ()>(keys::MANUAL_REVIEW_HOLDS, &row.video_id)
+                .await?;
+        }
+        let table = &tables::USER_UPLOADED_CONTENT_APPROVAL;
+        let row = UserUploadedContentApproval {
+            is_approved: false,
+            ..self.get(table, &row.video_id).await?.unwrap_or(row)
+        };
+        self.put(table, &row.video_id, &row).await?;
+        self.enqueue_pending_approval(&row.video_id, row.created_at_millis())
+            .await
     }
 
-    pub async fn store_bot_uploaded_ai_content(&self, data: &BotUploadedAiContent) -> Result<()> {
-        let key = format!("{}:{}", keys::BOT_UPLOADED_AI_CONTENT, data.video_id);
-        self.set_hash(&key, data).await
+    pub async fn is_held_for_manual_review(&self, video_id: &str) -> Result<bool> {
+        let mut conn = self.get_connection().await?;
+        Ok(conn.sismember(keys::MANUAL_REVIEW_HOLDS, video_id).await?)
     }
 
-    pub async fn store_video_metadata(&self, data: &VideoMetadata) -> Result<()> {
-        let key = format!("{}:{}", keys::VIDEO_METADATA, data.video_id);
-        self.set_hash(&key, data).await
+    /// Hides a video from feeds for `row.reason`. Each reason holds the hide
+    /// separately, so it stays until every reason has been cleared.
+    pub async fn hide_video(&self, row: &VideoHidden) -> Result<()> {
+        let mut fields = hash_fields(row)?;
+        fields.push((
+            format!("{HIDE_HOLD_PREFIX}{}", row.reason),
+            row.hidden_at.clone(),
+        ));
+        let mut conn = self.get_connection().await?;
+        conn.hset_multiple::<_, _, _, ()>(tables::VIDEO_HIDDEN.key(&row.video_id), &fields)
+            .await?;
+        Ok(())
     }
 
-    pub async fn get_video_metadata(&self, video_id: &str) -> Result<Option<VideoMetadata>> {
-        let key = format!("{}:{}", keys::VIDEO_METADATA, video_id);
-        self.get_hash(&key).await
+    /// Clears the hide held for `reason`; the video stays hidden while any
+    /// other reason holds it
+    pub async fn unhide_video(&self, video_id: &str, reason: &str) -> Result<()> {
+        let mut conn = self.get_connection().await?;
+        let deleted: i64 = UNHIDE_SCRIPT
+            .key(tables::VIDEO_HIDDEN.key(video_id))
+            .arg(HIDE_HOLD_PREFIX)
+            .arg(reason)
+            .invoke_async(&mut conn)
+            .await?;
+        if deleted == 0 {
+            log::debug!("Video {video_id} stays hidden after clearing {reason}");
+        }
+        Ok(())
     }
 
-    pub async fn delete_video_unique_v2(&self, video_id: &str) -> Result<()> {
-        let key = format!("{}:{}", keys::VIDEO_UNIQUE_V2, video_id);
-        self.del(&key).await
+    pub async fn enqueue_pending_approval(&self, video_id: &str, created_at_ms: i64) -> Result<()> {
+        let mut conn = self.get_connection().await?;
+        conn.zadd::<_, _, _, ()>(keys::PENDING_APPROVAL_QUEUE, video_id, created_at_ms)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn dequeue_pending_approval(&self, video_id: &str) -> Result<()> {
+        let mut conn = self.get_connection().await?;
+        conn.zrem::<_, _, ()>(keys::PENDING_APPROVAL_QUEUE, video_id)
+            .await?;
+        Ok(())
+    }
+
+    /// Page of queued video ids, newest first, plus the queue length
+    pub async fn pending_approval_page(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(u64, Vec<String>)> {
+        let mut conn = self.get_connection().await?;
+        let total: u64 = conn.zcard(keys::PENDING_APPROVAL_QUEUE).await?;
+        let ids: Vec<String> = conn
+            .zrevrange(
+                keys::PENDING_APPROVAL_QUEUE,
+                offset as isize,
+                (offset + limit) as isize - 1,
+            )
+            .await?;
+        Ok((total, ids))
     }
 
     pub async fn fetch_unprocessed_video_phashes(
         &self,
         limit: usize,
     ) -> Result<Vec<(String, String)>> {
-        let mut conn = self.get_connection().await?;
-        let pattern = format!("{}:*", keys::VIDEOHASH_PHASH);
         let mut cursor = 0u64;
         let mut unprocessed = Vec::new();
 
         loop {
-            let (new_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
-                .arg(cursor)
-                .arg("MATCH")
-                .arg(&pattern)
-                .arg("COUNT")
-                .arg(100)
-                .query_async(&mut conn)
-                .await
-                .context("Failed to scan videohash_phash keys")?;
+            let (new_cursor, keys) = self
+                .scan_prefix(tables::VIDEOHASH_PHASH.prefix(), cursor, 100)
+                .await?;
 
             for key in keys {
                 if unprocessed.len() >= limit {
                     break;
                 }
 
-                let video_id = key
-                    .strip_prefix(&format!("{}:", keys::VIDEOHASH_PHASH))
-                    .unwrap_or("")
-                    .to_string();
-
-                if video_id.is_empty() {
+                let Some(video_id) = tables::VIDEOHASH_PHASH.id_from_key(&key) else {
                     continue;
-                }
+                };
 
-                let dedup_key = format!("{}:{}", keys::VIDEO_DEDUP_STATUS, video_id);
-                let exists: bool = conn.exists(&dedup_key).await.unwrap_or(false);
+                let exists = self
+                    .contains(&tables::VIDEO_DEDUP_STATUS, video_id)
+                    .await
+                    .unwrap_or(false);
 
                 if !exists {
-                    if let Some(phash_data) = self.get_videohash_phash(&video_id).await? {
-                        unprocessed.push((video_id, phash_data.phash));
+                    if let Some(phash_data) = self.get(&tables::VIDEOHASH_PHASH, video_id).await? {
+                        unprocessed.push((video_id.to_string(), phash_data.phash));
                     }
                 }
             }
@@ -586,19 +949,10 @@ impl KvrocksClient {
     }
 
     pub async fn has_any_processed_videos(&self) -> Result<bool> {
-        let mut conn = self.get_connection().await?;
-        let pattern = format!("{}:*", keys::VIDEO_DEDUP_STATUS);
-
         // Just try to find one key
-        let (_, keys): (u64, Vec<String>) = redis::cmd("SCAN")
-            .arg(0)
-            .arg("MATCH")
-            .arg(&pattern)
-            .arg("COUNT")
-            .arg(1)
-            .query_async(&mut conn)
-            .await
-            .context("Failed to scan video_dedup_status keys")?;
+        let (_, keys) = self
+            .scan_prefix(tables::VIDEO_DEDUP_STATUS.prefix(), 0, 1)
+            .await?;
 
         Ok(!keys.is_empty())
     }
@@ -607,45 +961,34 @@ impl KvrocksClient {
         &self,
         limit: usize,
     ) -> Result<Vec<(String, String)>> {
-        let mut conn = self.get_connection().await?;
-        let pattern = format!("{}:*", keys::VIDEO_UNIQUE_V2);
         let mut cursor = 0u64;
         let mut unique_videos = Vec::new();
 
         loop {
-            let (new_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
-                .arg(cursor)
-                .arg("MATCH")
-                .arg(&pattern)
-                .arg("COUNT")
-                .arg(100)
-                .query_async(&mut conn)
-                .await
-                .context("Failed to scan video_unique_v2 keys")?;
+            let (new_cursor, keys) = self
+                .scan_prefix(tables::VIDEO_UNIQUE_V2.prefix(), cursor, 100)
+                .await?;
 
             for key in keys {
                 if unique_videos.len() >= limit {
                     break;
                 }
 
-                let video_id = key
-                    .strip_prefix(&format!("{}:", keys::VIDEO_UNIQUE_V2))
-                    .unwrap_or("")
-                    .to_string();
-
-                if video_id.is_empty() {
+                let Some(video_id) = tables::VIDEO_UNIQUE_V2.id_from_key(&key) else {
                     continue;
-                }
+                };
 
-                let dedup_key = format!("{}:{}", keys::VIDEO_DEDUP_STATUS, video_id);
-                let exists: bool = conn.exists(&dedup_key).await.unwrap_or(false);
+                let exists = self
+                    .contains(&tables::VIDEO_DEDUP_STATUS, video_id)
+                    .await
+                    .unwrap_or(false);
 
                 if exists {
                     continue; // Already processed
                 }
 
-                if let Some(phash_data) = self.get_videohash_phash(&video_id).await? {
-                    unique_videos.push((video_id, phash_data.phash));
+                if let Some(phash_data) = self.get(&tables::VIDEOHASH_PHASH, video_id).await? {
+                    unique_videos.push((video_id.to_string(), phash_data.phash));
                 }
             }
 
@@ -667,6 +1010,21 @@ impl KvrocksClient {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_table_keys() {
+        let table = &tables::VIDEO_NSFW;
+        assert_eq!(table.key("abc"), "offchain:video_nsfw:abc");
+        assert_eq!(table.id_from_key("offchain:video_nsfw:abc"), Some("abc"));
+        assert_eq!(table.id_from_key("offchain:video_nsfw:"), None);
+        assert_eq!(table.id_from_key("offchain:video_nsfw_v2:abc"), None);
+
+        let verdicts = &tables::DETECTOR_VERDICT_CACHE;
+        assert_eq!(
+            verdicts.key(&tables::detector_verdict_id("nsfw_v2", "h1")),
+            "offchain:detector_verdict_cache:nsfw_v2:h1"
+        );
+    }
+
     #[tokio::test]
     async fn test_kvrocks_connection() {
         if env::var("KVROCKS_PASSWORD").is_err() {
@@ -0,0 +1,594 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use candid::Principal;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    anomaly::{review_frozen_entry, ReviewDecision, ReviewOutcome},
+    handlers::create_tournament,
+    redis_ops::LeaderboardRedis,
+    tournament::{prize_pool_in_units, prize_usd_rate, MAX_CKBTC_PRIZE_SATS},
+    types::{
+        CreateTournamentRequest, EventMetricMapping, FrozenEntry, MetricType, PrizeDistribution,
+        TokenType, Tournament, TournamentStatus,
+    },
+};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    config::runtime::runtime,
+    error::{ApiError, ApiErrorBody},
+    qstash::job::{PublishOptions, TournamentStep, TournamentStepJob},
+    tokens::pricing::UsdRate,
+};
+
+const MIN_TOURNAMENT_DURATION_SECS: i64 = 10 * 60;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PrizePreviewEntry {
+    pub rank: u32,
+    /// Reward in payout units (YRAL, or ckBTC sats)
+    pub reward: u64,
+    /// Finalization skips ckBTC rewards above the per-winner cap
+    pub exceeds_cap: bool,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PrizePreviewResponse {
+    pub prize_pool: f64,
+    pub prize_token: TokenType,
+    pub prize_distribution: PrizeDistribution,
+    pub prize_pool_in_units: u64,
+    pub num_winners: u32,
+    pub total_distributed: u64,
+    /// Part of the pool the top `num_winners` ranks don't receive
+    pub undistributed: u64,
+    pub entries: Vec<PrizePreviewEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct PrizePreviewRequest {
+    pub prize_pool: f64,
+    #[serde(default)]
+    pub prize_token: TokenType,
+    pub num_winners: u32,
+    #[serde(default)]
+    pub prize_distribution: PrizeDistribution,
+    /// Participant count to size percentile buckets with; defaults to `num_winners`
+    pub total_participants: Option<u32>,
+}
+
+/// Fields of an upcoming tournament that can be changed before it starts
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+pub struct UpdateTournamentRequest {
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub prize_pool: Option<f64>,
+    pub prize_token: Option<TokenType>,
+    pub metric_type: Option<MetricType>,
+    pub metric_display_name: Option<String>,
+    pub allowed_sources: Option<Vec<String>>,
+    pub num_winners: Option<u32>,
+    pub prize_distribution: Option<PrizeDistribution>,
+    pub event_metrics: Option<Vec<EventMetricMapping>>,
+}
+
+/// Current price of the prize token when the pool is in USD
+async fn pool_usd_rate(prize_token: &TokenType) -> Result<Option<UsdRate>, ApiError> {
+    prize_usd_rate(prize_token)
+        .await
+        .map_err(|e| ApiError::ServiceUnavailable(format!("Prize token price unavailable: {e}")))
+}
+
+pub(crate) fn preview_distribution(
+    prize_pool: f64,
+    prize_token: TokenType,
+    prize_distribution: PrizeDistribution,
+    num_winners: u32,
+    total_participants: u32,
+    usd_rate: Option<UsdRate>,
+) -> PrizePreviewResponse {
+    let pool_in_units = prize_pool_in_units(prize_pool, usd_rate);
+    let entries: Vec<PrizePreviewEntry> = (1..=num_winners)
+        .map(|rank| {
+            let reward = prize_distribution
+                .reward(rank, num_winners, total_participants, pool_in_units)
+                .unwrap_or(0);
+            PrizePreviewEntry {
+                rank,
+                reward,
+                exceeds_cap: prize_token == TokenType::CKBTC && reward > MAX_CKBTC_PRIZE_SATS,
+            }
+        })
+        .collect();
+    let total_distributed = entries
+        .iter()
+        .filter(|entry| !entry.exceeds_cap)
+        .map(|entry| entry.reward)
+        .sum();
+
+    PrizePreviewResponse {
+        prize_pool,
+        prize_token,
+        prize_distribution,
+        prize_pool_in_units: pool_in_units,
+        num_winners,
+        total_distributed,
+        undistributed: pool_in_units.saturating_sub(total_distributed),
+        entries,
+    }
+}
+
+fn validate_tournament_request(
+    request: &CreateTournamentRequest,
+    now: i64,
+    usd_rate: Option<UsdRate>,
+) -> Result<(), ApiError> {
+    let limits = runtime().leaderboard.clone();
+    let duration = request.end_time - request.start_time;
+    if duration < MIN_TOURNAMENT_DURATION_SECS || duration > limits.max_tournament_duration_secs {
+        return Err(ApiError::InvalidRequest(format!(
+            "Tournament must last between {MIN_TOURNAMENT_DURATION_SECS} and {} seconds",
+            limits.max_tournament_duration_secs
+        )));
+    }
+    if request.end_time <= now {
+        return Err(ApiError::InvalidRequest(
+            "Tournament must end in the future".to_string(),
+        ));
+    }
+    if request.start_time > now + limits.max_start_lead_secs {
+        return Err(ApiError::InvalidRequest(
+            "Tournament starts too far in the future".to_string(),
+        ));
+    }
+
+    let num_winners = request.num_winners.unwrap_or(10);
+    validate_winners(&request.prize_distribution, num_winners)?;
+
+    if !request.prize_pool.is_finite() || request.prize_pool <= 0.0 {
+        return Err(ApiError::InvalidRequest(
+            "prize_pool must be a positive number".to_string(),
+        ));
+    }
+    if request.prize_token == TokenType::YRAL && request.prize_pool > limits.max_yral_prize_pool {
+        return Err(ApiError::InvalidRequest(format!(
+            "YRAL prize_pool cannot exceed {}",
+            limits.max_yral_prize_pool
+        )));
+    }
+    let preview = preview_distribution(
+        request.prize_pool,
+        request.prize_token.clone(),
+        request.prize_distribution.clone(),
+        num_winners,
+        num_winners,
+        usd_rate,
+    );
+    if let Some(entry) = preview.entries.iter().find(|entry| entry.exceeds_cap) {
+        return Err(ApiError::InvalidRequest(format!(
+            "Rank {} would receive {} sats, above the {MAX_CKBTC_PRIZE_SATS} sats per-winner cap",
+            entry.rank, entry.reward
+        )));
+    }
+    // Percentile buckets legitimately leave ranks unpaid until enough people join
+    let pays_every_winner = !matches!(
+        request.prize_distribution,
+        PrizeDistribution::PercentileBuckets { .. }
+    );
+    if pays_every_winner && preview.entries.iter().any(|entry| entry.reward == 0) {
+        return Err(ApiError::InvalidRequest(
+            "prize_pool is too small to pay every winner".to_string(),
+        ));
+    }
+
+    let mut events = std::collections::HashSet::new();
+    for mapping in &request.event_metrics {
+        if mapping.event.trim().is_empty() || mapping.principal_param.trim().is_empty() {
+            return Err(ApiError::InvalidRequest(
+                "event_metrics entries need an event and principal_param".to_string(),
+            ));
+        }
+        let filters = serde_json::Value::Object(mapping.match_params.clone()).to_string();
+        if !events.insert((mapping.event.as_str(), filters)) {
+            return Err(ApiError::InvalidRequest(format!(
+                "Duplicate event_metrics entry for {}",
+                mapping.event
+            )));
+        }
+    }
+
+    if request.metric_display_name.trim().is_empty() {
+        return Err(ApiError::InvalidRequest(
+            "metric_display_name is required".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_winners(
+    prize_distribution: &PrizeDistribution,
+    num_winners: u32,
+) -> Result<(), ApiError> {
+    prize_distribution
+        .validate()
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid prize_distribution: {e}")))?;
+
+    let max_winners = prize_distribution.max_winners();
+    if num_winners == 0 || num_winners > max_winners {
+        return Err(ApiError::InvalidRequest(format!(
+            "num_winners must be between 1 and {max_winners} for this prize distribution"
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects windows that overlap the current or upcoming tournament
+async fn check_no_overlap(
+    redis: &LeaderboardRedis,
+    start_time: i64,
+    end_time: i64,
+    exclude_id: Option<&str>,
+) -> Result<(), ApiError> {
+    let current_id = redis.get_current_tournament().await?;
+    let upcoming_id = redis.get_upcoming_tournament().await?;
+
+    for tournament_id in [current_id, upcoming_id].into_iter().flatten() {
+        if Some(tournament_id.as_str()) == exclude_id {
+            continue;
+        }
+        let Some(other) = redis.get_tournament_info(&tournament_id).await? else {
+            continue;
+        };
+        if !matches!(
+            other.status,
+            TournamentStatus::Upcoming | TournamentStatus::Active
+        ) {
+            continue;
+        }
+        if other.status == TournamentStatus::Upcoming && exclude_id.is_none() {
+            return Err(ApiError::Conflict(format!(
+                "Tournament {} is already upcoming; edit it instead",
+                other.id
+            )));
+        }
+        if start_time < other.end_time && other.start_time < end_time {
+            return Err(ApiError::Conflict(format!(
+                "Overlaps tournament {} ({} - {})",
+                other.id, other.start_time, other.end_time
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Create a tournament
+#[utoipa::path(
+    post,
+    path = "/admin/tournaments",
+    tag = "leaderboard",
+    request_body = CreateTournamentRequest,
+    responses(
+        (status = 201, description = "Tournament created"),
+        (status = 400, description = "Invalid tournament", body = ApiErrorBody),
+        (status = 401, description = "Authentication failed", body = ApiErrorBody),
+        (status = 409, description = "Overlaps an existing tournament", body = ApiErrorBody),
+        (status = 503, description = "Prize token price unavailable", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn admin_create_tournament(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<CreateTournamentRequest>,
+) -> Result<(StatusCode, Json<Tournament>), ApiError> {
+    require_operator(&headers)?;
+    let usd_rate = pool_usd_rate(&request.prize_token).await?;
+    validate_tournament_request(&request, Utc::now().timestamp(), usd_rate)?;
+
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+    check_no_overlap(&redis, request.start_time, request.end_time, None).await?;
+
+    let tournament = create_tournament(&state, request).await?;
+    log::info!("Admin created tournament {}", tournament.id);
+
+    Ok((StatusCode::CREATED, Json(tournament)))
+}
+
+/// Edit a tournament that hasn't started yet
+#[utoipa::path(
+    patch,
+    path = "/admin/tournaments/{tournament_id}",
+    tag = "leaderboard",
+    params(
+        ("tournament_id" = String, Path, description = "Tournament ID")
+    ),
+    request_body = UpdateTournamentRequest,
+    responses(
+        (status = 200, description = "Tournament updated"),
+        (status = 400, description = "Invalid tournament", body = ApiErrorBody),
+        (status = 401, description = "Authentication failed", body = ApiErrorBody),
+        (status = 404, description = "Tournament not found", body = ApiErrorBody),
+        (status = 409, description = "Tournament already started or overlaps another", body = ApiErrorBody),
+        (status = 503, description = "Prize token price unavailable", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn admin_update_tournament(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_id): Path<String>,
+    headers: HeaderMap,
+    Json(update): Json<UpdateTournamentRequest>,
+) -> Result<Json<Tournament>, ApiError> {
+    require_operator(&headers)?;
+
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+    let mut tournament = redis
+        .get_tournament_info(&tournament_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Tournament {tournament_id} not found")))?;
+
+    let now = Utc::now().timestamp();
+    if tournament.status != TournamentStatus::Upcoming || tournament.start_time <= now {
+        return Err(ApiError::Conflict(
+            "Only upcoming tournaments can be edited".to_string(),
+        ));
+    }
+
+    let merged = CreateTournamentRequest {
+        start_time: update.start_time.unwrap_or(tournament.start_time),
+        end_time: update.end_time.unwrap_or(tournament.end_time),
+        prize_pool: update.prize_pool.unwrap_or(tournament.prize_pool),
+        prize_token: update.prize_token.unwrap_or(tournament.prize_token),
+        metric_type: update.metric_type.unwrap_or(tournament.metric_type),
+        metric_display_name: update
+            .metric_display_name
+            .unwrap_or(tournament.metric_display_name),
+        allowed_sources: update.allowed_sources.unwrap_or(tournament.allowed_sources),
+        num_winners: Some(update.num_winners.unwrap_or(tournament.num_winners)),
+        prize_distribution: update
+            .prize_distribution
+            .unwrap_or(tournament.prize_distribution),
+        event_metrics: update.event_metrics.unwrap_or(tournament.event_metrics),
+    };
+    let usd_rate = pool_usd_rate(&merged.prize_token).await?;
+    validate_tournament_request(&merged, now, usd_rate)?;
+    if merged.start_time <= now {
+        return Err(ApiError::InvalidRequest(
+            "An upcoming tournament must keep a future start_time".to_string(),
+        ));
+    }
+    check_no_overlap(
+        &redis,
+        merged.start_time,
+        merged.end_time,
+        Some(&tournament_id),
+    )
+    .await?;
+
+    let start_changed = merged.start_time != tournament.start_time;
+    tournament.start_time = merged.start_time;
+    tournament.end_time = merged.end_time;
+    tournament.prize_pool = merged.prize_pool;
+    tournament.prize_token = merged.prize_token;
+    tournament.metric_type = merged.metric_type;
+    tournament.metric_display_name = merged.metric_display_name;
+    tournament.allowed_sources = merged.allowed_sources;
+    tournament.num_winners = merged.num_winners.unwrap_or(tournament.num_winners);
+    tournament.prize_distribution = merged.prize_distribution;
+    tournament.event_metrics = merged.event_metrics;
+    tournament.updated_at = now;
+    redis.set_tournament_info(&tournament).await?;
+
+    // The previously scheduled start becomes a no-op once it fires early
+    if start_changed {
+        state
+            .qstash_client
+            .publish_job_with(
+                &TournamentStepJob {
+                    tournament_id: &tournament_id,
+                    step: TournamentStep::Start,
+                },
+                PublishOptions::delayed_secs(tournament.start_time - now),
+            )
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to reschedule start: {e}")))?;
+    }
+
+    log::info!("Admin updated upcoming tournament {tournament_id}");
+    Ok(Json(tournament))
+}
+
+/// Dry-run the prize distribution for a prize pool and number of winners
+#[utoipa::path(
+    post,
+    path = "/admin/tournaments/preview",
+    tag = "leaderboard",
+    request_body = PrizePreviewRequest,
+    responses(
+        (status = 200, description = "Prize distribution preview", body = PrizePreviewResponse),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 401, description = "Authentication failed", body = ApiErrorBody),
+        (status = 503, description = "Prize token price unavailable", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn admin_preview_prizes(
+    headers: HeaderMap,
+    Json(request): Json<PrizePreviewRequest>,
+) -> Result<Json<PrizePreviewResponse>, ApiError> {
+    require_operator(&headers)?;
+
+    if !request.prize_pool.is_finite() || request.prize_pool <= 0.0 {
+        return Err(ApiError::InvalidRequest(
+            "prize_pool must be a positive number".to_string(),
+        ));
+    }
+    validate_winners(&request.prize_distribution, request.num_winners)?;
+    let usd_rate = pool_usd_rate(&request.prize_token).await?;
+
+    Ok(Json(preview_distribution(
+        request.prize_pool,
+        request.prize_token,
+        request.prize_distribution,
+        request.num_winners,
+        request.total_participants.unwrap_or(request.num_winners),
+        usd_rate,
+    )))
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FrozenParticipant {
+    #[schema(value_type = String)]
+    pub principal_id: Principal,
+    pub entry: FrozenEntry,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ReviewFrozenRequest {
+    pub decision: ReviewDecision,
+    /// Recorded on the entry for audit
+    pub reviewer: Option<String>,
+}
+
+/// Participants frozen by score anomaly detection, oldest first
+#[utoipa::path(
+    get,
+    path = "/admin/tournaments/{tournament_id}/frozen",
+    tag = "leaderboard",
+    params(
+        ("tournament_id" = String, Path, description = "Tournament ID")
+    ),
+    responses(
+        (status = 200, description = "Frozen participants", body = Vec<FrozenParticipant>),
+        (status = 401, description = "Authentication failed", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn admin_list_frozen(
+    State(state): State<Arc<AppState>>,
+    Path(tournament_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<FrozenParticipant>>, ApiError> {
+    require_operator(&headers)?;
+
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+    let mut frozen: Vec<FrozenParticipant> = redis
+        .get_frozen_entries(&tournament_id)
+        .await?
+        .into_iter()
+        .map(|(principal_id, entry)| FrozenParticipant {
+            principal_id,
+            entry,
+        })
+        .collect();
+    frozen.sort_by_key(|participant| participant.entry.frozen_at);
+
+    Ok(Json(frozen))
+}
+
+/// Clear or disqualify a participant frozen pending review
+#[utoipa::path(
+    post,
+    path = "/admin/tournaments/{tournament_id}/frozen/{principal}/review",
+    tag = "leaderboard",
+    params(
+        ("tournament_id" = String, Path, description = "Tournament ID"),
+        ("principal" = String, Path, description = "Frozen participant")
+    ),
+    request_body = ReviewFrozenRequest,
+    responses(
+        (status = 200, description = "Decision applied", body = ReviewOutcome),
+        (status = 400, description = "Invalid principal", body = ApiErrorBody),
+        (status = 401, description = "Authentication failed", body = ApiErrorBody),
+        (status = 404, description = "Participant is not frozen", body = ApiErrorBody),
+        (status = 409, description = "Entry already reviewed", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn admin_review_frozen(
+    State(state): State<Arc<AppState>>,
+    Path((tournament_id, principal)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(request): Json<ReviewFrozenRequest>,
+) -> Result<Json<ReviewOutcome>, ApiError> {
+    require_operator(&headers)?;
+    let principal = Principal::from_text(&principal)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid principal: {e}")))?;
+
+    let outcome = review_frozen_entry(
+        &state,
+        &tournament_id,
+        principal,
+        request.decision,
+        request.reviewer,
+    )
+    .await?;
+    Ok(Json(outcome))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokens::Token;
+
+    fn request(start_time: i64, end_time: i64, prize_pool: f64) -> CreateTournamentRequest {
+        CreateTournamentRequest {
+            start_time,
+            end_time,
+            prize_pool,
+            prize_token: TokenType::CKBTC,
+            metric_type: MetricType::GamesWon,
+            metric_display_name: "Games Won".to_string(),
+            allowed_sources: vec![],
+            num_winners: Some(10),
+            prize_distribution: PrizeDistribution::Tiered,
+            event_metrics: vec![],
+        }
+    }
+
+    #[test]
+    fn test_validate_tournament_request() {
+        let now = 1_700_000_000;
+        // 1,000 sats per USD
+        let rate = Some(UsdRate::new(Token::CkBtc, 100_000.0));
+        let validate =
+            |request: CreateTournamentRequest| validate_tournament_request(&request, now, rate);
+        assert!(validate(request(now + 60, now + 3600, 100.0)).is_ok());
+        // Too short, ended, and a top prize above the ckBTC cap
+        assert!(validate(request(now, now + 60, 100.0)).is_err());
+        assert!(validate(request(now - 7200, now - 60, 100.0)).is_err());
+        assert!(validate(request(now, now + 3600, 1000.0)).is_err());
+        assert!(validate(request(now, now + 3600, f64::NAN)).is_err());
+
+        let preview = preview_distribution(
+            1_000_000.0,
+            TokenType::YRAL,
+            PrizeDistribution::Tiered,
+            3,
+            3,
+            None,
+        );
+        assert_eq!(preview.total_distributed, 410_000);
+        assert_eq!(preview.undistributed, 590_000);
+    }
+}
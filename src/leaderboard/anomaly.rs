@@ -0,0 +1,251 @@
+//! Score anomaly detection for tournaments.
+//!
+//! Every increment also feeds a per-participant counter that expires after
+//! `leaderboard.velocity_window_secs`. A participant whose gain within one
+//! window exceeds `leaderboard.max_score_per_window` is frozen: further score
+//! updates are ignored, a prize won while frozen is escrowed as
+//! [`PrizeClaimStatus::Frozen`](super::types::PrizeClaimStatus::Frozen), and
+//! operators are alerted on `GCHAT_LEADERBOARD_ALERTS_WEBHOOK_URL` to review
+//! the entry through the admin endpoints.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use candid::Principal;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::ToSchema;
+
+use super::{
+    redis_ops::LeaderboardRedis,
+    tournament::{forfeit_frozen_prize, release_frozen_prize},
+    types::{
+        FrozenEntry, FrozenReview, FrozenStatus, PrizeClaim, PrizeClaimStatus, ScoreOperation,
+        ScoreUpdate,
+    },
+};
+use crate::{
+    app_state::AppState,
+    config::{runtime::runtime, LeaderboardSection},
+    error::ApiError,
+    offchain_service::send_message_gchat_webhook,
+};
+
+static LEADERBOARD_ALERTS_WEBHOOK_URL: Lazy<Option<String>> =
+    Lazy::new(|| std::env::var("GCHAT_LEADERBOARD_ALERTS_WEBHOOK_URL").ok());
+
+/// Why `update` should freeze the participant, if it should
+fn freeze_reason(update: &ScoreUpdate, limits: &LeaderboardSection) -> Option<String> {
+    if update.frozen || update.window_gain <= limits.max_score_per_window {
+        return None;
+    }
+    Some(format!(
+        "Gained {} in {}s, limit is {}",
+        update.window_gain, limits.velocity_window_secs, limits.max_score_per_window
+    ))
+}
+
+async fn alert_frozen(tournament_id: &str, principal: Principal, entry: &FrozenEntry) {
+    log::warn!(
+        "Froze {} in tournament {} pending review: {}",
+        principal,
+        tournament_id,
+        entry.reason
+    );
+
+    let Some(webhook_url) = LEADERBOARD_ALERTS_WEBHOOK_URL.as_deref() else {
+        log::warn!("GCHAT_LEADERBOARD_ALERTS_WEBHOOK_URL not set, skipping chat alert");
+        return;
+    };
+
+    let message = json!({
+        "text": format!(
+            "*Tournament entry frozen*\nTournament: {}\nPrincipal: {}\nScore: {}\n{}",
+            tournament_id, principal, entry.score_at_freeze, entry.reason
+        )
+    });
+    if let Err(e) = send_message_gchat_webhook(webhook_url, message).await {
+        log::error!("Failed to send leaderboard anomaly alert: {e:?}");
+    }
+}
+
+/// Applies a score update and freezes the participant when it pushes their
+/// gain over the velocity limit. Frozen participants keep their score but
+/// receive no further updates.
+pub async fn apply_score_update(
+    redis: &LeaderboardRedis,
+    tournament_id: &str,
+    principal: Principal,
+    metric_value: f64,
+    operation: &ScoreOperation,
+) -> Result<ScoreUpdate> {
+    let limits = runtime().leaderboard.clone();
+    let update = redis
+        .update_user_score_tracked(
+            tournament_id,
+            principal,
+            metric_value,
+            operation,
+            limits.velocity_window_secs,
+        )
+        .await?;
+
+    if let Some(reason) = freeze_reason(&update, &limits) {
+        let entry = FrozenEntry {
+            status: FrozenStatus::PendingReview,
+            reason,
+            window_gain: update.window_gain,
+            window_secs: limits.velocity_window_secs,
+            score_at_freeze: update.score,
+            frozen_at: Utc::now().timestamp(),
+            reviewed_at: None,
+            reviewed_by: None,
+        };
+        if redis
+            .freeze_participant(tournament_id, principal, &entry)
+            .await?
+        {
+            alert_frozen(tournament_id, principal, &entry).await;
+        }
+    }
+
+    Ok(update)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewDecision {
+    /// False positive: lift the freeze and release any held prize
+    Clear,
+    /// Keep the freeze, drop the entry from the leaderboard and return any
+    /// held prize to the pool
+    Disqualify,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReviewOutcome {
+    pub decision: ReviewDecision,
+    pub entry: FrozenEntry,
+    /// The prize that was held for the participant, after the decision
+    pub claim: Option<PrizeClaim>,
+}
+
+/// Why an operator review was refused
+#[derive(Debug, thiserror::Error)]
+pub enum ReviewFrozenError {
+    #[error("Frozen entry not found")]
+    NotFound,
+    #[error("Frozen entry was already reviewed")]
+    AlreadyReviewed,
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<ReviewFrozenError> for ApiError {
+    fn from(err: ReviewFrozenError) -> Self {
+        let message = format!("Failed to review frozen entry: {err}");
+        match err {
+            ReviewFrozenError::NotFound => ApiError::NotFound(message),
+            ReviewFrozenError::AlreadyReviewed => ApiError::Conflict(message),
+            ReviewFrozenError::Internal(_) => ApiError::Internal(message),
+        }
+    }
+}
+
+/// Applies an operator decision to a participant frozen pending review.
+/// The entry changes state in a single script, so of two concurrent reviews
+/// only one goes on to release or forfeit the held prize.
+pub async fn review_frozen_entry(
+    app_state: &Arc<AppState>,
+    tournament_id: &str,
+    principal: Principal,
+    decision: ReviewDecision,
+    reviewer: Option<String>,
+) -> Result<ReviewOutcome, ReviewFrozenError> {
+    let redis = LeaderboardRedis::new(app_state.leaderboard_redis_pool.clone());
+    let mut entry = redis
+        .get_frozen_entry(tournament_id, principal)
+        .await?
+        .ok_or(ReviewFrozenError::NotFound)?;
+    if entry.status != FrozenStatus::PendingReview {
+        return Err(ReviewFrozenError::AlreadyReviewed);
+    }
+    entry.reviewed_at = Some(Utc::now().timestamp());
+    entry.reviewed_by = reviewer;
+
+    let reviewed = match decision {
+        ReviewDecision::Clear => None,
+        ReviewDecision::Disqualify => {
+            entry.status = FrozenStatus::Disqualified;
+            Some(&entry)
+        }
+    };
+    match redis
+        .review_frozen_entry(tournament_id, principal, reviewed)
+        .await?
+    {
+        FrozenReview::Applied => {}
+        FrozenReview::NotFound => return Err(ReviewFrozenError::NotFound),
+        FrozenReview::AlreadyReviewed => return Err(ReviewFrozenError::AlreadyReviewed),
+    }
+
+    let held_claim = redis
+        .get_prize_claim(tournament_id, principal)
+        .await?
+        .filter(|claim| claim.status == PrizeClaimStatus::Frozen);
+
+    let claim = match decision {
+        ReviewDecision::Clear => match held_claim {
+            Some(claim) => Some(release_frozen_prize(&redis, claim, app_state).await?),
+            None => None,
+        },
+        ReviewDecision::Disqualify => {
+            redis
+                .remove_user_from_leaderboard(tournament_id, principal)
+                .await?;
+            match held_claim {
+                Some(claim) => Some(forfeit_frozen_prize(&redis, claim).await?),
+                None => None,
+            }
+        }
+    };
+
+    log::info!(
+        "Reviewed frozen {} in tournament {}: {:?}",
+        principal,
+        tournament_id,
+        decision
+    );
+    Ok(ReviewOutcome {
+        decision,
+        entry,
+        claim,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_freeze_reason() {
+        let limits = LeaderboardSection {
+            max_score_per_window: 100.0,
+            ..Default::default()
+        };
+        let update = |window_gain, frozen| ScoreUpdate {
+            score: 250.0,
+            window_gain,
+            frozen,
+        };
+
+        assert!(freeze_reason(&update(40.0, false), &limits).is_none());
+        // At the limit is still allowed
+        assert!(freeze_reason(&update(100.0, false), &limits).is_none());
+        assert!(freeze_reason(&update(101.0, false), &limits).is_some());
+        // Already frozen, the update was not applied
+        assert!(freeze_reason(&update(0.0, true), &limits).is_none());
+    }
+}
@@ -5,20 +5,26 @@ use axum::{
 };
 use candid::Principal;
 use chrono::Utc;
+use ic_agent::identity::{DelegatedIdentity, Identity};
 use std::sync::Arc;
 use yral_username_gen::random_username_from_principal;
 
+use super::anomaly::apply_score_update;
 use super::redis_ops::LeaderboardRedis;
+use super::tournament::{finalized_usd_rate, prize_units_for_display};
 use super::types::*;
 use super::utils::get_usernames_with_fallback;
-use crate::{app_state::AppState, auth::check_auth_events, consts::ANALYTICS_SERVER_URL};
+use crate::{
+    app_state::AppState,
+    auth::check_auth_events,
+    consts::ANALYTICS_SERVER_URL,
+    error::{ApiError, ApiErrorBody},
+    qstash::job::{PublishOptions, TournamentStep, TournamentStepJob},
+};
 use chrono::{DateTime, TimeZone};
 use chrono_tz::Tz;
 use serde::Deserialize;
 
-// Conversion rate: 1 USD = 886 SATS (ckBTC satoshis)
-const USD_TO_CKBTC_SATS_RATE: f64 = 886.0;
-
 // Timezone API response structure
 #[derive(Debug, Deserialize)]
 struct TimezoneApiResponse {
@@ -85,9 +91,10 @@ fn convert_timestamp_to_timezone(timestamp: i64, tz: &Tz) -> String {
     request_body = serde_json::Value,
     responses(
         (status = 200, description = "Score updated successfully"),
-        (status = 401, description = "Authentication failed"),
-        (status = 404, description = "No active tournament"),
-        (status = 400, description = "Invalid request")
+        (status = 401, description = "Authentication failed", body = ApiErrorBody),
+        (status = 404, description = "No active tournament", body = ApiErrorBody),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 409, description = "Tournament is scored server-side", body = ApiErrorBody)
     ),
     security(
         ("bearer" = [])
@@ -105,13 +112,7 @@ pub async fn update_score_handler(
         .map(|value| value.trim_start_matches("Bearer ").to_string());
 
     if let Err(e) = check_auth_events(auth_token) {
-        return (
-            StatusCode::UNAUTHORIZED,
-            Json(serde_json::json!({
-                "error": format!("Authentication failed: {}", e)
-            })),
-        )
-            .into_response();
+        return ApiError::Unauthorized(format!("Authentication failed: {}", e)).into_response();
     }
 
     let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
@@ -120,22 +121,11 @@ pub async fn update_score_handler(
     let current_tournament = match redis.get_current_tournament().await {
         Ok(Some(id)) => id,
         Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({
-                    "error": "No active tournament"
-                })),
-            )
-                .into_response();
+            return ApiError::NotFound("No active tournament".to_string()).into_response();
         }
         Err(e) => {
             log::error!("Failed to get current tournament: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to get current tournament"
-                })),
-            )
+            return ApiError::Internal("Failed to get current tournament".to_string())
                 .into_response();
         }
     };
@@ -144,23 +134,11 @@ pub async fn update_score_handler(
     let tournament = match redis.get_tournament_info(&current_tournament).await {
         Ok(Some(t)) => t,
         Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({
-                    "error": "Tournament info not found"
-                })),
-            )
-                .into_response();
+            return ApiError::NotFound("Tournament info not found".to_string()).into_response();
         }
         Err(e) => {
             log::error!("Failed to get tournament info: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to get tournament info"
-                })),
-            )
-                .into_response();
+            return ApiError::Internal("Failed to get tournament info".to_string()).into_response();
         }
     };
 
@@ -170,36 +148,33 @@ pub async fn update_score_handler(
         || now < tournament.start_time
         || now > tournament.end_time
     {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": "Tournament is not active"
-            })),
-        )
-            .into_response();
+        return ApiError::InvalidRequest("Tournament is not active".to_string()).into_response();
     }
 
     // Validate metric type matches tournament
     if request.metric_type != tournament.metric_type.to_string() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": format!("Invalid metric type. Expected: {}, Got: {}",
-                    tournament.metric_type, request.metric_type)
-            })),
+        return ApiError::InvalidRequest(format!(
+            "Invalid metric type. Expected: {}, Got: {}",
+            tournament.metric_type, request.metric_type
+        ))
+        .into_response();
+    }
+
+    // Event-scored tournaments and server-side metrics would double count client reports
+    if !tournament.event_metrics.is_empty() || tournament.metric_type == MetricType::ReferralsMade {
+        return ApiError::Conflict(
+            "Scores for this tournament are computed server-side".to_string(),
         )
-            .into_response();
+        .into_response();
     }
 
     // Validate source is allowed
     if !tournament.allowed_sources.contains(&request.source) {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({
-                "error": format!("Source '{}' not allowed for this tournament", request.source)
-            })),
-        )
-            .into_response();
+        return ApiError::InvalidRequest(format!(
+            "Source '{}' not allowed for this tournament",
+            request.source
+        ))
+        .into_response();
     }
 
     // Determine operation based on metric type
@@ -213,25 +188,19 @@ pub async fn update_score_handler(
     };
 
     // Update score
-    let new_score = match redis
-        .update_user_score(
-            &current_tournament,
-            request.principal_id,
-            request.metric_value,
-            &operation,
-        )
-        .await
+    let new_score = match apply_score_update(
+        &redis,
+        &current_tournament,
+        request.principal_id,
+        request.metric_value,
+        &operation,
+    )
+    .await
     {
-        Ok(score) => score,
+        Ok(update) => update.score,
         Err(e) => {
             log::error!("Failed to update score: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to update score"
-                })),
-            )
-                .into_response();
+            return ApiError::Internal("Failed to update score".to_string()).into_response();
         }
     };
 
@@ -276,7 +245,7 @@ pub async fn update_score_handler(
     tag = "leaderboard",
     responses(
         (status = 200, description = "Leaderboard data retrieved", body = LeaderboardWithTournamentResponse),
-        (status = 404, description = "No active tournament")
+        (status = 404, description = "No active tournament", body = ApiErrorBody)
     )
 )]
 pub async fn get_leaderboard_handler(
@@ -284,7 +253,8 @@ pub async fn get_leaderboard_handler(
     Query(params): Query<LeaderboardQueryParams>,
     State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
-    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone())
+        .with_read_replicas(state.leaderboard_read_replicas.clone());
 
     let start = params.get_start();
     let limit = params.get_limit();
@@ -299,22 +269,11 @@ pub async fn get_leaderboard_handler(
         match redis.get_current_tournament().await {
             Ok(Some(id)) => id,
             Ok(None) => {
-                return (
-                    StatusCode::NOT_FOUND,
-                    Json(serde_json::json!({
-                        "error": "No active tournament"
-                    })),
-                )
-                    .into_response();
+                return ApiError::NotFound("No active tournament".to_string()).into_response();
             }
             Err(e) => {
                 log::error!("Failed to get current tournament: {:?}", e);
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(serde_json::json!({
-                        "error": "Failed to get current tournament"
-                    })),
-                )
+                return ApiError::Internal("Failed to get current tournament".to_string())
                     .into_response();
             }
         }
@@ -324,23 +283,11 @@ pub async fn get_leaderboard_handler(
     let tournament = match redis.get_tournament_info(&tournament_id).await {
         Ok(Some(t)) => t,
         Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({
-                    "error": "Tournament info not found"
-                })),
-            )
-                .into_response();
+            return ApiError::NotFound("Tournament info not found".to_string()).into_response();
         }
         Err(e) => {
             log::error!("Failed to get tournament info: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to get tournament info"
-                })),
-            )
-                .into_response();
+            return ApiError::Internal("Failed to get tournament info".to_string()).into_response();
         }
     };
 
@@ -368,6 +315,13 @@ pub async fn get_leaderboard_handler(
         } else {
             std::collections::HashMap::new()
         };
+    // Sats are shown at the price the prizes were sized with
+    let display_rate = finalized_usd_rate(
+        &tournament.prize_token,
+        saved_results
+            .as_ref()
+            .and_then(|results| results.prize_usd_rate),
+    );
 
     // Get total participants first (needed for rank calculation in ascending order)
     let total_participants = redis
@@ -388,13 +342,7 @@ pub async fn get_leaderboard_handler(
         Ok(data) => data,
         Err(e) => {
             log::error!("Failed to get leaderboard: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to get leaderboard"
-                })),
-            )
-                .into_response();
+            return ApiError::Internal("Failed to get leaderboard".to_string()).into_response();
         }
     };
 
@@ -439,11 +387,7 @@ pub async fn get_leaderboard_handler(
                     // Use saved reward if exists (for winners), None for others
                     let saved_reward = rewards_map.get(&principal).copied();
                     // Convert CKBTC rewards from sats to USD for display (only for saved rewards)
-                    if tournament.prize_token == TokenType::CKBTC {
-                        saved_reward.map(|r| (r as f64 / USD_TO_CKBTC_SATS_RATE) as u64)
-                    } else {
-                        saved_reward
-                    }
+                    saved_reward.map(|r| prize_units_for_display(r, display_rate))
                 } else {
                     // Tournament still active - calculate potential reward
                     // Always use the "real" rank (1 = top prize)
@@ -451,7 +395,11 @@ pub async fn get_leaderboard_handler(
                         SortOrder::Desc => rank,                         // Same as display rank
                         SortOrder::Asc => total_participants - rank + 1, // Convert back to real rank
                     };
-                    calculate_reward(reward_rank, tournament.prize_pool as u64)
+                    tournament.reward_for_rank(
+                        reward_rank,
+                        total_participants,
+                        tournament.prize_pool as u64,
+                    )
                 };
 
                 Some(LeaderboardEntry {
@@ -529,14 +477,14 @@ pub async fn get_leaderboard_handler(
                     // Use saved reward if exists
                     let saved_reward = rewards_map.get(&user_principal).copied();
                     // Convert CKBTC rewards from sats to USD for display (only for saved rewards)
-                    if tournament.prize_token == TokenType::CKBTC {
-                        saved_reward.map(|r| (r as f64 / USD_TO_CKBTC_SATS_RATE) as u64)
-                    } else {
-                        saved_reward
-                    }
+                    saved_reward.map(|r| prize_units_for_display(r, display_rate))
                 } else {
                     // Tournament still active - calculate potential reward
-                    calculate_reward(user_rank, tournament.prize_pool as u64)
+                    tournament.reward_for_rank(
+                        user_rank,
+                        total_participants,
+                        tournament.prize_pool as u64,
+                    )
                 };
 
                 Some(serde_json::json!({
@@ -587,6 +535,7 @@ pub async fn get_leaderboard_handler(
             client_start_time: Some(convert_timestamp_to_timezone(tournament.start_time, tz)),
             client_end_time: Some(convert_timestamp_to_timezone(tournament.end_time, tz)),
             num_winners: tournament.num_winners,
+            prize_distribution: tournament.prize_distribution,
         }
     } else {
         // Fallback when timezone cannot be determined
@@ -603,6 +552,7 @@ pub async fn get_leaderboard_handler(
             client_start_time: None,
             client_end_time: None,
             num_winners: tournament.num_winners,
+            prize_distribution: tournament.prize_distribution,
         }
     };
 
@@ -631,6 +581,7 @@ pub async fn get_leaderboard_handler(
                             tz,
                         )),
                         num_winners: upcoming_tournament.num_winners,
+                        prize_distribution: upcoming_tournament.prize_distribution,
                     }
                 } else {
                     TournamentInfo {
@@ -646,6 +597,7 @@ pub async fn get_leaderboard_handler(
                         client_start_time: None,
                         client_end_time: None,
                         num_winners: upcoming_tournament.num_winners,
+                        prize_distribution: upcoming_tournament.prize_distribution,
                     }
                 };
                 Some(upcoming_info)
@@ -712,26 +664,21 @@ pub async fn get_leaderboard_handler(
     tag = "leaderboard",
     responses(
         (status = 200, description = "User rank data retrieved"),
-        (status = 404, description = "User not found in leaderboard")
+        (status = 404, description = "User not found in leaderboard", body = ApiErrorBody)
     )
 )]
 pub async fn get_user_rank_handler(
     Path(user_id): Path<String>,
     State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
-    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone())
+        .with_read_replicas(state.leaderboard_read_replicas.clone());
 
     // Parse principal ID
     let principal = match Principal::from_text(&user_id) {
         Ok(p) => p,
         Err(_) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({
-                    "error": "Invalid principal ID"
-                })),
-            )
-                .into_response();
+            return ApiError::InvalidRequest("Invalid principal ID".to_string()).into_response();
         }
     };
 
@@ -739,22 +686,11 @@ pub async fn get_user_rank_handler(
     let current_tournament = match redis.get_current_tournament().await {
         Ok(Some(id)) => id,
         Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({
-                    "error": "No active tournament"
-                })),
-            )
-                .into_response();
+            return ApiError::NotFound("No active tournament".to_string()).into_response();
         }
         Err(e) => {
             log::error!("Failed to get current tournament: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to get current tournament"
-                })),
-            )
+            return ApiError::Internal("Failed to get current tournament".to_string())
                 .into_response();
         }
     };
@@ -775,13 +711,7 @@ pub async fn get_user_rank_handler(
             }
             Err(e) => {
                 log::error!("Failed to get user rank: {:?}", e);
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(serde_json::json!({
-                        "error": "Failed to get user rank"
-                    })),
-                )
-                    .into_response();
+                return ApiError::Internal("Failed to get user rank".to_string()).into_response();
             }
         };
 
@@ -800,13 +730,7 @@ pub async fn get_user_rank_handler(
     let tournament = match redis.get_tournament_info(&current_tournament).await {
         Ok(Some(t)) => t,
         _ => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to get tournament info"
-                })),
-            )
-                .into_response();
+            return ApiError::Internal("Failed to get tournament info".to_string()).into_response();
         }
     };
 
@@ -858,8 +782,12 @@ pub async fn get_user_rank_handler(
             .filter_map(|(index, (principal_str, score))| {
                 if let Ok(p) = Principal::from_text(principal_str) {
                     let rank = context_start + index as u32 + 1;
-                    // For active tournaments, calculate_reward already returns the correct value
-                    let reward = calculate_reward(rank, tournament.prize_pool as u64);
+                    // For active tournaments, reward_for_rank already returns the correct value
+                    let reward = tournament.reward_for_rank(
+                        rank,
+                        total_participants,
+                        tournament.prize_pool as u64,
+                    );
 
                     Some(LeaderboardEntry {
                         principal_id: p,
@@ -883,9 +811,9 @@ pub async fn get_user_rank_handler(
     };
 
     // Build user info struct
-    // For active tournaments, calculate_reward already returns the correct value
+    // For active tournaments, reward_for_rank already returns the correct value
     let user_reward = if is_in_leaderboard {
-        calculate_reward(user_rank, tournament.prize_pool as u64)
+        tournament.reward_for_rank(user_rank, total_participants, tournament.prize_pool as u64)
     } else {
         None
     };
@@ -929,21 +857,22 @@ pub async fn get_user_rank_handler(
     (StatusCode::OK, Json(response)).into_response()
 }
 
-// Search users in leaderboard
+// Search current tournament participants by username prefix
 #[utoipa::path(
     get,
     path = "/search",
     tag = "leaderboard",
     responses(
         (status = 200, description = "Search results retrieved"),
-        (status = 404, description = "No active tournament")
+        (status = 404, description = "No active tournament", body = ApiErrorBody)
     )
 )]
 pub async fn search_users_handler(
     Query(params): Query<SearchParams>,
     State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
-    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone())
+        .with_read_replicas(state.leaderboard_read_replicas.clone());
 
     let start = params.get_start();
     let limit = params.get_limit();
@@ -953,56 +882,26 @@ pub async fn search_users_handler(
     let current_tournament = match redis.get_current_tournament().await {
         Ok(Some(id)) => id,
         Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({
-                    "error": "No active tournament"
-                })),
-            )
-                .into_response();
+            return ApiError::NotFound("No active tournament".to_string()).into_response();
         }
         Err(e) => {
             log::error!("Failed to get current tournament: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to get current tournament"
-                })),
-            )
+            return ApiError::Internal("Failed to get current tournament".to_string())
                 .into_response();
         }
     };
 
-    // Search users (this is a simplified version - could be optimized)
-    let search_results = match redis
-        .search_users(
-            &current_tournament,
-            &params.q,
-            limit * 10,
-            sort_order.clone(),
-        )
+    let (paginated_results, total_matches) = match redis
+        .search_users(&current_tournament, &params.q, start, limit, sort_order)
         .await
     {
         Ok(results) => results,
         Err(e) => {
             log::error!("Failed to search users: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to search users"
-                })),
-            )
-                .into_response();
+            return ApiError::Internal("Failed to search users".to_string()).into_response();
         }
     };
 
-    // Apply pagination to search results
-    let paginated_results: Vec<_> = search_results
-        .into_iter()
-        .skip(start as usize)
-        .take(limit as usize)
-        .collect();
-
     // Fetch usernames for results using the fallback strategy
     let principals: Vec<Principal> = paginated_results.iter().map(|(p, _)| *p).collect();
 
@@ -1014,18 +913,12 @@ pub async fn search_users_handler(
     let tournament = match redis.get_tournament_info(&current_tournament).await {
         Ok(Some(t)) => t,
         _ => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to get tournament info"
-                })),
-            )
-                .into_response();
+            return ApiError::Internal("Failed to get tournament info".to_string()).into_response();
         }
     };
 
     // Get total participants for rank calculation in ascending order
-    let _total_participants = redis
+    let total_participants = redis
         .get_total_participants(&current_tournament)
         .await
         .unwrap_or(0);
@@ -1045,8 +938,9 @@ pub async fn search_users_handler(
                 random_username_from_principal(*principal, 15)
             });
 
-            // For active tournaments, calculate_reward already returns the correct value
-            let reward = calculate_reward(rank, tournament.prize_pool as u64);
+            // For active tournaments, reward_for_rank already returns the correct value
+            let reward =
+                tournament.reward_for_rank(rank, total_participants, tournament.prize_pool as u64);
 
             entries.push(LeaderboardEntry {
                 principal_id: *principal,
@@ -1058,14 +952,13 @@ pub async fn search_users_handler(
         }
     }
 
-    let total_count = entries.len() as u32;
-    let has_more = total_count == limit;
+    let has_more = start + limit < total_matches;
     let next_cursor = if has_more { Some(start + limit) } else { None };
 
     let cursor_info = CursorInfo {
         start,
         limit,
-        total_count,
+        total_count: total_matches,
         next_cursor,
         has_more,
     };
@@ -1101,12 +994,7 @@ pub async fn get_tournament_history_handler(
         Ok(ids) => ids,
         Err(e) => {
             log::error!("Failed to get tournament history: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to get tournament history"
-                })),
-            )
+            return ApiError::Internal("Failed to get tournament history".to_string())
                 .into_response();
         }
     };
@@ -1122,6 +1010,11 @@ pub async fn get_tournament_history_handler(
     let mut summaries = Vec::new();
     for tournament_id in &paginated_ids {
         if let Ok(Some(tournament)) = redis.get_tournament_info(tournament_id).await {
+            let total_participants = redis
+                .get_total_participants(tournament_id)
+                .await
+                .unwrap_or(0);
+
             // Get winner (rank 1)
             let winner_info = if let Ok(top_players) = redis
                 .get_leaderboard(tournament_id, 0, 0, SortOrder::Desc)
@@ -1144,8 +1037,10 @@ pub async fn get_tournament_history_handler(
                             }
                         };
 
-                        // For historical tournaments, calculate_reward returns the correct value
-                        let reward = calculate_reward(1, tournament.prize_pool as u64).unwrap_or(0);
+                        // For historical tournaments, reward_for_rank returns the correct value
+                        let reward = tournament
+                            .reward_for_rank(1, total_participants, tournament.prize_pool as u64)
+                            .unwrap_or(0);
 
                         Some(WinnerInfo {
                             principal_id: principal,
@@ -1163,11 +1058,6 @@ pub async fn get_tournament_history_handler(
                 None
             };
 
-            let total_participants = redis
-                .get_total_participants(tournament_id)
-                .await
-                .unwrap_or(0);
-
             summaries.push(TournamentSummary {
                 id: tournament.id.clone(),
                 start_time: tournament.start_time,
@@ -1203,10 +1093,47 @@ pub async fn get_tournament_history_handler(
 }
 
 // Admin: Create new tournament
+#[utoipa::path(
+    post,
+    path = "/tournament/create",
+    tag = "qstash",
+    request_body = CreateTournamentRequest,
+    responses(
+        (status = 201, description = "Tournament created", body = serde_json::Value),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
 pub async fn create_tournament_handler(
     State(state): State<Arc<AppState>>,
     Json(request): Json<CreateTournamentRequest>,
 ) -> impl IntoResponse {
+    let tournament = match create_tournament(&state, request).await {
+        Ok(tournament) => tournament,
+        Err(e) => return e.into_response(),
+    };
+
+    (
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "success": true,
+            "tournament": tournament,
+            "status_message": if tournament.status == TournamentStatus::Active {
+                "Tournament created and started immediately"
+            } else {
+                "Tournament created and scheduled to start"
+            }
+        })),
+    )
+        .into_response()
+}
+
+/// Stores a new tournament, marks it current or upcoming and schedules its
+/// QStash lifecycle. Shared by the QStash job and the admin API.
+pub(crate) async fn create_tournament(
+    state: &Arc<AppState>,
+    request: CreateTournamentRequest,
+) -> Result<Tournament, ApiError> {
     let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
 
     // Generate tournament ID
@@ -1222,12 +1149,29 @@ pub async fn create_tournament_handler(
         TournamentStatus::Upcoming
     };
 
+    // Fold prizes that expired unclaimed back into the new tournament's pool
+    let rollover = match redis.take_prize_pool_rollover(&request.prize_token).await {
+        Ok(amount) => amount,
+        Err(e) => {
+            log::warn!("Failed to read prize pool rollover: {:?}", e);
+            0.0
+        }
+    };
+    if rollover > 0.0 {
+        log::info!(
+            "Adding {} {} of unclaimed prizes to tournament {}",
+            rollover,
+            request.prize_token,
+            tournament_id
+        );
+    }
+
     // Create tournament
     let tournament = Tournament {
         id: tournament_id.clone(),
         start_time: request.start_time,
         end_time: request.end_time,
-        prize_pool: request.prize_pool,
+        prize_pool: request.prize_pool + rollover,
         prize_token: request.prize_token,
         status: status.clone(),
         metric_type: request.metric_type,
@@ -1236,35 +1180,35 @@ pub async fn create_tournament_handler(
         created_at: now,
         updated_at: now,
         num_winners: request.num_winners.unwrap_or(10),
+        prize_distribution: request.prize_distribution,
+        event_metrics: request.event_metrics,
     };
 
     // Store tournament info
     if let Err(e) = redis.set_tournament_info(&tournament).await {
         log::error!("Failed to store tournament info: {:?}", e);
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({
-                "error": "Failed to create tournament"
-            })),
-        )
-            .into_response();
+        if rollover > 0.0 {
+            redis
+                .add_prize_pool_rollover(&tournament.prize_token, rollover)
+                .await
+                .ok();
+        }
+        return Err(ApiError::Internal(
+            "Failed to create tournament".to_string(),
+        ));
     }
 
     // If tournament is active, set as current and schedule finalize
     if status == TournamentStatus::Active {
         if let Err(e) = redis.set_current_tournament(&tournament_id).await {
             log::error!("Failed to set current tournament: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to set current tournament"
-                })),
-            )
-                .into_response();
+            return Err(ApiError::Internal(
+                "Failed to set current tournament".to_string(),
+            ));
         }
 
         // Send start notifications
-        if let Err(e) = super::tournament::start_tournament(&tournament_id, &state).await {
+        if let Err(e) = super::tournament::start_tournament(&tournament_id, state).await {
             log::error!("Failed to send start notifications: {:?}", e);
         }
 
@@ -1273,7 +1217,13 @@ pub async fn create_tournament_handler(
         if delay > 0 {
             if let Err(e) = state
                 .qstash_client
-                .schedule_tournament_finalize(&tournament_id, delay)
+                .publish_job_with(
+                    &TournamentStepJob {
+                        tournament_id: &tournament_id,
+                        step: TournamentStep::Finalize,
+                    },
+                    PublishOptions::delayed_secs(delay),
+                )
                 .await
             {
                 log::error!("Failed to schedule tournament finalize: {:?}", e);
@@ -1300,7 +1250,13 @@ pub async fn create_tournament_handler(
         if delay > 0 {
             if let Err(e) = state
                 .qstash_client
-                .schedule_tournament_start(&tournament_id, delay)
+                .publish_job_with(
+                    &TournamentStepJob {
+                        tournament_id: &tournament_id,
+                        step: TournamentStep::Start,
+                    },
+                    PublishOptions::delayed_secs(delay),
+                )
                 .await
             {
                 log::error!("Failed to schedule tournament start: {:?}", e);
@@ -1315,22 +1271,22 @@ pub async fn create_tournament_handler(
         }
     }
 
-    (
-        StatusCode::CREATED,
-        Json(serde_json::json!({
-            "success": true,
-            "tournament": tournament,
-            "status_message": if status == TournamentStatus::Active {
-                "Tournament created and started immediately"
-            } else {
-                "Tournament created and scheduled to start"
-            }
-        })),
-    )
-        .into_response()
+    Ok(tournament)
 }
 
 // Admin: Finalize tournament and distribute prizes
+#[utoipa::path(
+    post,
+    path = "/tournament/finalize/{id}",
+    tag = "qstash",
+    params(
+        ("id" = String, Path, description = "Tournament ID")
+    ),
+    responses(
+        (status = 200, description = "Tournament finalized and prizes distributed", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
 pub async fn finalize_tournament_handler(
     Path(tournament_id): Path<String>,
     State(state): State<Arc<AppState>>,
@@ -1386,23 +1342,14 @@ pub async fn finalize_tournament_handler(
         }
         Err(e) => {
             log::error!("Failed to finalize tournament {}: {:?}", tournament_id, e);
-            let (status, message) = if e.to_string().contains("not found") {
-                (StatusCode::NOT_FOUND, "Tournament not found")
+            let error = if e.to_string().contains("not found") {
+                ApiError::NotFound(format!("Tournament not found: {}", e))
             } else if e.to_string().contains("not active") {
-                (StatusCode::BAD_REQUEST, "Tournament is not active")
+                ApiError::Conflict(format!("Tournament is not active: {}", e))
             } else {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to finalize tournament",
-                )
+                ApiError::Internal(format!("Failed to finalize tournament: {}", e))
             };
-            (
-                status,
-                Json(serde_json::json!({
-                    "error": format!("{}: {}", message, e)
-                })),
-            )
-                .into_response()
+            error.into_response()
         }
     }
 }
@@ -1414,7 +1361,7 @@ pub async fn finalize_tournament_handler(
     tag = "leaderboard",
     responses(
         (status = 200, description = "Tournament results retrieved"),
-        (status = 404, description = "Tournament not found")
+        (status = 404, description = "Tournament not found", body = ApiErrorBody)
     )
 )]
 pub async fn get_tournament_results_handler(
@@ -1431,23 +1378,11 @@ pub async fn get_tournament_results_handler(
     let tournament = match redis.get_tournament_info(&tournament_id).await {
         Ok(Some(t)) => t,
         Ok(None) => {
-            return (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({
-                    "error": "Tournament not found"
-                })),
-            )
-                .into_response();
+            return ApiError::NotFound("Tournament not found".to_string()).into_response();
         }
         Err(e) => {
             log::error!("Failed to get tournament info: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to get tournament info"
-                })),
-            )
-                .into_response();
+            return ApiError::Internal("Failed to get tournament info".to_string()).into_response();
         }
     };
 
@@ -1473,12 +1408,7 @@ pub async fn get_tournament_results_handler(
         Ok(data) => data,
         Err(e) => {
             log::error!("Failed to get tournament results: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to get tournament results"
-                })),
-            )
+            return ApiError::Internal("Failed to get tournament results".to_string())
                 .into_response();
         }
     };
@@ -1494,6 +1424,13 @@ pub async fn get_tournament_results_handler(
         } else {
             std::collections::HashMap::new()
         };
+    // Sats are shown at the price the prizes were sized with
+    let display_rate = finalized_usd_rate(
+        &tournament.prize_token,
+        saved_results
+            .as_ref()
+            .and_then(|results| results.prize_usd_rate),
+    );
 
     // Collect principals for bulk username fetch
     let principals: Vec<Principal> = leaderboard_data
@@ -1505,6 +1442,12 @@ pub async fn get_tournament_results_handler(
     let username_map =
         get_usernames_with_fallback(&redis, &state.yral_metadata_client, principals.clone()).await;
 
+    // Get total participants
+    let total_participants = redis
+        .get_total_participants(&tournament_id)
+        .await
+        .unwrap_or(0);
+
     // Build result entries
     let entries: Vec<LeaderboardEntry> = leaderboard_data
         .iter()
@@ -1518,14 +1461,14 @@ pub async fn get_tournament_results_handler(
                     // Use saved reward if exists (for winners), None for others
                     let saved_reward = rewards_map.get(&principal).copied();
                     // Convert CKBTC rewards from sats to USD for display (only for saved rewards)
-                    if tournament.prize_token == TokenType::CKBTC {
-                        saved_reward.map(|r| (r as f64 / USD_TO_CKBTC_SATS_RATE) as u64)
-                    } else {
-                        saved_reward
-                    }
+                    saved_reward.map(|r| prize_units_for_display(r, display_rate))
                 } else {
                     // Tournament still active - calculate potential reward
-                    calculate_reward(rank, tournament.prize_pool as u64)
+                    tournament.reward_for_rank(
+                        rank,
+                        total_participants,
+                        tournament.prize_pool as u64,
+                    )
                 };
 
                 // Username is guaranteed to exist for every principal
@@ -1547,12 +1490,6 @@ pub async fn get_tournament_results_handler(
         })
         .collect();
 
-    // Get total participants
-    let total_participants = redis
-        .get_total_participants(&tournament_id)
-        .await
-        .unwrap_or(0);
-
     // Calculate cursor info
     let has_more = (start + limit) < total_participants;
     let next_cursor = if has_more { Some(start + limit) } else { None };
@@ -1576,6 +1513,7 @@ pub async fn get_tournament_results_handler(
         metric_type: tournament.metric_type.to_string(),
         metric_display_name: tournament.metric_display_name,
         num_winners: tournament.num_winners,
+        prize_distribution: tournament.prize_distribution,
     };
 
     // Build response struct
@@ -1589,6 +1527,18 @@ pub async fn get_tournament_results_handler(
 }
 
 // Admin: Start tournament and send notifications
+#[utoipa::path(
+    post,
+    path = "/tournament/start/{id}",
+    tag = "qstash",
+    params(
+        ("id" = String, Path, description = "Tournament ID")
+    ),
+    responses(
+        (status = 200, description = "Tournament started", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
 pub async fn start_tournament_handler(
     Path(tournament_id): Path<String>,
     State(state): State<Arc<AppState>>,
@@ -1604,18 +1554,24 @@ pub async fn start_tournament_handler(
             .into_response(),
         Err(e) => {
             log::error!("Failed to start tournament {}: {:?}", tournament_id, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": format!("Failed to start tournament: {}", e)
-                })),
-            )
-                .into_response()
+            ApiError::Internal(format!("Failed to start tournament: {}", e)).into_response()
         }
     }
 }
 
 // Admin: End tournament manually (just change status to Ended)
+#[utoipa::path(
+    post,
+    path = "/tournament/end/{id}",
+    tag = "qstash",
+    params(
+        ("id" = String, Path, description = "Tournament ID")
+    ),
+    responses(
+        (status = 200, description = "Tournament ended", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
 pub async fn end_tournament_handler(
     Path(tournament_id): Path<String>,
     State(state): State<Arc<AppState>>,
@@ -1631,20 +1587,17 @@ pub async fn end_tournament_handler(
             .into_response(),
         Err(e) => {
             log::error!("Failed to end tournament {}: {:?}", tournament_id, e);
-            let (status, message) = if e.to_string().contains("not found") {
-                (StatusCode::NOT_FOUND, "Tournament not found")
+            let error = if e.to_string().contains("not found") {
+                ApiError::NotFound(format!("Tournament not found: {}", e))
             } else if e.to_string().contains("cannot be ended") {
-                (StatusCode::BAD_REQUEST, "Tournament cannot be ended from current status")
+                ApiError::Conflict(format!(
+                    "Tournament cannot be ended from current status: {}",
+                    e
+                ))
             } else {
-                (StatusCode::INTERNAL_SERVER_ERROR, "Failed to end tournament")
+                ApiError::Internal(format!("Failed to end tournament: {}", e))
             };
-            (
-                status,
-                Json(serde_json::json!({
-                    "error": format!("{}: {}", message, e)
-                })),
-            )
-                .into_response()
+            error.into_response()
         }
     }
 }
@@ -1672,13 +1625,139 @@ pub async fn tournament_lifecycle_check_handler(
             .into_response(),
         Err(e) => {
             log::error!("Tournament lifecycle check failed: {:?}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": format!("Lifecycle check failed: {}", e)
-                })),
-            )
+            ApiError::Internal(format!("Lifecycle check failed: {}", e)).into_response()
+        }
+    }
+}
+
+// Claim an escrowed tournament prize
+#[utoipa::path(
+    post,
+    path = "/claim",
+    tag = "leaderboard",
+    request_body = ClaimPrizeRequest,
+    responses(
+        (status = 200, description = "Prize claimed successfully", body = ClaimPrizeResponse),
+        (status = 401, description = "Invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Claimant not eligible or prize frozen pending review", body = ApiErrorBody),
+        (status = 404, description = "No prize to claim", body = ApiErrorBody),
+        (status = 409, description = "Prize already claimed or claim in progress", body = ApiErrorBody),
+        (status = 410, description = "Prize claim has expired", body = ApiErrorBody),
+        (status = 500, description = "Prize transfer failed", body = ApiErrorBody)
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+pub async fn claim_prize_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ClaimPrizeRequest>,
+) -> impl IntoResponse {
+    let principal = match DelegatedIdentity::try_from(request.delegated_identity_wire)
+        .map_err(|e| e.to_string())
+        .and_then(|identity| identity.sender())
+    {
+        Ok(principal) => principal,
+        Err(e) => {
+            return ApiError::Unauthorized(format!("Invalid delegated identity: {}", e))
+                .into_response();
+        }
+    };
+
+    crate::middleware::set_user_context(principal);
+
+    match super::tournament::claim_prize(&request.tournament_id, principal, &state).await {
+        Ok(claim) => (
+            StatusCode::OK,
+            Json(ClaimPrizeResponse {
+                success: true,
+                claim,
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            log::warn!(
+                "Prize claim failed for {} in tournament {}: {:?}",
+                principal,
+                request.tournament_id,
+                e
+            );
+            ApiError::from(e).into_response()
+        }
+    }
+}
+
+// Internal: Remind winners with unclaimed prizes (scheduled via QStash)
+#[utoipa::path(
+    post,
+    path = "/tournament/claim_reminder/{id}",
+    tag = "qstash",
+    params(
+        ("id" = String, Path, description = "Tournament ID")
+    ),
+    responses(
+        (status = 200, description = "Reminders sent", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn prize_claim_reminder_handler(
+    Path(tournament_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    match super::tournament::send_prize_claim_reminders(&tournament_id, &state).await {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "message": format!("Prize claim reminders sent for tournament {}", tournament_id),
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            log::error!(
+                "Failed to send prize claim reminders for {}: {:?}",
+                tournament_id,
+                e
+            );
+            ApiError::Internal(format!("Failed to send prize claim reminders: {}", e))
                 .into_response()
         }
     }
 }
+
+// Internal: Expire unclaimed prizes back to the pool (scheduled via QStash)
+#[utoipa::path(
+    post,
+    path = "/tournament/expire_claims/{id}",
+    tag = "qstash",
+    params(
+        ("id" = String, Path, description = "Tournament ID")
+    ),
+    responses(
+        (status = 200, description = "Unclaimed prizes expired", body = serde_json::Value),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn expire_prize_claims_handler(
+    Path(tournament_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    match super::tournament::expire_unclaimed_prizes(&tournament_id, &state).await {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "success": true,
+                "message": format!("Unclaimed prizes expired for tournament {}", tournament_id),
+            })),
+        )
+            .into_response(),
+        Err(e) => {
+            log::error!(
+                "Failed to expire prize claims for {}: {:?}",
+                tournament_id,
+                e
+            );
+            ApiError::Internal(format!("Failed to expire prize claims: {}", e)).into_response()
+        }
+    }
+}
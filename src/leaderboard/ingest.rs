@@ -0,0 +1,194 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use candid::Principal;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+use super::{
+    anomaly::apply_score_update,
+    redis_ops::LeaderboardRedis,
+    types::{EventMetricMapping, MetricType, ScoreOperation, Tournament, TournamentStatus},
+};
+use crate::app_state::AppState;
+
+/// Every warehouse event checks the active tournament, so keep it in process briefly
+const ACTIVE_TOURNAMENT_CACHE_TTL: Duration = Duration::from_secs(15);
+
+static ACTIVE_TOURNAMENT: Lazy<RwLock<Option<(Instant, Option<Tournament>)>>> =
+    Lazy::new(|| RwLock::new(None));
+
+async fn active_tournament(state: &AppState) -> Result<Option<Tournament>> {
+    if let Ok(cached) = ACTIVE_TOURNAMENT.read() {
+        if let Some((fetched_at, tournament)) = cached.as_ref() {
+            if fetched_at.elapsed() < ACTIVE_TOURNAMENT_CACHE_TTL {
+                return Ok(tournament.clone());
+            }
+        }
+    }
+
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+    let tournament = match redis.get_current_tournament().await? {
+        Some(id) => redis.get_tournament_info(&id).await?,
+        None => None,
+    };
+
+    if let Ok(mut cached) = ACTIVE_TOURNAMENT.write() {
+        *cached = Some((Instant::now(), tournament.clone()));
+    }
+    Ok(tournament)
+}
+
+/// Principal and score increment an event contributes under `mapping`, if any
+fn score_for_event(
+    mapping: &EventMetricMapping,
+    event_name: &str,
+    params: &Value,
+) -> Option<(Principal, f64)> {
+    if mapping.event != event_name {
+        return None;
+    }
+    if mapping
+        .match_params
+        .iter()
+        .any(|(key, expected)| params.get(key) != Some(expected))
+    {
+        return None;
+    }
+
+    let principal = params
+        .get(&mapping.principal_param)
+        .and_then(Value::as_str)
+        .and_then(|text| Principal::from_text(text).ok())?;
+
+    let value = match &mapping.value_param {
+        Some(param) => match params.get(param)? {
+            Value::Number(number) => number.as_f64()?,
+            Value::String(text) => text.parse().ok()?,
+            _ => return None,
+        },
+        None => 1.0,
+    };
+    if !value.is_finite() || value <= 0.0 {
+        return None;
+    }
+
+    Some((principal, value))
+}
+
+/// Scores the active tournament from a warehouse event when the tournament
+/// allow-lists it, so clients don't have to call `/score/update` as well
+pub async fn ingest_event_score(
+    state: &AppState,
+    event_name: &str,
+    raw_params: &str,
+) -> Result<()> {
+    let Some(tournament) = active_tournament(state).await? else {
+        return Ok(());
+    };
+    if !tournament
+        .event_metrics
+        .iter()
+        .any(|mapping| mapping.event == event_name)
+    {
+        return Ok(());
+    }
+
+    let now = Utc::now().timestamp();
+    if tournament.status != TournamentStatus::Active
+        || now < tournament.start_time
+        || now > tournament.end_time
+    {
+        return Ok(());
+    }
+
+    let params: Value = serde_json::from_str(raw_params)?;
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+    for mapping in &tournament.event_metrics {
+        let Some((principal, value)) = score_for_event(mapping, event_name, &params) else {
+            continue;
+        };
+        apply_score_update(
+            &redis,
+            &tournament.id,
+            principal,
+            value,
+            &ScoreOperation::Increment,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Credits a metric the backend computes itself, such as referrals, when the
+/// active tournament ranks by it
+pub async fn record_server_metric(
+    state: &AppState,
+    metric_type: &MetricType,
+    principal: Principal,
+    value: f64,
+) -> Result<()> {
+    let Some(tournament) = active_tournament(state).await? else {
+        return Ok(());
+    };
+    let now = Utc::now().timestamp();
+    if &tournament.metric_type != metric_type
+        || tournament.status != TournamentStatus::Active
+        || now < tournament.start_time
+        || now > tournament.end_time
+    {
+        return Ok(());
+    }
+
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+    apply_score_update(
+        &redis,
+        &tournament.id,
+        principal,
+        value,
+        &ScoreOperation::Increment,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Spawned from the event pipeline so leaderboard Redis latency never slows ingestion
+pub fn spawn_ingest_event_score(state: Arc<AppState>, event_name: String, raw_params: String) {
+    tokio::spawn(async move {
+        if let Err(e) = ingest_event_score(&state, &event_name, &raw_params).await {
+            log::warn!("Failed to score {event_name} event for leaderboard: {e:?}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_score_for_event() {
+        let principal = Principal::anonymous().to_text();
+        let mapping: EventMetricMapping = serde_json::from_value(json!({
+            "event": "game_played",
+            "value_param": "tokens",
+            "match_params": { "is_win": true },
+        }))
+        .unwrap();
+
+        let won = json!({ "user_id": principal, "tokens": "12.5", "is_win": true });
+        assert_eq!(
+            score_for_event(&mapping, "game_played", &won),
+            Some((Principal::anonymous(), 12.5))
+        );
+
+        let lost = json!({ "user_id": principal, "tokens": 3, "is_win": false });
+        assert_eq!(score_for_event(&mapping, "game_played", &lost), None);
+        assert_eq!(score_for_event(&mapping, "like_video", &won), None);
+    }
+}
@@ -1,5 +1,9 @@
+pub mod admin;
+pub mod anomaly;
 pub mod handlers;
+pub mod ingest;
 pub mod redis_ops;
+pub mod snapshots;
 pub mod tournament;
 pub mod types;
 pub mod utils;
@@ -21,6 +25,15 @@ pub fn leaderboard_router(state: Arc<AppState>) -> OpenApiRouter {
         .routes(routes!(handlers::search_users_handler))
         .routes(routes!(handlers::get_tournament_history_handler))
         .routes(routes!(handlers::get_tournament_results_handler))
+        .routes(routes!(snapshots::get_score_timeseries_handler))
         .routes(routes!(handlers::tournament_lifecycle_check_handler))
+        // Prize escrow
+        .routes(routes!(handlers::claim_prize_handler))
+        // Admin console
+        .routes(routes!(admin::admin_create_tournament))
+        .routes(routes!(admin::admin_update_tournament))
+        .routes(routes!(admin::admin_preview_prizes))
+        .routes(routes!(admin::admin_list_frozen))
+        .routes(routes!(admin::admin_review_frozen))
         .with_state(state)
 }
@@ -5,10 +5,13 @@ use once_cell::sync::Lazy;
 use redis::AsyncCommands;
 use serde_json;
 use sha1::{Digest, Sha1};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
 
 use super::types::*;
 use crate::types::RedisPool;
+use crate::utils::cache::TwoTierCache;
+use crate::utils::read_replicas::ReadReplicas;
 
 // Constants for tie-breaking in leaderboard ranking
 const TIEBREAKER_WEIGHT: f64 = 0.0000000001; // 10^-10, negligible impact on actual scores
@@ -16,12 +19,23 @@ const TIMESTAMP_BASE: i64 = 1_700_000_000; // Base timestamp to keep numbers man
 
 // Lua script for atomic increment - simple and reliable
 // This script needs to be loaded into Redis with SCRIPT LOAD
+// Frozen participants are left untouched. Every increment is also added to a
+// per-participant counter that expires after the velocity window, so the
+// caller can spot impossible score rates.
 const LUA_INCREMENT_SCRIPT: &str = r#"
     local users_key = KEYS[1]
     local scores_key = KEYS[2]
+    local frozen_key = KEYS[3]
+    local velocity_key = KEYS[4]
     local principal = ARGV[1]
     local increment = tonumber(ARGV[2])
     local composite_score_offset = tonumber(ARGV[3])
+    local window_secs = tonumber(ARGV[4])
+    
+    if redis.call('HEXISTS', frozen_key, principal) == 1 then
+        local current = redis.call('HGET', users_key, principal) or '0'
+        return {tostring(current), '0', 1}
+    end
     
     -- Atomically increment the score in the hash (handles nil as 0)
     local new_score = redis.call('HINCRBYFLOAT', users_key, principal, increment)
@@ -36,7 +50,12 @@ const LUA_INCREMENT_SCRIPT: &str = r#"
     -- Update sorted set with composite score
     redis.call('ZADD', scores_key, composite_score, principal)
     
-    return tostring(new_score)
+    local window_gain = redis.call('INCRBYFLOAT', velocity_key, increment)
+    if redis.call('TTL', velocity_key) < 0 then
+        redis.call('EXPIRE', velocity_key, window_secs)
+    end
+    
+    return {tostring(new_score), tostring(window_gain), 0}
 "#;
 
 // Helper function to calculate SHA1 hash of a script
@@ -55,22 +74,75 @@ static LUA_INCREMENT_SCRIPT_SHA: Lazy<String> = Lazy::new(|| {
     sha
 });
 
+// Applies an operator review to a frozen participant, only while the entry
+// is still pending review. An empty ARGV[2] lifts the freeze, anything else
+// replaces the entry. Returns 0 if the participant isn't frozen and -1 if the
+// entry was already reviewed.
+static REVIEW_FROZEN_SCRIPT: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+        local current = redis.call('HGET', KEYS[1], ARGV[1])
+        if not current then return 0 end
+        if cjson.decode(current).status ~= 'pending_review' then return -1 end
+        if ARGV[2] == '' then
+            redis.call('HDEL', KEYS[1], ARGV[1])
+            redis.call('DEL', KEYS[2])
+        else
+            redis.call('HSET', KEYS[1], ARGV[1], ARGV[2])
+        end
+        return 1
+        "#,
+    )
+});
+
+// Tournament info is read on every leaderboard request but only changes on
+// lifecycle transitions and admin edits, all of which go through
+// `set_tournament_info`
+static TOURNAMENT_INFO_CACHE: Lazy<TwoTierCache<Option<Tournament>>> =
+    Lazy::new(|| TwoTierCache::new("leaderboard_tournament_info", 256, Duration::from_secs(60)));
+
+fn username_index_member(username: &str, principal: &Principal) -> String {
+    format!("{}:{}", username.trim().to_lowercase(), principal)
+}
+
+// ZRANGEBYLEX bounds covering every member starting with `query`. 0xff never
+// appears in UTF-8, so it sorts after any continuation of the prefix.
+fn username_prefix_range(query: &str) -> (Vec<u8>, Vec<u8>) {
+    let prefix = query.trim().to_lowercase();
+    let mut min = b"[".to_vec();
+    min.extend_from_slice(prefix.as_bytes());
+    let mut max = min.clone();
+    max.push(0xff);
+    (min, max)
+}
+
 #[derive(Clone)]
 pub struct LeaderboardRedis {
     pool: RedisPool,
+    /// Serve rank and score reads; empty unless set with `with_read_replicas`
+    replicas: ReadReplicas,
     key_prefix: String,
 }
 
 impl LeaderboardRedis {
     pub fn new(pool: RedisPool) -> Self {
+        Self::new_with_prefix(pool, "leaderboard".to_string())
+    }
+
+    pub fn new_with_prefix(pool: RedisPool, key_prefix: String) -> Self {
         Self {
             pool,
-            key_prefix: "leaderboard".to_string(),
+            replicas: ReadReplicas::default(),
+            key_prefix,
         }
     }
 
-    pub fn new_with_prefix(pool: RedisPool, key_prefix: String) -> Self {
-        Self { pool, key_prefix }
+    /// Route rank and score reads (`get_leaderboard`, `get_user_rank`,
+    /// `get_user_score`, `get_total_participants`) to read replicas. Only for
+    /// display paths; anything that pays out must read the primary.
+    pub fn with_read_replicas(mut self, replicas: ReadReplicas) -> Self {
+        self.replicas = replicas;
+        self
     }
 
     // Load the Lua script into Redis and return its SHA
@@ -112,6 +184,62 @@ impl LeaderboardRedis {
         format!("{}:username:{}", self.key_prefix, principal)
     }
 
+    // Lowercase usernames of participants as `<username>:<principal>`, all
+    // scored 0 so ZRANGEBYLEX gives prefix search
+    fn username_index_key(&self, tournament_id: &str) -> String {
+        format!(
+            "{}:tournament:{}:username_index",
+            self.key_prefix, tournament_id
+        )
+    }
+
+    // Principal -> its current member in the username index, so renames can
+    // drop the old entry
+    fn username_index_members_key(&self, tournament_id: &str) -> String {
+        format!(
+            "{}:tournament:{}:username_index:members",
+            self.key_prefix, tournament_id
+        )
+    }
+
+    // Set once the index covers every participant that joined before it existed
+    fn username_index_built_key(&self, tournament_id: &str) -> String {
+        format!(
+            "{}:tournament:{}:username_index:built",
+            self.key_prefix, tournament_id
+        )
+    }
+
+    // Hourly score series for one participant: hour start (unix secs) -> score
+    fn score_series_key(&self, tournament_id: &str, principal: &str) -> String {
+        format!(
+            "{}:tournament:{}:series:{}",
+            self.key_prefix, tournament_id, principal
+        )
+    }
+
+    // Score of each participant at the last snapshot, so unchanged scores are
+    // not written again
+    fn snapshot_scores_key(&self, tournament_id: &str) -> String {
+        format!(
+            "{}:tournament:{}:snapshot_scores",
+            self.key_prefix, tournament_id
+        )
+    }
+
+    // Participants frozen by the anomaly detector: principal -> FrozenEntry JSON
+    fn frozen_participants_key(&self, tournament_id: &str) -> String {
+        format!("{}:tournament:{}:frozen", self.key_prefix, tournament_id)
+    }
+
+    // Score gained by one participant in the current velocity window
+    fn score_velocity_key(&self, tournament_id: &str, principal: &Principal) -> String {
+        format!(
+            "{}:tournament:{}:velocity:{}",
+            self.key_prefix, tournament_id, principal
+        )
+    }
+
     fn current_tournament_key(&self) -> String {
         format!("{}:tournament:current", self.key_prefix)
     }
@@ -132,6 +260,28 @@ impl LeaderboardRedis {
         format!("{}:internal-users", self.key_prefix)
     }
 
+    fn tournament_claims_key(&self, tournament_id: &str) -> String {
+        format!("{}:tournament:{}:claims", self.key_prefix, tournament_id)
+    }
+
+    fn claim_transfer_key(&self, tournament_id: &str, principal: &Principal) -> String {
+        format!(
+            "{}:tournament:{}:claim_transfer:{}",
+            self.key_prefix, tournament_id, principal
+        )
+    }
+
+    fn claim_lock_key(&self, tournament_id: &str, principal: &Principal) -> String {
+        format!(
+            "{}:tournament:{}:claim_lock:{}",
+            self.key_prefix, tournament_id, principal
+        )
+    }
+
+    fn prize_pool_rollover_key(&self, token: &TokenType) -> String {
+        format!("{}:prize_pool_rollover:{}", self.key_prefix, token)
+    }
+
     // Get current active tournament
     pub async fn get_current_tournament(&self) -> Result<Option<String>> {
         let mut conn = self.pool.get().await?;
@@ -176,27 +326,33 @@ impl LeaderboardRedis {
         Ok(users)
     }
 
-    // Get tournament info
+    // Get tournament info, cached in-process until it is next stored
     pub async fn get_tournament_info(&self, tournament_id: &str) -> Result<Option<Tournament>> {
-        let mut conn = self.pool.get().await?;
-        let data: Option<String> = conn.get(self.tournament_info_key(tournament_id)).await?;
-
-        match data {
-            Some(json_str) => {
-                let tournament = serde_json::from_str(&json_str)
-                    .context("Failed to deserialize tournament info")?;
-                Ok(Some(tournament))
-            }
-            None => Ok(None),
-        }
+        let key = self.tournament_info_key(tournament_id);
+        TOURNAMENT_INFO_CACHE
+            .get_or_load(&key, || async {
+                let mut conn = self.pool.get().await?;
+                let data: Option<String> = conn.get(&key).await?;
+
+                match data {
+                    Some(json_str) => {
+                        let tournament = serde_json::from_str(&json_str)
+                            .context("Failed to deserialize tournament info")?;
+                        Ok(Some(tournament))
+                    }
+                    None => Ok(None),
+                }
+            })
+            .await
     }
 
     // Store tournament info
     pub async fn set_tournament_info(&self, tournament: &Tournament) -> Result<()> {
         let mut conn = self.pool.get().await?;
+        let key = self.tournament_info_key(&tournament.id);
         let json_str = serde_json::to_string(tournament)?;
-        conn.set::<_, _, ()>(self.tournament_info_key(&tournament.id), json_str)
-            .await?;
+        conn.set::<_, _, ()>(&key, json_str).await?;
+        TOURNAMENT_INFO_CACHE.invalidate(&key).await;
         Ok(())
     }
 
@@ -208,12 +364,38 @@ impl LeaderboardRedis {
         metric_value: f64,
         operation: &ScoreOperation,
     ) -> Result<f64> {
+        let window_secs = crate::config::runtime::runtime()
+            .leaderboard
+            .velocity_window_secs;
+        self.update_user_score_tracked(
+            tournament_id,
+            principal,
+            metric_value,
+            operation,
+            window_secs,
+        )
+        .await
+        .map(|update| update.score)
+    }
+
+    // Like `update_user_score`, also reporting the score gained within the
+    // last `window_secs` and whether the participant is frozen
+    pub async fn update_user_score_tracked(
+        &self,
+        tournament_id: &str,
+        principal: Principal,
+        metric_value: f64,
+        operation: &ScoreOperation,
+        window_secs: u64,
+    ) -> Result<ScoreUpdate> {
         let mut conn = self.pool.get().await?;
         let scores_key = self.tournament_scores_key(tournament_id);
         let users_key = self.tournament_users_key(tournament_id);
+        let frozen_key = self.frozen_participants_key(tournament_id);
+        let velocity_key = self.score_velocity_key(tournament_id, &principal);
         let timestamp = Utc::now().timestamp();
 
-        let new_score = match operation {
+        let update = match operation {
             ScoreOperation::Increment => {
                 // Calculate the composite score offset in Rust
                 let time_component = (timestamp - TIMESTAMP_BASE) as f64;
@@ -223,18 +405,22 @@ impl LeaderboardRedis {
                 let script_sha = &*LUA_INCREMENT_SCRIPT_SHA;
 
                 // Try EVALSHA first for better performance, fallback to EVAL on NOSCRIPT
-                let score_str: String = {
+                let (score_str, gain_str, frozen): (String, String, i64) = {
                     // First attempt with EVALSHA
-                    let result: Result<String, redis::RedisError> = redis::cmd("EVALSHA")
-                        .arg(script_sha)
-                        .arg(2) // number of keys
-                        .arg(&users_key)
-                        .arg(&scores_key)
-                        .arg(principal.to_string())
-                        .arg(metric_value)
-                        .arg(composite_offset)
-                        .query_async(&mut *conn)
-                        .await;
+                    let result: Result<(String, String, i64), redis::RedisError> =
+                        redis::cmd("EVALSHA")
+                            .arg(script_sha)
+                            .arg(4) // number of keys
+                            .arg(&users_key)
+                            .arg(&scores_key)
+                            .arg(&frozen_key)
+                            .arg(&velocity_key)
+                            .arg(principal.to_string())
+                            .arg(metric_value)
+                            .arg(composite_offset)
+                            .arg(window_secs)
+                            .query_async(&mut *conn)
+                            .await;
 
                     match result {
                         Ok(s) => s,
@@ -253,12 +439,15 @@ impl LeaderboardRedis {
                                 // Script not loaded, use EVAL which loads it automatically
                                 redis::cmd("EVAL")
                                     .arg(LUA_INCREMENT_SCRIPT)
-                                    .arg(2) // number of keys
+                                    .arg(4) // number of keys
                                     .arg(&users_key)
                                     .arg(&scores_key)
+                                    .arg(&frozen_key)
+                                    .arg(&velocity_key)
                                     .arg(principal.to_string())
                                     .arg(metric_value)
                                     .arg(composite_offset)
+                                    .arg(window_secs)
                                     .query_async(&mut *conn)
                                     .await
                                     .map_err(|e| {
@@ -274,10 +463,16 @@ impl LeaderboardRedis {
                     }
                 };
 
-                // Parse the returned string to f64
-                score_str
-                    .parse::<f64>()
-                    .context("Failed to parse score from Lua script")?
+                // Parse the returned strings to f64
+                ScoreUpdate {
+                    score: score_str
+                        .parse::<f64>()
+                        .context("Failed to parse score from Lua script")?,
+                    window_gain: gain_str
+                        .parse::<f64>()
+                        .context("Failed to parse window gain from Lua script")?,
+                    frozen: frozen == 1,
+                }
             }
             ScoreOperation::Set => {
                 return Err(anyhow::anyhow!(
@@ -291,7 +486,100 @@ impl LeaderboardRedis {
             }
         };
 
-        Ok(new_score)
+        // First score in this tournament: index a username cached before the
+        // user joined. Later-cached usernames are indexed by `cache_username`.
+        if !update.frozen && update.score == metric_value {
+            drop(conn);
+            if let Err(e) = self.index_cached_username(tournament_id, principal).await {
+                log::warn!(
+                    "Failed to index username of {} in tournament {}: {:?}",
+                    principal,
+                    tournament_id,
+                    e
+                );
+            }
+        }
+
+        Ok(update)
+    }
+
+    // Freeze a participant; the entry is kept until an operator reviews it.
+    // Returns false if the participant was already frozen.
+    pub async fn freeze_participant(
+        &self,
+        tournament_id: &str,
+        principal: Principal,
+        entry: &FrozenEntry,
+    ) -> Result<bool> {
+        let mut conn = self.pool.get().await?;
+        let key = self.frozen_participants_key(tournament_id);
+        let json_str = serde_json::to_string(entry)?;
+        let added: bool = conn.hset_nx(&key, principal.to_string(), json_str).await?;
+        Ok(added)
+    }
+
+    // Review a frozen participant in one step: `None` lifts the freeze, an
+    // entry replaces it. Entries no longer pending review are left alone.
+    pub async fn review_frozen_entry(
+        &self,
+        tournament_id: &str,
+        principal: Principal,
+        reviewed: Option<&FrozenEntry>,
+    ) -> Result<FrozenReview> {
+        let mut conn = self.pool.get().await?;
+        let json_str = reviewed
+            .map(serde_json::to_string)
+            .transpose()?
+            .unwrap_or_default();
+        let result: i64 = REVIEW_FROZEN_SCRIPT
+            .key(self.frozen_participants_key(tournament_id))
+            .key(self.score_velocity_key(tournament_id, &principal))
+            .arg(principal.to_string())
+            .arg(json_str)
+            .invoke_async(&mut *conn)
+            .await?;
+        Ok(match result {
+            1 => FrozenReview::Applied,
+            0 => FrozenReview::NotFound,
+            _ => FrozenReview::AlreadyReviewed,
+        })
+    }
+
+    pub async fn get_frozen_entry(
+        &self,
+        tournament_id: &str,
+        principal: Principal,
+    ) -> Result<Option<FrozenEntry>> {
+        let mut conn = self.pool.get().await?;
+        let key = self.frozen_participants_key(tournament_id);
+        let json_str: Option<String> = conn.hget(&key, principal.to_string()).await?;
+        json_str
+            .map(|s| serde_json::from_str(&s).context("Failed to deserialize frozen entry"))
+            .transpose()
+    }
+
+    pub async fn get_frozen_entries(
+        &self,
+        tournament_id: &str,
+    ) -> Result<HashMap<Principal, FrozenEntry>> {
+        let mut conn = self.pool.get().await?;
+        let key = self.frozen_participants_key(tournament_id);
+        let entries: HashMap<String, String> = conn.hgetall(&key).await?;
+
+        let mut frozen = HashMap::with_capacity(entries.len());
+        for (principal_str, json_str) in entries {
+            let Ok(principal) = Principal::from_text(&principal_str) else {
+                log::warn!("Invalid principal in frozen entries: {}", principal_str);
+                continue;
+            };
+            match serde_json::from_str(&json_str) {
+                Ok(entry) => {
+                    frozen.insert(principal, entry);
+                }
+                Err(e) => log::warn!("Invalid frozen entry for {}: {}", principal_str, e),
+            }
+        }
+        Ok(frozen)
     }
 
     // Remove user from leaderboard
@@ -302,18 +590,117 @@ impl LeaderboardRedis {
     ) -> Result<()> {
         let mut conn = self.pool.get().await?;
         let key = self.tournament_scores_key(tournament_id);
+        let members_key = self.username_index_members_key(tournament_id);
         conn.zrem::<_, _, ()>(&key, principal.to_string()).await?;
+
+        let member: Option<String> = conn.hget(&members_key, principal.to_string()).await?;
+        if let Some(member) = member {
+            let _: () = redis::pipe()
+                .zrem(self.username_index_key(tournament_id), member)
+                .hdel(&members_key, principal.to_string())
+                .query_async(&mut *conn)
+                .await?;
+        }
         Ok(())
     }
 
-    // Cache username
+    // Cache username, indexing it for search if the user is in the current
+    // tournament
     pub async fn cache_username(&self, principal: Principal, username: &str) -> Result<()> {
         let mut conn = self.pool.get().await?;
         let key = self.username_cache_key(&principal);
         conn.set::<_, _, ()>(&key, username).await?;
+
+        let Some(tournament_id) = conn
+            .get::<_, Option<String>>(self.current_tournament_key())
+            .await?
+        else {
+            return Ok(());
+        };
+        let participating: Option<f64> = conn
+            .zscore(
+                self.tournament_scores_key(&tournament_id),
+                principal.to_string(),
+            )
+            .await?;
+        drop(conn);
+        if participating.is_some() {
+            self.index_username(&tournament_id, principal, username)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // Add or move a participant's entry in the tournament's username index
+    pub async fn index_username(
+        &self,
+        tournament_id: &str,
+        principal: Principal,
+        username: &str,
+    ) -> Result<()> {
+        let mut conn = self.pool.get().await?;
+        let index_key = self.username_index_key(tournament_id);
+        let members_key = self.username_index_members_key(tournament_id);
+        let member = username_index_member(username, &principal);
+
+        let previous: Option<String> = conn.hget(&members_key, principal.to_string()).await?;
+        if previous.as_deref() == Some(member.as_str()) {
+            return Ok(());
+        }
+
+        let mut pipeline = redis::pipe();
+        pipeline.atomic();
+        if let Some(previous) = previous {
+            pipeline.zrem(&index_key, previous);
+        }
+        pipeline
+            .zadd(&index_key, &member, 0)
+            .hset(&members_key, principal.to_string(), &member);
+        let _: () = pipeline.query_async(&mut *conn).await?;
+        Ok(())
+    }
+
+    async fn index_cached_username(&self, tournament_id: &str, principal: Principal) -> Result<()> {
+        if let Some(username) = self.get_cached_username(principal).await? {
+            self.index_username(tournament_id, principal, &username)
+                .await?;
+        }
         Ok(())
     }
 
+    // Index every participant with a cached username. Needed once for
+    // tournaments that started before the index existed; safe to repeat.
+    pub async fn rebuild_username_index(&self, tournament_id: &str) -> Result<u32> {
+        let mut conn = self.pool.get().await?;
+        let scores_key = self.tournament_scores_key(tournament_id);
+        let participants: Vec<String> = conn.zrange(&scores_key, 0, -1).await?;
+        drop(conn);
+
+        let principals: Vec<Principal> = participants
+            .iter()
+            .filter_map(|p| Principal::from_text(p).ok())
+            .collect();
+        let username_map = self.get_cached_usernames_bulk(&principals).await?;
+
+        let mut indexed = 0;
+        for (principal, username) in &username_map {
+            self.index_username(tournament_id, *principal, username)
+                .await?;
+            indexed += 1;
+        }
+
+        let mut conn = self.pool.get().await?;
+        conn.set::<_, _, ()>(self.username_index_built_key(tournament_id), 1)
+            .await?;
+        log::info!(
+            "Built username index for tournament {}: {} of {} participants",
+            tournament_id,
+            indexed,
+            participants.len()
+        );
+        Ok(indexed)
+    }
+
     // Get cached username
     pub async fn get_cached_username(&self, principal: Principal) -> Result<Option<String>> {
         let mut conn = self.pool.get().await?;
@@ -404,15 +791,22 @@ impl LeaderboardRedis {
             return Ok(HashMap::new());
         }
 
-        let mut conn = self.pool.get().await?;
         let key = self.tournament_users_key(tournament_id);
         let mut score_map = HashMap::with_capacity(principals.len());
 
         // Use HMGET to fetch all scores in one call
-        let results: Vec<Option<f64>> = redis::cmd("HMGET")
-            .arg(&key)
-            .arg(principals)
-            .query_async(&mut *conn)
+        let results: Vec<Option<f64>> = self
+            .replicas
+            .read(&self.pool, |mut conn| {
+                let key = key.clone();
+                async move {
+                    redis::cmd("HMGET")
+                        .arg(&key)
+                        .arg(principals)
+                        .query_async(&mut conn)
+                        .await
+                }
+            })
             .await
             .context("Failed to fetch user scores in bulk")?;
 
@@ -434,14 +828,24 @@ impl LeaderboardRedis {
         stop: isize,
         sort_order: SortOrder,
     ) -> Result<Vec<(String, f64)>> {
-        let mut conn = self.pool.get().await?;
         let scores_key = self.tournament_scores_key(tournament_id);
 
         // Get ranking from sorted set (contains composite scores for tie-breaking)
-        let ranked_members: Vec<(String, f64)> = match sort_order {
-            SortOrder::Asc => conn.zrange_withscores(&scores_key, start, stop).await?,
-            SortOrder::Desc => conn.zrevrange_withscores(&scores_key, start, stop).await?,
-        };
+        let ranked_members: Vec<(String, f64)> = self
+            .replicas
+            .read(&self.pool, |mut conn| {
+                let scores_key = scores_key.clone();
+                let sort_order = sort_order.clone();
+                async move {
+                    match sort_order {
+                        SortOrder::Asc => conn.zrange_withscores(&scores_key, start, stop).await,
+                        SortOrder::Desc => {
+                            conn.zrevrange_withscores(&scores_key, start, stop).await
+                        }
+                    }
+                }
+            })
+            .await?;
 
         if ranked_members.is_empty() {
             return Ok(vec![]);
@@ -476,11 +880,16 @@ impl LeaderboardRedis {
         tournament_id: &str,
         principal: Principal,
     ) -> Result<Option<u32>> {
-        let mut conn = self.pool.get().await?;
         let key = self.tournament_scores_key(tournament_id);
 
         // ZREVRANK returns 0-based rank for highest to lowest
-        let rank: Option<isize> = conn.zrevrank(&key, principal.to_string()).await?;
+        let rank: Option<isize> = self
+            .replicas
+            .read(&self.pool, |mut conn| {
+                let key = key.clone();
+                async move { conn.zrevrank(&key, principal.to_string()).await }
+            })
+            .await?;
 
         Ok(rank.map(|r| (r + 1) as u32)) // Convert to 1-based rank
     }
@@ -492,81 +901,171 @@ impl LeaderboardRedis {
         principal: Principal,
     ) -> Result<Option<f64>> {
         // Get score directly from hash
-        let mut conn = self.pool.get().await?;
         let key = self.tournament_users_key(tournament_id);
-        let score: Option<f64> = conn.hget(&key, principal.to_string()).await?;
-        Ok(score)
+        self.replicas
+            .read(&self.pool, |mut conn| {
+                let key = key.clone();
+                async move { conn.hget(&key, principal.to_string()).await }
+            })
+            .await
     }
 
     // Get total participants
     pub async fn get_total_participants(&self, tournament_id: &str) -> Result<u32> {
-        let mut conn = self.pool.get().await?;
         let key = self.tournament_scores_key(tournament_id);
 
-        let count: usize = conn.zcard(&key).await?;
+        let count: usize = self
+            .replicas
+            .read(&self.pool, |mut conn| {
+                let key = key.clone();
+                async move { conn.zcard(&key).await }
+            })
+            .await?;
         Ok(count as u32)
     }
 
-    // Search users by username (using cached usernames)
+    // Prefix search over participants' usernames, case-insensitive. Returns
+    // one page of (principal, score) ordered by leaderboard position, and the
+    // total number of matches.
     pub async fn search_users(
         &self,
         tournament_id: &str,
         query: &str,
+        start: u32,
         limit: u32,
         sort_order: SortOrder,
-    ) -> Result<Vec<(Principal, f64)>> {
+    ) -> Result<(Vec<(Principal, f64)>, u32)> {
         let mut conn = self.pool.get().await?;
+        let built: bool = conn
+            .exists(self.username_index_built_key(tournament_id))
+            .await?;
+        if !built {
+            drop(conn);
+            self.rebuild_username_index(tournament_id).await?;
+            conn = self.pool.get().await?;
+        }
 
-        // Get all participants with scores (sorted by composite score)
-        let scores_key = self.tournament_scores_key(tournament_id);
-        let participants: Vec<(String, f64)> = match sort_order {
-            SortOrder::Asc => conn.zrange_withscores(&scores_key, 0, -1).await?,
-            SortOrder::Desc => conn.zrevrange_withscores(&scores_key, 0, -1).await?,
-        };
+        let (min, max) = username_prefix_range(query);
+        let index_key = self.username_index_key(tournament_id);
+        let members: Vec<String> = redis::cmd("ZRANGEBYLEX")
+            .arg(&index_key)
+            .arg(&min)
+            .arg(&max)
+            .query_async(&mut *conn)
+            .await?;
+        if members.is_empty() {
+            return Ok((vec![], 0));
+        }
 
-        if participants.is_empty() {
-            return Ok(vec![]);
+        // Matches are ordered by name; rank them by composite score so ties
+        // break the same way as the leaderboard
+        let principal_strings: Vec<String> = members
+            .iter()
+            .filter_map(|member| member.rsplit_once(':').map(|(_, p)| p.to_string()))
+            .collect();
+        let mut pipeline = redis::pipe();
+        let scores_key = self.tournament_scores_key(tournament_id);
+        for principal in &principal_strings {
+            pipeline.zscore(&scores_key, principal);
         }
+        let composite_scores: Vec<Option<f64>> = pipeline.query_async(&mut *conn).await?;
+        drop(conn);
 
-        // Collect all principal strings for bulk operations
-        let principal_strings: Vec<String> = participants
+        let mut ranked: Vec<(&String, f64)> = principal_strings
             .iter()
-            .map(|(principal_str, _)| principal_str.clone())
+            .zip(composite_scores)
+            .filter_map(|(principal, score)| score.map(|score| (principal, score)))
             .collect();
+        ranked.sort_by(|a, b| match sort_order {
+            SortOrder::Asc => a.1.total_cmp(&b.1),
+            SortOrder::Desc => b.1.total_cmp(&a.1),
+        });
+        let total = ranked.len() as u32;
+
+        let page: Vec<String> = ranked
+            .into_iter()
+            .skip(start as usize)
+            .take(limit as usize)
+            .map(|(principal, _)| principal.clone())
+            .collect();
+        let score_map = self.get_user_scores_bulk(tournament_id, &page).await?;
 
-        // Parse all valid principals
-        let principals: Vec<Principal> = participants
+        let results = page
             .iter()
-            .filter_map(|(principal_str, _)| Principal::from_text(principal_str).ok())
+            .filter_map(|principal_str| {
+                let principal = Principal::from_text(principal_str).ok()?;
+                let score = score_map.get(principal_str).copied().unwrap_or(0.0);
+                Some((principal, score))
+            })
             .collect();
 
-        // Batch retrieve all usernames and all user scores in parallel
-        let username_map = self.get_cached_usernames_bulk(&principals).await?;
-        let score_map = self
-            .get_user_scores_bulk(tournament_id, &principal_strings)
+        Ok((results, total))
+    }
+
+    // Actual scores of every participant
+    pub async fn get_all_user_scores(&self, tournament_id: &str) -> Result<HashMap<String, f64>> {
+        let mut conn = self.pool.get().await?;
+        let scores: HashMap<String, f64> = conn
+            .hgetall(self.tournament_users_key(tournament_id))
             .await?;
+        Ok(scores)
+    }
 
-        // Filter and build results with actual scores
-        let mut results = Vec::new();
-        let query_lower = query.to_lowercase();
+    // Append `hour`'s score to the series of every participant whose score
+    // changed since the last snapshot. Returns how many series were written.
+    pub async fn record_score_snapshot(
+        &self,
+        tournament_id: &str,
+        hour: i64,
+        scores: &HashMap<String, f64>,
+        series_ttl_secs: i64,
+    ) -> Result<usize> {
+        let mut conn = self.pool.get().await?;
+        let last_key = self.snapshot_scores_key(tournament_id);
+        let last: HashMap<String, f64> = conn.hgetall(&last_key).await?;
 
-        for (principal_str, _composite_score) in participants.iter() {
-            if let Ok(principal) = Principal::from_text(principal_str) {
-                if let Some(username) = username_map.get(&principal) {
-                    if username.to_lowercase().contains(&query_lower) {
-                        // Get actual score from pre-fetched scores
-                        let actual_score = score_map.get(principal_str).copied().unwrap_or(0.0); // Fallback to 0 if no score
+        let changed: Vec<(&String, f64)> = scores
+            .iter()
+            .filter(|(principal, score)| last.get(*principal) != Some(score))
+            .map(|(principal, score)| (principal, *score))
+            .collect();
 
-                        results.push((principal, actual_score));
-                        if results.len() >= limit as usize {
-                            break;
-                        }
-                    }
-                }
+        const BATCH_SIZE: usize = 500;
+        for batch in changed.chunks(BATCH_SIZE) {
+            let mut pipeline = redis::pipe();
+            for (principal, score) in batch {
+                let series_key = self.score_series_key(tournament_id, principal);
+                pipeline
+                    .hset(&series_key, hour, *score)
+                    .ignore()
+                    .expire(&series_key, series_ttl_secs)
+                    .ignore()
+                    .hset(&last_key, *principal, *score)
+                    .ignore();
             }
+            pipeline
+                .query_async::<()>(&mut *conn)
+                .await
+                .context("Failed to write score snapshot batch")?;
+        }
+        if !changed.is_empty() {
+            conn.expire::<_, ()>(&last_key, series_ttl_secs).await?;
         }
 
-        Ok(results)
+        Ok(changed.len())
+    }
+
+    // Recorded hourly scores of one participant, by hour start
+    pub async fn get_score_series(
+        &self,
+        tournament_id: &str,
+        principal: Principal,
+    ) -> Result<BTreeMap<i64, f64>> {
+        let mut conn = self.pool.get().await?;
+        let series: BTreeMap<i64, f64> = conn
+            .hgetall(self.score_series_key(tournament_id, &principal.to_string()))
+            .await?;
+        Ok(series)
     }
 
     // Add tournament to history
@@ -694,6 +1193,168 @@ impl LeaderboardRedis {
         pipeline.query_async::<()>(&mut *conn).await?;
         Ok(())
     }
+
+    // Prize escrow: record claimable prizes for all winners of a tournament
+    pub async fn save_prize_claims(
+        &self,
+        tournament_id: &str,
+        claims: &[PrizeClaim],
+    ) -> Result<()> {
+        if claims.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.pool.get().await?;
+        let key = self.tournament_claims_key(tournament_id);
+
+        let mut pipeline = redis::pipe();
+        for claim in claims {
+            let json_value =
+                serde_json::to_string(claim).context("Failed to serialize prize claim")?;
+            pipeline.hset(&key, claim.principal_id.to_string(), json_value);
+        }
+
+        pipeline.query_async::<()>(&mut *conn).await?;
+        Ok(())
+    }
+
+    // Update a single prize claim
+    pub async fn set_prize_claim(&self, claim: &PrizeClaim) -> Result<()> {
+        let mut conn = self.pool.get().await?;
+        let key = self.tournament_claims_key(&claim.tournament_id);
+        let json_value = serde_json::to_string(claim).context("Failed to serialize prize claim")?;
+
+        conn.hset::<_, _, _, ()>(&key, claim.principal_id.to_string(), json_value)
+            .await?;
+        Ok(())
+    }
+
+    // Get a user's prize claim for a tournament
+    pub async fn get_prize_claim(
+        &self,
+        tournament_id: &str,
+        principal: Principal,
+    ) -> Result<Option<PrizeClaim>> {
+        let mut conn = self.pool.get().await?;
+        let key = self.tournament_claims_key(tournament_id);
+
+        let data: Option<String> = conn.hget(&key, principal.to_string()).await?;
+
+        match data {
+            Some(json_str) => {
+                let claim =
+                    serde_json::from_str(&json_str).context("Failed to deserialize prize claim")?;
+                Ok(Some(claim))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Get all prize claims for a tournament
+    pub async fn get_tournament_prize_claims(
+        &self,
+        tournament_id: &str,
+    ) -> Result<Vec<PrizeClaim>> {
+        let mut conn = self.pool.get().await?;
+        let key = self.tournament_claims_key(tournament_id);
+
+        let data: HashMap<String, String> = conn.hgetall(&key).await?;
+
+        let claims = data
+            .into_values()
+            .filter_map(|json_str| match serde_json::from_str(&json_str) {
+                Ok(claim) => Some(claim),
+                Err(e) => {
+                    log::warn!("Skipping malformed prize claim: {:?}", e);
+                    None
+                }
+            })
+            .collect();
+
+        Ok(claims)
+    }
+
+    // Acquire a short-lived lock so a prize can't be claimed twice concurrently
+    pub async fn acquire_claim_lock(
+        &self,
+        tournament_id: &str,
+        principal: Principal,
+        ttl_secs: u64,
+    ) -> Result<bool> {
+        let mut conn = self.pool.get().await?;
+        let key = self.claim_lock_key(tournament_id, &principal);
+
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(Utc::now().timestamp())
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs)
+            .query_async(&mut *conn)
+            .await?;
+
+        Ok(acquired.is_some())
+    }
+
+    // Marks a prize transfer as started. Never expires, so once the tokens
+    // may have moved no later claim request can transfer again.
+    pub async fn begin_prize_transfer(
+        &self,
+        tournament_id: &str,
+        principal: Principal,
+    ) -> Result<bool> {
+        let mut conn = self.pool.get().await?;
+        let started: Option<String> = redis::cmd("SET")
+            .arg(self.claim_transfer_key(tournament_id, &principal))
+            .arg(Utc::now().timestamp())
+            .arg("NX")
+            .query_async(&mut *conn)
+            .await?;
+
+        Ok(started.is_some())
+    }
+
+    // Clears the marker after a transfer the ledger rejected
+    pub async fn abort_prize_transfer(
+        &self,
+        tournament_id: &str,
+        principal: Principal,
+    ) -> Result<()> {
+        let mut conn = self.pool.get().await?;
+        conn.del::<_, ()>(self.claim_transfer_key(tournament_id, &principal))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn release_claim_lock(
+        &self,
+        tournament_id: &str,
+        principal: Principal,
+    ) -> Result<()> {
+        let mut conn = self.pool.get().await?;
+        conn.del::<_, ()>(self.claim_lock_key(tournament_id, &principal))
+            .await?;
+        Ok(())
+    }
+
+    // Return expired prize amounts to the pool for the next tournament
+    pub async fn add_prize_pool_rollover(&self, token: &TokenType, amount: f64) -> Result<f64> {
+        let mut conn = self.pool.get().await?;
+        let total: f64 = conn
+            .incr(self.prize_pool_rollover_key(token), amount)
+            .await?;
+        Ok(total)
+    }
+
+    // Take (and reset) the rolled-over prize pool amount for a token
+    pub async fn take_prize_pool_rollover(&self, token: &TokenType) -> Result<f64> {
+        let mut conn = self.pool.get().await?;
+        let amount: Option<f64> = redis::cmd("GETDEL")
+            .arg(self.prize_pool_rollover_key(token))
+            .query_async(&mut *conn)
+            .await?;
+        Ok(amount.unwrap_or(0.0))
+    }
 }
 
 #[cfg(test)]
@@ -792,6 +1453,8 @@ mod tests {
                 .unwrap()
                 .as_secs() as i64,
             num_winners: 10,
+            prize_distribution: PrizeDistribution::default(),
+            event_metrics: vec![],
         }
     }
 
@@ -1012,17 +1675,41 @@ mod tests {
         }
 
         // Search for "alice"
-        let results = redis
-            .search_users(&tournament_id, "alice", 10, SortOrder::Desc)
+        let (results, total) = redis
+            .search_users(&tournament_id, "alice", 0, 10, SortOrder::Desc)
             .await
             .expect("Failed to search users");
 
         assert_eq!(results.len(), 2); // Should find "alice" and "alice2"
+        assert_eq!(total, 2);
 
-        // Verify principals match
+        // Verify principals match, highest score first
         let alice_principals: Vec<Principal> = results.iter().map(|(p, _)| *p).collect();
-        assert!(alice_principals.contains(&principals[0]));
-        assert!(alice_principals.contains(&principals[3]));
+        assert_eq!(alice_principals, vec![principals[0], principals[3]]);
+
+        // Prefix only, case-insensitive, paginated
+        let (results, total) = redis
+            .search_users(&tournament_id, "ALI", 1, 1, SortOrder::Desc)
+            .await
+            .expect("Failed to search users");
+        assert_eq!(total, 2);
+        assert_eq!(results[0].0, principals[3]);
+        let (results, _) = redis
+            .search_users(&tournament_id, "lice", 0, 10, SortOrder::Desc)
+            .await
+            .expect("Failed to search users");
+        assert!(results.is_empty());
+
+        // Renames replace the old index entry
+        redis
+            .index_username(&tournament_id, principals[0], "zed")
+            .await
+            .expect("Failed to index username");
+        let (_, total) = redis
+            .search_users(&tournament_id, "alice", 0, 10, SortOrder::Desc)
+            .await
+            .expect("Failed to search users");
+        assert_eq!(total, 1);
 
         // Cleanup
         test_redis.cleanup().await.expect("Failed to cleanup");
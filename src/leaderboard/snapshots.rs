@@ -0,0 +1,275 @@
+//! Hourly leaderboard snapshots for score-over-time charts.
+//!
+//! The `leaderboard_snapshot` schedule records, for the active tournament,
+//! the score of every participant whose score moved since the previous run
+//! into a per-participant Redis hash keyed by hour, and appends the top
+//! [`TOP_N`] standings to BigQuery for longer-term analysis. Charts read the
+//! Redis series through `GET /{tournament_id}/timeseries/{principal}`, with
+//! hours without a write carrying the previous score forward.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use candid::Principal;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    redis_ops::LeaderboardRedis,
+    types::{SortOrder, TournamentStatus},
+};
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+};
+
+const HOUR_SECS: i64 = 60 * 60;
+
+/// Standings appended to BigQuery on every run
+const TOP_N: isize = 100;
+
+/// Series outlive their tournament long enough for post-mortem charts
+const SERIES_TTL_SECS: i64 = 90 * 24 * HOUR_SECS;
+
+fn hour_start(timestamp: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(HOUR_SECS)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LeaderboardSnapshotRow {
+    tournament_id: String,
+    snapshot_at: String,
+    rank: u32,
+    principal_id: String,
+    score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SnapshotSummary {
+    /// None when no tournament is active
+    pub tournament_id: Option<String>,
+    pub hour: i64,
+    pub participants: usize,
+    /// Participants whose score changed since the previous snapshot
+    pub series_written: usize,
+    pub top_n_recorded: usize,
+}
+
+#[cfg(not(feature = "local-bin"))]
+async fn insert_top_n_to_bigquery(
+    bq_client: &google_cloud_bigquery::client::Client,
+    rows: Vec<LeaderboardSnapshotRow>,
+) -> Result<()> {
+    use google_cloud_bigquery::http::tabledata::insert_all::{InsertAllRequest, Row};
+
+    let request = InsertAllRequest {
+        rows: rows
+            .into_iter()
+            .map(|row| Row {
+                insert_id: Some(format!(
+                    "{}:{}:{}",
+                    row.tournament_id, row.snapshot_at, row.rank
+                )),
+                json: row,
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    let res = bq_client
+        .tabledata()
+        .insert(
+            "hot-or-not-feed-intelligence",
+            "yral_ds",
+            "leaderboard_snapshots",
+            &request,
+        )
+        .await?;
+
+    if let Some(errors) = res.insert_errors {
+        if !errors.is_empty() {
+            log::error!("leaderboard_snapshots insert errors: {errors:?}");
+            anyhow::bail!("Failed to insert leaderboard snapshots to bigquery");
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn run_leaderboard_snapshot(state: &AppState) -> Result<SnapshotSummary> {
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+    let now = Utc::now();
+    let hour = hour_start(now.timestamp());
+    let mut summary = SnapshotSummary {
+        tournament_id: None,
+        hour,
+        participants: 0,
+        series_written: 0,
+        top_n_recorded: 0,
+    };
+
+    let Some(tournament_id) = redis.get_current_tournament().await? else {
+        return Ok(summary);
+    };
+    match redis.get_tournament_info(&tournament_id).await? {
+        Some(tournament) if tournament.status == TournamentStatus::Active => {}
+        _ => return Ok(summary),
+    }
+    summary.tournament_id = Some(tournament_id.clone());
+
+    let scores = redis.get_all_user_scores(&tournament_id).await?;
+    summary.participants = scores.len();
+    summary.series_written = redis
+        .record_score_snapshot(&tournament_id, hour, &scores, SERIES_TTL_SECS)
+        .await?;
+
+    let top = redis
+        .get_leaderboard(&tournament_id, 0, TOP_N - 1, SortOrder::Desc)
+        .await?;
+    let snapshot_at = chrono::DateTime::from_timestamp(hour, 0)
+        .unwrap_or(now)
+        .to_rfc3339();
+    let rows: Vec<LeaderboardSnapshotRow> = top
+        .into_iter()
+        .enumerate()
+        .map(|(i, (principal_id, score))| LeaderboardSnapshotRow {
+            tournament_id: tournament_id.clone(),
+            snapshot_at: snapshot_at.clone(),
+            rank: i as u32 + 1,
+            principal_id,
+            score,
+        })
+        .collect();
+    summary.top_n_recorded = rows.len();
+
+    #[cfg(not(feature = "local-bin"))]
+    if !rows.is_empty() {
+        insert_top_n_to_bigquery(&state.bigquery_client, rows).await?;
+    }
+
+    Ok(summary)
+}
+
+/// QStash scheduled job: hourly leaderboard snapshot
+#[utoipa::path(
+    post,
+    path = "/leaderboard/snapshot",
+    tag = "qstash",
+    responses(
+        (status = 200, description = "Snapshot summary", body = SnapshotSummary),
+        (status = 500, description = "Snapshot failed", body = ApiErrorBody)
+    )
+)]
+pub async fn leaderboard_snapshot_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match run_leaderboard_snapshot(&state).await {
+        Ok(summary) => {
+            log::info!("Leaderboard snapshot completed: {:?}", summary);
+            (StatusCode::OK, Json(summary)).into_response()
+        }
+        Err(e) => {
+            log::error!("Leaderboard snapshot failed: {:?}", e);
+            ApiError::Internal(format!("Leaderboard snapshot failed: {}", e)).into_response()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ScorePoint {
+    /// Start of the hour, unix seconds
+    pub hour: i64,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ScoreTimeseriesResponse {
+    pub tournament_id: String,
+    pub principal_id: String,
+    pub metric_display_name: String,
+    /// One point per hour from the tournament start to its end or now
+    pub points: Vec<ScorePoint>,
+}
+
+/// One point per hour in `[from, to]`, carrying the last recorded score
+/// forward; hours before the first record score 0
+fn hourly_progression(series: &BTreeMap<i64, f64>, from: i64, to: i64) -> Vec<ScorePoint> {
+    let mut points = Vec::new();
+    let mut hour = hour_start(from);
+    while hour <= to {
+        let score = series
+            .range(..=hour)
+            .next_back()
+            .map_or(0.0, |(_, score)| *score);
+        points.push(ScorePoint { hour, score });
+        hour += HOUR_SECS;
+    }
+    points
+}
+
+/// Hourly score progression of one participant
+#[utoipa::path(
+    get,
+    path = "/{tournament_id}/timeseries/{principal}",
+    tag = "leaderboard",
+    params(
+        ("tournament_id" = String, Path, description = "Tournament ID"),
+        ("principal" = String, Path, description = "Participant principal")
+    ),
+    responses(
+        (status = 200, description = "Hourly scores", body = ScoreTimeseriesResponse),
+        (status = 400, description = "Invalid principal", body = ApiErrorBody),
+        (status = 404, description = "Tournament not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+pub async fn get_score_timeseries_handler(
+    Path((tournament_id, principal)): Path<(String, String)>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<ScoreTimeseriesResponse>, ApiError> {
+    let principal = Principal::from_text(&principal)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid principal: {e}")))?;
+    let redis = LeaderboardRedis::new(state.leaderboard_redis_pool.clone());
+
+    let tournament = redis
+        .get_tournament_info(&tournament_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Tournament not found".to_string()))?;
+    let series = redis.get_score_series(&tournament_id, principal).await?;
+
+    let until = tournament.end_time.min(Utc::now().timestamp());
+    Ok(Json(ScoreTimeseriesResponse {
+        tournament_id,
+        principal_id: principal.to_text(),
+        metric_display_name: tournament.metric_display_name,
+        points: hourly_progression(&series, tournament.start_time, until),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hourly_progression_carries_scores_forward() {
+        let start = 1_700_000_000;
+        let first_hour = hour_start(start);
+        let series = BTreeMap::from([
+            (first_hour + HOUR_SECS, 10.0),
+            (first_hour + 3 * HOUR_SECS, 25.0),
+        ]);
+
+        let points = hourly_progression(&series, start, first_hour + 4 * HOUR_SECS);
+        let scores: Vec<f64> = points.iter().map(|p| p.score).collect();
+        assert_eq!(scores, vec![0.0, 10.0, 10.0, 25.0, 25.0]);
+        assert_eq!(points[0].hour, first_hour);
+
+        // Not started yet
+        assert!(hourly_progression(&series, start, start - HOUR_SECS).is_empty());
+    }
+}
@@ -1,24 +1,45 @@
 use anyhow::{Context, Result};
 use candid::Principal;
 use chrono::Utc;
-use futures::stream::{self, StreamExt};
 use serde_json::json;
 use std::sync::Arc;
 use yral_canisters_client::user_info_service::{SessionType, UserInfoService};
-use yral_canisters_common::utils::token::{
-    CkBtcOperations, SatsOperations, TokenOperations, TokenOperationsProvider,
-};
 use yral_username_gen::random_username_from_principal;
 
-// Conversion rate: 1 USD = 886 SATS (ckBTC satoshis)
-const USD_TO_CKBTC_SATS_RATE: f64 = 886.0;
+// Results finalized before prize rates were recorded used 886 sats per USD
+const LEGACY_CKBTC_USD_PRICE: f64 = 100_000_000.0 / 886.0;
+
+// Maximum ckBTC prize per winner (in sats)
+pub(crate) const MAX_CKBTC_PRIZE_SATS: u64 = 50000;
+
+// Winners have 7 days to claim their prize before it returns to the pool
+const PRIZE_CLAIM_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+
+// Remind unclaimed winners 1 day before their claim window closes
+const PRIZE_CLAIM_REMINDER_LEAD_SECS: i64 = 24 * 60 * 60;
+
+// QStash may deliver a scheduled start slightly early
+const START_SCHEDULE_TOLERANCE_SECS: i64 = 60;
+
+// Guards against double transfers from concurrent claim requests
+const CLAIM_LOCK_TTL_SECS: u64 = 60;
 
 use crate::canister::utils::get_user_principal_canister_list_v2;
+use crate::events::notification_templates::NotificationCopy;
+use crate::events::push_notifications::NotificationBatch;
 use crate::{
     app_state::AppState,
+    canister::agent_pool,
     consts::USER_INFO_SERVICE_CANISTER_ID,
+    error::ApiError,
     events::types::{EventPayload, TournamentEndedWinnerPayload, TournamentStartedPayload},
     leaderboard::TokenType,
+    qstash::job::{PublishOptions, TournamentStep, TournamentStepJob},
+    tokens::{
+        pricing::{self, UsdRate},
+        transfer_memo, Token, TokenWallet, TransferError,
+    },
+    webhook_subscriptions::{self, WebhookEvent},
 };
 use yral_metadata_types::{
     NotificationPayload, SendNotificationReq, WebpushConfig, WebpushFcmOptions,
@@ -27,17 +48,63 @@ use yral_metadata_types::{
 use super::{
     redis_ops::LeaderboardRedis,
     types::{
-        calculate_reward, LeaderboardEntry, TournamentResult, TournamentStatus, UserLastTournament,
+        LeaderboardEntry, PrizeClaim, PrizeClaimStatus, TournamentResult, TournamentStatus,
+        UserLastTournament,
     },
 };
 
+/// Live price that values a USD-denominated prize pool; None when the pool
+/// is already in token units
+pub(crate) async fn prize_usd_rate(prize_token: &TokenType) -> Result<Option<UsdRate>> {
+    match prize_token {
+        TokenType::CKBTC => pricing::usd_rate(Token::CkBtc).await.map(Some),
+        TokenType::YRAL => Ok(None),
+    }
+}
+
+/// Price prize units of a finalized tournament are shown and rolled over at,
+/// given the price recorded at finalization
+pub(crate) fn finalized_usd_rate(
+    prize_token: &TokenType,
+    recorded: Option<f64>,
+) -> Option<UsdRate> {
+    match prize_token {
+        TokenType::CKBTC => Some(UsdRate::new(
+            Token::CkBtc,
+            recorded.unwrap_or(LEGACY_CKBTC_USD_PRICE),
+        )),
+        TokenType::YRAL => None,
+    }
+}
+
+/// Converts a prize pool into the units rewards are paid out in
+pub(crate) fn prize_pool_in_units(prize_pool: f64, usd_rate: Option<UsdRate>) -> u64 {
+    match usd_rate {
+        // USD to ckBTC sats: e.g., $100 at $100,000 per BTC = 100,000 sats
+        Some(rate) => rate.usd_to_units(prize_pool),
+        // YRAL uses the prize_pool value directly (already in YRAL units)
+        None => prize_pool as u64,
+    }
+}
+
+/// Prize units shown in pool units (USD for CKBTC)
+pub(crate) fn prize_units_for_display(units: u64, usd_rate: Option<UsdRate>) -> u64 {
+    match usd_rate {
+        Some(rate) => rate.units_to_usd(units) as u64,
+        None => units,
+    }
+}
+
 /// Checks if user is registered via user info service
 async fn check_user_registration(user_principal: Principal, app_state: &Arc<AppState>) -> bool {
     let user_info_service = UserInfoService(*USER_INFO_SERVICE_CANISTER_ID, &app_state.agent);
 
-    let result = match user_info_service
-        .get_user_session_type(user_principal)
-        .await
+    let result = match agent_pool::query(
+        *USER_INFO_SERVICE_CANISTER_ID,
+        "get_user_session_type",
+        || user_info_service.get_user_session_type(user_principal),
+    )
+    .await
     {
         Ok(result) => result,
         Err(e) => {
@@ -88,6 +155,19 @@ pub async fn start_tournament(tournament_id: &str, app_state: &Arc<AppState>) ->
         ));
     }
 
+    // An admin edit can push the start time back after a start job was
+    // scheduled; the edit schedules a fresh job, so the stale one is a no-op
+    if tournament.status == TournamentStatus::Upcoming
+        && tournament.start_time > Utc::now().timestamp() + START_SCHEDULE_TOLERANCE_SECS
+    {
+        log::info!(
+            "Ignoring early start for tournament {} scheduled at {}",
+            tournament_id,
+            tournament.start_time
+        );
+        return Ok(());
+    }
+
     // Only update status if it's not already Active
     if tournament.status != TournamentStatus::Active {
         // Update tournament status to Active
@@ -128,7 +208,13 @@ pub async fn start_tournament(tournament_id: &str, app_state: &Arc<AppState>) ->
     if delay > 0 {
         if let Err(e) = app_state
             .qstash_client
-            .schedule_tournament_finalize(tournament_id, delay)
+            .publish_job_with(
+                &TournamentStepJob {
+                    tournament_id,
+                    step: TournamentStep::Finalize,
+                },
+                PublishOptions::delayed_secs(delay),
+            )
             .await
         {
             log::error!("Failed to schedule tournament finalize: {:?}", e);
@@ -151,7 +237,7 @@ pub async fn start_tournament(tournament_id: &str, app_state: &Arc<AppState>) ->
     Ok(())
 }
 
-/// Finalize a tournament: calculate winners, distribute prizes, and send notifications
+/// Finalize a tournament: calculate winners, escrow prizes for claiming, and send notifications
 pub async fn finalize_tournament(tournament_id: &str, app_state: &Arc<AppState>) -> Result<()> {
     let redis = LeaderboardRedis::new(app_state.leaderboard_redis_pool.clone());
 
@@ -166,6 +252,9 @@ pub async fn finalize_tournament(tournament_id: &str, app_state: &Arc<AppState>)
         return Err(anyhow::anyhow!("Tournament is not active, cannot finalize"));
     }
 
+    // Priced before anything changes so a missing price leaves the tournament retryable
+    let usd_rate = prize_usd_rate(&tournament.prize_token).await?;
+
     // Update tournament status to Finalizing
     tournament.status = TournamentStatus::Finalizing;
     tournament.updated_at = Utc::now().timestamp();
@@ -192,6 +281,9 @@ pub async fn finalize_tournament(tournament_id: &str, app_state: &Arc<AppState>)
         ));
     }
 
+    // Percentile-bucket distributions size their buckets by participant count
+    let total_participants = redis.get_total_participants(tournament_id).await?;
+
     // Calculate prize distribution and prepare for token distribution
     let mut distribution_tasks = Vec::new();
 
@@ -221,21 +313,13 @@ pub async fn finalize_tournament(tournament_id: &str, app_state: &Arc<AppState>)
                 continue;
             }
 
-            // Convert prize pool based on token type
-            let prize_pool_in_units = match tournament.prize_token {
-                TokenType::CKBTC => {
-                    // Convert USD to ckBTC sats: e.g., $100 * 886 = 88,600 sats
-                    (tournament.prize_pool * USD_TO_CKBTC_SATS_RATE) as u64
-                }
-                TokenType::YRAL => {
-                    // YRAL uses the prize_pool value directly (already in YRAL units)
-                    tournament.prize_pool as u64
-                }
-            };
+            let prize_pool_in_units = prize_pool_in_units(tournament.prize_pool, usd_rate);
 
-            if let Some(reward) = calculate_reward(rank, prize_pool_in_units) {
+            if let Some(reward) =
+                tournament.reward_for_rank(rank, total_participants, prize_pool_in_units)
+            {
                 // Check for CKBTC reward limit
-                if tournament.prize_token == TokenType::CKBTC && reward > 50000 {
+                if tournament.prize_token == TokenType::CKBTC && reward > MAX_CKBTC_PRIZE_SATS {
                     log::error!(
                         "CKBTC reward {} sats exceeds 50000 limit for user {} (rank {}) in tournament {}. Skipping distribution.",
                         reward, principal, rank, tournament_id
@@ -247,71 +331,68 @@ pub async fn finalize_tournament(tournament_id: &str, app_state: &Arc<AppState>)
         }
     }
 
-    // Distribute prizes based on token type
-    if !distribution_tasks.is_empty() {
-        // Create appropriate token operations provider based on token type
-        let token_ops = match tournament.prize_token {
-            TokenType::YRAL => {
-                // Get JWT token from environment for YRAL/SATS
-                let jwt_token = std::env::var("YRAL_HON_WORKER_JWT").ok();
-                Arc::new(TokenOperationsProvider::Sats(SatsOperations::new(
-                    jwt_token,
-                )))
-            }
-            TokenType::CKBTC => {
-                // Use admin agent for direct ckBTC transfers
-                Arc::new(TokenOperationsProvider::CkBtc(CkBtcOperations::new(
-                    app_state.agent.clone(),
-                )))
-            }
-        };
+    // Prizes of entries under anomaly review are held until an operator decides
+    let frozen = redis.get_frozen_entries(tournament_id).await?;
 
-        let token_name = tournament.prize_token.to_string();
-
-        // Process distributions concurrently, 5 at a time
-        let results: Vec<_> = stream::iter(distribution_tasks.clone())
-            .map(|(principal, reward, rank, _)| {
-                let token_ops = token_ops.clone();
-                let token_name = token_name.clone();
-                async move {
-                    match token_ops.add_balance(principal, reward).await {
-                        Ok(_) => {
-                            log::info!(
-                                "Distributed {} {} to {} (rank {})",
-                                reward,
-                                token_name,
-                                principal,
-                                rank
-                            );
-                            Ok((principal, reward, rank))
-                        }
-                        Err(e) => {
-                            log::error!(
-                                "Failed to distribute {} {} to {} (rank {}): {:?}",
-                                reward,
-                                token_name,
-                                principal,
-                                rank,
-                                e
-                            );
-                            Err((principal, reward, rank, e))
-                        }
-                    }
-                }
-            })
-            .buffer_unordered(5) // Process 5 concurrent requests at a time
-            .collect()
-            .await;
+    // Escrow prizes instead of pushing payouts; winners claim them within the window
+    let now = Utc::now().timestamp();
+    let claims: Vec<PrizeClaim> = distribution_tasks
+        .iter()
+        .map(|(principal, reward, rank, _)| PrizeClaim {
+            tournament_id: tournament_id.to_string(),
+            principal_id: *principal,
+            rank: *rank,
+            amount: *reward,
+            prize_token: tournament.prize_token.clone(),
+            status: if frozen.contains_key(principal) {
+                PrizeClaimStatus::Frozen
+            } else {
+                PrizeClaimStatus::Claimable
+            },
+            created_at: now,
+            expires_at: now + PRIZE_CLAIM_WINDOW_SECS,
+            claimed_at: None,
+        })
+        .collect();
 
-        // Log summary
-        let successful = results.iter().filter(|r| r.is_ok()).count();
-        let failed = results.iter().filter(|r| r.is_err()).count();
-        log::info!(
-            "{} distribution complete: {} successful, {} failed",
-            token_name,
-            successful,
-            failed
-        );
+    redis.save_prize_claims(tournament_id, &claims).await?;
+    log::info!(
+        "Recorded {} claimable prizes for tournament {}",
+        claims.len(),
+        tournament_id
+    );
+
+    if !claims.is_empty() {
+        if let Err(e) = app_state
+            .qstash_client
+            .publish_job_with(
+                &TournamentStepJob {
+                    tournament_id,
+                    step: TournamentStep::ClaimReminder,
+                },
+                PublishOptions::delayed_secs(
+                    PRIZE_CLAIM_WINDOW_SECS - PRIZE_CLAIM_REMINDER_LEAD_SECS,
+                ),
+            )
+            .await
+        {
+            log::error!("Failed to schedule prize claim reminder: {:?}", e);
+        }
+
+        // Small buffer so the expiry job never runs before the window closes
+        if let Err(e) = app_state
+            .qstash_client
+            .publish_job_with(
+                &TournamentStepJob {
+                    tournament_id,
+                    step: TournamentStep::ExpireClaims,
+                },
+                PublishOptions::delayed_secs(PRIZE_CLAIM_WINDOW_SECS + 60),
+            )
+            .await
+        {
+            log::error!("Failed to schedule prize claim expiry: {:?}", e);
+        }
     }
 
     // Build and save tournament results for winners
@@ -347,6 +428,16 @@ pub async fn finalize_tournament(tournament_id: &str, app_state: &Arc<AppState>)
 
         total_prize_distributed += reward;
 
+        if frozen.contains_key(principal) {
+            log::info!(
+                "Holding winner notification for frozen {} (rank {}) in tournament {}",
+                principal,
+                rank,
+                tournament_id
+            );
+            continue;
+        }
+
         // Send winner notification
         let winner_payload = TournamentEndedWinnerPayload {
             user_id: *principal,
@@ -382,6 +473,7 @@ pub async fn finalize_tournament(tournament_id: &str, app_state: &Arc<AppState>)
             .unwrap_or(0),
         total_prize_distributed,
         finalized_at: Utc::now().timestamp(),
+        prize_usd_rate: usd_rate.map(|rate| rate.usd_per_token),
     };
 
     // Save tournament results
@@ -405,7 +497,11 @@ pub async fn finalize_tournament(tournament_id: &str, app_state: &Arc<AppState>)
     for (index, (principal_str, _score)) in all_participants.iter().enumerate() {
         if let Ok(principal) = Principal::from_text(principal_str) {
             let rank = (index + 1) as u32;
-            let reward = calculate_reward(rank, tournament.prize_pool as u64);
+            let reward = tournament.reward_for_rank(
+                rank,
+                all_participants.len() as u32,
+                tournament.prize_pool as u64,
+            );
 
             let last_tournament_info = UserLastTournament {
                 tournament_id: tournament_id.to_string(),
@@ -451,6 +547,20 @@ pub async fn finalize_tournament(tournament_id: &str, app_state: &Arc<AppState>)
         }
     }
 
+    webhook_subscriptions::dispatch(
+        app_state,
+        WebhookEvent::TournamentFinalized,
+        json!({
+            "tournament_id": tournament_id,
+            "total_participants": tournament_result.total_participants,
+            "total_prize_distributed": tournament_result.total_prize_distributed,
+            "prize_token": tournament.prize_token,
+            "finalized_at": tournament_result.finalized_at,
+            "winners": tournament_result.user_results,
+        }),
+    )
+    .await;
+
     log::info!("Tournament {} finalized successfully", tournament_id);
 
     Ok(())
@@ -502,13 +612,17 @@ pub async fn end_tournament(tournament_id: &str, app_state: &Arc<AppState>) -> R
 }
 
 /// Send broadcast notification for tournament start
-/// Sends notifications to all users with a concurrency limit of 500
+/// Sends notifications to all users via provider-side multicast
 async fn send_tournament_start_broadcast(
     payload: &TournamentStartedPayload,
     app_state: &Arc<AppState>,
 ) -> Result<()> {
-    let title = "New Tournament Started!";
-    let body = "The new YRAL tournament is live!  Play to climb the leaderboard and win rewards.";
+    // Multicast is addressed by user, so everyone gets the default locale
+    let (title, body) = app_state
+        .notification_client
+        .templates()
+        .render(&NotificationCopy::new("tournament_started"), None)
+        .await;
 
     // Create notification payload
     let notif_payload = SendNotificationReq {
@@ -587,36 +701,13 @@ async fn send_tournament_start_broadcast(
         payload.tournament_id
     );
 
-    // Send notifications concurrently with a limit of 500
-    let notification_client = app_state.notification_client.clone();
-    let notif_payload_arc = Arc::new(notif_payload);
-
-    let sent_count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
-
-    stream::iter(users)
-        .for_each_concurrent(500, |user_principal| {
-            let client = notification_client.clone();
-            let payload = notif_payload_arc.clone();
-            let sent = sent_count.clone();
-
-            async move {
-                // Send notification and track result
-                client
-                    .send_notification((*payload).clone(), user_principal)
-                    .await;
-
-                // Track progress
-                let count = sent.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
-
-                // Log progress every 100 notifications
-                if count.is_multiple_of(100) {
-                    log::info!("Notification progress: {}/{} sent", count, total_users);
-                }
-            }
-        })
+    // Same payload for everyone: one multicast send per batch of recipients
+    let statuses = app_state
+        .notification_client
+        .send_multicast(&notif_payload, &users)
         .await;
 
-    let final_sent = sent_count.load(std::sync::atomic::Ordering::Relaxed);
+    let final_sent = statuses.iter().filter(|s| s.delivered).count();
 
     log::info!(
         "Tournament broadcast completed: {}/{} notifications sent successfully for tournament {}",
@@ -649,3 +740,423 @@ pub async fn check_tournament_lifecycle(app_state: &Arc<AppState>) -> Result<()>
 
     Ok(())
 }
+
+/// Why a prize claim was refused
+#[derive(Debug, thiserror::Error)]
+pub enum PrizeClaimError {
+    #[error("Prize claim not found")]
+    NotFound,
+    #[error("Prize already claimed")]
+    AlreadyClaimed,
+    #[error("Claim already in progress")]
+    InProgress,
+    #[error("Prize claim has expired")]
+    Expired,
+    #[error("Prize is frozen pending review")]
+    Frozen,
+    #[error("User is not registered, cannot claim prize")]
+    NotRegistered,
+    #[error("Prize amount exceeds transfer limit")]
+    OverTransferLimit,
+    #[error("Prize transfer failed")]
+    TransferFailed(#[source] anyhow::Error),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl From<PrizeClaimError> for ApiError {
+    fn from(err: PrizeClaimError) -> Self {
+        let message = format!("Failed to claim prize: {err}");
+        match err {
+            PrizeClaimError::NotFound => ApiError::NotFound(message),
+            PrizeClaimError::AlreadyClaimed | PrizeClaimError::InProgress => {
+                ApiError::Conflict(message)
+            }
+            PrizeClaimError::Expired => ApiError::Expired(message),
+            PrizeClaimError::Frozen
+            | PrizeClaimError::NotRegistered
+            | PrizeClaimError::OverTransferLimit => ApiError::Forbidden(message),
+            PrizeClaimError::TransferFailed(_) | PrizeClaimError::Internal(_) => {
+                ApiError::Internal(message)
+            }
+        }
+    }
+}
+
+/// Claim an escrowed tournament prize: verifies the claim and transfers the tokens
+pub async fn claim_prize(
+    tournament_id: &str,
+    principal: Principal,
+    app_state: &Arc<AppState>,
+) -> Result<PrizeClaim, PrizeClaimError> {
+    let redis = LeaderboardRedis::new(app_state.leaderboard_redis_pool.clone());
+
+    if !redis
+        .acquire_claim_lock(tournament_id, principal, CLAIM_LOCK_TTL_SECS)
+        .await?
+    {
+        return Err(PrizeClaimError::InProgress);
+    }
+
+    let result = process_prize_claim(&redis, tournament_id, principal, app_state).await;
+
+    if let Err(e) = redis.release_claim_lock(tournament_id, principal).await {
+        log::warn!(
+            "Failed to release claim lock for {} in tournament {}: {:?}",
+            principal,
+            tournament_id,
+            e
+        );
+    }
+
+    result
+}
+
+async fn process_prize_claim(
+    redis: &LeaderboardRedis,
+    tournament_id: &str,
+    principal: Principal,
+    app_state: &Arc<AppState>,
+) -> Result<PrizeClaim, PrizeClaimError> {
+    let mut claim = redis
+        .get_prize_claim(tournament_id, principal)
+        .await?
+        .ok_or(PrizeClaimError::NotFound)?;
+
+    match claim.status {
+        PrizeClaimStatus::Claimed => return Err(PrizeClaimError::AlreadyClaimed),
+        PrizeClaimStatus::Claiming => return Err(PrizeClaimError::InProgress),
+        PrizeClaimStatus::Expired => return Err(PrizeClaimError::Expired),
+        PrizeClaimStatus::Frozen => return Err(PrizeClaimError::Frozen),
+        PrizeClaimStatus::Claimable => {}
+    }
+
+    let now = Utc::now().timestamp();
+    if !claim.is_claimable_at(now) {
+        return Err(PrizeClaimError::Expired);
+    }
+
+    // Verify the claimant has a registered session before transferring
+    if !check_user_registration(principal, app_state).await {
+        return Err(PrizeClaimError::NotRegistered);
+    }
+
+    if claim.prize_token == TokenType::CKBTC && claim.amount > MAX_CKBTC_PRIZE_SATS {
+        log::error!(
+            "CKBTC prize {} sats exceeds {} limit for user {} in tournament {}",
+            claim.amount,
+            MAX_CKBTC_PRIZE_SATS,
+            principal,
+            tournament_id
+        );
+        return Err(PrizeClaimError::OverTransferLimit);
+    }
+
+    // Persist the claiming state before any tokens move. The transfer marker
+    // outlives a crash mid-transfer, so a retry can never pay twice.
+    if !redis.begin_prize_transfer(tournament_id, principal).await? {
+        log::error!(
+            "Prize transfer for {} in tournament {} was already started; reconcile manually",
+            principal,
+            tournament_id
+        );
+        return Err(PrizeClaimError::InProgress);
+    }
+    claim.status = PrizeClaimStatus::Claiming;
+    if let Err(e) = redis.set_prize_claim(&claim).await {
+        redis
+            .abort_prize_transfer(tournament_id, principal)
+            .await
+            .ok();
+        return Err(e.into());
+    }
+
+    let memo = transfer_memo(&["tournament_prize", tournament_id, &principal.to_text()]);
+    match TokenWallet::new(app_state.agent.clone())
+        .transfer(
+            Token::from(&claim.prize_token),
+            principal,
+            claim.amount,
+            Some(memo),
+        )
+        .await
+    {
+        Ok(()) => {}
+        Err(e @ TransferError::Rejected(..)) => {
+            // The ledger refused it, so the prize can be claimed again
+            claim.status = PrizeClaimStatus::Claimable;
+            let reverted = match redis.set_prize_claim(&claim).await {
+                Ok(()) => redis.abort_prize_transfer(tournament_id, principal).await,
+                Err(e) => Err(e),
+            };
+            if let Err(revert_error) = reverted {
+                log::error!(
+                    "Prize transfer failed and claim for {} in tournament {} is stuck claiming: {:?}",
+                    principal,
+                    tournament_id,
+                    revert_error
+                );
+            }
+            return Err(PrizeClaimError::TransferFailed(e.into()));
+        }
+        Err(e @ TransferError::Unknown(..)) => {
+            // The tokens may have moved; the claim stays claiming and the
+            // marker blocks a retry until someone checks the ledger
+            log::error!(
+                "Prize transfer for {} in tournament {} has an unknown outcome; reconcile manually: {}",
+                principal,
+                tournament_id,
+                e
+            );
+            return Err(PrizeClaimError::TransferFailed(e.into()));
+        }
+    }
+
+    log::info!(
+        "Claimed {} {} by {} (rank {}) in tournament {}",
+        claim.amount,
+        claim.prize_token,
+        principal,
+        claim.rank,
+        tournament_id
+    );
+
+    claim.status = PrizeClaimStatus::Claimed;
+    claim.claimed_at = Some(now);
+    if let Err(e) = redis.set_prize_claim(&claim).await {
+        // Transfer already went through and the marker blocks a second one;
+        // surface loudly so the claim record can be fixed
+        log::error!(
+            "Prize transferred but failed to mark claim as claimed for {} in tournament {}: {:?}",
+            principal,
+            tournament_id,
+            e
+        );
+    }
+
+    Ok(claim)
+}
+
+/// Rollover is tracked in prize pool units (USD for CKBTC), at the price the
+/// prize was computed with
+async fn rollover_amount(redis: &LeaderboardRedis, claim: &PrizeClaim) -> Result<f64> {
+    let recorded = redis
+        .get_tournament_results(&claim.tournament_id)
+        .await?
+        .and_then(|results| results.prize_usd_rate);
+    Ok(match finalized_usd_rate(&claim.prize_token, recorded) {
+        Some(rate) => rate.units_to_usd(claim.amount),
+        None => claim.amount as f64,
+    })
+}
+
+/// Expire prizes that were not claimed within the window and return them to the pool
+pub async fn expire_unclaimed_prizes(tournament_id: &str, app_state: &Arc<AppState>) -> Result<()> {
+    let redis = LeaderboardRedis::new(app_state.leaderboard_redis_pool.clone());
+    let now = Utc::now().timestamp();
+
+    let claims = redis.get_tournament_prize_claims(tournament_id).await?;
+
+    let mut expired_count = 0;
+    for snapshot in claims {
+        if snapshot.status != PrizeClaimStatus::Claimable || now < snapshot.expires_at {
+            continue;
+        }
+        let principal = snapshot.principal_id;
+
+        // Skip claims that are being processed right now
+        if !redis
+            .acquire_claim_lock(tournament_id, principal, CLAIM_LOCK_TTL_SECS)
+            .await?
+        {
+            log::warn!(
+                "Claim for {} in tournament {} is locked, skipping expiry",
+                principal,
+                tournament_id
+            );
+            continue;
+        }
+
+        // A claim may have finished between the listing and the lock
+        let expired = match redis.get_prize_claim(tournament_id, principal).await {
+            Ok(Some(mut claim))
+                if claim.status == PrizeClaimStatus::Claimable && now >= claim.expires_at =>
+            {
+                claim.status = PrizeClaimStatus::Expired;
+                redis.set_prize_claim(&claim).await.map(|()| Some(claim))
+            }
+            Ok(_) => Ok(None),
+            Err(e) => Err(e),
+        };
+        redis
+            .release_claim_lock(tournament_id, principal)
+            .await
+            .ok();
+        let Some(claim) = expired? else {
+            continue;
+        };
+
+        let pool_amount = rollover_amount(&redis, &claim).await?;
+        redis
+            .add_prize_pool_rollover(&claim.prize_token, pool_amount)
+            .await?;
+
+        expired_count += 1;
+    }
+
+    log::info!(
+        "Expired {} unclaimed prizes for tournament {}",
+        expired_count,
+        tournament_id
+    );
+
+    Ok(())
+}
+
+/// Opens a claim held by the anomaly detector with a fresh claim window and
+/// sends the winner notification that finalization held back
+pub(crate) async fn release_frozen_prize(
+    redis: &LeaderboardRedis,
+    mut claim: PrizeClaim,
+    app_state: &Arc<AppState>,
+) -> Result<PrizeClaim> {
+    let now = Utc::now().timestamp();
+    claim.status = PrizeClaimStatus::Claimable;
+    claim.expires_at = now + PRIZE_CLAIM_WINDOW_SECS;
+    redis.set_prize_claim(&claim).await?;
+
+    // Earlier expiry runs skipped this claim; this one runs after its new window
+    if let Err(e) = app_state
+        .qstash_client
+        .publish_job_with(
+            &TournamentStepJob {
+                tournament_id: &claim.tournament_id,
+                step: TournamentStep::ExpireClaims,
+            },
+            PublishOptions::delayed_secs(PRIZE_CLAIM_WINDOW_SECS + 60),
+        )
+        .await
+    {
+        log::error!("Failed to schedule prize claim expiry: {:?}", e);
+    }
+
+    let event = EventPayload::TournamentEndedWinner(TournamentEndedWinnerPayload {
+        user_id: claim.principal_id,
+        tournament_id: claim.tournament_id.clone(),
+        rank: claim.rank,
+        prize_amount: claim.amount,
+        prize_token: claim.prize_token.to_string(),
+        total_participants: redis
+            .get_total_participants(&claim.tournament_id)
+            .await
+            .unwrap_or(0),
+    });
+    event.send_notification(app_state).await;
+
+    log::info!(
+        "Released frozen prize of {} (rank {}) in tournament {}",
+        claim.principal_id,
+        claim.rank,
+        claim.tournament_id
+    );
+    Ok(claim)
+}
+
+/// Expires a claim held by the anomaly detector and returns it to the pool
+pub(crate) async fn forfeit_frozen_prize(
+    redis: &LeaderboardRedis,
+    mut claim: PrizeClaim,
+) -> Result<PrizeClaim> {
+    claim.status = PrizeClaimStatus::Expired;
+    redis.set_prize_claim(&claim).await?;
+    let pool_amount = rollover_amount(redis, &claim).await?;
+    redis
+        .add_prize_pool_rollover(&claim.prize_token, pool_amount)
+        .await?;
+
+    log::info!(
+        "Forfeited frozen prize of {} (rank {}) in tournament {}",
+        claim.principal_id,
+        claim.rank,
+        claim.tournament_id
+    );
+    Ok(claim)
+}
+
+/// Remind winners who haven't claimed their prize yet
+pub async fn send_prize_claim_reminders(
+    tournament_id: &str,
+    app_state: &Arc<AppState>,
+) -> Result<()> {
+    let redis = LeaderboardRedis::new(app_state.leaderboard_redis_pool.clone());
+    let now = Utc::now().timestamp();
+
+    let pending: Vec<PrizeClaim> = redis
+        .get_tournament_prize_claims(tournament_id)
+        .await?
+        .into_iter()
+        .filter(|claim| claim.is_claimable_at(now))
+        .collect();
+
+    let total = pending.len();
+    let mut batch = NotificationBatch::new();
+
+    for claim in pending {
+        let copy = NotificationCopy::new("tournament_prize_claim_reminder")
+            .var("prize_amount", claim.amount)
+            .var("prize_token", &claim.prize_token)
+            .var("rank", claim.rank);
+        let (title, body) = app_state
+            .notification_client
+            .templates()
+            .render(&copy, None)
+            .await;
+        let notif_payload = SendNotificationReq {
+            notification: Some(NotificationPayload {
+                title: Some(title),
+                body: Some(body),
+                image: Some("https://yral.com/img/yral/android-chrome-384x384.png".to_string()),
+            }),
+            data: Some(json!({
+                "event": "tournament_prize_claim_reminder",
+                "tournament_id": claim.tournament_id,
+                "rank": claim.rank,
+                "prize_amount": claim.amount,
+                "prize_token": claim.prize_token.to_string(),
+                "expires_at": claim.expires_at,
+            })),
+            android: None,
+            webpush: Some(WebpushConfig {
+                fcm_options: Some(WebpushFcmOptions {
+                    link: Some(format!(
+                        "https://yral.com/leaderboard/results/{}",
+                        claim.tournament_id
+                    )),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            apns: None,
+            ..Default::default()
+        };
+
+        batch.add(notif_payload, claim.principal_id);
+    }
+
+    // Winners sharing a rank and prize get the same reminder, so they're multicast together
+    let statuses = batch.dispatch(&app_state.notification_client).await;
+    let delivered = statuses.iter().filter(|s| s.delivered).count();
+    log::info!(
+        "Prize claim reminders delivered to {}/{} winners",
+        delivered,
+        total
+    );
+
+    log::info!(
+        "Sent {} prize claim reminders for tournament {}",
+        total,
+        tournament_id
+    );
+
+    Ok(())
+}
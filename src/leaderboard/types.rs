@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumString};
 use utoipa::{IntoParams, ToSchema};
 
+use crate::types::DelegatedIdentityWire;
+
 fn default_num_winners() -> u32 {
     10
 }
@@ -114,6 +116,25 @@ pub struct Tournament {
     pub updated_at: i64,
     #[serde(default = "default_num_winners")]
     pub num_winners: u32,
+    #[serde(default)]
+    pub prize_distribution: PrizeDistribution,
+    /// Warehouse events that score this tournament directly; empty means
+    /// scores only arrive through `/score/update`
+    #[serde(default)]
+    pub event_metrics: Vec<EventMetricMapping>,
+}
+
+impl Tournament {
+    /// Reward for `rank` under this tournament's prize distribution
+    pub fn reward_for_rank(
+        &self,
+        rank: u32,
+        total_participants: u32,
+        prize_pool: u64,
+    ) -> Option<u64> {
+        self.prize_distribution
+            .reward(rank, self.num_winners, total_participants, prize_pool)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -133,9 +154,13 @@ pub struct TournamentResult {
     pub total_participants: u32,
     pub total_prize_distributed: u64,
     pub finalized_at: i64,
+    /// USD per prize token used to size the prizes; None for YRAL pools and
+    /// results saved before it was recorded
+    #[serde(default)]
+    pub prize_usd_rate: Option<f64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CreateTournamentRequest {
     pub start_time: i64,
     pub end_time: i64,
@@ -145,6 +170,30 @@ pub struct CreateTournamentRequest {
     pub metric_display_name: String,
     pub allowed_sources: Vec<String>,
     pub num_winners: Option<u32>,
+    #[serde(default)]
+    pub prize_distribution: PrizeDistribution,
+    #[serde(default)]
+    pub event_metrics: Vec<EventMetricMapping>,
+}
+
+fn default_principal_param() -> String {
+    "user_id".to_string()
+}
+
+/// Allow-listed warehouse event that adds to a tournament's metric
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EventMetricMapping {
+    /// snake_case event name, e.g. `video_duration_watched`
+    pub event: String,
+    /// Param holding the principal to credit
+    #[serde(default = "default_principal_param")]
+    pub principal_param: String,
+    /// Numeric param to add to the score; each event counts as 1 when unset
+    pub value_param: Option<String>,
+    /// Params the event must carry with exactly these values, e.g. `{"is_win": true}`
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub match_params: serde_json::Map<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -244,10 +293,12 @@ pub struct TournamentResultInfo {
     pub metric_display_name: String,
     #[serde(default = "default_num_winners")]
     pub num_winners: u32,
+    #[serde(default)]
+    pub prize_distribution: PrizeDistribution,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PrizeDistribution {
+pub struct PrizeTier {
     pub rank_range: RankRange,
     pub percentage: f32,
 }
@@ -258,62 +309,62 @@ pub enum RankRange {
     Range(u32, u32),
 }
 
-impl PrizeDistribution {
-    fn default_distribution() -> Vec<PrizeDistribution> {
+impl PrizeTier {
+    fn default_tiers() -> Vec<PrizeTier> {
         vec![
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Single(1),
                 percentage: 15.0, // 30/200
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Single(2),
                 percentage: 13.5, // 27/200
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Single(3),
                 percentage: 12.5, // 25/200
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Single(4),
                 percentage: 10.0, // 20/200
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Single(5),
                 percentage: 7.5, // 15/200
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Single(6),
                 percentage: 6.5, // 13/200
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Single(7),
                 percentage: 5.5, // 11/200
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Single(8),
                 percentage: 4.5, // 9/200
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Single(9),
                 percentage: 4.0, // 8/200
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Single(10),
                 percentage: 3.5, // 7/200
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Range(11, 13),
                 percentage: 2.0, // Each gets 2% (4/200)
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Range(14, 17),
                 percentage: 1.5, // Each gets 1.5% (3/200)
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Range(18, 20),
                 percentage: 1.0, // Each gets 1% (2/200)
             },
-            PrizeDistribution {
+            PrizeTier {
                 rank_range: RankRange::Range(21, 25),
                 percentage: 0.5, // Each gets 0.5% (1/200)
             },
@@ -321,22 +372,141 @@ impl PrizeDistribution {
     }
 }
 
+/// Reward for a rank under the default tiered distribution
 pub fn calculate_reward(rank: u32, total_prize_pool: u64) -> Option<u64> {
-    let distributions = PrizeDistribution::default_distribution();
+    PrizeDistribution::Tiered.reward(rank, u32::MAX, 0, total_prize_pool)
+}
 
-    for dist in distributions.iter() {
-        match dist.rank_range {
-            RankRange::Single(r) if r == rank => {
-                return Some((total_prize_pool as f64 * dist.percentage as f64 / 100.0) as u64);
+/// Largest `num_winners` the tiered table pays out
+pub const TIERED_MAX_WINNERS: u32 = 25;
+
+/// Upper bound on `num_winners` for the formula-based strategies
+pub const MAX_WINNERS: u32 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PercentileBucket {
+    /// Upper bound of the bucket as a percentile of participants, e.g. 1.0 for the top 1%
+    pub up_to_percentile: f64,
+    /// Share of the prize pool split equally across the ranks in the bucket
+    pub percentage: f64,
+}
+
+/// How a tournament's prize pool is split across its top `num_winners` ranks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type")]
+pub enum PrizeDistribution {
+    /// Fixed percentage table for the top 25 ranks
+    #[serde(rename = "tiered")]
+    Tiered,
+    #[serde(rename = "winner_takes_all")]
+    WinnerTakesAll,
+    #[serde(rename = "equal_split")]
+    EqualSplit,
+    /// Each rank gets `ratio` times the rank above it
+    #[serde(rename = "exponential_decay")]
+    ExponentialDecay { ratio: f64 },
+    #[serde(rename = "percentile_buckets")]
+    PercentileBuckets { buckets: Vec<PercentileBucket> },
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for PrizeDistribution {
+    fn default() -> Self {
+        PrizeDistribution::Tiered
+    }
+}
+
+impl PrizeDistribution {
+    pub fn max_winners(&self) -> u32 {
+        match self {
+            PrizeDistribution::Tiered => TIERED_MAX_WINNERS,
+            PrizeDistribution::WinnerTakesAll => 1,
+            _ => MAX_WINNERS,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            PrizeDistribution::ExponentialDecay { ratio } => {
+                if !ratio.is_finite() || *ratio <= 0.0 || *ratio > 1.0 {
+                    return Err("ratio must be in (0, 1]".to_string());
+                }
             }
-            RankRange::Range(start, end) if rank >= start && rank <= end => {
-                return Some((total_prize_pool as f64 * dist.percentage as f64 / 100.0) as u64);
+            PrizeDistribution::PercentileBuckets { buckets } => {
+                if buckets.is_empty() {
+                    return Err("at least one bucket is required".to_string());
+                }
+                let mut previous = 0.0;
+                let mut total_percentage = 0.0;
+                for bucket in buckets {
+                    if !bucket.up_to_percentile.is_finite()
+                        || bucket.up_to_percentile <= previous
+                        || bucket.up_to_percentile > 100.0
+                    {
+                        return Err("bucket percentiles must increase within (0, 100]".to_string());
+                    }
+                    if !bucket.percentage.is_finite() || bucket.percentage < 0.0 {
+                        return Err("bucket percentages must be non-negative".to_string());
+                    }
+                    previous = bucket.up_to_percentile;
+                    total_percentage += bucket.percentage;
+                }
+                if total_percentage > 100.0 {
+                    return Err("bucket percentages add up to more than 100".to_string());
+                }
             }
-            _ => continue,
+            _ => {}
         }
+        Ok(())
     }
 
-    None
+    /// Reward for `rank` (1 = top) given the tournament's `num_winners`.
+    /// `total_participants` is only used by percentile buckets.
+    pub fn reward(
+        &self,
+        rank: u32,
+        num_winners: u32,
+        total_participants: u32,
+        total_prize_pool: u64,
+    ) -> Option<u64> {
+        let winners = num_winners.min(self.max_winners());
+        if rank == 0 || rank > winners {
+            return None;
+        }
+        let share = |fraction: f64| Some((total_prize_pool as f64 * fraction) as u64);
+
+        match self {
+            PrizeDistribution::Tiered => PrizeTier::default_tiers()
+                .iter()
+                .find(|tier| match tier.rank_range {
+                    RankRange::Single(r) => r == rank,
+                    RankRange::Range(start, end) => rank >= start && rank <= end,
+                })
+                .and_then(|tier| share(tier.percentage as f64 / 100.0)),
+            PrizeDistribution::WinnerTakesAll => share(1.0),
+            PrizeDistribution::EqualSplit => share(1.0 / winners as f64),
+            PrizeDistribution::ExponentialDecay { ratio } => {
+                let total_weight: f64 = (0..winners).map(|i| ratio.powi(i as i32)).sum();
+                share(ratio.powi(rank as i32 - 1) / total_weight)
+            }
+            PrizeDistribution::PercentileBuckets { buckets } => {
+                // Small tournaments still pay at least one rank per bucket boundary
+                let last_rank = |percentile: f64| {
+                    ((percentile * total_participants.max(rank) as f64 / 100.0).ceil() as u32)
+                        .min(winners)
+                };
+                let mut first = 1;
+                for bucket in buckets {
+                    let last = last_rank(bucket.up_to_percentile);
+                    if rank >= first && rank <= last {
+                        return share(bucket.percentage / 100.0 / (last - first + 1) as f64);
+                    }
+                    first = first.max(last + 1);
+                }
+                None
+            }
+        }
+    }
 }
 
 // Pagination types
@@ -449,6 +619,8 @@ pub struct TournamentInfo {
     pub client_end_time: Option<String>,   // ISO 8601 formatted in client's timezone
     #[serde(default = "default_num_winners")]
     pub num_winners: u32,
+    #[serde(default)]
+    pub prize_distribution: PrizeDistribution,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -488,6 +660,94 @@ pub struct FinalizeTournamentRequest {
     pub tournament_id: String,
 }
 
+// Escrowed prize awaiting claim by a tournament winner
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub enum PrizeClaimStatus {
+    #[serde(rename = "claimable")]
+    Claimable,
+    // Transfer started; a retry must not pay again
+    #[serde(rename = "claiming")]
+    Claiming,
+    #[serde(rename = "claimed")]
+    Claimed,
+    #[serde(rename = "expired")]
+    Expired,
+    // Held while the winner's entry is under anomaly review
+    #[serde(rename = "frozen")]
+    Frozen,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PrizeClaim {
+    pub tournament_id: String,
+    #[schema(value_type = String)]
+    pub principal_id: Principal,
+    pub rank: u32,
+    pub amount: u64, // In token units (sats for CKBTC, YRAL for YRAL)
+    pub prize_token: TokenType,
+    pub status: PrizeClaimStatus,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub claimed_at: Option<i64>,
+}
+
+impl PrizeClaim {
+    pub fn is_claimable_at(&self, now: i64) -> bool {
+        self.status == PrizeClaimStatus::Claimable && now < self.expires_at
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ClaimPrizeRequest {
+    pub delegated_identity_wire: DelegatedIdentityWire,
+    pub tournament_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ClaimPrizeResponse {
+    pub success: bool,
+    pub claim: PrizeClaim,
+}
+
+// Outcome of an atomic score increment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreUpdate {
+    pub score: f64,
+    // Score gained within the current velocity window, this update included
+    pub window_gain: f64,
+    // The participant was frozen and the update was not applied
+    pub frozen: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub enum FrozenStatus {
+    #[serde(rename = "pending_review")]
+    PendingReview,
+    #[serde(rename = "disqualified")]
+    Disqualified,
+}
+
+// Participant frozen by the score anomaly detector
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FrozenEntry {
+    pub status: FrozenStatus,
+    pub reason: String,
+    pub window_gain: f64,
+    pub window_secs: u64,
+    pub score_at_freeze: f64,
+    pub frozen_at: i64,
+    pub reviewed_at: Option<i64>,
+    pub reviewed_by: Option<String>,
+}
+
+// Result of atomically reviewing a frozen entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrozenReview {
+    Applied,
+    NotFound,
+    AlreadyReviewed,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LeaderboardError {
     TournamentNotFound,
@@ -543,6 +803,83 @@ mod tests {
         assert_eq!(calculate_reward(26, total_prize), None); // No reward
     }
 
+    #[test]
+    fn test_prize_distribution_totals() {
+        let pool = 1_000_000;
+        let total = |distribution: &PrizeDistribution, num_winners: u32, participants: u32| {
+            (1..=num_winners + 5)
+                .filter_map(|rank| distribution.reward(rank, num_winners, participants, pool))
+                .sum::<u64>()
+        };
+
+        assert_eq!(total(&PrizeDistribution::Tiered, 25, 100), pool);
+        assert_eq!(total(&PrizeDistribution::Tiered, 10, 100), 825_000);
+
+        assert_eq!(total(&PrizeDistribution::WinnerTakesAll, 10, 100), pool);
+        assert_eq!(
+            PrizeDistribution::WinnerTakesAll.reward(2, 10, 100, pool),
+            None
+        );
+
+        assert_eq!(total(&PrizeDistribution::EqualSplit, 4, 100), pool);
+        assert_eq!(
+            PrizeDistribution::EqualSplit.reward(4, 4, 100, pool),
+            Some(250_000)
+        );
+
+        let decay = PrizeDistribution::ExponentialDecay { ratio: 0.5 };
+        let decay_total = total(&decay, 10, 100);
+        assert!(decay_total <= pool && decay_total >= pool - 10);
+        assert!(decay.reward(1, 10, 100, pool) > decay.reward(2, 10, 100, pool));
+
+        let buckets = PrizeDistribution::PercentileBuckets {
+            buckets: vec![
+                PercentileBucket {
+                    up_to_percentile: 1.0,
+                    percentage: 50.0,
+                },
+                PercentileBucket {
+                    up_to_percentile: 10.0,
+                    percentage: 30.0,
+                },
+                PercentileBucket {
+                    up_to_percentile: 50.0,
+                    percentage: 20.0,
+                },
+            ],
+        };
+        assert!(buckets.validate().is_ok());
+        assert_eq!(buckets.reward(1, 50, 100, pool), Some(500_000));
+        let bucket_total = total(&buckets, 50, 100);
+        assert!(bucket_total <= pool && bucket_total >= pool - 50);
+
+        assert!(PrizeDistribution::ExponentialDecay { ratio: 1.5 }
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_prize_claim_is_claimable_at() {
+        let mut claim = PrizeClaim {
+            tournament_id: "tournament_1".to_string(),
+            principal_id: Principal::anonymous(),
+            rank: 1,
+            amount: 1000,
+            prize_token: TokenType::YRAL,
+            status: PrizeClaimStatus::Claimable,
+            created_at: 100,
+            expires_at: 200,
+            claimed_at: None,
+        };
+
+        assert!(claim.is_claimable_at(150));
+        assert!(!claim.is_claimable_at(200)); // Window closed
+        claim.status = PrizeClaimStatus::Claiming;
+        assert!(!claim.is_claimable_at(150)); // Transfer already started
+        claim.status = PrizeClaimStatus::Claimed;
+        assert!(!claim.is_claimable_at(150));
+    }
+
     #[test]
     fn test_token_type_enum_string() {
         // Test FromStr (via EnumString)
@@ -1,31 +1,37 @@
 #![recursion_limit = "256"]
 
+use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
 use anyhow::Result;
-use axum::extract::DefaultBodyLimit;
+use axum::extract::{ConnectInfo, DefaultBodyLimit};
 use axum::http::StatusCode;
 use axum::routing::post;
+use axum::serve::IncomingStream;
 use axum::{routing::get, Router};
 use canister::canister_health_handler;
 use config::AppConfig;
 use events::event::storj::enqueue_storj_backfill_item;
 use http::header::CONTENT_TYPE;
+use middleware::body_guard::{
+    body_limit, DEFAULT_LIMIT, IMAGE_LIMIT, SMALL_JSON_LIMIT, UPLOAD_LIMIT,
+};
 use offchain_service::report_approved_handler;
 use qstash::qstash_router;
 use sentry_tower::{NewSentryLayer, SentryHttpLayer};
+use tokio::net::TcpListener;
 use tonic::service::Routes;
-use tower::make::Shared;
 use tower::steer::Steer;
-use tower::ServiceBuilder;
+use tower::{ServiceBuilder, ServiceExt};
 use tower_http::cors::CorsLayer;
+use tower_http::limit::RequestBodyLimitLayer;
 use tracing::instrument;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use utoipa::OpenApi;
 use utoipa_axum::router::OpenApiRouter;
-use utoipa_swagger_ui::SwaggerUi;
+use utoipa_swagger_ui::{SwaggerUi, Url};
 use webhooks::sentry_webhook_handler;
 
 use crate::auth::check_auth_grpc;
@@ -35,17 +41,39 @@ use crate::offchain_service::off_chain::off_chain_server::OffChainServer;
 use crate::offchain_service::{off_chain, OffChainService};
 use error::*;
 
+#[cfg(not(feature = "local-bin"))]
+mod admin_lookup;
 mod ai_video_detector;
+mod api_docs;
 mod app_state;
 mod auth;
+#[cfg(not(feature = "local-bin"))]
+mod bookmarks;
 pub mod canister;
+#[cfg(not(feature = "local-bin"))]
+mod comments;
 mod config;
 mod consts;
+#[cfg(not(feature = "local-bin"))]
+mod content_gating;
+#[cfg(not(feature = "local-bin"))]
+mod daily_missions;
+mod devices;
 mod duplicate_video;
 mod error;
+#[cfg(not(feature = "local-bin"))]
+mod event_export;
 mod events;
+#[cfg(not(feature = "local-bin"))]
+mod experiments;
+#[cfg(not(feature = "local-bin"))]
+mod feature_flags;
+#[cfg(not(feature = "local-bin"))]
+mod identity;
 pub mod kvrocks;
 pub mod leaderboard;
+#[cfg(not(feature = "local-bin"))]
+mod maintenance;
 mod middleware;
 #[cfg(not(feature = "local-bin"))]
 mod milvus;
@@ -53,15 +81,36 @@ mod moderation;
 mod offchain_service;
 pub mod pipeline;
 mod posts;
+mod provenance;
 mod qstash;
+mod rates;
+#[cfg(not(feature = "local-bin"))]
+mod redis_health;
+#[cfg(not(feature = "local-bin"))]
+mod referrals;
 mod rewards;
+#[cfg(not(feature = "local-bin"))]
+mod rollups;
+#[cfg(not(feature = "local-bin"))]
+mod scheduled_publish;
+#[cfg(not(feature = "local-bin"))]
+mod scheduler;
 pub mod scratchpad;
+#[cfg(not(feature = "local-bin"))]
+mod streaks;
+#[cfg(not(feature = "local-bin"))]
+mod text_moderation;
+mod tokens;
 mod types;
 pub mod user;
 pub mod utils;
+mod verdict_cache;
+#[cfg(not(feature = "local-bin"))]
+mod video_availability;
 #[cfg(not(feature = "local-bin"))]
 mod video_processing;
 pub mod videogen;
+mod webhook_subscriptions;
 mod webhooks;
 pub mod yral_auth;
 
@@ -72,15 +121,44 @@ async fn main_impl() -> Result<()> {
     #[openapi(
         tags(
             (name = "OFF_CHAIN", description = "Off Chain Agent API"),
-        )
+        ),
+        modifiers(&api_docs::SecurityAddon)
     )]
     struct ApiDoc;
 
+    /// QStash jobs and provider webhooks; callers are other services, not
+    /// clients, so these are kept out of the public doc
+    #[derive(OpenApi)]
+    #[openapi(
+        tags(
+            (name = "qstash", description = "QStash job endpoints (signed by QStash)"),
+            (name = "webhooks", description = "Video generation provider webhooks"),
+        ),
+        modifiers(&api_docs::SecurityAddon)
+    )]
+    struct InternalApiDoc;
+
     let conf = AppConfig::load()?;
+    config::runtime::init(&conf);
 
     let shared_state = Arc::new(AppState::new(conf.clone()).await);
+    events::bigquery_writer::spawn_event_writer(shared_state.clone());
+    if let Err(e) = events::sink::spawn_event_sink(&conf.event_sink).await {
+        log::error!("Event sink not started: {e:?}");
+    }
+    utils::cache::spawn_invalidation_listener(shared_state.yral_redis_store_dragonfly.clone());
+    #[cfg(not(feature = "local-bin"))]
+    config::runtime::spawn_watcher(shared_state.yral_redis_store_dragonfly.clone());
+    #[cfg(not(feature = "local-bin"))]
+    videogen::costs::spawn_budget_watcher(shared_state.yral_redis_store_dragonfly.clone());
+    #[cfg(not(feature = "local-bin"))]
+    maintenance::spawn_watcher(shared_state.clone());
+    #[cfg(not(feature = "local-bin"))]
+    scheduler::spawn_sync(shared_state.clone());
     #[cfg(not(feature = "local-bin"))]
     video_processing::worker::spawn_worker(shared_state.clone())?;
+    #[cfg(not(feature = "local-bin"))]
+    events::view_aggregator::spawn_view_flusher(shared_state.clone());
 
     let sentry_tower_layer = ServiceBuilder::new()
         .layer(NewSentryLayer::new_from_top())
@@ -114,32 +192,36 @@ async fn main_impl() -> Result<()> {
             "/api/v1/events",
             events::events_router(shared_state.clone()),
         )
-        .nest("/api/v1/user", user::user_router(shared_state.clone()))
+        .nest(
+            "/api/v1/user",
+            user::user_router(shared_state.clone()).layer(body_limit(IMAGE_LIMIT)),
+        )
         .nest(
             "/api/v1/videogen",
-            videogen::videogen_router(shared_state.clone()).layer(
-                axum::middleware::from_fn_with_state(
+            videogen::videogen_router(shared_state.clone())
+                .layer(axum::middleware::from_fn_with_state(
                     videogen_sentry_hub.clone(),
                     videogen_sentry_capture,
-                ),
-            ),
+                ))
+                .layer(body_limit(UPLOAD_LIMIT)),
         )
         .nest(
             "/api/v1/leaderboard",
-            leaderboard::leaderboard_router(shared_state.clone()),
+            leaderboard::leaderboard_router(shared_state.clone())
+                .layer(body_limit(SMALL_JSON_LIMIT)),
         )
         .nest(
             "/api/v1/rewards",
-            rewards::api::rewards_router(shared_state.clone()),
+            rewards::api::rewards_router(shared_state.clone()).layer(body_limit(SMALL_JSON_LIMIT)),
         )
         .nest(
             "/api/v2/videogen",
-            videogen::videogen_router_v2(shared_state.clone()).layer(
-                axum::middleware::from_fn_with_state(
+            videogen::videogen_router_v2(shared_state.clone())
+                .layer(axum::middleware::from_fn_with_state(
                     videogen_sentry_hub.clone(),
                     videogen_sentry_capture,
-                ),
-            ),
+                ))
+                .layer(body_limit(UPLOAD_LIMIT)),
         )
         .nest(
             "/api/v2/events",
@@ -156,6 +238,10 @@ async fn main_impl() -> Result<()> {
         .nest(
             "/api/v1/moderation",
             moderation::moderation_router(shared_state.clone()),
+        )
+        .nest(
+            "/api/v1/admin/webhooks",
+            webhook_subscriptions::webhooks_admin_router(shared_state.clone()),
         );
 
     #[cfg(not(feature = "local-bin"))]
@@ -164,39 +250,229 @@ async fn main_impl() -> Result<()> {
         milvus::router::milvus_router(shared_state.clone()),
     );
 
-    let (router, api) = router.split_for_parts();
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/daily-missions",
+        daily_missions::daily_missions_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/bookmarks",
+        bookmarks::bookmarks_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/scheduled-publish",
+        scheduled_publish::scheduled_publish_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/comments",
+        comments::comments_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/content-gating",
+        content_gating::content_gating_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/referrals",
+        referrals::referrals_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/rollups",
+        rollups::rollups_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/feature-flags",
+        feature_flags::feature_flags_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/experiments",
+        experiments::experiments_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/provenance",
+        provenance::provenance_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/identity",
+        identity::identity_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/devices",
+        devices::devices_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/notification-templates",
+        events::notification_templates::notification_templates_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/config",
+        config::runtime::runtime_config_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/maintenance",
+        maintenance::maintenance_router(shared_state.clone()),
+    );
 
-    let router =
-        router.merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", api.clone()));
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/admin/schedules",
+        scheduler::schedules_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/event-export",
+        event_export::event_export_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/admin/lookup",
+        admin_lookup::lookup_router(shared_state.clone()),
+    );
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.nest(
+        "/api/v1/admin/pipeline-stats",
+        pipeline::stats::pipeline_stats_router(shared_state.clone()),
+    );
+
+    let (router, mut api) = router.split_for_parts();
+    api_docs::describe_tag_auth(&mut api);
+    let router = router
+        .layer(axum::middleware::from_fn(
+            middleware::body_guard::require_json,
+        ))
+        .layer(axum::middleware::from_fn(
+            middleware::http_metrics::expose_matched_path,
+        ));
 
-    // build our application with a route
-    let qstash_routes = qstash_router(shared_state.clone());
     let vg_middleware =
         axum::middleware::from_fn_with_state(videogen_sentry_hub.clone(), videogen_sentry_capture);
-    let replicate_webhook_routes = videogen::router::replicate_webhook_router(shared_state.clone())
-        .layer(vg_middleware.clone());
-    let comfyui_webhook_routes =
-        videogen::router::comfyui_webhook_router(shared_state.clone()).layer(vg_middleware);
+    let (internal_routes, mut internal_api) =
+        OpenApiRouter::with_openapi(InternalApiDoc::openapi())
+            .nest("/qstash", qstash_router(shared_state.clone()))
+            .nest(
+                "/replicate",
+                videogen::router::replicate_webhook_router(shared_state.clone())
+                    .layer(vg_middleware.clone()),
+            )
+            .nest(
+                "/comfyui",
+                videogen::router::comfyui_webhook_router(shared_state.clone()).layer(vg_middleware),
+            )
+            .split_for_parts();
+    api_docs::require_scheme(&mut internal_api, "/qstash", "qstash_signature");
+    api_docs::describe_tag_auth(&mut internal_api);
+
+    let router = router.merge(
+        SwaggerUi::new("/swagger-ui")
+            .url(Url::new("public", "/api-docs/openapi.json"), api)
+            .url(
+                Url::new("internal", "/api-docs/internal/openapi.json"),
+                internal_api,
+            ),
+    );
 
     let http = Router::new()
         .route("/healthz", get(health_handler))
         .route("/canister-health", get(canister_health_handler))
+        .route(
+            "/canister-metrics",
+            get(canister::agent_pool::canister_metrics_handler),
+        )
+        .route(
+            "/http-metrics",
+            get(middleware::http_metrics::http_metrics_handler),
+        )
+        .route(
+            "/event-sink-metrics",
+            get(events::sink::event_sink_metrics_handler),
+        )
+        .route(
+            "/event-rate-limit-metrics",
+            get(events::velocity::event_rate_limit_metrics_handler),
+        )
+        .route(
+            "/storj-ingest-metrics",
+            get(events::event::storj::storj_ingest_metrics_handler),
+        )
         .route("/report-approved", post(report_approved_handler))
         .route("/webhooks/sentry", post(sentry_webhook_handler))
         .route(
             "/enqueue_storj_backfill_item",
             post(enqueue_storj_backfill_item),
+        );
+
+    #[cfg(not(feature = "local-bin"))]
+    let http = http
+        .nest(
+            "/admin/dedup_config",
+            qstash::dedup_config::dedup_config_router(shared_state.clone()),
+        )
+        .nest(
+            "/admin/qstash_drain",
+            qstash::drain::qstash_drain_router(shared_state.clone()),
         )
-        .nest("/qstash", qstash_routes)
-        .nest("/replicate", replicate_webhook_routes)
-        .nest("/comfyui", comfyui_webhook_routes)
-        .fallback_service(router)
-        .layer(DefaultBodyLimit::max(50 * 1024 * 1024)) // 50MB limit
+        .nest(
+            "/admin/storj_backfill",
+            events::event::storj_backfill::storj_backfill_router(shared_state.clone()),
+        )
+        .route(
+            "/webhooks/cloudflare-stream",
+            post(webhooks::cloudflare_stream::cloudflare_stream_webhook_handler),
+        )
+        .route("/redis-health", get(redis_health::redis_health_handler));
+
+    let http = http.merge(internal_routes).fallback_service(router);
+
+    // Inside the logging and Sentry layers so rejected requests are still recorded
+    #[cfg(not(feature = "local-bin"))]
+    let http = http.layer(axum::middleware::from_fn_with_state(
+        shared_state.clone(),
+        maintenance::maintenance_guard,
+    ));
+
+    let http = http
+        .layer(DefaultBodyLimit::max(DEFAULT_LIMIT)) // route groups override this
         .layer(CorsLayer::permissive())
         .layer(axum::middleware::from_fn(
             crate::middleware::http_logging_middleware,
         )) // HTTP logging before Sentry
+        // Outside the logger, which buffers whole request bodies
+        .layer(RequestBodyLimitLayer::new(UPLOAD_LIMIT))
         .layer(sentry_tower_layer)
+        // Runs before the Sentry layer so W3C-only callers continue their trace
+        .layer(axum::middleware::from_fn(
+            crate::middleware::trace_context::continue_trace_middleware,
+        ))
         .with_state(shared_state.clone());
 
     let reflection_service = tonic_reflection::server::Builder::configure()
@@ -236,15 +512,68 @@ async fn main_impl() -> Result<()> {
 
     // run it
     let addr = SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 0], 50051));
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    let listener = TcpListener::bind(&addr).await.unwrap();
 
     log::info!("listening on {addr}");
 
-    axum::serve(listener, Shared::new(http_grpc)).await.unwrap();
+    // Each connection's peer address goes on its requests as `ConnectInfo`,
+    // for handlers that fall back to it when there is no forwarded address
+    let make_service = tower::service_fn(move |incoming: IncomingStream<'_, TcpListener>| {
+        let peer = *incoming.remote_addr();
+        let service = http_grpc
+            .clone()
+            .map_request(move |mut req: axum::extract::Request| {
+                req.extensions_mut().insert(ConnectInfo(peer));
+                req
+            });
+        std::future::ready(Ok::<_, Infallible>(service))
+    });
+
+    axum::serve(listener, make_service)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+        .unwrap();
+
+    let flushed = events::bigquery_writer::flush_events().await;
+    log::info!("Flushed {flushed} buffered events to BigQuery on shutdown");
+
+    // Drain aggregated views so a deploy doesn't hold them until the next flusher tick
+    #[cfg(not(feature = "local-bin"))]
+    match events::view_aggregator::flush_views(&shared_state).await {
+        Ok(flushed) => log::info!("Flushed view details for {flushed} posts on shutdown"),
+        Err(e) => log::error!("Final view flush failed: {e:?}"),
+    }
 
     Ok(())
 }
 
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                log::error!("Failed to install SIGTERM handler: {e:?}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    log::info!("Shutdown signal received");
+}
+
 fn main() {
     // Initialize ffmpeg
     ffmpeg_next::init().expect("Failed to initialize ffmpeg");
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{
+    alert, load_windows, remove_window, store_window, MaintenanceWindow, Subsystem,
+    DEFAULT_RETRY_AFTER_SECS,
+};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+};
+
+const MAX_DURATION_SECS: u64 = 7 * 24 * 60 * 60;
+const MAX_RETRY_AFTER_SECS: u64 = 60 * 60;
+
+pub fn maintenance_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(get_maintenance))
+        .routes(routes!(start_maintenance, end_maintenance))
+        .with_state(state)
+}
+
+fn parse_subsystem(name: &str) -> Result<Subsystem, ApiError> {
+    Subsystem::parse(name)
+        .ok_or_else(|| ApiError::InvalidRequest(format!("Unknown subsystem {name}")))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SubsystemStatus {
+    pub subsystem: Subsystem,
+    /// Present while the subsystem is paused
+    pub maintenance: Option<MaintenanceWindow>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StartMaintenanceRequest {
+    pub reason: Option<String>,
+    /// Omit to stay paused until resumed
+    pub duration_secs: Option<u64>,
+    /// Sent to rejected callers; defaults to 300
+    pub retry_after_secs: Option<u64>,
+}
+
+/// Maintenance status of every subsystem
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "maintenance",
+    responses(
+        (status = 200, description = "Subsystems", body = Vec<SubsystemStatus>),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_maintenance(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<SubsystemStatus>>, ApiError> {
+    require_operator(&headers)?;
+    let mut windows = load_windows(&state.yral_redis_store_dragonfly).await?;
+
+    Ok(Json(
+        Subsystem::ALL
+            .into_iter()
+            .map(|subsystem| SubsystemStatus {
+                subsystem,
+                maintenance: windows.remove(&subsystem),
+            })
+            .collect(),
+    ))
+}
+
+/// Pause a subsystem, or replace its current window
+#[utoipa::path(
+    put,
+    path = "/{subsystem}",
+    params(
+        ("subsystem" = Subsystem, Path, description = "Subsystem to pause")
+    ),
+    request_body = StartMaintenanceRequest,
+    tag = "maintenance",
+    responses(
+        (status = 200, description = "Subsystem paused", body = MaintenanceWindow),
+        (status = 400, description = "Unknown subsystem or invalid duration", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers, request))]
+pub async fn start_maintenance(
+    State(state): State<Arc<AppState>>,
+    Path(subsystem): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<StartMaintenanceRequest>,
+) -> Result<Json<MaintenanceWindow>, ApiError> {
+    require_operator(&headers)?;
+    let subsystem = parse_subsystem(&subsystem)?;
+    if request
+        .duration_secs
+        .is_some_and(|duration| duration == 0 || duration > MAX_DURATION_SECS)
+    {
+        return Err(ApiError::InvalidRequest(format!(
+            "duration_secs must be between 1 and {MAX_DURATION_SECS}"
+        )));
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let window = MaintenanceWindow {
+        reason: request.reason,
+        started_at: now,
+        until: request.duration_secs.map(|duration| now + duration as i64),
+        retry_after_secs: request
+            .retry_after_secs
+            .unwrap_or(DEFAULT_RETRY_AFTER_SECS)
+            .clamp(1, MAX_RETRY_AFTER_SECS),
+    };
+    store_window(&state.yral_redis_store_dragonfly, subsystem, &window).await?;
+
+    let transition = match window.until {
+        Some(until) => format!("paused until {until}"),
+        None => "paused until resumed".to_string(),
+    };
+    alert(subsystem, window.reason.as_deref(), &transition);
+    Ok(Json(window))
+}
+
+/// Resume a paused subsystem
+#[utoipa::path(
+    delete,
+    path = "/{subsystem}",
+    params(
+        ("subsystem" = Subsystem, Path, description = "Subsystem to resume")
+    ),
+    tag = "maintenance",
+    responses(
+        (status = 204, description = "Subsystem resumed"),
+        (status = 400, description = "Unknown subsystem", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Subsystem is not paused", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn end_maintenance(
+    State(state): State<Arc<AppState>>,
+    Path(subsystem): Path<String>,
+    headers: HeaderMap,
+) -> Result<axum::http::StatusCode, ApiError> {
+    require_operator(&headers)?;
+    let subsystem = parse_subsystem(&subsystem)?;
+    if !remove_window(&state.yral_redis_store_dragonfly, subsystem).await? {
+        return Err(ApiError::NotFound(format!(
+            "{} is not paused",
+            subsystem.as_str()
+        )));
+    }
+    alert(subsystem, None, "resumed");
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,369 @@
+//! Operator-controlled maintenance mode, per subsystem.
+//!
+//! Windows are JSON records in the Dragonfly hash `offchain:maintenance`,
+//! keyed by subsystem, and managed through `/api/v1/maintenance`. While a
+//! subsystem is paused, writes to its routes are answered with 503 and a
+//! `Retry-After` (QStash redelivers them after the window), and its
+//! background work is skipped or deferred. Reads keep being served.
+//!
+//! A window with `until` ends by itself; the first instance to notice
+//! removes it, at the latest on the watcher's next tick. View milestones
+//! reached while payouts are paused are deferred by the reward engine and
+//! paid by the watcher once they resume. Every start and end is posted to
+//! `GCHAT_MAINTENANCE_ALERTS_WEBHOOK_URL`. Like the QStash drain, checks
+//! fail open when Redis is unreachable.
+
+pub mod handlers;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState, error::ApiError, offchain_service::send_message_gchat_webhook,
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+pub use handlers::maintenance_router;
+
+const MAINTENANCE_KEY: &str = "offchain:maintenance";
+const CACHE_TTL: Duration = Duration::from_secs(5);
+const WATCH_INTERVAL: Duration = Duration::from_secs(30);
+pub const DEFAULT_RETRY_AFTER_SECS: u64 = 300;
+
+static CACHE: Lazy<RwLock<Option<(Instant, Windows)>>> = Lazy::new(|| RwLock::new(None));
+
+static ALERTS_WEBHOOK_URL: Lazy<Option<String>> =
+    Lazy::new(|| std::env::var("GCHAT_MAINTENANCE_ALERTS_WEBHOOK_URL").ok());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    /// Phash dedup and Milvus ingestion
+    DedupIngestion,
+    /// View milestone payouts, mission and tournament prize claims
+    RewardsPayouts,
+    Videogen,
+    Leaderboard,
+    /// Transcoding, transcription and the scheduled NSFW worker
+    VideoProcessing,
+    Rollups,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 6] = [
+        Subsystem::DedupIngestion,
+        Subsystem::RewardsPayouts,
+        Subsystem::Videogen,
+        Subsystem::Leaderboard,
+        Subsystem::VideoProcessing,
+        Subsystem::Rollups,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Subsystem::DedupIngestion => "dedup_ingestion",
+            Subsystem::RewardsPayouts => "rewards_payouts",
+            Subsystem::Videogen => "videogen",
+            Subsystem::Leaderboard => "leaderboard",
+            Subsystem::VideoProcessing => "video_processing",
+            Subsystem::Rollups => "rollups",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|subsystem| subsystem.as_str() == name)
+    }
+
+    /// Path prefixes whose writes are refused while the subsystem is paused
+    fn route_prefixes(self) -> &'static [&'static str] {
+        match self {
+            Subsystem::DedupIngestion => &[
+                "/qstash/video_deduplication",
+                "/qstash/milvus/",
+                "/qstash/compute_video_phash",
+                "/qstash/bulk_compute_phash",
+            ],
+            Subsystem::RewardsPayouts => {
+                &["/api/v1/daily-missions/claim", "/api/v1/leaderboard/claim"]
+            }
+            Subsystem::Videogen => &[
+                "/api/v1/videogen/",
+                "/api/v2/videogen/",
+                "/qstash/process_video_gen",
+            ],
+            Subsystem::Leaderboard => &["/api/v1/leaderboard/", "/qstash/tournament/"],
+            Subsystem::VideoProcessing => &["/qstash/transcode_video", "/qstash/transcribe_video"],
+            Subsystem::Rollups => &["/qstash/rollups/run"],
+        }
+    }
+}
+
+/// Subsystems whose routes include `path`
+fn subsystems_for_path(path: &str) -> impl Iterator<Item = Subsystem> + '_ {
+    Subsystem::ALL.into_iter().filter(move |subsystem| {
+        subsystem
+            .route_prefixes()
+            .iter()
+            .any(|prefix| path.starts_with(prefix))
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MaintenanceWindow {
+    pub reason: Option<String>,
+    /// Unix timestamps in seconds
+    pub started_at: i64,
+    /// Absent for windows that last until resumed
+    pub until: Option<i64>,
+    pub retry_after_secs: u64,
+}
+
+impl MaintenanceWindow {
+    pub fn is_active(&self, now: i64) -> bool {
+        self.until.is_none_or(|until| now < until)
+    }
+
+    /// Seconds callers should wait, capped by the window's end
+    fn retry_after(&self, now: i64) -> u64 {
+        match self.until {
+            Some(until) => self.retry_after_secs.min((until - now).max(1) as u64),
+            None => self.retry_after_secs,
+        }
+    }
+}
+
+type Windows = HashMap<Subsystem, MaintenanceWindow>;
+
+/// Active windows. Windows past their end are removed and alerted on.
+pub async fn load_windows(pool: &DragonflyPool) -> Result<Windows> {
+    let mut conn = pool.get().await?;
+    let entries: HashMap<String, String> = conn.hgetall(MAINTENANCE_KEY).await?;
+    let now = chrono::Utc::now().timestamp();
+
+    let mut windows = Windows::new();
+    for (field, payload) in entries {
+        let (Some(subsystem), Ok(window)) = (
+            Subsystem::parse(&field),
+            serde_json::from_str::<MaintenanceWindow>(&payload),
+        ) else {
+            log::warn!("Ignoring unreadable maintenance window {field}");
+            continue;
+        };
+        if window.is_active(now) {
+            windows.insert(subsystem, window);
+            continue;
+        }
+
+        // Only the instance that removes it alerts
+        let removed: u64 = conn.hdel(MAINTENANCE_KEY, &field).await?;
+        if removed > 0 {
+            alert(subsystem, None, "ended (window expired)");
+        }
+    }
+    Ok(windows)
+}
+
+pub async fn store_window(
+    pool: &DragonflyPool,
+    subsystem: Subsystem,
+    window: &MaintenanceWindow,
+) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = conn
+        .hset(
+            MAINTENANCE_KEY,
+            subsystem.as_str(),
+            serde_json::to_string(window)?,
+        )
+        .await?;
+    invalidate();
+    Ok(())
+}
+
+/// Returns whether the subsystem was paused
+pub async fn remove_window(pool: &DragonflyPool, subsystem: Subsystem) -> Result<bool> {
+    let mut conn = pool.get().await?;
+    let removed: u64 = conn.hdel(MAINTENANCE_KEY, subsystem.as_str()).await?;
+    invalidate();
+    Ok(removed > 0)
+}
+
+fn invalidate() {
+    if let Ok(mut cache) = CACHE.write() {
+        *cache = None;
+    }
+}
+
+async fn cached_windows(pool: &DragonflyPool) -> Windows {
+    if let Some(windows) = CACHE.read().ok().and_then(|cache| {
+        cache
+            .as_ref()
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < CACHE_TTL)
+            .map(|(_, windows)| windows.clone())
+    }) {
+        return windows;
+    }
+
+    match load_windows(pool).await {
+        Ok(windows) => {
+            if let Ok(mut cache) = CACHE.write() {
+                *cache = Some((Instant::now(), windows.clone()));
+            }
+            windows
+        }
+        Err(e) => {
+            log::warn!("Failed to read maintenance windows, assuming none: {e}");
+            Windows::new()
+        }
+    }
+}
+
+/// The subsystem's active window, if it is paused
+pub async fn paused(pool: &DragonflyPool, subsystem: Subsystem) -> Option<MaintenanceWindow> {
+    let window = cached_windows(pool).await.remove(&subsystem)?;
+    window
+        .is_active(chrono::Utc::now().timestamp())
+        .then_some(window)
+}
+
+/// Posts a maintenance transition to chat; never blocks the caller
+pub(crate) fn alert(subsystem: Subsystem, reason: Option<&str>, transition: &str) {
+    let mut text = format!("*Maintenance* `{}` {transition}", subsystem.as_str());
+    if let Some(reason) = reason {
+        text.push_str(&format!(": {reason}"));
+    }
+    log::warn!("{text}");
+
+    let Some(webhook_url) = ALERTS_WEBHOOK_URL.as_ref() else {
+        log::warn!("GCHAT_MAINTENANCE_ALERTS_WEBHOOK_URL not set, skipping chat alert");
+        return;
+    };
+    tokio::spawn(async move {
+        if let Err(e) = send_message_gchat_webhook(webhook_url, json!({ "text": text })).await {
+            log::error!("Failed to send maintenance alert: {e:?}");
+        }
+    });
+}
+
+pub fn spawn_watcher(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let windows = match load_windows(&state.yral_redis_store_dragonfly).await {
+                Ok(windows) => windows,
+                Err(e) => {
+                    log::warn!("Failed to read maintenance windows: {e}");
+                    continue;
+                }
+            };
+            if windows.contains_key(&Subsystem::RewardsPayouts) {
+                continue;
+            }
+
+            let engine = &state.rewards_module.reward_engine;
+            match engine.replay_deferred_milestones(&state).await {
+                Ok(0) => {}
+                Ok(paid) => log::info!("Paid {paid} milestone rewards deferred by maintenance"),
+                Err(e) => log::error!("Failed to replay deferred milestone rewards: {e:?}"),
+            }
+        }
+    });
+}
+
+/// Refuses writes to paused subsystems' routes with 503 and `Retry-After`
+pub async fn maintenance_guard(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return next.run(request).await;
+    }
+
+    let path = request.uri().path().to_string();
+    for subsystem in subsystems_for_path(&path) {
+        let Some(window) = paused(&state.yral_redis_store_dragonfly, subsystem).await else {
+            continue;
+        };
+
+        log::info!("{} paused: rejecting {path}", subsystem.as_str());
+        let retry_after = window.retry_after(chrono::Utc::now().timestamp());
+        let mut response = ApiError::ServiceUnavailable(format!(
+            "{} is under maintenance{}",
+            subsystem.as_str(),
+            window
+                .reason
+                .map(|reason| format!(": {reason}"))
+                .unwrap_or_default()
+        ))
+        .into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        return response;
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes_and_windows() {
+        let paused_for = |path| subsystems_for_path(path).collect::<Vec<_>>();
+        assert_eq!(
+            paused_for("/qstash/milvus/ingest_phash"),
+            vec![Subsystem::DedupIngestion]
+        );
+        assert_eq!(
+            paused_for("/api/v1/leaderboard/claim"),
+            vec![Subsystem::RewardsPayouts, Subsystem::Leaderboard]
+        );
+        assert_eq!(
+            paused_for("/qstash/tournament/end/7"),
+            vec![Subsystem::Leaderboard]
+        );
+        assert!(paused_for("/api/v1/posts/report").is_empty());
+        assert_eq!(Subsystem::parse("rollups"), Some(Subsystem::Rollups));
+
+        let window = MaintenanceWindow {
+            reason: None,
+            started_at: 0,
+            until: Some(100),
+            retry_after_secs: 300,
+        };
+        assert!(window.is_active(99));
+        assert!(!window.is_active(100));
+        assert_eq!(window.retry_after(40), 60);
+        let open_ended = MaintenanceWindow {
+            until: None,
+            ..window
+        };
+        assert!(open_ended.is_active(i64::MAX));
+        assert_eq!(open_ended.retry_after(40), 300);
+    }
+}
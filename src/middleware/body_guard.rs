@@ -0,0 +1,100 @@
+//! Per-route-group request body limits and JSON content-type enforcement.
+//!
+//! [`body_limit`] sets both axum's extractor limit and a
+//! `RequestBodyLimitLayer`, so middleware that reads the body itself
+//! (delegated identity verification) is bounded too. A
+//! `RequestBodyLimitLayer` can only narrow the limit of an outer one, so the
+//! server-wide cap, applied outside the HTTP logger, is [`UPLOAD_LIMIT`].
+
+use axum::{
+    extract::{DefaultBodyLimit, Request},
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING},
+        Method,
+    },
+    middleware::Next,
+    response::Response,
+};
+use tower::{
+    layer::util::{Identity, Stack},
+    ServiceBuilder,
+};
+use tower_http::limit::RequestBodyLimitLayer;
+
+use crate::error::ApiError;
+
+/// Small JSON APIs: single events, leaderboard, rewards
+pub const SMALL_JSON_LIMIT: usize = 64 * 1024;
+/// Anything without a more specific limit, including bulk events
+pub const DEFAULT_LIMIT: usize = 1024 * 1024;
+/// Base64 profile images
+pub const IMAGE_LIMIT: usize = 10 * 1024 * 1024;
+/// Video generation inputs carrying base64 images and audio
+pub const UPLOAD_LIMIT: usize = 50 * 1024 * 1024;
+
+pub type BodyLimitLayer =
+    ServiceBuilder<Stack<RequestBodyLimitLayer, Stack<DefaultBodyLimit, Identity>>>;
+
+/// Rejects request bodies over `limit` bytes with 413
+pub fn body_limit(limit: usize) -> BodyLimitLayer {
+    ServiceBuilder::new()
+        .layer(DefaultBodyLimit::max(limit))
+        .layer(RequestBodyLimitLayer::new(limit))
+}
+
+fn is_json(content_type: &str) -> bool {
+    let Some(essence) = content_type.split(';').next() else {
+        return false;
+    };
+    let essence = essence.trim().to_ascii_lowercase();
+    essence == "application/json"
+        || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+fn has_body(request: &Request) -> bool {
+    if request.headers().contains_key(TRANSFER_ENCODING) {
+        return true;
+    }
+    request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+        .is_some_and(|len| len > 0)
+}
+
+/// Rejects POST/PUT/PATCH requests with a body that isn't JSON with 415
+pub async fn require_json(request: Request, next: Next) -> Result<Response, ApiError> {
+    let writes = matches!(
+        *request.method(),
+        Method::POST | Method::PUT | Method::PATCH
+    );
+    if writes && has_body(&request) {
+        let content_type = request
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if !is_json(content_type) {
+            return Err(ApiError::UnsupportedMediaType(format!(
+                "Expected application/json, got '{content_type}'"
+            )));
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_json() {
+        assert!(is_json("application/json"));
+        assert!(is_json("Application/JSON; charset=utf-8"));
+        assert!(is_json("application/problem+json"));
+        assert!(!is_json("text/plain"));
+        assert!(!is_json("multipart/form-data; boundary=x"));
+        assert!(!is_json(""));
+    }
+}
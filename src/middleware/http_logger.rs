@@ -1,6 +1,6 @@
 use axum::{
     body::{Body, Bytes},
-    extract::Request,
+    extract::{MatchedPath, Request},
     http::StatusCode,
     middleware::Next,
     response::{IntoResponse, Response},
@@ -10,6 +10,8 @@ use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Instant;
 
+use super::http_metrics::{self, RequestTimings};
+
 /// Global request counter for generating unique request IDs
 static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(0);
 
@@ -47,20 +49,34 @@ fn should_capture_body(content_type: Option<&str>) -> bool {
 }
 
 /// HTTP logging middleware that captures request/response data as Sentry breadcrumbs
+/// and records per-route latency metrics
 /// Optimized: Only parses/scrubs bodies for error responses (status >= 400)
 pub async fn http_logging_middleware(
-    req: Request,
+    mut req: Request,
     next: Next,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let start = Instant::now();
+    let method = req.method().clone();
+    let matched_path = req.extensions().get::<MatchedPath>().cloned();
+    let timings = RequestTimings::default();
+    req.extensions_mut().insert(timings.clone());
+
     if !is_logging_enabled() {
-        return Ok(next.run(req).await);
+        let res = next.run(req).await;
+        let route = http_metrics::route_template(matched_path.as_ref(), &res);
+        http_metrics::observe_request(
+            method.as_str(),
+            &route,
+            res.status().as_u16(),
+            start.elapsed(),
+            &timings,
+        );
+        return Ok(res);
     }
 
     // Generate unique request ID for tracing
     let request_id = REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed);
 
-    let start = Instant::now();
-    let method = req.method().clone();
     let uri = req.uri().clone();
 
     // Extract safe headers (excluding sensitive ones)
@@ -101,6 +117,8 @@ pub async fn http_logging_middleware(
     // Capture response details
     let status = res.status();
     let duration = start.elapsed();
+    let route = http_metrics::route_template(matched_path.as_ref(), &res);
+    http_metrics::observe_request(method.as_str(), &route, status.as_u16(), duration, &timings);
     let response_headers = extract_safe_headers(res.headers());
 
     let response_content_type = res
@@ -0,0 +1,275 @@
+//! Per-route HTTP latency histograms and slow-request logging.
+//!
+//! [`http_logging_middleware`](super::http_logging_middleware) records every
+//! request into a histogram keyed by method, route template and status,
+//! served at `/http-metrics`. Auth layers and handlers can attribute time to
+//! named phases through the [`RequestTimings`] request extension; requests
+//! slower than `SLOW_REQUEST_THRESHOLD_MS` are logged with that breakdown.
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    http::Extensions,
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{auth::require_operator, error::ApiError};
+
+const DEFAULT_SLOW_REQUEST_MS: u64 = 2000;
+
+/// Upper bounds of the latency buckets in milliseconds; the last bucket is
+/// unbounded
+const BUCKET_BOUNDS_MS: [u64; 11] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Template used for requests that matched no route, so probing does not
+/// create one series per path
+const UNMATCHED_ROUTE: &str = "<unmatched>";
+
+static SLOW_REQUEST_THRESHOLD: Lazy<Duration> = Lazy::new(|| {
+    Duration::from_millis(
+        std::env::var("SLOW_REQUEST_THRESHOLD_MS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_SLOW_REQUEST_MS),
+    )
+});
+
+static METRICS: Lazy<Mutex<BTreeMap<String, RouteStats>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RouteStats {
+    pub count: u64,
+    pub total_latency_ms: u64,
+    pub max_latency_ms: u64,
+    /// Request counts per bucket, aligned with `bucket_bounds_ms` plus a
+    /// final overflow bucket
+    pub buckets: Vec<u64>,
+}
+
+impl Default for RouteStats {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            total_latency_ms: 0,
+            max_latency_ms: 0,
+            buckets: vec![0; BUCKET_BOUNDS_MS.len() + 1],
+        }
+    }
+}
+
+impl RouteStats {
+    fn observe(&mut self, latency_ms: u64) {
+        self.count += 1;
+        self.total_latency_ms += latency_ms;
+        self.max_latency_ms = self.max_latency_ms.max(latency_ms);
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&bound| latency_ms <= bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.buckets[bucket] += 1;
+    }
+
+    /// Upper bound of the bucket holding the given quantile; the overflow
+    /// bucket reports the observed maximum
+    fn quantile_ms(&self, quantile: f64) -> u64 {
+        let rank = ((self.count as f64) * quantile).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return BUCKET_BOUNDS_MS
+                    .get(i)
+                    .copied()
+                    .unwrap_or(self.max_latency_ms)
+                    .min(self.max_latency_ms);
+            }
+        }
+        self.max_latency_ms
+    }
+}
+
+/// Time spent in named phases of one request, shared through request
+/// extensions so auth layers and handlers can report into the slow-request log
+#[derive(Debug, Clone, Default)]
+pub struct RequestTimings(Arc<Mutex<Vec<(&'static str, Duration)>>>);
+
+impl RequestTimings {
+    /// The timings of the current request, if the metrics middleware is
+    /// installed in front of the caller
+    pub fn from_extensions(extensions: &Extensions) -> Option<Self> {
+        extensions.get::<Self>().cloned()
+    }
+
+    pub fn record(&self, phase: &'static str, elapsed: Duration) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((phase, elapsed));
+    }
+
+    /// Awaits `fut` and attributes its duration to `phase`
+    pub async fn time<T>(&self, phase: &'static str, fut: impl Future<Output = T>) -> T {
+        let started = Instant::now();
+        let output = fut.await;
+        self.record(phase, started.elapsed());
+        output
+    }
+
+    /// Milliseconds per phase, summing repeated phases
+    fn phases_ms(&self) -> BTreeMap<&'static str, u64> {
+        let mut phases = BTreeMap::new();
+        for (phase, elapsed) in self.0.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            *phases.entry(*phase).or_insert(0) += elapsed.as_millis() as u64;
+        }
+        phases
+    }
+}
+
+/// Route template of a request matched by a nested or fallback router, copied
+/// into the response because the outer logging layer runs before routing
+#[derive(Debug, Clone)]
+struct RouteTemplate(MatchedPath);
+
+/// Forwards the matched route template to the outer logging layer. Installed
+/// on routers that are merged in as fallbacks.
+pub async fn expose_matched_path(req: Request, next: Next) -> Response {
+    let matched = req.extensions().get::<MatchedPath>().cloned();
+    let mut res = next.run(req).await;
+    if let Some(matched) = matched {
+        res.extensions_mut().insert(RouteTemplate(matched));
+    }
+    res
+}
+
+/// Route template for metrics: the one seen by the outer router, else the
+/// one forwarded by [`expose_matched_path`]
+pub(crate) fn route_template(matched: Option<&MatchedPath>, res: &Response) -> String {
+    matched
+        .or_else(|| res.extensions().get::<RouteTemplate>().map(|t| &t.0))
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string())
+}
+
+/// Records one finished request and logs it if it crossed the slow threshold
+pub(crate) fn observe_request(
+    method: &str,
+    route: &str,
+    status: u16,
+    elapsed: Duration,
+    timings: &RequestTimings,
+) {
+    let latency_ms = elapsed.as_millis() as u64;
+    METRICS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(format!("{method} {route} {status}"))
+        .or_default()
+        .observe(latency_ms);
+
+    if elapsed < *SLOW_REQUEST_THRESHOLD {
+        return;
+    }
+
+    let phases = timings.phases_ms();
+    let attributed: u64 = phases.values().sum();
+    tracing::warn!(
+        method,
+        route,
+        status,
+        latency_ms,
+        auth_ms = phases.get("auth").copied().unwrap_or(0),
+        unattributed_ms = latency_ms.saturating_sub(attributed),
+        phases = %serde_json::json!(phases),
+        "Slow HTTP request"
+    );
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HttpMetricsResponse {
+    pub slow_request_threshold_ms: u64,
+    pub bucket_bounds_ms: Vec<u64>,
+    /// Keyed by `<method> <route template> <status>`
+    pub routes: BTreeMap<String, RouteSummary>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RouteSummary {
+    #[serde(flatten)]
+    pub stats: RouteStats,
+    /// Bucket-resolution estimates
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+}
+
+/// Per-route latency histograms since process start; operators only
+pub async fn http_metrics_handler(
+    headers: HeaderMap,
+) -> Result<Json<HttpMetricsResponse>, ApiError> {
+    require_operator(&headers)?;
+    let metrics = METRICS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let routes = metrics
+        .into_iter()
+        .map(|(key, stats)| {
+            let summary = RouteSummary {
+                p50_ms: stats.quantile_ms(0.5),
+                p95_ms: stats.quantile_ms(0.95),
+                p99_ms: stats.quantile_ms(0.99),
+                stats,
+            };
+            (key, summary)
+        })
+        .collect();
+    Ok(Json(HttpMetricsResponse {
+        slow_request_threshold_ms: SLOW_REQUEST_THRESHOLD.as_millis() as u64,
+        bucket_bounds_ms: BUCKET_BOUNDS_MS.to_vec(),
+        routes,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_stats_buckets_and_quantiles() {
+        let mut stats = RouteStats::default();
+        for latency_ms in [1, 3, 7, 40, 40, 90, 200, 400, 800, 12_000] {
+            stats.observe(latency_ms);
+        }
+        assert_eq!(stats.count, 10);
+        assert_eq!(stats.max_latency_ms, 12_000);
+        assert_eq!(stats.buckets[0], 2);
+        assert_eq!(stats.buckets[BUCKET_BOUNDS_MS.len()], 1);
+        assert_eq!(stats.quantile_ms(0.5), 50);
+        assert_eq!(stats.quantile_ms(0.99), 12_000);
+    }
+
+    #[tokio::test]
+    async fn test_request_timings_sum_repeated_phases() {
+        let timings = RequestTimings::default();
+        timings.record("auth", Duration::from_millis(30));
+        timings.record("redis", Duration::from_millis(5));
+        timings.record("redis", Duration::from_millis(7));
+        let value = timings.time("handler", async { 42 }).await;
+        assert_eq!(value, 42);
+
+        let phases = timings.phases_ms();
+        assert_eq!(phases["auth"], 30);
+        assert_eq!(phases["redis"], 12);
+        assert!(phases.contains_key("handler"));
+    }
+}
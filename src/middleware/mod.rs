@@ -1,6 +1,9 @@
+pub mod body_guard;
 pub mod http_logger;
+pub mod http_metrics;
 pub mod sentry_scrub;
 pub mod sentry_user;
+pub mod trace_context;
 
 pub use http_logger::http_logging_middleware;
 pub use sentry_user::set_user_context;
@@ -0,0 +1,168 @@
+//! Distributed trace propagation across QStash hops.
+//!
+//! Outgoing QStash publishes carry the current span as `sentry-trace` and W3C
+//! `traceparent` headers, prefixed with `Upstash-Forward-` so QStash replays
+//! them on delivery. Incoming requests that only carry `traceparent` get an
+//! equivalent `sentry-trace` header so the Sentry tower layer continues the
+//! same trace instead of starting a new one.
+
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+pub const SENTRY_TRACE_HEADER: &str = "sentry-trace";
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+const UPSTASH_FORWARD_PREFIX: &str = "Upstash-Forward-";
+
+/// Converts `{trace_id}-{span_id}[-{sampled}]` into a W3C `traceparent`
+pub fn sentry_trace_to_traceparent(sentry_trace: &str) -> Option<String> {
+    let mut parts = sentry_trace.trim().split('-');
+    let trace_id = parts.next()?;
+    let span_id = parts.next()?;
+    let sampled = parts.next();
+
+    if !is_hex(trace_id, 32) || !is_hex(span_id, 16) {
+        return None;
+    }
+
+    let flags = match sampled {
+        Some("0") => "00",
+        _ => "01",
+    };
+    Some(format!("00-{trace_id}-{span_id}-{flags}"))
+}
+
+/// Converts a W3C `traceparent` (`{version}-{trace_id}-{parent_id}-{flags}`)
+/// into a `sentry-trace` value
+pub fn traceparent_to_sentry_trace(traceparent: &str) -> Option<String> {
+    let mut parts = traceparent.trim().split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let parent_id = parts.next()?;
+    let flags = parts.next()?;
+
+    if !is_hex(version, 2) || version == "ff" || !is_hex(flags, 2) {
+        return None;
+    }
+    if !is_hex(trace_id, 32) || !is_hex(parent_id, 16) {
+        return None;
+    }
+    // All-zero ids are invalid per the W3C spec
+    if trace_id.bytes().all(|b| b == b'0') || parent_id.bytes().all(|b| b == b'0') {
+        return None;
+    }
+
+    let sampled = u8::from_str_radix(flags, 16).ok()? & 0x01;
+    Some(format!("{trace_id}-{parent_id}-{sampled}"))
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Trace headers for the span that is currently active on the Sentry scope.
+/// Empty when there is no active span, e.g. in background workers.
+pub fn current_trace_headers() -> Vec<(&'static str, String)> {
+    let Some(span) = sentry::configure_scope(|scope| scope.get_span()) else {
+        return Vec::new();
+    };
+
+    let mut headers = Vec::new();
+    for (name, value) in span.iter_headers() {
+        if name == SENTRY_TRACE_HEADER {
+            if let Some(traceparent) = sentry_trace_to_traceparent(&value) {
+                headers.push((TRACEPARENT_HEADER, traceparent));
+            }
+        }
+        headers.push((name, value));
+    }
+    headers
+}
+
+fn forwarded_header_pairs(headers: Vec<(&'static str, String)>) -> Vec<(String, String)> {
+    headers
+        .into_iter()
+        .map(|(name, value)| (format!("{UPSTASH_FORWARD_PREFIX}{name}"), value))
+        .collect()
+}
+
+/// Trace headers for a QStash publish, prefixed so QStash forwards them to
+/// the destination handler
+pub fn qstash_forward_trace_headers() -> HeaderMap {
+    let mut header_map = HeaderMap::new();
+    for (name, value) in forwarded_header_pairs(current_trace_headers()) {
+        let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) else {
+            continue;
+        };
+        header_map.insert(name, value);
+    }
+    header_map
+}
+
+/// Same as [`qstash_forward_trace_headers`], for the per-message `headers`
+/// object of the QStash batch API
+pub fn qstash_forward_trace_headers_json() -> serde_json::Map<String, serde_json::Value> {
+    forwarded_header_pairs(current_trace_headers())
+        .into_iter()
+        .map(|(name, value)| (name, serde_json::Value::String(value)))
+        .collect()
+}
+
+/// Derives `sentry-trace` from `traceparent` when a caller only speaks W3C
+/// trace context. Must run before the Sentry tower layer.
+pub async fn continue_trace_middleware(mut request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    if !headers.contains_key(SENTRY_TRACE_HEADER) {
+        let sentry_trace = headers
+            .get(TRACEPARENT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(traceparent_to_sentry_trace)
+            .and_then(|value| HeaderValue::from_str(&value).ok());
+
+        if let Some(sentry_trace) = sentry_trace {
+            request
+                .headers_mut()
+                .insert(HeaderName::from_static(SENTRY_TRACE_HEADER), sentry_trace);
+        }
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_header_round_trip() {
+        let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+        let span_id = "00f067aa0ba902b7";
+
+        let traceparent = sentry_trace_to_traceparent(&format!("{trace_id}-{span_id}-1")).unwrap();
+        assert_eq!(traceparent, format!("00-{trace_id}-{span_id}-01"));
+        assert_eq!(
+            traceparent_to_sentry_trace(&traceparent).unwrap(),
+            format!("{trace_id}-{span_id}-1")
+        );
+
+        // Unsampled and sampling-deferred sentry traces
+        assert_eq!(
+            sentry_trace_to_traceparent(&format!("{trace_id}-{span_id}-0")).unwrap(),
+            format!("00-{trace_id}-{span_id}-00")
+        );
+        assert!(sentry_trace_to_traceparent(&format!("{trace_id}-{span_id}")).is_some());
+
+        assert!(traceparent_to_sentry_trace("00-abc-def-01").is_none());
+        assert!(
+            traceparent_to_sentry_trace(&format!("00-{}-{span_id}-01", "0".repeat(32))).is_none()
+        );
+        assert!(traceparent_to_sentry_trace(&format!("ff-{trace_id}-{span_id}-01")).is_none());
+    }
+}
@@ -1,5 +1,12 @@
 use crate::app_state::AppState;
-use axum::{extract::State, http::StatusCode, Json};
+use crate::auth::check_api_key;
+use crate::error::{ApiError, ApiErrorBody};
+use crate::milvus::embeddings;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::instrument;
@@ -131,7 +138,8 @@ pub async fn check_duplicate_handler(
     );
 
     if let Some(milvus_client) = &state.milvus_client {
-        match crate::milvus::search_similar_videos(milvus_client, &phash, req.hamming_threshold)
+        match milvus_client
+            .search_similar_videos(&phash, req.hamming_threshold, 1)
             .await
         {
             Ok(results) => {
@@ -159,7 +167,11 @@ pub async fn check_duplicate_handler(
             }
             Err(e) => {
                 log::error!("Milvus search failed for URL {}: {}", req.video_url, e);
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(if e.is_unavailable() {
+                    StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                });
             }
         }
     } else {
@@ -175,3 +187,240 @@ pub async fn check_duplicate_handler(
         threshold_used: req.hamming_threshold,
     }))
 }
+
+/// Env var holding the comma-separated API keys allowed to call batch search
+const SEARCH_API_KEYS_ENV: &str = "MILVUS_SEARCH_API_KEYS";
+const MAX_BATCH_PHASHES: usize = 500;
+const MAX_TOP_K: u32 = 20;
+
+fn default_top_k() -> u32 {
+    5
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchSearchRequest {
+    /// 640-character binary phash strings, at most 500
+    pub phashes: Vec<String>,
+    /// Neighbours to return per phash (1-20, default 5)
+    #[serde(default = "default_top_k")]
+    pub top_k: u32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NeighbourMatch {
+    pub video_id: String,
+    pub hamming_distance: u32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PhashNeighbours {
+    pub phash: String,
+    /// Closest first; includes exact matches at distance 0
+    pub matches: Vec<NeighbourMatch>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchSearchResponse {
+    pub results: Vec<PhashNeighbours>,
+}
+
+fn validate_batch_request(req: &BatchSearchRequest) -> Result<(), ApiError> {
+    if req.phashes.is_empty() || req.phashes.len() > MAX_BATCH_PHASHES {
+        return Err(ApiError::InvalidRequest(format!(
+            "phashes must contain between 1 and {MAX_BATCH_PHASHES} entries"
+        )));
+    }
+    if req.top_k == 0 || req.top_k > MAX_TOP_K {
+        return Err(ApiError::InvalidRequest(format!(
+            "top_k must be between 1 and {MAX_TOP_K}"
+        )));
+    }
+    if let Some((index, e)) = req.phashes.iter().enumerate().find_map(|(index, phash)| {
+        crate::milvus::utils::phash_to_binary_vector(phash)
+            .err()
+            .map(|e| (index, e))
+    }) {
+        return Err(ApiError::InvalidRequest(format!(
+            "Invalid phash at index {index}: {e}"
+        )));
+    }
+    Ok(())
+}
+
+/// Nearest neighbours for a batch of phashes in a single Milvus search
+#[utoipa::path(
+    post,
+    path = "/search/batch",
+    request_body = BatchSearchRequest,
+    tag = "milvus",
+    responses(
+        (status = 200, description = "Neighbours per phash, in request order", body = BatchSearchResponse),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 401, description = "Missing or invalid API key", body = ApiErrorBody),
+        (status = 503, description = "Milvus service unavailable", body = ApiErrorBody)
+    ),
+    security(
+        ("api_key" = [])
+    )
+)]
+#[instrument(skip(state, headers, req), fields(num_phashes = req.phashes.len()))]
+pub async fn batch_search_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<BatchSearchRequest>,
+) -> Result<Json<BatchSearchResponse>, ApiError> {
+    check_api_key(&headers, SEARCH_API_KEYS_ENV)
+        .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+    validate_batch_request(&req)?;
+
+    let milvus_client = state
+        .milvus_client
+        .as_ref()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Milvus client not available".to_string()))?;
+
+    let neighbours = milvus_client
+        .search_similar_videos_batch(&req.phashes, req.top_k as i32)
+        .await?;
+
+    let results = req
+        .phashes
+        .into_iter()
+        .zip(
+            neighbours
+                .into_iter()
+                .chain(std::iter::repeat_with(Vec::new)),
+        )
+        .map(|(phash, matches)| PhashNeighbours {
+            phash,
+            matches: matches
+                .into_iter()
+                .map(|m| NeighbourMatch {
+                    video_id: m.video_id,
+                    hamming_distance: m.hamming_distance,
+                })
+                .collect(),
+        })
+        .collect();
+
+    Ok(Json(BatchSearchResponse { results }))
+}
+
+fn default_similar_top_k() -> u32 {
+    10
+}
+
+const MAX_SIMILAR_TOP_K: u32 = 100;
+
+/// Either a video to find neighbours of, or a raw embedding
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SimilarByEmbeddingRequest {
+    /// Use this video's stored embedding as the query ("more like this")
+    pub video_id: Option<String>,
+    /// Query embedding, 512 floats; ignored when video_id is set
+    pub embedding: Option<Vec<f32>>,
+    /// Number of similar videos to return (1-100, default 10)
+    #[serde(default = "default_similar_top_k")]
+    pub top_k: u32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimilarVideo {
+    pub video_id: String,
+    /// Cosine similarity, 1.0 = identical
+    pub score: f32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SimilarByEmbeddingResponse {
+    pub results: Vec<SimilarVideo>,
+}
+
+/// Semantically similar videos by content embedding, most similar first
+#[utoipa::path(
+    post,
+    path = "/similar_by_embedding",
+    request_body = SimilarByEmbeddingRequest,
+    tag = "milvus",
+    responses(
+        (status = 200, description = "Similar videos", body = SimilarByEmbeddingResponse),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 404, description = "No embedding stored for video", body = ApiErrorBody),
+        (status = 503, description = "Milvus service unavailable", body = ApiErrorBody)
+    )
+)]
+#[instrument(skip(state, req), fields(video_id = ?req.video_id, top_k = req.top_k))]
+pub async fn similar_by_embedding_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SimilarByEmbeddingRequest>,
+) -> Result<Json<SimilarByEmbeddingResponse>, ApiError> {
+    if req.top_k == 0 || req.top_k > MAX_SIMILAR_TOP_K {
+        return Err(ApiError::InvalidRequest(format!(
+            "top_k must be between 1 and {MAX_SIMILAR_TOP_K}"
+        )));
+    }
+
+    let embedding = match (&req.video_id, req.embedding) {
+        (Some(video_id), _) => {
+            let stored = state
+                .kvrocks_client
+                .get(&crate::kvrocks::tables::VIDEO_EMBEDDINGS, video_id)
+                .await?
+                .ok_or_else(|| {
+                    ApiError::NotFound(format!("No embedding stored for video {video_id}"))
+                })?;
+            embeddings::mean_embedding(&stored.embedding_vectors).ok_or_else(|| {
+                ApiError::NotFound(format!("Stored embedding for {video_id} is unusable"))
+            })?
+        }
+        (None, Some(embedding)) => embeddings::normalize(&embedding).ok_or_else(|| {
+            ApiError::InvalidRequest(format!(
+                "embedding must have {} non-zero dimensions",
+                embeddings::EMBEDDING_DIM
+            ))
+        })?,
+        (None, None) => {
+            return Err(ApiError::InvalidRequest(
+                "Either video_id or embedding is required".to_string(),
+            ))
+        }
+    };
+
+    let milvus_client = state
+        .milvus_client
+        .as_ref()
+        .ok_or_else(|| ApiError::ServiceUnavailable("Milvus client not available".to_string()))?;
+
+    // Fetch one extra so the query video can be dropped from its own results
+    let matches = milvus_client
+        .search_by_embedding(&embedding, req.top_k as i32 + 1)
+        .await?;
+
+    let results = matches
+        .into_iter()
+        .filter(|m| req.video_id.as_deref() != Some(m.video_id.as_str()))
+        .take(req.top_k as usize)
+        .map(|m| SimilarVideo {
+            video_id: m.video_id,
+            score: m.score,
+        })
+        .collect();
+
+    Ok(Json(SimilarByEmbeddingResponse { results }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_batch_request() {
+        let valid = "01".repeat(320);
+        let request = |phashes: Vec<String>, top_k| BatchSearchRequest { phashes, top_k };
+
+        assert!(validate_batch_request(&request(vec![valid.clone()], 5)).is_ok());
+        assert!(validate_batch_request(&request(vec![], 5)).is_err());
+        assert!(validate_batch_request(&request(vec![valid.clone(); 501], 5)).is_err());
+        assert!(validate_batch_request(&request(vec![valid.clone()], 0)).is_err());
+        assert!(validate_batch_request(&request(vec![valid, "0101".to_string()], 5)).is_err());
+    }
+}
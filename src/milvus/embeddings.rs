@@ -0,0 +1,282 @@
+use anyhow::{Context, Result};
+use milvus::client::Client as MilvusClient;
+use milvus::collection::SearchOption;
+use milvus::data::FieldColumn;
+use milvus::index::{IndexParams, IndexType, MetricType};
+use milvus::options::CreateCollectionOptions;
+use milvus::schema::{CollectionSchemaBuilder, FieldSchema};
+use milvus::value::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+pub const EMBEDDING_COLLECTION_NAME: &str = "video_embedding";
+pub const EMBEDDING_DIM: usize = 512;
+const IVF_NLIST: u32 = 1024;
+const IVF_NPROBE: u32 = 16;
+
+#[derive(Debug, Clone)]
+pub struct VideoEmbeddingRecord {
+    pub video_id: String,
+    pub embedding: Vec<f32>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingMatch {
+    pub video_id: String,
+    /// Cosine similarity, 1.0 = identical direction
+    pub score: f32,
+}
+
+/// Scales `embedding` to unit length so inner product equals cosine
+/// similarity. Returns None for wrong-sized or all-zero vectors.
+pub fn normalize(embedding: &[f32]) -> Option<Vec<f32>> {
+    if embedding.len() != EMBEDDING_DIM {
+        return None;
+    }
+    let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if !norm.is_finite() || norm == 0.0 {
+        return None;
+    }
+    Some(embedding.iter().map(|v| v / norm).collect())
+}
+
+/// Mean of the per-segment embeddings of a video
+pub fn mean_embedding(vectors: &[Vec<f32>]) -> Option<Vec<f32>> {
+    let first = vectors.first()?;
+    let mut sum = vec![0.0f32; first.len()];
+    for vector in vectors.iter().filter(|v| v.len() == first.len()) {
+        for (acc, v) in sum.iter_mut().zip(vector) {
+            *acc += v;
+        }
+    }
+    normalize(&sum)
+}
+
+/// Check if the embedding collection exists and create it with an IVF index if not
+pub async fn init_embedding_collection(client: &MilvusClient) -> Result<()> {
+    if client
+        .has_collection(EMBEDDING_COLLECTION_NAME)
+        .await
+        .context("Failed to check if embedding collection exists")?
+    {
+        return Ok(());
+    }
+
+    log::info!("Creating new collection: {}", EMBEDDING_COLLECTION_NAME);
+
+    let mut schema_builder = CollectionSchemaBuilder::new(
+        EMBEDDING_COLLECTION_NAME,
+        "Video content embeddings for semantic similarity",
+    );
+    schema_builder.add_field(FieldSchema::new_primary_varchar(
+        "video_id",
+        "Unique video identifier",
+        false,
+        256,
+    ));
+    schema_builder.add_field(FieldSchema::new_float_vector(
+        "embedding",
+        "L2-normalized video embedding",
+        EMBEDDING_DIM as i64,
+    ));
+    schema_builder.add_field(FieldSchema::new_int64(
+        "created_at",
+        "Timestamp when embedding was ingested",
+    ));
+
+    let schema = schema_builder
+        .build()
+        .context("Failed to build embedding collection schema")?;
+
+    client
+        .create_collection(schema, Some(CreateCollectionOptions::default()))
+        .await
+        .context("Failed to create embedding collection")?;
+
+    let collection = client
+        .get_collection(EMBEDDING_COLLECTION_NAME)
+        .await
+        .context("Failed to get embedding collection")?;
+
+    // Vectors are normalized on the way in, so IP ranks by cosine similarity
+    let index_params = IndexParams::new(
+        "embedding_index".to_string(),
+        IndexType::IvfFlat,
+        MetricType::IP,
+        HashMap::from([("nlist".to_string(), IVF_NLIST.to_string())]),
+    );
+    collection
+        .create_index("embedding", index_params)
+        .await
+        .context("Failed to create embedding index")?;
+
+    collection
+        .load(1)
+        .await
+        .context("Failed to load embedding collection")?;
+
+    log::info!(
+        "Collection {} created successfully",
+        EMBEDDING_COLLECTION_NAME
+    );
+    Ok(())
+}
+
+/// Upsert-free batch insert; callers filter out already-ingested videos
+pub async fn insert_embeddings(
+    client: &MilvusClient,
+    records: Vec<VideoEmbeddingRecord>,
+) -> Result<()> {
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let collection = client
+        .get_collection(EMBEDDING_COLLECTION_NAME)
+        .await
+        .context("Failed to get embedding collection")?;
+    let schema = collection.schema();
+
+    let mut video_ids = Vec::with_capacity(records.len());
+    let mut embeddings_flat = Vec::with_capacity(records.len() * EMBEDDING_DIM);
+    let mut timestamps = Vec::with_capacity(records.len());
+
+    for record in records {
+        let embedding = normalize(&record.embedding).with_context(|| {
+            format!(
+                "Invalid embedding for {}: expected {} non-zero dims, got {}",
+                record.video_id,
+                EMBEDDING_DIM,
+                record.embedding.len()
+            )
+        })?;
+        video_ids.push(record.video_id);
+        embeddings_flat.extend(embedding);
+        timestamps.push(record.created_at);
+    }
+
+    let video_id_field = schema
+        .get_field("video_id")
+        .context("video_id field not found")?;
+    let embedding_field = schema
+        .get_field("embedding")
+        .context("embedding field not found")?;
+    let timestamp_field = schema
+        .get_field("created_at")
+        .context("created_at field not found")?;
+
+    collection
+        .insert(
+            vec![
+                FieldColumn::new(video_id_field, video_ids),
+                FieldColumn::new(embedding_field, embeddings_flat),
+                FieldColumn::new(timestamp_field, timestamps),
+            ],
+            None,
+        )
+        .await
+        .context("Failed to insert embeddings into Milvus")?;
+
+    Ok(())
+}
+
+/// Delete a video's embedding rows
+pub async fn delete_embeddings(client: &MilvusClient, video_id: &str) -> Result<()> {
+    let expr = super::video_id_expr(video_id)?;
+    let collection = client
+        .get_collection(EMBEDDING_COLLECTION_NAME)
+        .await
+        .context("Failed to get embedding collection")?;
+
+    collection
+        .delete(&expr, None)
+        .await
+        .context("Failed to delete embeddings from Milvus")?;
+
+    Ok(())
+}
+
+/// Videos whose embeddings are closest to `embedding`, most similar first
+pub async fn search_by_embedding(
+    client: &MilvusClient,
+    embedding: &[f32],
+    top_k: i32,
+) -> Result<Vec<EmbeddingMatch>> {
+    let query_vector = normalize(embedding).with_context(|| {
+        format!(
+            "Invalid query embedding: expected {} non-zero dims, got {}",
+            EMBEDDING_DIM,
+            embedding.len()
+        )
+    })?;
+
+    let collection = client
+        .get_collection(EMBEDDING_COLLECTION_NAME)
+        .await
+        .context("Failed to get embedding collection")?;
+
+    if !collection
+        .is_loaded()
+        .await
+        .context("Failed to check if embedding collection is loaded")?
+    {
+        collection
+            .load(1)
+            .await
+            .context("Failed to load embedding collection")?;
+    }
+
+    let mut search_option = SearchOption::new();
+    search_option.add_param("nprobe", serde_json::json!(IVF_NPROBE));
+
+    let results = collection
+        .search(
+            vec![Value::FloatArray(Cow::Owned(query_vector))],
+            "embedding",
+            top_k,
+            MetricType::IP,
+            vec!["video_id".to_string()],
+            &search_option,
+        )
+        .await
+        .context("Failed to search embeddings in Milvus")?;
+
+    let mut matches = Vec::new();
+    for result_set in results {
+        for i in 0..result_set.size as usize {
+            if let Some(Value::String(video_id)) = result_set.id.get(i) {
+                matches.push(EmbeddingMatch {
+                    video_id: video_id.to_string(),
+                    score: result_set.score[i],
+                });
+            }
+        }
+    }
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_and_mean() {
+        let mut v = vec![0.0; EMBEDDING_DIM];
+        v[0] = 3.0;
+        v[1] = 4.0;
+        let n = normalize(&v).unwrap();
+        assert!((n[0] - 0.6).abs() < 1e-6 && (n[1] - 0.8).abs() < 1e-6);
+
+        assert!(normalize(&[1.0, 2.0]).is_none());
+        assert!(normalize(&vec![0.0; EMBEDDING_DIM]).is_none());
+
+        let mut w = vec![0.0; EMBEDDING_DIM];
+        w[0] = 1.0;
+        let mean = mean_embedding(&[v.clone(), w]).unwrap();
+        assert!(mean[0] > mean[1]);
+        assert!(mean_embedding(&[]).is_none());
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use thiserror::Error;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::error::ApiError;
+
+use super::embeddings::{init_embedding_collection, EmbeddingMatch, VideoEmbeddingRecord};
+use super::{
+    create_milvus_client, init_collection, Client as MilvusClient, SearchResult, VideoHashRecord,
+    COLLECTION_NAME,
+};
+
+/// Upper bound for a single Milvus request
+const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+/// Upper bound for connecting and initialising the collections
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Error)]
+pub enum MilvusError {
+    #[error("Milvus unavailable: {0}")]
+    Unavailable(String),
+    #[error("Milvus request timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("Milvus request failed: {0}")]
+    Request(String),
+}
+
+impl MilvusError {
+    /// Whether the caller should report the service as unavailable rather
+    /// than the request as failed
+    pub fn is_unavailable(&self) -> bool {
+        matches!(self, Self::Unavailable(_) | Self::Timeout(_))
+    }
+}
+
+impl From<MilvusError> for ApiError {
+    fn from(err: MilvusError) -> Self {
+        if err.is_unavailable() {
+            ApiError::ServiceUnavailable(err.to_string())
+        } else {
+            ApiError::Upstream(err.to_string())
+        }
+    }
+}
+
+struct Inner {
+    url: &'static str,
+    /// Only written to swap a finished connection in or out, so requests on
+    /// a live connection never wait behind a reconnect
+    client: RwLock<Option<MilvusClient>>,
+    /// Serialises reconnects so a dead server isn't dialled by every caller
+    connecting: Mutex<()>,
+    /// The embeddings collection is initialised separately so a failure there
+    /// doesn't take phash search down with it
+    embeddings_ready: AtomicBool,
+}
+
+/// Owns the Milvus connection and rebuilds it when the server goes away, so
+/// a Milvus restart doesn't need a redeploy
+#[derive(Clone)]
+pub struct MilvusManager {
+    inner: Arc<Inner>,
+}
+
+impl MilvusManager {
+    /// Connects eagerly, but still returns a manager if Milvus is down so
+    /// later calls and the health check can reconnect
+    pub async fn connect(url: String) -> Self {
+        // Leaked once per process; the SDK wants a 'static endpoint
+        let url: &'static str = Box::leak(url.into_boxed_str());
+        let manager = Self {
+            inner: Arc::new(Inner {
+                url,
+                client: RwLock::new(None),
+                connecting: Mutex::new(()),
+                embeddings_ready: AtomicBool::new(false),
+            }),
+        };
+
+        if let Err(e) = manager.client().await {
+            log::error!("Initial Milvus connection failed, will retry: {}", e);
+        }
+        manager
+    }
+
+    async fn client(&self) -> Result<MilvusClient, MilvusError> {
+        if let Some(client) = self.inner.client.read().await.as_ref() {
+            return Ok(client.clone());
+        }
+
+        let _connecting = self.inner.connecting.lock().await;
+        // Another caller may have reconnected while we waited for the lock
+        if let Some(client) = self.inner.client.read().await.as_ref() {
+            return Ok(client.clone());
+        }
+
+        let client = tokio::time::timeout(CONNECT_TIMEOUT, async {
+            let client = create_milvus_client(self.inner.url).await?;
+            init_collection(&client).await?;
+            anyhow::Ok(client)
+        })
+        .await
+        .map_err(|_| MilvusError::Timeout(CONNECT_TIMEOUT))?
+        .map_err(|e| MilvusError::Unavailable(format!("{e:#}")))?;
+
+        log::info!("Milvus connection established");
+        self.inner.embeddings_ready.store(false, Ordering::Release);
+        *self.inner.client.write().await = Some(client.clone());
+        Ok(client)
+    }
+
+    /// The client for embedding requests, creating the embeddings collection
+    /// first if this connection hasn't yet
+    async fn embeddings_client(&self) -> Result<MilvusClient, MilvusError> {
+        let client = self.client().await?;
+        if self.inner.embeddings_ready.load(Ordering::Acquire) {
+            return Ok(client);
+        }
+
+        match tokio::time::timeout(CONNECT_TIMEOUT, init_embedding_collection(&client)).await {
+            Ok(Ok(())) => {
+                self.inner.embeddings_ready.store(true, Ordering::Release);
+                Ok(client)
+            }
+            Ok(Err(e)) => Err(MilvusError::Unavailable(format!("{e:#}"))),
+            Err(_) => Err(MilvusError::Timeout(CONNECT_TIMEOUT)),
+        }
+    }
+
+    async fn invalidate(&self) {
+        *self.inner.client.write().await = None;
+    }
+
+    /// Cheap round trip used to tell a dead connection from a failed request
+    pub async fn health_check(&self) -> Result<(), MilvusError> {
+        let client = self.client().await?;
+        match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, client.has_collection(COLLECTION_NAME))
+            .await
+        {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(MilvusError::Unavailable(e.to_string())),
+            Err(_) => Err(MilvusError::Timeout(HEALTH_CHECK_TIMEOUT)),
+        }
+    }
+
+    /// Periodically probes Milvus, dropping a dead connection and
+    /// reconnecting in the background
+    pub fn spawn_health_check(&self) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = manager.health_check().await {
+                    log::warn!("Milvus health check failed, reconnecting: {}", e);
+                    manager.invalidate().await;
+                }
+            }
+        });
+    }
+
+    async fn call<T, F, Fut>(&self, operation: F) -> Result<T, MilvusError>
+    where
+        F: Fn(MilvusClient) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        self.call_on(false, operation).await
+    }
+
+    async fn call_embeddings<T, F, Fut>(&self, operation: F) -> Result<T, MilvusError>
+    where
+        F: Fn(MilvusClient) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        self.call_on(true, operation).await
+    }
+
+    /// Runs `operation` with a timeout. When it fails and Milvus doesn't
+    /// answer a health check either, the connection is rebuilt and the
+    /// operation retried once.
+    async fn call_on<T, F, Fut>(&self, embeddings: bool, operation: F) -> Result<T, MilvusError>
+    where
+        F: Fn(MilvusClient) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let operation = &operation;
+        let attempt = |client: MilvusClient| async move {
+            match tokio::time::timeout(CALL_TIMEOUT, operation(client)).await {
+                Ok(Ok(value)) => Ok(value),
+                Ok(Err(e)) => Err(MilvusError::Request(format!("{e:#}"))),
+                Err(_) => Err(MilvusError::Timeout(CALL_TIMEOUT)),
+            }
+        };
+
+        let connected = || async {
+            if embeddings {
+                self.embeddings_client().await
+            } else {
+                self.client().await
+            }
+        };
+
+        let error = match attempt(connected().await?).await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+
+        if self.health_check().await.is_ok() {
+            return Err(error);
+        }
+
+        log::warn!("Milvus request failed on a dead connection, reconnecting: {error}");
+        self.invalidate().await;
+        attempt(connected().await?).await
+    }
+
+    pub async fn search_similar_videos(
+        &self,
+        phash: &str,
+        distance_threshold: u32,
+        top_k: i32,
+    ) -> Result<Vec<SearchResult>, MilvusError> {
+        self.call(|client| async move {
+            super::search_similar_videos(&client, phash, distance_threshold, top_k).await
+        })
+        .await
+    }
+
+    pub async fn search_similar_videos_topk2(
+        &self,
+        phash: &str,
+    ) -> Result<Vec<SearchResult>, MilvusError> {
+        self.call(|client| async move { super::search_similar_videos_topk2(&client, phash).await })
+            .await
+    }
+
+    pub async fn search_similar_videos_batch(
+        &self,
+        phashes: &[String],
+        top_k: i32,
+    ) -> Result<Vec<Vec<SearchResult>>, MilvusError> {
+        self.call(|client| async move {
+            super::search_similar_videos_batch(&client, phashes, top_k).await
+        })
+        .await
+    }
+
+    pub async fn insert_embeddings(
+        &self,
+        records: Vec<VideoEmbeddingRecord>,
+    ) -> Result<(), MilvusError> {
+        self.call_embeddings(|client| {
+            let records = records.clone();
+            async move { super::embeddings::insert_embeddings(&client, records).await }
+        })
+        .await
+    }
+
+    pub async fn search_by_embedding(
+        &self,
+        embedding: &[f32],
+        top_k: i32,
+    ) -> Result<Vec<EmbeddingMatch>, MilvusError> {
+        self.call_embeddings(|client| async move {
+            super::embeddings::search_by_embedding(&client, embedding, top_k).await
+        })
+        .await
+    }
+
+    pub async fn insert_video_hash(
+        &self,
+        video_id: &str,
+        phash: &str,
+        created_at: i64,
+    ) -> Result<(), MilvusError> {
+        self.call(|client| async move {
+            super::insert_video_hash(&client, video_id, phash, created_at).await
+        })
+        .await
+    }
+
+    pub async fn insert_batch_video_hashes(
+        &self,
+        records: Vec<VideoHashRecord>,
+    ) -> Result<(), MilvusError> {
+        self.call(|client| {
+            let records = records.clone();
+            async move { super::insert_batch_video_hashes(&client, records).await }
+        })
+        .await
+    }
+
+    pub async fn delete_video_hash(&self, video_id: &str) -> Result<(), MilvusError> {
+        self.call(|client| async move { super::delete_video_hash(&client, video_id).await })
+            .await
+    }
+
+    pub async fn delete_embeddings(&self, video_id: &str) -> Result<(), MilvusError> {
+        self.call_embeddings(|client| async move {
+            super::embeddings::delete_embeddings(&client, video_id).await
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unavailable_errors() {
+        assert!(MilvusError::Timeout(CALL_TIMEOUT).is_unavailable());
+        assert!(MilvusError::Unavailable("down".to_string()).is_unavailable());
+        assert!(!MilvusError::Request("bad vector".to_string()).is_unavailable());
+    }
+}
@@ -7,5 +7,7 @@ use utoipa_axum::routes;
 pub fn milvus_router(app_state: Arc<AppState>) -> OpenApiRouter {
     OpenApiRouter::new()
         .routes(routes!(api::check_duplicate_handler))
+        .routes(routes!(api::batch_search_handler))
+        .routes(routes!(api::similar_by_embedding_handler))
         .with_state(app_state)
 }
@@ -0,0 +1,250 @@
+//! Auto-moderation policy for reported videos.
+//!
+//! Once enough distinct users report a video within the policy window, the
+//! video is flagged hidden in kvrocks (feed services skip flagged videos),
+//! its owner is notified, and the case is pinned to the top of the triage
+//! queue until a moderator dismisses it or takes it down.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{extract::State, Json};
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    events::push_notifications::dispatch_notif,
+    kvrocks::{KvrocksClient, VideoHidden},
+    utils::cache::TwoTierCache,
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+use super::reports::{reporter_times_key, summary_key, ReportStatus, ReportedVideo, QUEUE_KEY};
+
+const POLICY_KEY: &str = "offchain:reports:auto_hide_policy";
+/// `VideoHidden::reason` of videos hidden by this policy
+const HIDDEN_REASON: &str = "auto_moderation_reports";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AutoHidePolicy {
+    pub enabled: bool,
+    /// Distinct reporters needed inside the window to hide a video
+    pub unique_reporters: u64,
+    pub window_secs: u64,
+}
+
+impl Default for AutoHidePolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            unique_reporters: 5,
+            window_secs: 60 * 60,
+        }
+    }
+}
+
+impl AutoHidePolicy {
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.unique_reporters < 2 {
+            return Err(ApiError::InvalidRequest(
+                "unique_reporters must be at least 2".to_string(),
+            ));
+        }
+        if self.window_secs == 0 {
+            return Err(ApiError::InvalidRequest(
+                "window_secs must be positive".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn should_hide(&self, reporters_in_window: u64) -> bool {
+        self.enabled && reporters_in_window >= self.unique_reporters
+    }
+}
+
+/// Read on every report; edits invalidate it on all instances
+static POLICY_CACHE: Lazy<TwoTierCache<AutoHidePolicy>> =
+    Lazy::new(|| TwoTierCache::new("auto_hide_policy", 1, Duration::from_secs(300)));
+
+async fn load_policy(pool: &Arc<DragonflyPool>) -> anyhow::Result<AutoHidePolicy> {
+    POLICY_CACHE
+        .get_or_load(POLICY_KEY, || async {
+            let mut conn = pool.get().await?;
+            let payload: Option<String> = conn.get(POLICY_KEY).await?;
+            Ok(match payload {
+                Some(payload) => serde_json::from_str(&payload)?,
+                None => AutoHidePolicy::default(),
+            })
+        })
+        .await
+}
+
+/// Hides `video` if the policy threshold is crossed. Already-hidden cases
+/// are left alone so owners are notified once.
+pub async fn evaluate(state: &AppState, video: &ReportedVideo) -> anyhow::Result<()> {
+    if video.status == ReportStatus::AutoHidden {
+        return Ok(());
+    }
+
+    let pool = &state.yral_redis_store_dragonfly;
+    let policy = load_policy(pool).await?;
+    if !policy.enabled {
+        return Ok(());
+    }
+
+    let window_start = chrono::Utc::now().timestamp() - policy.window_secs as i64;
+    let mut conn = pool.get().await?;
+    let reporters_in_window: u64 = conn
+        .zcount(reporter_times_key(&video.video_id), window_start, "+inf")
+        .await?;
+
+    if !policy.should_hide(reporters_in_window) {
+        return Ok(());
+    }
+
+    state
+        .kvrocks_client
+        .hide_video(&VideoHidden {
+            video_id: video.video_id.clone(),
+            reason: HIDDEN_REASON.to_string(),
+            unique_reporters: reporters_in_window,
+            hidden_at: chrono::Utc::now().to_rfc3339(),
+        })
+        .await?;
+
+    let hidden = ReportedVideo {
+        status: ReportStatus::AutoHidden,
+        ..video.clone()
+    };
+    let _: () = redis::pipe()
+        .hset(summary_key(&video.video_id), "status", "auto_hidden")
+        .zadd(QUEUE_KEY, &video.video_id, hidden.queue_score())
+        .query_async(&mut conn)
+        .await?;
+
+    log::warn!(
+        "Auto-hid video {} after {} unique reports in {}s",
+        video.video_id,
+        reporters_in_window,
+        policy.window_secs
+    );
+
+    let params = json!({
+        "video_id": video.video_id,
+        "post_id": video.post_id,
+        "canister_id": video.canister_id,
+        "user_id": video.publisher_id,
+    });
+    if let Err(e) = dispatch_notif("video_hidden_pending_review", params, state).await {
+        log::error!(
+            "Failed to notify owner of auto-hidden video {}: {:?}",
+            video.video_id,
+            e
+        );
+    }
+
+    Ok(())
+}
+
+/// Lifts this policy's hide; a video hidden for another reason, such as a
+/// pending scheduled publish, stays hidden
+pub async fn unhide(kvrocks_client: &KvrocksClient, video_id: &str) -> anyhow::Result<()> {
+    kvrocks_client.unhide_video(video_id, HIDDEN_REASON).await
+}
+
+/// Current auto-hide policy
+#[utoipa::path(
+    get,
+    path = "/reports/policy",
+    tag = "moderation",
+    responses(
+        (status = 200, description = "Auto-hide policy", body = AutoHidePolicy),
+        (status = 401, description = "Unauthorized - invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Forbidden - not a moderator", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("moderator_identity" = [])
+    )
+)]
+#[instrument(skip(state))]
+pub async fn get_policy(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AutoHidePolicy>, ApiError> {
+    Ok(Json(load_policy(&state.yral_redis_store_dragonfly).await?))
+}
+
+/// The body also carries `delegated_identity_wire`, which the moderator
+/// middleware verifies before the handler runs
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdatePolicyRequest {
+    pub policy: AutoHidePolicy,
+}
+
+/// Replace the auto-hide policy; applies to the next report received
+#[utoipa::path(
+    put,
+    path = "/reports/policy",
+    request_body = UpdatePolicyRequest,
+    tag = "moderation",
+    responses(
+        (status = 200, description = "Updated policy", body = AutoHidePolicy),
+        (status = 400, description = "Invalid policy", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized - invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Forbidden - not a moderator", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("moderator_identity" = [])
+    )
+)]
+#[instrument(skip(state))]
+pub async fn update_policy(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<UpdatePolicyRequest>,
+) -> Result<Json<AutoHidePolicy>, ApiError> {
+    request.policy.validate()?;
+
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+    let _: () = conn
+        .set(
+            POLICY_KEY,
+            serde_json::to_string(&request.policy)
+                .map_err(|e| ApiError::Internal(e.to_string()))?,
+        )
+        .await?;
+    POLICY_CACHE.invalidate(POLICY_KEY).await;
+    log::warn!("Auto-hide policy updated: {:?}", request.policy);
+
+    Ok(Json(request.policy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_hide() {
+        let policy = AutoHidePolicy::default();
+        assert!(!policy.should_hide(4));
+        assert!(policy.should_hide(5));
+
+        let disabled = AutoHidePolicy {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(!disabled.should_hide(100));
+        assert!(AutoHidePolicy {
+            unique_reporters: 1,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,260 @@
+//! Claims that lock a pending video to one moderator.
+//!
+//! A claim is a Dragonfly key (`offchain:moderation:claim:{video_id}`) with a
+//! TTL, so it lapses on its own if the moderator walks away. Claiming again
+//! as the same moderator extends it. Approve and disapprove require the
+//! caller to hold the claim: the check also extends it to cover the
+//! decision, and a decision releases it. Every read-and-write of a claim is
+//! one Lua script, so two moderators can't both end up holding a video.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use candid::Principal;
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use super::{Moderator, PendingVideo};
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    types::DelegatedIdentityWire,
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const DEFAULT_CLAIM_MINUTES: u32 = 10;
+const MAX_CLAIM_MINUTES: u32 = 60;
+/// How long a claim is kept at least once its holder starts a decision
+const DECISION_SECS: u64 = 120;
+
+/// Sets the claim unless another moderator holds it. Returns the other
+/// moderator's claim, or nothing once ours is set.
+static CLAIM_SCRIPT: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+        local current = redis.call('GET', KEYS[1])
+        if current and cjson.decode(current).moderator ~= ARGV[1] then
+            return current
+        end
+        redis.call('SET', KEYS[1], ARGV[2], 'EX', tonumber(ARGV[3]))
+        return false
+        "#,
+    )
+});
+
+/// Keeps the caller's claim for at least the decision window. Returns
+/// `unclaimed`, `held`, or the other moderator's claim.
+static DECIDE_SCRIPT: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+        local current = redis.call('GET', KEYS[1])
+        if not current then return 'unclaimed' end
+        if cjson.decode(current).moderator ~= ARGV[1] then return current end
+        if redis.call('TTL', KEYS[1]) < tonumber(ARGV[2]) then
+            redis.call('EXPIRE', KEYS[1], tonumber(ARGV[2]))
+        end
+        return 'held'
+        "#,
+    )
+});
+
+/// Deletes the claim only if the caller still holds it
+static RELEASE_SCRIPT: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+        local current = redis.call('GET', KEYS[1])
+        if current and cjson.decode(current).moderator == ARGV[1] then
+            return redis.call('DEL', KEYS[1])
+        end
+        return 0
+        "#,
+    )
+});
+
+fn claim_key(video_id: &str) -> String {
+    format!("offchain:moderation:claim:{video_id}")
+}
+
+fn claim_ttl_secs(minutes: Option<u32>) -> u64 {
+    u64::from(
+        minutes
+            .unwrap_or(DEFAULT_CLAIM_MINUTES)
+            .clamp(1, MAX_CLAIM_MINUTES),
+    ) * 60
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+pub struct VideoClaim {
+    /// Principal of the moderator holding the claim
+    pub moderator: String,
+    pub claimed_at: i64,
+    pub expires_at: i64,
+}
+
+impl VideoClaim {
+    fn new(moderator: Principal, ttl_secs: u64) -> Self {
+        let claimed_at = chrono::Utc::now().timestamp();
+        Self {
+            moderator: moderator.to_text(),
+            claimed_at,
+            expires_at: claimed_at + ttl_secs as i64,
+        }
+    }
+
+    fn conflict(&self, video_id: &str) -> ApiError {
+        ApiError::Conflict(format!(
+            "Video {video_id} is claimed by {} until {}",
+            self.moderator, self.expires_at
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
+pub struct ClaimRequest {
+    pub delegated_identity_wire: DelegatedIdentityWire,
+    /// How long to hold the claim (default 10, max 60)
+    pub minutes: Option<u32>,
+}
+
+/// Fills in the current claim of each pending video
+pub async fn with_claims(
+    pool: &DragonflyPool,
+    mut videos: Vec<PendingVideo>,
+) -> Result<Vec<PendingVideo>, ApiError> {
+    if videos.is_empty() {
+        return Ok(videos);
+    }
+    let keys: Vec<String> = videos
+        .iter()
+        .map(|video| claim_key(&video.video_id))
+        .collect();
+    let mut conn = pool.get().await?;
+    let values: Vec<Option<String>> = conn.mget(keys).await?;
+
+    for (video, value) in videos.iter_mut().zip(values) {
+        video.claim = value.and_then(|value| serde_json::from_str(&value).ok());
+    }
+    Ok(videos)
+}
+
+fn other_claim(video_id: &str, value: &str) -> ApiError {
+    match serde_json::from_str::<VideoClaim>(value) {
+        Ok(claim) => claim.conflict(video_id),
+        Err(_) => ApiError::Conflict(format!("Video {video_id} is claimed by another moderator")),
+    }
+}
+
+/// Fails unless `moderator` holds the claim, and keeps it held long enough
+/// for the decision to go through
+pub async fn ensure_claimer(
+    pool: &DragonflyPool,
+    video_id: &str,
+    moderator: Principal,
+) -> Result<(), ApiError> {
+    let mut conn = pool.get().await?;
+    let result: String = DECIDE_SCRIPT
+        .key(claim_key(video_id))
+        .arg(moderator.to_text())
+        .arg(DECISION_SECS)
+        .invoke_async(&mut conn)
+        .await?;
+    match result.as_str() {
+        "held" => Ok(()),
+        "unclaimed" => Err(ApiError::Conflict(format!(
+            "Claim video {video_id} before deciding it"
+        ))),
+        other => Err(other_claim(video_id, other)),
+    }
+}
+
+/// Drops `moderator`'s claim once the video has been decided
+pub async fn release(pool: &DragonflyPool, video_id: &str, moderator: Principal) {
+    let result: anyhow::Result<()> = async {
+        let mut conn = pool.get().await?;
+        let _: i64 = RELEASE_SCRIPT
+            .key(claim_key(video_id))
+            .arg(moderator.to_text())
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        log::warn!("Failed to release moderation claim on {video_id}: {e}");
+    }
+}
+
+/// Lock a pending video to the calling moderator
+#[utoipa::path(
+    post,
+    path = "/claim/{video_id}",
+    request_body = ClaimRequest,
+    params(
+        ("video_id" = String, Path, description = "The video ID to claim")
+    ),
+    tag = "moderation",
+    responses(
+        (status = 200, description = "Claim taken or extended", body = VideoClaim),
+        (status = 401, description = "Unauthorized - invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Forbidden - not a moderator", body = ApiErrorBody),
+        (status = 409, description = "Claimed by another moderator", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("moderator_identity" = [])
+    )
+)]
+#[instrument(skip(state, request))]
+pub async fn claim_video(
+    Path(video_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(Moderator(moderator)): Extension<Moderator>,
+    Json(request): Json<ClaimRequest>,
+) -> Result<Json<VideoClaim>, ApiError> {
+    let pool = &state.yral_redis_store_dragonfly;
+    let ttl_secs = claim_ttl_secs(request.minutes);
+    let claim = VideoClaim::new(moderator, ttl_secs);
+    let value = serde_json::to_string(&claim).map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    let mut conn = pool.get().await?;
+    let other: Option<String> = CLAIM_SCRIPT
+        .key(claim_key(&video_id))
+        .arg(moderator.to_text())
+        .arg(&value)
+        .arg(ttl_secs)
+        .invoke_async(&mut conn)
+        .await?;
+    if let Some(other) = other {
+        return Err(other_claim(&video_id, &other));
+    }
+
+    log::info!("Moderator {moderator} claimed video {video_id} for {ttl_secs}s");
+    Ok(Json(claim))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim_ttl_and_conflict() {
+        assert_eq!(claim_ttl_secs(None), 600);
+        assert_eq!(claim_ttl_secs(Some(0)), 60);
+        assert_eq!(claim_ttl_secs(Some(500)), 3600);
+
+        let moderator = Principal::anonymous();
+        let claim = VideoClaim::new(moderator, 600);
+        assert_eq!(claim.expires_at - claim.claimed_at, 600);
+        assert_eq!(claim.moderator, moderator.to_text());
+        assert!(matches!(
+            other_claim("v1", &serde_json::to_string(&claim).unwrap()),
+            ApiError::Conflict(message) if message.contains(&claim.moderator)
+        ));
+    }
+}
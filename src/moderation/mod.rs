@@ -1,3 +1,11 @@
+#[cfg(not(feature = "local-bin"))]
+pub mod auto_hide;
+#[cfg(not(feature = "local-bin"))]
+pub mod claims;
+pub mod pending_queue;
+#[cfg(not(feature = "local-bin"))]
+pub mod reports;
+
 use std::sync::Arc;
 
 use axum::{
@@ -5,7 +13,7 @@ use axum::{
     http::StatusCode,
     middleware::Next,
     response::{IntoResponse, Response},
-    Json,
+    Extension, Json,
 };
 use candid::Principal;
 use google_cloud_bigquery::http::job::query::QueryRequest;
@@ -15,11 +23,19 @@ use tracing::instrument;
 use utoipa::ToSchema;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
-use crate::kvrocks::KvrocksClient;
+use crate::kvrocks::{tables, KvrocksClient};
 use crate::{
-    app_state::AppState, consts::MODERATOR_PRINCIPALS, events::push_notifications::dispatch_notif,
+    app_state::AppState,
+    consts::MODERATOR_PRINCIPALS,
+    error::{ApiError, ApiErrorBody},
+    events::push_notifications::dispatch_notif,
+    middleware::http_metrics::RequestTimings,
     types::DelegatedIdentityWire,
-    utils::delegated_identity::get_user_info_from_delegated_identity_wire, AppError,
+    utils::delegated_identity::{
+        delegated_identity_wire_from_headers, verify_delegated_identity_request,
+        DELEGATED_IDENTITY_HEADER,
+    },
+    webhook_subscriptions::{self, WebhookEvent},
 };
 
 #[derive(Serialize, Deserialize, ToSchema, Debug, Clone)]
@@ -40,6 +56,10 @@ pub struct PendingVideo {
     pub canister_id: Option<String>,
     pub user_id: Option<String>,
     pub created_at: Option<String>,
+    /// Moderator currently holding the video, if any
+    #[cfg(not(feature = "local-bin"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claim: Option<claims::VideoClaim>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema, Debug)]
@@ -63,6 +83,11 @@ pub struct PendingVideosRequest {
     pub query: PendingVideosQuery,
 }
 
+/// Principal of the verified moderator, added to request extensions by
+/// [`verify_moderator`]
+#[derive(Debug, Clone, Copy)]
+pub struct Moderator(pub Principal);
+
 /// Check if a principal is a whitelisted moderator
 fn is_moderator(principal: &Principal) -> bool {
     MODERATOR_PRINCIPALS.contains(principal)
@@ -73,31 +98,48 @@ pub async fn verify_moderator(
     State(state): State<Arc<AppState>>,
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, ApiError> {
     let (parts, body) = request.into_parts();
     let bytes = match axum::body::to_bytes(body, usize::MAX).await {
         Ok(bytes) => bytes,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
-
-    let moderation_request: ModerationRequest = match serde_json::from_slice(&bytes) {
-        Ok(req) => req,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+        Err(_) => {
+            return Err(ApiError::InvalidRequest(
+                "Failed to read request body".to_string(),
+            ))
+        }
     };
 
-    let user_info = get_user_info_from_delegated_identity_wire(
+    // Requests without a body, such as `GET`s, carry the wire in the shared
+    // delegated identity header
+    let moderation_request: ModerationRequest =
+        if parts.headers.contains_key(DELEGATED_IDENTITY_HEADER) {
+            delegated_identity_wire_from_headers(&parts.headers)
+                .map(|delegated_identity_wire| ModerationRequest {
+                    delegated_identity_wire,
+                })
+                .map_err(|e| ApiError::InvalidRequest(e.to_string()))?
+        } else {
+            serde_json::from_slice(&bytes)
+                .map_err(|e| ApiError::InvalidRequest(format!("Invalid request body: {e}")))?
+        };
+
+    let verify = verify_delegated_identity_request(
         &state,
         moderation_request.delegated_identity_wire,
-    )
-    .await
-    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        &parts.headers,
+    );
+    let user_info = match RequestTimings::from_extensions(&parts.extensions) {
+        Some(timings) => timings.time("auth", verify).await,
+        None => verify.await,
+    }
+    .map_err(|e| ApiError::Unauthorized(format!("Invalid delegated identity: {e}")))?;
 
     if !is_moderator(&user_info.user_principal) {
         log::warn!(
             "Unauthorized moderation attempt by principal: {}",
             user_info.user_principal
         );
-        return Err(StatusCode::FORBIDDEN);
+        return Err(ApiError::Forbidden("Not a moderator".to_string()));
     }
 
     log::info!(
@@ -105,7 +147,10 @@ pub async fn verify_moderator(
         user_info.user_principal
     );
 
-    let request = Request::from_parts(parts, axum::body::Body::from(bytes));
+    let mut request = Request::from_parts(parts, axum::body::Body::from(bytes));
+    request
+        .extensions_mut()
+        .insert(Moderator(user_info.user_principal));
     Ok(next.run(request).await)
 }
 
@@ -113,10 +158,19 @@ pub async fn verify_moderator(
 pub fn moderation_router(state: Arc<AppState>) -> OpenApiRouter {
     use axum::middleware;
 
-    OpenApiRouter::new()
+    let router = OpenApiRouter::new()
         .routes(routes!(get_pending_videos))
         .routes(routes!(approve_video))
-        .routes(routes!(disapprove_video))
+        .routes(routes!(disapprove_video));
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router
+        .routes(routes!(claims::claim_video))
+        .routes(routes!(reports::list_reports))
+        .routes(routes!(reports::act_on_report))
+        .routes(routes!(auto_hide::get_policy, auto_hide::update_policy));
+
+    router
         .layer(middleware::from_fn_with_state(
             state.clone(),
             verify_moderator,
@@ -132,21 +186,34 @@ pub fn moderation_router(state: Arc<AppState>) -> OpenApiRouter {
     tag = "moderation",
     responses(
         (status = 200, description = "List of pending videos", body = PendingVideosResponse),
-        (status = 401, description = "Unauthorized - invalid delegated identity"),
-        (status = 403, description = "Forbidden - not a moderator"),
-        (status = 500, description = "Internal server error"),
+        (status = 401, description = "Unauthorized - invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Forbidden - not a moderator", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("moderator_identity" = [])
     )
 )]
 #[instrument(skip(state))]
 pub async fn get_pending_videos(
     State(state): State<Arc<AppState>>,
     Json(request): Json<PendingVideosRequest>,
-) -> Result<impl IntoResponse, AppError> {
+) -> Result<impl IntoResponse, ApiError> {
     let limit = request.query.limit.unwrap_or(100);
     let offset = request.query.offset.unwrap_or(0);
 
-    let videos = fetch_pending_videos(&state.bigquery_client, limit, offset).await?;
-    let total_count = videos.len();
+    // Until the first reconciliation has filled the queue, BigQuery is the
+    // only complete view of pending videos
+    let (videos, total_count) = if pending_queue::is_reconciled(&state.kvrocks_client).await? {
+        pending_queue::fetch_page(&state.kvrocks_client, limit as usize, offset as usize).await?
+    } else {
+        let videos = fetch_pending_videos(&state.bigquery_client, limit, offset).await?;
+        let total_count = videos.len();
+        (videos, total_count)
+    };
+
+    #[cfg(not(feature = "local-bin"))]
+    let videos = claims::with_claims(&state.yral_redis_store_dragonfly, videos).await?;
 
     Ok((
         StatusCode::OK,
@@ -168,28 +235,56 @@ pub async fn get_pending_videos(
     tag = "moderation",
     responses(
         (status = 200, description = "Video approved successfully", body = ModerationResponse),
-        (status = 401, description = "Unauthorized - invalid delegated identity"),
-        (status = 403, description = "Forbidden - not a moderator"),
-        (status = 404, description = "Video not found"),
-        (status = 500, description = "Internal server error"),
+        (status = 401, description = "Unauthorized - invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Forbidden - not a moderator", body = ApiErrorBody),
+        (status = 404, description = "Video not found", body = ApiErrorBody),
+        (status = 409, description = "Not claimed by the caller, or auto-blocked as a duplicate", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("moderator_identity" = [])
     )
 )]
 #[instrument(skip(state))]
 pub async fn approve_video(
     Path(video_id): Path<String>,
     State(state): State<Arc<AppState>>,
+    Extension(Moderator(moderator)): Extension<Moderator>,
     Json(_request): Json<ModerationRequest>,
-) -> Result<impl IntoResponse, AppError> {
+) -> Result<impl IntoResponse, ApiError> {
+    #[cfg(not(feature = "local-bin"))]
+    claims::ensure_claimer(&state.yral_redis_store_dragonfly, &video_id, moderator).await?;
+
+    if pending_queue::is_auto_blocked(&state.kvrocks_client, &video_id).await? {
+        return Err(ApiError::Conflict(format!(
+            "Video {video_id} was auto-blocked as a duplicate"
+        )));
+    }
+
     // First fetch the video info before updating
     let video_info = fetch_video_info(&state.bigquery_client, &video_id).await?;
 
     let updated =
         update_approval_status(&state.bigquery_client, &state.kvrocks_client, &video_id).await?;
     if updated {
+        #[cfg(not(feature = "local-bin"))]
+        claims::release(&state.yral_redis_store_dragonfly, &video_id, moderator).await;
+        log::info!("Moderator {moderator} approved video {video_id}");
+
         // Send notification to the video owner via event pipeline
-        if let Some(info) = video_info {
-            send_approval_notification(&state, &info, true).await;
+        if let Some(info) = &video_info {
+            send_approval_notification(&state, info, true).await;
         }
+        webhook_subscriptions::dispatch(
+            &state,
+            WebhookEvent::VideoApproved,
+            json!({
+                "video_id": video_id,
+                "post_id": video_info.as_ref().and_then(|info| info.post_id.clone()),
+                "publisher_user_id": video_info.as_ref().and_then(|info| info.user_id.clone()),
+            }),
+        )
+        .await;
 
         Ok((
             StatusCode::OK,
@@ -199,13 +294,7 @@ pub async fn approve_video(
             }),
         ))
     } else {
-        Ok((
-            StatusCode::NOT_FOUND,
-            Json(ModerationResponse {
-                success: false,
-                message: format!("Video {} not found", video_id),
-            }),
-        ))
+        Err(ApiError::NotFound(format!("Video {} not found", video_id)))
     }
 }
 
@@ -220,23 +309,42 @@ pub async fn approve_video(
     tag = "moderation",
     responses(
         (status = 200, description = "Video disapproved successfully", body = ModerationResponse),
-        (status = 401, description = "Unauthorized - invalid delegated identity"),
-        (status = 403, description = "Forbidden - not a moderator"),
-        (status = 404, description = "Video not found"),
-        (status = 500, description = "Internal server error"),
+        (status = 401, description = "Unauthorized - invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Forbidden - not a moderator", body = ApiErrorBody),
+        (status = 404, description = "Video not found", body = ApiErrorBody),
+        (status = 409, description = "Not claimed by the caller", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("moderator_identity" = [])
     )
 )]
 #[instrument(skip(state))]
 pub async fn disapprove_video(
     Path(video_id): Path<String>,
     State(state): State<Arc<AppState>>,
+    Extension(Moderator(moderator)): Extension<Moderator>,
     Json(_request): Json<ModerationRequest>,
-) -> Result<impl IntoResponse, AppError> {
+) -> Result<impl IntoResponse, ApiError> {
+    #[cfg(not(feature = "local-bin"))]
+    claims::ensure_claimer(&state.yral_redis_store_dragonfly, &video_id, moderator).await?;
+
     // First fetch the video info before deleting
     let video_info = fetch_video_info(&state.bigquery_client, &video_id).await?;
 
     let deleted = delete_video(&state.bigquery_client, &state.kvrocks_client, &video_id).await?;
     if deleted {
+        #[cfg(not(feature = "local-bin"))]
+        claims::release(&state.yral_redis_store_dragonfly, &video_id, moderator).await;
+        log::info!("Moderator {moderator} disapproved video {video_id}");
+        #[cfg(not(feature = "local-bin"))]
+        crate::posts::feed_cache_invalidation::enqueue_invalidation(
+            &state,
+            &video_id,
+            crate::posts::feed_cache_invalidation::InvalidationReason::Disapproved,
+        )
+        .await;
+
         // Send notification to the video owner via event pipeline
         if let Some(info) = video_info {
             send_approval_notification(&state, &info, false).await;
@@ -250,18 +358,12 @@ pub async fn disapprove_video(
             }),
         ))
     } else {
-        Ok((
-            StatusCode::NOT_FOUND,
-            Json(ModerationResponse {
-                success: false,
-                message: format!("Video {} not found", video_id),
-            }),
-        ))
+        Err(ApiError::NotFound(format!("Video {} not found", video_id)))
     }
 }
 
 #[instrument(skip(bigquery_client))]
-async fn fetch_pending_videos(
+pub(crate) async fn fetch_pending_videos(
     bigquery_client: &google_cloud_bigquery::client::Client,
     limit: u32,
     offset: u32,
@@ -319,6 +421,8 @@ async fn fetch_pending_videos(
                 canister_id,
                 user_id,
                 created_at,
+                #[cfg(not(feature = "local-bin"))]
+                claim: None,
             });
         }
     }
@@ -418,13 +522,17 @@ pub async fn delete_video(
 ) -> Result<bool, anyhow::Error> {
     // First delete from kvrocks (fast, synchronous)
     if let Err(e) = kvrocks_client
-        .delete_user_uploaded_content_approval(video_id)
+        .remove(&tables::USER_UPLOADED_CONTENT_APPROVAL, video_id)
         .await
     {
         log::error!("Error deleting approval from kvrocks: {}", e);
         return Err(anyhow::anyhow!("Failed to delete from kvrocks: {}", e));
     }
 
+    if let Err(e) = kvrocks_client.dequeue_pending_approval(video_id).await {
+        log::error!("Error removing {video_id} from the pending approval queue: {e}");
+    }
+
     log::info!("Deleted approval from kvrocks for video {}", video_id);
 
     // Then delete from BigQuery in the background
@@ -0,0 +1,248 @@
+//! Pending-approval queue in kvrocks.
+//!
+//! The moderation dashboard used to page through pending videos with a
+//! BigQuery scan per request. Video ids are now kept in a kvrocks sorted set
+//! scored by upload time: ingest enqueues, approve/disapprove dequeues, and
+//! a page is a `ZREVRANGE` plus `ZCARD` for the total.
+//!
+//! BigQuery stays the source of truth. The `/qstash/moderation/reconcile_pending_queue`
+//! job adds videos the queue missed and drops ids that are no longer pending.
+//! Until it has run once the endpoint keeps reading from BigQuery.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use axum::extract::State;
+use redis::AsyncCommands;
+use tracing::instrument;
+
+use super::{fetch_pending_videos, PendingVideo};
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    kvrocks::{keys, tables, KvrocksClient, UserUploadedContentApproval},
+};
+
+/// Upper bound on pending rows pulled from BigQuery per reconciliation
+const RECONCILE_LIMIT: u32 = 100_000;
+/// Queued ids younger than this are kept even when BigQuery doesn't have
+/// them yet, since ingest writes to kvrocks before the BigQuery insert lands
+const REMOVAL_GRACE_MS: i64 = 60 * 60 * 1000;
+
+pub async fn is_reconciled(kvrocks_client: &KvrocksClient) -> Result<bool> {
+    kvrocks_client
+        .exists(keys::PENDING_APPROVAL_RECONCILED_AT)
+        .await
+}
+
+/// Whether dedup blocked the upload as a duplicate. Such videos are never
+/// up for moderation.
+pub async fn is_auto_blocked(kvrocks_client: &KvrocksClient, video_id: &str) -> Result<bool> {
+    Ok(kvrocks_client
+        .get(&tables::VIDEO_DEDUP_STATUS, video_id)
+        .await?
+        .and_then(|status| status.auto_blocked)
+        .unwrap_or(false))
+}
+
+/// One page of pending videos, newest first, with the queue size.
+/// Ids whose approval row is gone, already approved or auto-blocked as a
+/// duplicate are dropped from the queue on the way.
+pub async fn fetch_page(
+    kvrocks_client: &KvrocksClient,
+    limit: usize,
+    offset: usize,
+) -> Result<(Vec<PendingVideo>, usize)> {
+    let (total, ids) = kvrocks_client.pending_approval_page(offset, limit).await?;
+
+    let rows = futures::future::try_join_all(
+        ids.iter()
+            .map(|id| kvrocks_client.get(&tables::USER_UPLOADED_CONTENT_APPROVAL, id)),
+    )
+    .await?;
+    let blocked =
+        futures::future::try_join_all(ids.iter().map(|id| is_auto_blocked(kvrocks_client, id)))
+            .await?;
+
+    let mut videos = Vec::with_capacity(ids.len());
+    let mut stale = 0;
+    for ((video_id, row), blocked) in ids.into_iter().zip(rows).zip(blocked) {
+        match row {
+            Some(row) if !row.is_approved && !blocked => videos.push(PendingVideo {
+                video_id,
+                post_id: Some(row.post_id),
+                canister_id: Some(row.canister_id),
+                user_id: Some(row.user_id),
+                created_at: Some(row.created_at),
+                #[cfg(not(feature = "local-bin"))]
+                claim: None,
+            }),
+            _ => {
+                stale += 1;
+                if let Err(e) = kvrocks_client.dequeue_pending_approval(&video_id).await {
+                    log::warn!("Failed to drop stale pending video {video_id}: {e}");
+                }
+            }
+        }
+    }
+
+    Ok((videos, (total as usize).saturating_sub(stale)))
+}
+
+/// Ids to enqueue (with their score) and ids to drop, given the pending set
+/// in BigQuery and the queue's current members
+fn plan_reconcile(
+    pending: &[(String, i64)],
+    queued: &[(String, i64)],
+    now_ms: i64,
+) -> (Vec<(String, i64)>, Vec<String>) {
+    let queued_ids: HashSet<&str> = queued.iter().map(|(id, _)| id.as_str()).collect();
+    let pending_ids: HashMap<&str, i64> = pending
+        .iter()
+        .map(|(id, score)| (id.as_str(), *score))
+        .collect();
+
+    let to_add = pending
+        .iter()
+        .filter(|(id, _)| !queued_ids.contains(id.as_str()))
+        .cloned()
+        .collect();
+    let to_remove = queued
+        .iter()
+        .filter(|(id, score)| {
+            !pending_ids.contains_key(id.as_str()) && now_ms - score > REMOVAL_GRACE_MS
+        })
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    (to_add, to_remove)
+}
+
+/// QStash scheduled job: reconciles the pending-approval queue with
+/// `ugc_content_approval` in BigQuery
+#[utoipa::path(
+    post,
+    path = "/moderation/reconcile_pending_queue",
+    responses(
+        (status = 200, description = "Queue reconciled"),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state))]
+pub async fn reconcile_pending_queue_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<(), ApiError> {
+    let kvrocks_client = &state.kvrocks_client;
+
+    let pending_videos = fetch_pending_videos(&state.bigquery_client, RECONCILE_LIMIT, 0).await?;
+    if pending_videos.len() as u32 >= RECONCILE_LIMIT {
+        log::warn!("Pending videos reached the reconcile limit of {RECONCILE_LIMIT}");
+    }
+    let rows: HashMap<String, UserUploadedContentApproval> = pending_videos
+        .into_iter()
+        .map(|video| {
+            let row = UserUploadedContentApproval {
+                video_id: video.video_id.clone(),
+                post_id: video.post_id.unwrap_or_default(),
+                canister_id: video.canister_id.unwrap_or_default(),
+                user_id: video.user_id.unwrap_or_default(),
+                is_approved: false,
+                created_at: video.created_at.unwrap_or_default(),
+            };
+            (video.video_id, row)
+        })
+        .collect();
+    let pending: Vec<(String, i64)> = rows
+        .iter()
+        .map(|(id, row)| (id.clone(), row.created_at_millis()))
+        .collect();
+
+    let queued: Vec<(String, f64)> = {
+        let mut conn = kvrocks_client.get_connection().await?;
+        conn.zrange_withscores(keys::PENDING_APPROVAL_QUEUE, 0, -1)
+            .await
+            .map_err(anyhow::Error::from)?
+    };
+    let queued: Vec<(String, i64)> = queued
+        .into_iter()
+        .map(|(id, score)| (id, score as i64))
+        .collect();
+
+    let (to_add, to_remove) =
+        plan_reconcile(&pending, &queued, chrono::Utc::now().timestamp_millis());
+
+    for (video_id, created_at_ms) in &to_add {
+        // Pages are hydrated from the approval rows, so backfill any the
+        // ingest path never wrote
+        let existing = kvrocks_client
+            .get(&tables::USER_UPLOADED_CONTENT_APPROVAL, video_id)
+            .await?;
+        if existing.is_none() {
+            kvrocks_client
+                .put(
+                    &tables::USER_UPLOADED_CONTENT_APPROVAL,
+                    video_id,
+                    &rows[video_id],
+                )
+                .await?;
+        }
+        kvrocks_client
+            .enqueue_pending_approval(video_id, *created_at_ms)
+            .await?;
+    }
+    for video_id in &to_remove {
+        kvrocks_client.dequeue_pending_approval(video_id).await?;
+    }
+
+    {
+        let mut conn = kvrocks_client.get_connection().await?;
+        conn.set::<_, _, ()>(
+            keys::PENDING_APPROVAL_RECONCILED_AT,
+            chrono::Utc::now().timestamp(),
+        )
+        .await
+        .map_err(anyhow::Error::from)?;
+    }
+
+    log::info!(
+        "Reconciled pending approval queue: {} pending, {} added, {} removed",
+        pending.len(),
+        to_add.len(),
+        to_remove.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_reconcile() {
+        let now = 10 * REMOVAL_GRACE_MS;
+        let pending = vec![("a".to_string(), 1), ("b".to_string(), 2)];
+        let queued = vec![
+            ("b".to_string(), 2),
+            ("approved".to_string(), 3),
+            ("just_ingested".to_string(), now - 1000),
+        ];
+
+        let (to_add, to_remove) = plan_reconcile(&pending, &queued, now);
+        assert_eq!(to_add, vec![("a".to_string(), 1)]);
+        assert_eq!(to_remove, vec!["approved".to_string()]);
+
+        let row = UserUploadedContentApproval {
+            video_id: "a".to_string(),
+            post_id: String::new(),
+            canister_id: String::new(),
+            user_id: String::new(),
+            is_approved: false,
+            created_at: "2025-01-02 03:04:05.123456+00".to_string(),
+        };
+        assert_eq!(row.created_at_millis(), 1_735_787_045_123);
+    }
+}
@@ -0,0 +1,431 @@
+//! Triage queue for user reports.
+//!
+//! Every report is folded into a per-video aggregate (report count, unique
+//! reporters, reason histogram) and the video is (re)scored into a severity
+//! ZSET that moderators work through. Acting on a video records the outcome
+//! and takes it out of the queue; a later report reopens it.
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    posts::{
+        delete_post::insert_video_delete_row_to_bigquery_v2, report_post::ReportPostRequestV3,
+    },
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+use super::{ModerationResponse, Moderator};
+
+pub(super) const QUEUE_KEY: &str = "offchain:reports:queue";
+pub(super) const ESCALATED_BOOST: f64 = 100.0;
+const MAX_PAGE_SIZE: usize = 200;
+
+pub(super) fn summary_key(video_id: &str) -> String {
+    format!("offchain:reports:video:{video_id}")
+}
+
+fn reporters_key(video_id: &str) -> String {
+    format!("offchain:reports:reporters:{video_id}")
+}
+
+fn reasons_key(video_id: &str) -> String {
+    format!("offchain:reports:reasons:{video_id}")
+}
+
+/// ZSET of reporter -> time of their latest report, for windowed counts
+pub(super) fn reporter_times_key(video_id: &str) -> String {
+    format!("offchain:reports:reporter_times:{video_id}")
+}
+
+fn outcome_key(video_id: &str) -> String {
+    format!("offchain:reports:outcome:{video_id}")
+}
+
+/// Reason keywords and how strongly they raise severity
+const REASON_WEIGHTS: &[(&str, f64)] = &[
+    ("child", 10.0),
+    ("minor", 10.0),
+    ("csam", 10.0),
+    ("violence", 6.0),
+    ("gore", 6.0),
+    ("terror", 6.0),
+    ("nudity", 5.0),
+    ("sexual", 5.0),
+    ("harass", 4.0),
+    ("hate", 4.0),
+    ("copyright", 2.0),
+    ("spam", 1.0),
+];
+
+fn reason_weight(reason: &str) -> f64 {
+    let reason = reason.to_lowercase();
+    REASON_WEIGHTS
+        .iter()
+        .filter(|(keyword, _)| reason.contains(keyword))
+        .map(|(_, weight)| *weight)
+        .fold(0.0, f64::max)
+}
+
+/// Reporter diversity dominates raw volume so one user spamming the report
+/// button can't outrank many independent reports.
+pub fn severity_score(
+    unique_reporters: u64,
+    report_count: u64,
+    reasons: &HashMap<String, u64>,
+) -> f64 {
+    let worst_reason = reasons.keys().map(|r| reason_weight(r)).fold(0.0, f64::max);
+    unique_reporters as f64 * 3.0 + (1.0 + report_count as f64).ln() + worst_reason
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportStatus {
+    Open,
+    Escalated,
+    /// Hidden from feeds by the auto-moderation policy, awaiting review
+    AutoHidden,
+}
+
+impl ReportStatus {
+    fn from_field(value: &str) -> Self {
+        match value {
+            "escalated" => ReportStatus::Escalated,
+            "auto_hidden" => ReportStatus::AutoHidden,
+            _ => ReportStatus::Open,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportAction {
+    /// Reports were unfounded; close and restore an auto-hidden video
+    Dismiss,
+    /// Remove the video from feeds for good
+    Takedown,
+    /// Keep it open and pin it to the top for senior review
+    Escalate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReportedVideo {
+    pub video_id: String,
+    pub post_id: String,
+    pub canister_id: String,
+    pub publisher_id: String,
+    pub report_count: u64,
+    pub unique_reporters: u64,
+    pub reasons: HashMap<String, u64>,
+    pub severity: f64,
+    pub status: ReportStatus,
+    pub first_reported_at: i64,
+    pub last_reported_at: i64,
+}
+
+impl ReportedVideo {
+    /// Escalated and auto-hidden cases sort above every open one
+    pub(super) fn queue_score(&self) -> f64 {
+        match self.status {
+            ReportStatus::Open => self.severity,
+            ReportStatus::Escalated | ReportStatus::AutoHidden => self.severity + ESCALATED_BOOST,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReportOutcome {
+    pub action: ReportAction,
+    pub moderator: String,
+    pub note: Option<String>,
+    pub report_count: u64,
+    pub unique_reporters: u64,
+    pub acted_at: i64,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ReportsQuery {
+    /// Maximum number of videos to return (default: 50, max: 200)
+    pub limit: Option<usize>,
+    /// Offset for pagination (default: 0)
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReportsResponse {
+    pub videos: Vec<ReportedVideo>,
+    pub total_count: u64,
+}
+
+/// The body also carries `delegated_identity_wire`, which the moderator
+/// middleware verifies before the handler runs
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReportActionRequest {
+    pub action: ReportAction,
+    pub note: Option<String>,
+}
+
+/// Entry point for the report flow: records the report, then lets the
+/// auto-moderation policy decide whether the video should be hidden
+pub async fn process_report(state: &AppState, report: &ReportPostRequestV3) -> anyhow::Result<()> {
+    let video = record_report(&state.yral_redis_store_dragonfly, report).await?;
+    super::auto_hide::evaluate(state, &video).await
+}
+
+/// Folds a report into the video's aggregate and rescores it in the queue.
+/// Returns the refreshed aggregate.
+pub async fn record_report(
+    pool: &Arc<DragonflyPool>,
+    report: &ReportPostRequestV3,
+) -> anyhow::Result<ReportedVideo> {
+    let video_id = &report.video_id;
+    let summary_key = summary_key(video_id);
+    let now = chrono::Utc::now().timestamp();
+    let reason = report.reason.trim().to_lowercase();
+
+    let mut conn = pool.get().await?;
+    let _: () = redis::pipe()
+        .sadd(reporters_key(video_id), report.user_principal.to_text())
+        .zadd(
+            reporter_times_key(video_id),
+            report.user_principal.to_text(),
+            now,
+        )
+        .hincr(reasons_key(video_id), &reason, 1)
+        .hincr(&summary_key, "report_count", 1)
+        .hset_nx(&summary_key, "first_reported_at", now)
+        .hset_multiple(
+            &summary_key,
+            &[
+                ("post_id", report.post_id.clone()),
+                ("canister_id", report.canister_id.to_text()),
+                ("publisher_id", report.publisher_principal.to_text()),
+                ("last_reported_at", now.to_string()),
+            ],
+        )
+        .hset_nx(&summary_key, "status", "open")
+        .query_async(&mut conn)
+        .await?;
+
+    let video = load_reported_video(pool, video_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Report aggregate for {video_id} vanished"))?;
+    let _: () = conn.zadd(QUEUE_KEY, video_id, video.queue_score()).await?;
+
+    Ok(video)
+}
+
+pub(crate) async fn load_reported_video(
+    pool: &Arc<DragonflyPool>,
+    video_id: &str,
+) -> anyhow::Result<Option<ReportedVideo>> {
+    let mut conn = pool.get().await?;
+    let (summary, unique_reporters, reasons): (HashMap<String, String>, u64, HashMap<String, u64>) =
+        redis::pipe()
+            .hgetall(summary_key(video_id))
+            .scard(reporters_key(video_id))
+            .hgetall(reasons_key(video_id))
+            .query_async(&mut conn)
+            .await?;
+
+    if summary.is_empty() {
+        return Ok(None);
+    }
+
+    let field = |name: &str| summary.get(name).cloned().unwrap_or_default();
+    let number = |name: &str| field(name).parse::<i64>().unwrap_or(0);
+    let report_count = number("report_count") as u64;
+    let status = ReportStatus::from_field(&field("status"));
+    let severity = severity_score(unique_reporters, report_count, &reasons);
+
+    Ok(Some(ReportedVideo {
+        video_id: video_id.to_string(),
+        post_id: field("post_id"),
+        canister_id: field("canister_id"),
+        publisher_id: field("publisher_id"),
+        report_count,
+        unique_reporters,
+        reasons,
+        severity,
+        status,
+        first_reported_at: number("first_reported_at"),
+        last_reported_at: number("last_reported_at"),
+    }))
+}
+
+/// Reported videos awaiting triage, most severe first
+#[utoipa::path(
+    get,
+    path = "/reports",
+    params(ReportsQuery),
+    tag = "moderation",
+    responses(
+        (status = 200, description = "Report queue", body = ReportsResponse),
+        (status = 401, description = "Unauthorized - invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Forbidden - not a moderator", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("moderator_identity" = [])
+    )
+)]
+#[instrument(skip(state))]
+pub async fn list_reports(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ReportsQuery>,
+) -> Result<Json<ReportsResponse>, ApiError> {
+    let limit = query.limit.unwrap_or(50).clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0);
+    let pool = &state.yral_redis_store_dragonfly;
+
+    let (video_ids, total_count): (Vec<String>, u64) = {
+        let mut conn = pool.get().await?;
+        redis::pipe()
+            .zrevrange(QUEUE_KEY, offset as isize, (offset + limit) as isize - 1)
+            .zcard(QUEUE_KEY)
+            .query_async(&mut conn)
+            .await?
+    };
+
+    let mut videos = Vec::with_capacity(video_ids.len());
+    for video_id in video_ids {
+        if let Some(video) = load_reported_video(pool, &video_id).await? {
+            videos.push(video);
+        }
+    }
+
+    Ok(Json(ReportsResponse {
+        videos,
+        total_count,
+    }))
+}
+
+/// Record a moderator decision on a reported video
+#[utoipa::path(
+    post,
+    path = "/reports/{video_id}/action",
+    request_body = ReportActionRequest,
+    params(
+        ("video_id" = String, Path, description = "The reported video ID")
+    ),
+    tag = "moderation",
+    responses(
+        (status = 200, description = "Action recorded", body = ModerationResponse),
+        (status = 401, description = "Unauthorized - invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Forbidden - not a moderator", body = ApiErrorBody),
+        (status = 404, description = "Video has no open reports", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("moderator_identity" = [])
+    )
+)]
+#[instrument(skip(state, request), fields(action = ?request.action))]
+pub async fn act_on_report(
+    Path(video_id): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Extension(Moderator(moderator)): Extension<Moderator>,
+    Json(request): Json<ReportActionRequest>,
+) -> Result<Json<ModerationResponse>, ApiError> {
+    let pool = &state.yral_redis_store_dragonfly;
+    let video = load_reported_video(pool, &video_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("No open reports for video {video_id}")))?;
+
+    match request.action {
+        ReportAction::Escalate => {
+            let mut conn = pool.get().await?;
+            let _: () = redis::pipe()
+                .hset(summary_key(&video_id), "status", "escalated")
+                .zadd(QUEUE_KEY, &video_id, video.severity + ESCALATED_BOOST)
+                .query_async(&mut conn)
+                .await?;
+        }
+        ReportAction::Takedown => {
+            insert_video_delete_row_to_bigquery_v2(
+                state.clone(),
+                video.canister_id.clone(),
+                video.post_id.clone(),
+                video_id.clone(),
+            )
+            .await?;
+            super::auto_hide::unhide(&state.kvrocks_client, &video_id).await?;
+            close_reports(pool, &video_id).await?;
+        }
+        ReportAction::Dismiss => {
+            // Also covers auto-hidden cases that were escalated before review
+            super::auto_hide::unhide(&state.kvrocks_client, &video_id).await?;
+            close_reports(pool, &video_id).await?;
+        }
+    }
+
+    let outcome = ReportOutcome {
+        action: request.action,
+        moderator: moderator.to_text(),
+        note: request.note,
+        report_count: video.report_count,
+        unique_reporters: video.unique_reporters,
+        acted_at: chrono::Utc::now().timestamp(),
+    };
+    let mut conn = pool.get().await?;
+    let _: () = conn
+        .rpush(
+            outcome_key(&video_id),
+            serde_json::to_string(&outcome).map_err(|e| ApiError::Internal(e.to_string()))?,
+        )
+        .await?;
+
+    log::info!(
+        "Moderator {} applied {:?} to reported video {}",
+        outcome.moderator,
+        outcome.action,
+        video_id
+    );
+
+    Ok(Json(ModerationResponse {
+        success: true,
+        message: format!("Applied {:?} to video {}", outcome.action, video_id),
+    }))
+}
+
+/// Drops the aggregate so any later report starts a fresh case
+async fn close_reports(pool: &Arc<DragonflyPool>, video_id: &str) -> anyhow::Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = redis::pipe()
+        .zrem(QUEUE_KEY, video_id)
+        .del(&[
+            summary_key(video_id),
+            reporters_key(video_id),
+            reasons_key(video_id),
+            reporter_times_key(video_id),
+        ])
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity_prefers_reporter_diversity() {
+        let spam = HashMap::from([("spam".to_string(), 1)]);
+        let one_user_many_reports = severity_score(1, 20, &spam);
+        let many_users = severity_score(5, 5, &spam);
+        assert!(many_users > one_user_many_reports);
+
+        let violence = HashMap::from([("Graphic violence".to_lowercase(), 1)]);
+        assert!(severity_score(1, 1, &violence) > severity_score(1, 1, &spam));
+    }
+}
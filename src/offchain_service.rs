@@ -4,6 +4,7 @@ use std::{collections::HashMap, env, sync::Arc};
 use crate::video_processing::nsfw_api::{NsfwApiClient, VideoBanRequest};
 use crate::{
     app_state::AppState,
+    canister::agent_pool,
     consts::{GOOGLE_CHAT_REPORT_SPACE_URL, OFF_CHAIN_AGENT_URL, USER_POST_SERVICE_CANISTER_ID},
     posts::report_post::repost_post_common_impl,
     AppError,
@@ -513,10 +514,13 @@ pub async fn report_approved_handler(
 
     let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &state.agent);
 
-    let post_details = match user_post_service
-        .get_individual_post_details_by_id(post_id.to_string())
-        .await
-        .with_context(|| format!("Failed to fetch post details for {canister_id}/{post_id}"))
+    let post_details = match agent_pool::query(
+        *USER_POST_SERVICE_CANISTER_ID,
+        "get_individual_post_details_by_id",
+        || user_post_service.get_individual_post_details_by_id(post_id.to_string()),
+    )
+    .await
+    .with_context(|| format!("Failed to fetch post details for {canister_id}/{post_id}"))
     {
         Ok(post_details) => post_details,
         Err(e) => {
@@ -582,10 +586,14 @@ pub async fn report_approved_handler(
     }
     log::info!("Manual NSFW ban recorded for video_id={}", video_uid);
 
-    if let Err(e) = user_post_service
-        .update_post_status(post_id.to_string(), PostStatus::BannedDueToUserReporting)
-        .await
-        .with_context(|| format!("Failed to ban post {canister_id}/{post_id}"))
+    if let Err(e) = agent_pool::update(
+        *USER_POST_SERVICE_CANISTER_ID,
+        "update_post_status",
+        user_post_service
+            .update_post_status(post_id.to_string(), PostStatus::BannedDueToUserReporting),
+    )
+    .await
+    .with_context(|| format!("Failed to ban post {canister_id}/{post_id}"))
     {
         log::error!(
             "Failed to update post status after manual NSFW ban for {}/{} video_id={}: {e:?}",
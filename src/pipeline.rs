@@ -1,3 +1,7 @@
+#[cfg(not(feature = "local-bin"))]
+pub mod stats;
+pub mod telemetry;
+
 /// All possible steps in our processing pipeline
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Step {
@@ -8,7 +12,11 @@ pub enum Step {
     NsfwDetectionV2,
     NsfwApiHandoff,
     NsfwApiStatusPoll,
+    PostCleanup,
     StorjIngest,
+    Thumbnails,
+    Transcode,
+    Transcribe,
 }
 
 impl std::fmt::Display for Step {
@@ -21,7 +29,11 @@ impl std::fmt::Display for Step {
             Step::NsfwDetectionV2 => "nsfw_detection_v2",
             Step::NsfwApiHandoff => "nsfw_api_handoff",
             Step::NsfwApiStatusPoll => "nsfw_api_status_poll",
+            Step::PostCleanup => "post_cleanup",
             Step::StorjIngest => "storj_ingest",
+            Step::Thumbnails => "thumbnails",
+            Step::Transcode => "transcode",
+            Step::Transcribe => "transcribe",
         };
 
         f.write_str(text)
@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use google_cloud_bigquery::http::{
+    job::query::QueryRequest,
+    tabledata::list::{Tuple, Value},
+};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::telemetry::PIPELINE_STEP_EVENT;
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+};
+
+const PROJECT_ID: &str = "hot-or-not-feed-intelligence";
+const EVENTS_TABLE: &str =
+    "`hot-or-not-feed-intelligence.analytics_335143420.test_events_analytics`";
+pub const PIPELINE_STEP_DAILY_STATS_TABLE: &str =
+    "`hot-or-not-feed-intelligence.yral_ds.pipeline_step_daily_stats`";
+
+const DEFAULT_DAYS: u32 = 7;
+const MAX_DAYS: u32 = 90;
+
+pub fn pipeline_stats_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(get_pipeline_stats))
+        .with_state(state)
+}
+
+pub(crate) fn daily_step_stats_table_ddl() -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {PIPELINE_STEP_DAILY_STATS_TABLE} (
+            day DATE NOT NULL,
+            step STRING NOT NULL,
+            runs INT64,
+            failures INT64,
+            p50_duration_ms INT64,
+            p95_duration_ms INT64,
+            bigquery_bytes INT64,
+            provider_calls INT64
+        )
+        PARTITION BY day
+        CLUSTER BY step"
+    )
+}
+
+/// Step durations (p50/p95) and downstream costs per step per day
+pub(crate) fn daily_step_stats_rollup_query(days_back: u32) -> String {
+    format!(
+        "MERGE {PIPELINE_STEP_DAILY_STATS_TABLE} T
+        USING (
+            SELECT
+                DATE(timestamp) AS day,
+                JSON_EXTRACT_SCALAR(params, '$.step') AS step,
+                COUNT(*) AS runs,
+                COUNTIF(JSON_EXTRACT_SCALAR(params, '$.success') = 'false') AS failures,
+                APPROX_QUANTILES(
+                    SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.duration_ms') AS INT64), 100
+                )[OFFSET(50)] AS p50_duration_ms,
+                APPROX_QUANTILES(
+                    SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.duration_ms') AS INT64), 100
+                )[OFFSET(95)] AS p95_duration_ms,
+                SUM(SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.bigquery_bytes') AS INT64))
+                    AS bigquery_bytes,
+                SUM(SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.provider_calls') AS INT64))
+                    AS provider_calls
+            FROM {EVENTS_TABLE}
+            WHERE event = '{PIPELINE_STEP_EVENT}'
+              AND DATE(timestamp) >= DATE_SUB(CURRENT_DATE(), INTERVAL {days_back} DAY)
+            GROUP BY day, step
+            HAVING step IS NOT NULL
+        ) S
+        ON T.day = S.day AND T.step = S.step
+        WHEN MATCHED THEN UPDATE SET
+            runs = S.runs,
+            failures = S.failures,
+            p50_duration_ms = S.p50_duration_ms,
+            p95_duration_ms = S.p95_duration_ms,
+            bigquery_bytes = S.bigquery_bytes,
+            provider_calls = S.provider_calls
+        WHEN NOT MATCHED THEN INSERT ROW"
+    )
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PipelineStatsQuery {
+    /// Days back to include, today included (default 7, max 90)
+    pub days: Option<u32>,
+    /// Only this step, e.g. `transcode` or `upload_to_live`
+    pub step: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PipelineStepDailyStats {
+    /// `YYYY-MM-DD`, UTC
+    pub day: String,
+    pub step: String,
+    pub runs: u64,
+    pub failures: u64,
+    pub p50_duration_ms: u64,
+    pub p95_duration_ms: u64,
+    pub bigquery_bytes: u64,
+    pub provider_calls: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PipelineStatsResponse {
+    /// Newest day first, steps by name within a day
+    pub days: Vec<PipelineStepDailyStats>,
+}
+
+fn cell_str(row: &Tuple, index: usize) -> Option<String> {
+    match row.f.get(index).map(|cell| &cell.v) {
+        Some(Value::String(s)) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn cell_u64(row: &Tuple, index: usize) -> u64 {
+    cell_str(row, index)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_default()
+}
+
+fn parse_row(row: &Tuple) -> Option<PipelineStepDailyStats> {
+    Some(PipelineStepDailyStats {
+        day: cell_str(row, 0)?,
+        step: cell_str(row, 1)?,
+        runs: cell_u64(row, 2),
+        failures: cell_u64(row, 3),
+        p50_duration_ms: cell_u64(row, 4),
+        p95_duration_ms: cell_u64(row, 5),
+        bigquery_bytes: cell_u64(row, 6),
+        provider_calls: cell_u64(row, 7),
+    })
+}
+
+/// Per-step processing time and cost per day, from the daily rollup
+#[utoipa::path(
+    get,
+    path = "",
+    params(PipelineStatsQuery),
+    tag = "admin",
+    responses(
+        (status = 200, description = "Daily pipeline step stats", body = PipelineStatsResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "BigQuery error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_pipeline_stats(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<PipelineStatsQuery>,
+) -> Result<Json<PipelineStatsResponse>, ApiError> {
+    require_operator(&headers)?;
+
+    let days = params.days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+    let step_filter = match &params.step {
+        Some(step) => format!("AND step = '{}'", step.replace('\'', "''")),
+        None => String::new(),
+    };
+    let query = format!(
+        "SELECT CAST(day AS STRING), step, runs, failures, p50_duration_ms, p95_duration_ms,
+                bigquery_bytes, provider_calls
+         FROM {PIPELINE_STEP_DAILY_STATS_TABLE}
+         WHERE day >= DATE_SUB(CURRENT_DATE(), INTERVAL {} DAY) {step_filter}
+         ORDER BY day DESC, step",
+        days - 1
+    );
+
+    let response = state
+        .bigquery_client
+        .job()
+        .query(
+            PROJECT_ID,
+            &QueryRequest {
+                query,
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|e| ApiError::BigQuery(e.to_string()))?;
+
+    Ok(Json(PipelineStatsResponse {
+        days: response
+            .rows
+            .unwrap_or_default()
+            .iter()
+            .filter_map(parse_row)
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollup_query() {
+        let query = daily_step_stats_rollup_query(3);
+        assert!(query.contains("INTERVAL 3 DAY"));
+        assert!(query.contains("event = 'pipeline_step_completed'"));
+        assert!(query.contains("[OFFSET(95)] AS p95_duration_ms"));
+    }
+}
@@ -0,0 +1,157 @@
+//! Per-video step timing and cost telemetry.
+//!
+//! [`measure`] runs one pipeline step for a video and records it as a
+//! `pipeline_step_completed` event: how long it took, whether it succeeded
+//! and what it cost downstream. Code running inside the step reports its
+//! costs with [`count_bigquery_bytes`] and [`count_provider_call`]; outside a
+//! measured step both are no-ops. When a video's processing job completes,
+//! [`record_upload_to_live`] records the time from upload to live under the
+//! `upload_to_live` step.
+//!
+//! The events are aggregated per day and step by the
+//! `pipeline_step_daily_stats` rollup.
+
+use std::{cell::RefCell, future::Future, time::Instant};
+
+use serde::Serialize;
+
+use super::Step;
+use crate::{
+    app_state::AppState,
+    events::{event::Event, warehouse_events::WarehouseEvent},
+};
+
+pub const PIPELINE_STEP_EVENT: &str = "pipeline_step_completed";
+/// Step name of the span from upload to the processing job completing
+pub const UPLOAD_TO_LIVE_STEP: &str = "upload_to_live";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StepCost {
+    /// Bytes processed by BigQuery queries
+    pub bigquery_bytes: u64,
+    /// Requests to external providers, retries included
+    pub provider_calls: u32,
+}
+
+tokio::task_local! {
+    static CURRENT_COST: RefCell<StepCost>;
+}
+
+/// Adds a query's `total_bytes_processed` to the current step's cost
+pub fn count_bigquery_bytes(total_bytes_processed: Option<i64>) {
+    let bytes = total_bytes_processed.unwrap_or_default().max(0) as u64;
+    let _ = CURRENT_COST.try_with(|cost| cost.borrow_mut().bigquery_bytes += bytes);
+}
+
+/// Counts a request to an external provider against the current step
+pub fn count_provider_call() {
+    let _ = CURRENT_COST.try_with(|cost| cost.borrow_mut().provider_calls += 1);
+}
+
+fn step_params(
+    video_id: &str,
+    step: &str,
+    duration_ms: u64,
+    success: bool,
+    cost: &StepCost,
+) -> serde_json::Value {
+    serde_json::json!({
+        "video_id": video_id,
+        "step": step,
+        "duration_ms": duration_ms,
+        "success": success,
+        "bigquery_bytes": cost.bigquery_bytes,
+        "provider_calls": cost.provider_calls,
+    })
+}
+
+fn emit(state: &AppState, params: serde_json::Value) {
+    Event::new(WarehouseEvent {
+        event: PIPELINE_STEP_EVENT.to_string(),
+        params: params.to_string(),
+    })
+    .stream_to_bigquery(state);
+}
+
+/// Runs `step` for `video_id` and records its duration, outcome and cost
+pub async fn measure<T, E>(
+    state: &AppState,
+    video_id: &str,
+    step: Step,
+    fut: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let started = Instant::now();
+    let (result, cost) = CURRENT_COST
+        .scope(RefCell::new(StepCost::default()), async {
+            let result = fut.await;
+            (result, CURRENT_COST.with(|cost| cost.take()))
+        })
+        .await;
+
+    emit(
+        state,
+        step_params(
+            video_id,
+            &step.to_string(),
+            started.elapsed().as_millis() as u64,
+            result.is_ok(),
+            &cost,
+        ),
+    );
+    result
+}
+
+/// Records the time from `uploaded_at` (RFC 3339) until now
+pub fn record_upload_to_live(state: &AppState, video_id: &str, uploaded_at: &str) {
+    let Ok(uploaded_at) = chrono::DateTime::parse_from_rfc3339(uploaded_at) else {
+        log::warn!("Not recording upload_to_live for {video_id}: bad upload time {uploaded_at}");
+        return;
+    };
+    let duration_ms = (chrono::Utc::now() - uploaded_at.with_timezone(&chrono::Utc))
+        .num_milliseconds()
+        .max(0) as u64;
+    emit(
+        state,
+        step_params(
+            video_id,
+            UPLOAD_TO_LIVE_STEP,
+            duration_ms,
+            true,
+            &StepCost::default(),
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_costs_are_scoped_to_the_step() {
+        // Outside a measured step, nothing to count against
+        count_provider_call();
+
+        let cost = CURRENT_COST
+            .scope(RefCell::new(StepCost::default()), async {
+                count_provider_call();
+                count_provider_call();
+                count_bigquery_bytes(Some(1_024));
+                count_bigquery_bytes(None);
+                count_bigquery_bytes(Some(-1));
+                CURRENT_COST.with(|cost| cost.take())
+            })
+            .await;
+        assert_eq!(
+            cost,
+            StepCost {
+                bigquery_bytes: 1_024,
+                provider_calls: 2,
+            }
+        );
+
+        let params = step_params("v1", "transcode", 1_500, false, &cost);
+        assert_eq!(params["step"], "transcode");
+        assert_eq!(params["duration_ms"], 1_500);
+        assert_eq!(params["provider_calls"], 2);
+    }
+}
@@ -0,0 +1,221 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use google_cloud_bigquery::http::{job::query::QueryRequest, tabledata::list::Value};
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+use yral_canisters_client::user_post_service::{Post, Result2, UserPostService};
+
+use crate::{
+    app_state::AppState,
+    canister::agent_pool,
+    comments::comment_count,
+    consts::USER_POST_SERVICE_CANISTER_ID,
+    error::{ApiError, ApiErrorBody},
+    kvrocks::{tables, PostAnalytics},
+    rollups::POST_DAILY_STATS_TABLE,
+    utils::delegated_identity::{
+        delegated_identity_wire_from_headers, verify_delegated_identity_request,
+    },
+};
+
+/// Stats only move when the hourly rollup runs
+const POST_ANALYTICS_CACHE_TTL_SECS: u64 = 10 * 60;
+
+/// Reads the `post_daily_stats` rollup rather than scanning raw events; the
+/// rollup is refreshed hourly
+fn post_analytics_query(video_id: &str) -> String {
+    format!(
+        "SELECT
+            SUM(views) AS views,
+            SAFE_DIVIDE(SUM(watch_percentage_sum), SUM(watch_percentage_count))
+                AS avg_watch_percentage,
+            SUM(likes) AS likes,
+            SUM(shares) AS shares,
+            SUM(reward_btc) AS reward_btc,
+            SUM(reward_inr) AS reward_inr
+        FROM {POST_DAILY_STATS_TABLE}
+        WHERE video_id = '{video_id}'"
+    )
+}
+
+fn cell_f64(value: &Value) -> f64 {
+    match value {
+        Value::String(s) => s.parse().unwrap_or(0.0),
+        _ => 0.0,
+    }
+}
+
+async fn compute_post_analytics(
+    state: &AppState,
+    post_id: &str,
+    video_id: &str,
+) -> Result<PostAnalytics> {
+    let request = QueryRequest {
+        query: post_analytics_query(video_id),
+        ..Default::default()
+    };
+    let result = state
+        .bigquery_client
+        .job()
+        .query("hot-or-not-feed-intelligence", &request)
+        .await?;
+
+    let row = result.rows.unwrap_or_default().into_iter().next();
+    let cell = |index: usize| {
+        row.as_ref()
+            .and_then(|row| row.f.get(index))
+            .map(|cell| cell_f64(&cell.v))
+            .unwrap_or(0.0)
+    };
+
+    Ok(PostAnalytics {
+        post_id: post_id.to_string(),
+        video_id: video_id.to_string(),
+        views: cell(0) as u64,
+        avg_watch_percentage: cell(1),
+        likes: cell(2) as u64,
+        shares: cell(3) as u64,
+        reward_btc: cell(4),
+        reward_inr: cell(5),
+        computed_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PostAnalyticsResponse {
+    pub post_id: String,
+    pub video_id: String,
+    pub views: u64,
+    /// Mean `percentage_watched` across views, 0-100
+    pub avg_watch_percentage: f64,
+    pub likes: u64,
+    pub shares: u64,
+    /// Live count from the comments store, not part of the cached stats
+    pub comments: u64,
+    /// Creator rewards generated by this post
+    pub reward_btc: f64,
+    pub reward_inr: f64,
+    /// Unix timestamp the stats were computed at
+    pub computed_at: i64,
+}
+
+impl From<PostAnalytics> for PostAnalyticsResponse {
+    fn from(stats: PostAnalytics) -> Self {
+        Self {
+            post_id: stats.post_id,
+            video_id: stats.video_id,
+            views: stats.views,
+            avg_watch_percentage: stats.avg_watch_percentage,
+            likes: stats.likes,
+            shares: stats.shares,
+            comments: 0,
+            reward_btc: stats.reward_btc,
+            reward_inr: stats.reward_inr,
+            computed_at: stats.computed_at,
+        }
+    }
+}
+
+/// Get analytics for one of the caller's posts
+#[utoipa::path(
+    get,
+    path = "/{post_id}/analytics",
+    params(
+        ("post_id" = String, Path, description = "Post ID"),
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the post's creator")
+    ),
+    tag = "posts",
+    responses(
+        (status = 200, description = "Post analytics", body = PostAnalyticsResponse),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Caller does not own the post", body = ApiErrorBody),
+        (status = 404, description = "Post not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_post_analytics(
+    State(state): State<Arc<AppState>>,
+    Path(post_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<PostAnalyticsResponse>, ApiError> {
+    let wire = delegated_identity_wire_from_headers(&headers)
+        .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+    let user_info = verify_delegated_identity_request(&state, wire, &headers)
+        .await
+        .map_err(|e| ApiError::Unauthorized(format!("Invalid delegated identity: {e}")))?;
+    crate::middleware::set_user_context(user_info.user_principal);
+
+    let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &state.agent);
+    let Result2::Ok(Post {
+        video_uid,
+        creator_principal,
+        ..
+    }) = agent_pool::query(
+        *USER_POST_SERVICE_CANISTER_ID,
+        "get_individual_post_details_by_id",
+        || user_post_service.get_individual_post_details_by_id(post_id.clone()),
+    )
+    .await?
+    else {
+        return Err(ApiError::NotFound(format!("Post {post_id} not found")));
+    };
+
+    if creator_principal != user_info.user_principal {
+        return Err(ApiError::Forbidden(
+            "Only the creator can view post analytics".to_string(),
+        ));
+    }
+
+    let stats = match state
+        .kvrocks_client
+        .get(&tables::POST_ANALYTICS, &post_id)
+        .await?
+    {
+        Some(cached) => cached,
+        None => {
+            let stats = compute_post_analytics(&state, &post_id, &video_uid).await?;
+            if let Err(e) = state
+                .kvrocks_client
+                .put_ex(
+                    &tables::POST_ANALYTICS,
+                    &stats.post_id,
+                    &stats,
+                    POST_ANALYTICS_CACHE_TTL_SECS,
+                )
+                .await
+            {
+                log::warn!("Failed to cache analytics for post {post_id}: {e:?}");
+            }
+            stats
+        }
+    };
+
+    let mut response = PostAnalyticsResponse::from(stats);
+    response.comments = comment_count(&state.yral_redis_store_dragonfly, &post_id).await?;
+
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_f64() {
+        assert_eq!(cell_f64(&Value::String("42".to_string())), 42.0);
+        assert_eq!(cell_f64(&Value::String("0.75".to_string())), 0.75);
+        assert_eq!(cell_f64(&Value::Null), 0.0);
+        assert!(post_analytics_query("vid123").contains("= 'vid123'"));
+    }
+}
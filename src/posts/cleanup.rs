@@ -0,0 +1,593 @@
+//! Cascading cleanup of everything derived from a deleted post's video.
+//!
+//! `handle_delete_post_v2` enqueues a `post_cleanup` QStash job once the
+//! canister delete succeeds. The job removes each artifact independently and
+//! records its outcome in a Dragonfly hash (`offchain:post_cleanup:{video_id}`)
+//! so operators can see what is left behind. Failed artifacts make the job
+//! return an error; QStash redelivers it and artifacts already removed are
+//! skipped.
+//!
+//! The detector verdict cache is keyed by content hash and shared by
+//! re-uploads of the same file, so it is left to expire on its own.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use cloud_storage::ListRequest;
+use futures::StreamExt;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use super::{
+    delete_post::handle_duplicate_post_on_delete,
+    feed_cache_invalidation::{self, FeedCacheInvalidationRequest, InvalidationReason},
+    search::remove_from_index,
+};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    comments::remove_post_comments,
+    consts::{STORJ_INTERFACE_TOKEN, STORJ_INTERFACE_URL},
+    error::{ApiError, ApiErrorBody},
+    kvrocks::tables,
+    pipeline::Step,
+    setup_context,
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const CLEANUP_STATUS_KEY_PREFIX: &str = "offchain:post_cleanup";
+/// Long enough to investigate a stuck cleanup, short enough not to pile up
+const CLEANUP_STATUS_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+const VIDEOS_BUCKET: &str = "yral-videos";
+const GCS_PUBLIC_URL_PREFIX: &str = "https://storage.googleapis.com/";
+
+fn status_key(video_id: &str) -> String {
+    format!("{CLEANUP_STATUS_KEY_PREFIX}:{video_id}")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PostCleanupRequest {
+    pub video_id: String,
+    pub post_id: String,
+    pub publisher_user_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupArtifact {
+    RedisPhash,
+    MilvusPhash,
+    MilvusEmbedding,
+    DedupRows,
+    NsfwVerdict,
+    SearchIndex,
+    FeedCache,
+    Thumbnails,
+    Renditions,
+    Comments,
+    GcsVideo,
+    StorjVideo,
+}
+
+impl CleanupArtifact {
+    /// Execution order: the Redis phash entry is looked up through the
+    /// kvrocks phash row, so it must go before the dedup rows
+    pub const ALL: [Self; 12] = [
+        Self::RedisPhash,
+        Self::MilvusPhash,
+        Self::MilvusEmbedding,
+        Self::DedupRows,
+        Self::NsfwVerdict,
+        Self::SearchIndex,
+        Self::FeedCache,
+        Self::Thumbnails,
+        Self::Renditions,
+        Self::Comments,
+        Self::GcsVideo,
+        Self::StorjVideo,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RedisPhash => "redis_phash",
+            Self::MilvusPhash => "milvus_phash",
+            Self::MilvusEmbedding => "milvus_embedding",
+            Self::DedupRows => "dedup_rows",
+            Self::NsfwVerdict => "nsfw_verdict",
+            Self::SearchIndex => "search_index",
+            Self::FeedCache => "feed_cache",
+            Self::Thumbnails => "thumbnails",
+            Self::Renditions => "renditions",
+            Self::Comments => "comments",
+            Self::GcsVideo => "gcs_video",
+            Self::StorjVideo => "storj_video",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|artifact| artifact.as_str() == value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ArtifactStatus {
+    Pending,
+    Done,
+    /// Nothing to remove, e.g. the artifact was never produced
+    Skipped {
+        reason: String,
+    },
+    Failed {
+        error: String,
+        attempts: u32,
+    },
+}
+
+impl ArtifactStatus {
+    /// Whether the job can skip this artifact on redelivery
+    fn is_settled(&self) -> bool {
+        matches!(self, Self::Done | Self::Skipped { .. })
+    }
+
+    fn attempts(&self) -> u32 {
+        match self {
+            Self::Failed { attempts, .. } => *attempts,
+            _ => 0,
+        }
+    }
+}
+
+fn skipped(reason: impl Into<String>) -> ArtifactStatus {
+    ArtifactStatus::Skipped {
+        reason: reason.into(),
+    }
+}
+
+/// Marks every artifact pending so the status endpoint reflects a queued job
+pub async fn init_cleanup_status(
+    pool: &Arc<DragonflyPool>,
+    video_id: &str,
+    post_id: &str,
+) -> Result<()> {
+    let key = status_key(video_id);
+    let pending = serde_json::to_string(&ArtifactStatus::Pending)?;
+    let mut pipe = redis::pipe();
+    pipe.hset(&key, "post_id", post_id).ignore();
+    pipe.hset(&key, "updated_at", chrono::Utc::now().to_rfc3339())
+        .ignore();
+    for artifact in CleanupArtifact::ALL {
+        pipe.hset_nx(&key, artifact.as_str(), &pending).ignore();
+    }
+    pipe.expire(&key, CLEANUP_STATUS_TTL_SECS).ignore();
+
+    let mut conn = pool.get().await?;
+    let _: () = pipe.query_async(&mut conn).await?;
+    Ok(())
+}
+
+async fn record_status(
+    pool: &Arc<DragonflyPool>,
+    video_id: &str,
+    artifact: CleanupArtifact,
+    status: &ArtifactStatus,
+) -> Result<()> {
+    let key = status_key(video_id);
+    let mut conn = pool.get().await?;
+    let _: () = redis::pipe()
+        .hset(&key, artifact.as_str(), serde_json::to_string(status)?)
+        .ignore()
+        .hset(&key, "updated_at", chrono::Utc::now().to_rfc3339())
+        .ignore()
+        .expire(&key, CLEANUP_STATUS_TTL_SECS)
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PostCleanupStatus {
+    pub video_id: String,
+    pub post_id: Option<String>,
+    /// Every artifact is done or skipped
+    pub complete: bool,
+    pub artifacts: BTreeMap<CleanupArtifact, ArtifactStatus>,
+    pub updated_at: Option<String>,
+}
+
+pub(crate) async fn load_status(
+    pool: &Arc<DragonflyPool>,
+    video_id: &str,
+) -> Result<Option<PostCleanupStatus>> {
+    let mut conn = pool.get().await?;
+    let mut fields: BTreeMap<String, String> = conn.hgetall(status_key(video_id)).await?;
+    if fields.is_empty() {
+        return Ok(None);
+    }
+
+    let artifacts: BTreeMap<_, _> = fields
+        .iter()
+        .filter_map(|(field, value)| {
+            let artifact = CleanupArtifact::parse(field)?;
+            let status = serde_json::from_str(value).ok()?;
+            Some((artifact, status))
+        })
+        .collect();
+    let complete = CleanupArtifact::ALL.iter().all(|artifact| {
+        artifacts
+            .get(artifact)
+            .is_some_and(ArtifactStatus::is_settled)
+    });
+
+    Ok(Some(PostCleanupStatus {
+        video_id: video_id.to_string(),
+        post_id: fields.remove("post_id"),
+        complete,
+        artifacts,
+        updated_at: fields.remove("updated_at"),
+    }))
+}
+
+/// Splits a public GCS URL into bucket and object path
+fn gcs_object_from_url(url: &str) -> Option<(&str, &str)> {
+    let (bucket, path) = url.strip_prefix(GCS_PUBLIC_URL_PREFIX)?.split_once('/')?;
+    (!bucket.is_empty() && !path.is_empty()).then_some((bucket, path))
+}
+
+/// Deletes a GCS object; an object that is already gone counts as deleted
+async fn delete_gcs_object(state: &AppState, bucket: &str, path: &str) -> Result<()> {
+    match state.gcs_client.object().delete(bucket, path).await {
+        Ok(()) => Ok(()),
+        Err(cloud_storage::Error::Google(response)) if response.error.code == 404 => Ok(()),
+        Err(e) => Err(anyhow::anyhow!(
+            "Failed to delete gs://{bucket}/{path}: {e}"
+        )),
+    }
+}
+
+async fn cleanup_redis_phash(state: &AppState, video_id: &str) -> Result<ArtifactStatus> {
+    let Some(row) = state
+        .kvrocks_client
+        .get(&tables::VIDEOHASH_PHASH, video_id)
+        .await?
+    else {
+        return Ok(skipped("no phash recorded"));
+    };
+
+    let key = format!("impressions:video_phash:{}", row.phash);
+    let mut conn = state.rewards_module.dragonfly_pool.get().await?;
+    let owner: Option<String> = conn.get(&key).await?;
+    match owner {
+        Some(owner) if owner == video_id => {
+            let _: () = conn.del(&key).await?;
+            Ok(ArtifactStatus::Done)
+        }
+        // The exact-match entry belongs to the unique original, not this copy
+        Some(owner) => Ok(skipped(format!("phash maps to {owner}"))),
+        None => Ok(skipped("no exact-match entry")),
+    }
+}
+
+async fn cleanup_dedup_rows(state: &AppState, video_id: &str) -> Result<ArtifactStatus> {
+    // Promotes a duplicate to unique parent and drops the BigQuery row
+    handle_duplicate_post_on_delete(
+        state.bigquery_client.clone(),
+        &state.kvrocks_client,
+        video_id.to_string(),
+    )
+    .await?;
+
+    let kvrocks = &state.kvrocks_client;
+    kvrocks.remove(&tables::VIDEO_UNIQUE_V2, video_id).await?;
+    kvrocks
+        .remove(&tables::VIDEO_DEDUP_STATUS, video_id)
+        .await?;
+    kvrocks.remove(&tables::VIDEOHASH_PHASH, video_id).await?;
+    kvrocks
+        .remove(&tables::VIDEOHASH_ORIGINAL, video_id)
+        .await?;
+    kvrocks
+        .remove(&tables::VIDEO_CONTENT_HASH, video_id)
+        .await?;
+    Ok(ArtifactStatus::Done)
+}
+
+async fn cleanup_thumbnails(state: &AppState, video_id: &str) -> Result<ArtifactStatus> {
+    let Some(record) = state
+        .kvrocks_client
+        .get(&tables::VIDEO_THUMBNAILS, video_id)
+        .await?
+    else {
+        return Ok(skipped("no thumbnails generated"));
+    };
+
+    for thumbnail in &record.thumbnails {
+        match gcs_object_from_url(&thumbnail.url) {
+            Some((bucket, path)) => delete_gcs_object(state, bucket, path).await?,
+            None => log::warn!(
+                "Unrecognised thumbnail URL for {video_id}: {}",
+                thumbnail.url
+            ),
+        }
+    }
+    state
+        .kvrocks_client
+        .remove(&tables::VIDEO_THUMBNAILS, video_id)
+        .await?;
+    Ok(ArtifactStatus::Done)
+}
+
+/// Deletes the HLS playlists and segments next to the master playlist
+async fn cleanup_renditions(state: &AppState, video_id: &str) -> Result<ArtifactStatus> {
+    let Some(record) = state
+        .kvrocks_client
+        .get(&tables::VIDEO_RENDITIONS, video_id)
+        .await?
+    else {
+        return Ok(skipped("no renditions transcoded"));
+    };
+
+    let (bucket, master) = gcs_object_from_url(&record.master_playlist_url)
+        .with_context(|| format!("Unrecognised playlist URL {}", record.master_playlist_url))?;
+    let prefix = match master.rsplit_once('/') {
+        Some((dir, _)) => format!("{dir}/"),
+        None => master.to_string(),
+    };
+
+    let mut pages = Box::pin(
+        state
+            .gcs_client
+            .object()
+            .list(
+                bucket,
+                ListRequest {
+                    prefix: Some(prefix),
+                    ..Default::default()
+                },
+            )
+            .await?,
+    );
+    let mut objects = Vec::new();
+    while let Some(page) = pages.next().await {
+        objects.extend(page?.items.into_iter().map(|object| object.name));
+    }
+    for object in &objects {
+        delete_gcs_object(state, bucket, object).await?;
+    }
+
+    state
+        .kvrocks_client
+        .remove(&tables::VIDEO_RENDITIONS, video_id)
+        .await?;
+    Ok(ArtifactStatus::Done)
+}
+
+async fn cleanup_storj_video(req: &PostCleanupRequest) -> Result<ArtifactStatus> {
+    let response = reqwest::Client::new()
+        .post(
+            STORJ_INTERFACE_URL
+                .join("/delete")
+                .expect("url to be valid"),
+        )
+        .json(&serde_json::json!({
+            "publisher_user_id": req.publisher_user_id,
+            "video_id": req.video_id,
+        }))
+        .bearer_auth(STORJ_INTERFACE_TOKEN.as_str())
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(skipped("not in Storj"));
+    }
+    response
+        .error_for_status()
+        .context("Storj interface delete failed")?;
+    Ok(ArtifactStatus::Done)
+}
+
+async fn run_step(
+    state: &AppState,
+    req: &PostCleanupRequest,
+    artifact: CleanupArtifact,
+) -> Result<ArtifactStatus> {
+    let video_id = req.video_id.as_str();
+    match artifact {
+        CleanupArtifact::RedisPhash => cleanup_redis_phash(state, video_id).await,
+        CleanupArtifact::MilvusPhash => {
+            let Some(milvus) = &state.milvus_client else {
+                return Ok(skipped("Milvus not configured"));
+            };
+            milvus.delete_video_hash(video_id).await?;
+            Ok(ArtifactStatus::Done)
+        }
+        CleanupArtifact::MilvusEmbedding => {
+            let Some(milvus) = &state.milvus_client else {
+                return Ok(skipped("Milvus not configured"));
+            };
+            milvus.delete_embeddings(video_id).await?;
+            state
+                .kvrocks_client
+                .remove(&tables::VIDEO_EMBEDDINGS, video_id)
+                .await?;
+            Ok(ArtifactStatus::Done)
+        }
+        CleanupArtifact::DedupRows => cleanup_dedup_rows(state, video_id).await,
+        CleanupArtifact::NsfwVerdict => {
+            state
+                .kvrocks_client
+                .remove(&tables::VIDEO_NSFW, video_id)
+                .await?;
+            Ok(ArtifactStatus::Done)
+        }
+        CleanupArtifact::SearchIndex => {
+            remove_from_index(&state.yral_redis_store_dragonfly, video_id).await?;
+            Ok(ArtifactStatus::Done)
+        }
+        // The feed cache service drops the video in its own bulk job; done once queued
+        CleanupArtifact::FeedCache => {
+            if !feed_cache_invalidation::is_configured() {
+                return Ok(skipped("ML feed cache invalidation is not configured"));
+            }
+            state
+                .qstash_client
+                .publish_job(&FeedCacheInvalidationRequest {
+                    video_id: video_id.to_string(),
+                    reason: InvalidationReason::Deleted,
+                })
+                .await?;
+            Ok(ArtifactStatus::Done)
+        }
+        CleanupArtifact::Thumbnails => cleanup_thumbnails(state, video_id).await,
+        CleanupArtifact::Renditions => cleanup_renditions(state, video_id).await,
+        CleanupArtifact::Comments => {
+            remove_post_comments(&state.yral_redis_store_dragonfly, &req.post_id).await?;
+            Ok(ArtifactStatus::Done)
+        }
+        CleanupArtifact::GcsVideo => {
+            delete_gcs_object(state, VIDEOS_BUCKET, &format!("{video_id}.mp4")).await?;
+            Ok(ArtifactStatus::Done)
+        }
+        CleanupArtifact::StorjVideo => cleanup_storj_video(req).await,
+    }
+}
+
+/// QStash job: removes every artifact derived from a deleted post's video.
+/// Errors make QStash retry; artifacts already removed are skipped.
+#[utoipa::path(
+    post,
+    path = "/post_cleanup",
+    request_body = PostCleanupRequest,
+    responses(
+        (status = 200, description = "Every artifact removed or skipped"),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state))]
+pub async fn post_cleanup_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<PostCleanupRequest>,
+) -> Result<(), ApiError> {
+    setup_context!(&req.video_id, Step::PostCleanup);
+
+    let pool = &state.yral_redis_store_dragonfly;
+    let previous = load_status(pool, &req.video_id)
+        .await?
+        .map(|status| status.artifacts)
+        .unwrap_or_default();
+
+    let mut failed = Vec::new();
+    for artifact in CleanupArtifact::ALL {
+        let prior = previous.get(&artifact);
+        if prior.is_some_and(ArtifactStatus::is_settled) {
+            continue;
+        }
+
+        let status = match run_step(&state, &req, artifact).await {
+            Ok(status) => status,
+            Err(e) => {
+                log::warn!(
+                    "Post cleanup of {} for {} failed: {e:?}",
+                    artifact.as_str(),
+                    req.video_id
+                );
+                failed.push(artifact.as_str());
+                ArtifactStatus::Failed {
+                    error: e.to_string(),
+                    attempts: prior.map_or(0, ArtifactStatus::attempts) + 1,
+                }
+            }
+        };
+        record_status(pool, &req.video_id, artifact, &status).await?;
+    }
+
+    if !failed.is_empty() {
+        return Err(ApiError::Internal(format!(
+            "Post cleanup for {} incomplete: {}",
+            req.video_id,
+            failed.join(", ")
+        )));
+    }
+
+    log::info!("Post cleanup for {} complete", req.video_id);
+    Ok(())
+}
+
+/// Per-artifact status of a deleted post's cleanup job
+#[utoipa::path(
+    get,
+    path = "/cleanup/{video_id}",
+    params(
+        ("video_id" = String, Path, description = "Video ID of the deleted post")
+    ),
+    tag = "posts",
+    responses(
+        (status = 200, description = "Cleanup status", body = PostCleanupStatus),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "No cleanup recorded for this video", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_post_cleanup_status(
+    State(state): State<Arc<AppState>>,
+    Path(video_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<PostCleanupStatus>, ApiError> {
+    require_operator(&headers)?;
+
+    load_status(&state.yral_redis_store_dragonfly, &video_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("No cleanup recorded for {video_id}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcs_object_and_status_encoding() {
+        assert_eq!(
+            gcs_object_from_url(
+                "https://storage.googleapis.com/yral-video-thumbnails/vid1/thumb-0.jpg"
+            ),
+            Some(("yral-video-thumbnails", "vid1/thumb-0.jpg"))
+        );
+        assert_eq!(gcs_object_from_url("https://example.com/a/b.jpg"), None);
+
+        let status = ArtifactStatus::Failed {
+            error: "boom".to_string(),
+            attempts: 2,
+        };
+        let encoded = serde_json::to_string(&status).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ArtifactStatus>(&encoded).unwrap(),
+            status
+        );
+        assert!(!status.is_settled());
+        assert!(skipped("none").is_settled());
+        assert_eq!(
+            CleanupArtifact::parse("storj_video"),
+            Some(CleanupArtifact::StorjVideo)
+        );
+        assert_eq!(
+            CleanupArtifact::parse("renditions"),
+            Some(CleanupArtifact::Renditions)
+        );
+    }
+}
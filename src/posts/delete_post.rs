@@ -17,14 +17,18 @@ use types::PostRequest;
 use verify::VerifiedPostRequest;
 use yral_canisters_client::user_post_service::UserPostService;
 
-use crate::kvrocks::{KvrocksClient, VideoDeleted};
+use crate::kvrocks::{tables, KvrocksClient, VideoDeleted};
 use crate::{
     app_state::AppState,
+    canister::agent_pool,
     consts::{USER_INFO_SERVICE_CANISTER_ID, USER_POST_SERVICE_CANISTER_ID},
+    error::{ApiError, ApiErrorBody},
     posts::queries::get_duplicate_children_query,
     user::utils::get_agent_from_delegated_identity_wire,
 };
 
+#[cfg(not(feature = "local-bin"))]
+use super::cleanup::{init_cleanup_status, PostCleanupRequest};
 use super::{types, verify, DeletePostRequest, DeletePostRequestV2};
 
 const BULK_INSERT_DELETE_BIGQUERY_BATCH_SIZE: usize = 500;
@@ -37,19 +41,22 @@ const BULK_INSERT_DELETE_BIGQUERY_BATCH_SIZE: usize = 500;
     tag = "posts",
     responses(
         (status = 200, description = "Delete post success"),
-        (status = 400, description = "Delete post failed"),
-        (status = 500, description = "Internal server error"),
-        (status = 403, description = "Forbidden"),
+        (status = 400, description = "Delete post failed", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+        (status = 403, description = "Forbidden", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
     )
 )]
 #[instrument(skip(state, verified_request))]
 pub async fn handle_delete_post(
     State(state): State<Arc<AppState>>,
     Json(verified_request): Json<VerifiedPostRequest<DeletePostRequest>>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     // Verify that the canister ID matches the user's canister
     if verified_request.request.request_body.canister_id != verified_request.user_canister {
-        return Err((StatusCode::FORBIDDEN, "Forbidden".to_string()));
+        return Err(ApiError::Forbidden("Forbidden".to_string()));
     }
 
     let request_body = verified_request.request.request_body;
@@ -61,7 +68,7 @@ pub async fn handle_delete_post(
     let user_ic_agent =
         get_agent_from_delegated_identity_wire(&verified_request.request.delegated_identity_wire)
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
 
     // TODO: migrate to user_post_service/user_info_service
     // Previously used IndividualUserTemplate to delete posts from individual user
@@ -70,21 +77,20 @@ pub async fn handle_delete_post(
     let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &user_ic_agent);
 
     // Call the canister to delete the post
-    let delete_res = user_post_service.delete_post(post_id.to_string()).await;
+    let delete_res = agent_pool::update(
+        *USER_POST_SERVICE_CANISTER_ID,
+        "delete_post",
+        user_post_service.delete_post(post_id.to_string()),
+    )
+    .await;
     match delete_res {
         Ok(yral_canisters_client::user_post_service::Result_::Ok) => (),
         Ok(yral_canisters_client::user_post_service::Result_::Err(_)) => {
-            return Err((
-                StatusCode::BAD_REQUEST,
+            return Err(ApiError::NotFound(
                 "Delete post failed - either the post doesn't exist or already deleted".to_string(),
             ))
         }
-        Err(e) => {
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Internal server error: {e}"),
-            ))
-        }
+        Err(e) => return Err(e.into()),
     }
 
     insert_video_delete_row_to_bigquery(state.clone(), canister_id, post_id, video_id.clone())
@@ -92,10 +98,7 @@ pub async fn handle_delete_post(
         .map_err(|e| {
             log::error!("Failed to insert video delete row to bigquery: {e}");
 
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to insert video to bigquery: {e}"),
-            )
+            ApiError::BigQuery(format!("Failed to insert video to bigquery: {e}"))
         })?;
 
     // spawn to not block the request since as far as user is concerned, the post is deleted
@@ -120,16 +123,19 @@ pub async fn handle_delete_post(
     tag = "posts",
     responses(
         (status = 200, description = "Delete post success"),
-        (status = 400, description = "Delete post failed"),
-        (status = 500, description = "Internal server error"),
-        (status = 403, description = "Forbidden"),
+        (status = 400, description = "Delete post failed", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+        (status = 403, description = "Forbidden", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
     )
 )]
 #[instrument(skip(state, verified_request))]
 pub async fn handle_delete_post_v2(
     State(state): State<Arc<AppState>>,
     Json(verified_request): Json<VerifiedPostRequest<DeletePostRequestV2>>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let request_body = verified_request.request.request_body;
     let publisher_user_id = request_body.publisher_user_id;
     let post_id = request_body.post_id.clone();
@@ -137,8 +143,7 @@ pub async fn handle_delete_post_v2(
 
     // Verify that the requesting user is the publisher
     if verified_request.user_principal != publisher_user_id {
-        return Err((
-            StatusCode::FORBIDDEN,
+        return Err(ApiError::Forbidden(
             "Only the publisher can delete their own post".to_string(),
         ));
     }
@@ -147,17 +152,12 @@ pub async fn handle_delete_post_v2(
     let publisher_canister_id = state
         .get_individual_canister_by_user_principal(publisher_user_id)
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to get publisher canister: {e}"),
-            )
-        })?;
+        .map_err(|e| ApiError::Canister(format!("Failed to get publisher canister: {e}")))?;
 
     let user_ic_agent =
         get_agent_from_delegated_identity_wire(&verified_request.request.delegated_identity_wire)
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            .map_err(|e| ApiError::Internal(e.to_string()))?;
 
     // Route based on canister or post_id format: UUID post_ids always belong to UserPostService,
     // even if the user's metadata still points to a legacy individual canister.
@@ -168,22 +168,21 @@ pub async fn handle_delete_post_v2(
         let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &user_ic_agent);
 
         // UserPostService.delete_post takes a String
-        let delete_res = user_post_service.delete_post(post_id.clone()).await;
+        let delete_res = agent_pool::update(
+            *USER_POST_SERVICE_CANISTER_ID,
+            "delete_post",
+            user_post_service.delete_post(post_id.clone()),
+        )
+        .await;
         match delete_res {
             Ok(yral_canisters_client::user_post_service::Result_::Ok) => (),
             Ok(yral_canisters_client::user_post_service::Result_::Err(_)) => {
-                return Err((
-                    StatusCode::BAD_REQUEST,
+                return Err(ApiError::NotFound(
                     "Delete post failed - either the post doesn't exist or already deleted"
                         .to_string(),
                 ))
             }
-            Err(e) => {
-                return Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Internal server error: {e}"),
-                ))
-            }
+            Err(e) => return Err(e.into()),
         }
     } else {
         // TODO: migrate to user_post_service/user_info_service
@@ -192,22 +191,21 @@ pub async fn handle_delete_post_v2(
         // a legacy canister.
         let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &user_ic_agent);
 
-        let delete_res = user_post_service.delete_post(post_id.clone()).await;
+        let delete_res = agent_pool::update(
+            *USER_POST_SERVICE_CANISTER_ID,
+            "delete_post",
+            user_post_service.delete_post(post_id.clone()),
+        )
+        .await;
         match delete_res {
             Ok(yral_canisters_client::user_post_service::Result_::Ok) => (),
             Ok(yral_canisters_client::user_post_service::Result_::Err(_)) => {
-                return Err((
-                    StatusCode::BAD_REQUEST,
+                return Err(ApiError::NotFound(
                     "Delete post failed - either the post doesn't exist or already deleted"
                         .to_string(),
                 ))
             }
-            Err(e) => {
-                return Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Internal server error: {e}"),
-                ))
-            }
+            Err(e) => return Err(e.into()),
         }
     }
 
@@ -215,19 +213,23 @@ pub async fn handle_delete_post_v2(
     insert_video_delete_row_to_bigquery_v2(
         state.clone(),
         publisher_canister_id.to_string(),
-        post_id,
+        post_id.clone(),
         video_id.clone(),
     )
     .await
     .map_err(|e| {
         log::error!("Failed to insert video delete row to bigquery: {e}");
 
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to insert video to bigquery: {e}"),
-        )
+        ApiError::BigQuery(format!("Failed to insert video to bigquery: {e}"))
     })?;
 
+    // The cleanup job also handles duplicate promotion, so only fall back to
+    // doing that inline if the job can't be queued
+    #[cfg(not(feature = "local-bin"))]
+    if queue_post_cleanup(&state, &video_id, &post_id, publisher_user_id.to_text()).await {
+        return Ok((StatusCode::OK, "Post deleted".to_string()));
+    }
+
     // spawn to not block the request since as far as user is concerned, the post is deleted
     let bigquery_client = state.bigquery_client.clone();
     let kvrocks_client = state.kvrocks_client.clone();
@@ -243,6 +245,35 @@ pub async fn handle_delete_post_v2(
     Ok((StatusCode::OK, "Post deleted".to_string()))
 }
 
+/// Whether the `post_cleanup` job was queued
+#[cfg(not(feature = "local-bin"))]
+async fn queue_post_cleanup(
+    state: &AppState,
+    video_id: &str,
+    post_id: &str,
+    publisher_user_id: String,
+) -> bool {
+    if let Err(e) = init_cleanup_status(&state.yral_redis_store_dragonfly, video_id, post_id).await
+    {
+        log::warn!("Failed to init cleanup status for {video_id}: {e}");
+    }
+    match state
+        .qstash_client
+        .publish_job(&PostCleanupRequest {
+            video_id: video_id.to_string(),
+            post_id: post_id.to_string(),
+            publisher_user_id,
+        })
+        .await
+    {
+        Ok(()) => true,
+        Err(e) => {
+            log::error!("Failed to queue post cleanup for {video_id}: {e}");
+            false
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VideoUniqueRow {
     pub video_id: String,
@@ -255,7 +286,9 @@ async fn get_hash_from_videohash_original(
     kvrocks_client: &KvrocksClient,
     video_id: &str,
 ) -> anyhow::Result<Option<String>> {
-    let data = kvrocks_client.get_videohash_original(video_id).await?;
+    let data = kvrocks_client
+        .get(&tables::VIDEOHASH_ORIGINAL, video_id)
+        .await?;
     Ok(data.map(|d| d.videohash))
 }
 
@@ -267,7 +300,7 @@ pub async fn handle_duplicate_post_on_delete(
 ) -> Result<(), anyhow::Error> {
     // check if its unique using kvrocks
     let is_unique = kvrocks_client
-        .get_video_unique_v2(&video_id)
+        .get(&tables::VIDEO_UNIQUE_V2, &video_id)
         .await?
         .is_some();
 
@@ -464,7 +497,10 @@ pub async fn bulk_insert_video_delete_rows(
                 gcs_video_id: format!("gs://yral-videos/{}.mp4", post.video_id),
                 deleted_at: chrono::Utc::now().to_rfc3339(),
             };
-            if let Err(e) = kvrocks_client.store_video_deleted(&delete_data).await {
+            if let Err(e) = kvrocks_client
+                .put(&tables::VIDEO_DELETED, &delete_data.video_id, &delete_data)
+                .await
+            {
                 log::error!(
                     "Error pushing video delete data to kvrocks for {}: {}",
                     post.video_id,
@@ -533,7 +569,10 @@ pub async fn bulk_insert_video_delete_rows_v2(
                 gcs_video_id: format!("gs://yral-videos/{}.mp4", post.video_id),
                 deleted_at: chrono::Utc::now().to_rfc3339(),
             };
-            if let Err(e) = kvrocks_client.store_video_deleted(&delete_data).await {
+            if let Err(e) = kvrocks_client
+                .put(&tables::VIDEO_DELETED, &delete_data.video_id, &delete_data)
+                .await
+            {
                 log::error!(
                     "Error pushing video delete data to kvrocks for {}: {}",
                     post.video_id,
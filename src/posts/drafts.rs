@@ -0,0 +1,268 @@
+//! Drafts left by AI video generation.
+//!
+//! `upload_ai_generated_video_to_canister_in_drafts` registers generated
+//! videos as `Draft` posts, which skip the upload pipeline. These endpoints
+//! let the creator list and delete them, and publish one by queueing it
+//! through the same durable dedup/NSFW job as a regular upload.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use candid::Principal;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+use yral_canisters_client::user_post_service::{
+    Post, PostStatus, Result2, Result_, UserPostService,
+};
+
+use crate::{
+    app_state::AppState,
+    canister::agent_pool,
+    consts::{USER_INFO_SERVICE_CANISTER_ID, USER_POST_SERVICE_CANISTER_ID},
+    error::{ApiError, ApiErrorBody},
+    events::{event::Event, warehouse_events::WarehouseEvent},
+    user::utils::get_agent_from_delegated_identity_wire,
+    utils::delegated_identity::{
+        delegated_identity_wire_from_headers, verify_delegated_identity_request,
+    },
+};
+
+pub const DRAFT_PUBLISHED_EVENT: &str = "draft_published";
+const PAGE_SIZE: u64 = 50;
+/// Posts scanned per listing; drafts older than this window aren't listed
+const MAX_SCANNED_POSTS: u64 = 1000;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DraftPost {
+    pub post_id: String,
+    pub video_id: String,
+    pub description: String,
+}
+
+impl From<Post> for DraftPost {
+    fn from(post: Post) -> Self {
+        Self {
+            post_id: post.id,
+            video_id: post.video_uid,
+            description: post.description,
+        }
+    }
+}
+
+fn is_draft(post: &Post) -> bool {
+    matches!(post.status, PostStatus::Draft)
+}
+
+async fn caller_principal(state: &AppState, headers: &HeaderMap) -> Result<Principal, ApiError> {
+    let wire = delegated_identity_wire_from_headers(headers)
+        .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+    let user_info = verify_delegated_identity_request(state, wire, headers)
+        .await
+        .map_err(|e| ApiError::Unauthorized(format!("Invalid delegated identity: {e}")))?;
+    crate::middleware::set_user_context(user_info.user_principal);
+    Ok(user_info.user_principal)
+}
+
+/// The draft, provided `caller` created it
+async fn owned_draft(state: &AppState, caller: Principal, post_id: &str) -> Result<Post, ApiError> {
+    let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &state.agent);
+    let Result2::Ok(post) = agent_pool::query(
+        *USER_POST_SERVICE_CANISTER_ID,
+        "get_individual_post_details_by_id",
+        || user_post_service.get_individual_post_details_by_id(post_id.to_string()),
+    )
+    .await?
+    else {
+        return Err(ApiError::NotFound(format!("Post {post_id} not found")));
+    };
+
+    if post.creator_principal != caller {
+        return Err(ApiError::Forbidden(
+            "Only the creator can manage their drafts".to_string(),
+        ));
+    }
+    if !is_draft(&post) {
+        return Err(ApiError::Conflict(format!("Post {post_id} is not a draft")));
+    }
+
+    Ok(post)
+}
+
+/// The caller's draft posts, newest first
+#[utoipa::path(
+    get,
+    path = "/drafts",
+    params(
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the creator")
+    ),
+    tag = "posts",
+    responses(
+        (status = 200, description = "Drafts", body = Vec<DraftPost>),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn list_drafts(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<DraftPost>>, ApiError> {
+    let caller = caller_principal(&state, &headers).await?;
+
+    let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &state.agent);
+    let mut drafts = Vec::new();
+    let mut start = 0;
+    while start < MAX_SCANNED_POSTS {
+        let end = start + PAGE_SIZE;
+        let posts = agent_pool::query(
+            *USER_POST_SERVICE_CANISTER_ID,
+            "get_posts_of_this_user_profile_with_pagination_cursor",
+            || {
+                user_post_service
+                    .get_posts_of_this_user_profile_with_pagination_cursor(caller, start, end)
+            },
+        )
+        .await?;
+
+        let page_len = posts.len();
+        drafts.extend(posts.into_iter().filter(is_draft).map(DraftPost::from));
+        if page_len < PAGE_SIZE as usize {
+            break;
+        }
+        start = end;
+    }
+
+    Ok(Json(drafts))
+}
+
+/// Delete one of the caller's drafts
+#[utoipa::path(
+    delete,
+    path = "/drafts/{post_id}",
+    params(
+        ("post_id" = String, Path, description = "Draft post ID"),
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the creator")
+    ),
+    tag = "posts",
+    responses(
+        (status = 200, description = "Draft deleted"),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Caller does not own the draft", body = ApiErrorBody),
+        (status = 404, description = "Post not found", body = ApiErrorBody),
+        (status = 409, description = "Post is not a draft", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn delete_draft(
+    State(state): State<Arc<AppState>>,
+    Path(post_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<(), ApiError> {
+    let wire = delegated_identity_wire_from_headers(&headers)
+        .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+    let caller = caller_principal(&state, &headers).await?;
+    owned_draft(&state, caller, &post_id).await?;
+
+    // Drafts never entered the upload pipeline, so there is nothing to clean
+    // up beyond the post itself
+    let user_ic_agent = get_agent_from_delegated_identity_wire(&wire)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+    let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &user_ic_agent);
+    match agent_pool::update(
+        *USER_POST_SERVICE_CANISTER_ID,
+        "delete_post",
+        user_post_service.delete_post(post_id.clone()),
+    )
+    .await?
+    {
+        Result_::Ok => Ok(()),
+        Result_::Err(_) => Err(ApiError::NotFound(format!(
+            "Draft {post_id} doesn't exist or was already deleted"
+        ))),
+    }
+}
+
+/// Publish one of the caller's drafts through the upload pipeline
+#[utoipa::path(
+    post,
+    path = "/drafts/{post_id}/publish",
+    params(
+        ("post_id" = String, Path, description = "Draft post ID"),
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the creator")
+    ),
+    tag = "posts",
+    responses(
+        (status = 200, description = "Draft queued for publishing", body = DraftPost),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Caller does not own the draft", body = ApiErrorBody),
+        (status = 404, description = "Post not found", body = ApiErrorBody),
+        (status = 409, description = "Post is not a draft", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn publish_draft(
+    State(state): State<Arc<AppState>>,
+    Path(post_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<DraftPost>, ApiError> {
+    let caller = caller_principal(&state, &headers).await?;
+    let post = owned_draft(&state, caller, &post_id).await?;
+
+    // Queue the job before flipping the status so a failed enqueue leaves a
+    // draft the creator can retry; the job bans the post if it fails NSFW
+    let job = crate::video_processing::worker::new_upload_job(
+        post.video_uid.clone(),
+        caller.to_text(),
+        post.id.clone(),
+        Some(USER_INFO_SERVICE_CANISTER_ID.to_text()),
+    );
+    crate::video_processing::queue::enqueue_video_processing_job(
+        &state.yral_redis_store_dragonfly,
+        job,
+    )
+    .await?;
+
+    let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &state.agent);
+    if let Result_::Err(e) = agent_pool::update(
+        *USER_POST_SERVICE_CANISTER_ID,
+        "update_post_status",
+        user_post_service.update_post_status(post.id.clone(), PostStatus::ReadyToView),
+    )
+    .await?
+    {
+        return Err(ApiError::Canister(format!(
+            "Failed to publish draft {post_id}: {e:?}"
+        )));
+    }
+    log::info!("Draft {post_id} published by {caller}");
+
+    Event::new(WarehouseEvent {
+        event: DRAFT_PUBLISHED_EVENT.to_string(),
+        params: serde_json::json!({
+            "post_id": post.id,
+            "video_id": post.video_uid,
+            "publisher_user_id": caller.to_text(),
+        })
+        .to_string(),
+    })
+    .stream_to_bigquery(&state);
+
+    Ok(Json(DraftPost::from(post)))
+}
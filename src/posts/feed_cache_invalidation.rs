@@ -0,0 +1,295 @@
+//! Removes a deleted or disapproved video from the per-user feed caches.
+//!
+//! Buffers, watch/success histories and plain-item sets belong to the ML
+//! feed cache service and live in its own Redis, so this service never
+//! touches them directly. Post cleanup and moderator disapproval publish a
+//! `feed_cache_invalidation` QStash job in the bulk lane; the job asks the
+//! feed cache service to drop the video (`ML_FEED_CACHE_INVALIDATION_URL`,
+//! authenticated with `ML_FEED_CACHE_API_TOKEN`) and records each attempt in
+//! a Dragonfly hash (`offchain:feed_cache_invalidation:{video_id}`). The
+//! call is idempotent, so QStash simply redelivers on failure.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const PROGRESS_KEY_PREFIX: &str = "offchain:feed_cache_invalidation";
+const PROGRESS_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+static INVALIDATION_URL: Lazy<Option<reqwest::Url>> = Lazy::new(|| {
+    let url = std::env::var("ML_FEED_CACHE_INVALIDATION_URL").ok()?;
+    reqwest::Url::parse(&url)
+        .inspect_err(|e| log::error!("Invalid ML_FEED_CACHE_INVALIDATION_URL: {e}"))
+        .ok()
+});
+static API_TOKEN: Lazy<Option<String>> =
+    Lazy::new(|| std::env::var("ML_FEED_CACHE_API_TOKEN").ok());
+
+fn progress_key(video_id: &str) -> String {
+    format!("{PROGRESS_KEY_PREFIX}:{video_id}")
+}
+
+/// Whether invalidation can run at all; callers record a skip otherwise
+pub fn is_configured() -> bool {
+    INVALIDATION_URL.is_some()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidationReason {
+    Deleted,
+    Disapproved,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeedCacheInvalidationRequest {
+    pub video_id: String,
+    pub reason: InvalidationReason,
+}
+
+/// What the feed cache service reports back
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct InvalidationResponse {
+    /// Entries dropped across every user's buffers, histories and sets
+    entries_removed: u64,
+    /// Users whose caches held the video
+    users_touched: u64,
+}
+
+/// Publishes the invalidation job; failures are logged, not returned, since
+/// the caller's delete or disapproval has already happened
+pub async fn enqueue_invalidation(state: &AppState, video_id: &str, reason: InvalidationReason) {
+    if !is_configured() {
+        log::warn!("ML_FEED_CACHE_INVALIDATION_URL is not set, {video_id} left in feed caches");
+        return;
+    }
+    let request = FeedCacheInvalidationRequest {
+        video_id: video_id.to_string(),
+        reason,
+    };
+    if let Err(e) = state.qstash_client.publish_job(&request).await {
+        log::error!("Failed to queue feed cache invalidation for {video_id}: {e}");
+    }
+}
+
+async fn record_attempt(
+    pool: &Arc<DragonflyPool>,
+    request: &FeedCacheInvalidationRequest,
+    outcome: &Result<InvalidationResponse>,
+) -> Result<()> {
+    let key = progress_key(&request.video_id);
+    let mut pipe = redis::pipe();
+    pipe.hset(&key, "reason", serde_json::to_string(&request.reason)?)
+        .ignore()
+        .hincr(&key, "attempts", 1)
+        .ignore()
+        .hset(&key, "updated_at", chrono::Utc::now().to_rfc3339())
+        .ignore();
+    match outcome {
+        Ok(response) => {
+            pipe.hset(&key, "state", "done")
+                .ignore()
+                .hset(&key, "entries_removed", response.entries_removed)
+                .ignore()
+                .hset(&key, "users_touched", response.users_touched)
+                .ignore()
+                .hdel(&key, "error")
+                .ignore();
+        }
+        Err(e) => {
+            pipe.hset(&key, "state", "failed")
+                .ignore()
+                .hset(&key, "error", e.to_string())
+                .ignore();
+        }
+    }
+    pipe.expire(&key, PROGRESS_TTL_SECS).ignore();
+
+    let mut conn = pool.get().await?;
+    let _: () = pipe.query_async(&mut conn).await?;
+    Ok(())
+}
+
+async fn invalidate(request: &FeedCacheInvalidationRequest) -> Result<InvalidationResponse> {
+    let url = INVALIDATION_URL
+        .clone()
+        .context("ML_FEED_CACHE_INVALIDATION_URL is not set")?;
+
+    let mut call = reqwest::Client::new().post(url).json(request);
+    if let Some(token) = API_TOKEN.as_deref() {
+        call = call.bearer_auth(token);
+    }
+    let response = call
+        .send()
+        .await?
+        .error_for_status()
+        .context("Feed cache service rejected the invalidation")?;
+
+    // An empty body still means the video is gone
+    let body = response.bytes().await?;
+    if body.is_empty() {
+        return Ok(InvalidationResponse::default());
+    }
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// QStash job: asks the ML feed cache service to drop the video from every
+/// user's caches. Errors make QStash retry.
+#[utoipa::path(
+    post,
+    path = "/feed_cache_invalidation",
+    request_body = FeedCacheInvalidationRequest,
+    responses(
+        (status = 200, description = "Video removed from the feed caches"),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state))]
+pub async fn feed_cache_invalidation_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<FeedCacheInvalidationRequest>,
+) -> Result<(), ApiError> {
+    let outcome = invalidate(&request).await;
+    if let Err(e) = record_attempt(&state.yral_redis_store_dragonfly, &request, &outcome).await {
+        log::warn!(
+            "Failed to record feed cache invalidation for {}: {e}",
+            request.video_id
+        );
+    }
+
+    match outcome {
+        Ok(response) => {
+            log::info!(
+                "Feed cache invalidation for {} removed {} entries for {} users",
+                request.video_id,
+                response.entries_removed,
+                response.users_touched
+            );
+            Ok(())
+        }
+        Err(e) => Err(ApiError::Upstream(format!(
+            "Feed cache invalidation for {} failed: {e}",
+            request.video_id
+        ))),
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeedCacheInvalidationStatus {
+    pub video_id: String,
+    pub reason: Option<InvalidationReason>,
+    /// `done` or `failed`
+    pub state: Option<String>,
+    pub attempts: u64,
+    pub entries_removed: u64,
+    pub users_touched: u64,
+    /// Last failure, cleared once an attempt succeeds
+    pub error: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+fn parse_status(
+    video_id: &str,
+    mut fields: BTreeMap<String, String>,
+) -> FeedCacheInvalidationStatus {
+    let number = |fields: &BTreeMap<String, String>, field: &str| {
+        fields
+            .get(field)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    };
+
+    FeedCacheInvalidationStatus {
+        video_id: video_id.to_string(),
+        reason: fields
+            .get("reason")
+            .and_then(|reason| serde_json::from_str(reason).ok()),
+        attempts: number(&fields, "attempts"),
+        entries_removed: number(&fields, "entries_removed"),
+        users_touched: number(&fields, "users_touched"),
+        state: fields.remove("state"),
+        error: fields.remove("error"),
+        updated_at: fields.remove("updated_at"),
+    }
+}
+
+/// Outcome of a video's feed cache invalidation
+#[utoipa::path(
+    get,
+    path = "/feed-cache-invalidation/{video_id}",
+    params(
+        ("video_id" = String, Path, description = "Deleted or disapproved video ID")
+    ),
+    tag = "posts",
+    responses(
+        (status = 200, description = "Invalidation progress", body = FeedCacheInvalidationStatus),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "No invalidation recorded for this video", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_feed_cache_invalidation_status(
+    State(state): State<Arc<AppState>>,
+    Path(video_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<FeedCacheInvalidationStatus>, ApiError> {
+    require_operator(&headers)?;
+
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+    let fields: BTreeMap<String, String> = conn.hgetall(progress_key(&video_id)).await?;
+    if fields.is_empty() {
+        return Err(ApiError::NotFound(format!(
+            "No feed cache invalidation recorded for {video_id}"
+        )));
+    }
+    Ok(Json(parse_status(&video_id, fields)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        let fields = BTreeMap::from([
+            ("reason".to_string(), "\"disapproved\"".to_string()),
+            ("state".to_string(), "failed".to_string()),
+            ("attempts".to_string(), "2".to_string()),
+            ("error".to_string(), "timeout".to_string()),
+        ]);
+        let status = parse_status("v1", fields);
+        assert_eq!(status.reason, Some(InvalidationReason::Disapproved));
+        assert_eq!(status.state.as_deref(), Some("failed"));
+        assert_eq!(status.attempts, 2);
+        assert_eq!(status.entries_removed, 0);
+        assert_eq!(status.error.as_deref(), Some("timeout"));
+
+        let response: InvalidationResponse =
+            serde_json::from_str(r#"{"entries_removed":3}"#).unwrap();
+        assert_eq!(response.entries_removed, 3);
+        assert_eq!(response.users_touched, 0);
+    }
+}
@@ -0,0 +1,368 @@
+//! Hashtag counters and trending hashtags for the Explore tab.
+//!
+//! Every upload bumps an all-time counter per tag, an hourly bucket used for
+//! trending, and a per-tag sorted set of videos. Trending sums the hourly
+//! buckets of the window with a half-life decay so fresh activity wins.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    posts::search::{caption_from_params, is_visible, load_document, SearchDocument},
+    user::blocklist::{filter_blocked_publishers, parse_viewer},
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const COUNT_KEY: &str = "offchain:hashtags:count";
+const HOURLY_KEY_PREFIX: &str = "offchain:hashtags:hourly";
+const VIDEOS_KEY_PREFIX: &str = "offchain:hashtags:videos";
+
+const MAX_HASHTAGS_PER_VIDEO: usize = 30;
+const MAX_HASHTAG_LEN: usize = 64;
+/// Hourly buckets outlive the longest trending window
+const HOURLY_BUCKET_TTL_SECS: i64 = 8 * 24 * 60 * 60;
+const DEFAULT_WINDOW_HOURS: i64 = 24;
+const MAX_WINDOW_HOURS: i64 = 7 * 24;
+const TRENDING_HALF_LIFE_HOURS: f64 = 6.0;
+const DEFAULT_TRENDING_LIMIT: usize = 20;
+const MAX_TRENDING_LIMIT: usize = 100;
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 50;
+
+fn hourly_key(hour: i64) -> String {
+    format!("{HOURLY_KEY_PREFIX}:{hour}")
+}
+
+fn videos_key(tag: &str) -> String {
+    format!("{VIDEOS_KEY_PREFIX}:{tag}")
+}
+
+pub(crate) fn normalize_hashtag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    let valid = !tag.is_empty()
+        && tag.chars().count() <= MAX_HASHTAG_LEN
+        && tag.chars().all(|c| c.is_alphanumeric() || c == '_');
+    valid.then_some(tag)
+}
+
+/// `#tag` occurrences in free text, in order of appearance
+pub fn parse_caption_hashtags(caption: &str) -> Vec<String> {
+    caption
+        .split('#')
+        .skip(1)
+        .filter_map(|rest| {
+            let tag: String = rest
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+            normalize_hashtag(&tag)
+        })
+        .collect()
+}
+
+/// Hashtags from an upload payload: the explicit `hashtags` array when sent,
+/// plus any `#tags` in the caption. Deduplicated and capped.
+pub(crate) fn hashtags_from_params(params: &Value) -> Vec<String> {
+    let explicit = params
+        .get("hashtags")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter_map(normalize_hashtag);
+    let from_caption = caption_from_params(params)
+        .map(|caption| parse_caption_hashtags(&caption))
+        .unwrap_or_default();
+
+    let mut seen = BTreeSet::new();
+    explicit
+        .chain(from_caption)
+        .filter(|tag| seen.insert(tag.clone()))
+        .take(MAX_HASHTAGS_PER_VIDEO)
+        .collect()
+}
+
+/// Records an upload's hashtags. Returns the tags that were counted.
+pub async fn record_upload_hashtags(
+    pool: &Arc<DragonflyPool>,
+    video_id: &str,
+    raw_params: &Value,
+    uploaded_at: i64,
+) -> Result<Vec<String>> {
+    let tags = hashtags_from_params(raw_params);
+    if tags.is_empty() {
+        return Ok(tags);
+    }
+
+    // Redeliveries of the same upload must not inflate the counters
+    let mut conn = pool.get().await?;
+    let mut fresh = Vec::new();
+    for tag in &tags {
+        let added: i64 = conn.zadd(videos_key(tag), video_id, uploaded_at).await?;
+        if added > 0 {
+            fresh.push(tag.clone());
+        }
+    }
+    if fresh.is_empty() {
+        return Ok(fresh);
+    }
+
+    let bucket = hourly_key(uploaded_at / 3600);
+    let mut pipe = redis::pipe();
+    for tag in &fresh {
+        pipe.zincr(COUNT_KEY, tag, 1).ignore();
+        pipe.zincr(&bucket, tag, 1).ignore();
+    }
+    pipe.expire(&bucket, HOURLY_BUCKET_TTL_SECS).ignore();
+    let _: () = pipe.query_async(&mut conn).await?;
+
+    Ok(fresh)
+}
+
+/// Decay weight for the bucket `hours_ago` hours before the current one
+fn bucket_weight(hours_ago: i64) -> f64 {
+    0.5f64.powf(hours_ago as f64 / TRENDING_HALF_LIFE_HOURS)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TrendingHashtagsParams {
+    /// Number of hashtags to return (max 100)
+    pub limit: Option<usize>,
+    /// Trending window in hours (default 24, max 168)
+    pub window_hours: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TrendingHashtag {
+    pub tag: String,
+    /// Time-decayed upload count over the window
+    pub score: f64,
+    /// All-time number of videos with this tag
+    pub video_count: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TrendingHashtagsResponse {
+    pub hashtags: Vec<TrendingHashtag>,
+    pub window_hours: i64,
+}
+
+/// Trending hashtags by decayed recent upload volume
+#[utoipa::path(
+    get,
+    path = "/hashtags/trending",
+    params(TrendingHashtagsParams),
+    tag = "posts",
+    responses(
+        (status = 200, description = "Trending hashtags", body = TrendingHashtagsResponse),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn get_trending_hashtags(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TrendingHashtagsParams>,
+) -> Result<Json<TrendingHashtagsResponse>, ApiError> {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_TRENDING_LIMIT)
+        .clamp(1, MAX_TRENDING_LIMIT);
+    let window_hours = params
+        .window_hours
+        .unwrap_or(DEFAULT_WINDOW_HOURS)
+        .clamp(1, MAX_WINDOW_HOURS);
+
+    let current_hour = chrono::Utc::now().timestamp() / 3600;
+    let keys: Vec<String> = (0..window_hours)
+        .map(|hours_ago| hourly_key(current_hour - hours_ago))
+        .collect();
+    let weights: Vec<f64> = (0..window_hours).map(bucket_weight).collect();
+
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+    let mut scored: Vec<(String, f64)> = redis::cmd("ZUNION")
+        .arg(keys.len())
+        .arg(&keys)
+        .arg("WEIGHTS")
+        .arg(&weights)
+        .arg("WITHSCORES")
+        .query_async(&mut conn)
+        .await?;
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(limit);
+
+    let counts: Vec<Option<f64>> = if scored.is_empty() {
+        Vec::new()
+    } else {
+        redis::cmd("ZMSCORE")
+            .arg(COUNT_KEY)
+            .arg(scored.iter().map(|(tag, _)| tag).collect::<Vec<_>>())
+            .query_async(&mut conn)
+            .await?
+    };
+
+    let hashtags = scored
+        .into_iter()
+        .zip(counts)
+        .map(|((tag, score), count)| TrendingHashtag {
+            tag,
+            score,
+            video_count: count.unwrap_or(0.0) as u64,
+        })
+        .collect();
+
+    Ok(Json(TrendingHashtagsResponse {
+        hashtags,
+        window_hours,
+    }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct HashtagVideosParams {
+    /// Zero-based offset into the tag's videos, newest first
+    pub offset: Option<usize>,
+    /// Page size (max 50)
+    pub limit: Option<usize>,
+    /// Include NSFW videos (default false)
+    pub include_nsfw: Option<bool>,
+    /// Principal of the viewer; videos from creators they blocked are dropped
+    pub viewer_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HashtagVideo {
+    pub video_id: String,
+    pub post_id: Option<String>,
+    pub publisher_user_id: Option<String>,
+    pub title: Option<String>,
+    /// Unix timestamp of the upload
+    pub uploaded_at: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HashtagVideosResponse {
+    pub tag: String,
+    pub video_count: u64,
+    pub videos: Vec<HashtagVideo>,
+    /// Offset for the next page, absent on the last page
+    pub next_offset: Option<usize>,
+}
+
+/// List videos tagged with a hashtag, newest first
+#[utoipa::path(
+    get,
+    path = "/hashtags/{tag}",
+    params(
+        ("tag" = String, Path, description = "Hashtag, with or without the leading #"),
+        HashtagVideosParams
+    ),
+    tag = "posts",
+    responses(
+        (status = 200, description = "Videos for the hashtag", body = HashtagVideosResponse),
+        (status = 400, description = "Invalid hashtag", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn get_hashtag_videos(
+    State(state): State<Arc<AppState>>,
+    Path(tag): Path<String>,
+    Query(params): Query<HashtagVideosParams>,
+) -> Result<Json<HashtagVideosResponse>, ApiError> {
+    let tag = normalize_hashtag(&tag)
+        .ok_or_else(|| ApiError::InvalidRequest(format!("Invalid hashtag: {tag}")))?;
+    let offset = params.offset.unwrap_or(0);
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let include_nsfw = params.include_nsfw.unwrap_or(false);
+    let viewer = parse_viewer(params.viewer_id.as_deref())?;
+    let pool = &state.yral_redis_store_dragonfly;
+
+    let mut conn = pool.get().await?;
+    let video_count: u64 = conn.zcard(videos_key(&tag)).await?;
+    let page: Vec<(String, i64)> = conn
+        .zrevrange_withscores(
+            videos_key(&tag),
+            offset as isize,
+            (offset + limit) as isize - 1,
+        )
+        .await?;
+
+    // Hidden and blocked videos are dropped from the page rather than backfilled, so a
+    // page can come back short while `next_offset` still advances
+    let mut videos = Vec::with_capacity(page.len());
+    for (video_id, uploaded_at) in &page {
+        let doc = load_document(pool, video_id)
+            .await?
+            .unwrap_or_else(|| SearchDocument {
+                video_id: video_id.clone(),
+                created_at: *uploaded_at,
+                ..Default::default()
+            });
+        if !is_visible(&state, &doc, include_nsfw).await? {
+            continue;
+        }
+        videos.push(HashtagVideo {
+            video_id: doc.video_id,
+            post_id: doc.post_id,
+            publisher_user_id: doc.publisher_user_id,
+            title: doc.title,
+            uploaded_at: *uploaded_at,
+        });
+    }
+
+    let videos = filter_blocked_publishers(pool, viewer.as_ref(), videos, |video| {
+        video.publisher_user_id.as_deref()
+    })
+    .await?;
+
+    let next_offset = ((offset + page.len()) < video_count as usize).then_some(offset + limit);
+
+    Ok(Json(HashtagVideosResponse {
+        tag,
+        video_count,
+        videos,
+        next_offset,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashtag_parsing() {
+        assert_eq!(
+            parse_caption_hashtags("Sunset #Goa #beach_life! #goa #"),
+            vec!["goa", "beach_life", "goa"]
+        );
+        assert_eq!(normalize_hashtag("  #Travel "), Some("travel".to_string()));
+        assert_eq!(normalize_hashtag("#"), None);
+        assert_eq!(normalize_hashtag("two words"), None);
+
+        let params = serde_json::json!({
+            "caption": "Morning run #fitness #Goa",
+            "hashtags": ["#goa", "running"],
+        });
+        assert_eq!(
+            hashtags_from_params(&params),
+            vec!["goa", "running", "fitness"]
+        );
+
+        assert_eq!(bucket_weight(0), 1.0);
+        assert!((bucket_weight(6) - 0.5).abs() < 1e-9);
+    }
+}
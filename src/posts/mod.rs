@@ -21,10 +21,24 @@ use crate::posts::{
 };
 use crate::{app_state::AppState, posts::report_post::ReportPostRequestV3};
 
+#[cfg(not(feature = "local-bin"))]
+pub mod analytics;
+#[cfg(not(feature = "local-bin"))]
+pub mod cleanup;
 pub mod delete_post;
+#[cfg(not(feature = "local-bin"))]
+pub mod drafts;
+#[cfg(not(feature = "local-bin"))]
+pub mod feed_cache_invalidation;
+#[cfg(not(feature = "local-bin"))]
+pub mod hashtags;
 pub mod nsfw_query;
 mod queries;
 pub mod report_post;
+#[cfg(not(feature = "local-bin"))]
+pub mod search;
+#[cfg(not(feature = "local-bin"))]
+pub mod share_links;
 pub mod types;
 mod utils;
 mod verify;
@@ -46,6 +60,18 @@ pub fn posts_router(state: Arc<AppState>) -> OpenApiRouter {
     router = verified_route!(router, handle_delete_post, DeletePostRequest, state);
     router = verified_route!(router, handle_report_post_v2, ReportPostRequestV2, state);
 
+    #[cfg(not(feature = "local-bin"))]
+    {
+        router = router
+            .routes(routes!(hashtags::get_trending_hashtags))
+            .routes(routes!(hashtags::get_hashtag_videos))
+            .routes(routes!(share_links::create_share_link))
+            .routes(routes!(share_links::resolve_share_link))
+            .routes(routes!(drafts::list_drafts))
+            .routes(routes!(drafts::delete_draft))
+            .routes(routes!(drafts::publish_draft));
+    }
+
     router.with_state(state)
 }
 
@@ -56,9 +82,20 @@ pub fn posts_router_v2(state: Arc<AppState>) -> OpenApiRouter {
     router = verified_route!(router, handle_delete_post_v2, DeletePostRequestV2, state);
     router = verified_route!(router, handle_report_post_v3, ReportPostRequestV3, state);
 
-    router
-        .routes(routes!(nsfw_query::get_nsfw_data))
-        .with_state(state)
+    router = router.routes(routes!(nsfw_query::get_nsfw_data));
+
+    #[cfg(not(feature = "local-bin"))]
+    {
+        router = router
+            .routes(routes!(search::search_posts))
+            .routes(routes!(analytics::get_post_analytics))
+            .routes(routes!(cleanup::get_post_cleanup_status))
+            .routes(routes!(
+                feed_cache_invalidation::get_feed_cache_invalidation_status
+            ));
+    }
+
+    router.with_state(state)
 }
 
 #[derive(Serialize, Deserialize, Clone, ToSchema, Debug)]
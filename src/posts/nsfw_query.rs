@@ -11,7 +11,11 @@ use serde::{Deserialize, Serialize};
 use tracing::instrument;
 use utoipa::ToSchema;
 
-use crate::{app_state::AppState, kvrocks::KvrocksClient, AppError};
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    kvrocks::{tables, KvrocksClient},
+};
 
 #[derive(Serialize, Deserialize, ToSchema, Debug)]
 pub struct NsfwQueryResponse {
@@ -27,15 +31,15 @@ pub struct NsfwQueryResponse {
     tag = "posts",
     responses(
         (status = 200, description = "NSFW data found", body = NsfwQueryResponse),
-        (status = 404, description = "Video not found"),
-        (status = 500, description = "Internal server error"),
+        (status = 404, description = "Video not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
     )
 )]
 #[instrument(skip(state))]
 pub async fn get_nsfw_data(
     Path(video_id): Path<String>,
     State(state): State<Arc<AppState>>,
-) -> Result<impl IntoResponse, AppError> {
+) -> Result<impl IntoResponse, ApiError> {
     let nsfw_probability = query_nsfw(&state.kvrocks_client, &video_id).await?;
 
     match nsfw_probability {
@@ -45,18 +49,15 @@ pub async fn get_nsfw_data(
                 nsfw_probability: Some(probability),
             }),
         )),
-        None => Ok((
-            StatusCode::NOT_FOUND,
-            Json(NsfwQueryResponse {
-                nsfw_probability: None,
-            }),
-        )),
+        None => Err(ApiError::NotFound(format!(
+            "No NSFW data for video {video_id}"
+        ))),
     }
 }
 
 #[instrument(skip(kvrocks_client))]
 async fn query_nsfw(kvrocks_client: &KvrocksClient, video_id: &str) -> Result<Option<f32>, Error> {
-    let nsfw_data = kvrocks_client.get_video_nsfw(video_id).await?;
+    let nsfw_data = kvrocks_client.get(&tables::VIDEO_NSFW, video_id).await?;
     let probability = nsfw_data.and_then(|data| data.probability);
     Ok(probability)
 }
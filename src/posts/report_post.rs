@@ -13,6 +13,7 @@ use utoipa::ToSchema;
 use crate::{
     app_state::AppState,
     consts::{GOOGLE_CHAT_REPORT_SPACE_URL, ML_FEED_SERVER_GRPC_URL},
+    error::{ApiError, ApiErrorBody},
     offchain_service::send_message_gchat,
     utils::grpc_clients::ml_feed::{ml_feed_client::MlFeedClient, VideoReportRequestV3},
 };
@@ -105,13 +106,13 @@ impl From<ReportPostRequestV2> for ReportPostRequestV3 {
     tag = "posts",
     responses(
         (status = 200, description = "Report post success"),
-        (status = 500, description = "Internal server error"),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
     )
 )]
 pub async fn handle_report_post_v2(
     State(state): State<Arc<AppState>>,
     Json(verified_request): Json<VerifiedPostRequest<ReportPostRequestV2>>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let request_body = verified_request.request.request_body;
 
     repost_post_common_impl(state, request_body.into())
@@ -119,10 +120,7 @@ pub async fn handle_report_post_v2(
         .map_err(|e| {
             log::error!("Failed to report post: {e}");
 
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to report post: {e}"),
-            )
+            ApiError::Internal(format!("Failed to report post: {e}"))
         })?;
 
     Ok((StatusCode::OK, "Post reported".to_string()))
@@ -136,13 +134,13 @@ pub async fn handle_report_post_v2(
     tag = "posts",
     responses(
         (status = 200, description = "Report post success"),
-        (status = 500, description = "Internal server error"),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
     )
 )]
 pub async fn handle_report_post_v3(
     State(state): State<Arc<AppState>>,
     Json(verified_request): Json<VerifiedPostRequest<ReportPostRequestV3>>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let request_body = verified_request.request.request_body;
 
     repost_post_common_impl(state, request_body)
@@ -150,37 +148,36 @@ pub async fn handle_report_post_v3(
         .map_err(|e| {
             log::error!("Failed to report post: {e}");
 
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to report post: {e}"),
-            )
+            ApiError::Internal(format!("Failed to report post: {e}"))
         })?;
 
     Ok((StatusCode::OK, "Post reported".to_string()))
 }
 
+/// Forwards a report to the ML feed server
+#[utoipa::path(
+    post,
+    path = "/report_post",
+    request_body = ReportPostRequestV3,
+    responses(
+        (status = 200, description = "Report forwarded", body = String),
+        (status = 502, description = "ML feed server error", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    ),
+    tag = "qstash"
+)]
 pub async fn qstash_report_post(
     State(_state): State<Arc<AppState>>,
     Json(payload): Json<ReportPostRequestV3>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let tls_config = ClientTlsConfig::new().with_webpki_roots();
 
     let channel = Channel::from_static(ML_FEED_SERVER_GRPC_URL)
         .tls_config(tls_config)
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to create channel: {e}"),
-            )
-        })?
+        .map_err(|e| ApiError::Internal(format!("Failed to create channel: {e}")))?
         .connect()
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to connect to ML feed server: {e}"),
-            )
-        })?;
+        .map_err(|e| ApiError::Upstream(format!("Failed to connect to ML feed server: {e}")))?;
 
     let mut client = MlFeedClient::new(channel);
 
@@ -193,10 +190,7 @@ pub async fn qstash_report_post(
     client.report_video_v3(request).await.map_err(|e| {
         log::error!("Failed to report video: {e}");
 
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to report video: {e}"),
-        )
+        ApiError::Upstream(format!("Failed to report video: {e}"))
     })?;
 
     Ok((StatusCode::OK, "Report post success".to_string()))
@@ -266,6 +260,11 @@ pub async fn repost_post_common_impl(
         ]
     });
 
+    #[cfg(not(feature = "local-bin"))]
+    if let Err(e) = crate::moderation::reports::process_report(&state, &payload).await {
+        log::error!("Failed to add report to triage queue: {e:?}");
+    }
+
     let res = send_message_gchat(&state, &GOOGLE_CHAT_REPORT_SPACE_URL, data).await;
     if res.is_err() {
         log::error!("Error sending data to Google Chat: {res:?}");
@@ -274,7 +273,7 @@ pub async fn repost_post_common_impl(
     #[cfg(not(any(feature = "local-bin", feature = "use-local-agent")))]
     {
         let qstash_client = state.qstash_client.clone();
-        qstash_client.publish_report_post(payload).await?;
+        qstash_client.publish_job(&payload).await?;
     }
 
     Ok(())
@@ -0,0 +1,466 @@
+//! Full-text video search over captions, hashtags and transcripts.
+//!
+//! A simple inverted index lives in Dragonfly: one sorted set of video ids
+//! per term (scored by index time) plus a JSON document per video. Queries
+//! AND their terms, drop NSFW and deleted videos, and rank by recency decay
+//! blended with an engagement counter fed from view events.
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    events::types::VideoUploadSuccessfulPayload,
+    kvrocks::tables,
+    posts::hashtags::hashtags_from_params,
+    user::blocklist::{filter_blocked_publishers, parse_viewer},
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const TERM_KEY_PREFIX: &str = "offchain:search:term";
+const DOC_KEY_PREFIX: &str = "offchain:search:doc";
+const ENGAGEMENT_KEY: &str = "offchain:search:engagement";
+
+const MIN_TERM_LEN: usize = 2;
+const MAX_TERMS_PER_DOC: usize = 256;
+const MAX_QUERY_TERMS: usize = 8;
+/// Most recent postings read per query term
+const CANDIDATES_PER_TERM: isize = 2000;
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 50;
+
+const RECENCY_HALF_LIFE_SECS: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+const ENGAGEMENT_WEIGHT: f64 = 0.25;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchDocument {
+    pub video_id: String,
+    pub post_id: Option<String>,
+    pub publisher_user_id: Option<String>,
+    pub title: Option<String>,
+    #[serde(default)]
+    pub hashtags: Vec<String>,
+    #[serde(default)]
+    pub has_transcript: bool,
+    #[serde(default)]
+    pub is_nsfw: bool,
+    pub created_at: i64,
+    #[serde(default)]
+    pub terms: Vec<String>,
+}
+
+fn term_key(term: &str) -> String {
+    format!("{TERM_KEY_PREFIX}:{term}")
+}
+
+fn doc_key(video_id: &str) -> String {
+    format!("{DOC_KEY_PREFIX}:{video_id}")
+}
+
+/// Lowercased alphanumeric runs, deduplicated, in first-seen order
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(|token| token.to_lowercase())
+        .filter(|token| token.chars().count() >= MIN_TERM_LEN)
+        .filter(|token| seen.insert(token.clone()))
+        .collect()
+}
+
+/// The caption is not part of the typed upload payload yet, so it is read
+/// from the raw params when the client sends it.
+pub(crate) fn caption_from_params(params: &Value) -> Option<String> {
+    ["caption", "title", "description"]
+        .iter()
+        .find_map(|field| params.get(*field).and_then(Value::as_str))
+        .map(str::to_string)
+        .filter(|caption| !caption.trim().is_empty())
+}
+
+/// Title and hashtag terms first, then the transcript, then terms kept from
+/// earlier indexing, so the cap cuts the least relevant ones
+fn document_terms(doc: &SearchDocument, transcript: Option<&str>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let title = doc.title.as_deref().map(tokenize).unwrap_or_default();
+    let hashtags = doc.hashtags.iter().flat_map(|tag| tokenize(tag));
+    let transcript = transcript.map(tokenize).unwrap_or_default();
+    title
+        .into_iter()
+        .chain(hashtags)
+        .chain(transcript)
+        .chain(doc.terms.iter().cloned())
+        .filter(|term| seen.insert(term.clone()))
+        .take(MAX_TERMS_PER_DOC)
+        .collect()
+}
+
+pub(crate) async fn load_document(
+    pool: &Arc<DragonflyPool>,
+    video_id: &str,
+) -> Result<Option<SearchDocument>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.get(doc_key(video_id)).await?;
+    Ok(payload.and_then(|p| serde_json::from_str(&p).ok()))
+}
+
+/// Writes the document and its postings, dropping postings for terms in
+/// `previous_terms` that the new term set no longer has
+async fn write_document(
+    pool: &Arc<DragonflyPool>,
+    doc: &SearchDocument,
+    previous_terms: &[String],
+) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let mut pipe = redis::pipe();
+    pipe.set(doc_key(&doc.video_id), serde_json::to_string(doc)?)
+        .ignore();
+    for term in previous_terms
+        .iter()
+        .filter(|term| !doc.terms.contains(term))
+    {
+        pipe.zrem(term_key(term), &doc.video_id).ignore();
+    }
+    for term in &doc.terms {
+        pipe.zadd(term_key(term), &doc.video_id, doc.created_at)
+            .ignore();
+    }
+    let _: () = pipe.query_async(&mut conn).await?;
+    Ok(())
+}
+
+pub async fn remove_from_index(pool: &Arc<DragonflyPool>, video_id: &str) -> Result<()> {
+    let Some(doc) = load_document(pool, video_id).await? else {
+        return Ok(());
+    };
+
+    let mut conn = pool.get().await?;
+    let mut pipe = redis::pipe();
+    for term in &doc.terms {
+        pipe.zrem(term_key(term), video_id).ignore();
+    }
+    pipe.del(doc_key(video_id)).ignore();
+    pipe.zrem(ENGAGEMENT_KEY, video_id).ignore();
+    let _: () = pipe.query_async(&mut conn).await?;
+    Ok(())
+}
+
+/// Indexes a `video_upload_successful` event. `raw_params` is the untyped
+/// payload, which may carry the caption and hashtags.
+pub async fn index_upload(
+    pool: &Arc<DragonflyPool>,
+    params: &VideoUploadSuccessfulPayload,
+    raw_params: &Value,
+) -> Result<()> {
+    let mut doc = load_document(pool, &params.video_id)
+        .await?
+        .unwrap_or_else(|| SearchDocument {
+            video_id: params.video_id.clone(),
+            created_at: chrono::Utc::now().timestamp(),
+            ..Default::default()
+        });
+
+    doc.post_id = Some(params.post_id.clone());
+    doc.publisher_user_id = Some(params.publisher_user_id.to_text());
+    doc.title = caption_from_params(raw_params).or(doc.title);
+    let mut hashtags: BTreeSet<String> = doc.hashtags.drain(..).collect();
+    hashtags.extend(hashtags_from_params(raw_params));
+    doc.hashtags = hashtags.into_iter().collect();
+    doc.is_nsfw |= params.is_nsfw;
+    let previous_terms = std::mem::replace(&mut doc.terms, document_terms(&doc, None));
+
+    write_document(pool, &doc, &previous_terms).await
+}
+
+/// Adds transcript terms to a video's document
+pub async fn index_transcript(
+    pool: &Arc<DragonflyPool>,
+    video_id: &str,
+    transcript: &str,
+) -> Result<()> {
+    let mut doc = load_document(pool, video_id)
+        .await?
+        .unwrap_or_else(|| SearchDocument {
+            video_id: video_id.to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+            ..Default::default()
+        });
+
+    doc.has_transcript = true;
+    let previous_terms = std::mem::replace(&mut doc.terms, document_terms(&doc, Some(transcript)));
+
+    write_document(pool, &doc, &previous_terms).await
+}
+
+pub async fn record_engagement(
+    pool: &Arc<DragonflyPool>,
+    video_id: &str,
+    delta: f64,
+) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = conn.zincr(ENGAGEMENT_KEY, video_id, delta).await?;
+    Ok(())
+}
+
+fn rank_score(created_at: i64, engagement: f64, now: i64) -> f64 {
+    let age = (now - created_at).max(0) as f64;
+    let recency = 0.5f64.powf(age / RECENCY_HALF_LIFE_SECS);
+    recency + ENGAGEMENT_WEIGHT * (1.0 + engagement.max(0.0)).ln()
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SearchParams {
+    /// Search query of at most 8 terms
+    pub q: String,
+    /// Zero-based result offset
+    pub offset: Option<usize>,
+    /// Page size (max 50)
+    pub limit: Option<usize>,
+    /// Include NSFW videos (default false)
+    pub include_nsfw: Option<bool>,
+    /// Principal of the viewer; videos from creators they blocked are dropped
+    pub viewer_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchResultItem {
+    pub video_id: String,
+    pub post_id: Option<String>,
+    pub publisher_user_id: Option<String>,
+    pub title: Option<String>,
+    pub hashtags: Vec<String>,
+    pub created_at: i64,
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResultItem>,
+    /// Offset for the next page, absent on the last page
+    pub next_offset: Option<usize>,
+}
+
+/// Candidate ids matching every query term
+async fn matching_candidates(pool: &Arc<DragonflyPool>, terms: &[String]) -> Result<Vec<String>> {
+    let mut conn = pool.get().await?;
+    let mut candidates: Option<HashSet<String>> = None;
+    for term in terms {
+        let postings: Vec<String> = conn
+            .zrevrange(term_key(term), 0, CANDIDATES_PER_TERM - 1)
+            .await?;
+        let postings: HashSet<String> = postings.into_iter().collect();
+        candidates = Some(match candidates {
+            Some(existing) => existing.intersection(&postings).cloned().collect(),
+            None => postings,
+        });
+        if candidates.as_ref().is_some_and(HashSet::is_empty) {
+            break;
+        }
+    }
+    Ok(candidates.unwrap_or_default().into_iter().collect())
+}
+
+/// Whether a ranked candidate may be shown; deleted videos are evicted from
+/// the index as they are found
+pub(crate) async fn is_visible(
+    state: &AppState,
+    doc: &SearchDocument,
+    include_nsfw: bool,
+) -> Result<bool> {
+    if state
+        .kvrocks_client
+        .get(&tables::VIDEO_DELETED, &doc.video_id)
+        .await?
+        .is_some()
+    {
+        remove_from_index(&state.yral_redis_store_dragonfly, &doc.video_id).await?;
+        return Ok(false);
+    }
+
+    if include_nsfw {
+        return Ok(true);
+    }
+    if doc.is_nsfw {
+        return Ok(false);
+    }
+    let verdict = state
+        .kvrocks_client
+        .get(&tables::VIDEO_NSFW, &doc.video_id)
+        .await?;
+    Ok(!verdict.is_some_and(|v| v.is_nsfw))
+}
+
+/// Search videos by caption, hashtags and transcript
+#[utoipa::path(
+    get,
+    path = "/search",
+    params(SearchParams),
+    tag = "posts",
+    responses(
+        (status = 200, description = "Search results", body = SearchResponse),
+        (status = 400, description = "Empty, invalid or too long query", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn search_posts(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<SearchResponse>, ApiError> {
+    let terms = tokenize(&params.q);
+    if terms.is_empty() {
+        return Err(ApiError::InvalidRequest(
+            "Query must contain at least one searchable term".to_string(),
+        ));
+    }
+    if terms.len() > MAX_QUERY_TERMS {
+        return Err(ApiError::InvalidRequest(format!(
+            "Query has {} terms, at most {MAX_QUERY_TERMS} are allowed",
+            terms.len()
+        )));
+    }
+
+    let offset = params.offset.unwrap_or(0);
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let include_nsfw = params.include_nsfw.unwrap_or(false);
+    let viewer = parse_viewer(params.viewer_id.as_deref())?;
+    let pool = &state.yral_redis_store_dragonfly;
+
+    let candidates = matching_candidates(pool, &terms).await?;
+    if candidates.is_empty() {
+        return Ok(Json(SearchResponse {
+            results: Vec::new(),
+            next_offset: None,
+        }));
+    }
+
+    let mut conn = pool.get().await?;
+    let doc_keys: Vec<String> = candidates.iter().map(|id| doc_key(id)).collect();
+    let docs: Vec<Option<String>> = redis::cmd("MGET")
+        .arg(&doc_keys)
+        .query_async(&mut conn)
+        .await?;
+    let engagement: Vec<Option<f64>> = redis::cmd("ZMSCORE")
+        .arg(ENGAGEMENT_KEY)
+        .arg(&candidates)
+        .query_async(&mut conn)
+        .await?;
+    let engagement: HashMap<&str, f64> = candidates
+        .iter()
+        .zip(engagement)
+        .map(|(id, score)| (id.as_str(), score.unwrap_or(0.0)))
+        .collect();
+
+    let now = chrono::Utc::now().timestamp();
+    let mut ranked: Vec<(f64, SearchDocument)> = docs
+        .into_iter()
+        .flatten()
+        .filter_map(|payload| serde_json::from_str::<SearchDocument>(&payload).ok())
+        .map(|doc| {
+            let engagement = engagement
+                .get(doc.video_id.as_str())
+                .copied()
+                .unwrap_or(0.0);
+            (rank_score(doc.created_at, engagement, now), doc)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let ranked = filter_blocked_publishers(pool, viewer.as_ref(), ranked, |(_, doc)| {
+        doc.publisher_user_id.as_deref()
+    })
+    .await?;
+
+    // Visibility needs per-video lookups, so filter lazily up to the page end
+    let mut visible = Vec::new();
+    let mut exhausted = true;
+    for (score, doc) in ranked {
+        if visible.len() > offset + limit {
+            exhausted = false;
+            break;
+        }
+        if is_visible(&state, &doc, include_nsfw).await? {
+            visible.push((score, doc));
+        }
+    }
+
+    let has_more = !exhausted || visible.len() > offset + limit;
+    let results = visible
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|(score, doc)| SearchResultItem {
+            video_id: doc.video_id,
+            post_id: doc.post_id,
+            publisher_user_id: doc.publisher_user_id,
+            title: doc.title,
+            hashtags: doc.hashtags,
+            created_at: doc.created_at,
+            score,
+        })
+        .collect();
+
+    Ok(Json(SearchResponse {
+        results,
+        next_offset: has_more.then_some(offset + limit),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_and_rank() {
+        assert_eq!(
+            tokenize("Sunset at the BEACH, sunset! #goa a"),
+            vec!["sunset", "at", "the", "beach", "goa"]
+        );
+
+        let now = 1_000_000_000;
+        // Newer wins at equal engagement, engagement can outweigh age
+        assert!(rank_score(now, 0.0, now) > rank_score(now - 86_400, 0.0, now));
+        assert!(rank_score(now - 7 * 86_400, 1000.0, now) > rank_score(now, 0.0, now));
+    }
+
+    #[test]
+    fn test_document_terms_keep_document_order() {
+        let doc = SearchDocument {
+            title: Some("Zebra crossing".to_string()),
+            hashtags: vec!["yak".to_string()],
+            terms: vec!["aardvark".to_string(), "zebra".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            document_terms(&doc, Some("walrus zebra")),
+            vec!["zebra", "crossing", "yak", "walrus", "aardvark"]
+        );
+
+        let long_title = (0..MAX_TERMS_PER_DOC + 10)
+            .map(|i| format!("z{i}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let doc = SearchDocument {
+            title: Some(long_title),
+            ..Default::default()
+        };
+        let terms = document_terms(&doc, None);
+        assert_eq!(terms.len(), MAX_TERMS_PER_DOC);
+        assert_eq!(terms[0], "z0");
+    }
+}
@@ -0,0 +1,338 @@
+//! Short share links with attribution.
+//!
+//! A share link maps a short code to a post, its video and the principal who
+//! shared it. Sharing the same post twice returns the same code. Opening a
+//! link streams a `video_shared_opened` event to the warehouse. Only the
+//! first open by each signed-in viewer other than the sharer advances the
+//! sharer's daily missions; anonymous opens are recorded but never credited,
+//! so progress can't be farmed by replaying the URL. The response also carries the sharer's
+//! referral code, so a viewer who signs up from the link is attributed to
+//! the sharer by the referrals pipeline.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use candid::Principal;
+use rand::Rng;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+use yral_canisters_client::user_post_service::{Post, Result2, UserPostService};
+
+use crate::{
+    app_state::AppState,
+    canister::agent_pool,
+    consts::USER_POST_SERVICE_CANISTER_ID,
+    error::{ApiError, ApiErrorBody},
+    events::{event::Event, warehouse_events::WarehouseEvent},
+    referrals::store::get_or_create_code,
+    utils::delegated_identity::{
+        delegated_identity_wire_from_headers, verify_delegated_identity_request,
+    },
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const KEY_PREFIX: &str = "offchain:share_links";
+pub const SHARE_OPENED_EVENT: &str = "video_shared_opened";
+const CODE_LEN: usize = 10;
+/// Same alphabet as referral codes: no 0/O or 1/I
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const MAX_CODE_ATTEMPTS: u32 = 5;
+const SHARE_LINK_TTL_SECS: u64 = 90 * 24 * 60 * 60;
+
+fn link_key(code: &str) -> String {
+    format!("{KEY_PREFIX}:code:{code}")
+}
+
+fn sharer_post_key(sharer: &Principal, post_id: &str) -> String {
+    format!("{KEY_PREFIX}:sharer:{sharer}:{post_id}")
+}
+
+fn opens_key(code: &str) -> String {
+    format!("{KEY_PREFIX}:opens:{code}")
+}
+
+/// Viewers already credited to the sharer for this link
+fn credited_viewers_key(code: &str) -> String {
+    format!("{KEY_PREFIX}:credited:{code}")
+}
+
+fn generate_code() -> String {
+    let mut rng = rand::rng();
+    (0..CODE_LEN)
+        .map(|_| CODE_ALPHABET[rng.random_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+fn normalize_code(code: &str) -> Option<String> {
+    let code = code.trim().to_ascii_uppercase();
+    (code.len() == CODE_LEN && code.bytes().all(|byte| CODE_ALPHABET.contains(&byte)))
+        .then_some(code)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ShareLink {
+    pub code: String,
+    pub post_id: String,
+    pub video_id: String,
+    pub sharer_principal: String,
+    /// Sharer's referral code, passed on signup for attribution
+    pub referral_code: Option<String>,
+    /// Unix timestamp in seconds
+    pub created_at: i64,
+}
+
+async fn load_link(pool: &Arc<DragonflyPool>, code: &str) -> Result<Option<ShareLink>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.get(link_key(code)).await?;
+    payload
+        .map(|payload| serde_json::from_str(&payload).map_err(Into::into))
+        .transpose()
+}
+
+/// The sharer's existing link for the post, or a new one
+async fn get_or_create_link(
+    pool: &Arc<DragonflyPool>,
+    sharer: &Principal,
+    post_id: &str,
+    video_id: &str,
+    referral_code: Option<String>,
+) -> Result<ShareLink> {
+    let mut conn = pool.get().await?;
+    let existing: Option<String> = conn.get(sharer_post_key(sharer, post_id)).await?;
+    if let Some(code) = existing {
+        if let Some(link) = load_link(pool, &code).await? {
+            return Ok(link);
+        }
+    }
+
+    for _ in 0..MAX_CODE_ATTEMPTS {
+        let link = ShareLink {
+            code: generate_code(),
+            post_id: post_id.to_string(),
+            video_id: video_id.to_string(),
+            sharer_principal: sharer.to_text(),
+            referral_code: referral_code.clone(),
+            created_at: chrono::Utc::now().timestamp(),
+        };
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(link_key(&link.code))
+            .arg(serde_json::to_string(&link)?)
+            .arg("NX")
+            .arg("EX")
+            .arg(SHARE_LINK_TTL_SECS)
+            .query_async(&mut conn)
+            .await?;
+        if claimed.is_none() {
+            continue;
+        }
+        let _: () = conn
+            .set_ex(
+                sharer_post_key(sharer, post_id),
+                &link.code,
+                SHARE_LINK_TTL_SECS,
+            )
+            .await?;
+        return Ok(link);
+    }
+
+    anyhow::bail!("Could not allocate a share code for post {post_id}")
+}
+
+/// Viewer principal when the opener sent a delegated identity; anonymous
+/// opens are still recorded but never credited
+async fn optional_viewer(state: &AppState, headers: &HeaderMap) -> Option<Principal> {
+    let wire = delegated_identity_wire_from_headers(headers).ok()?;
+    match verify_delegated_identity_request(state, wire, headers).await {
+        Ok(user_info) => Some(user_info.user_principal),
+        Err(e) => {
+            log::debug!("Ignoring invalid identity on share link open: {e}");
+            None
+        }
+    }
+}
+
+/// Create (or fetch) the caller's share link for a post
+#[utoipa::path(
+    post,
+    path = "/{post_id}/share-link",
+    params(
+        ("post_id" = String, Path, description = "Post ID"),
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the sharer")
+    ),
+    tag = "posts",
+    responses(
+        (status = 200, description = "Share link", body = ShareLink),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 404, description = "Post not found", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn create_share_link(
+    State(state): State<Arc<AppState>>,
+    Path(post_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<ShareLink>, ApiError> {
+    let wire = delegated_identity_wire_from_headers(&headers)
+        .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+    let user_info = verify_delegated_identity_request(&state, wire, &headers)
+        .await
+        .map_err(|e| ApiError::Unauthorized(format!("Invalid delegated identity: {e}")))?;
+    let sharer = user_info.user_principal;
+    crate::middleware::set_user_context(sharer);
+
+    let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &state.agent);
+    let Result2::Ok(Post { video_uid, .. }) = agent_pool::query(
+        *USER_POST_SERVICE_CANISTER_ID,
+        "get_individual_post_details_by_id",
+        || user_post_service.get_individual_post_details_by_id(post_id.clone()),
+    )
+    .await?
+    else {
+        return Err(ApiError::NotFound(format!("Post {post_id} not found")));
+    };
+
+    let pool = &state.yral_redis_store_dragonfly;
+    // Attribution is best-effort; the link works without it
+    let referral_code = match get_or_create_code(pool, &sharer).await {
+        Ok(code) => Some(code),
+        Err(e) => {
+            log::warn!("Failed to get referral code for {sharer}: {e:?}");
+            None
+        }
+    };
+
+    let link = get_or_create_link(pool, &sharer, &post_id, &video_uid, referral_code).await?;
+    Ok(Json(link))
+}
+
+/// Advance the sharer's missions for the viewer's first open of the link
+async fn credit_open(
+    pool: &Arc<DragonflyPool>,
+    code: &str,
+    viewer: &Principal,
+    params: &str,
+) -> Result<()> {
+    let first_open = {
+        let mut conn = pool.get().await?;
+        let key = credited_viewers_key(code);
+        let (added,): (u32,) = redis::pipe()
+            .sadd(&key, viewer.to_text())
+            .expire(&key, SHARE_LINK_TTL_SECS as i64)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+        added == 1
+    };
+    if first_open {
+        crate::daily_missions::missions::track_event(pool, SHARE_OPENED_EVENT, params).await?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ShareLinkOpenResponse {
+    #[serde(flatten)]
+    pub link: ShareLink,
+    /// Times the link has been opened, including this one
+    pub opens: u64,
+}
+
+/// Resolve a share code to its post and record the open
+#[utoipa::path(
+    get,
+    path = "/share/{code}",
+    params(
+        ("code" = String, Path, description = "Share code"),
+        ("x-delegated-identity" = Option<String>, Header, description = "Base64-encoded JSON delegated identity wire of the viewer, if signed in")
+    ),
+    tag = "posts",
+    responses(
+        (status = 200, description = "Shared post", body = ShareLinkOpenResponse),
+        (status = 404, description = "Unknown or expired share code", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        (),
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn resolve_share_link(
+    State(state): State<Arc<AppState>>,
+    Path(code): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<ShareLinkOpenResponse>, ApiError> {
+    let not_found = || ApiError::NotFound(format!("Share link {code} not found"));
+    let normalized = normalize_code(&code).ok_or_else(not_found)?;
+    let pool = &state.yral_redis_store_dragonfly;
+    let link = load_link(pool, &normalized).await?.ok_or_else(not_found)?;
+
+    let viewer = optional_viewer(&state, &headers).await;
+    let self_open = viewer.is_some_and(|viewer| viewer.to_text() == link.sharer_principal);
+
+    let opens: u64 = {
+        let mut conn = pool.get().await?;
+        let key = opens_key(&link.code);
+        let (opens,): (u64,) = redis::pipe()
+            .incr(&key, 1)
+            .expire(&key, SHARE_LINK_TTL_SECS as i64)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+        opens
+    };
+
+    let params = serde_json::json!({
+        "share_code": link.code,
+        "post_id": link.post_id,
+        "video_id": link.video_id,
+        "sharer_id": link.sharer_principal,
+        "referral_code": link.referral_code,
+        "viewer_id": viewer.map(|viewer| viewer.to_text()),
+        "is_self_open": self_open,
+    });
+    Event::new(WarehouseEvent {
+        event: SHARE_OPENED_EVENT.to_string(),
+        params: params.to_string(),
+    })
+    .stream_to_bigquery(&state);
+
+    // Sharers don't earn mission progress by opening their own links, and
+    // each signed-in viewer counts once per link
+    let credit_viewer = viewer.filter(|_| !self_open);
+    if let Some(viewer) = credit_viewer {
+        if let Err(e) = credit_open(pool, &link.code, &viewer, &params.to_string()).await {
+            log::warn!("Failed to track share open for daily missions: {e:?}");
+        }
+    }
+
+    Ok(Json(ShareLinkOpenResponse { link, opens }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_codes_normalize() {
+        let code = generate_code();
+        assert_eq!(code.len(), CODE_LEN);
+        assert_eq!(
+            normalize_code(&format!(" {} ", code.to_lowercase())),
+            Some(code)
+        );
+        assert_eq!(normalize_code("SHORT"), None);
+        assert_eq!(normalize_code("O0O0O0O0O0"), None);
+    }
+}
@@ -2,7 +2,6 @@ use std::sync::Arc;
 
 use axum::{
     extract::{Request, State},
-    http::StatusCode,
     middleware::Next,
     response::Response,
 };
@@ -10,7 +9,8 @@ use candid::Principal;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    app_state::AppState, utils::delegated_identity::get_user_info_from_delegated_identity_wire,
+    app_state::AppState, error::ApiError,
+    utils::delegated_identity::verify_delegated_identity_request,
 };
 
 use super::PostRequest;
@@ -26,7 +26,7 @@ pub async fn verify_post_request<T>(
     State(state): State<Arc<AppState>>,
     request: Request,
     next: Next,
-) -> Result<Response, StatusCode>
+) -> Result<Response, ApiError>
 where
     T: for<'de> Deserialize<'de> + Serialize + Clone + Send + Sync + 'static,
 {
@@ -34,21 +34,30 @@ where
     let (parts, body) = request.into_parts();
     let bytes = match axum::body::to_bytes(body, usize::MAX).await {
         Ok(bytes) => bytes,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+        Err(_) => {
+            return Err(ApiError::InvalidRequest(
+                "Failed to read request body".to_string(),
+            ))
+        }
     };
 
     // Parse the JSON
     let post_request: PostRequest<T> = match serde_json::from_slice(&bytes) {
         Ok(req) => req,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            return Err(ApiError::InvalidRequest(format!(
+                "Invalid request body: {e}"
+            )))
+        }
     };
 
-    let user_info = get_user_info_from_delegated_identity_wire(
+    let user_info = verify_delegated_identity_request(
         &state,
         post_request.delegated_identity_wire.clone(),
+        &parts.headers,
     )
     .await
-    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    .map_err(|e| ApiError::Unauthorized(format!("Invalid delegated identity: {e}")))?;
     let user_principal = user_info.user_principal;
     let user_canister = user_info.user_canister;
 
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use redis::AsyncCommands;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{c2patool_available, has_generation_assertion, read_manifest, ProvenanceRecord};
+use crate::{
+    app_state::AppState,
+    consts::get_storj_video_url,
+    error::{ApiError, ApiErrorBody},
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const KEY_PREFIX: &str = "offchain:provenance";
+
+fn record_key(video_id: &str) -> String {
+    format!("{KEY_PREFIX}:{video_id}")
+}
+
+/// Best-effort; the video is already signed, the record only serves lookups
+pub async fn record_provenance(state: &AppState, record: &ProvenanceRecord) {
+    let result: Result<()> = async {
+        let mut conn = state.yral_redis_store_dragonfly.get().await?;
+        let _: () = conn
+            .set(record_key(&record.video_id), serde_json::to_string(record)?)
+            .await?;
+        Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        log::warn!(
+            "Failed to record provenance for video {}: {e:?}",
+            record.video_id
+        );
+    }
+}
+
+async fn load_record(pool: &DragonflyPool, video_id: &str) -> Result<Option<ProvenanceRecord>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.get(record_key(video_id)).await?;
+    payload
+        .map(|payload| serde_json::from_str(&payload).map_err(Into::into))
+        .transpose()
+}
+
+/// Stored copy of the video; moderation may have moved it to the NSFW bucket
+async fn download_stored_video(publisher_user_id: &str, video_id: &str) -> Result<Vec<u8>> {
+    let client = reqwest::Client::new();
+    for is_nsfw in [false, true] {
+        let response = client
+            .get(get_storj_video_url(publisher_user_id, video_id, is_nsfw))
+            .send()
+            .await?;
+        if response.status().is_success() {
+            return Ok(response.bytes().await?.to_vec());
+        }
+    }
+    anyhow::bail!("Video {video_id} not found in storage")
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProvenanceVerification {
+    pub record: ProvenanceRecord,
+    /// Manifest store parsed from the stored video; absent when it carries
+    /// no manifest
+    #[schema(value_type = Option<Object>)]
+    pub manifest: Option<serde_json::Value>,
+    /// Whether the stored video still carries our generation assertion
+    pub verified: bool,
+}
+
+/// C2PA manifest of a generated video, read back from storage
+#[utoipa::path(
+    get,
+    path = "/{video_id}",
+    params(("video_id" = String, Path, description = "Video ID")),
+    tag = "provenance",
+    responses(
+        (status = 200, description = "Provenance", body = ProvenanceVerification),
+        (status = 404, description = "Video was not generated here", body = ApiErrorBody),
+        (status = 502, description = "Stored video unavailable", body = ApiErrorBody),
+        (status = 503, description = "c2patool is not installed", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    )
+)]
+#[instrument(skip(state))]
+pub async fn get_provenance(
+    State(state): State<Arc<AppState>>,
+    Path(video_id): Path<String>,
+) -> Result<Json<ProvenanceVerification>, ApiError> {
+    let record = load_record(&state.yral_redis_store_dragonfly, &video_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("No provenance for video {video_id}")))?;
+    if !c2patool_available().await {
+        return Err(ApiError::ServiceUnavailable(
+            "c2patool is not installed, manifests can't be read".to_string(),
+        ));
+    }
+
+    let video = download_stored_video(&record.publisher_user_id, &video_id)
+        .await
+        .map_err(|e| ApiError::Upstream(e.to_string()))?;
+    let manifest = read_manifest(&video).await?;
+    let verified = manifest.as_ref().is_some_and(has_generation_assertion);
+
+    Ok(Json(ProvenanceVerification {
+        record,
+        manifest,
+        verified,
+    }))
+}
+
+pub fn provenance_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(get_provenance))
+        .with_state(state)
+}
@@ -0,0 +1,288 @@
+//! C2PA provenance for videos we generate.
+//!
+//! Generated videos get a signed C2PA manifest (provider, model, prompt hash,
+//! generation time) embedded before they are uploaded to drafts. Signing and
+//! parsing shell out to `c2patool` (`C2PATOOL_PATH`, default `c2patool` on
+//! the `PATH`), configured through `C2PA_SIGN_CERT_PATH`,
+//! `C2PA_PRIVATE_KEY_PATH`, `C2PA_SIGNING_ALG` (default `es256`) and
+//! optionally `C2PA_TSA_URL`. Without a certificate, or when the image has no
+//! `c2patool`, videos go out unsigned and the record says so.
+//!
+//! `GET /api/v1/provenance/{video_id}` re-reads the manifest from the stored
+//! copy of the video, so it reports what viewers actually receive; it
+//! answers 503 while `c2patool` is missing.
+
+use std::{path::PathBuf, process::Command};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[cfg(not(feature = "local-bin"))]
+mod handlers;
+#[cfg(not(feature = "local-bin"))]
+pub use handlers::{provenance_router, record_provenance};
+
+/// IPTC source type for fully AI-generated media
+const TRAINED_ALGORITHMIC_MEDIA: &str =
+    "http://cv.iptc.org/newscodes/digitalsourcetype/trainedAlgorithmicMedia";
+const GENERATION_ASSERTION: &str = "ai.yral.generation";
+
+/// Hex SHA-256 of the prompt, so the manifest never carries the prompt itself
+pub fn prompt_hash(prompt: &str) -> String {
+    hex::encode(Sha256::digest(prompt.trim().as_bytes()))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GenerationProvenance {
+    pub provider: String,
+    pub model: String,
+    /// Absent for jobs queued before prompt hashes were tracked
+    pub prompt_hash: Option<String>,
+    /// RFC 3339
+    pub generated_at: String,
+}
+
+/// What we embedded into a generated video, kept to locate and check it later
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProvenanceRecord {
+    pub video_id: String,
+    pub publisher_user_id: String,
+    #[serde(flatten)]
+    pub provenance: GenerationProvenance,
+    /// False when signing wasn't configured and no manifest was embedded
+    pub signed: bool,
+}
+
+#[cfg(feature = "local-bin")]
+pub async fn record_provenance(_state: &crate::app_state::AppState, _record: &ProvenanceRecord) {}
+
+struct SigningConfig {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    alg: String,
+    tsa_url: Option<String>,
+}
+
+impl SigningConfig {
+    fn from_env() -> Option<Self> {
+        Some(Self {
+            cert_path: std::env::var("C2PA_SIGN_CERT_PATH").ok()?.into(),
+            key_path: std::env::var("C2PA_PRIVATE_KEY_PATH").ok()?.into(),
+            alg: std::env::var("C2PA_SIGNING_ALG").unwrap_or_else(|_| "es256".to_string()),
+            tsa_url: std::env::var("C2PA_TSA_URL").ok(),
+        })
+    }
+}
+
+/// Manifest definition in the format `c2patool -m` takes
+fn manifest_definition(
+    video_id: &str,
+    provenance: &GenerationProvenance,
+    signing: &SigningConfig,
+) -> serde_json::Value {
+    serde_json::json!({
+        "claim_generator": concat!("yral-off-chain-agent/", env!("CARGO_PKG_VERSION")),
+        "title": format!("{video_id}.mp4"),
+        "format": "video/mp4",
+        "alg": signing.alg,
+        "sign_cert": signing.cert_path,
+        "private_key": signing.key_path,
+        "ta_url": signing.tsa_url,
+        "assertions": [
+            {
+                "label": "c2pa.actions",
+                "data": {
+                    "actions": [{
+                        "action": "c2pa.created",
+                        "digitalSourceType": TRAINED_ALGORITHMIC_MEDIA,
+                        "softwareAgent": provenance.model,
+                    }]
+                }
+            },
+            {
+                "label": GENERATION_ASSERTION,
+                "data": {
+                    "video_id": video_id,
+                    "provider": provenance.provider,
+                    "model": provenance.model,
+                    "prompt_sha256": provenance.prompt_hash,
+                    "generated_at": provenance.generated_at,
+                }
+            }
+        ]
+    })
+}
+
+static C2PATOOL_AVAILABLE: OnceCell<bool> = OnceCell::const_new();
+
+fn c2patool_path() -> String {
+    std::env::var("C2PATOOL_PATH").unwrap_or_else(|_| "c2patool".to_string())
+}
+
+/// Whether `c2patool` runs at all; probed once per process
+pub async fn c2patool_available() -> bool {
+    *C2PATOOL_AVAILABLE
+        .get_or_init(|| async {
+            let probe = tokio::task::spawn_blocking(|| {
+                Command::new(c2patool_path()).arg("--version").output()
+            })
+            .await;
+            let available = matches!(probe, Ok(Ok(output)) if output.status.success());
+            if !available {
+                log::error!("c2patool is not available, C2PA provenance is disabled");
+            }
+            available
+        })
+        .await
+}
+
+async fn run_c2patool(args: Vec<std::ffi::OsString>) -> Result<std::process::Output> {
+    tokio::task::spawn_blocking(move || Command::new(c2patool_path()).args(args).output())
+        .await?
+        .context("Failed to run c2patool")
+}
+
+async fn with_work_dir<T, F, Fut>(prefix: &str, f: F) -> Result<T>
+where
+    F: FnOnce(PathBuf) -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let work_dir = std::env::temp_dir().join(format!("{prefix}_{}", Uuid::new_v4()));
+    tokio::fs::create_dir_all(&work_dir).await?;
+    let result = f(work_dir.clone()).await;
+    let _ = tokio::fs::remove_dir_all(&work_dir).await;
+    result
+}
+
+/// The video with a signed manifest embedded, or `None` when no signing
+/// certificate is configured
+pub async fn embed(
+    video: &[u8],
+    video_id: &str,
+    provenance: &GenerationProvenance,
+) -> Result<Option<Vec<u8>>> {
+    let Some(signing) = SigningConfig::from_env() else {
+        log::warn!("C2PA signing is not configured, {video_id} goes out without a manifest");
+        return Ok(None);
+    };
+    if !c2patool_available().await {
+        log::warn!("c2patool is missing, {video_id} goes out without a manifest");
+        return Ok(None);
+    }
+    let manifest = manifest_definition(video_id, provenance, &signing);
+
+    with_work_dir("c2pa_sign", |work_dir| async move {
+        let input = work_dir.join("input.mp4");
+        let output = work_dir.join("signed.mp4");
+        let manifest_path = work_dir.join("manifest.json");
+        tokio::fs::write(&input, video).await?;
+        tokio::fs::write(&manifest_path, serde_json::to_vec(&manifest)?).await?;
+
+        let result = run_c2patool(vec![
+            input.into_os_string(),
+            "-m".into(),
+            manifest_path.into_os_string(),
+            "-o".into(),
+            output.clone().into_os_string(),
+            "-f".into(),
+        ])
+        .await?;
+        if !result.status.success() {
+            return Err(anyhow::anyhow!(
+                "c2patool failed to sign {video_id}: {}",
+                String::from_utf8_lossy(&result.stderr)
+            ));
+        }
+
+        Ok(Some(tokio::fs::read(&output).await?))
+    })
+    .await
+}
+
+/// Parsed manifest store, or `None` when the video carries no manifest
+pub async fn read_manifest(video: &[u8]) -> Result<Option<serde_json::Value>> {
+    with_work_dir("c2pa_read", |work_dir| async move {
+        let input = work_dir.join("video.mp4");
+        tokio::fs::write(&input, video).await?;
+        let result = run_c2patool(vec![input.into_os_string()]).await?;
+        parse_c2patool_output(&result.stdout, &result.stderr, result.status.success())
+    })
+    .await
+}
+
+fn parse_c2patool_output(
+    stdout: &[u8],
+    stderr: &[u8],
+    success: bool,
+) -> Result<Option<serde_json::Value>> {
+    if !success {
+        let stderr = String::from_utf8_lossy(stderr);
+        if stderr.contains("No claim found") {
+            return Ok(None);
+        }
+        return Err(anyhow::anyhow!(
+            "c2patool failed to read manifest: {stderr}"
+        ));
+    }
+    serde_json::from_slice(stdout)
+        .map(Some)
+        .context("c2patool returned invalid JSON")
+}
+
+/// Whether a parsed manifest store carries our generation assertion
+pub fn has_generation_assertion(manifest_store: &serde_json::Value) -> bool {
+    manifest_store["manifests"]
+        .as_object()
+        .into_iter()
+        .flat_map(|manifests| manifests.values())
+        .filter_map(|manifest| manifest["assertions"].as_array())
+        .flatten()
+        .any(|assertion| assertion["label"] == GENERATION_ASSERTION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_definition() {
+        let provenance = GenerationProvenance {
+            provider: "replicate".to_string(),
+            model: "wan2_5".to_string(),
+            prompt_hash: Some(prompt_hash(" a cat ")),
+            generated_at: "2026-01-01T00:00:00Z".to_string(),
+        };
+        assert_eq!(
+            provenance.prompt_hash.as_deref(),
+            Some(prompt_hash("a cat").as_str())
+        );
+
+        let signing = SigningConfig {
+            cert_path: "/certs/chain.pem".into(),
+            key_path: "/certs/key.pem".into(),
+            alg: "es256".to_string(),
+            tsa_url: None,
+        };
+        let manifest = manifest_definition("vid1", &provenance, &signing);
+        assert_eq!(
+            manifest["assertions"][0]["data"]["actions"][0]["digitalSourceType"],
+            TRAINED_ALGORITHMIC_MEDIA
+        );
+
+        let store = serde_json::json!({
+            "active_manifest": "urn:1",
+            "manifests": { "urn:1": { "assertions": manifest["assertions"] } }
+        });
+        assert!(has_generation_assertion(&store));
+        assert!(!has_generation_assertion(&serde_json::json!({})));
+
+        assert!(parse_c2patool_output(b"", b"Error: No claim found", false)
+            .unwrap()
+            .is_none());
+        assert!(parse_c2patool_output(b"", b"boom", false).is_err());
+    }
+}
@@ -1,7 +1,6 @@
 use std::env;
 use std::sync::Arc;
 
-use chrono::Timelike;
 use futures::StreamExt;
 use http::{
     header::{AUTHORIZATION, CONTENT_TYPE},
@@ -11,17 +10,11 @@ use reqwest::{Client, Url};
 use serde_json::json;
 use tracing::instrument;
 
+use super::job::{upstash_headers, JobPayload, PublishOptions};
 use crate::{
     consts::OFF_CHAIN_AGENT_URL,
-    events::event::UploadVideoInfoV2,
-    posts::report_post::ReportPostRequestV3,
-    qstash::service_canister_migration::MigrateIndividualUserRequest,
-    videogen::{
-        qstash_types::QstashVideoGenRequest,
-        upload_ai_generated_video_to_canister_in_drafts::UploadAiVideoToCanisterRequest,
-    },
+    middleware::trace_context::{qstash_forward_trace_headers, qstash_forward_trace_headers_json},
 };
-use videogen_common::VideoGenerator;
 
 #[derive(Clone, Debug)]
 pub struct QStashClient {
@@ -56,496 +49,66 @@ impl QStashClient {
         }
     }
 
-    #[instrument(skip(self))]
-    pub async fn duplicate_to_storj(
-        &self,
-        data: storj_interface::duplicate::Args,
-    ) -> anyhow::Result<()> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL.join("qstash/storj_ingest").unwrap();
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-
-        self.client
-            .post(url)
-            .json(&data)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .header("Upstash-Flow-Control-Key", "STORJ_INGESTION")
-            .header("Upstash-Flow-Control-Value", "Rate=20,Parallelism=10")
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self))]
-    pub async fn publish_video(
-        &self,
-        video_id: &str,
-        post_id: String,
-        timestamp_str: String,
-        publisher_user_id: &str,
-    ) -> Result<(), anyhow::Error> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL.join("qstash/upload_video_gcs").unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-        let req = serde_json::json!({
-            "video_id": video_id,
-            "post_id": post_id,
-            "timestamp": timestamp_str,
-            "publisher_user_id": publisher_user_id
-        });
-
-        self.client
-            .post(url)
-            .json(&req)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self))]
-    pub async fn publish_video_v2(
-        &self,
-        video_id: &str,
-        post_id: &str, // Changed from u64 to &str
-        timestamp_str: String,
-        publisher_user_id: &str,
-    ) -> Result<(), anyhow::Error> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL.join("qstash/upload_video_gcs").unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-        let req = serde_json::json!({
-            "video_id": video_id,
-            "post_id": post_id, // String instead of u64
-            "timestamp": timestamp_str,
-            "publisher_user_id": publisher_user_id
-        });
-
-        self.client
-            .post(url)
-            .json(&req)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self))]
-    pub async fn publish_video_frames(
-        &self,
-        video_id: &str,
-        video_info: &UploadVideoInfoV2,
-    ) -> Result<(), anyhow::Error> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join("qstash/enqueue_video_frames")
-            .unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-        let req = serde_json::json!({
-            "video_id": video_id,
-            "video_info": video_info,
-        });
-
-        // Add jitter (0-300ms)
-        let now = chrono::Utc::now();
-        let jitter_ms = now.nanosecond() % 301;
-
-        self.client
-            .post(url)
-            .json(&req)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .header("upstash-delay", format!("{}ms", jitter_ms))
-            .header("Upstash-Flow-Control-Key", "VIDEO_FRAMES_PROCESSING")
-            .header("Upstash-Flow-Control-Value", "Rate=50,Parallelism=20")
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self))]
-    pub async fn publish_video_nsfw_detection(
-        &self,
-        video_id: &str,
-        video_info: &UploadVideoInfoV2,
-    ) -> Result<(), anyhow::Error> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join("qstash/enqueue_video_nsfw_detection")
-            .unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-        let req = serde_json::json!({
-            "video_id": video_id,
-            "video_info": video_info,
-        });
-
-        // Add jitter (0-500ms)
-        let now = chrono::Utc::now();
-        let jitter_ms = now.nanosecond() % 501;
-
-        self.client
-            .post(url)
-            .json(&req)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .header("upstash-delay", format!("{}ms", jitter_ms))
-            .header("Upstash-Flow-Control-Key", "VIDEO_NSFW_DETECTION")
-            .header("Upstash-Flow-Control-Value", "Rate=30,Parallelism=15")
-            .header("Upstash-Retries", "5")
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self))]
-    pub async fn publish_video_nsfw_detection_v2(
-        &self,
-        video_id: &str,
-        video_info: UploadVideoInfoV2,
-    ) -> Result<(), anyhow::Error> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join("qstash/enqueue_video_nsfw_detection_v2")
-            .unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-        let req = serde_json::json!({
-            "video_id": video_id,
-            "video_info": video_info,
-        });
-
-        // Calculate delay until next :20 minute of any hour
-        let now = chrono::Utc::now();
-        let current_minute = now.minute();
-        let minutes_until_20 = if current_minute >= 20 {
-            60 - current_minute + 20
-        } else {
-            20 - current_minute
-        };
-
-        let jitter = now.nanosecond() % 601;
-        let delay_seconds = minutes_until_20 * 60 + jitter + 3600;
-
-        self.client
-            .post(url)
-            .json(&req)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .header("upstash-delay", format!("{delay_seconds}s"))
-            .header("Upstash-Flow-Control-Key", "VIDEO_NSFW_DETECTION_V2")
-            .header("Upstash-Flow-Control-Value", "Rate=20,Parallelism=10")
-            .header("Upstash-Retries", "5")
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self))]
-    pub async fn publish_report_post(
-        &self,
-        report_request: ReportPostRequestV3,
-    ) -> Result<(), anyhow::Error> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL.join("qstash/report_post").unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-        let req = serde_json::json!(report_request);
-
-        self.client
-            .post(url)
-            .json(&req)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self))]
-    pub async fn schedule_tournament_start(
-        &self,
-        tournament_id: &str,
-        delay_seconds: i64,
-    ) -> anyhow::Result<()> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join(&format!("qstash/tournament/start/{}", tournament_id))
-            .unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-
-        self.client
-            .post(url)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .header("upstash-delay", format!("{}s", delay_seconds))
-            .header("Upstash-Retries", "0")
-            .send()
-            .await?;
-
-        Ok(())
+    /// Publishes `job` to its route with the job's own delivery settings
+    pub async fn publish_job<T: JobPayload>(&self, job: &T) -> anyhow::Result<()> {
+        self.publish_job_with(job, PublishOptions::default()).await
     }
 
-    #[instrument(skip(self))]
-    pub async fn schedule_tournament_finalize(
+    #[instrument(skip(self, job, options), fields(path = %job.path()))]
+    pub async fn publish_job_with<T: JobPayload>(
         &self,
-        tournament_id: &str,
-        delay_seconds: i64,
+        job: &T,
+        options: PublishOptions,
     ) -> anyhow::Result<()> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join(&format!("qstash/tournament/finalize/{}", tournament_id))
-            .unwrap();
-
+        let off_chain_ep = OFF_CHAIN_AGENT_URL.join(&format!("qstash/{}", job.path()))?;
         let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
 
-        self.client
-            .post(url)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .header("upstash-delay", format!("{}s", delay_seconds))
-            .header("Upstash-Retries", "0")
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self, tournament_config))]
-    pub async fn schedule_tournament_create(
-        &self,
-        tournament_config: serde_json::Value,
-        delay_seconds: i64,
-    ) -> anyhow::Result<()> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join("qstash/tournament/create")
-            .unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-
-        self.client
-            .post(url)
-            .json(&tournament_config)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .header("upstash-delay", format!("{}s", delay_seconds))
-            .header("Upstash-Retries", "0")
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self))]
-    pub async fn queue_video_generation(
-        &self,
-        request: &QstashVideoGenRequest,
-        callback_url: Option<&str>,
-    ) -> anyhow::Result<()> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join("qstash/process_video_gen")
-            .unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-
-        // Get flow control from the model using the VideoGenerator trait
-        let flow_control = request.input.flow_control_config().map(|(rate, parallel)| {
-            let key = request.input.flow_control_key();
-            let value = format!("Rate={rate},Parallelism={parallel}");
-            (key, value)
-        });
-
-        let mut req_builder = self
+        let mut request = self
             .client
             .post(url)
-            .json(&request)
+            .json(job)
             .header(CONTENT_TYPE, "application/json")
             .header("upstash-method", "POST")
-            .header("Upstash-Retries", "0");
-
-        if let Some(callback) = callback_url {
-            req_builder = req_builder.header("Upstash-Callback", callback);
-        }
-
-        // Add flow control headers only if flow control is configured
-        if let Some((fc_key, fc_value)) = flow_control {
-            req_builder = req_builder
-                .header("Upstash-Flow-Control-Key", fc_key)
-                .header("Upstash-Flow-Control-Value", fc_value);
+            .headers(qstash_forward_trace_headers());
+        for (name, value) in upstash_headers(job, &options) {
+            request = request.header(name, value);
         }
 
-        req_builder.send().await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self))]
-    pub async fn upload_ai_generated_video_to_canister_in_drafts(
-        &self,
-        request: UploadAiVideoToCanisterRequest,
-    ) -> anyhow::Result<()> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join("qstash/upload_ai_generated_video_to_canister_in_drafts")
-            .unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-
-        // Get flow control from the model using the VideoGenerator trait
-
-        let req_builder = self
-            .client
-            .post(url)
-            .json(&request)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST");
-
-        req_builder.send().await?;
-
+        request.send().await?.error_for_status()?;
         Ok(())
     }
 
-    #[instrument(skip(self))]
-    pub async fn migrate_individual_user_to_service_canister(
+    /// Publishes many jobs through the batch API, 100 per request. Failed
+    /// batches are logged, not returned.
+    #[instrument(skip(self, jobs, options), fields(jobs = jobs.len()))]
+    pub async fn publish_job_batch<T: JobPayload>(
         &self,
-        request: &MigrateIndividualUserRequest,
+        jobs: &[T],
+        options: &PublishOptions,
     ) -> anyhow::Result<()> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join("qstash/migrate_individual_user_to_service_canister")
-            .unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-
-        self.client
-            .post(url)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .header("Upstash-Retries", "3")
-            .json(&request)
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self))]
-    pub async fn transfer_all_posts_to_service_canister(
-        &self,
-        request: &MigrateIndividualUserRequest,
-    ) -> anyhow::Result<()> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join("qstash/transfer_all_posts_for_individual_user")
-            .unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-
-        self.client
-            .post(url)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .header("Upstash-Retries", "3")
-            .json(&request)
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    pub async fn update_yral_metadata_mapping(
-        &self,
-        request: &MigrateIndividualUserRequest,
-    ) -> anyhow::Result<()> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join("qstash/update_yral_metadata_mapping")
-            .unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-
-        self.client
-            .post(url)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .header("Upstash-Delay", "2h")
-            .header("Upstash-Retries", "3")
-            .json(&request)
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self))]
-    pub async fn queue_compute_phash(&self, video_id: &str) -> anyhow::Result<()> {
-        let off_chain_ep = OFF_CHAIN_AGENT_URL
-            .join("qstash/compute_video_phash")
-            .unwrap();
-
-        let url = self.base_url.join(&format!("publish/{off_chain_ep}"))?;
-
-        let req = serde_json::json!({
-            "video_id": video_id
-        });
-
-        self.client
-            .post(url)
-            .json(&req)
-            .header(CONTENT_TYPE, "application/json")
-            .header("upstash-method", "POST")
-            .header("Upstash-Flow-Control-Key", "COMPUTE_PHASH")
-            .header("Upstash-Flow-Control-Value", "Rate=10,Parallelism=5")
-            .header("Upstash-Retries", "2")
-            .send()
-            .await?;
-
-        Ok(())
-    }
-
-    #[instrument(skip(self, video_data))]
-    pub async fn queue_compute_phash_batch(
-        &self,
-        video_data: Vec<(String, String)>, // (video_id, publisher_user_id)
-        rate_limit: u32,
-        parallelism: u32,
-    ) -> anyhow::Result<()> {
-        let destination_url = OFF_CHAIN_AGENT_URL
-            .join("qstash/compute_video_phash")?
-            .to_string();
         let qstash_batch_url = self.base_url.join("batch")?;
+        let trace_headers = qstash_forward_trace_headers_json();
+
+        let mut requests = Vec::with_capacity(jobs.len());
+        for job in jobs {
+            let destination_url = OFF_CHAIN_AGENT_URL.join(&format!("qstash/{}", job.path()))?;
+            let mut headers = json!({
+                "Upstash-Forward-Content-Type": "application/json",
+                "Upstash-Forward-Method": "POST",
+            });
+            if let Some(headers) = headers.as_object_mut() {
+                for (name, value) in upstash_headers(job, options) {
+                    headers.insert(name.to_string(), value.into());
+                }
+                headers.extend(trace_headers.clone());
+            }
 
-        log::info!("Compute phash batch URL: {}", qstash_batch_url);
-        log::info!("Queuing {} videos for phash computation", video_data.len());
-
-        let requests: Vec<serde_json::Value> = video_data
-            .iter()
-            .map(|(video_id, publisher_user_id)| {
-                let payload = json!({
-                    "video_id": video_id,
-                    "publisher_user_id": publisher_user_id
-                });
-                let body_str = serde_json::to_string(&payload).unwrap_or_else(|e| {
-                    tracing::error!("Failed to serialize ComputePhashRequest: {}", e);
-                    "{}".to_string()
-                });
-
-                json!({
-                    "destination": destination_url,
-                    "headers": {
-                        "Upstash-Forward-Content-Type": "application/json",
-                        "Upstash-Forward-Method": "POST",
-                        "Upstash-Flow-Control-Key": "COMPUTE_PHASH",
-                        "Upstash-Flow-Control-Value": format!("rate={},parallelism={}", rate_limit, parallelism),
-                        "Upstash-Retries": "1",
-                    },
-                    "body": body_str,
-                })
-            })
-            .collect();
-
-        log::info!("Created {} batch requests", requests.len());
+            requests.push(json!({
+                "destination": destination_url.to_string(),
+                "headers": headers,
+                "body": serde_json::to_string(job)?,
+            }));
+        }
 
         let chunk_size = 100;
 
@@ -563,15 +126,13 @@ impl QStashClient {
         }
 
         let num_chunks = futures.len();
-        log::info!("Processing {} batch chunks", num_chunks);
+        log::info!("Publishing {} jobs in {} batches", jobs.len(), num_chunks);
 
         let responses = futures::stream::iter(futures)
             .buffer_unordered(80) // less than qstash limit per sec = 100
             .collect::<Vec<_>>()
             .await;
 
-        log::info!("Received {} batch responses", responses.len());
-
         let mut failed_batches = 0;
         for response in responses {
             match response {
@@ -592,8 +153,6 @@ impl QStashClient {
             log::warn!("{} batch(es) failed out of {}", failed_batches, num_chunks);
         }
 
-        log::info!("Compute phash batch completed");
-
         Ok(())
     }
 }
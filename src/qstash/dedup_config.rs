@@ -0,0 +1,240 @@
+//! Runtime-tunable settings for the phash dedup subsystem.
+//!
+//! The record lives in Dragonfly so every instance sees the same values.
+//! Reads are cached in-process for a short TTL; an update is visible on the
+//! instance that handled it immediately and everywhere else within the TTL.
+//!
+//! The auto-block policy is the `dedup_auto_block` feature flag so it can be
+//! rolled out by publisher; see [`crate::feature_flags::DEDUP_AUTO_BLOCK`].
+
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::get,
+    Json, Router,
+};
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState, auth::require_operator, config::DedupSection,
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const DEDUP_CONFIG_KEY: &str = "offchain:dedup:config";
+const CACHE_TTL: Duration = Duration::from_secs(30);
+const MAX_HAMMING_THRESHOLD: u32 = 640;
+const MAX_TOP_K: u32 = 16;
+
+static CACHE: Lazy<RwLock<Option<(Instant, DedupConfig)>>> = Lazy::new(|| RwLock::new(None));
+
+/// Which duplicates stop an upload before it is handed to NSFW processing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoBlockPolicy {
+    /// Duplicates are recorded but still published
+    #[default]
+    Never,
+    /// Only byte-identical phashes are blocked
+    ExactOnly,
+    /// Exact and near duplicates within the threshold are blocked
+    AllDuplicates,
+}
+
+impl AutoBlockPolicy {
+    pub fn blocks(self, hamming_distance: u32) -> bool {
+        match self {
+            AutoBlockPolicy::Never => false,
+            AutoBlockPolicy::ExactOnly => hamming_distance == 0,
+            AutoBlockPolicy::AllDuplicates => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DedupConfig {
+    /// Max Hamming distance for a new upload to count as a near duplicate
+    pub hamming_threshold: u32,
+    /// Threshold used by the bulk backfill ingestion of historical videos
+    pub backfill_hamming_threshold: u32,
+    /// Neighbours fetched from Milvus per search
+    pub top_k: u32,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        DedupSection::default().into()
+    }
+}
+
+impl From<DedupSection> for DedupConfig {
+    fn from(section: DedupSection) -> Self {
+        Self {
+            hamming_threshold: section.hamming_threshold,
+            backfill_hamming_threshold: section.backfill_hamming_threshold,
+            top_k: section.top_k,
+        }
+    }
+}
+
+/// Values from the `dedup` config section, used until a config is stored
+fn configured_defaults() -> DedupConfig {
+    crate::config::runtime::runtime().dedup.clone().into()
+}
+
+impl DedupConfig {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        for (name, threshold) in [
+            ("hamming_threshold", self.hamming_threshold),
+            (
+                "backfill_hamming_threshold",
+                self.backfill_hamming_threshold,
+            ),
+        ] {
+            if threshold == 0 || threshold > MAX_HAMMING_THRESHOLD {
+                return Err(format!(
+                    "{name} must be between 1 and {MAX_HAMMING_THRESHOLD}"
+                ));
+            }
+        }
+        if self.top_k == 0 || self.top_k > MAX_TOP_K {
+            return Err(format!("top_k must be between 1 and {MAX_TOP_K}"));
+        }
+        Ok(())
+    }
+}
+
+fn cached() -> Option<DedupConfig> {
+    let cache = CACHE.read().ok()?;
+    cache
+        .as_ref()
+        .filter(|(fetched_at, _)| fetched_at.elapsed() < CACHE_TTL)
+        .map(|(_, config)| config.clone())
+}
+
+fn set_cached(config: &DedupConfig) {
+    if let Ok(mut cache) = CACHE.write() {
+        *cache = Some((Instant::now(), config.clone()));
+    }
+}
+
+async fn load_config(pool: &Arc<DragonflyPool>) -> Result<DedupConfig> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.get(DEDUP_CONFIG_KEY).await?;
+    Ok(match payload {
+        Some(payload) => serde_json::from_str(&payload)?,
+        None => configured_defaults(),
+    })
+}
+
+/// Current dedup config. Fails open: on a Redis error the last known (or
+/// default) values are used so uploads keep flowing.
+pub async fn get_dedup_config(pool: &Arc<DragonflyPool>) -> DedupConfig {
+    if let Some(config) = cached() {
+        return config;
+    }
+
+    match load_config(pool).await {
+        Ok(config) => {
+            set_cached(&config);
+            config
+        }
+        Err(e) => {
+            log::warn!("Failed to load dedup config, using last known values: {e}");
+            CACHE
+                .read()
+                .ok()
+                .and_then(|cache| cache.as_ref().map(|(_, config)| config.clone()))
+                .unwrap_or_else(configured_defaults)
+        }
+    }
+}
+
+async fn get_config_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<DedupConfig>, StatusCode> {
+    require_operator(&headers).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    load_config(&state.rewards_module.dragonfly_pool)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            log::error!("Failed to read dedup config: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+async fn update_config_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(config): Json<DedupConfig>,
+) -> Result<Json<DedupConfig>, (StatusCode, String)> {
+    require_operator(&headers)
+        .map_err(|_| StatusCode::UNAUTHORIZED)
+        .map_err(|status| (status, "Unauthorized".to_string()))?;
+    config
+        .validate()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let pool = &state.rewards_module.dragonfly_pool;
+    let result: Result<()> = async {
+        let payload = serde_json::to_string(&config)?;
+        let mut conn = pool.get().await?;
+        let _: () = conn.set(DEDUP_CONFIG_KEY, payload).await?;
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        log::error!("Failed to update dedup config: {e:?}");
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to update dedup config".to_string(),
+        ));
+    }
+
+    set_cached(&config);
+    log::warn!("Dedup config updated: {config:?}");
+
+    Ok(Json(config))
+}
+
+/// `GET` returns the stored config, `PUT` replaces it
+pub fn dedup_config_router<S>(app_state: Arc<AppState>) -> Router<S> {
+    Router::new()
+        .route("/", get(get_config_handler).put(update_config_handler))
+        .with_state(app_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_block_policy_and_validation() {
+        assert!(!AutoBlockPolicy::Never.blocks(0));
+        assert!(AutoBlockPolicy::ExactOnly.blocks(0));
+        assert!(!AutoBlockPolicy::ExactOnly.blocks(12));
+        assert!(AutoBlockPolicy::AllDuplicates.blocks(12));
+
+        assert!(DedupConfig::default().validate().is_ok());
+        let bad_top_k = DedupConfig {
+            top_k: 0,
+            ..Default::default()
+        };
+        assert!(bad_top_k.validate().is_err());
+        let bad_threshold = DedupConfig {
+            hamming_threshold: 641,
+            ..Default::default()
+        };
+        assert!(bad_threshold.validate().is_err());
+    }
+}
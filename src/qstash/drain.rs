@@ -0,0 +1,295 @@
+//! Operator-controlled drain mode for QStash intake.
+//!
+//! While draining, deliveries that would start new pipelines are answered
+//! with 503 so Upstash retries them after the deploy. Continuation steps of
+//! pipelines that are already running keep being processed, and requests
+//! that were accepted before the drain started run to completion.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::Result;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState, auth::require_operator, video_processing::queue::scheduled_job_count,
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const DRAIN_STATE_KEY: &str = "offchain:qstash_drain:state";
+const IN_FLIGHT_KEY: &str = "offchain:qstash_drain:in_flight";
+
+const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30 * 60;
+const MAX_DRAIN_TIMEOUT_SECS: u64 = 6 * 60 * 60;
+/// In-flight markers older than this belong to crashed instances and are pruned
+const IN_FLIGHT_STALE_SECS: i64 = 60 * 60;
+const RETRY_AFTER_SECS: u64 = 60;
+
+/// Routes that advance a pipeline which has already started. These keep
+/// running during drain so in-flight work can finish.
+const CONTINUATION_ROUTES: &[&str] = &[
+    "/video_gen_callback",
+    "/upload_ai_generated_video_to_canister_in_drafts",
+    "/transfer_all_posts_for_individual_user",
+    "/update_yral_metadata_mapping",
+    "/compute_video_phash",
+    "/tournament/end/{id}",
+    "/tournament/finalize/{id}",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrainState {
+    pub started_at: i64,
+    pub expires_at: i64,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartDrainRequest {
+    pub timeout_secs: Option<u64>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DrainStatus {
+    pub draining: bool,
+    pub state: Option<DrainState>,
+    /// Outstanding QStash deliveries across all instances, keyed by route
+    pub in_flight: BTreeMap<String, usize>,
+    pub in_flight_total: usize,
+    /// Durable video processing jobs still scheduled in the worker queue
+    pub video_processing_scheduled: u64,
+}
+
+fn route_key(matched_path: &str) -> &str {
+    matched_path.strip_prefix("/qstash").unwrap_or(matched_path)
+}
+
+fn is_continuation_route(route: &str) -> bool {
+    CONTINUATION_ROUTES.contains(&route)
+}
+
+async fn get_drain_state(pool: &Arc<DragonflyPool>) -> Result<Option<DrainState>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.get(DRAIN_STATE_KEY).await?;
+    Ok(payload.and_then(|p| serde_json::from_str(&p).ok()))
+}
+
+async fn mark_in_flight(pool: &Arc<DragonflyPool>, member: &str) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = conn
+        .zadd(IN_FLIGHT_KEY, member, chrono::Utc::now().timestamp())
+        .await?;
+    Ok(())
+}
+
+async fn clear_in_flight(pool: &Arc<DragonflyPool>, member: &str) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = conn.zrem(IN_FLIGHT_KEY, member).await?;
+    Ok(())
+}
+
+async fn count_in_flight(pool: &Arc<DragonflyPool>) -> Result<BTreeMap<String, usize>> {
+    let mut conn = pool.get().await?;
+    let stale_before = chrono::Utc::now().timestamp() - IN_FLIGHT_STALE_SECS;
+    let _: () = conn
+        .zrembyscore(IN_FLIGHT_KEY, "-inf", stale_before)
+        .await?;
+    let members: Vec<String> = conn.zrange(IN_FLIGHT_KEY, 0, -1).await?;
+
+    let mut counts = BTreeMap::new();
+    for member in members {
+        let route = member
+            .rsplit_once('|')
+            .map(|(route, _)| route.to_string())
+            .unwrap_or(member);
+        *counts.entry(route).or_insert(0) += 1;
+    }
+    Ok(counts)
+}
+
+/// Rejects pipeline-starting deliveries while draining and tracks every
+/// accepted delivery so drain progress can report what is still running.
+pub async fn qstash_drain_guard(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let pool = &state.yral_redis_store_dragonfly;
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| route_key(p.as_str()).to_string())
+        .unwrap_or_else(|| route_key(request.uri().path()).to_string());
+
+    // Fail open: a Redis outage must not block QStash processing
+    let draining = match get_drain_state(pool).await {
+        Ok(drain) => drain.is_some(),
+        Err(e) => {
+            log::warn!("Failed to read QStash drain state: {e}");
+            false
+        }
+    };
+
+    if draining && !is_continuation_route(&route) {
+        log::info!("Draining: rejecting QStash delivery for {route}");
+        let mut response = (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "QStash intake is draining; retry later",
+        )
+            .into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+        return response;
+    }
+
+    let member = format!("{route}|{}", uuid::Uuid::new_v4());
+    if let Err(e) = mark_in_flight(pool, &member).await {
+        log::warn!("Failed to mark QStash delivery in flight for {route}: {e}");
+    }
+
+    let response = next.run(request).await;
+
+    if let Err(e) = clear_in_flight(pool, &member).await {
+        log::warn!("Failed to clear in-flight QStash delivery for {route}: {e}");
+    }
+
+    response
+}
+
+async fn drain_status(pool: &Arc<DragonflyPool>) -> Result<DrainStatus> {
+    let state = get_drain_state(pool).await?;
+    let in_flight = count_in_flight(pool).await?;
+    let video_processing_scheduled = scheduled_job_count(pool).await?;
+
+    Ok(DrainStatus {
+        draining: state.is_some(),
+        state,
+        in_flight_total: in_flight.values().sum(),
+        in_flight,
+        video_processing_scheduled,
+    })
+}
+
+async fn get_drain_status_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<DrainStatus>, StatusCode> {
+    require_operator(&headers).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    drain_status(&state.yral_redis_store_dragonfly)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            log::error!("Failed to read QStash drain status: {e:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Starts (or extends) drain mode. The state key expires with the timeout,
+/// so intake resumes automatically even if nobody calls resume.
+async fn start_drain_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<StartDrainRequest>,
+) -> Result<Json<DrainStatus>, StatusCode> {
+    require_operator(&headers).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let timeout_secs = request
+        .timeout_secs
+        .unwrap_or(DEFAULT_DRAIN_TIMEOUT_SECS)
+        .clamp(1, MAX_DRAIN_TIMEOUT_SECS);
+    let now = chrono::Utc::now().timestamp();
+    let drain = DrainState {
+        started_at: now,
+        expires_at: now + timeout_secs as i64,
+        reason: request.reason,
+    };
+
+    let pool = &state.yral_redis_store_dragonfly;
+    let result: Result<()> = async {
+        let payload = serde_json::to_string(&drain)?;
+        let mut conn = pool.get().await?;
+        let _: () = conn.set_ex(DRAIN_STATE_KEY, payload, timeout_secs).await?;
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        log::error!("Failed to start QStash drain: {e:?}");
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    log::warn!(
+        "QStash drain started for {timeout_secs}s (reason: {:?})",
+        drain.reason
+    );
+
+    get_drain_status_handler(State(state), headers).await
+}
+
+async fn resume_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<DrainStatus>, StatusCode> {
+    require_operator(&headers).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let pool = &state.yral_redis_store_dragonfly;
+    let result: Result<()> = async {
+        let mut conn = pool.get().await?;
+        let _: () = conn.del(DRAIN_STATE_KEY).await?;
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        log::error!("Failed to resume QStash intake: {e:?}");
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    log::warn!("QStash drain ended, intake resumed");
+
+    get_drain_status_handler(State(state), headers).await
+}
+
+/// `GET` reports progress, `POST` starts draining, `DELETE` resumes intake
+pub fn qstash_drain_router<S>(app_state: Arc<AppState>) -> Router<S> {
+    Router::new()
+        .route(
+            "/",
+            get(get_drain_status_handler)
+                .post(start_drain_handler)
+                .delete(resume_handler),
+        )
+        .with_state(app_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_continuation_routes_bypass_drain() {
+        assert!(is_continuation_route(route_key(
+            "/qstash/video_gen_callback"
+        )));
+        assert!(is_continuation_route(route_key(
+            "/qstash/tournament/finalize/{id}"
+        )));
+        assert!(!is_continuation_route(route_key(
+            "/qstash/video_deduplication"
+        )));
+        assert!(!is_continuation_route(route_key(
+            "/qstash/process_video_gen"
+        )));
+    }
+}
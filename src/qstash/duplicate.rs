@@ -1,13 +1,15 @@
-use crate::ai_video_detector::{AiVideoDetectorClient, Verdict};
+use crate::ai_video_detector::{AiVideoDetectorClient, DetectionResponse, Verdict};
 use crate::events::types::string_or_number;
 use crate::kvrocks::{
-    BotUploadedAiContent, KvrocksClient, UserUploadedContentApproval,
+    tables, BotUploadedAiContent, KvrocksClient, UserUploadedContentApproval, VideoContentHash,
     VideoMetadata as KvrocksVideoMetadata, VideoUniqueV2, VideohashOriginal, VideohashPhash,
 };
 use crate::{
     app_state,
     consts::{get_cloudflare_stream_url, get_storj_video_url},
     duplicate_video::phash::{compute_phash_from_storj, VideoMetadata},
+    pipeline::telemetry,
+    verdict_cache::{self, VerdictCachePolicy},
 };
 use anyhow::Context;
 use google_cloud_bigquery::http::job::query::QueryRequest;
@@ -15,8 +17,9 @@ use google_cloud_bigquery::http::tabledata::insert_all::{InsertAllRequest, Row};
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct VideoPublisherDataV2 {
     pub publisher_principal: String,
     #[serde(deserialize_with = "string_or_number")]
@@ -47,10 +50,11 @@ impl VideoHashDuplication {
 
         log::info!("Storing hash in videohash_original for video_id [{video_id}]");
 
-        bigquery_client
+        let response = bigquery_client
             .job()
             .query("hot-or-not-feed-intelligence", &request)
             .await?;
+        telemetry::count_bigquery_bytes(response.total_bytes_processed);
 
         // Also push to kvrocks
         let hash_data = VideohashOriginal {
@@ -58,7 +62,10 @@ impl VideoHashDuplication {
             videohash: hash.to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
         };
-        if let Err(e) = kvrocks_client.store_videohash_original(&hash_data).await {
+        if let Err(e) = kvrocks_client
+            .put(&tables::VIDEOHASH_ORIGINAL, &hash_data.video_id, &hash_data)
+            .await
+        {
             log::error!("Error pushing videohash_original to kvrocks: {}", e);
         }
 
@@ -137,7 +144,10 @@ impl VideoHashDuplication {
             fps: metadata.fps,
             created_at: chrono::Utc::now().to_rfc3339(),
         };
-        if let Err(e) = kvrocks_client.store_videohash_phash(&phash_data).await {
+        if let Err(e) = kvrocks_client
+            .put(&tables::VIDEOHASH_PHASH, &phash_data.video_id, &phash_data)
+            .await
+        {
             log::error!("Error pushing phash to kvrocks: {}", e);
         }
 
@@ -165,10 +175,11 @@ impl VideoHashDuplication {
 
         log::info!("Storing unique video in video_unique_v2 for video_id [{video_id}]");
 
-        bigquery_client
+        let response = bigquery_client
             .job()
             .query("hot-or-not-feed-intelligence", &request)
             .await?;
+        telemetry::count_bigquery_bytes(response.total_bytes_processed);
 
         // Also push to kvrocks
         let unique_data = VideoUniqueV2 {
@@ -176,7 +187,14 @@ impl VideoHashDuplication {
             videohash: hash.to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
         };
-        if let Err(e) = kvrocks_client.store_video_unique_v2(&unique_data).await {
+        if let Err(e) = kvrocks_client
+            .put(
+                &tables::VIDEO_UNIQUE_V2,
+                &unique_data.video_id,
+                &unique_data,
+            )
+            .await
+        {
             log::error!("Error pushing video_unique_v2 to kvrocks: {}", e);
         }
 
@@ -190,6 +208,7 @@ impl VideoHashDuplication {
         video_id: &str,
         post_id: &str,
         user_id: &str,
+        content_hash: Option<&str>,
     ) -> Result<(), anyhow::Error> {
         log::info!("Processing content approval for video_id: {}", video_id);
 
@@ -214,6 +233,9 @@ impl VideoHashDuplication {
             .job()
             .query("hot-or-not-feed-intelligence", &event_request)
             .await;
+        if let Ok(response) = &event_result {
+            telemetry::count_bigquery_bytes(response.total_bytes_processed);
+        }
 
         let (is_bot, canister_id) = match event_result {
             Ok(response) => {
@@ -284,7 +306,10 @@ impl VideoHashDuplication {
             post_id: post_id.to_string(),
             publisher_user_id: user_id.to_string(),
         };
-        if let Err(e) = kvrocks_client.store_video_metadata(&metadata).await {
+        if let Err(e) = kvrocks_client
+            .put(&tables::VIDEO_METADATA, &metadata.video_id, &metadata)
+            .await
+        {
             log::error!("Error storing video metadata to kvrocks: {}", e);
         }
 
@@ -299,7 +324,11 @@ impl VideoHashDuplication {
                 created_at: chrono::Utc::now().to_rfc3339(),
             };
             if let Err(e) = kvrocks_client
-                .store_bot_uploaded_ai_content(&bot_data)
+                .put(
+                    &tables::BOT_UPLOADED_AI_CONTENT,
+                    &bot_data.video_id,
+                    &bot_data,
+                )
                 .await
             {
                 log::error!("Error storing bot marker to kvrocks: {}", e);
@@ -319,17 +348,51 @@ impl VideoHashDuplication {
                 video_id
             );
 
-            let detection_result = match ai_detector.detect_video(&storj_url).await {
-                Ok(response) => Ok(response),
-                Err(storj_err) => {
-                    log::warn!(
-                        "AI detection failed with Storj URL for video {}: {}. Trying Cloudflare Stream...",
-                        video_id,
-                        storj_err
-                    );
-                    // Fallback to Cloudflare Stream URL
-                    ai_detector.detect_video(&cf_url).await
+            let verdict_cache = VerdictCachePolicy::from_env(verdict_cache::AI_VIDEO_DETECTOR);
+            let cached = match content_hash {
+                Some(hash) => {
+                    verdict_cache
+                        .lookup::<DetectionResponse>(kvrocks_client, hash)
+                        .await
                 }
+                None => None,
+            };
+
+            let detection_result = if let Some((response, cached)) = cached {
+                log::info!(
+                    "Reusing cached AI detection for video {} from video {} (scored_at={})",
+                    video_id,
+                    cached.source_video_id,
+                    cached.scored_at
+                );
+                Ok(response)
+            } else {
+                let detection_result = match ai_detector.detect_video(&storj_url).await {
+                    Ok(response) => Ok(response),
+                    Err(storj_err) => {
+                        log::warn!(
+                            "AI detection failed with Storj URL for video {}: {}. Trying Cloudflare Stream...",
+                            video_id,
+                            storj_err
+                        );
+                        // Fallback to Cloudflare Stream URL
+                        ai_detector.detect_video(&cf_url).await
+                    }
+                };
+
+                if let (Ok(response), Some(hash)) = (&detection_result, content_hash) {
+                    verdict_cache
+                        .store(
+                            kvrocks_client,
+                            hash,
+                            video_id,
+                            response.confidence,
+                            response,
+                        )
+                        .await;
+                }
+
+                detection_result
             };
 
             match detection_result {
@@ -380,6 +443,16 @@ impl VideoHashDuplication {
             false // Not configured, send to manual review
         };
 
+        // NSFW scoring may already have held the video for a moderator
+        let is_approved = is_approved
+            && !kvrocks_client
+                .is_held_for_manual_review(video_id)
+                .await
+                .unwrap_or_else(|e| {
+                    log::error!("Failed to check manual review hold on {video_id}: {e}");
+                    true
+                });
+
         let approval_data = UserUploadedContentApproval {
             video_id: video_id.to_string(),
             post_id: post_id.to_string(),
@@ -389,13 +462,24 @@ impl VideoHashDuplication {
             created_at: chrono::Utc::now().to_rfc3339(),
         };
         if let Err(e) = kvrocks_client
-            .store_user_uploaded_content_approval(&approval_data)
+            .put(
+                &tables::USER_UPLOADED_CONTENT_APPROVAL,
+                &approval_data.video_id,
+                &approval_data,
+            )
             .await
         {
             log::error!(
                 "Error storing user_uploaded_content_approval to kvrocks: {}",
                 e
             );
+        } else if !is_approved {
+            if let Err(e) = kvrocks_client
+                .enqueue_pending_approval(video_id, approval_data.created_at_millis())
+                .await
+            {
+                log::error!("Error adding {video_id} to the pending approval queue: {e}");
+            }
         }
 
         log::info!(
@@ -558,20 +642,71 @@ impl VideoHashDuplication {
         Ok(())
     }
 
+    /// Marks an auto-blocked duplicate as not approved. The caller skips the
+    /// publish continuation, so the video never reaches NSFW processing.
+    #[cfg(not(feature = "local-bin"))]
+    async fn block_duplicate(
+        kvrocks_client: &KvrocksClient,
+        video_id: &str,
+        duplicate_of: &str,
+        hamming_distance: u32,
+    ) -> Result<(), anyhow::Error> {
+        log::info!(
+            "🚫 AUTO-BLOCKED: Video {} duplicates {} (Hamming distance {})",
+            video_id,
+            duplicate_of,
+            hamming_distance
+        );
+        kvrocks_client
+            .update_user_uploaded_content_approval_status(video_id, false)
+            .await
+    }
+
+    /// Records the upload-time dedup decision, so moderators can later see
+    /// why a video was (or wasn't) flagged
+    #[cfg(not(feature = "local-bin"))]
+    async fn record_dedup_decision(
+        kvrocks_client: &KvrocksClient,
+        video_id: &str,
+        phash: &str,
+        closest: Option<(&str, u32)>,
+        hamming_threshold: u32,
+        auto_blocked: bool,
+    ) {
+        let dedup_status = crate::kvrocks::VideoDedupStatus {
+            video_id: video_id.to_string(),
+            phash: phash.to_string(),
+            is_duplicate: closest.is_some(),
+            duplicate_of: closest.map(|(duplicate_of, _)| duplicate_of.to_string()),
+            hamming_distance: closest.map(|(_, distance)| distance),
+            ingested_at: chrono::Utc::now().to_rfc3339(),
+            hamming_threshold: Some(hamming_threshold),
+            auto_blocked: Some(auto_blocked),
+        };
+        if let Err(e) = kvrocks_client
+            .put(&tables::VIDEO_DEDUP_STATUS, video_id, &dedup_status)
+            .await
+        {
+            log::error!("Error pushing dedup_status to kvrocks: {}", e);
+        }
+    }
+
     /// V2 version that uses Milvus for deduplication
-    /// Provides configurable hamming distance threshold and Redis tier-1 caching
+    /// Threshold and top_k come from the runtime dedup config and the
+    /// auto-block policy from the `dedup_auto_block` feature flag;
+    /// Redis provides tier-1 exact-match caching. Blocked duplicates return Ok
+    /// without calling `publish_video_callback`.
     #[cfg(not(feature = "local-bin"))]
     #[allow(clippy::too_many_arguments)]
     pub async fn process_video_deduplication_v2<'a>(
         &self,
         bigquery_client: &google_cloud_bigquery::client::Client,
-        milvus_client: &Option<crate::milvus::Client>,
+        milvus_client: &Option<crate::milvus::MilvusManager>,
         dragonfly_pool: &std::sync::Arc<crate::yral_auth::dragonfly::DragonflyPool>,
         kvrocks_client: &KvrocksClient,
         video_id: &str,
         _video_url: &str,
         publisher_data: VideoPublisherDataV2,
-        hamming_threshold: u32,
         publish_video_callback: impl FnOnce(
             &str,
             String,
@@ -580,6 +715,13 @@ impl VideoHashDuplication {
         )
             -> futures::future::BoxFuture<'a, Result<(), anyhow::Error>>,
     ) -> Result<(), anyhow::Error> {
+        let dedup_config = crate::qstash::dedup_config::get_dedup_config(dragonfly_pool).await;
+        let hamming_threshold = dedup_config.hamming_threshold;
+        let auto_block_flag = &crate::feature_flags::DEDUP_AUTO_BLOCK;
+        let auto_block = match candid::Principal::from_text(&publisher_data.publisher_principal) {
+            Ok(publisher) => auto_block_flag.get_for(dragonfly_pool, &publisher).await,
+            Err(_) => auto_block_flag.get(dragonfly_pool).await,
+        };
         log::info!(
             "Computing phash for video ID: {video_id} (v2 with Milvus, threshold={})",
             hamming_threshold
@@ -588,6 +730,24 @@ impl VideoHashDuplication {
             compute_phash_from_storj(&publisher_data.publisher_principal, video_id)
                 .await
                 .map_err(|e| anyhow::anyhow!("Failed to compute phash: {}", e))?;
+        // Detector verdicts are cached by the exact checksum, not the phash
+        if let Some(sha256) = &metadata.content_sha256 {
+            let content_hash = VideoContentHash {
+                video_id: video_id.to_string(),
+                sha256: sha256.clone(),
+                created_at: chrono::Utc::now().to_rfc3339(),
+            };
+            if let Err(e) = kvrocks_client
+                .put(&tables::VIDEO_CONTENT_HASH, video_id, &content_hash)
+                .await
+            {
+                log::error!(
+                    "Error pushing content hash to kvrocks for {}: {}",
+                    video_id,
+                    e
+                );
+            }
+        }
 
         // TIER 1: Check Redis for exact match (FAST - <1ms)
         log::debug!("Tier 1: Checking Redis for exact phash match");
@@ -617,9 +777,26 @@ impl VideoHashDuplication {
                 video_id,
                 &publisher_data.post_id,
                 &publisher_data.publisher_principal,
+                metadata.content_sha256.as_deref(),
             )
             .await?;
 
+            let blocked = auto_block.blocks(0);
+            Self::record_dedup_decision(
+                kvrocks_client,
+                video_id,
+                &phash,
+                Some((&existing_video_id, 0)),
+                hamming_threshold,
+                blocked,
+            )
+            .await;
+
+            if blocked {
+                return Self::block_duplicate(kvrocks_client, video_id, &existing_video_id, 0)
+                    .await;
+            }
+
             // Continue with video processing pipeline
             let timestamp = chrono::Utc::now().to_rfc3339();
             publish_video_callback(
@@ -640,22 +817,24 @@ impl VideoHashDuplication {
             "Tier 2: Checking Milvus for similar videos (Hamming distance < {})",
             hamming_threshold
         );
-        let is_duplicate = if let Some(client) = milvus_client {
+        let closest_match = if let Some(client) = milvus_client {
             log::debug!(
                 "Checking Milvus for duplicates with threshold {}",
                 hamming_threshold
             );
-            match crate::milvus::search_similar_videos(client, &phash, hamming_threshold).await {
+            match client
+                .search_similar_videos(&phash, hamming_threshold, dedup_config.top_k as i32)
+                .await
+            {
                 Ok(results) => {
-                    let is_dup = !results.is_empty();
-                    if is_dup {
+                    if !results.is_empty() {
                         log::info!(
                             "🔍 SIMILAR DUPLICATE (Milvus): Video {} matches {} videos",
                             video_id,
                             results.len()
                         );
                     }
-                    is_dup
+                    results.into_iter().next()
                 }
                 Err(e) => {
                     log::warn!(
@@ -663,7 +842,7 @@ impl VideoHashDuplication {
                         video_id,
                         e
                     );
-                    false
+                    None
                 }
             }
         } else {
@@ -671,8 +850,23 @@ impl VideoHashDuplication {
                 "Milvus client not available, treating video {} as unique",
                 video_id
             );
-            false
+            None
         };
+        let is_duplicate = closest_match.is_some();
+        let blocked = closest_match
+            .as_ref()
+            .is_some_and(|closest| auto_block.blocks(closest.hamming_distance));
+        Self::record_dedup_decision(
+            kvrocks_client,
+            video_id,
+            &phash,
+            closest_match
+                .as_ref()
+                .map(|closest| (closest.video_id.as_str(), closest.hamming_distance)),
+            hamming_threshold,
+            blocked,
+        )
+        .await;
 
         // Store the phash regardless of duplication status (kvrocks push is inside the functions)
         self.store_videohash_original(bigquery_client, kvrocks_client, video_id, &phash)
@@ -685,6 +879,7 @@ impl VideoHashDuplication {
             video_id,
             &publisher_data.post_id,
             &publisher_data.publisher_principal,
+            metadata.content_sha256.as_deref(),
         )
         .await?;
 
@@ -697,9 +892,7 @@ impl VideoHashDuplication {
 
                 // Insert into Milvus
                 log::debug!("Inserting unique video into Milvus: {}", video_id);
-                if let Err(e) =
-                    crate::milvus::insert_video_hash(client, video_id, &phash, created_at).await
-                {
+                if let Err(e) = client.insert_video_hash(video_id, &phash, created_at).await {
                     log::error!("Failed to insert video {} into Milvus: {}", video_id, e);
                 }
 
@@ -721,6 +914,16 @@ impl VideoHashDuplication {
             );
         }
 
+        if let Some(closest) = closest_match.as_ref().filter(|_| blocked) {
+            return Self::block_duplicate(
+                kvrocks_client,
+                video_id,
+                &closest.video_id,
+                closest.hamming_distance,
+            )
+            .await;
+        }
+
         // Always proceed with normal video processing, regardless of duplicate status
         let timestamp = chrono::Utc::now().to_rfc3339();
         publish_video_callback(
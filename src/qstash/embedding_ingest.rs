@@ -0,0 +1,247 @@
+use crate::app_state::AppState;
+use crate::milvus::embeddings::{mean_embedding, VideoEmbeddingRecord, EMBEDDING_DIM};
+use crate::milvus::MilvusManager;
+use anyhow::{Context, Result};
+use axum::{extract::State, http::StatusCode, Json};
+use google_cloud_bigquery::http::job::get_query_results::GetQueryResultsRequest;
+use google_cloud_bigquery::http::job::query::QueryRequest;
+use google_cloud_bigquery::http::tabledata::list::{Tuple, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+/// Set of video ids already in the embedding collection. Milvus inserts
+/// don't dedupe on primary key, so re-runs must skip these.
+const INGESTED_SET_KEY: &str = "offchain:milvus:embedding_ingested";
+const PAGE_SIZE: i64 = 10000;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct IngestEmbeddingsRequest {
+    /// Only embeddings updated in the last N hours; omit for a full backfill
+    #[serde(default)]
+    pub since_hours: Option<u32>,
+
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+}
+
+fn default_batch_size() -> usize {
+    500
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IngestEmbeddingsResponse {
+    pub total_inserted: u32,
+    pub skipped: u32,
+    pub failed: u32,
+}
+
+/// QStash handler to ingest video embeddings from BigQuery into the Milvus
+/// embedding collection. Per-segment embeddings are averaged per video.
+/// Spawns background task and returns immediately
+#[utoipa::path(
+    post,
+    path = "/milvus/ingest_embeddings",
+    request_body = IngestEmbeddingsRequest,
+    responses(
+        (status = 202, description = "Embedding ingestion started"),
+        (status = 503, description = "Milvus service unavailable")
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state))]
+pub async fn ingest_embeddings_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<IngestEmbeddingsRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let Some(milvus_client) = state.milvus_client.clone() else {
+        log::error!("Milvus client not initialized");
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    };
+
+    tokio::spawn(async move {
+        match process_embedding_ingest(&state, &milvus_client, &req).await {
+            Ok(response) => log::info!(
+                "Embedding ingestion completed: inserted={}, skipped={}, failed={}",
+                response.total_inserted,
+                response.skipped,
+                response.failed
+            ),
+            Err(e) => log::error!("Embedding ingestion failed: {e:#}"),
+        }
+    });
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn process_embedding_ingest(
+    state: &AppState,
+    milvus_client: &MilvusManager,
+    req: &IngestEmbeddingsRequest,
+) -> Result<IngestEmbeddingsResponse> {
+    let segments = fetch_embedding_segments(state, req.since_hours).await?;
+
+    let mut by_video: HashMap<String, Vec<Vec<f32>>> = HashMap::new();
+    for (video_id, embedding) in segments {
+        by_video.entry(video_id).or_default().push(embedding);
+    }
+
+    let mut conn = state
+        .rewards_module
+        .dragonfly_pool
+        .get()
+        .await
+        .context("Failed to get Dragonfly connection")?;
+
+    let video_ids: Vec<String> = by_video.keys().cloned().collect();
+    let mut already_ingested = Vec::with_capacity(video_ids.len());
+    for chunk in video_ids.chunks(1000) {
+        let flags: Vec<bool> = redis::cmd("SMISMEMBER")
+            .arg(INGESTED_SET_KEY)
+            .arg(chunk)
+            .query_async(&mut conn)
+            .await
+            .context("Failed to check ingested embeddings")?;
+        already_ingested.extend(flags);
+    }
+
+    let created_at = chrono::Utc::now().timestamp();
+    let mut skipped = 0;
+    let records: Vec<VideoEmbeddingRecord> = video_ids
+        .into_iter()
+        .zip(already_ingested)
+        .filter_map(|(video_id, ingested)| {
+            let embedding = (!ingested)
+                .then(|| mean_embedding(&by_video[&video_id]))
+                .flatten();
+            if embedding.is_none() {
+                skipped += 1;
+            }
+            embedding.map(|embedding| VideoEmbeddingRecord {
+                video_id,
+                embedding,
+                created_at,
+            })
+        })
+        .collect();
+
+    let mut total_inserted = 0;
+    let mut failed = 0;
+
+    for chunk in records.chunks(req.batch_size.max(1)) {
+        if let Err(e) = milvus_client.insert_embeddings(chunk.to_vec()).await {
+            log::error!("Failed to insert embedding batch: {}", e);
+            failed += chunk.len() as u32;
+            continue;
+        }
+
+        let ids: Vec<&str> = chunk.iter().map(|r| r.video_id.as_str()).collect();
+        redis::cmd("SADD")
+            .arg(INGESTED_SET_KEY)
+            .arg(&ids)
+            .query_async::<()>(&mut conn)
+            .await
+            .context("Failed to mark embeddings as ingested")?;
+        total_inserted += chunk.len() as u32;
+    }
+
+    Ok(IngestEmbeddingsResponse {
+        total_inserted,
+        skipped,
+        failed,
+    })
+}
+
+fn parse_segment_row(row: &Tuple) -> Option<(String, Vec<f32>)> {
+    let video_id = match &row.f.first()?.v {
+        Value::String(s) => s.clone(),
+        _ => return None,
+    };
+    let embedding: Vec<f32> = match &row.f.get(1)?.v {
+        Value::Array(arr) => arr
+            .iter()
+            .filter_map(|cell| match &cell.v {
+                Value::String(s) => s.parse::<f32>().ok(),
+                _ => None,
+            })
+            .collect(),
+        _ => return None,
+    };
+    (embedding.len() == EMBEDDING_DIM).then_some((video_id, embedding))
+}
+
+/// Pages through `video_embeddings_agg`, returning one entry per segment
+async fn fetch_embedding_segments(
+    state: &AppState,
+    since_hours: Option<u32>,
+) -> Result<Vec<(String, Vec<f32>)>> {
+    let since_filter = since_hours
+        .map(|hours| {
+            format!(
+                " AND SAFE_CAST(updated AS TIMESTAMP) >= TIMESTAMP_SUB(CURRENT_TIMESTAMP(), INTERVAL {hours} HOUR)"
+            )
+        })
+        .unwrap_or_default();
+    let query = format!(
+        "SELECT video_id, ml_generate_embedding_result
+         FROM `hot-or-not-feed-intelligence`.`yral_ds`.`video_embeddings_agg`
+         WHERE video_id IS NOT NULL
+           AND ARRAY_LENGTH(ml_generate_embedding_result) = {EMBEDDING_DIM}{since_filter}"
+    );
+
+    let request = QueryRequest {
+        query,
+        max_results: Some(PAGE_SIZE),
+        ..Default::default()
+    };
+
+    let mut response = state
+        .bigquery_client
+        .job()
+        .query("hot-or-not-feed-intelligence", &request)
+        .await
+        .context("Failed to query video embeddings")?;
+
+    let mut segments: Vec<(String, Vec<f32>)> = response
+        .rows
+        .take()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(parse_segment_row)
+        .collect();
+
+    while let Some(page_token) = response.page_token.take().filter(|t| !t.is_empty()) {
+        let job_ref = &response.job_reference;
+        let get_results_req = GetQueryResultsRequest {
+            start_index: 0,
+            page_token: Some(page_token),
+            max_results: Some(PAGE_SIZE),
+            timeout_ms: None,
+            location: job_ref.location.clone(),
+            format_options: None,
+        };
+
+        let page = state
+            .bigquery_client
+            .job()
+            .get_query_results(&job_ref.project_id, &job_ref.job_id, &get_results_req)
+            .await
+            .context("Failed to fetch video embeddings page")?;
+
+        segments.extend(
+            page.rows
+                .unwrap_or_default()
+                .iter()
+                .filter_map(parse_segment_row),
+        );
+        response.page_token = page.page_token;
+    }
+
+    log::info!(
+        "Fetched {} embedding segments from BigQuery",
+        segments.len()
+    );
+    Ok(segments)
+}
@@ -0,0 +1,601 @@
+//! Typed QStash jobs.
+//!
+//! Every payload published to this service's `/qstash/` routes implements
+//! [`JobPayload`], which declares the route it is delivered to and how:
+//! default delay, retries, flow control and priority [`Lane`].
+//! [`QStashClient::publish_job`] turns that into the Upstash headers, so call
+//! sites only build the payload and per-call settings (a schedule, a
+//! callback, a lane) go in [`PublishOptions`].
+//!
+//! Lanes keep bulk work from delaying work a user is waiting on: realtime
+//! and bulk deliveries get their own flow-control keys, and bulk runs at
+//! half the job's declared rate and parallelism.
+//!
+//! [`QStashClient::publish_job`]: super::client::QStashClient::publish_job
+
+use std::{borrow::Cow, time::Duration};
+
+use chrono::Timelike;
+use serde::Serialize;
+use videogen_common::VideoGenerator;
+
+use crate::{
+    events::event::{storj::StorjBackfillChunk, UploadVideoInfoV2},
+    posts::report_post::ReportPostRequestV3,
+    qstash::{
+        phash_bulk::ComputePhashRequest, service_canister_migration::MigrateIndividualUserRequest,
+    },
+    videogen::{
+        qstash_types::QstashVideoGenRequest,
+        upload_ai_generated_video_to_canister_in_drafts::UploadAiVideoToCanisterRequest,
+    },
+    webhook_subscriptions::delivery::WebhookDeliveryJob,
+};
+#[cfg(not(feature = "local-bin"))]
+use crate::{
+    posts::{cleanup::PostCleanupRequest, feed_cache_invalidation::FeedCacheInvalidationRequest},
+    video_availability::GcsReuploadRequest,
+    video_processing::{transcode::TranscodeVideoRequest, transcribe::TranscribeVideoRequest},
+};
+
+/// Upstash flow control: deliveries sharing a key are limited together
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowControl {
+    pub key: Cow<'static, str>,
+    /// Deliveries per second
+    pub rate: u32,
+    pub parallelism: u32,
+}
+
+impl FlowControl {
+    pub const fn new(key: &'static str, rate: u32, parallelism: u32) -> Self {
+        Self {
+            key: Cow::Borrowed(key),
+            rate,
+            parallelism,
+        }
+    }
+
+    pub fn header_value(&self) -> String {
+        format!("Rate={},Parallelism={}", self.rate, self.parallelism)
+    }
+}
+
+/// Priority lane of a delivery
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Lane {
+    /// Work on a fresh upload or request someone is waiting on
+    Realtime,
+    #[default]
+    Default,
+    /// Backfills and other bulk re-processing
+    Bulk,
+}
+
+impl Lane {
+    /// The lane's own key; the default lane keeps the job's key as is
+    fn keyed(self, flow_control: FlowControl) -> FlowControl {
+        let suffix = match self {
+            Lane::Realtime => "_REALTIME",
+            Lane::Default => return flow_control,
+            Lane::Bulk => "_BULK",
+        };
+        FlowControl {
+            key: format!("{}{suffix}", flow_control.key).into(),
+            ..flow_control
+        }
+    }
+
+    /// The lane's key and share of the job's declared budget
+    fn budgeted(self, flow_control: FlowControl) -> FlowControl {
+        let flow_control = self.keyed(flow_control);
+        match self {
+            Lane::Bulk => FlowControl {
+                rate: flow_control.rate.div_ceil(2),
+                parallelism: flow_control.parallelism.div_ceil(2),
+                ..flow_control
+            },
+            Lane::Realtime | Lane::Default => flow_control,
+        }
+    }
+}
+
+pub trait JobPayload: Serialize {
+    /// Route under `/qstash/`
+    fn path(&self) -> Cow<'static, str>;
+
+    fn default_delay(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Upstash's default applies when unset
+    fn retries(&self) -> Option<u32> {
+        None
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        None
+    }
+
+    fn lane(&self) -> Lane {
+        Lane::Default
+    }
+}
+
+/// Per-publish settings that override the job's own. An explicit flow
+/// control still moves to the lane's key but keeps its budget.
+#[derive(Debug, Clone, Default)]
+pub struct PublishOptions {
+    pub delay: Option<Duration>,
+    pub flow_control: Option<FlowControl>,
+    pub lane: Option<Lane>,
+    pub callback_url: Option<String>,
+}
+
+impl PublishOptions {
+    /// Deliver after `seconds`; negative delays deliver right away
+    pub fn delayed_secs(seconds: i64) -> Self {
+        Self {
+            delay: Some(Duration::from_secs(seconds.max(0) as u64)),
+            ..Default::default()
+        }
+    }
+
+    pub fn in_lane(lane: Lane) -> Self {
+        Self {
+            lane: Some(lane),
+            ..Default::default()
+        }
+    }
+}
+
+/// Upstash headers for publishing `job`, with `options` taking precedence
+pub fn upstash_headers<T: JobPayload>(
+    job: &T,
+    options: &PublishOptions,
+) -> Vec<(&'static str, String)> {
+    let mut headers = Vec::new();
+    if let Some(delay) = options.delay.or_else(|| job.default_delay()) {
+        headers.push(("Upstash-Delay", delay_header(delay)));
+    }
+    if let Some(retries) = job.retries() {
+        headers.push(("Upstash-Retries", retries.to_string()));
+    }
+    let lane = options.lane.unwrap_or_else(|| job.lane());
+    let flow_control = match options.flow_control.clone() {
+        Some(flow_control) => Some(lane.keyed(flow_control)),
+        None => job
+            .flow_control()
+            .map(|flow_control| lane.budgeted(flow_control)),
+    };
+    if let Some(flow_control) = flow_control {
+        headers.push(("Upstash-Flow-Control-Key", flow_control.key.to_string()));
+        headers.push(("Upstash-Flow-Control-Value", flow_control.header_value()));
+    }
+    if let Some(callback_url) = &options.callback_url {
+        headers.push(("Upstash-Callback", callback_url.clone()));
+    }
+    headers
+}
+
+fn delay_header(delay: Duration) -> String {
+    if delay.subsec_millis() == 0 {
+        format!("{}s", delay.as_secs())
+    } else {
+        format!("{}ms", delay.as_millis())
+    }
+}
+
+/// Spreads a burst of deliveries over up to `max_ms`
+fn jitter(max_ms: u32) -> Duration {
+    Duration::from_millis((chrono::Utc::now().nanosecond() % (max_ms + 1)) as u64)
+}
+
+impl JobPayload for storj_interface::duplicate::Args {
+    fn path(&self) -> Cow<'static, str> {
+        "storj_ingest".into()
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl::new("STORJ_INGESTION", 20, 10))
+    }
+
+    /// Backfill items and availability repairs publish in the bulk lane
+    fn lane(&self) -> Lane {
+        Lane::Realtime
+    }
+}
+
+impl JobPayload for StorjBackfillChunk {
+    fn path(&self) -> Cow<'static, str> {
+        "storj_backfill_chunk".into()
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl::new("STORJ_BACKFILL", 2, 2))
+    }
+
+    fn lane(&self) -> Lane {
+        Lane::Bulk
+    }
+}
+
+#[cfg(not(feature = "local-bin"))]
+impl JobPayload for TranscodeVideoRequest {
+    fn path(&self) -> Cow<'static, str> {
+        "transcode_video".into()
+    }
+
+    fn retries(&self) -> Option<u32> {
+        Some(2)
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl::new("VIDEO_TRANSCODE", 5, 2))
+    }
+
+    fn lane(&self) -> Lane {
+        Lane::Realtime
+    }
+}
+
+#[cfg(not(feature = "local-bin"))]
+impl JobPayload for TranscribeVideoRequest {
+    fn path(&self) -> Cow<'static, str> {
+        "transcribe_video".into()
+    }
+
+    fn retries(&self) -> Option<u32> {
+        Some(2)
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl::new("VIDEO_TRANSCRIBE", 5, 3))
+    }
+
+    fn lane(&self) -> Lane {
+        Lane::Realtime
+    }
+}
+
+/// Failed artifacts are retried by redelivery; steps already done are skipped
+#[cfg(not(feature = "local-bin"))]
+impl JobPayload for PostCleanupRequest {
+    fn path(&self) -> Cow<'static, str> {
+        "post_cleanup".into()
+    }
+
+    fn retries(&self) -> Option<u32> {
+        Some(5)
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl::new("POST_CLEANUP", 10, 3))
+    }
+}
+
+/// Each delivery fans out over every user's caches in the feed cache service,
+/// so keep only a couple in flight
+#[cfg(not(feature = "local-bin"))]
+impl JobPayload for FeedCacheInvalidationRequest {
+    fn path(&self) -> Cow<'static, str> {
+        "feed_cache_invalidation".into()
+    }
+
+    fn retries(&self) -> Option<u32> {
+        Some(3)
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl::new("FEED_CACHE_INVALIDATION", 2, 2))
+    }
+
+    fn lane(&self) -> Lane {
+        Lane::Bulk
+    }
+}
+
+/// Each delivery moves a whole video from Storj to GCS
+#[cfg(not(feature = "local-bin"))]
+impl JobPayload for GcsReuploadRequest {
+    fn path(&self) -> Cow<'static, str> {
+        "video_availability/gcs_reupload".into()
+    }
+
+    fn retries(&self) -> Option<u32> {
+        Some(3)
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl::new("GCS_REUPLOAD", 4, 4))
+    }
+
+    fn lane(&self) -> Lane {
+        Lane::Bulk
+    }
+}
+
+/// Upstash backs off exponentially between attempts, so the last retry
+/// lands hours after the event
+impl JobPayload for WebhookDeliveryJob {
+    fn path(&self) -> Cow<'static, str> {
+        "webhooks/deliver".into()
+    }
+
+    fn retries(&self) -> Option<u32> {
+        Some(8)
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl {
+            key: format!("WEBHOOK_{}", self.subscription_id).into(),
+            rate: 10,
+            parallelism: 5,
+        })
+    }
+}
+
+impl JobPayload for ReportPostRequestV3 {
+    fn path(&self) -> Cow<'static, str> {
+        "report_post".into()
+    }
+}
+
+impl JobPayload for ComputePhashRequest {
+    fn path(&self) -> Cow<'static, str> {
+        "compute_video_phash".into()
+    }
+
+    fn retries(&self) -> Option<u32> {
+        Some(1)
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl::new("COMPUTE_PHASH", 10, 5))
+    }
+
+    fn lane(&self) -> Lane {
+        Lane::Bulk
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExtractFramesJob<'a> {
+    pub video_id: &'a str,
+    pub video_info: &'a UploadVideoInfoV2,
+}
+
+impl JobPayload for ExtractFramesJob<'_> {
+    fn path(&self) -> Cow<'static, str> {
+        "enqueue_video_frames".into()
+    }
+
+    fn default_delay(&self) -> Option<Duration> {
+        Some(jitter(300))
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl::new("VIDEO_FRAMES_PROCESSING", 50, 20))
+    }
+
+    fn lane(&self) -> Lane {
+        Lane::Realtime
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NsfwDetectionJob<'a> {
+    pub video_id: &'a str,
+    pub video_info: &'a UploadVideoInfoV2,
+}
+
+impl JobPayload for NsfwDetectionJob<'_> {
+    fn path(&self) -> Cow<'static, str> {
+        "enqueue_video_nsfw_detection".into()
+    }
+
+    fn default_delay(&self) -> Option<Duration> {
+        Some(jitter(500))
+    }
+
+    fn retries(&self) -> Option<u32> {
+        Some(5)
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl::new("VIDEO_NSFW_DETECTION", 30, 15))
+    }
+
+    fn lane(&self) -> Lane {
+        Lane::Realtime
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NsfwDetectionV2Job<'a> {
+    pub video_id: &'a str,
+    pub video_info: &'a UploadVideoInfoV2,
+}
+
+impl JobPayload for NsfwDetectionV2Job<'_> {
+    fn path(&self) -> Cow<'static, str> {
+        "enqueue_video_nsfw_detection_v2".into()
+    }
+
+    /// An hour after the next :20 of the hour, plus up to 10 minutes of jitter
+    fn default_delay(&self) -> Option<Duration> {
+        let minute = chrono::Utc::now().minute();
+        let minutes_until_20 = if minute >= 20 {
+            60 - minute + 20
+        } else {
+            20 - minute
+        };
+        let jitter_secs = chrono::Utc::now().nanosecond() % 601;
+        Some(Duration::from_secs(
+            (minutes_until_20 * 60 + jitter_secs + 3600) as u64,
+        ))
+    }
+
+    fn retries(&self) -> Option<u32> {
+        Some(5)
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        Some(FlowControl::new("VIDEO_NSFW_DETECTION_V2", 20, 10))
+    }
+}
+
+impl JobPayload for QstashVideoGenRequest {
+    fn path(&self) -> Cow<'static, str> {
+        "process_video_gen".into()
+    }
+
+    fn retries(&self) -> Option<u32> {
+        Some(0)
+    }
+
+    fn flow_control(&self) -> Option<FlowControl> {
+        let (rate, parallelism) = self.input.flow_control_config()?;
+        Some(FlowControl {
+            key: self.input.flow_control_key().into(),
+            rate,
+            parallelism,
+        })
+    }
+}
+
+impl JobPayload for UploadAiVideoToCanisterRequest {
+    fn path(&self) -> Cow<'static, str> {
+        "upload_ai_generated_video_to_canister_in_drafts".into()
+    }
+}
+
+impl JobPayload for MigrateIndividualUserRequest {
+    fn path(&self) -> Cow<'static, str> {
+        "run_user_migration".into()
+    }
+
+    fn retries(&self) -> Option<u32> {
+        Some(3)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TournamentStep {
+    Start,
+    Finalize,
+    ClaimReminder,
+    ExpireClaims,
+}
+
+/// A scheduled step of a tournament's lifecycle; the id travels in the path
+#[derive(Debug, Serialize)]
+pub struct TournamentStepJob<'a> {
+    #[serde(skip)]
+    pub tournament_id: &'a str,
+    #[serde(skip)]
+    pub step: TournamentStep,
+}
+
+impl JobPayload for TournamentStepJob<'_> {
+    fn path(&self) -> Cow<'static, str> {
+        let step = match self.step {
+            TournamentStep::Start => "start",
+            TournamentStep::Finalize => "finalize",
+            TournamentStep::ClaimReminder => "claim_reminder",
+            TournamentStep::ExpireClaims => "expire_claims",
+        };
+        format!("tournament/{step}/{}", self.tournament_id).into()
+    }
+
+    fn retries(&self) -> Option<u32> {
+        match self.step {
+            TournamentStep::ExpireClaims => Some(3),
+            _ => Some(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upstash_headers() {
+        let job = TournamentStepJob {
+            tournament_id: "t1",
+            step: TournamentStep::ExpireClaims,
+        };
+        assert_eq!(job.path(), "tournament/expire_claims/t1");
+        assert_eq!(serde_json::to_string(&job).unwrap(), "{}");
+        assert_eq!(
+            upstash_headers(&job, &PublishOptions::delayed_secs(90)),
+            vec![
+                ("Upstash-Delay", "90s".to_string()),
+                ("Upstash-Retries", "3".to_string()),
+            ]
+        );
+
+        #[cfg(not(feature = "local-bin"))]
+        {
+            let cleanup = PostCleanupRequest {
+                video_id: "v".to_string(),
+                post_id: "p".to_string(),
+                publisher_user_id: "u".to_string(),
+            };
+            let options = PublishOptions {
+                flow_control: Some(FlowControl::new("POST_CLEANUP", 1, 1)),
+                callback_url: Some("https://example.com/cb".to_string()),
+                ..PublishOptions::delayed_secs(-5)
+            };
+            assert_eq!(
+                upstash_headers(&cleanup, &options),
+                vec![
+                    ("Upstash-Delay", "0s".to_string()),
+                    ("Upstash-Retries", "5".to_string()),
+                    ("Upstash-Flow-Control-Key", "POST_CLEANUP".to_string()),
+                    (
+                        "Upstash-Flow-Control-Value",
+                        "Rate=1,Parallelism=1".to_string()
+                    ),
+                    ("Upstash-Callback", "https://example.com/cb".to_string()),
+                ]
+            );
+        }
+        assert_eq!(delay_header(Duration::from_millis(250)), "250ms");
+
+        // Bulk jobs get their own key and half the declared budget
+        let chunk = StorjBackfillChunk {
+            backfill_id: "b".to_string(),
+            video_ids: vec![],
+        };
+        assert_eq!(
+            upstash_headers(&chunk, &PublishOptions::default()),
+            vec![
+                (
+                    "Upstash-Flow-Control-Key",
+                    "STORJ_BACKFILL_BULK".to_string()
+                ),
+                (
+                    "Upstash-Flow-Control-Value",
+                    "Rate=1,Parallelism=1".to_string()
+                ),
+            ]
+        );
+        let options = PublishOptions {
+            flow_control: Some(FlowControl::new("STORJ_BACKFILL", 8, 4)),
+            ..PublishOptions::in_lane(Lane::Realtime)
+        };
+        assert_eq!(
+            upstash_headers(&chunk, &options)[1],
+            (
+                "Upstash-Flow-Control-Value",
+                "Rate=8,Parallelism=4".to_string()
+            )
+        );
+        assert_eq!(
+            upstash_headers(&chunk, &options)[0].1,
+            "STORJ_BACKFILL_REALTIME"
+        );
+    }
+}
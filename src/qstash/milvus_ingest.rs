@@ -1,6 +1,6 @@
 use crate::app_state::AppState;
-use crate::kvrocks::VideoDedupStatus;
-use crate::milvus::{self, Client as MilvusClient};
+use crate::kvrocks::{tables, VideoDedupStatus};
+use crate::milvus::{self, MilvusManager};
 use anyhow::{Context, Result};
 use axum::{extract::State, http::StatusCode, Json};
 use futures::stream::{self, StreamExt};
@@ -256,7 +256,7 @@ pub struct DeduplicateResponse {
 /// Spawns background task and returns immediately
 #[utoipa::path(
     post,
-    path = "/qstash/milvus/ingest_phash",
+    path = "/milvus/ingest_phash",
     request_body = IngestPhashRequest,
     responses(
         (status = 202, description = "Batch ingestion started"),
@@ -320,7 +320,7 @@ pub async fn ingest_phash_to_milvus_handler(
 /// Spawns background task and returns immediately
 #[utoipa::path(
     post,
-    path = "/qstash/milvus/backfill_unique_videos",
+    path = "/milvus/backfill_unique_videos",
     request_body = IngestPhashRequest,
     responses(
         (status = 202, description = "Backfill started"),
@@ -382,7 +382,7 @@ pub async fn backfill_unique_videos_handler(
 #[cfg(not(feature = "local-bin"))]
 async fn process_batch(
     state: &AppState,
-    milvus_client: &MilvusClient,
+    milvus_client: &MilvusManager,
     req: &IngestPhashRequest,
 ) -> Result<IngestPhashResponse> {
     // 1. Fetch unprocessed videos from BigQuery
@@ -484,7 +484,7 @@ async fn process_batch(
 #[cfg(not(feature = "local-bin"))]
 async fn process_backfill_batch(
     state: &AppState,
-    milvus_client: &MilvusClient,
+    milvus_client: &MilvusManager,
     req: &IngestPhashRequest,
 ) -> Result<IngestPhashResponse> {
     // 1. Fetch unique videos from BigQuery (video_unique JOIN videohash_phash)
@@ -670,12 +670,13 @@ async fn store_unique_phash_in_redis(
 #[cfg(not(feature = "local-bin"))]
 async fn process_single_video(
     state: &AppState,
-    milvus_client: &MilvusClient,
+    milvus_client: &MilvusManager,
     video_id: &str,
     phash: &str,
     metrics: &mut MetricsCollector,
 ) -> Result<bool> {
-    const HAMMING_THRESHOLD: u32 = 20;
+    let dedup_config =
+        super::dedup_config::get_dedup_config(&state.rewards_module.dragonfly_pool).await;
 
     // TIER 1: Check Redis for exact match (FAST - <1ms)
     log::debug!("Tier 1: Checking Redis for exact phash match");
@@ -713,7 +714,7 @@ async fn process_single_video(
     // TIER 2: Check Milvus for similar matches (SLOWER - 10-50ms)
     log::debug!(
         "Tier 2: Checking Milvus for similar videos (Hamming distance < {})",
-        HAMMING_THRESHOLD
+        dedup_config.backfill_hamming_threshold
     );
 
     // Check if collection has any data (to avoid SDK panic on empty collection)
@@ -721,7 +722,12 @@ async fn process_single_video(
 
     let start = Instant::now();
     let similar_videos = if collection_has_data {
-        milvus::search_similar_videos(milvus_client, phash, HAMMING_THRESHOLD)
+        milvus_client
+            .search_similar_videos(
+                phash,
+                dedup_config.backfill_hamming_threshold,
+                dedup_config.top_k as i32,
+            )
             .await
             .context("Failed to search in Milvus")?
     } else {
@@ -763,7 +769,8 @@ async fn process_single_video(
 
         // Store in Milvus (only unique hashes)
         let start = Instant::now();
-        milvus::insert_video_hash(milvus_client, video_id, phash, created_at)
+        milvus_client
+            .insert_video_hash(video_id, phash, created_at)
             .await
             .context("Failed to insert into Milvus")?;
         metrics.record_milvus_insert(start.elapsed().as_micros());
@@ -864,10 +871,16 @@ async fn store_dedup_status(
         duplicate_of,
         hamming_distance,
         ingested_at: chrono::Utc::now().to_rfc3339(),
+        hamming_threshold: None,
+        auto_blocked: None,
     };
     if let Err(e) = state
         .kvrocks_client
-        .store_video_dedup_status(&dedup_status)
+        .put(
+            &tables::VIDEO_DEDUP_STATUS,
+            &dedup_status.video_id,
+            &dedup_status,
+        )
         .await
     {
         log::error!("Error pushing dedup_status to kvrocks: {}", e);
@@ -881,7 +894,7 @@ async fn store_dedup_status(
 #[cfg(not(feature = "local-bin"))]
 #[utoipa::path(
     post,
-    path = "/qstash/milvus/bulk_ingest_unique_hashes",
+    path = "/milvus/bulk_ingest_unique_hashes",
     request_body = BulkIngestRequest,
     responses(
         (status = 202, description = "Bulk ingestion started"),
@@ -931,7 +944,7 @@ pub async fn bulk_ingest_unique_hashes_handler(
 #[cfg(not(feature = "local-bin"))]
 async fn process_bulk_ingest(
     state: &AppState,
-    milvus_client: &MilvusClient,
+    milvus_client: &MilvusManager,
     req: &BulkIngestRequest,
 ) -> Result<BulkIngestResponse> {
     log::info!("Fetching ALL unique videos from video_unique table");
@@ -1118,7 +1131,7 @@ async fn fetch_all_unique_videos(state: &AppState) -> Result<Vec<(String, String
 #[cfg(not(feature = "local-bin"))]
 async fn bulk_insert_to_milvus_and_redis(
     state: &AppState,
-    milvus_client: &MilvusClient,
+    milvus_client: &MilvusManager,
     videos: &[(String, String)],
 ) -> Result<u32> {
     let created_at = chrono::Utc::now().timestamp();
@@ -1142,7 +1155,8 @@ async fn bulk_insert_to_milvus_and_redis(
         .collect();
 
     // Insert batch into Milvus
-    milvus::insert_batch_video_hashes(milvus_client, records)
+    milvus_client
+        .insert_batch_video_hashes(records)
         .await
         .context("Failed to insert batch into Milvus")?;
 
@@ -1173,7 +1187,7 @@ async fn bulk_insert_to_milvus_and_redis(
 #[cfg(not(feature = "local-bin"))]
 #[utoipa::path(
     post,
-    path = "/qstash/milvus/deduplicate_videos",
+    path = "/milvus/deduplicate_videos",
     request_body = DeduplicateRequest,
     responses(
         (status = 202, description = "Deduplication started"),
@@ -1227,7 +1241,7 @@ pub async fn deduplicate_videos_handler(
 #[cfg(not(feature = "local-bin"))]
 async fn process_deduplicate(
     state: &AppState,
-    milvus_client: &MilvusClient,
+    milvus_client: &MilvusManager,
     req: &DeduplicateRequest,
 ) -> Result<DeduplicateResponse> {
     log::info!(
@@ -1486,7 +1500,7 @@ async fn fetch_unprocessed_videos_for_threshold(
 #[cfg(not(feature = "local-bin"))]
 async fn process_deduplicate_video(
     state: &AppState,
-    milvus_client: &MilvusClient,
+    milvus_client: &MilvusManager,
     video_id: &str,
     phash: &str,
     hamming_distance: u32,
@@ -1495,7 +1509,8 @@ async fn process_deduplicate_video(
     // Search for top 2 results: [self-match, nearest_neighbor]
     log::debug!("Searching for duplicates of video: {}", video_id);
     let start = Instant::now();
-    let results = milvus::search_similar_videos_topk2(milvus_client, phash)
+    let results = milvus_client
+        .search_similar_videos_topk2(phash)
         .await
         .context("Failed to search in Milvus")?;
     metrics.record_milvus_search(start.elapsed().as_micros());
@@ -1621,10 +1636,16 @@ async fn store_dedup_status_with_table(
         duplicate_of,
         hamming_distance: hamming_distance_actual,
         ingested_at: chrono::Utc::now().to_rfc3339(),
+        hamming_threshold: Some(hamming_distance_threshold),
+        auto_blocked: None,
     };
     if let Err(e) = state
         .kvrocks_client
-        .store_video_dedup_status(&dedup_status)
+        .put(
+            &tables::VIDEO_DEDUP_STATUS,
+            &dedup_status.video_id,
+            &dedup_status,
+        )
         .await
     {
         log::error!("Error pushing dedup_status to kvrocks: {}", e);
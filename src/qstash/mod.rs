@@ -3,29 +3,31 @@ mod verify;
 use std::sync::Arc;
 
 use axum::middleware;
-use axum::{extract::State, response::Response, routing::post, Json, Router};
+use axum::{extract::State, response::Response, Json};
 use http::StatusCode;
 use jsonwebtoken::{Algorithm, DecodingKey, Validation};
 use serde::Deserialize;
 use tower::ServiceBuilder;
 use tracing::instrument;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
 
+use crate::app_state::AppState;
 use crate::pipeline::Step;
 use crate::qstash::duplicate::VideoPublisherDataV2;
-use crate::qstash::service_canister_migration::{
-    migrate_individual_user_to_service_canister, transfer_all_posts_for_the_individual_user,
-    update_the_metadata_mapping,
-};
 use crate::qstash::verify::verify_qstash_message;
 use crate::setup_context;
-use crate::{
-    app_state::AppState, events::event::storj::storj_ingest,
-    posts::report_post::qstash_report_post, rewards::api::update_reward_config,
-};
 
 pub mod client;
+#[cfg(not(feature = "local-bin"))]
+pub mod dedup_config;
+#[cfg(not(feature = "local-bin"))]
+pub mod drain;
 pub mod duplicate;
 #[cfg(not(feature = "local-bin"))]
+pub mod embedding_ingest;
+pub mod job;
+#[cfg(not(feature = "local-bin"))]
 pub mod milvus_ingest;
 pub mod phash_bulk;
 pub mod service_canister_migration;
@@ -51,14 +53,26 @@ impl QStashState {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct VideoHashIndexingRequest {
     video_id: String,
     video_url: String,
     publisher_data: VideoPublisherDataV2,
 }
 
+/// Runs perceptual-hash deduplication for a new upload, then hands it off to
+/// NSFW detection
 #[cfg(not(feature = "local-bin"))]
+#[utoipa::path(
+    post,
+    path = "/video_deduplication",
+    request_body = VideoHashIndexingRequest,
+    responses(
+        (status = 200, description = "Video deduplication check completed", body = String),
+        (status = 500, description = "Deduplication failed")
+    ),
+    tag = "qstash"
+)]
 #[instrument(skip(state))]
 async fn video_deduplication_handler(
     State(state): State<Arc<AppState>>,
@@ -85,7 +99,6 @@ async fn video_deduplication_handler(
             &req.video_id,
             &req.video_url,
             publisher_data,
-            30, // Default hamming threshold
             move |vid_id, post_id, timestamp, publisher_user_id| {
                 // Clone the values to ensure they have 'static lifetime
                 let vid_id = vid_id.to_string();
@@ -123,86 +136,122 @@ async fn video_deduplication_handler(
 }
 
 #[instrument(skip(app_state))]
-pub fn qstash_router<S>(app_state: Arc<AppState>) -> Router<S> {
-    let mut router = Router::new();
+pub fn qstash_router<S>(app_state: Arc<AppState>) -> OpenApiRouter<S> {
+    let mut router = OpenApiRouter::new();
 
     #[cfg(not(feature = "local-bin"))]
     {
-        router = router.route("/video_deduplication", post(video_deduplication_handler));
+        router = router.routes(routes!(video_deduplication_handler));
     }
 
     // Retired video GCS/frame/NSFW QStash routes stay unmounted; the handlers remain in the repo only for cleanup/rollback context.
     let router = router
-        .route("/storj_ingest", post(storj_ingest))
-        .route("/report_post", post(qstash_report_post))
-        .route(
-            "/process_video_gen",
-            post(crate::videogen::qstash_process::process_video_generation),
-        )
-        .route(
-            "/video_gen_callback",
-            post(crate::videogen::qstash_callback::handle_video_gen_callback),
-        )
-        .route(
-            "/upload_ai_generated_video_to_canister_in_drafts",
-            post(crate::videogen::qstash_process::upload_ai_generated_video_to_canister_in_drafts),
-        )
-        .route(
-            "/tournament/create",
-            post(crate::leaderboard::handlers::create_tournament_handler),
-        )
-        .route(
-            "/migrate_individual_user_to_service_canister",
-            post(migrate_individual_user_to_service_canister),
-        )
-        .route(
-            "/transfer_all_posts_for_individual_user",
-            post(transfer_all_posts_for_the_individual_user),
-        )
-        .route(
-            "/update_yral_metadata_mapping",
-            post(update_the_metadata_mapping),
-        )
-        .route(
-            "/tournament/start/{id}",
-            post(crate::leaderboard::handlers::start_tournament_handler),
-        )
-        .route(
-            "/tournament/finalize/{id}",
-            post(crate::leaderboard::handlers::finalize_tournament_handler),
-        )
-        .route(
-            "/tournament/end/{id}",
-            post(crate::leaderboard::handlers::end_tournament_handler),
-        )
-        .route("/rewards/update_config", post(update_reward_config))
-        .route(
-            "/compute_video_phash",
-            post(phash_bulk::compute_video_phash_handler),
-        )
-        .route(
-            "/bulk_compute_phash",
-            post(phash_bulk::bulk_compute_phash_handler),
-        );
+        .routes(routes!(crate::events::event::storj::storj_ingest))
+        .routes(routes!(crate::posts::report_post::qstash_report_post))
+        .routes(routes!(
+            crate::videogen::qstash_process::process_video_generation
+        ))
+        .routes(routes!(
+            crate::videogen::qstash_callback::handle_video_gen_callback
+        ))
+        .routes(routes!(
+            crate::videogen::qstash_process::upload_ai_generated_video_to_canister_in_drafts
+        ))
+        .routes(routes!(
+            crate::leaderboard::handlers::create_tournament_handler
+        ))
+        .routes(routes!(
+            service_canister_migration::migrate_individual_user_to_service_canister
+        ))
+        .routes(routes!(
+            service_canister_migration::transfer_all_posts_for_the_individual_user
+        ))
+        .routes(routes!(
+            service_canister_migration::update_the_metadata_mapping
+        ))
+        .routes(routes!(crate::user::migration_job::run_user_migration))
+        .routes(routes!(
+            crate::leaderboard::handlers::start_tournament_handler
+        ))
+        .routes(routes!(
+            crate::leaderboard::handlers::finalize_tournament_handler
+        ))
+        .routes(routes!(
+            crate::leaderboard::handlers::end_tournament_handler
+        ))
+        .routes(routes!(
+            crate::leaderboard::handlers::prize_claim_reminder_handler
+        ))
+        .routes(routes!(
+            crate::leaderboard::handlers::expire_prize_claims_handler
+        ))
+        .routes(routes!(
+            crate::leaderboard::snapshots::leaderboard_snapshot_handler
+        ))
+        .routes(routes!(crate::rewards::api::update_reward_config))
+        .routes(routes!(
+            crate::webhook_subscriptions::delivery::deliver_webhook_handler
+        ))
+        .routes(routes!(phash_bulk::compute_video_phash_handler))
+        .routes(routes!(phash_bulk::bulk_compute_phash_handler));
 
     #[cfg(not(feature = "local-bin"))]
     let router = router
-        .route(
-            "/milvus/ingest_phash",
-            post(milvus_ingest::ingest_phash_to_milvus_handler),
-        )
-        .route(
-            "/milvus/backfill_unique_videos",
-            post(milvus_ingest::backfill_unique_videos_handler),
-        )
-        .route(
-            "/milvus/bulk_ingest_unique_hashes",
-            post(milvus_ingest::bulk_ingest_unique_hashes_handler),
-        )
-        .route(
-            "/milvus/deduplicate_videos",
-            post(milvus_ingest::deduplicate_videos_handler),
-        );
+        .routes(routes!(milvus_ingest::ingest_phash_to_milvus_handler))
+        .routes(routes!(
+            crate::rewards::digest::flush_reward_digests_handler
+        ))
+        .routes(routes!(
+            crate::scheduled_publish::run_scheduled_publish_handler
+        ))
+        .routes(routes!(milvus_ingest::backfill_unique_videos_handler))
+        .routes(routes!(milvus_ingest::bulk_ingest_unique_hashes_handler))
+        .routes(routes!(milvus_ingest::deduplicate_videos_handler))
+        .routes(routes!(embedding_ingest::ingest_embeddings_handler))
+        .routes(routes!(
+            crate::canister::cycles_monitor::cycles_monitor_handler
+        ))
+        .routes(routes!(
+            crate::events::event::storj_backfill::storj_backfill_chunk_handler
+        ))
+        .routes(routes!(
+            crate::video_processing::transcode::transcode_video_handler
+        ))
+        .routes(routes!(
+            crate::video_processing::transcribe::transcribe_video_handler
+        ))
+        .routes(routes!(
+            crate::user::creator_stats::creator_stats_rollup_handler
+        ))
+        .routes(routes!(crate::posts::cleanup::post_cleanup_handler))
+        .routes(routes!(
+            crate::posts::feed_cache_invalidation::feed_cache_invalidation_handler
+        ))
+        .routes(routes!(crate::rollups::handlers::run_rollups_handler))
+        .routes(routes!(
+            crate::event_export::handlers::run_event_export_handler
+        ))
+        .routes(routes!(
+            crate::moderation::pending_queue::reconcile_pending_queue_handler
+        ))
+        .routes(routes!(
+            crate::video_availability::video_availability_audit_handler
+        ))
+        .routes(routes!(crate::video_availability::gcs_reupload_handler));
+
+    // Drain guard is inner to signature verification so unsigned requests never touch Redis
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.layer(middleware::from_fn_with_state(
+        app_state.clone(),
+        drain::qstash_drain_guard,
+    ));
+
+    // Outside the drain guard so deliveries it turns away still count as runs
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.layer(middleware::from_fn_with_state(
+        app_state.clone(),
+        crate::scheduler::record_scheduled_run,
+    ));
 
     router
         .layer(ServiceBuilder::new().layer(middleware::from_fn_with_state(
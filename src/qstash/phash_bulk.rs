@@ -1,7 +1,8 @@
 use crate::app_state::AppState;
 use crate::duplicate_video::phash::{download_video_from_storj, extract_metadata, PHasher};
-use crate::kvrocks::VideohashPhash;
+use crate::kvrocks::{tables, VideohashPhash};
 use crate::pipeline::Step;
+use crate::qstash::job::{FlowControl, PublishOptions};
 use crate::setup_context;
 use axum::{extract::State, response::Response, Json};
 use google_cloud_bigquery::http::job::query::QueryRequest;
@@ -11,17 +12,18 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::sync::Arc;
 use tracing::instrument;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 /// Request payload for computing video phash
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct ComputePhashRequest {
     pub video_id: String,
     pub publisher_user_id: String,
 }
 
 /// Request payload for bulk phash computation
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct BulkComputePhashRequest {
     /// Optional limit on number of videos to process
     #[serde(default = "default_limit")]
@@ -47,7 +49,7 @@ fn default_parallelism() -> u32 {
 }
 
 /// Response for bulk operation
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct BulkComputePhashResponse {
     pub total_videos: usize,
     pub queued: usize,
@@ -55,6 +57,16 @@ pub struct BulkComputePhashResponse {
 }
 
 /// Handler for computing and storing phash for a single video
+#[utoipa::path(
+    post,
+    path = "/compute_video_phash",
+    request_body = ComputePhashRequest,
+    responses(
+        (status = 200, description = "Phash computed and stored"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "qstash"
+)]
 #[instrument(skip(state))]
 pub async fn compute_video_phash_handler(
     State(state): State<Arc<AppState>>,
@@ -129,6 +141,16 @@ pub async fn compute_video_phash_handler(
 }
 
 /// Handler for bulk phash computation - reads from BigQuery and fires QStash requests
+#[utoipa::path(
+    post,
+    path = "/bulk_compute_phash",
+    request_body = BulkComputePhashRequest,
+    responses(
+        (status = 200, description = "Per-video jobs queued", body = BulkComputePhashResponse),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "qstash"
+)]
 #[instrument(skip(state))]
 pub async fn bulk_compute_phash_handler(
     State(state): State<Arc<AppState>>,
@@ -162,11 +184,19 @@ pub async fn bulk_compute_phash_handler(
     let queued = total_videos;
     let failed = 0;
 
-    match state
-        .qstash_client
-        .queue_compute_phash_batch(video_ids, req.rate, req.parallelism)
-        .await
-    {
+    let jobs: Vec<ComputePhashRequest> = video_ids
+        .into_iter()
+        .map(|(video_id, publisher_user_id)| ComputePhashRequest {
+            video_id,
+            publisher_user_id,
+        })
+        .collect();
+    let options = PublishOptions {
+        flow_control: Some(FlowControl::new("COMPUTE_PHASH", req.rate, req.parallelism)),
+        ..Default::default()
+    };
+
+    match state.qstash_client.publish_job_batch(&jobs, &options).await {
         Ok(_) => {
             log::info!(
                 "Successfully queued {} videos for phash computation using batch API",
@@ -331,7 +361,7 @@ async fn store_phash_to_bigquery(
         };
         if let Err(e) = state
             .kvrocks_client
-            .store_videohash_phash(&phash_data)
+            .put(&tables::VIDEOHASH_PHASH, &phash_data.video_id, &phash_data)
             .await
         {
             log::error!("Error pushing phash to kvrocks: {}", e);
@@ -5,14 +5,17 @@ use candid::Principal;
 use http::StatusCode;
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use yral_canisters_client::ic::USER_INFO_SERVICE_ID;
 use yral_metadata_types::SetUserMetadataReqMetadata;
 
 use crate::{app_state::AppState, types::RedisPool};
 
-#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, PartialOrd, Debug)]
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, PartialOrd, Debug, ToSchema)]
 pub struct MigrateIndividualUserRequest {
+    #[schema(value_type = String)]
     pub user_canister: Principal,
+    #[schema(value_type = String)]
     pub user_principal: Principal,
 }
 
@@ -65,21 +68,39 @@ impl ServiceCanisterMigrationRedis {
     }
 }
 
+/// Points the user's metadata at the user info service canister
+#[utoipa::path(
+    post,
+    path = "/update_yral_metadata_mapping",
+    request_body = MigrateIndividualUserRequest,
+    responses(
+        (status = 200, description = "Metadata mapping updated"),
+        (status = 500, description = "Internal server error", body = String)
+    ),
+    tag = "qstash"
+)]
 pub async fn update_the_metadata_mapping(
     State(state): State<Arc<AppState>>,
     Json(request): Json<MigrateIndividualUserRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    apply_metadata_mapping(&state, &request)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Repoints the user's metadata and records the migration
+pub async fn apply_metadata_mapping(
+    state: &AppState,
+    request: &MigrateIndividualUserRequest,
+) -> Result<(), String> {
     let admin_identity = &state.admin_identity;
 
     let _user_metadata = state
         .yral_metadata_client
         .get_user_metadata_v2(request.user_principal.to_text())
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-        .ok_or((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "User metadata not found".to_string(),
-        ))?;
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "User metadata not found".to_string())?;
 
     state
         .yral_metadata_client
@@ -92,7 +113,7 @@ pub async fn update_the_metadata_mapping(
             },
         )
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| e.to_string())?;
 
     let service_canister_migration_redis =
         ServiceCanisterMigrationRedis::new(state.service_cansister_migration_redis_pool.clone());
@@ -106,11 +127,21 @@ pub async fn update_the_metadata_mapping(
             },
         )
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| e.to_string())?;
 
     Ok(())
 }
 
+/// Deprecated: individual user canisters have been decommissioned
+#[utoipa::path(
+    post,
+    path = "/migrate_individual_user_to_service_canister",
+    request_body = MigrateIndividualUserRequest,
+    responses(
+        (status = 200, description = "No-op", body = String)
+    ),
+    tag = "qstash"
+)]
 pub async fn migrate_individual_user_to_service_canister(
     State(_state): State<Arc<AppState>>,
     Json(_request): Json<MigrateIndividualUserRequest>,
@@ -126,6 +157,16 @@ pub async fn migrate_individual_user_to_service_canister(
     Ok((StatusCode::OK, "Migration no longer needed".to_string()))
 }
 
+/// Deprecated: individual user canisters have been decommissioned
+#[utoipa::path(
+    post,
+    path = "/transfer_all_posts_for_individual_user",
+    request_body = MigrateIndividualUserRequest,
+    responses(
+        (status = 200, description = "No-op", body = String)
+    ),
+    tag = "qstash"
+)]
 pub async fn transfer_all_posts_for_the_individual_user(
     State(_state): State<Arc<AppState>>,
     Json(_request): Json<MigrateIndividualUserRequest>,
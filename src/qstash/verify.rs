@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use axum::{
     body::Body,
     extract::{Request, State},
@@ -11,6 +13,7 @@ use k256::sha2::{Digest, Sha256};
 use serde::{Deserialize, Serialize};
 
 use super::QStashState;
+use crate::middleware::http_metrics::RequestTimings;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
@@ -30,6 +33,7 @@ pub async fn verify_qstash_message(
     request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
+    let started = Instant::now();
     let sig = headers
         .get("Upstash-Signature")
         .ok_or(StatusCode::UNAUTHORIZED)?;
@@ -55,6 +59,10 @@ pub async fn verify_qstash_message(
         return Err(StatusCode::UNAUTHORIZED);
     }
 
+    if let Some(timings) = RequestTimings::from_extensions(&parts.extensions) {
+        timings.record("auth", started.elapsed());
+    }
+
     let new_req = Request::from_parts(parts, Body::from(body_raw));
 
     Ok(next.run(new_req).await)
@@ -0,0 +1,405 @@
+//! Live exchange rates.
+//!
+//! Every provider that quotes a [`Pair`] is asked concurrently and the median
+//! answer is cached for `rates.ttl_secs`. When all providers fail the last
+//! rate keeps being served, and before any provider has answered the
+//! `rates.fallback_*` value from runtime config is used; a failed refresh is
+//! not retried for [`RETRY_AFTER`]. Only one refresh per pair runs at a time,
+//! and callers arriving during it are served the degraded rate instead of
+//! waiting on the providers. Callers that must not act on a guessed rate
+//! check [`Quote::source`]. A rate older than
+//! `rates.stale_alert_secs`, or a fallback, is reported to
+//! `GCHAT_RATES_ALERTS_WEBHOOK_URL` at most once per that interval.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use futures::future::join_all;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::RwLock;
+
+use crate::{config::runtime::runtime, offchain_service::send_message_gchat_webhook};
+
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(10);
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+/// A refresh not finished by now was dropped mid-flight; another may start
+const REFRESH_TIMEOUT: Duration = Duration::from_secs(2 * PROVIDER_TIMEOUT.as_secs());
+
+static RATES_ALERTS_WEBHOOK_URL: Lazy<Option<String>> =
+    Lazy::new(|| std::env::var("GCHAT_RATES_ALERTS_WEBHOOK_URL").ok());
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(PROVIDER_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+});
+
+static STATE: Lazy<RwLock<HashMap<Pair, PairState>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pair {
+    /// USD per BTC
+    BtcUsd,
+    /// INR per USD
+    UsdInr,
+}
+
+impl Pair {
+    fn fallback(self) -> f64 {
+        let rates = &runtime().rates;
+        match self {
+            Pair::BtcUsd => rates.fallback_btc_usd,
+            Pair::UsdInr => rates.fallback_usd_inr,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateSource {
+    Live,
+    /// Providers are failing; last live rate
+    Stale,
+    /// No provider has answered yet; configured rate
+    Fallback,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Quote {
+    pub pair: Pair,
+    pub rate: f64,
+    pub source: RateSource,
+    /// When the rate was fetched, None for the fallback
+    pub fetched_at: Option<i64>,
+}
+
+#[derive(Default)]
+struct PairState {
+    live: Option<(f64, i64)>,
+    failed_at: Option<Instant>,
+    /// Set while a refresh is in flight so concurrent misses don't start
+    /// their own
+    refreshing_since: Option<Instant>,
+    alerted_at: Option<i64>,
+}
+
+impl PairState {
+    /// Whether a miss should serve the degraded rate rather than refresh
+    fn refresh_blocked(&self) -> bool {
+        self.failed_at
+            .is_some_and(|failed_at| failed_at.elapsed() < RETRY_AFTER)
+            || self
+                .refreshing_since
+                .is_some_and(|started| started.elapsed() < REFRESH_TIMEOUT)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Provider {
+    BlockchainInfo,
+    Coinbase,
+    CoinGecko,
+}
+
+const PROVIDERS: [Provider; 3] = [
+    Provider::BlockchainInfo,
+    Provider::Coinbase,
+    Provider::CoinGecko,
+];
+
+impl Provider {
+    fn name(self) -> &'static str {
+        match self {
+            Provider::BlockchainInfo => "blockchain.info",
+            Provider::Coinbase => "coinbase",
+            Provider::CoinGecko => "coingecko",
+        }
+    }
+
+    fn url(self, pair: Pair) -> &'static str {
+        match (self, pair) {
+            (Provider::BlockchainInfo, _) => "https://blockchain.info/ticker",
+            (Provider::Coinbase, Pair::BtcUsd) => "https://api.coinbase.com/v2/prices/BTC-USD/spot",
+            (Provider::Coinbase, Pair::UsdInr) => {
+                "https://api.coinbase.com/v2/exchange-rates?currency=USD"
+            }
+            (Provider::CoinGecko, _) => {
+                "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=usd,inr"
+            }
+        }
+    }
+
+    fn parse(self, pair: Pair, body: &Value) -> Option<f64> {
+        let number = |value: &Value| {
+            value
+                .as_f64()
+                .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+        };
+        match (self, pair) {
+            (Provider::BlockchainInfo, Pair::BtcUsd) => number(&body["USD"]["last"]),
+            (Provider::BlockchainInfo, Pair::UsdInr) => {
+                Some(number(&body["INR"]["last"])? / number(&body["USD"]["last"])?)
+            }
+            (Provider::Coinbase, Pair::BtcUsd) => number(&body["data"]["amount"]),
+            (Provider::Coinbase, Pair::UsdInr) => number(&body["data"]["rates"]["INR"]),
+            (Provider::CoinGecko, Pair::BtcUsd) => number(&body["bitcoin"]["usd"]),
+            (Provider::CoinGecko, Pair::UsdInr) => {
+                Some(number(&body["bitcoin"]["inr"])? / number(&body["bitcoin"]["usd"])?)
+            }
+        }
+        .filter(|rate| rate.is_finite() && *rate > 0.0)
+    }
+
+    async fn fetch(self, pair: Pair) -> Result<f64> {
+        let response = CLIENT
+            .get(self.url(pair))
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", self.name()))?;
+        if !response.status().is_success() {
+            anyhow::bail!("{} returned status {}", self.name(), response.status());
+        }
+        let body: Value = response
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse {} response", self.name()))?;
+        self.parse(pair, &body)
+            .with_context(|| format!("{} response has no {pair:?} rate", self.name()))
+    }
+}
+
+fn median(mut rates: Vec<f64>) -> Option<f64> {
+    if rates.is_empty() {
+        return None;
+    }
+    rates.sort_by(f64::total_cmp);
+    let mid = rates.len() / 2;
+    Some(if rates.len() % 2 == 0 {
+        (rates[mid - 1] + rates[mid]) / 2.0
+    } else {
+        rates[mid]
+    })
+}
+
+async fn fetch_median(pair: Pair) -> Option<f64> {
+    let results = join_all(PROVIDERS.iter().map(|provider| provider.fetch(pair))).await;
+    let mut rates = Vec::with_capacity(results.len());
+    for (provider, result) in PROVIDERS.iter().zip(results) {
+        match result {
+            Ok(rate) => rates.push(rate),
+            Err(e) => log::warn!("{pair:?} rate from {} failed: {e:?}", provider.name()),
+        }
+    }
+    median(rates)
+}
+
+fn live_quote(pair: Pair, state: &PairState, now: i64, ttl_secs: i64) -> Option<Quote> {
+    let (rate, fetched_at) = state.live?;
+    (now - fetched_at < ttl_secs).then_some(Quote {
+        pair,
+        rate,
+        source: RateSource::Live,
+        fetched_at: Some(fetched_at),
+    })
+}
+
+/// Rate to serve while providers are failing
+fn degraded_quote(pair: Pair, state: &PairState) -> Quote {
+    match state.live {
+        Some((rate, fetched_at)) => Quote {
+            pair,
+            rate,
+            source: RateSource::Stale,
+            fetched_at: Some(fetched_at),
+        },
+        None => Quote {
+            pair,
+            rate: pair.fallback(),
+            source: RateSource::Fallback,
+            fetched_at: None,
+        },
+    }
+}
+
+/// Whether serving `quote` warrants an alert now
+fn needs_alert(quote: &Quote, alerted_at: Option<i64>, now: i64, stale_alert_secs: i64) -> bool {
+    let degraded = match quote.fetched_at {
+        Some(fetched_at) => now - fetched_at >= stale_alert_secs,
+        None => true,
+    };
+    degraded && alerted_at.is_none_or(|at| now - at >= stale_alert_secs)
+}
+
+async fn alert_degraded(quote: &Quote) {
+    let age = quote
+        .fetched_at
+        .map(|fetched_at| format!("last fetched {}s ago", Utc::now().timestamp() - fetched_at))
+        .unwrap_or_else(|| "no provider has answered".to_string());
+    log::error!(
+        "Serving {:?} {:?} rate {} ({age})",
+        quote.source,
+        quote.pair,
+        quote.rate
+    );
+
+    let Some(webhook_url) = RATES_ALERTS_WEBHOOK_URL.as_deref() else {
+        log::warn!("GCHAT_RATES_ALERTS_WEBHOOK_URL not set, skipping chat alert");
+        return;
+    };
+    let message = json!({
+        "text": format!(
+            "*Exchange rate providers failing*\n{:?}: serving {:?} rate {} ({age})",
+            quote.pair, quote.source, quote.rate
+        )
+    });
+    if let Err(e) = send_message_gchat_webhook(webhook_url, message).await {
+        log::error!("Failed to send exchange rate alert: {e:?}");
+    }
+}
+
+/// Current rate for `pair`. Never fails: see the module docs for what is
+/// served while providers are down.
+pub async fn quote(pair: Pair) -> Quote {
+    let config = runtime().rates.clone();
+    let ttl_secs = config.ttl_secs as i64;
+    let stale_alert_secs = config.stale_alert_secs as i64;
+
+    if let Some(state) = STATE.read().await.get(&pair) {
+        if let Some(quote) = live_quote(pair, state, Utc::now().timestamp(), ttl_secs) {
+            return quote;
+        }
+    }
+
+    let now = Utc::now().timestamp();
+    {
+        let mut states = STATE.write().await;
+        let state = states.entry(pair).or_default();
+        if let Some(quote) = live_quote(pair, state, now, ttl_secs) {
+            return quote;
+        }
+        if state.refresh_blocked() {
+            return degraded_quote(pair, state);
+        }
+        state.refreshing_since = Some(Instant::now());
+    }
+
+    let fetched = fetch_median(pair).await;
+
+    let mut states = STATE.write().await;
+    let state = states.entry(pair).or_default();
+    if let Some(rate) = fetched {
+        log::debug!("Updated {pair:?} rate: {rate}");
+        *state = PairState {
+            live: Some((rate, now)),
+            ..Default::default()
+        };
+        return Quote {
+            pair,
+            rate,
+            source: RateSource::Live,
+            fetched_at: Some(now),
+        };
+    }
+
+    state.refreshing_since = None;
+    state.failed_at = Some(Instant::now());
+    let quote = degraded_quote(pair, state);
+    let alert = needs_alert(&quote, state.alerted_at, now, stale_alert_secs);
+    if alert {
+        state.alerted_at = Some(now);
+    }
+    drop(states);
+
+    if alert {
+        alert_degraded(&quote).await;
+    } else {
+        log::warn!(
+            "All {pair:?} providers failed, serving {:?} rate {}",
+            quote.source,
+            quote.rate
+        );
+    }
+    quote
+}
+
+/// USD per BTC
+pub async fn btc_usd() -> f64 {
+    quote(Pair::BtcUsd).await.rate
+}
+
+/// INR per USD
+pub async fn usd_inr() -> f64 {
+    quote(Pair::UsdInr).await.rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_parsing_and_median() {
+        let ticker = json!({
+            "USD": { "last": 100_000.0 },
+            "INR": { "last": 8_800_000.0 },
+        });
+        assert_eq!(
+            Provider::BlockchainInfo.parse(Pair::BtcUsd, &ticker),
+            Some(100_000.0)
+        );
+        assert_eq!(
+            Provider::BlockchainInfo.parse(Pair::UsdInr, &ticker),
+            Some(88.0)
+        );
+        let spot = json!({ "data": { "base": "BTC", "currency": "USD", "amount": "101000.5" } });
+        assert_eq!(
+            Provider::Coinbase.parse(Pair::BtcUsd, &spot),
+            Some(101_000.5)
+        );
+        assert_eq!(
+            Provider::CoinGecko.parse(Pair::BtcUsd, &json!({ "bitcoin": { "usd": 0 } })),
+            None
+        );
+
+        assert_eq!(median(vec![]), None);
+        assert_eq!(median(vec![3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(vec![4.0, 1.0, 2.0, 3.0]), Some(2.5));
+    }
+
+    #[test]
+    fn test_degraded_quotes_and_alerts() {
+        let now = 1_700_000_000;
+        let state = PairState {
+            live: Some((95_000.0, now - 600)),
+            ..Default::default()
+        };
+        assert!(live_quote(Pair::BtcUsd, &state, now, 300).is_none());
+        assert!(live_quote(Pair::BtcUsd, &state, now, 900).is_some());
+
+        let stale = degraded_quote(Pair::BtcUsd, &state);
+        assert_eq!(stale.source, RateSource::Stale);
+        assert_eq!(stale.rate, 95_000.0);
+        // Not old enough to alert, then old enough but alerted recently
+        assert!(!needs_alert(&stale, None, now, 1800));
+        assert!(needs_alert(&stale, None, now, 600));
+        assert!(!needs_alert(&stale, Some(now - 60), now, 600));
+
+        assert!(!state.refresh_blocked());
+        let refreshing = PairState {
+            refreshing_since: Some(Instant::now()),
+            ..Default::default()
+        };
+        assert!(refreshing.refresh_blocked());
+
+        let fallback = degraded_quote(Pair::BtcUsd, &PairState::default());
+        assert_eq!(fallback.source, RateSource::Fallback);
+        assert!(needs_alert(&fallback, None, now, 1800));
+    }
+}
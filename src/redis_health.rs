@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::HeaderMap, response::IntoResponse, Json};
+use http::StatusCode;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+    utils::read_replicas::ReplicaStatus,
+    yral_auth::dragonfly::PoolStatsSnapshot,
+};
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DragonflyHealth {
+    pub healthy: bool,
+    pub stats: PoolStatsSnapshot,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Bb8PoolHealth {
+    pub name: String,
+    pub healthy: bool,
+    pub connections: u32,
+    pub idle_connections: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RedisHealthResponse {
+    pub healthy: bool,
+    pub dragonfly: DragonflyHealth,
+    pub pools: Vec<Bb8PoolHealth>,
+    /// Leaderboard read replicas; informational, reads fall back to the
+    /// primary so replicas don't affect `healthy`
+    pub leaderboard_replicas: Vec<ReplicaStatus>,
+}
+
+async fn check_dragonfly(state: &AppState) -> DragonflyHealth {
+    let pool = &state.yral_redis_store_dragonfly;
+    let ping = pool
+        .execute_with_retry(|mut conn| async move {
+            redis::cmd("PING").query_async::<String>(&mut conn).await
+        })
+        .await;
+
+    DragonflyHealth {
+        healthy: ping.is_ok(),
+        stats: pool.stats(),
+        error: ping.err().map(|e| e.to_string()),
+    }
+}
+
+async fn check_bb8_pool(name: &str, pool: &crate::types::RedisPool) -> Bb8PoolHealth {
+    let ping = match pool.get().await {
+        Ok(mut conn) => redis::cmd("PING")
+            .query_async::<String>(&mut *conn)
+            .await
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let pool_state = pool.state();
+
+    Bb8PoolHealth {
+        name: name.to_string(),
+        healthy: ping.is_ok(),
+        connections: pool_state.connections,
+        idle_connections: pool_state.idle_connections,
+        error: ping.err(),
+    }
+}
+
+/// Redis connectivity and connection pool statistics; operators only
+#[utoipa::path(
+    get,
+    path = "/redis-health",
+    tag = "health",
+    responses(
+        (status = 200, description = "All Redis backends healthy", body = RedisHealthResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 503, description = "One or more Redis backends unhealthy", body = RedisHealthResponse),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn redis_health_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    require_operator(&headers)?;
+    let (dragonfly, leaderboard, migration) = tokio::join!(
+        check_dragonfly(&state),
+        check_bb8_pool("leaderboard", &state.leaderboard_redis_pool),
+        check_bb8_pool(
+            "service_canister_migration",
+            &state.service_cansister_migration_redis_pool
+        ),
+    );
+
+    let pools = vec![leaderboard, migration];
+    let healthy = dragonfly.healthy && pools.iter().all(|pool| pool.healthy);
+    if !healthy {
+        log::warn!("Redis health check failed: {:?} {:?}", dragonfly, pools);
+    }
+
+    let status = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    Ok((
+        status,
+        Json(RedisHealthResponse {
+            healthy,
+            dragonfly,
+            pools,
+            leaderboard_replicas: state.leaderboard_read_replicas.statuses(),
+        }),
+    ))
+}
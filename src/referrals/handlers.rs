@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use candid::Principal;
+use redis::AsyncCommands;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::store::{get_code, get_or_create_code, get_referred_by, referred_key, rejected_key};
+use crate::{
+    app_state::AppState,
+    auth::require_user,
+    error::{ApiError, ApiErrorBody},
+};
+
+const RECENT_REFERRALS_LIMIT: isize = 20;
+
+fn parse_principal(principal: &str) -> Result<Principal, ApiError> {
+    Principal::from_text(principal)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid principal: {e}")))
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReferralCodeResponse {
+    pub principal: String,
+    pub code: String,
+}
+
+/// The caller's referral code, created on first request
+#[utoipa::path(
+    get,
+    path = "/{principal}/code",
+    params(
+        ("principal" = String, Path, description = "User principal"),
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the user")
+    ),
+    tag = "referrals",
+    responses(
+        (status = 200, description = "Referral code", body = ReferralCodeResponse),
+        (status = 400, description = "Invalid principal", body = ApiErrorBody),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Caller is not the user", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+pub async fn get_referral_code(
+    State(state): State<Arc<AppState>>,
+    Path(principal): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<ReferralCodeResponse>, ApiError> {
+    let principal = parse_principal(&principal)?;
+    let caller = require_user(&state, &headers).await?;
+    if caller != principal {
+        return Err(ApiError::Forbidden(
+            "Only the user can create their referral code".to_string(),
+        ));
+    }
+    let code = get_or_create_code(&state.yral_redis_store_dragonfly, &principal).await?;
+
+    Ok(Json(ReferralCodeResponse {
+        principal: principal.to_text(),
+        code,
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReferralEntry {
+    pub principal: String,
+    /// Unix timestamp of the attributed signup
+    pub referred_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReferralStatsResponse {
+    pub principal: String,
+    /// Unset until the user requests a code
+    pub code: Option<String>,
+    pub referred_count: u64,
+    /// Signups rejected by fraud checks
+    pub rejected_count: u64,
+    pub referred_by: Option<String>,
+    /// Most recent referrals first
+    pub recent_referrals: Vec<ReferralEntry>,
+}
+
+/// Referral stats for a user
+#[utoipa::path(
+    get,
+    path = "/{principal}/stats",
+    params(
+        ("principal" = String, Path, description = "User principal")
+    ),
+    tag = "referrals",
+    responses(
+        (status = 200, description = "Referral stats", body = ReferralStatsResponse),
+        (status = 400, description = "Invalid principal", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    )
+)]
+pub async fn get_referral_stats(
+    State(state): State<Arc<AppState>>,
+    Path(principal): Path<String>,
+) -> Result<Json<ReferralStatsResponse>, ApiError> {
+    let principal = parse_principal(&principal)?;
+    let pool = &state.yral_redis_store_dragonfly;
+
+    let code = get_code(pool, &principal).await?;
+    let referred_by = get_referred_by(pool, &principal).await?;
+
+    let mut conn = pool.get().await?;
+    let referred_count: u64 = conn.zcard(referred_key(&principal)).await?;
+    let rejected_count: Option<u64> = conn.get(rejected_key(&principal)).await?;
+    let recent: Vec<(String, i64)> = conn
+        .zrevrange_withscores(referred_key(&principal), 0, RECENT_REFERRALS_LIMIT - 1)
+        .await?;
+
+    Ok(Json(ReferralStatsResponse {
+        principal: principal.to_text(),
+        code,
+        referred_count,
+        rejected_count: rejected_count.unwrap_or(0),
+        referred_by: referred_by.map(|referrer| referrer.to_text()),
+        recent_referrals: recent
+            .into_iter()
+            .map(|(principal, referred_at)| ReferralEntry {
+                principal,
+                referred_at,
+            })
+            .collect(),
+    }))
+}
@@ -0,0 +1,39 @@
+pub mod handlers;
+pub mod store;
+
+use std::sync::Arc;
+
+use candid::Principal;
+
+use utoipa_axum::router::OpenApiRouter;
+use utoipa_axum::routes;
+
+use crate::app_state::AppState;
+
+pub fn referrals_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(handlers::get_referral_code))
+        .routes(routes!(handlers::get_referral_stats))
+        .with_state(state)
+}
+
+/// Spawned from the event pipeline so attribution never slows ingestion.
+/// Only events from a verified sender are attributed.
+pub fn spawn_track_event(
+    state: Arc<AppState>,
+    event_name: String,
+    raw_params: String,
+    sender: Option<Principal>,
+) {
+    if event_name != store::SIGNUP_EVENT {
+        return;
+    }
+    let Some(referee) = sender else {
+        return;
+    };
+    tokio::spawn(async move {
+        if let Err(e) = store::attribute_signup(&state, referee, &raw_params).await {
+            log::warn!("Failed to attribute referral for signup: {e:?}");
+        }
+    });
+}
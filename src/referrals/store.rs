@@ -0,0 +1,233 @@
+use anyhow::Result;
+use candid::Principal;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+
+use crate::{
+    app_state::AppState,
+    leaderboard::types::MetricType,
+    rewards::fraud_detection::{FraudCheck, FraudDetector},
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const KEY_PREFIX: &str = "offchain:referrals";
+pub const SIGNUP_EVENT: &str = "login_successful";
+/// Emitted into daily missions once a referral is attributed
+pub const REFERRAL_ATTRIBUTED_EVENT: &str = "referral_attributed";
+const CODE_LEN: usize = 8;
+/// No 0/O or 1/I so codes survive being read aloud
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const MAX_CODE_ATTEMPTS: u32 = 8;
+
+/// Records the referee against the referrer unless the referee is already
+/// attributed. Returns 1 when this call made the attribution.
+static ATTRIBUTE_SCRIPT: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+        if not redis.call('SET', KEYS[1], ARGV[1], 'NX') then
+            return 0
+        end
+        redis.call('ZADD', KEYS[2], tonumber(ARGV[3]), ARGV[2])
+        return 1
+        "#,
+    )
+});
+
+fn code_key(code: &str) -> String {
+    format!("{KEY_PREFIX}:code:{code}")
+}
+
+fn user_code_key(principal: &Principal) -> String {
+    format!("{KEY_PREFIX}:user_code:{principal}")
+}
+
+/// When the user first signed in with a verified identity; never expires
+fn first_seen_key(principal: &Principal) -> String {
+    format!("{KEY_PREFIX}:first_seen:{principal}")
+}
+
+fn referred_by_key(principal: &Principal) -> String {
+    format!("{KEY_PREFIX}:referred_by:{principal}")
+}
+
+pub(crate) fn referred_key(referrer: &Principal) -> String {
+    format!("{KEY_PREFIX}:referred:{referrer}")
+}
+
+pub(crate) fn rejected_key(referrer: &Principal) -> String {
+    format!("{KEY_PREFIX}:rejected:{referrer}")
+}
+
+/// Deterministic candidate code; `attempt` moves past collisions
+fn derive_code(principal: &Principal, attempt: u32) -> String {
+    let digest = Sha1::digest(format!("{principal}:{attempt}").as_bytes());
+    digest
+        .iter()
+        .take(CODE_LEN)
+        .map(|byte| CODE_ALPHABET[*byte as usize % CODE_ALPHABET.len()] as char)
+        .collect()
+}
+
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_ascii_uppercase()
+}
+
+/// The user's referral code, if one was allocated
+pub async fn get_code(pool: &DragonflyPool, principal: &Principal) -> Result<Option<String>> {
+    let mut conn = pool.get().await?;
+    Ok(conn.get(user_code_key(principal)).await?)
+}
+
+/// The user's referral code, allocating one on first use
+pub async fn get_or_create_code(pool: &DragonflyPool, principal: &Principal) -> Result<String> {
+    let mut conn = pool.get().await?;
+    if let Some(code) = conn
+        .get::<_, Option<String>>(user_code_key(principal))
+        .await?
+    {
+        return Ok(code);
+    }
+
+    for attempt in 0..MAX_CODE_ATTEMPTS {
+        let code = derive_code(principal, attempt);
+        let claimed: bool = conn.set_nx(code_key(&code), principal.to_text()).await?;
+        if !claimed {
+            let owner: Option<String> = conn.get(code_key(&code)).await?;
+            if owner.as_deref() != Some(principal.to_text().as_str()) {
+                continue;
+            }
+        }
+        // A concurrent request may have stored a code first; keep whichever won
+        let stored: bool = conn.set_nx(user_code_key(principal), &code).await?;
+        if stored {
+            return Ok(code);
+        }
+        conn.del::<_, ()>(code_key(&code)).await?;
+        let existing: String = conn.get(user_code_key(principal)).await?;
+        return Ok(existing);
+    }
+
+    anyhow::bail!("Could not allocate a referral code for {principal}")
+}
+
+pub async fn resolve_code(pool: &DragonflyPool, code: &str) -> Result<Option<Principal>> {
+    let mut conn = pool.get().await?;
+    let owner: Option<String> = conn.get(code_key(&normalize_code(code))).await?;
+    Ok(owner.and_then(|text| Principal::from_text(text).ok()))
+}
+
+pub async fn get_referred_by(
+    pool: &DragonflyPool,
+    principal: &Principal,
+) -> Result<Option<Principal>> {
+    let mut conn = pool.get().await?;
+    let referrer: Option<String> = conn.get(referred_by_key(principal)).await?;
+    Ok(referrer.and_then(|text| Principal::from_text(text).ok()))
+}
+
+/// Attributes a new user's signup to the owner of the referral code it carried.
+/// `referee` is the event's verified sender. The client's `is_new_user` flag
+/// is only honoured the first time we see that sender sign in, so a replayed
+/// or forged signup never counts twice.
+pub async fn attribute_signup(
+    state: &AppState,
+    referee: Principal,
+    raw_params: &str,
+) -> Result<()> {
+    let pool = &state.yral_redis_store_dragonfly;
+
+    // Every verified sign-in is recorded, with or without a code, so a later
+    // event can't pass itself off as the first
+    let mut conn = pool.get().await?;
+    let first_seen: Option<String> = redis::cmd("SET")
+        .arg(first_seen_key(&referee))
+        .arg(Utc::now().timestamp())
+        .arg("NX")
+        .query_async(&mut conn)
+        .await?;
+    drop(conn);
+    if first_seen.is_none() {
+        return Ok(());
+    }
+
+    let params: Value = serde_json::from_str(raw_params)?;
+    if params.get("is_new_user").and_then(Value::as_bool) != Some(true) {
+        return Ok(());
+    }
+    let Some(code) = params.get("referral_code").and_then(Value::as_str) else {
+        return Ok(());
+    };
+
+    let Some(referrer) = resolve_code(pool, code).await? else {
+        log::info!("Ignoring unknown referral code {code} for {referee}");
+        return Ok(());
+    };
+
+    let fraud_detector = FraudDetector::new(pool.clone());
+    if fraud_detector.check_referral(referrer, referee).await == FraudCheck::Suspicious {
+        let mut conn = pool.get().await?;
+        conn.incr::<_, _, ()>(rejected_key(&referrer), 1).await?;
+        log::warn!("Rejected referral of {referee} by {referrer}");
+        return Ok(());
+    }
+
+    let mut conn = pool.get().await?;
+    let attributed: i64 = ATTRIBUTE_SCRIPT
+        .key(referred_by_key(&referee))
+        .key(referred_key(&referrer))
+        .arg(referrer.to_text())
+        .arg(referee.to_text())
+        .arg(Utc::now().timestamp())
+        .invoke_async(&mut conn)
+        .await?;
+    drop(conn);
+    if attributed == 0 {
+        return Ok(());
+    }
+
+    if let Err(e) = crate::leaderboard::ingest::record_server_metric(
+        state,
+        &MetricType::ReferralsMade,
+        referrer,
+        1.0,
+    )
+    .await
+    {
+        log::warn!("Failed to record referral for leaderboard: {e:?}");
+    }
+
+    let mission_params = json!({
+        "referrer_id": referrer.to_text(),
+        "referee_id": referee.to_text(),
+    });
+    if let Err(e) = crate::daily_missions::missions::track_event(
+        pool,
+        REFERRAL_ATTRIBUTED_EVENT,
+        &mission_params.to_string(),
+    )
+    .await
+    {
+        log::warn!("Failed to track referral for daily missions: {e:?}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_code() {
+        let principal = Principal::anonymous();
+        let code = derive_code(&principal, 0);
+        assert_eq!(code.len(), CODE_LEN);
+        assert!(code.bytes().all(|byte| CODE_ALPHABET.contains(&byte)));
+        assert_eq!(code, derive_code(&principal, 0));
+        assert_ne!(code, derive_code(&principal, 1));
+        assert_eq!(normalize_code(&format!(" {} ", code.to_lowercase())), code);
+    }
+}
@@ -8,6 +8,10 @@ pub struct BtcVideoViewedEventParams<'a> {
     pub video_id: &'a str,
     pub publisher_user_id: &'a Principal,
     pub is_unique_view: bool,
+    /// Viewed by the same user within the dedup window, on any device
+    pub is_replay: bool,
+    pub is_cross_device_replay: bool,
+    pub device_id: Option<String>,
     pub source: Option<String>,
     pub client_type: Option<String>,
     pub btc_video_view_count: u64,
@@ -43,6 +47,9 @@ pub async fn send_btc_video_viewed_event(
     let user_id_text = params.user_id.to_text();
     let user_id_principal = *params.user_id;
     let is_unique_view = params.is_unique_view;
+    let is_replay = params.is_replay;
+    let is_cross_device_replay = params.is_cross_device_replay;
+    let device_id = params.device_id;
     let source = params.source;
     let client_type = params.client_type;
     let btc_video_view_count = params.btc_video_view_count;
@@ -71,6 +78,9 @@ pub async fn send_btc_video_viewed_event(
             "is_logged_in": is_logged_in,
             "canister_id": canister_id.map(|c| c.to_text()),
             "is_unique_view": is_unique_view,
+            "is_replay": is_replay,
+            "is_cross_device_replay": is_cross_device_replay,
+            "device_id": device_id,
             "source": source,
             "client_type": client_type,
             "btc_video_view_count": btc_video_view_count,
@@ -4,6 +4,7 @@ use crate::{
         config::RewardConfig,
         history::{HistoryTracker, RewardRecord, ViewRecord},
     },
+    tokens::Token,
 };
 use axum::{
     extract::{Path, Query, State},
@@ -19,6 +20,8 @@ use utoipa::{IntoParams, ToSchema};
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_axum::routes;
 
+#[cfg(not(feature = "local-bin"))]
+use crate::rewards::digest::{self, RewardInboxEntry};
 #[cfg(not(feature = "local-bin"))]
 use google_cloud_bigquery::http::job::query::QueryRequest;
 
@@ -102,6 +105,13 @@ pub struct RewardHistoryResponse {
     pub total: usize,
 }
 
+#[cfg(not(feature = "local-bin"))]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RewardInboxResponse {
+    pub entries: Vec<RewardInboxEntry>,
+    pub total: usize,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct ConfigResponse {
     pub config: Option<RewardConfig>,
@@ -156,10 +166,12 @@ pub fn rewards_router(state: Arc<AppState>) -> OpenApiRouter {
         .routes(routes!(get_user_view_history))
         .routes(routes!(get_user_reward_history))
         .routes(routes!(get_creator_reward_history))
+        .routes(routes!(get_user_reward_inbox))
         .routes(routes!(get_reward_config))
         .routes(routes!(get_reward_config_v2))
         .routes(routes!(bulk_get_video_stats))
         .routes(routes!(bulk_get_video_stats_v2))
+        .routes(routes!(crate::rewards::estimate::estimate_rewards_handler))
         .with_state(state)
 }
 
@@ -308,6 +320,44 @@ async fn get_creator_reward_history(
     Ok(Json(RewardHistoryResponse { rewards, total }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/user/{user_id}/inbox",
+    params(
+        ("user_id" = String, Path, description = "User Principal ID"),
+        PaginationParams,
+    ),
+    tag = "rewards",
+    responses(
+        (status = 200, description = "Reward digests sent to the user, newest first", body = RewardInboxResponse),
+        (status = 500, description = "Internal server error"),
+    )
+)]
+#[cfg(not(feature = "local-bin"))]
+async fn get_user_reward_inbox(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<RewardInboxResponse>, (StatusCode, String)> {
+    let principal = Principal::from_text(&user_id)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid principal: {}", e)))?;
+
+    let entries = digest::get_inbox(
+        &state.rewards_module.dragonfly_pool,
+        &principal,
+        params.limit,
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to get user reward inbox: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    })?;
+
+    let total = entries.len();
+
+    Ok(Json(RewardInboxResponse { entries, total }))
+}
+
 #[cfg(not(feature = "local-bin"))]
 #[utoipa::path(
     get,
@@ -339,37 +389,21 @@ async fn get_reward_config(
             let token_amount = *amount_per_milestone_e8s as f64 / 100_000_000.0;
 
             // Get the exchange rate and convert to INR
-            let inr_total = match config.reward_token {
-                crate::rewards::config::RewardTokenType::Btc => {
-                    // Get BTC/INR rate
-                    match state.rewards_module.btc_converter.get_btc_inr_rate().await {
-                        Ok(rate) => token_amount * rate,
-                        Err(e) => {
-                            log::error!("Failed to get BTC/INR rate for config API: {}", e);
-                            0.0
-                        }
-                    }
-                }
-                crate::rewards::config::RewardTokenType::Dolr => {
-                    // Get DOLR → USD → INR
-                    if let Some(icpswap_client) = &state.rewards_module.icpswap_client {
-                        match (
-                            icpswap_client.get_dolr_usd_rate().await,
-                            state.rewards_module.btc_converter.get_inr_usd_rate().await,
-                        ) {
-                            (Ok(dolr_usd_rate), Ok(inr_usd_rate)) => {
-                                let usd_amount = token_amount * dolr_usd_rate;
-                                usd_amount * inr_usd_rate
-                            }
-                            _ => {
-                                log::error!("Failed to get exchange rates for DOLR config API");
-                                0.0
-                            }
-                        }
-                    } else {
-                        log::error!("ICPSwap client not available for config API");
-                        0.0
-                    }
+            let token = Token::from(config.reward_token);
+            let inr_total = match state
+                .rewards_module
+                .btc_converter
+                .token_value_inr(token, token_amount)
+                .await
+            {
+                Ok(inr_total) => inr_total,
+                Err(e) => {
+                    log::error!(
+                        "Failed to value {} in INR for config API: {:?}",
+                        token.symbol(),
+                        e
+                    );
+                    0.0
                 }
             };
 
@@ -419,35 +453,21 @@ async fn get_reward_config_v2(
             // Convert e8s per milestone to INR per view for API compatibility
             let token_amount = *amount_per_milestone_e8s as f64 / 100_000_000.0;
 
-            let inr_total = match config.reward_token {
-                crate::rewards::config::RewardTokenType::Btc => {
-                    match state.rewards_module.btc_converter.get_btc_inr_rate().await {
-                        Ok(rate) => token_amount * rate,
-                        Err(e) => {
-                            log::error!("Failed to get BTC/INR rate for config API: {}", e);
-                            0.0
-                        }
-                    }
-                }
-                crate::rewards::config::RewardTokenType::Dolr => {
-                    if let Some(icpswap_client) = &state.rewards_module.icpswap_client {
-                        match (
-                            icpswap_client.get_dolr_usd_rate().await,
-                            state.rewards_module.btc_converter.get_inr_usd_rate().await,
-                        ) {
-                            (Ok(dolr_usd_rate), Ok(inr_usd_rate)) => {
-                                let usd_amount = token_amount * dolr_usd_rate;
-                                usd_amount * inr_usd_rate
-                            }
-                            _ => {
-                                log::error!("Failed to get exchange rates for DOLR config API");
-                                0.0
-                            }
-                        }
-                    } else {
-                        log::error!("ICPSwap client not available for config API");
-                        0.0
-                    }
+            let token = Token::from(config.reward_token);
+            let inr_total = match state
+                .rewards_module
+                .btc_converter
+                .token_value_inr(token, token_amount)
+                .await
+            {
+                Ok(inr_total) => inr_total,
+                Err(e) => {
+                    log::error!(
+                        "Failed to value {} in INR for config API: {:?}",
+                        token.symbol(),
+                        e
+                    );
+                    0.0
                 }
             };
 
@@ -492,6 +512,16 @@ async fn get_reward_config_v2(
 }
 
 #[cfg(not(feature = "local-bin"))]
+#[utoipa::path(
+    post,
+    path = "/rewards/update_config",
+    request_body = RewardConfig,
+    tag = "qstash",
+    responses(
+        (status = 200, description = "Reward configuration updated"),
+        (status = 500, description = "Internal server error", body = String),
+    )
+)]
 pub async fn update_reward_config(
     State(state): State<Arc<AppState>>,
     Json(new_config): Json<RewardConfig>,
@@ -0,0 +1,417 @@
+//! Reward notification digests.
+//!
+//! Instead of a push per view milestone, every credited reward is added to
+//! the creator's pending digest in Dragonfly. The first credit of a window
+//! schedules the digest `rewards.digest_window_secs` later in [`DUE_KEY`];
+//! the `rewards_digest_flush` schedule then sends one summary notification
+//! per due digest and keeps a copy in the creator's reward inbox, read
+//! through `GET /user/{user_id}/inbox`. A window of 0 notifies on every
+//! reward, as before digests.
+//!
+//! A flush moves the pending digest to an inflight key and pushes its due
+//! time out by [`SEND_LEASE_SECS`]; the inflight key is only deleted once
+//! the inbox write succeeds, so a failed send is retried when the lease
+//! lapses instead of being dropped.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::Result;
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use candid::Principal;
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::ToSchema;
+use yral_metadata_types::{
+    NotificationPayload, SendNotificationReq, WebpushConfig, WebpushFcmOptions,
+};
+
+use crate::{
+    app_state::AppState,
+    config::runtime::runtime,
+    error::{ApiError, ApiErrorBody},
+    events::notification_templates::NotificationCopy,
+    rewards::config::RewardTokenType,
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+/// Creators with a pending digest, scored by when it is due
+const DUE_KEY: &str = "impressions:rewards:digest:due";
+
+/// Digests sent per flush; the rest wait for the next run
+const FLUSH_BATCH: isize = 1000;
+const FLUSH_CONCURRENCY: usize = 50;
+
+/// Inbox entries kept per creator
+const INBOX_LEN: isize = 100;
+
+/// How long a flush owns a digest before another flush may retry it
+const SEND_LEASE_SECS: i64 = 300;
+
+const STARTED_AT_FIELD: &str = "started_at";
+const COUNT_FIELD: &str = "count";
+const INR_FIELD: &str = "inr";
+const TOKEN_FIELD_PREFIX: &str = "token:";
+
+fn pending_key(creator_id: &Principal) -> String {
+    format!("impressions:rewards:digest:pending:{creator_id}")
+}
+
+fn inflight_key(creator_id: &Principal) -> String {
+    format!("impressions:rewards:digest:inflight:{creator_id}")
+}
+
+fn inbox_key(creator_id: &Principal) -> String {
+    format!("impressions:rewards:user:{creator_id}:inbox")
+}
+
+/// Digest key for a token, its serde name
+fn token_name(token: RewardTokenType) -> &'static str {
+    match token {
+        RewardTokenType::Btc => "btc",
+        RewardTokenType::Dolr => "dolr",
+    }
+}
+
+fn token_label(name: &str) -> Option<&'static str> {
+    match name {
+        "btc" => Some("Bitcoin"),
+        "dolr" => Some("DOLR"),
+        _ => None,
+    }
+}
+
+/// Rewards credited to one creator within a window
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RewardDigest {
+    pub rewards: u64,
+    pub total_inr: f64,
+    /// Whole tokens credited, by token
+    pub tokens: BTreeMap<String, f64>,
+    /// First credit of the window, unix seconds
+    pub started_at: i64,
+}
+
+impl RewardDigest {
+    fn from_fields(fields: &[(String, f64)]) -> Self {
+        let mut digest = Self::default();
+        for (field, value) in fields {
+            match field.as_str() {
+                STARTED_AT_FIELD => digest.started_at = *value as i64,
+                COUNT_FIELD => digest.rewards = *value as u64,
+                INR_FIELD => digest.total_inr = *value,
+                field => {
+                    if let Some(token) = field.strip_prefix(TOKEN_FIELD_PREFIX) {
+                        digest.tokens.insert(token.to_string(), *value);
+                    }
+                }
+            }
+        }
+        digest
+    }
+
+    /// "Bitcoin and DOLR"
+    fn token_labels(&self) -> String {
+        let labels: Vec<&str> = self
+            .tokens
+            .keys()
+            .filter_map(|name| token_label(name))
+            .collect();
+        labels.join(" and ")
+    }
+}
+
+/// A digest as shown in the creator's reward inbox
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RewardInboxEntry {
+    pub title: String,
+    pub body: String,
+    pub digest: RewardDigest,
+    pub sent_at: i64,
+}
+
+/// Adds a credited reward to the creator's pending digest, scheduling the
+/// digest `window_secs` out if this is the first reward of the window
+pub async fn add_credit(
+    pool: &DragonflyPool,
+    creator_id: Principal,
+    token: RewardTokenType,
+    token_amount: f64,
+    reward_inr: f64,
+    window_secs: u64,
+) -> Result<()> {
+    let now = Utc::now().timestamp();
+    let token_field = format!("{TOKEN_FIELD_PREFIX}{}", token_name(token));
+    let key = pending_key(&creator_id);
+
+    let mut conn = pool.get().await?;
+    redis::pipe()
+        .atomic()
+        .hset_nx(&key, STARTED_AT_FIELD, now)
+        .ignore()
+        .hincr(&key, COUNT_FIELD, 1)
+        .ignore()
+        .hincr(&key, INR_FIELD, reward_inr)
+        .ignore()
+        .hincr(&key, token_field, token_amount)
+        .ignore()
+        .cmd("ZADD")
+        .arg(DUE_KEY)
+        .arg("NX")
+        .arg(now + window_secs as i64)
+        .arg(creator_id.to_text())
+        .ignore()
+        .query_async::<()>(&mut conn)
+        .await?;
+    Ok(())
+}
+
+/// Leases a due digest to the caller: moves the pending hash to the
+/// inflight key (unless an earlier failed send left one there) and pushes
+/// the due time out by the lease. Returns nothing if the digest is not due,
+/// which means another flush holds it.
+static TAKE_SCRIPT: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+        local due = redis.call('ZSCORE', KEYS[3], ARGV[1])
+        if not due or tonumber(due) > tonumber(ARGV[2]) then return {} end
+        if redis.call('EXISTS', KEYS[2]) == 0 then
+            if redis.call('EXISTS', KEYS[1]) == 0 then
+                redis.call('ZREM', KEYS[3], ARGV[1])
+                return {}
+            end
+            redis.call('RENAME', KEYS[1], KEYS[2])
+        end
+        redis.call('ZADD', KEYS[3], tonumber(ARGV[2]) + tonumber(ARGV[3]), ARGV[1])
+        return redis.call('HGETALL', KEYS[2])
+        "#,
+    )
+});
+
+/// Drops a delivered digest. Credits that arrived while it was being sent
+/// are rescheduled for the end of their own window.
+static FINISH_SCRIPT: Lazy<redis::Script> = Lazy::new(|| {
+    redis::Script::new(
+        r#"
+        redis.call('DEL', KEYS[2])
+        local started = redis.call('HGET', KEYS[1], ARGV[2])
+        if started then
+            redis.call('ZADD', KEYS[3], tonumber(started) + tonumber(ARGV[3]), ARGV[1])
+        else
+            redis.call('ZREM', KEYS[3], ARGV[1])
+        end
+        return 1
+        "#,
+    )
+});
+
+/// Takes the creator's due digest, if it still has one
+async fn take_digest(pool: &DragonflyPool, creator_id: &Principal) -> Result<Option<RewardDigest>> {
+    let mut conn = pool.get().await?;
+    let fields: Vec<(String, f64)> = TAKE_SCRIPT
+        .key(pending_key(creator_id))
+        .key(inflight_key(creator_id))
+        .key(DUE_KEY)
+        .arg(creator_id.to_text())
+        .arg(Utc::now().timestamp())
+        .arg(SEND_LEASE_SECS)
+        .invoke_async(&mut conn)
+        .await?;
+
+    let digest = RewardDigest::from_fields(&fields);
+    Ok((digest.rewards > 0).then_some(digest))
+}
+
+/// Deletes the inflight digest once it has reached the creator's inbox
+async fn finish_digest(pool: &DragonflyPool, creator_id: &Principal) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let _: i64 = FINISH_SCRIPT
+        .key(pending_key(creator_id))
+        .key(inflight_key(creator_id))
+        .key(DUE_KEY)
+        .arg(creator_id.to_text())
+        .arg(STARTED_AT_FIELD)
+        .arg(runtime().rewards.digest_window_secs)
+        .invoke_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+async fn send_digest(
+    app_state: &AppState,
+    creator_id: Principal,
+    digest: RewardDigest,
+) -> Result<()> {
+    let show_amount = crate::experiments::shows_reward_amount(app_state, creator_id).await;
+    let copy = if show_amount {
+        NotificationCopy::new("reward_digest_amount")
+            .var("amount", format!("{:.2}", digest.total_inr))
+    } else {
+        NotificationCopy::new("reward_digest")
+    }
+    .var("count", digest.rewards)
+    .var("token", digest.token_labels());
+    let (title, body) = app_state
+        .notification_client
+        .templates()
+        .render(&copy, None)
+        .await;
+
+    let notif_payload = SendNotificationReq {
+        notification: Some(NotificationPayload {
+            title: Some(title.clone()),
+            body: Some(body.clone()),
+            image: Some("https://yral.com/img/yral/android-chrome-384x384.png".to_string()),
+        }),
+        data: Some(json!({
+            "event": "reward_digest",
+            "rewards": digest.rewards,
+            "total_inr": digest.total_inr,
+            "tokens": digest.tokens,
+        })),
+        webpush: Some(WebpushConfig {
+            fcm_options: Some(WebpushFcmOptions {
+                link: Some("https://link.yral.com/dJqgFEnM6Wb".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    app_state
+        .notification_client
+        .send_localized(&copy, notif_payload, creator_id)
+        .await;
+
+    let entry = RewardInboxEntry {
+        title,
+        body,
+        digest,
+        sent_at: Utc::now().timestamp(),
+    };
+    let key = inbox_key(&creator_id);
+    let mut conn = app_state.rewards_module.dragonfly_pool.get().await?;
+    redis::pipe()
+        .atomic()
+        .lpush(&key, serde_json::to_string(&entry)?)
+        .ignore()
+        .ltrim(&key, 0, INBOX_LEN - 1)
+        .ignore()
+        .query_async::<()>(&mut conn)
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct DigestFlushSummary {
+    pub due: usize,
+    pub sent: usize,
+    pub failed: usize,
+}
+
+/// Sends every digest whose window has closed
+pub async fn flush_due_digests(app_state: &Arc<AppState>) -> Result<DigestFlushSummary> {
+    let pool = &app_state.rewards_module.dragonfly_pool;
+    let due: Vec<String> = {
+        let mut conn = pool.get().await?;
+        conn.zrangebyscore_limit(DUE_KEY, "-inf", Utc::now().timestamp(), 0, FLUSH_BATCH)
+            .await?
+    };
+
+    let results: Vec<Result<bool>> = stream::iter(due.iter())
+        .map(|creator| async move {
+            let creator_id = Principal::from_text(creator)?;
+            // Another flush may have sent it already
+            let Some(digest) = take_digest(pool, &creator_id).await? else {
+                return Ok(false);
+            };
+            // On failure the inflight digest is retried once the lease lapses
+            send_digest(app_state, creator_id, digest).await?;
+            finish_digest(pool, &creator_id).await?;
+            Ok(true)
+        })
+        .buffer_unordered(FLUSH_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut summary = DigestFlushSummary {
+        due: due.len(),
+        ..Default::default()
+    };
+    for result in results {
+        match result {
+            Ok(true) => summary.sent += 1,
+            Ok(false) => {}
+            Err(e) => {
+                log::error!("Failed to send reward digest: {e:?}");
+                summary.failed += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Latest digests sent to the creator, newest first
+pub async fn get_inbox(
+    pool: &DragonflyPool,
+    creator_id: &Principal,
+    limit: usize,
+) -> Result<Vec<RewardInboxEntry>> {
+    let mut conn = pool.get().await?;
+    let entries: Vec<String> = conn
+        .lrange(inbox_key(creator_id), 0, limit as isize - 1)
+        .await?;
+    Ok(entries
+        .iter()
+        .filter_map(|entry| serde_json::from_str(entry).ok())
+        .collect())
+}
+
+/// QStash scheduled job: send reward digests whose window has closed
+#[utoipa::path(
+    post,
+    path = "/rewards/digest/flush",
+    tag = "qstash",
+    responses(
+        (status = 200, description = "Flush summary", body = DigestFlushSummary),
+        (status = 500, description = "Flush failed", body = ApiErrorBody)
+    )
+)]
+pub async fn flush_reward_digests_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match flush_due_digests(&state).await {
+        Ok(summary) => {
+            log::info!("Reward digest flush completed: {:?}", summary);
+            (StatusCode::OK, Json(summary)).into_response()
+        }
+        Err(e) => {
+            log::error!("Reward digest flush failed: {:?}", e);
+            ApiError::Internal(format!("Reward digest flush failed: {}", e)).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest_from_fields() {
+        let fields = vec![
+            (STARTED_AT_FIELD.to_string(), 1_700_000_000.0),
+            (COUNT_FIELD.to_string(), 3.0),
+            (INR_FIELD.to_string(), 11.1),
+            ("token:btc".to_string(), 0.00002),
+            ("token:dolr".to_string(), 40.0),
+        ];
+        let digest = RewardDigest::from_fields(&fields);
+
+        assert_eq!(digest.started_at, 1_700_000_000);
+        assert_eq!(digest.rewards, 3);
+        assert_eq!(digest.tokens.len(), 2);
+        assert_eq!(digest.token_labels(), "Bitcoin and DOLR");
+        assert_eq!(RewardDigest::from_fields(&[]).rewards, 0);
+    }
+}
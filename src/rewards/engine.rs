@@ -13,13 +13,29 @@ use crate::{
         view_tracking::ViewTracker,
         wallet::WalletIntegration,
     },
+    tokens::Token,
     yral_auth::dragonfly::DragonflyPool,
 };
+#[cfg(not(feature = "local-bin"))]
+use crate::{config::runtime::runtime, rewards::digest};
 use anyhow::{Context, Result};
 use candid::Principal;
 use chrono::Utc;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+/// Milestones reached while reward payouts were under maintenance
+const DEFERRED_MILESTONES_KEY: &str = "impressions:rewards:deferred_milestones";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DeferredMilestone {
+    video_id: String,
+    creator_id: Principal,
+    view_count: u64,
+    milestone_number: u64,
+}
+
 #[derive(Clone)]
 pub struct RewardEngine {
     dragonfly_redis_store: Arc<DragonflyPool>,
@@ -210,12 +226,13 @@ impl RewardEngine {
         }
 
         // 4. ATOMIC: Count the view (config version is now checked in Lua script)
-        let view_count = self
+        let device_id = event.device_id.as_deref().or(event.client_type.as_deref());
+        let outcome = self
             .view_tracker
-            .track_view(video_id, &event.user_id, true)
+            .track_logged_in_view(video_id, &event.user_id, device_id)
             .await?;
 
-        if let Some(count) = view_count {
+        if let Some(count) = outcome.count {
             log::info!(
                 "New view recorded for video {} by user {}: total count = {}",
                 video_id,
@@ -289,6 +306,9 @@ impl RewardEngine {
                     video_id,
                     publisher_user_id,
                     is_unique_view: true,
+                    is_replay: outcome.is_replay,
+                    is_cross_device_replay: outcome.is_cross_device_replay(),
+                    device_id: event.device_id.clone(),
                     source: event.source.clone(),
                     client_type: event.client_type.clone(),
                     btc_video_view_count: count,
@@ -319,6 +339,9 @@ impl RewardEngine {
                         video_id,
                         publisher_user_id,
                         is_unique_view: false,
+                        is_replay: outcome.is_replay,
+                        is_cross_device_replay: outcome.is_cross_device_replay(),
+                        device_id: event.device_id.clone(),
                         source: event.source.clone(),
                         client_type: event.client_type.clone(),
                         btc_video_view_count: current_count,
@@ -379,96 +402,33 @@ impl RewardEngine {
         config: &RewardConfig,
         app_state: &Arc<AppState>,
     ) -> Result<f64> {
-        use crate::rewards::config::{RewardMode, RewardTokenType};
-
-        // Calculate reward based on mode
-        let (token_amount, total_inr) = match &config.reward_mode {
-            RewardMode::InrAmount {
-                amount_per_view_inr,
-            } => {
-                // Mode 1: INR amount → convert to tokens
-                let total_inr = amount_per_view_inr * config.view_milestone as f64;
-
-                // Convert INR to token amount based on reward_token type
-                let token_amount = match config.reward_token {
-                    RewardTokenType::Btc => {
-                        // Use live BTC/INR rate from blockchain.info
-                        self.btc_converter.convert_inr_to_btc(total_inr).await?
-                    }
-                    RewardTokenType::Dolr => {
-                        // Use ICPSwap for DOLR price conversion
-                        let icpswap_client = app_state
-                            .rewards_module
-                            .icpswap_client
-                            .as_ref()
-                            .context("ICPSwap client not available")?;
-
-                        let amount = self
-                            .btc_converter
-                            .convert_inr_to_dolr_with_icpswap(total_inr, icpswap_client)
-                            .await?;
-
-                        log::info!(
-                            "Used ICPSwap for DOLR conversion: ₹{} INR = {} DOLR",
-                            total_inr,
-                            amount
-                        );
-                        amount
-                    }
-                };
-
-                (token_amount, total_inr)
-            }
-            RewardMode::DirectTokenE8s {
-                amount_per_milestone_e8s,
-            } => {
-                // Mode 2: Direct e8s → convert to token amount, calculate INR for analytics
-                let token_amount = *amount_per_milestone_e8s as f64 / 100_000_000.0;
-
-                // Convert token amount to INR for analytics only
-                let total_inr = match config.reward_token {
-                    RewardTokenType::Btc => {
-                        // Get BTC/INR rate and calculate INR equivalent
-                        let btc_inr_rate = self.btc_converter.get_btc_inr_rate().await?;
-                        token_amount * btc_inr_rate
-                    }
-                    RewardTokenType::Dolr => {
-                        // Get DOLR/USD rate from ICPSwap and convert to INR
-                        let icpswap_client = app_state
-                            .rewards_module
-                            .icpswap_client
-                            .as_ref()
-                            .context("ICPSwap client not available")?;
-
-                        let inr_usd_rate = self.btc_converter.get_inr_usd_rate().await?;
-                        let dolr_usd_rate = icpswap_client.get_dolr_usd_rate().await?;
-
-                        // Convert DOLR → USD → INR
-                        let usd_amount = token_amount * dolr_usd_rate;
-                        let inr_amount = usd_amount * inr_usd_rate;
+        use crate::rewards::config::RewardTokenType;
+
+        #[cfg(not(feature = "local-bin"))]
+        if crate::maintenance::paused(
+            &self.dragonfly_redis_store,
+            crate::maintenance::Subsystem::RewardsPayouts,
+        )
+        .await
+        .is_some()
+        {
+            self.defer_milestone(DeferredMilestone {
+                video_id: video_id.to_string(),
+                creator_id: *creator_id,
+                view_count,
+                milestone_number,
+            })
+            .await?;
+            anyhow::bail!("Reward payouts are under maintenance; milestone deferred");
+        }
 
-                        log::info!(
-                            "Converted {} DOLR to ₹{} INR for analytics (DOLR/USD: {}, INR/USD: {})",
-                            token_amount,
-                            inr_amount,
-                            dolr_usd_rate,
-                            inr_usd_rate
-                        );
+        let (token_amount, total_inr) = self.milestone_reward(config, app_state).await?;
 
-                        inr_amount
-                    }
-                };
-
-                log::info!(
-                    "Using direct e8s mode: {} e8s = {} tokens ≈ ₹{} INR (for analytics)",
-                    amount_per_milestone_e8s,
-                    token_amount,
-                    total_inr
-                );
-
-                (token_amount, total_inr)
-            }
-        };
+        #[cfg(not(feature = "local-bin"))]
+        let multiplier = crate::experiments::reward_multiplier(app_state, *creator_id).await;
+        #[cfg(feature = "local-bin")]
+        let multiplier = 1.0;
+        let (token_amount, total_inr) = (token_amount * multiplier, total_inr * multiplier);
 
         let token_name = match config.reward_token {
             RewardTokenType::Btc => "BTC",
@@ -490,6 +450,8 @@ impl RewardEngine {
             video_id: video_id.to_string(),
             milestone: milestone_number,
             reward_btc: token_amount,
+            reward_amount: token_amount,
+            reward_token: config.reward_token,
             reward_inr: total_inr,
             timestamp: Utc::now().timestamp(),
             tx_id: None,
@@ -542,18 +504,47 @@ impl RewardEngine {
                 )
                 .await;
 
-                // Send notification to creator
-                self.send_reward_notification(
-                    creator_id,
-                    video_id,
-                    milestone_number,
-                    token_amount,
-                    total_inr,
-                    view_count,
-                    config.reward_token,
-                    app_state,
-                )
-                .await;
+                // Notify the creator, batched into a digest unless the window is 0
+                #[cfg(not(feature = "local-bin"))]
+                let digested = {
+                    let digest_window_secs = runtime().rewards.digest_window_secs;
+                    digest_window_secs > 0
+                        && match digest::add_credit(
+                            &self.dragonfly_redis_store,
+                            *creator_id,
+                            config.reward_token,
+                            token_amount,
+                            total_inr,
+                            digest_window_secs,
+                        )
+                        .await
+                        {
+                            Ok(()) => true,
+                            Err(e) => {
+                                log::error!(
+                                "Failed to add reward to digest for creator {}, notifying now: {:?}",
+                                creator_id,
+                                e
+                            );
+                                false
+                            }
+                        }
+                };
+                #[cfg(feature = "local-bin")]
+                let digested = false;
+                if !digested {
+                    self.send_reward_notification(
+                        creator_id,
+                        video_id,
+                        milestone_number,
+                        token_amount,
+                        total_inr,
+                        view_count,
+                        config.reward_token,
+                        app_state,
+                    )
+                    .await;
+                }
             }
             Err(e) => {
                 log::error!(
@@ -569,6 +560,147 @@ impl RewardEngine {
         Ok(total_inr)
     }
 
+    /// Tokens and INR paid for one milestone under `config`, before any
+    /// experiment multiplier
+    pub async fn milestone_reward(
+        &self,
+        config: &RewardConfig,
+        app_state: &Arc<AppState>,
+    ) -> Result<(f64, f64)> {
+        use crate::rewards::config::{RewardMode, RewardTokenType};
+
+        // Calculate reward based on mode
+        let reward = match &config.reward_mode {
+            RewardMode::InrAmount {
+                amount_per_view_inr,
+            } => {
+                // Mode 1: INR amount → convert to tokens
+                let total_inr = amount_per_view_inr * config.view_milestone as f64;
+
+                // Convert INR to token amount based on reward_token type
+                let token_amount = match config.reward_token {
+                    RewardTokenType::Btc => {
+                        // Use live BTC/INR rate from the rates service
+                        self.btc_converter.convert_inr_to_btc(total_inr).await?
+                    }
+                    RewardTokenType::Dolr => {
+                        // Use ICPSwap for DOLR price conversion
+                        let icpswap_client = app_state
+                            .rewards_module
+                            .icpswap_client
+                            .as_ref()
+                            .context("ICPSwap client not available")?;
+
+                        let amount = self
+                            .btc_converter
+                            .convert_inr_to_dolr_with_icpswap(total_inr, icpswap_client)
+                            .await?;
+
+                        log::info!(
+                            "Used ICPSwap for DOLR conversion: ₹{} INR = {} DOLR",
+                            total_inr,
+                            amount
+                        );
+                        amount
+                    }
+                };
+
+                (token_amount, total_inr)
+            }
+            RewardMode::DirectTokenE8s {
+                amount_per_milestone_e8s,
+            } => {
+                // Mode 2: Direct e8s → convert to token amount, calculate INR for analytics
+                let token_amount = *amount_per_milestone_e8s as f64 / 100_000_000.0;
+
+                // Convert token amount to INR for analytics only
+                let total_inr = self
+                    .btc_converter
+                    .token_value_inr(Token::from(config.reward_token), token_amount)
+                    .await?;
+
+                log::info!(
+                    "Using direct e8s mode: {} e8s = {} tokens ≈ ₹{} INR (for analytics)",
+                    amount_per_milestone_e8s,
+                    token_amount,
+                    total_inr
+                );
+
+                (token_amount, total_inr)
+            }
+        };
+
+        Ok(reward)
+    }
+
+    async fn defer_milestone(&self, milestone: DeferredMilestone) -> Result<()> {
+        log::warn!(
+            "Deferring milestone {} reward for video {} until payouts resume",
+            milestone.milestone_number,
+            milestone.video_id
+        );
+        let mut conn = self.dragonfly_redis_store.get().await?;
+        let _: () = conn
+            .rpush(DEFERRED_MILESTONES_KEY, serde_json::to_string(&milestone)?)
+            .await?;
+        Ok(())
+    }
+
+    /// Pays milestones deferred by maintenance, oldest first. Returns how
+    /// many were paid; stops early if payouts are paused again.
+    pub async fn replay_deferred_milestones(&self, app_state: &Arc<AppState>) -> Result<usize> {
+        let config = get_config(&self.dragonfly_redis_store).await?;
+        let mut paid = 0;
+        loop {
+            let payload: Option<String> = {
+                let mut conn = self.dragonfly_redis_store.get().await?;
+                conn.lpop(DEFERRED_MILESTONES_KEY, None).await?
+            };
+            let Some(payload) = payload else {
+                return Ok(paid);
+            };
+            let milestone: DeferredMilestone = match serde_json::from_str(&payload) {
+                Ok(milestone) => milestone,
+                Err(e) => {
+                    log::error!("Dropping unreadable deferred milestone {payload}: {e}");
+                    continue;
+                }
+            };
+
+            #[cfg(not(feature = "local-bin"))]
+            if crate::maintenance::paused(
+                &self.dragonfly_redis_store,
+                crate::maintenance::Subsystem::RewardsPayouts,
+            )
+            .await
+            .is_some()
+            {
+                let mut conn = self.dragonfly_redis_store.get().await?;
+                let _: () = conn.lpush(DEFERRED_MILESTONES_KEY, payload).await?;
+                return Ok(paid);
+            }
+
+            match self
+                .process_milestone(
+                    &milestone.video_id,
+                    &milestone.creator_id,
+                    milestone.view_count,
+                    milestone.milestone_number,
+                    &config,
+                    app_state,
+                )
+                .await
+            {
+                Ok(_) => paid += 1,
+                Err(e) => log::error!(
+                    "Failed to pay deferred milestone {} for video {}: {e:?}",
+                    milestone.milestone_number,
+                    milestone.video_id
+                ),
+            }
+        }
+    }
+
     /// Send notification to creator about reward
     #[allow(clippy::too_many_arguments)]
     async fn send_reward_notification(
@@ -609,6 +741,46 @@ impl RewardEngine {
         );
     }
 
+    /// Credit a completed daily mission and record it in the user's reward history
+    pub async fn credit_mission_reward(
+        &self,
+        user_id: Principal,
+        mission_id: &str,
+        day: &str,
+        amount_e8s: u64,
+        token_type: crate::rewards::config::RewardTokenType,
+    ) -> Result<String> {
+        use crate::rewards::config::RewardTokenType;
+
+        let tx_id = self
+            .wallet
+            .credit_mission_reward(user_id, amount_e8s, mission_id, day, token_type)
+            .await?;
+        let reward_amount = amount_e8s as f64 / 100_000_000.0;
+
+        self.history_tracker
+            .record_reward(
+                &user_id,
+                RewardRecord {
+                    video_id: format!("mission:{mission_id}"),
+                    milestone: 0,
+                    reward_btc: match token_type {
+                        RewardTokenType::Btc => reward_amount,
+                        RewardTokenType::Dolr => 0.0,
+                    },
+                    reward_amount,
+                    reward_token: token_type,
+                    reward_inr: 0.0,
+                    timestamp: Utc::now().timestamp(),
+                    tx_id: Some(tx_id.clone()),
+                    view_count: 0,
+                },
+            )
+            .await;
+
+        Ok(tx_id)
+    }
+
     /// Get current configuration
     pub async fn get_config(&self) -> RewardConfig {
         get_config(&self.dragonfly_redis_store)
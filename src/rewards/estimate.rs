@@ -0,0 +1,259 @@
+//! Payout estimate for a candidate reward config over recorded views.
+//!
+//! `POST /admin/estimate` does not run [`RewardEngine`](super::RewardEngine).
+//! It is a separate SQL approximation: it counts distinct viewers per video
+//! in the `video_duration_watched` events of a date range that meet the
+//! candidate's watch-time threshold, divides by its milestone size, and
+//! prices each milestone through
+//! [`RewardEngine::milestone_reward`](super::RewardEngine::milestone_reward)
+//! at current rates. Nothing is credited or written.
+//!
+//! What the live engine does and this estimate doesn't:
+//! - view counts carry over from before the range and reset when the config
+//!   version changes; here they start at zero
+//! - shadow bans, fraud caps and registration checks drop views; here every
+//!   logged-in view counts, and a missing `is_logged_in` counts as logged in
+//! - the engine dedupes a viewer's replays within a window and counts them
+//!   again after it; here each viewer counts once per video
+//! - non-web views only count between 3 and 4.5 seconds watched; here any
+//!   view over the candidate's threshold counts
+//! - experiment multipliers are not applied
+//!
+//! Use it to compare configs against each other, not as a payout forecast.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{extract::State, http::HeaderMap, Json};
+use chrono::NaiveDate;
+use google_cloud_bigquery::http::{
+    job::query::QueryRequest,
+    tabledata::list::{Tuple, Value},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    config::runtime::runtime,
+    error::{ApiError, ApiErrorBody},
+    rewards::config::{RewardConfig, RewardTokenType},
+    tokens::Token,
+};
+
+const PROJECT_ID: &str = "hot-or-not-feed-intelligence";
+const EVENTS_TABLE: &str =
+    "`hot-or-not-feed-intelligence.analytics_335143420.test_events_analytics`";
+
+/// Longest range one estimate may scan
+const MAX_RANGE_DAYS: i64 = 31;
+
+const PERCENTILES: [u8; 3] = [50, 90, 99];
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EstimateRequest {
+    pub config: RewardConfig,
+    /// First day replayed, inclusive (UTC)
+    #[schema(value_type = String, format = Date)]
+    pub from: NaiveDate,
+    /// Last day replayed, inclusive (UTC)
+    #[schema(value_type = String, format = Date)]
+    pub to: NaiveDate,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct EarningsPercentile {
+    /// 100 is the top earner
+    pub percentile: u8,
+    pub milestones: u64,
+    pub tokens: f64,
+    pub inr: f64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EstimateResponse {
+    pub reward_token: RewardTokenType,
+    pub reward_per_milestone_tokens: f64,
+    pub reward_per_milestone_inr: f64,
+    pub milestones: u64,
+    pub rewarded_creators: u64,
+    pub total_tokens: f64,
+    pub total_inr: f64,
+    /// Earnings of rewarded creators over the range
+    pub percentiles: Vec<EarningsPercentile>,
+}
+
+/// Approximate milestones per creator in the range, summarised in one row: creators,
+/// total milestones, p50, p90, p99 and max
+fn milestones_query(config: &RewardConfig, from: NaiveDate, to: NaiveDate) -> String {
+    let min_watch = config.min_watch_duration;
+    let view_milestone = config.view_milestone;
+    format!(
+        "WITH views AS (
+            SELECT
+                JSON_EXTRACT_SCALAR(params, '$.publisher_user_id') AS creator_id,
+                JSON_EXTRACT_SCALAR(params, '$.video_id') AS video_id,
+                COUNT(DISTINCT JSON_EXTRACT_SCALAR(params, '$.user_id')) AS viewers
+            FROM {EVENTS_TABLE}
+            WHERE event = 'video_duration_watched'
+              AND DATE(timestamp) BETWEEN '{from}' AND '{to}'
+              AND COALESCE(SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.is_logged_in') AS BOOL), TRUE)
+              AND SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.absolute_watched') AS FLOAT64) >= {min_watch}
+              AND JSON_EXTRACT_SCALAR(params, '$.user_id')
+                  != JSON_EXTRACT_SCALAR(params, '$.publisher_user_id')
+            GROUP BY creator_id, video_id
+            HAVING creator_id IS NOT NULL AND video_id IS NOT NULL
+        ),
+        creators AS (
+            SELECT creator_id, SUM(DIV(viewers, {view_milestone})) AS milestones
+            FROM views
+            GROUP BY creator_id
+            HAVING milestones > 0
+        )
+        SELECT
+            COUNT(*) AS creators,
+            SUM(milestones) AS milestones,
+            APPROX_QUANTILES(milestones, 100)[SAFE_OFFSET(50)] AS p50,
+            APPROX_QUANTILES(milestones, 100)[SAFE_OFFSET(90)] AS p90,
+            APPROX_QUANTILES(milestones, 100)[SAFE_OFFSET(99)] AS p99,
+            MAX(milestones) AS max_milestones
+        FROM creators"
+    )
+}
+
+fn cell_u64(row: &Tuple, index: usize) -> u64 {
+    match row.f.get(index).map(|cell| &cell.v) {
+        Some(Value::String(s)) => s.parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Rejects ranges and configs the live engine could not run
+fn validate_request(request: &EstimateRequest) -> Result<(), ApiError> {
+    if request.from > request.to {
+        return Err(ApiError::InvalidRequest(
+            "from must not be after to".to_string(),
+        ));
+    }
+    if (request.to - request.from).num_days() >= MAX_RANGE_DAYS {
+        return Err(ApiError::InvalidRequest(format!(
+            "Range must be at most {MAX_RANGE_DAYS} days"
+        )));
+    }
+    if request.config.view_milestone == 0 {
+        return Err(ApiError::InvalidRequest(
+            "view_milestone must be positive".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Estimate payouts for a candidate reward config over recorded views
+#[utoipa::path(
+    post,
+    path = "/admin/estimate",
+    request_body = EstimateRequest,
+    tag = "rewards",
+    responses(
+        (status = 200, description = "Estimated payouts", body = EstimateResponse),
+        (status = 400, description = "Invalid range or config", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Pricing or BigQuery failed", body = ApiErrorBody)
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn estimate_rewards_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<EstimateRequest>,
+) -> Result<Json<EstimateResponse>, ApiError> {
+    require_operator(&headers)?;
+    validate_request(&request)?;
+    let config = &request.config;
+
+    let (tokens_per_milestone, inr_per_milestone) = state
+        .rewards_module
+        .reward_engine
+        .milestone_reward(config, &state)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to price a milestone: {e}")))?;
+    let units = Token::from(config.reward_token).to_units(tokens_per_milestone);
+    if units > runtime().rewards.max_view_reward_e8s {
+        return Err(ApiError::InvalidRequest(format!(
+            "A milestone pays {units} e8s, over rewards.max_view_reward_e8s; every payout would be rejected"
+        )));
+    }
+
+    let query_request = QueryRequest {
+        query: milestones_query(config, request.from, request.to),
+        ..Default::default()
+    };
+    let result = state
+        .bigquery_client
+        .job()
+        .query(PROJECT_ID, &query_request)
+        .await
+        .map_err(|e| ApiError::BigQuery(e.to_string()))?;
+    let rows = result.rows.unwrap_or_default();
+    let row = rows
+        .first()
+        .ok_or_else(|| ApiError::BigQuery("Estimate query returned no rows".to_string()))?;
+
+    let earnings = |percentile: u8, milestones: u64| EarningsPercentile {
+        percentile,
+        milestones,
+        tokens: milestones as f64 * tokens_per_milestone,
+        inr: milestones as f64 * inr_per_milestone,
+    };
+    let mut percentiles: Vec<EarningsPercentile> = PERCENTILES
+        .iter()
+        .enumerate()
+        .map(|(i, percentile)| earnings(*percentile, cell_u64(row, 2 + i)))
+        .collect();
+    percentiles.push(earnings(100, cell_u64(row, 5)));
+
+    let milestones = cell_u64(row, 1);
+    log::info!(
+        "Estimated reward config over {}..={}: {} milestones, ₹{:.2}",
+        request.from,
+        request.to,
+        milestones,
+        milestones as f64 * inr_per_milestone
+    );
+
+    Ok(Json(EstimateResponse {
+        reward_token: config.reward_token,
+        reward_per_milestone_tokens: tokens_per_milestone,
+        reward_per_milestone_inr: inr_per_milestone,
+        milestones,
+        rewarded_creators: cell_u64(row, 0),
+        total_tokens: milestones as f64 * tokens_per_milestone,
+        total_inr: milestones as f64 * inr_per_milestone,
+        percentiles,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_request() {
+        let request = |from: &str, to: &str, view_milestone| EstimateRequest {
+            config: RewardConfig {
+                view_milestone,
+                ..Default::default()
+            },
+            from: from.parse().unwrap(),
+            to: to.parse().unwrap(),
+        };
+
+        assert!(validate_request(&request("2026-01-01", "2026-01-31", 100)).is_ok());
+        assert!(validate_request(&request("2026-01-01", "2026-02-01", 100)).is_err());
+        assert!(validate_request(&request("2026-01-02", "2026-01-01", 100)).is_err());
+        assert!(validate_request(&request("2026-01-01", "2026-01-01", 0)).is_err());
+    }
+}
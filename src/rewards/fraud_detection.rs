@@ -1,6 +1,8 @@
 use std::env;
 use std::sync::Arc;
 
+use crate::config::runtime::runtime;
+use crate::offchain_service::send_message_gchat_webhook;
 use crate::yral_auth::dragonfly::DragonflyPool;
 use anyhow::Result;
 use candid::Principal;
@@ -150,6 +152,79 @@ impl FraudDetector {
         FraudCheck::Clean
     }
 
+    /// Check a referral attribution: self-referrals, shadow banned referrers and
+    /// referrers signing up accounts faster than real invites would are rejected
+    pub async fn check_referral(&self, referrer: Principal, referee: Principal) -> FraudCheck {
+        if referrer == referee {
+            return FraudCheck::Suspicious;
+        }
+        if self.is_shadow_banned(&referrer).await.unwrap_or(false) {
+            return FraudCheck::Suspicious;
+        }
+
+        let key = format!("impressions:referrals:user:{}:recent", referrer);
+        let current_timestamp = Utc::now().timestamp();
+        let cutoff = current_timestamp - self.time_window;
+        let recent = self
+            .dragonfly_redis_store
+            .execute_with_retry(|mut conn| {
+                let k = key.clone();
+                async move {
+                    conn.lpush::<_, _, ()>(&k, current_timestamp).await?;
+                    conn.ltrim::<_, ()>(&k, 0, 100).await?;
+                    conn.expire::<_, ()>(&k, 3600).await?;
+                    conn.lrange::<_, Vec<i64>>(&k, 0, -1).await
+                }
+            })
+            .await;
+
+        let referral_threshold = runtime().rewards.referral_fraud_threshold;
+        match recent {
+            Ok(timestamps)
+                if timestamps.iter().filter(|&&ts| ts > cutoff).count() > referral_threshold =>
+            {
+                log::warn!(
+                    "Referrer {} exceeded {} referrals per hour",
+                    referrer,
+                    referral_threshold
+                );
+                FraudCheck::Suspicious
+            }
+            _ => FraudCheck::Clean,
+        }
+    }
+
+    /// Shadow ban a principal that keeps sending events past their velocity
+    /// limit, see [`crate::events::velocity`]
+    pub async fn report_event_spam(
+        &self,
+        principal: Principal,
+        event: &str,
+        limited_count: u64,
+    ) -> Result<()> {
+        let ban_key = format!("impressions:rewards:shadow_ban:{}", principal);
+        let shadow_ban_duration = self.shadow_ban_duration;
+        self.dragonfly_redis_store
+            .execute_with_retry(|mut conn| {
+                let key = ban_key.clone();
+                async move {
+                    conn.set_ex::<_, _, ()>(&key, "1", shadow_ban_duration)
+                        .await
+                }
+            })
+            .await?;
+
+        log::warn!(
+            "Shadow banned {} for {} seconds after {} rate limited {} events",
+            principal,
+            shadow_ban_duration,
+            limited_count,
+            event
+        );
+        send_event_spam_alert(principal.to_string(), event.to_string(), limited_count);
+        Ok(())
+    }
+
     /// Check if a creator is currently shadow banned
     pub async fn is_shadow_banned(&self, creator_id: &Principal) -> Result<bool> {
         let ban_key = format!("impressions:rewards:shadow_ban:{}", creator_id);
@@ -217,6 +292,29 @@ fn send_fraud_alert(creator_id: String, reward_count: usize) {
     });
 }
 
+/// Send event spam alert to Google Chat
+fn send_event_spam_alert(principal: String, event: String, limited_count: u64) {
+    tokio::spawn(async move {
+        let Ok(webhook_url) = env::var("BTC_REWARDS_GCHAT_WEBHOOK_URL") else {
+            log::warn!("BTC_REWARDS_GCHAT_WEBHOOK_URL not set, skipping event spam alert");
+            return;
+        };
+
+        let data = json!({
+            "text": format!(
+                "⚠️ FRAUD ALERT - Event Spam\n\nPrincipal: {}\nEvent: {}\nRate limited: {} events\nAction: Shadow banned from rewards\nTime: {}",
+                principal,
+                event,
+                limited_count,
+                chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+            )
+        });
+        if let Err(e) = send_message_gchat_webhook(&webhook_url, data).await {
+            log::error!("Failed to send event spam alert to Google Chat: {e:?}");
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
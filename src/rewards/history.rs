@@ -1,4 +1,4 @@
-use crate::yral_auth::dragonfly::DragonflyPool;
+use crate::{rewards::config::RewardTokenType, yral_auth::dragonfly::DragonflyPool};
 use anyhow::Result;
 use candid::Principal;
 use redis::AsyncCommands;
@@ -20,7 +20,15 @@ pub struct ViewRecord {
 pub struct RewardRecord {
     pub video_id: String,
     pub milestone: u64,
+    /// BTC credited; zero for rewards paid in another token
     pub reward_btc: f64,
+    /// Amount credited in `reward_token`, in whole tokens
+    #[serde(default)]
+    pub reward_amount: f64,
+    /// Token the reward was paid in; records written before this field
+    /// existed were BTC
+    #[serde(default)]
+    pub reward_token: RewardTokenType,
     pub reward_inr: f64,
     pub timestamp: i64,
     pub tx_id: Option<String>,
@@ -146,6 +154,34 @@ impl HistoryTracker {
         });
     }
 
+    /// Move `from`'s view and reward history behind `into`'s, keeping the
+    /// per-user caps. Returns the number of records moved.
+    pub async fn merge_user_history(&self, from: &Principal, into: &Principal) -> Result<usize> {
+        let mut conn = self.dragonfly_redis_store.get().await?;
+        let mut moved = 0;
+
+        for suffix in ["view_history", "reward_history"] {
+            let from_key = format!("impressions:rewards:user:{}:{}", from, suffix);
+            let into_key = format!("impressions:rewards:user:{}:{}", into, suffix);
+
+            // Lists are newest first and `from`'s activity predates the link
+            let records: Vec<String> = conn.lrange(&from_key, 0, -1).await?;
+            if records.is_empty() {
+                continue;
+            }
+            moved += records.len();
+
+            conn.rpush::<_, _, ()>(&into_key, &records).await?;
+            conn.ltrim::<_, ()>(&into_key, 0, 999).await?;
+            if suffix == "view_history" {
+                conn.expire::<_, ()>(&into_key, 7776000).await?; // 90 days TTL
+            }
+            conn.del::<_, ()>(&from_key).await?;
+        }
+
+        Ok(moved)
+    }
+
     /// Get video view history
     pub async fn get_video_views(&self, video_id: &str, limit: usize) -> Result<Vec<ViewRecord>> {
         let key = format!("impressions:rewards:video:{}:view_history", video_id);
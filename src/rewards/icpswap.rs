@@ -5,6 +5,8 @@ use once_cell::sync::Lazy;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::canister::agent_pool;
+
 // ICPSwap pool canister IDs
 const DOLR_ICP_POOL: &str = "rxwy2-zaaaa-aaaag-qcfna-cai"; // DOLR/ICP pool
 const ICP_CKUSDT_POOL: &str = "hkstf-6iaaa-aaaag-qkcoq-cai"; // ICP/ckUSDT pool
@@ -44,18 +46,13 @@ enum SwapResult {
 
 #[derive(Clone)]
 pub struct IcpSwapClient {
-    agent: Arc<Agent>,
+    agent: Agent,
 }
 
 impl IcpSwapClient {
     pub async fn new() -> Result<Self> {
-        let agent = Agent::builder()
-            .with_url("https://icp-api.io")
-            .build()
-            .context("Failed to create IC agent")?;
-
         Ok(Self {
-            agent: Arc::new(agent),
+            agent: agent_pool::anonymous_agent(),
         })
     }
 
@@ -149,13 +146,15 @@ impl IcpSwapClient {
             amount_out_minimum: "0".to_string(),
         };
 
-        let response = self
-            .agent
-            .query(&pool_principal, "quote")
-            .with_arg(candid::encode_one(&args).context("Failed to encode args")?)
-            .call()
-            .await
-            .context("Failed to call quote method")?;
+        let arg = candid::encode_one(&args).context("Failed to encode args")?;
+        let response = agent_pool::query(pool_principal, "quote", || {
+            self.agent
+                .query(&pool_principal, "quote")
+                .with_arg(arg.clone())
+                .call()
+        })
+        .await
+        .context("Failed to call quote method")?;
 
         let result: SwapResult =
             candid::decode_one(&response).context("Failed to decode response")?;
@@ -2,7 +2,11 @@ pub mod analytics;
 pub mod api;
 pub mod btc_conversion;
 pub mod config;
+#[cfg(not(feature = "local-bin"))]
+pub mod digest;
 pub mod engine;
+#[cfg(not(feature = "local-bin"))]
+pub mod estimate;
 pub mod fraud_detection;
 pub mod history;
 pub mod icpswap;
@@ -1,5 +1,6 @@
 use crate::{
-    app_state::AppState, consts::USER_INFO_SERVICE_CANISTER_ID, yral_auth::dragonfly::DragonflyPool,
+    app_state::AppState, canister::agent_pool, consts::USER_INFO_SERVICE_CANISTER_ID,
+    yral_auth::dragonfly::DragonflyPool,
 };
 use anyhow::Result;
 use candid::Principal;
@@ -81,9 +82,12 @@ impl UserVerification {
 async fn check_user_registration(user_principal: Principal, app_state: &Arc<AppState>) -> bool {
     let user_info_service = UserInfoService(*USER_INFO_SERVICE_CANISTER_ID, &app_state.agent);
 
-    let result = match user_info_service
-        .get_user_session_type(user_principal)
-        .await
+    let result = match agent_pool::query(
+        *USER_INFO_SERVICE_CANISTER_ID,
+        "get_user_session_type",
+        || user_info_service.get_user_session_type(user_principal),
+    )
+    .await
     {
         Ok(result) => result,
         Err(e) => {
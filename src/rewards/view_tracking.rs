@@ -1,10 +1,13 @@
-use crate::yral_auth::dragonfly::DragonflyPool;
+use crate::{config::runtime::runtime, yral_auth::dragonfly::DragonflyPool};
 use anyhow::{Context, Result};
 use candid::Principal;
 use redis::AsyncCommands;
 use sha1::{Digest, Sha1};
 use std::sync::Arc;
 
+/// Device recorded for views whose event carries no device id
+const UNKNOWN_DEVICE: &str = "unknown";
+
 const LUA_ATOMIC_VIEW_SCRIPT: &str = r#"
     --!df flags=allow-undeclared-keys
     -- Atomic operation for view counting with config change handling
@@ -13,12 +16,28 @@ const LUA_ATOMIC_VIEW_SCRIPT: &str = r#"
     local views_set = KEYS[1]  -- impressions:rewards:views:{video_id} (set of user IDs)
     local video_hash = KEYS[2]  -- impressions:rewards:video:{video_id} (hash with count & config_version)
     local config_version_key = KEYS[3]  -- impressions:rewards:config:version
+    local seen_set = KEYS[4]  -- impressions:rewards:seen:{video_id}:{user_id} (devices, rolling TTL)
 
     local user_id = ARGV[1]
+    local device_id = ARGV[2]
+    local seen_ttl = tonumber(ARGV[3])
 
     -- Always increment total_count_all (counts all views including duplicates)
     redis.call('HINCRBY', video_hash, 'total_count_all', 1)
 
+    -- A view within the dedup window of the user's last view, on any device, is a replay
+    local is_replay = redis.call('EXISTS', seen_set)
+    local new_device = redis.call('SADD', seen_set, device_id)
+    redis.call('EXPIRE', seen_set, seen_ttl)
+    if is_replay == 1 then
+        redis.call('HINCRBY', video_hash, 'replay_count', 1)
+        if new_device == 1 then
+            redis.call('HINCRBY', video_hash, 'cross_device_replay_count', 1)
+        end
+    else
+        redis.call('HINCRBY', video_hash, 'unique_view_count', 1)
+    end
+
     -- Get current global config version from Redis
     local current_global_version = redis.call('GET', config_version_key) or '1'
 
@@ -33,17 +52,37 @@ const LUA_ATOMIC_VIEW_SCRIPT: &str = r#"
     end
 
     -- Check if user already viewed (critical check for unique views)
+    -- Returns {count, is_replay, new_device}; count is 0 for a duplicate view
     local added = redis.call('SADD', views_set, user_id)
     if added == 1 then
         -- New unique logged-in view: increment unique counters
         redis.call('HINCRBY', video_hash, 'count', 1)
         redis.call('HINCRBY', video_hash, 'total_count_loggedin', 1)
-        return redis.call('HGET', video_hash, 'count')
+        return {tonumber(redis.call('HGET', video_hash, 'count')), is_replay, new_device}
     else
-        return nil  -- Duplicate view (total_count_all already incremented above)
+        return {0, is_replay, new_device}  -- Duplicate view (total_count_all already incremented above)
     end
 "#;
 
+/// Result of counting a logged-in view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViewOutcome {
+    /// Reward view count after this view, None when the user was already
+    /// counted for the video on any device
+    pub count: Option<u64>,
+    /// The user viewed the video within `rewards.view_dedup_window_secs`
+    pub is_replay: bool,
+    /// First view from this device within the window
+    pub new_device: bool,
+}
+
+impl ViewOutcome {
+    /// A replay from a device the user hadn't watched the video on
+    pub fn is_cross_device_replay(&self) -> bool {
+        self.is_replay && self.new_device
+    }
+}
+
 fn calculate_script_sha(script: &str) -> String {
     let mut hasher = Sha1::new();
     hasher.update(script.as_bytes());
@@ -112,21 +151,39 @@ impl ViewTracker {
             return Ok(None);
         }
 
+        let outcome = self.track_logged_in_view(video_id, user_id, None).await?;
+        Ok(outcome.count)
+    }
+
+    /// Counts a logged-in view from `device_id`. The reward count is per
+    /// user, so a second device never counts again; replays within the
+    /// dedup window are counted separately for analytics.
+    pub async fn track_logged_in_view(
+        &self,
+        video_id: &str,
+        user_id: &Principal,
+        device_id: Option<&str>,
+    ) -> Result<ViewOutcome> {
         // Logged-in path: use Lua script for atomic duplicate checking
         let views_set_key = format!("impressions:rewards:views:{}", video_id);
         let video_hash_key = format!("impressions:rewards:video:{}", video_id);
         let config_version_key = "impressions:rewards:config:version".to_string();
+        let seen_set_key = format!("impressions:rewards:seen:{}:{}", video_id, user_id);
         let user_id_str = user_id.to_string();
+        let device = device_id.unwrap_or(UNKNOWN_DEVICE).to_string();
+        let seen_ttl = runtime().rewards.view_dedup_window_secs;
         let sha = self.script_sha.clone();
 
         // Try to use the loaded script SHA, fallback to EVAL if not loaded
-        let result: Option<u64> = self
+        let (count, is_replay, new_device): (u64, u8, u8) = self
             .redis_store_pool
             .execute_with_retry(|mut conn| {
                 let views_key = views_set_key.clone();
                 let video_key = video_hash_key.clone();
                 let config_key = config_version_key.clone();
+                let seen_key = seen_set_key.clone();
                 let user = user_id_str.clone();
+                let device = device.clone();
                 let script_sha = sha.clone();
 
                 async move {
@@ -134,11 +191,14 @@ impl ViewTracker {
                         // Use EVALSHA for better performance
                         let evalsha_result = redis::cmd("EVALSHA")
                             .arg(sha_str)
-                            .arg(3) // number of keys
+                            .arg(4) // number of keys
                             .arg(&views_key)
                             .arg(&video_key)
                             .arg(&config_key)
+                            .arg(&seen_key)
                             .arg(&user)
+                            .arg(&device)
+                            .arg(seen_ttl)
                             .query_async(&mut conn)
                             .await;
 
@@ -149,11 +209,14 @@ impl ViewTracker {
                                 log::warn!("EVALSHA failed, falling back to EVAL: {}", e);
                                 redis::cmd("EVAL")
                                     .arg(LUA_ATOMIC_VIEW_SCRIPT)
-                                    .arg(3)
+                                    .arg(4)
                                     .arg(&views_key)
                                     .arg(&video_key)
                                     .arg(&config_key)
+                                    .arg(&seen_key)
                                     .arg(&user)
+                                    .arg(&device)
+                                    .arg(seen_ttl)
                                     .query_async(&mut conn)
                                     .await
                             }
@@ -162,11 +225,14 @@ impl ViewTracker {
                         // Script not loaded, use EVAL
                         redis::cmd("EVAL")
                             .arg(LUA_ATOMIC_VIEW_SCRIPT)
-                            .arg(3)
+                            .arg(4)
                             .arg(&views_key)
                             .arg(&video_key)
                             .arg(&config_key)
+                            .arg(&seen_key)
                             .arg(&user)
+                            .arg(&device)
+                            .arg(seen_ttl)
                             .query_async(&mut conn)
                             .await
                     }
@@ -175,7 +241,11 @@ impl ViewTracker {
             .await
             .context("Failed to execute view tracking script")?;
 
-        Ok(result)
+        Ok(ViewOutcome {
+            count: (count > 0).then_some(count),
+            is_replay: is_replay == 1,
+            new_device: new_device == 1,
+        })
     }
 
     pub async fn get_view_count(&self, video_id: &str) -> Result<u64> {
@@ -381,6 +451,7 @@ mod tests {
             let patterns = vec![
                 format!("impressions:rewards:video:{}*", self.key_prefix),
                 format!("impressions:rewards:views:{}*", self.key_prefix),
+                format!("impressions:rewards:seen:{}*", self.key_prefix),
                 "impressions:rewards:config:version".to_string(),
             ];
 
@@ -815,4 +886,45 @@ mod tests {
 
         test_tracker.cleanup().await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_cross_device_replay() {
+        let test_tracker = TestViewTracker::new().await;
+        let video_id = test_tracker.test_video_id("video11");
+        let user1 = Principal::self_authenticating("device_user1");
+
+        let first = test_tracker
+            .tracker
+            .track_logged_in_view(&video_id, &user1, Some("phone"))
+            .await
+            .unwrap();
+        assert_eq!(first.count, Some(1));
+        assert!(!first.is_replay);
+
+        // Same user on a second device: a replay, not a second reward view
+        let second = test_tracker
+            .tracker
+            .track_logged_in_view(&video_id, &user1, Some("laptop"))
+            .await
+            .unwrap();
+        assert_eq!(second.count, None);
+        assert!(second.is_cross_device_replay());
+
+        let third = test_tracker
+            .tracker
+            .track_logged_in_view(&video_id, &user1, Some("phone"))
+            .await
+            .unwrap();
+        assert!(third.is_replay);
+        assert!(!third.is_cross_device_replay());
+
+        let count = test_tracker
+            .tracker
+            .get_view_count(&video_id)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        test_tracker.cleanup().await.unwrap();
+    }
 }
@@ -1,22 +1,19 @@
+use crate::config::runtime::runtime;
 use crate::rewards::config::RewardTokenType;
+use crate::tokens::{transfer_memo, Token, TokenWallet};
 use anyhow::Result;
 use candid::Principal;
 use serde_json::json;
-use yral_canisters_common::utils::token::{CkBtcOperations, DolrOperations, TokenOperations};
 
 #[derive(Clone)]
 pub struct WalletIntegration {
-    dolr_ops: DolrOperations,
-    btc_ops: CkBtcOperations,
+    wallet: TokenWallet,
 }
 
-const MAX_AMOUNT_E8S_VIEWS: u64 = 10_000_000_000; // 10 tokens max
-
 impl WalletIntegration {
     pub fn new(admin_agent: ic_agent::Agent) -> Self {
         Self {
-            dolr_ops: DolrOperations::new(admin_agent.clone()),
-            btc_ops: CkBtcOperations::new(admin_agent),
+            wallet: TokenWallet::new(admin_agent),
         }
     }
 
@@ -31,9 +28,9 @@ impl WalletIntegration {
         token_type: RewardTokenType,
     ) -> Result<String> {
         // Convert to e8s (1 token = 100,000,000 e8s)
-        let amount_e8s = (token_amount * 100_000_000.0) as u64;
+        let amount_e8s = Token::from(token_type).to_units(token_amount);
 
-        if amount_e8s > MAX_AMOUNT_E8S_VIEWS {
+        if amount_e8s > runtime().rewards.max_view_reward_e8s {
             return Err(anyhow::anyhow!("Amount exceeds maximum allowed"));
         }
 
@@ -67,18 +64,15 @@ impl WalletIntegration {
         );
 
         // Transfer using appropriate token operations
-        let transfer_result = match token_type {
-            RewardTokenType::Btc => {
-                self.btc_ops
-                    .add_balance_with_memo(creator_id, amount_e8s, Some(memo_bytes))
-                    .await
-            }
-            RewardTokenType::Dolr => {
-                self.dolr_ops
-                    .add_balance_with_memo(creator_id, amount_e8s, Some(memo_bytes))
-                    .await
-            }
-        };
+        let transfer_result = self
+            .wallet
+            .transfer(
+                Token::from(token_type),
+                creator_id,
+                amount_e8s,
+                Some(memo_bytes),
+            )
+            .await;
 
         match transfer_result {
             Ok(_) => {
@@ -116,4 +110,52 @@ impl WalletIntegration {
             }
         }
     }
+
+    /// Credit a daily mission reward to a user. The memo is unique to the
+    /// user, mission and day, so the ledger can refuse a repeat credit.
+    pub async fn credit_mission_reward(
+        &self,
+        user_id: Principal,
+        amount_e8s: u64,
+        mission_id: &str,
+        day: &str,
+        token_type: RewardTokenType,
+    ) -> Result<String> {
+        if amount_e8s > runtime().rewards.max_mission_reward_e8s {
+            return Err(anyhow::anyhow!("Amount exceeds maximum allowed"));
+        }
+
+        let token_name = match token_type {
+            RewardTokenType::Btc => "BTC",
+            RewardTokenType::Dolr => "DOLR",
+        };
+        let memo_bytes = transfer_memo(&["mission", &user_id.to_text(), mission_id, day]);
+
+        log::info!(
+            "Transferring {} e8s {} to user {} for mission {} on {}",
+            amount_e8s,
+            token_name,
+            user_id,
+            mission_id,
+            day
+        );
+
+        // Kept as a `TransferError` so callers can tell a rejection from an
+        // unknown outcome
+        self.wallet
+            .transfer(
+                Token::from(token_type),
+                user_id,
+                amount_e8s,
+                Some(memo_bytes),
+            )
+            .await?;
+
+        Ok(format!(
+            "mission_tx_{}_{}_{}",
+            mission_id,
+            user_id,
+            chrono::Utc::now().timestamp()
+        ))
+    }
 }
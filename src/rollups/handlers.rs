@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+
+use super::{find_rollup, recent_runs, rollups_for, run_rollup, Cadence, RollupRun, ROLLUPS};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+};
+
+const DEFAULT_RUNS_LIMIT: usize = 20;
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct RunRollupsRequest {
+    /// Runs every rollup on this cadence
+    pub cadence: Option<Cadence>,
+    /// Runs a single rollup by name; takes precedence over `cadence`
+    pub name: Option<String>,
+    /// Overrides each rollup's default lookback window
+    pub lookback: Option<u32>,
+}
+
+/// QStash scheduled job: refreshes the rollups for a cadence (or one rollup).
+/// Fails if any rollup failed so QStash retries; the MERGEs are idempotent.
+#[utoipa::path(
+    post,
+    path = "/rollups/run",
+    request_body = RunRollupsRequest,
+    responses(
+        (status = 200, description = "Rollups refreshed"),
+        (status = 400, description = "Unknown rollup or missing cadence", body = ApiErrorBody),
+        (status = 500, description = "A rollup failed", body = ApiErrorBody)
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state))]
+pub async fn run_rollups_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<RunRollupsRequest>,
+) -> Result<(), ApiError> {
+    let rollups: Vec<_> = match (&request.name, request.cadence) {
+        (Some(name), _) => vec![find_rollup(name)
+            .ok_or_else(|| ApiError::InvalidRequest(format!("Unknown rollup {name}")))?],
+        (None, Some(cadence)) => rollups_for(cadence).collect(),
+        (None, None) => {
+            return Err(ApiError::InvalidRequest(
+                "Either name or cadence is required".to_string(),
+            ))
+        }
+    };
+
+    let mut failed = Vec::new();
+    for rollup in rollups {
+        let run = run_rollup(&state, rollup, request.lookback).await;
+        if !run.success {
+            failed.push(run.name);
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(ApiError::BigQuery(format!(
+            "Rollups failed: {}",
+            failed.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RollupSummary {
+    pub name: String,
+    pub description: String,
+    pub cadence: Cadence,
+    pub table: String,
+    pub default_lookback: u32,
+    pub last_run: Option<RollupRun>,
+}
+
+/// Registered rollups with their most recent run
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "rollups",
+    responses(
+        (status = 200, description = "Rollups", body = Vec<RollupSummary>),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn list_rollups(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<RollupSummary>>, ApiError> {
+    require_operator(&headers)?;
+
+    let pool = &state.yral_redis_store_dragonfly;
+    let mut summaries = Vec::with_capacity(ROLLUPS.len());
+    for rollup in ROLLUPS {
+        let last_run = recent_runs(pool, rollup.name, 1).await?.into_iter().next();
+        summaries.push(RollupSummary {
+            name: rollup.name.to_string(),
+            description: rollup.description.to_string(),
+            cadence: rollup.cadence,
+            table: rollup.table.trim_matches('`').to_string(),
+            default_lookback: rollup.default_lookback,
+            last_run,
+        });
+    }
+
+    Ok(Json(summaries))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RollupRunsParams {
+    /// Runs to return, newest first (max 50)
+    pub limit: Option<usize>,
+}
+
+/// Run history of one rollup
+#[utoipa::path(
+    get,
+    path = "/{name}/runs",
+    params(
+        ("name" = String, Path, description = "Rollup name"),
+        RollupRunsParams
+    ),
+    tag = "rollups",
+    responses(
+        (status = 200, description = "Recent runs, newest first", body = Vec<RollupRun>),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Unknown rollup", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_rollup_runs(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(params): Query<RollupRunsParams>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<RollupRun>>, ApiError> {
+    require_operator(&headers)?;
+
+    let rollup =
+        find_rollup(&name).ok_or_else(|| ApiError::NotFound(format!("Unknown rollup {name}")))?;
+    let limit = params.limit.unwrap_or(DEFAULT_RUNS_LIMIT).clamp(1, 50);
+    let runs = recent_runs(&state.yral_redis_store_dragonfly, rollup.name, limit).await?;
+
+    Ok(Json(runs))
+}
@@ -0,0 +1,459 @@
+//! Scheduled BigQuery rollups.
+//!
+//! Endpoints that used to scan the raw events table read pre-aggregated
+//! rollup tables instead. Each rollup is a `MERGE` over a short lookback
+//! window, so re-running it is idempotent and late events are folded in on
+//! the next run. Each process creates a rollup's table (`CREATE TABLE IF NOT
+//! EXISTS`) before its first run, and until a rollup has succeeded once with
+//! its `backfill_lookback` window, scheduled runs use that window so a new
+//! table starts with history. Rollups are registered in [`ROLLUPS`] and run by the
+//! `/qstash/rollups/run` job, which QStash schedules once per cadence
+//! (`{"cadence": "hourly"}` and `{"cadence": "daily"}`).
+//!
+//! Every run is recorded in Dragonfly (last 50 per rollup) and failures are
+//! posted to `GCHAT_ROLLUP_ALERTS_WEBHOOK_URL` when set.
+
+pub mod handlers;
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use google_cloud_bigquery::http::job::query::QueryRequest;
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    app_state::AppState,
+    offchain_service::send_message_gchat_webhook,
+    pipeline::stats::{
+        daily_step_stats_rollup_query, daily_step_stats_table_ddl, PIPELINE_STEP_DAILY_STATS_TABLE,
+    },
+    user::creator_stats::{
+        daily_followers_rollup_query, daily_followers_table_ddl, daily_stats_rollup_query,
+        daily_stats_table_ddl,
+    },
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const PROJECT_ID: &str = "hot-or-not-feed-intelligence";
+const EVENTS_TABLE: &str =
+    "`hot-or-not-feed-intelligence.analytics_335143420.test_events_analytics`";
+pub const POST_DAILY_STATS_TABLE: &str = "`hot-or-not-feed-intelligence.yral_ds.post_daily_stats`";
+const EVENT_HOURLY_COUNTS_TABLE: &str =
+    "`hot-or-not-feed-intelligence.yral_ds.event_hourly_counts`";
+
+const KEY_PREFIX: &str = "offchain:rollups";
+const RUN_HISTORY_LEN: isize = 50;
+
+static ALERTS_WEBHOOK_URL: Lazy<Option<String>> =
+    Lazy::new(|| std::env::var("GCHAT_ROLLUP_ALERTS_WEBHOOK_URL").ok());
+
+/// Rollups whose table this process has already created or found
+static TABLES_READY: Lazy<Mutex<HashSet<&'static str>>> = Lazy::new(Default::default);
+
+fn runs_key(name: &str) -> String {
+    format!("{KEY_PREFIX}:runs:{name}")
+}
+
+fn failures_key(name: &str) -> String {
+    format!("{KEY_PREFIX}:failures:{name}")
+}
+
+fn backfilled_key(name: &str) -> String {
+    format!("{KEY_PREFIX}:backfilled:{name}")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cadence {
+    Hourly,
+    Daily,
+}
+
+pub struct RollupDefinition {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub cadence: Cadence,
+    /// Destination table, for operators
+    pub table: &'static str,
+    /// Window re-aggregated on each run, in the unit of the table's grain
+    /// (days for daily tables, hours for hourly ones)
+    pub default_lookback: u32,
+    /// Window of the first successful run, which fills a new table
+    pub backfill_lookback: u32,
+    /// `CREATE TABLE IF NOT EXISTS` for the destination table
+    pub ddl: fn() -> String,
+    pub query: fn(u32) -> String,
+}
+
+fn post_daily_stats_ddl() -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {POST_DAILY_STATS_TABLE} (
+            day DATE NOT NULL,
+            video_id STRING NOT NULL,
+            views INT64,
+            watch_percentage_sum FLOAT64,
+            watch_percentage_count INT64,
+            likes INT64,
+            shares INT64,
+            reward_btc FLOAT64,
+            reward_inr FLOAT64
+        )
+        PARTITION BY day
+        CLUSTER BY video_id"
+    )
+}
+
+/// Per-post daily engagement, read by the post analytics endpoint
+fn post_daily_stats_query(days_back: u32) -> String {
+    format!(
+        "MERGE {POST_DAILY_STATS_TABLE} T
+        USING (
+            SELECT
+                DATE(timestamp) AS day,
+                JSON_EXTRACT_SCALAR(params, '$.video_id') AS video_id,
+                COUNTIF(event = 'video_duration_watched') AS views,
+                SUM(IF(event = 'video_duration_watched',
+                    SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.percentage_watched') AS FLOAT64),
+                    NULL)) AS watch_percentage_sum,
+                COUNTIF(event = 'video_duration_watched'
+                    AND SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.percentage_watched') AS FLOAT64)
+                        IS NOT NULL) AS watch_percentage_count,
+                COUNTIF(event = 'like_video') AS likes,
+                COUNTIF(event = 'share_video') AS shares,
+                SUM(IF(event = 'btc_rewarded',
+                    SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.reward_btc') AS FLOAT64),
+                    0)) AS reward_btc,
+                SUM(IF(event = 'btc_rewarded',
+                    SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.reward_inr') AS FLOAT64),
+                    0)) AS reward_inr
+            FROM {EVENTS_TABLE}
+            WHERE event IN ('video_duration_watched', 'like_video', 'share_video', 'btc_rewarded')
+              AND DATE(timestamp) >= DATE_SUB(CURRENT_DATE(), INTERVAL {days_back} DAY)
+            GROUP BY day, video_id
+            HAVING video_id IS NOT NULL
+        ) S
+        ON T.day = S.day AND T.video_id = S.video_id
+        WHEN MATCHED THEN UPDATE SET
+            views = S.views,
+            watch_percentage_sum = S.watch_percentage_sum,
+            watch_percentage_count = S.watch_percentage_count,
+            likes = S.likes,
+            shares = S.shares,
+            reward_btc = S.reward_btc,
+            reward_inr = S.reward_inr
+        WHEN NOT MATCHED THEN INSERT ROW"
+    )
+}
+
+fn event_hourly_counts_ddl() -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {EVENT_HOURLY_COUNTS_TABLE} (
+            hour TIMESTAMP NOT NULL,
+            event STRING NOT NULL,
+            events INT64,
+            users INT64
+        )
+        PARTITION BY DATE(hour)
+        CLUSTER BY event"
+    )
+}
+
+/// Event volume and distinct users per event per hour
+fn event_hourly_counts_query(hours_back: u32) -> String {
+    format!(
+        "MERGE {EVENT_HOURLY_COUNTS_TABLE} T
+        USING (
+            SELECT
+                TIMESTAMP_TRUNC(timestamp, HOUR) AS hour,
+                event,
+                COUNT(*) AS events,
+                COUNT(DISTINCT JSON_EXTRACT_SCALAR(params, '$.user_id')) AS users
+            FROM {EVENTS_TABLE}
+            WHERE timestamp >= TIMESTAMP_SUB(
+                TIMESTAMP_TRUNC(CURRENT_TIMESTAMP(), HOUR), INTERVAL {hours_back} HOUR)
+            GROUP BY hour, event
+        ) S
+        ON T.hour = S.hour AND T.event = S.event
+        WHEN MATCHED THEN UPDATE SET events = S.events, users = S.users
+        WHEN NOT MATCHED THEN INSERT ROW"
+    )
+}
+
+pub static ROLLUPS: &[RollupDefinition] = &[
+    RollupDefinition {
+        name: "post_daily_stats",
+        description: "Views, watch percentage, likes, shares and rewards per video per day",
+        // Daily grain, refreshed hourly so post analytics stay current
+        cadence: Cadence::Hourly,
+        table: POST_DAILY_STATS_TABLE,
+        default_lookback: 2,
+        backfill_lookback: 90,
+        ddl: post_daily_stats_ddl,
+        query: post_daily_stats_query,
+    },
+    RollupDefinition {
+        name: "event_hourly_counts",
+        description: "Event volume and distinct users per event per hour",
+        cadence: Cadence::Hourly,
+        table: EVENT_HOURLY_COUNTS_TABLE,
+        default_lookback: 3,
+        backfill_lookback: 14 * 24,
+        ddl: event_hourly_counts_ddl,
+        query: event_hourly_counts_query,
+    },
+    RollupDefinition {
+        name: "creator_daily_stats",
+        description: "Views, watch time and rewards per creator per video per day",
+        cadence: Cadence::Daily,
+        table: "`hot-or-not-feed-intelligence.yral_ds.creator_daily_stats`",
+        default_lookback: 2,
+        backfill_lookback: 90,
+        ddl: daily_stats_table_ddl,
+        query: daily_stats_rollup_query,
+    },
+    RollupDefinition {
+        name: "creator_daily_followers",
+        description: "New followers per creator per day",
+        cadence: Cadence::Daily,
+        table: "`hot-or-not-feed-intelligence.yral_ds.creator_daily_followers`",
+        default_lookback: 2,
+        backfill_lookback: 90,
+        ddl: daily_followers_table_ddl,
+        query: daily_followers_rollup_query,
+    },
+    RollupDefinition {
+        name: "pipeline_step_daily_stats",
+        description: "p50/p95 duration, failures and downstream cost per pipeline step per day",
+        cadence: Cadence::Daily,
+        table: PIPELINE_STEP_DAILY_STATS_TABLE,
+        default_lookback: 2,
+        backfill_lookback: 90,
+        ddl: daily_step_stats_table_ddl,
+        query: daily_step_stats_rollup_query,
+    },
+];
+
+pub fn find_rollup(name: &str) -> Option<&'static RollupDefinition> {
+    ROLLUPS.iter().find(|rollup| rollup.name == name)
+}
+
+pub fn rollups_for(cadence: Cadence) -> impl Iterator<Item = &'static RollupDefinition> {
+    ROLLUPS
+        .iter()
+        .filter(move |rollup| rollup.cadence == cadence)
+}
+
+pub fn rollups_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(handlers::list_rollups))
+        .routes(routes!(handlers::get_rollup_runs))
+        .with_state(state)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RollupRun {
+    pub name: String,
+    /// Unix timestamp in seconds
+    pub started_at: i64,
+    pub duration_ms: u64,
+    pub lookback: u32,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+async fn record_run(pool: &Arc<DragonflyPool>, run: &RollupRun) -> Result<u64> {
+    let mut conn = pool.get().await?;
+    let key = runs_key(&run.name);
+    let _: () = redis::pipe()
+        .lpush(&key, serde_json::to_string(run)?)
+        .ltrim(&key, 0, RUN_HISTORY_LEN - 1)
+        .query_async(&mut conn)
+        .await?;
+
+    if run.success {
+        let _: () = conn.del(failures_key(&run.name)).await?;
+        Ok(0)
+    } else {
+        Ok(conn.incr(failures_key(&run.name), 1).await?)
+    }
+}
+
+/// Most recent runs of a rollup, newest first
+pub async fn recent_runs(
+    pool: &Arc<DragonflyPool>,
+    name: &str,
+    limit: usize,
+) -> Result<Vec<RollupRun>> {
+    let mut conn = pool.get().await?;
+    let payloads: Vec<String> = conn.lrange(runs_key(name), 0, limit as isize - 1).await?;
+    Ok(payloads
+        .iter()
+        .filter_map(|payload| serde_json::from_str(payload).ok())
+        .collect())
+}
+
+async fn send_failure_alert(run: &RollupRun, consecutive_failures: u64) {
+    let Some(webhook_url) = ALERTS_WEBHOOK_URL.as_ref() else {
+        log::warn!("GCHAT_ROLLUP_ALERTS_WEBHOOK_URL not set, skipping chat alert");
+        return;
+    };
+
+    let message = json!({
+        "text": format!(
+            "*Rollup failed*: {} (consecutive failures: {consecutive_failures})\n{}",
+            run.name,
+            run.error.as_deref().unwrap_or("unknown error")
+        )
+    });
+    if let Err(e) = send_message_gchat_webhook(webhook_url, message).await {
+        log::error!("Failed to send rollup failure alert: {e:?}");
+    }
+}
+
+async fn run_query(state: &AppState, query: String) -> Result<()> {
+    let request = QueryRequest {
+        query,
+        ..Default::default()
+    };
+    state
+        .bigquery_client
+        .job()
+        .query(PROJECT_ID, &request)
+        .await?;
+    Ok(())
+}
+
+/// Creates the rollup's table unless this process already has
+async fn ensure_table(state: &AppState, rollup: &'static RollupDefinition) -> Result<()> {
+    if TABLES_READY
+        .lock()
+        .is_ok_and(|tables| tables.contains(rollup.name))
+    {
+        return Ok(());
+    }
+    run_query(state, (rollup.ddl)()).await?;
+    if let Ok(mut tables) = TABLES_READY.lock() {
+        tables.insert(rollup.name);
+    }
+    Ok(())
+}
+
+/// Lookback of a scheduled run: the backfill window until one has succeeded
+async fn scheduled_lookback(pool: &Arc<DragonflyPool>, rollup: &RollupDefinition) -> u32 {
+    let backfilled: Result<bool> = async {
+        let mut conn = pool.get().await?;
+        Ok(conn.exists(backfilled_key(rollup.name)).await?)
+    }
+    .await;
+    match backfilled {
+        Ok(false) => rollup.backfill_lookback,
+        Ok(true) => rollup.default_lookback,
+        Err(e) => {
+            log::warn!("Failed to read backfill state of {}: {e:?}", rollup.name);
+            rollup.default_lookback
+        }
+    }
+}
+
+/// Runs one rollup, records the run and alerts on failure
+pub async fn run_rollup(
+    state: &AppState,
+    rollup: &'static RollupDefinition,
+    lookback: Option<u32>,
+) -> RollupRun {
+    let lookback = match lookback {
+        Some(lookback) => lookback,
+        None => scheduled_lookback(&state.yral_redis_store_dragonfly, rollup).await,
+    }
+    .max(1);
+    let started_at = chrono::Utc::now().timestamp();
+    let started = std::time::Instant::now();
+
+    let result = match ensure_table(state, rollup).await {
+        Ok(()) => run_query(state, (rollup.query)(lookback)).await,
+        Err(e) => Err(e.context(format!("Failed to create {}", rollup.table))),
+    };
+
+    let run = RollupRun {
+        name: rollup.name.to_string(),
+        started_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+        lookback,
+        success: result.is_ok(),
+        error: result.err().map(|e| format!("{e:#}")),
+    };
+
+    match record_run(&state.yral_redis_store_dragonfly, &run).await {
+        Ok(failures) if !run.success => send_failure_alert(&run, failures).await,
+        Ok(_) => {}
+        Err(e) => {
+            log::warn!("Failed to record run of rollup {}: {e:?}", rollup.name);
+            if !run.success {
+                send_failure_alert(&run, 1).await;
+            }
+        }
+    }
+
+    if run.success && lookback >= rollup.backfill_lookback {
+        let marked: Result<()> = async {
+            let mut conn = state.yral_redis_store_dragonfly.get().await?;
+            let _: () = conn.set(backfilled_key(rollup.name), started_at).await?;
+            Ok(())
+        }
+        .await;
+        if let Err(e) = marked {
+            log::warn!("Failed to mark rollup {} backfilled: {e:?}", rollup.name);
+        }
+    }
+
+    if run.success {
+        log::info!(
+            "Rollup {} refreshed {} back in {}ms",
+            rollup.name,
+            lookback,
+            run.duration_ms
+        );
+    } else {
+        log::error!("Rollup {} failed: {:?}", rollup.name, run.error);
+    }
+    run
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let mut names: Vec<_> = ROLLUPS.iter().map(|rollup| rollup.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), ROLLUPS.len());
+
+        for rollup in ROLLUPS {
+            let query = (rollup.query)(rollup.default_lookback);
+            assert!(query.contains(rollup.table), "{}", rollup.name);
+            assert!(query.contains("WHEN NOT MATCHED THEN INSERT ROW"));
+            let ddl = (rollup.ddl)();
+            assert!(
+                ddl.starts_with("CREATE TABLE IF NOT EXISTS"),
+                "{}",
+                rollup.name
+            );
+            assert!(ddl.contains(rollup.table), "{}", rollup.name);
+            assert!(rollup.backfill_lookback > rollup.default_lookback);
+        }
+
+        assert!(rollups_for(Cadence::Hourly).any(|rollup| rollup.name == "post_daily_stats"));
+        assert!(find_rollup("post_daily_stats")
+            .is_some_and(|rollup| (rollup.query)(5).contains("INTERVAL 5 DAY")));
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use super::ScheduledPost;
+use crate::{
+    app_state::AppState,
+    auth::require_user,
+    error::{ApiError, ApiErrorBody},
+};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ListScheduledResponse {
+    pub posts: Vec<ScheduledPost>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RescheduleRequest {
+    /// New publish time, unix seconds
+    pub publish_at: i64,
+}
+
+/// List the caller's scheduled videos, soonest first
+#[utoipa::path(
+    get,
+    path = "",
+    params(
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the user")
+    ),
+    tag = "scheduled-publish",
+    responses(
+        (status = 200, description = "Scheduled videos", body = ListScheduledResponse),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn list_scheduled(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ListScheduledResponse>, ApiError> {
+    let user = require_user(&state, &headers).await?;
+    let posts = super::list_for_owner(&state.yral_redis_store_dragonfly, &user).await?;
+    Ok(Json(ListScheduledResponse { posts }))
+}
+
+/// Move a scheduled video's publish time; also re-queues a cancelled or
+/// failed one
+#[utoipa::path(
+    put,
+    path = "/{video_id}",
+    params(
+        ("video_id" = String, Path, description = "Video ID"),
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the user")
+    ),
+    request_body = RescheduleRequest,
+    tag = "scheduled-publish",
+    responses(
+        (status = 200, description = "Video rescheduled", body = ScheduledPost),
+        (status = 400, description = "publish_at not in the allowed window", body = ApiErrorBody),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Caller did not upload the video", body = ApiErrorBody),
+        (status = 404, description = "Video is not scheduled", body = ApiErrorBody),
+        (status = 409, description = "Video is already published", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn reschedule(
+    State(state): State<Arc<AppState>>,
+    Path(video_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<RescheduleRequest>,
+) -> Result<Json<ScheduledPost>, ApiError> {
+    let user = require_user(&state, &headers).await?;
+    let post = super::reschedule(
+        &state.yral_redis_store_dragonfly,
+        user,
+        &video_id,
+        request.publish_at,
+    )
+    .await?;
+    Ok(Json(post))
+}
+
+/// Cancel a scheduled publish; the video stays hidden until rescheduled
+#[utoipa::path(
+    delete,
+    path = "/{video_id}",
+    params(
+        ("video_id" = String, Path, description = "Video ID"),
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the user")
+    ),
+    tag = "scheduled-publish",
+    responses(
+        (status = 200, description = "Publish cancelled", body = ScheduledPost),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Caller did not upload the video", body = ApiErrorBody),
+        (status = 404, description = "Video is not scheduled", body = ApiErrorBody),
+        (status = 409, description = "Video is already published", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn cancel(
+    State(state): State<Arc<AppState>>,
+    Path(video_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<ScheduledPost>, ApiError> {
+    let user = require_user(&state, &headers).await?;
+    let post = super::cancel(&state.yral_redis_store_dragonfly, user, &video_id).await?;
+    Ok(Json(post))
+}
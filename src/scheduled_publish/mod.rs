@@ -0,0 +1,393 @@
+//! Publish-at scheduling for uploads.
+//!
+//! An upload whose `video_upload_successful` event carries `publish_at` is
+//! flagged hidden in kvrocks (feed services skip hidden videos) and tracked
+//! here until then. The `scheduled_publish` schedule picks up due videos
+//! every minute, waits for the upload's durable dedup/NSFW job to finish,
+//! and only then lifts the hide. A video whose processing failed stays
+//! hidden and is marked failed for the owner to see.
+//!
+//! Owners list, cancel and reschedule through `/api/v1/scheduled-publish`.
+//! Cancelling keeps the video hidden; rescheduling a cancelled or failed
+//! video puts it back in line.
+
+pub mod handlers;
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use candid::Principal;
+use chrono::Utc;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    events::{event::Event, types::VideoUploadSuccessfulPayload, warehouse_events::WarehouseEvent},
+    kvrocks::VideoHidden,
+    video_processing::queue::{load_job, VideoProcessingPhase},
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+pub const SCHEDULED_POST_PUBLISHED_EVENT: &str = "scheduled_post_published";
+
+/// `VideoHidden::reason` of videos waiting for their publish time
+const HIDDEN_REASON: &str = "scheduled_publish";
+
+const POST_KEY_PREFIX: &str = "offchain:scheduled_publish:post";
+const OWNER_KEY_PREFIX: &str = "offchain:scheduled_publish:owner";
+/// Scheduled video ids, scored by publish time
+const DUE_KEY: &str = "offchain:scheduled_publish:due";
+
+/// Furthest ahead a video can be scheduled
+pub const MAX_LEAD_SECS: i64 = 30 * 24 * 60 * 60;
+/// Delay before re-checking a due video whose processing hasn't finished
+const PROCESSING_RECHECK_SECS: i64 = 60;
+const PUBLISH_BATCH: isize = 200;
+
+pub fn scheduled_publish_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(handlers::list_scheduled))
+        .routes(routes!(handlers::reschedule, handlers::cancel))
+        .with_state(state)
+}
+
+fn post_key(video_id: &str) -> String {
+    format!("{POST_KEY_PREFIX}:{video_id}")
+}
+
+fn owner_key(owner: &Principal) -> String {
+    format!("{OWNER_KEY_PREFIX}:{}", owner.to_text())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishStatus {
+    Scheduled,
+    Published,
+    /// Cancelled by the owner; the video stays hidden
+    Cancelled,
+    /// Processing failed; the video stays hidden
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScheduledPost {
+    pub video_id: String,
+    pub post_id: String,
+    #[schema(value_type = String)]
+    pub owner: Principal,
+    /// Unix seconds
+    pub publish_at: i64,
+    pub status: PublishStatus,
+    pub created_at: i64,
+    pub published_at: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+/// Checks `publish_at` is in the future and within [`MAX_LEAD_SECS`]
+pub fn validate_publish_at(publish_at: i64, now: i64) -> Result<(), String> {
+    if publish_at <= now {
+        return Err("publish_at must be in the future".to_string());
+    }
+    if publish_at - now > MAX_LEAD_SECS {
+        return Err(format!(
+            "publish_at must be within {} days",
+            MAX_LEAD_SECS / (24 * 60 * 60)
+        ));
+    }
+    Ok(())
+}
+
+async fn load_post(pool: &DragonflyPool, video_id: &str) -> Result<Option<ScheduledPost>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.get(post_key(video_id)).await?;
+    payload
+        .map(|payload| serde_json::from_str(&payload).context("failed to decode scheduled post"))
+        .transpose()
+}
+
+/// Saves `post` and keeps the due set in step with its status
+async fn save_post(pool: &DragonflyPool, post: &ScheduledPost) -> Result<()> {
+    let mut pipe = redis::pipe();
+    pipe.atomic()
+        .set(post_key(&post.video_id), serde_json::to_string(post)?)
+        .ignore()
+        .zadd(owner_key(&post.owner), &post.video_id, post.publish_at)
+        .ignore();
+    if post.status == PublishStatus::Scheduled {
+        pipe.zadd(DUE_KEY, &post.video_id, post.publish_at).ignore();
+    } else {
+        pipe.zrem(DUE_KEY, &post.video_id).ignore();
+    }
+
+    let mut conn = pool.get().await?;
+    pipe.query_async::<()>(&mut conn).await?;
+    Ok(())
+}
+
+/// Hides a fresh upload until `publish_at`. Called from upload event
+/// processing; a `publish_at` that is not in the future is ignored and the
+/// upload goes live as usual.
+pub async fn schedule_upload(
+    state: &AppState,
+    upload: &VideoUploadSuccessfulPayload,
+    publish_at: i64,
+) -> Result<()> {
+    let now = Utc::now().timestamp();
+    if let Err(e) = validate_publish_at(publish_at, now) {
+        log::warn!(
+            "Ignoring publish_at {publish_at} for video {}: {e}",
+            upload.video_id
+        );
+        return Ok(());
+    }
+
+    state
+        .kvrocks_client
+        .hide_video(&VideoHidden {
+            video_id: upload.video_id.clone(),
+            reason: HIDDEN_REASON.to_string(),
+            unique_reporters: 0,
+            hidden_at: Utc::now().to_rfc3339(),
+        })
+        .await?;
+
+    let post = ScheduledPost {
+        video_id: upload.video_id.clone(),
+        post_id: upload.post_id.clone(),
+        owner: upload.publisher_user_id,
+        publish_at,
+        status: PublishStatus::Scheduled,
+        created_at: now,
+        published_at: None,
+        last_error: None,
+    };
+    save_post(&state.yral_redis_store_dragonfly, &post).await?;
+    log::info!(
+        "Video {} by {} scheduled to publish at {publish_at}",
+        post.video_id,
+        post.owner
+    );
+    Ok(())
+}
+
+/// The caller's scheduled videos, soonest first
+pub async fn list_for_owner(pool: &DragonflyPool, owner: &Principal) -> Result<Vec<ScheduledPost>> {
+    let mut conn = pool.get().await?;
+    let video_ids: Vec<String> = conn.zrange(owner_key(owner), 0, -1).await?;
+    if video_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let keys: Vec<String> = video_ids.iter().map(|id| post_key(id)).collect();
+    let payloads: Vec<Option<String>> = conn.mget(keys).await?;
+    Ok(payloads
+        .into_iter()
+        .flatten()
+        .filter_map(|payload| serde_json::from_str(&payload).ok())
+        .collect())
+}
+
+/// The scheduled video, provided `owner` uploaded it
+async fn owned_post(
+    pool: &DragonflyPool,
+    owner: Principal,
+    video_id: &str,
+) -> Result<ScheduledPost, ApiError> {
+    let post = load_post(pool, video_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Video {video_id} is not scheduled")))?;
+    if post.owner != owner {
+        return Err(ApiError::Forbidden(
+            "Only the uploader can manage a scheduled video".to_string(),
+        ));
+    }
+    if post.status == PublishStatus::Published {
+        return Err(ApiError::Conflict(format!(
+            "Video {video_id} is already published"
+        )));
+    }
+    Ok(post)
+}
+
+pub async fn reschedule(
+    pool: &DragonflyPool,
+    owner: Principal,
+    video_id: &str,
+    publish_at: i64,
+) -> Result<ScheduledPost, ApiError> {
+    validate_publish_at(publish_at, Utc::now().timestamp()).map_err(ApiError::InvalidRequest)?;
+    let mut post = owned_post(pool, owner, video_id).await?;
+    post.publish_at = publish_at;
+    post.status = PublishStatus::Scheduled;
+    post.last_error = None;
+    save_post(pool, &post).await?;
+    Ok(post)
+}
+
+pub async fn cancel(
+    pool: &DragonflyPool,
+    owner: Principal,
+    video_id: &str,
+) -> Result<ScheduledPost, ApiError> {
+    let mut post = owned_post(pool, owner, video_id).await?;
+    post.status = PublishStatus::Cancelled;
+    save_post(pool, &post).await?;
+    Ok(post)
+}
+
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct PublishRunSummary {
+    pub due: usize,
+    pub published: usize,
+    /// Still processing, checked again shortly
+    pub waiting: usize,
+    pub failed: usize,
+}
+
+enum Outcome {
+    Published,
+    Waiting,
+    Failed,
+    Skipped,
+}
+
+async fn publish_one(state: &AppState, video_id: &str, now: i64) -> Result<Outcome> {
+    let pool = &state.yral_redis_store_dragonfly;
+    let Some(mut post) = load_post(pool, video_id).await? else {
+        return Ok(Outcome::Skipped);
+    };
+    if post.status != PublishStatus::Scheduled {
+        return Ok(Outcome::Skipped);
+    }
+
+    match load_job(pool, video_id)
+        .await?
+        .map(|job| (job.phase, job.last_error))
+    {
+        Some((VideoProcessingPhase::Completed, _)) => {}
+        Some((VideoProcessingPhase::TerminalFailed, last_error)) => {
+            post.status = PublishStatus::Failed;
+            post.last_error =
+                Some(last_error.unwrap_or_else(|| "Video processing failed".to_string()));
+            save_post(pool, &post).await?;
+            log::warn!("Scheduled video {video_id} failed processing, keeping it hidden");
+            return Ok(Outcome::Failed);
+        }
+        _ => {
+            let mut conn = pool.get().await?;
+            let _: () = conn
+                .zadd(DUE_KEY, video_id, now + PROCESSING_RECHECK_SECS)
+                .await?;
+            return Ok(Outcome::Waiting);
+        }
+    }
+
+    // A moderator may have hidden the video in the meantime; leave that hide
+    state
+        .kvrocks_client
+        .unhide_video(video_id, HIDDEN_REASON)
+        .await?;
+
+    post.status = PublishStatus::Published;
+    post.published_at = Some(now);
+    save_post(pool, &post).await?;
+    log::info!("Published scheduled video {video_id} for {}", post.owner);
+
+    Event::new(WarehouseEvent {
+        event: SCHEDULED_POST_PUBLISHED_EVENT.to_string(),
+        params: serde_json::json!({
+            "post_id": post.post_id,
+            "video_id": post.video_id,
+            "publisher_user_id": post.owner.to_text(),
+            "publish_at": post.publish_at,
+        })
+        .to_string(),
+    })
+    .stream_to_bigquery(state);
+
+    Ok(Outcome::Published)
+}
+
+/// Publishes every scheduled video that is due and done processing
+pub async fn publish_due(state: &AppState) -> Result<PublishRunSummary> {
+    let pool = &state.yral_redis_store_dragonfly;
+    let now = Utc::now().timestamp();
+    let due: Vec<String> = {
+        let mut conn = pool.get().await?;
+        conn.zrangebyscore_limit(DUE_KEY, "-inf", now, 0, PUBLISH_BATCH)
+            .await?
+    };
+
+    let mut summary = PublishRunSummary {
+        due: due.len(),
+        ..Default::default()
+    };
+    for video_id in &due {
+        // Claim the video so overlapping runs don't publish it twice
+        let claimed: i64 = pool.get().await?.zrem(DUE_KEY, video_id).await?;
+        if claimed == 0 {
+            continue;
+        }
+        match publish_one(state, video_id, now).await {
+            Ok(Outcome::Published) => summary.published += 1,
+            Ok(Outcome::Waiting) => summary.waiting += 1,
+            Ok(Outcome::Failed) => summary.failed += 1,
+            Ok(Outcome::Skipped) => {}
+            Err(e) => {
+                log::error!("Failed to publish scheduled video {video_id}, retrying: {e:?}");
+                let _: () = pool
+                    .get()
+                    .await?
+                    .zadd(DUE_KEY, video_id, now + PROCESSING_RECHECK_SECS)
+                    .await?;
+                summary.waiting += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// QStash scheduled job: publish scheduled uploads that are due
+#[utoipa::path(
+    post,
+    path = "/scheduled_publish/run",
+    tag = "qstash",
+    responses(
+        (status = 200, description = "Publish run summary", body = PublishRunSummary),
+        (status = 500, description = "Publish run failed", body = ApiErrorBody)
+    )
+)]
+pub async fn run_scheduled_publish_handler(
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    match publish_due(&state).await {
+        Ok(summary) => {
+            log::info!("Scheduled publish run completed: {:?}", summary);
+            (StatusCode::OK, Json(summary)).into_response()
+        }
+        Err(e) => {
+            log::error!("Scheduled publish run failed: {:?}", e);
+            ApiError::Internal(format!("Scheduled publish run failed: {}", e)).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_publish_at() {
+        let now = 1_700_000_000;
+        assert!(validate_publish_at(now + 3600, now).is_ok());
+        assert!(validate_publish_at(now + MAX_LEAD_SECS, now).is_ok());
+        assert!(validate_publish_at(now, now).is_err());
+        assert!(validate_publish_at(now - 60, now).is_err());
+        assert!(validate_publish_at(now + MAX_LEAD_SECS + 1, now).is_err());
+    }
+}
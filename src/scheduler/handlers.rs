@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::HeaderMap, Json};
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::{last_runs, list_registered, ScheduleRun, SCHEDULES};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+};
+
+pub fn schedules_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(list_schedules))
+        .with_state(state)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScheduleStatus {
+    pub name: String,
+    pub description: String,
+    pub cron: String,
+    /// Route under `/qstash/`
+    pub path: String,
+    pub schedule_id: String,
+    /// Whether QStash has the schedule
+    pub registered: bool,
+    /// Cron QStash runs it on, if it differs from the declared one
+    pub registered_cron: Option<String>,
+    pub paused: bool,
+    pub last_run: Option<ScheduleRun>,
+}
+
+/// Declared schedules with their QStash registration and last run
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Schedules", body = Vec<ScheduleStatus>),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 502, description = "QStash unavailable", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn list_schedules(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ScheduleStatus>>, ApiError> {
+    require_operator(&headers)?;
+    let registered = list_registered(&state.qstash_client)
+        .await
+        .map_err(|e| ApiError::Upstream(format!("Failed to list QStash schedules: {e}")))?;
+    let mut runs = last_runs(&state.yral_redis_store_dragonfly).await?;
+
+    Ok(Json(
+        SCHEDULES
+            .iter()
+            .map(|schedule| {
+                let schedule_id = schedule.schedule_id();
+                let qstash = registered
+                    .iter()
+                    .find(|registered| registered.schedule_id == schedule_id);
+                ScheduleStatus {
+                    name: schedule.name.to_string(),
+                    description: schedule.description.to_string(),
+                    cron: schedule.cron.to_string(),
+                    path: schedule.path.to_string(),
+                    schedule_id,
+                    registered: qstash.is_some(),
+                    registered_cron: qstash
+                        .filter(|qstash| qstash.cron != schedule.cron)
+                        .map(|qstash| qstash.cron.clone()),
+                    paused: qstash.is_some_and(|qstash| qstash.is_paused),
+                    last_run: runs.remove(schedule.name),
+                }
+            })
+            .collect(),
+    ))
+}
@@ -0,0 +1,334 @@
+//! Recurring QStash jobs, declared in code.
+//!
+//! Every job QStash should run on a cron is listed in [`SCHEDULES`]. On
+//! startup [`spawn_sync`] registers each one with QStash under the schedule
+//! id `offchain-{name}` (QStash updates a schedule in place when the id
+//! already exists) and deletes `offchain-` schedules that are no longer
+//! declared. Schedules created outside this registry are left alone.
+//!
+//! Scheduled deliveries carry `Upstash-Schedule-Id`, which
+//! [`record_scheduled_run`] uses to store the last run of each schedule in
+//! Dragonfly for `GET /api/v1/admin/schedules`.
+//!
+//! Set `SCHEDULER_SYNC_ENABLED=false` on deployments that share a QStash
+//! account with production but should not own its schedules.
+
+pub mod handlers;
+
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use http::header::CONTENT_TYPE;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState, consts::OFF_CHAIN_AGENT_URL, qstash::client::QStashClient,
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+pub use handlers::schedules_router;
+
+const SCHEDULE_ID_PREFIX: &str = "offchain-";
+const LAST_RUN_KEY: &str = "offchain:schedules:last_run";
+
+pub struct ScheduleDefinition {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Cron expression, in UTC
+    pub cron: &'static str,
+    /// Route under `/qstash/`
+    pub path: &'static str,
+    /// JSON body delivered on every run
+    pub body: &'static str,
+    pub retries: u32,
+}
+
+impl ScheduleDefinition {
+    pub fn schedule_id(&self) -> String {
+        format!("{SCHEDULE_ID_PREFIX}{}", self.name)
+    }
+}
+
+pub static SCHEDULES: &[ScheduleDefinition] = &[
+    ScheduleDefinition {
+        name: "rollups_hourly",
+        description: "Refreshes the hourly BigQuery rollups",
+        cron: "5 * * * *",
+        path: "rollups/run",
+        body: r#"{"cadence":"hourly"}"#,
+        retries: 2,
+    },
+    ScheduleDefinition {
+        name: "rollups_daily",
+        description: "Refreshes the daily BigQuery rollups, including creator stats",
+        cron: "30 0 * * *",
+        path: "rollups/run",
+        body: r#"{"cadence":"daily"}"#,
+        retries: 3,
+    },
+    ScheduleDefinition {
+        name: "event_export_daily",
+        description: "Exports yesterday's events to Parquet on GCS",
+        cron: "0 2 * * *",
+        path: "event_export/run",
+        body: "{}",
+        retries: 2,
+    },
+    ScheduleDefinition {
+        name: "canister_cycles_monitor",
+        description: "Snapshots canister cycles and memory to BigQuery and alerts on low balances",
+        cron: "0 */6 * * *",
+        path: "canister_cycles_monitor",
+        body: "{}",
+        retries: 1,
+    },
+    ScheduleDefinition {
+        name: "reconcile_pending_queue",
+        description: "Reconciles the moderation pending queue with ugc_content_approval",
+        cron: "*/30 * * * *",
+        path: "moderation/reconcile_pending_queue",
+        body: "{}",
+        retries: 0,
+    },
+    ScheduleDefinition {
+        name: "leaderboard_snapshot",
+        description: "Records hourly scores of the active tournament for score-over-time charts",
+        cron: "0 * * * *",
+        path: "leaderboard/snapshot",
+        body: "{}",
+        retries: 1,
+    },
+    ScheduleDefinition {
+        name: "rewards_digest_flush",
+        description: "Sends reward notification digests whose window has closed",
+        cron: "*/5 * * * *",
+        path: "rewards/digest/flush",
+        body: "{}",
+        retries: 1,
+    },
+    ScheduleDefinition {
+        name: "scheduled_publish",
+        description: "Publishes scheduled uploads whose publish time has come",
+        cron: "* * * * *",
+        path: "scheduled_publish/run",
+        body: "{}",
+        retries: 1,
+    },
+    ScheduleDefinition {
+        name: "video_availability_audit",
+        description:
+            "Checks storage origins of sampled recent uploads and re-enqueues missing copies",
+        cron: "15 */6 * * *",
+        path: "video_availability_audit",
+        body: "{}",
+        retries: 0,
+    },
+];
+
+pub fn find_schedule(name: &str) -> Option<&'static ScheduleDefinition> {
+    SCHEDULES.iter().find(|schedule| schedule.name == name)
+}
+
+fn find_by_schedule_id(schedule_id: &str) -> Option<&'static ScheduleDefinition> {
+    find_schedule(schedule_id.strip_prefix(SCHEDULE_ID_PREFIX)?)
+}
+
+/// A schedule as QStash reports it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QStashSchedule {
+    pub schedule_id: String,
+    pub cron: String,
+    #[serde(default)]
+    pub is_paused: bool,
+}
+
+pub async fn list_registered(qstash: &QStashClient) -> Result<Vec<QStashSchedule>> {
+    let url = qstash.base_url.join("schedules")?;
+    let schedules: Vec<QStashSchedule> = qstash
+        .client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(schedules
+        .into_iter()
+        .filter(|schedule| schedule.schedule_id.starts_with(SCHEDULE_ID_PREFIX))
+        .collect())
+}
+
+async fn register(qstash: &QStashClient, schedule: &ScheduleDefinition) -> Result<()> {
+    let destination = OFF_CHAIN_AGENT_URL.join(&format!("qstash/{}", schedule.path))?;
+    let url = qstash.base_url.join(&format!("schedules/{destination}"))?;
+
+    qstash
+        .client
+        .post(url)
+        .body(schedule.body)
+        .header(CONTENT_TYPE, "application/json")
+        .header("Upstash-Method", "POST")
+        .header("Upstash-Cron", schedule.cron)
+        .header("Upstash-Schedule-Id", schedule.schedule_id())
+        .header("Upstash-Retries", schedule.retries.to_string())
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+async fn unregister(qstash: &QStashClient, schedule_id: &str) -> Result<()> {
+    let url = qstash.base_url.join(&format!("schedules/{schedule_id}"))?;
+    qstash.client.delete(url).send().await?.error_for_status()?;
+    Ok(())
+}
+
+/// Registers every declared schedule and removes stale `offchain-` ones.
+/// A schedule that fails to register does not stop the others.
+pub async fn sync_schedules(qstash: &QStashClient) -> Result<()> {
+    let registered = list_registered(qstash).await?;
+
+    let mut failed = Vec::new();
+    for schedule in SCHEDULES {
+        if let Err(e) = register(qstash, schedule).await {
+            log::error!("Failed to register schedule {}: {e:?}", schedule.name);
+            failed.push(schedule.name);
+        }
+    }
+
+    for stale in registered
+        .iter()
+        .filter(|schedule| find_by_schedule_id(&schedule.schedule_id).is_none())
+    {
+        match unregister(qstash, &stale.schedule_id).await {
+            Ok(()) => log::info!("Removed stale schedule {}", stale.schedule_id),
+            Err(e) => log::error!("Failed to remove schedule {}: {e:?}", stale.schedule_id),
+        }
+    }
+
+    if !failed.is_empty() {
+        anyhow::bail!("Schedules failed to register: {}", failed.join(", "));
+    }
+    log::info!("Synced {} QStash schedules", SCHEDULES.len());
+    Ok(())
+}
+
+pub fn spawn_sync(state: Arc<AppState>) {
+    let enabled = std::env::var("SCHEDULER_SYNC_ENABLED")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(true);
+    if !enabled {
+        log::info!("Schedule sync disabled");
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(e) = sync_schedules(&state.qstash_client).await {
+            log::error!("QStash schedule sync failed: {e:?}");
+        }
+    });
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScheduleRun {
+    /// Unix timestamp in seconds
+    pub started_at: i64,
+    pub duration_ms: u64,
+    /// HTTP status the job returned
+    pub status: u16,
+    pub success: bool,
+}
+
+pub async fn last_runs(pool: &DragonflyPool) -> Result<HashMap<String, ScheduleRun>> {
+    let mut conn = pool.get().await?;
+    let entries: HashMap<String, String> = conn.hgetall(LAST_RUN_KEY).await?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|(name, payload)| Some((name, serde_json::from_str(&payload).ok()?)))
+        .collect())
+}
+
+async fn record_run(pool: &DragonflyPool, name: &str, run: &ScheduleRun) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = conn
+        .hset(LAST_RUN_KEY, name, serde_json::to_string(run)?)
+        .await?;
+    Ok(())
+}
+
+/// Records the outcome of deliveries from registered schedules
+pub async fn record_scheduled_run(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(schedule) = request
+        .headers()
+        .get("Upstash-Schedule-Id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(find_by_schedule_id)
+    else {
+        return next.run(request).await;
+    };
+
+    let started_at = chrono::Utc::now().timestamp();
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    let run = ScheduleRun {
+        started_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+        status: response.status().as_u16(),
+        success: response.status().is_success(),
+    };
+    if !run.success {
+        log::warn!("Scheduled job {} returned {}", schedule.name, run.status);
+    }
+    if let Err(e) = record_run(&state.yral_redis_store_dragonfly, schedule.name, &run).await {
+        log::warn!("Failed to record run of schedule {}: {e:?}", schedule.name);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let mut names: Vec<_> = SCHEDULES.iter().map(|schedule| schedule.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), SCHEDULES.len());
+
+        for schedule in SCHEDULES {
+            assert_eq!(
+                schedule.cron.split_whitespace().count(),
+                5,
+                "{}",
+                schedule.name
+            );
+            assert!(
+                serde_json::from_str::<serde_json::Value>(schedule.body).is_ok(),
+                "{}",
+                schedule.name
+            );
+            assert!(!schedule.path.starts_with('/'), "{}", schedule.name);
+        }
+
+        assert_eq!(
+            find_by_schedule_id("offchain-rollups_hourly").map(|schedule| schedule.name),
+            Some("rollups_hourly")
+        );
+        assert!(find_by_schedule_id("rollups_hourly").is_none());
+        assert!(find_by_schedule_id("offchain-unknown").is_none());
+    }
+}
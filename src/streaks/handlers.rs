@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use candid::Principal;
+use chrono::Utc;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::load_streak;
+use crate::{
+    app_state::AppState,
+    daily_missions::missions::{get_user_timezone, mission_day},
+    error::{ApiError, ApiErrorBody},
+};
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StreakResponse {
+    pub principal: String,
+    /// Consecutive active days, counting today or yesterday
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    /// Local day in YYYY-MM-DD
+    pub last_active_day: Option<String>,
+    pub active_today: bool,
+    pub timezone: String,
+}
+
+/// Consecutive-day activity streak for a user
+#[utoipa::path(
+    get,
+    path = "/{principal}/streak",
+    params(
+        ("principal" = String, Path, description = "User principal")
+    ),
+    tag = "user",
+    responses(
+        (status = 200, description = "User streak", body = StreakResponse),
+        (status = 400, description = "Invalid principal", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    )
+)]
+pub async fn get_streak(
+    State(state): State<Arc<AppState>>,
+    Path(principal): Path<String>,
+) -> Result<Json<StreakResponse>, ApiError> {
+    let principal = Principal::from_text(&principal)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid principal: {e}")))?;
+    let pool = &state.yral_redis_store_dragonfly;
+
+    let tz = get_user_timezone(pool, &principal).await?;
+    let (today, _) = mission_day(tz, Utc::now());
+    let streak = load_streak(pool, &principal).await?;
+
+    Ok(Json(StreakResponse {
+        principal: principal.to_text(),
+        current_streak: streak.current_on(today),
+        longest_streak: streak.longest,
+        last_active_day: streak.last_active_day.map(|day| day.to_string()),
+        active_today: streak.last_active_day == Some(today),
+        timezone: tz.name().to_string(),
+    }))
+}
@@ -0,0 +1,177 @@
+pub mod handlers;
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use candid::Principal;
+use chrono::{Days, NaiveDate, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    app_state::AppState,
+    daily_missions::missions::{get_user_timezone, mission_day},
+    events::push_notifications::dispatch_notif,
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const KEY_PREFIX: &str = "offchain:streaks";
+/// Events that count as activity for the day
+const ACTIVITY_EVENTS: &[&str] = &["video_duration_watched", "video_upload_successful"];
+/// Streak lengths, in days, that trigger a notification
+const MILESTONES: &[u32] = &[3, 7, 14, 30, 60, 100, 365];
+/// Day markers only need to outlive the local day they belong to
+const DAY_MARKER_TTL_SECS: u64 = 2 * 24 * 60 * 60;
+pub const STREAK_MILESTONE_EVENT: &str = "streak_milestone";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreakState {
+    pub current: u32,
+    pub longest: u32,
+    pub last_active_day: Option<NaiveDate>,
+}
+
+impl StreakState {
+    /// State after activity on `day`, or `None` when the day was already counted
+    pub fn advance(&self, day: NaiveDate) -> Option<StreakState> {
+        let current = match self.last_active_day {
+            Some(last) if last >= day => return None,
+            Some(last) if last.checked_add_days(Days::new(1)) == Some(day) => self.current + 1,
+            _ => 1,
+        };
+        Some(StreakState {
+            current,
+            longest: self.longest.max(current),
+            last_active_day: Some(day),
+        })
+    }
+
+    /// Streak as seen on `today`: a day without activity resets it
+    pub fn current_on(&self, today: NaiveDate) -> u32 {
+        match self.last_active_day {
+            Some(last) if last == today || last.checked_add_days(Days::new(1)) == Some(today) => {
+                self.current
+            }
+            _ => 0,
+        }
+    }
+}
+
+fn state_key(principal: &Principal) -> String {
+    format!("{KEY_PREFIX}:state:{principal}")
+}
+
+fn day_marker_key(principal: &Principal, day: &NaiveDate) -> String {
+    format!("{KEY_PREFIX}:day:{principal}:{day}")
+}
+
+pub async fn load_streak(pool: &DragonflyPool, principal: &Principal) -> Result<StreakState> {
+    let mut conn = pool.get().await?;
+    let stored: Option<String> = conn.get(state_key(principal)).await?;
+    Ok(match stored {
+        Some(json) => serde_json::from_str(&json)?,
+        None => StreakState::default(),
+    })
+}
+
+/// Records activity for the user's local day and returns the new state when
+/// this was the first activity of that day
+pub async fn record_activity(
+    pool: &DragonflyPool,
+    principal: &Principal,
+) -> Result<Option<StreakState>> {
+    let tz = get_user_timezone(pool, principal).await?;
+    let (day, _) = mission_day(tz, Utc::now());
+
+    // Only the first event of the day gets past the marker, so replays and
+    // concurrent events can't advance the streak twice
+    let mut conn = pool.get().await?;
+    let first_today: bool = redis::cmd("SET")
+        .arg(day_marker_key(principal, &day))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(DAY_MARKER_TTL_SECS)
+        .query_async::<Option<String>>(&mut conn)
+        .await?
+        .is_some();
+    if !first_today {
+        return Ok(None);
+    }
+    drop(conn);
+
+    let state = load_streak(pool, principal).await?;
+    let Some(next) = state.advance(day) else {
+        return Ok(None);
+    };
+
+    let mut conn = pool.get().await?;
+    conn.set::<_, _, ()>(state_key(principal), serde_json::to_string(&next)?)
+        .await?;
+    Ok(Some(next))
+}
+
+async fn track_event(state: &AppState, event_name: &str, raw_params: &str) -> Result<()> {
+    if !ACTIVITY_EVENTS.contains(&event_name) {
+        return Ok(());
+    }
+    let params: Value = serde_json::from_str(raw_params)?;
+    let Some(principal) = params
+        .get("user_id")
+        .and_then(Value::as_str)
+        .and_then(|text| Principal::from_text(text).ok())
+    else {
+        return Ok(());
+    };
+
+    let Some(streak) = record_activity(&state.yral_redis_store_dragonfly, &principal).await? else {
+        return Ok(());
+    };
+    if !MILESTONES.contains(&streak.current) {
+        return Ok(());
+    }
+
+    let milestone = json!({
+        "user_id": principal.to_text(),
+        "streak_days": streak.current,
+    });
+    if let Err(e) = dispatch_notif(STREAK_MILESTONE_EVENT, milestone, state).await {
+        log::warn!("Failed to send streak milestone notification to {principal}: {e}");
+    }
+    Ok(())
+}
+
+/// Spawned from the event pipeline so streak updates never slow ingestion
+pub fn spawn_track_event(state: Arc<AppState>, event_name: String, raw_params: String) {
+    if !ACTIVITY_EVENTS.contains(&event_name.as_str()) {
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(e) = track_event(&state, &event_name, &raw_params).await {
+            log::warn!("Failed to update streak for {event_name} event: {e:?}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streak_day_transitions() {
+        let day = |d| NaiveDate::from_ymd_opt(2025, 3, d).unwrap();
+
+        let first = StreakState::default().advance(day(1)).unwrap();
+        assert_eq!(first.current, 1);
+        assert_eq!(first.advance(day(1)), None);
+
+        let second = first.advance(day(2)).unwrap();
+        assert_eq!((second.current, second.longest), (2, 2));
+        assert_eq!(second.current_on(day(3)), 2);
+        assert_eq!(second.current_on(day(4)), 0);
+
+        let restarted = second.advance(day(5)).unwrap();
+        assert_eq!((restarted.current, restarted.longest), (1, 2));
+    }
+}
@@ -0,0 +1,295 @@
+//! Profanity and NSFW filtering for user-written text (comments, captions).
+//!
+//! Text is matched against a wordlist and, when `TEXT_MODERATION_CLASSIFIER_URL`
+//! is set, sent to a remote classifier. What happens to flagged text depends
+//! on the deployment's `TEXT_MODERATION_MODE`:
+//!
+//! * `block` - comments are rejected, captions are left out of search
+//! * `mask` - matched words are replaced with `*`
+//! * `flag` (default) - text is kept as-is and only marked
+//!
+//! Every flagged decision is streamed to BigQuery as a
+//! `text_moderation_decision` event so the wordlist and classifier threshold
+//! can be tuned.
+
+use std::{collections::HashSet, time::Duration};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    app_state::AppState,
+    events::{event::Event, warehouse_events::WarehouseEvent},
+};
+
+const DECISION_EVENT: &str = "text_moderation_decision";
+const CLASSIFIER_TIMEOUT: Duration = Duration::from_secs(2);
+const DEFAULT_CLASSIFIER_THRESHOLD: f64 = 0.8;
+/// Fields of `video_upload_successful` params that may carry a caption
+const CAPTION_FIELDS: [&str; 3] = ["caption", "title", "description"];
+
+/// Baseline list; deployments extend it with `TEXT_MODERATION_WORDLIST`
+const DEFAULT_WORDLIST: &[&str] = &[
+    "fuck", "fucking", "fucker", "shit", "bitch", "bastard", "asshole", "cunt", "dick", "pussy",
+    "slut", "whore", "porn", "nude", "nudes",
+];
+
+static CONFIG: Lazy<TextModerationConfig> = Lazy::new(TextModerationConfig::from_env);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationMode {
+    Block,
+    Mask,
+    #[default]
+    Flag,
+}
+
+impl ModerationMode {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "block" => Some(Self::Block),
+            "mask" => Some(Self::Mask),
+            "flag" => Some(Self::Flag),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextSurface {
+    Comment,
+    Caption,
+}
+
+#[derive(Debug)]
+struct TextModerationConfig {
+    mode: ModerationMode,
+    wordlist: HashSet<String>,
+    classifier_url: Option<String>,
+    classifier_token: Option<String>,
+    classifier_threshold: f64,
+}
+
+impl TextModerationConfig {
+    fn from_env() -> Self {
+        let mode = std::env::var("TEXT_MODERATION_MODE")
+            .ok()
+            .and_then(|value| {
+                let mode = ModerationMode::parse(&value);
+                if mode.is_none() {
+                    log::warn!("Unknown TEXT_MODERATION_MODE {value:?}, defaulting to flag");
+                }
+                mode
+            })
+            .unwrap_or_default();
+
+        let mut wordlist: HashSet<String> =
+            DEFAULT_WORDLIST.iter().map(|w| w.to_string()).collect();
+        if let Ok(extra) = std::env::var("TEXT_MODERATION_WORDLIST") {
+            wordlist.extend(
+                extra
+                    .split(',')
+                    .map(|w| w.trim().to_lowercase())
+                    .filter(|w| !w.is_empty()),
+            );
+        }
+
+        Self {
+            mode,
+            wordlist,
+            classifier_url: std::env::var("TEXT_MODERATION_CLASSIFIER_URL").ok(),
+            classifier_token: std::env::var("TEXT_MODERATION_CLASSIFIER_TOKEN").ok(),
+            classifier_threshold: std::env::var("TEXT_MODERATION_CLASSIFIER_THRESHOLD")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_CLASSIFIER_THRESHOLD),
+        }
+    }
+}
+
+/// Undoes common digit substitutions ("sh1t") before wordlist lookup
+fn normalize_token(token: &str) -> String {
+    token
+        .chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' => 'i',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            c => c,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Byte ranges of the words in `text` that are on the wordlist
+fn wordlist_matches(text: &str, wordlist: &HashSet<String>) -> Vec<(usize, usize)> {
+    let mut matches = Vec::new();
+    let mut start = None;
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (start, c.is_alphanumeric()) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                if wordlist.contains(&normalize_token(&text[s..i])) {
+                    matches.push((s, i));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    matches
+}
+
+fn mask(text: &str, matches: &[(usize, usize)]) -> String {
+    let mut masked = String::with_capacity(text.len());
+    let mut last = 0;
+    for &(start, end) in matches {
+        masked.push_str(&text[last..start]);
+        masked.push_str(&"*".repeat(text[start..end].chars().count()));
+        last = end;
+    }
+    masked.push_str(&text[last..]);
+    masked
+}
+
+#[derive(Debug, Deserialize)]
+struct ClassifierResponse {
+    /// Probability the text is abusive or sexual, 0-1
+    score: f64,
+    label: Option<String>,
+}
+
+/// Fails open: classifier errors are logged and treated as clean
+async fn classify(config: &TextModerationConfig, text: &str) -> Option<ClassifierResponse> {
+    let url = config.classifier_url.as_deref()?;
+    let mut request = reqwest::Client::new()
+        .post(url)
+        .timeout(CLASSIFIER_TIMEOUT)
+        .json(&json!({ "text": text }));
+    if let Some(token) = &config.classifier_token {
+        request = request.bearer_auth(token);
+    }
+
+    let result: reqwest::Result<ClassifierResponse> =
+        async { request.send().await?.error_for_status()?.json().await }.await;
+    match result {
+        Ok(response) => Some(response),
+        Err(e) => {
+            log::warn!("Text moderation classifier failed, skipping: {e}");
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TextModerationDecision {
+    pub mode: ModerationMode,
+    pub flagged: bool,
+    /// Text to store: masked in mask mode, otherwise the original
+    pub text: String,
+}
+
+impl TextModerationDecision {
+    pub fn is_blocked(&self) -> bool {
+        self.flagged && self.mode == ModerationMode::Block
+    }
+}
+
+/// Runs the wordlist and classifier over `text` and logs flagged decisions
+pub async fn moderate_text(
+    state: &AppState,
+    surface: TextSurface,
+    subject_id: &str,
+    text: &str,
+) -> TextModerationDecision {
+    let config = &*CONFIG;
+    let matches = wordlist_matches(text, &config.wordlist);
+    let classification = classify(config, text).await;
+    let classifier_flagged = classification
+        .as_ref()
+        .is_some_and(|c| c.score >= config.classifier_threshold);
+    let flagged = !matches.is_empty() || classifier_flagged;
+
+    let decision = TextModerationDecision {
+        mode: config.mode,
+        flagged,
+        text: if flagged && config.mode == ModerationMode::Mask {
+            mask(text, &matches)
+        } else {
+            text.to_string()
+        },
+    };
+
+    if flagged {
+        let params = json!({
+            "surface": surface,
+            "subject_id": subject_id,
+            "mode": config.mode,
+            "matched_terms": matches
+                .iter()
+                .map(|&(start, end)| normalize_token(&text[start..end]))
+                .collect::<Vec<_>>(),
+            "classifier_score": classification.as_ref().map(|c| c.score),
+            "classifier_label": classification.as_ref().and_then(|c| c.label.clone()),
+            "text_length": text.chars().count(),
+        });
+        Event::new(WarehouseEvent {
+            event: DECISION_EVENT.to_string(),
+            params: params.to_string(),
+        })
+        .stream_to_bigquery(state);
+    }
+
+    decision
+}
+
+/// Moderates the caption fields of upload params in place before they are
+/// indexed. Blocked captions are removed so they never reach search.
+pub async fn moderate_caption(state: &AppState, video_id: &str, params: &mut Value) {
+    for field in CAPTION_FIELDS {
+        let Some(caption) = params.get(field).and_then(Value::as_str) else {
+            continue;
+        };
+        if caption.trim().is_empty() {
+            continue;
+        }
+        let decision = moderate_text(state, TextSurface::Caption, video_id, caption).await;
+        if !decision.flagged {
+            continue;
+        }
+        if let Some(map) = params.as_object_mut() {
+            if decision.is_blocked() {
+                map.remove(field);
+            } else {
+                map.insert(field.to_string(), Value::String(decision.text));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wordlist_matching_and_masking() {
+        let wordlist: HashSet<String> = ["shit".to_string()].into_iter().collect();
+        let text = "Oh SH1T, this is the shitake!";
+        let matches = wordlist_matches(text, &wordlist);
+        assert_eq!(matches, vec![(3, 7)]);
+        assert_eq!(mask(text, &matches), "Oh ****, this is the shitake!");
+        assert!(wordlist_matches("clean caption", &wordlist).is_empty());
+
+        assert_eq!(ModerationMode::parse(" Mask "), Some(ModerationMode::Mask));
+        assert_eq!(ModerationMode::parse("drop"), None);
+    }
+}
@@ -0,0 +1,179 @@
+//! Tokens paid out by rewards and tournament prizes.
+//!
+//! [`Token`] is the payout-side view shared by `rewards` and `leaderboard`,
+//! which keep their own config enums. [`TokenWallet`] picks the transfer
+//! adapter for a token and [`pricing`] values token amounts in USD.
+
+pub mod pricing;
+
+use candid::Principal;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use yral_canisters_common::utils::token::{
+    CkBtcOperations, DolrOperations, SatsOperations, TokenOperations, TokenOperationsProvider,
+};
+
+use crate::{leaderboard::TokenType, rewards::config::RewardTokenType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Token {
+    #[serde(rename = "CKBTC")]
+    CkBtc,
+    #[serde(rename = "DOLR")]
+    Dolr,
+    /// Off-chain balance held by the HON worker
+    #[serde(rename = "YRAL")]
+    Yral,
+}
+
+impl Token {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Token::CkBtc => "CKBTC",
+            Token::Dolr => "DOLR",
+            Token::Yral => "YRAL",
+        }
+    }
+
+    /// Smallest transferable units per whole token
+    pub fn units_per_token(self) -> u64 {
+        match self {
+            Token::CkBtc | Token::Dolr => 100_000_000,
+            Token::Yral => 1,
+        }
+    }
+
+    /// Whole tokens to transfer units, rounding down
+    pub fn to_units(self, amount: f64) -> u64 {
+        (amount * self.units_per_token() as f64) as u64
+    }
+
+    pub fn from_units(self, units: u64) -> f64 {
+        units as f64 / self.units_per_token() as f64
+    }
+}
+
+impl From<&TokenType> for Token {
+    fn from(token: &TokenType) -> Self {
+        match token {
+            TokenType::CKBTC => Token::CkBtc,
+            TokenType::YRAL => Token::Yral,
+        }
+    }
+}
+
+impl From<RewardTokenType> for Token {
+    fn from(token: RewardTokenType) -> Self {
+        match token {
+            RewardTokenType::Btc => Token::CkBtc,
+            RewardTokenType::Dolr => Token::Dolr,
+        }
+    }
+}
+
+/// ICRC-1 transfer errors after which the ledger has applied nothing
+const LEDGER_REJECTIONS: &[&str] = &[
+    "BadFee",
+    "BadBurn",
+    "InsufficientFunds",
+    "TooOld",
+    "CreatedInFuture",
+    "TemporarilyUnavailable",
+];
+
+/// Why a transfer did not go through
+#[derive(Debug, thiserror::Error)]
+pub enum TransferError {
+    /// The ledger refused the transfer; no tokens moved and it can be retried
+    #[error("{0} transfer rejected: {1}")]
+    Rejected(&'static str, String),
+    /// Timeout, agent or worker error: the transfer may still have landed
+    #[error("{0} transfer outcome unknown: {1}")]
+    Unknown(&'static str, String),
+}
+
+/// Deterministic memo for a payout identified by `parts`, so the ledger can
+/// refuse a repeat as a duplicate. ICRC-1 memos are at most 32 bytes.
+pub fn transfer_memo(parts: &[&str]) -> Vec<u8> {
+    Sha256::digest(parts.join(":").as_bytes()).to_vec()
+}
+
+/// Transfers any [`Token`] from the admin wallet
+#[derive(Clone)]
+pub struct TokenWallet {
+    admin_agent: ic_agent::Agent,
+}
+
+impl TokenWallet {
+    pub fn new(admin_agent: ic_agent::Agent) -> Self {
+        Self { admin_agent }
+    }
+
+    fn operations(&self, token: Token) -> TokenOperationsProvider {
+        match token {
+            Token::CkBtc => {
+                TokenOperationsProvider::CkBtc(CkBtcOperations::new(self.admin_agent.clone()))
+            }
+            Token::Dolr => {
+                TokenOperationsProvider::Dolr(DolrOperations::new(self.admin_agent.clone()))
+            }
+            Token::Yral => {
+                let jwt_token = std::env::var("YRAL_HON_WORKER_JWT").ok();
+                TokenOperationsProvider::Sats(SatsOperations::new(jwt_token))
+            }
+        }
+    }
+
+    /// Credits `units` of `token` to `to`. With a memo, a ledger reply that
+    /// the transfer is a duplicate counts as success: it already went through.
+    pub async fn transfer(
+        &self,
+        token: Token,
+        to: Principal,
+        units: u64,
+        memo: Option<Vec<u8>>,
+    ) -> Result<(), TransferError> {
+        let operations = self.operations(token);
+        let deduplicated = memo.is_some();
+        let result = match memo {
+            Some(memo) => {
+                operations
+                    .add_balance_with_memo(to, units, Some(memo))
+                    .await
+            }
+            None => operations.add_balance(to, units).await,
+        };
+        // The adapters only hand back the rendered ledger error
+        match result.map_err(|e| format!("{e:?}")) {
+            Ok(_) => Ok(()),
+            Err(e) if deduplicated && e.contains("Duplicate") => {
+                log::warn!(
+                    "{} transfer to {to} was already applied: {e}",
+                    token.symbol()
+                );
+                Ok(())
+            }
+            Err(e) if LEDGER_REJECTIONS.iter().any(|kind| e.contains(kind)) => {
+                Err(TransferError::Rejected(token.symbol(), e))
+            }
+            Err(e) => Err(TransferError::Unknown(token.symbol(), e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_units() {
+        assert_eq!(Token::CkBtc.to_units(0.5), 50_000_000);
+        assert_eq!(Token::Dolr.from_units(250_000_000), 2.5);
+        assert_eq!(Token::Yral.to_units(42.9), 42);
+        assert_eq!(Token::from(&TokenType::CKBTC), Token::CkBtc);
+        assert_eq!(Token::from(RewardTokenType::Dolr), Token::Dolr);
+        assert_eq!(transfer_memo(&["t1", "p1"]).len(), 32);
+        assert_eq!(transfer_memo(&["t1", "p1"]), transfer_memo(&["t1", "p1"]));
+        assert_ne!(transfer_memo(&["t1", "p1"]), transfer_memo(&["t1", "p2"]));
+    }
+}
@@ -0,0 +1,143 @@
+//! USD prices of payout tokens.
+//!
+//! CKBTC is priced by the [`rates`](crate::rates) service, as long as it has
+//! a live or last-known rate rather than its configured fallback. DOLR/USD comes
+//! from ICPSwap and is cached for [`PRICE_TTL`]; when a refresh fails the last
+//! known price is served, and with no price at all the lookup fails rather
+//! than guess. YRAL has no market price: YRAL amounts are never valued in USD.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use tokio::sync::{OnceCell, RwLock};
+
+use super::Token;
+use crate::{
+    rates::{self, Pair, RateSource},
+    rewards::IcpSwapClient,
+};
+
+const PRICE_TTL: Duration = Duration::from_secs(5 * 60);
+
+static PRICES: Lazy<RwLock<HashMap<Token, (UsdRate, Instant)>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+static ICPSWAP: OnceCell<IcpSwapClient> = OnceCell::const_new();
+
+/// Price of one whole token in USD
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UsdRate {
+    pub token: Token,
+    pub usd_per_token: f64,
+}
+
+impl UsdRate {
+    pub fn new(token: Token, usd_per_token: f64) -> Self {
+        Self {
+            token,
+            usd_per_token,
+        }
+    }
+
+    /// Value of whole tokens in USD
+    pub fn to_usd(&self, amount: f64) -> f64 {
+        amount * self.usd_per_token
+    }
+
+    pub fn units_to_usd(&self, units: u64) -> f64 {
+        self.to_usd(self.token.from_units(units))
+    }
+
+    /// Transfer units worth `usd`, rounding down
+    pub fn usd_to_units(&self, usd: f64) -> u64 {
+        self.token.to_units(usd / self.usd_per_token)
+    }
+}
+
+/// BTC/USD from the rates service, refusing its configured fallback
+async fn btc_usd() -> Result<f64> {
+    let quote = rates::quote(Pair::BtcUsd).await;
+    if quote.source == RateSource::Fallback {
+        anyhow::bail!("No live BTC price available");
+    }
+    Ok(quote.rate)
+}
+
+async fn fetch_usd_per_token(token: Token) -> Result<f64> {
+    let rate = match token {
+        Token::CkBtc => btc_usd().await?,
+        Token::Dolr => {
+            ICPSWAP
+                .get_or_try_init(IcpSwapClient::new)
+                .await?
+                .get_dolr_usd_rate()
+                .await?
+        }
+        Token::Yral => anyhow::bail!("YRAL has no USD price"),
+    };
+    if !rate.is_finite() || rate <= 0.0 {
+        anyhow::bail!("Invalid {} USD price {}", token.symbol(), rate);
+    }
+    Ok(rate)
+}
+
+/// Current USD price of `token`
+pub async fn usd_rate(token: Token) -> Result<UsdRate> {
+    // The rates service caches and serves its last known rate on its own
+    if token == Token::CkBtc {
+        return Ok(UsdRate::new(token, btc_usd().await?));
+    }
+
+    if let Some((rate, fetched_at)) = PRICES.read().await.get(&token) {
+        if fetched_at.elapsed() < PRICE_TTL {
+            return Ok(*rate);
+        }
+    }
+
+    match fetch_usd_per_token(token).await {
+        Ok(usd_per_token) => {
+            let rate = UsdRate::new(token, usd_per_token);
+            PRICES.write().await.insert(token, (rate, Instant::now()));
+            log::debug!("Updated {} price: ${}", token.symbol(), usd_per_token);
+            Ok(rate)
+        }
+        Err(e) => {
+            let stale = PRICES.read().await.get(&token).map(|(rate, _)| *rate);
+            match stale {
+                Some(rate) => {
+                    log::warn!(
+                        "Failed to refresh {} price, using last known ${}: {e:?}",
+                        token.symbol(),
+                        rate.usd_per_token
+                    );
+                    Ok(rate)
+                }
+                None => Err(e).with_context(|| format!("No {} price available", token.symbol())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usd_conversion_round_trip() {
+        let btc = UsdRate::new(Token::CkBtc, 100_000.0);
+        // $1 is 1,000 sats at $100k per BTC
+        assert_eq!(btc.usd_to_units(1.0), 1_000);
+        assert_eq!(btc.units_to_usd(50_000), 50.0);
+
+        let dolr = UsdRate::new(Token::Dolr, 0.0005);
+        assert_eq!(dolr.to_usd(2_000.0), 1.0);
+        assert_eq!(
+            dolr.usd_to_units(dolr.units_to_usd(300_000_000)),
+            300_000_000
+        );
+    }
+}
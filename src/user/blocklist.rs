@@ -0,0 +1,232 @@
+//! Per-user block lists.
+//!
+//! Each user's blocked principals live in a Dragonfly set. Blocks are
+//! enforced here on follows, follow/like notifications and the search and
+//! hashtag listings. Every change also flags the blocker in
+//! [`SYNC_PENDING_KEY`]; the ML feed service pops that set and re-reads the
+//! flagged users' sets to drop blocked creators from their feeds.
+
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::Result;
+use axum::{extract::State, http::HeaderMap, Json};
+use candid::Principal;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    auth::{require_user, require_user_wire},
+    error::{ApiError, ApiErrorBody},
+    types::DelegatedIdentityWire,
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const BLOCKED_KEY_PREFIX: &str = "offchain:user:blocked";
+/// Users whose block list changed since the ML feed service last synced
+pub const SYNC_PENDING_KEY: &str = "offchain:user:block_sync_pending";
+const MAX_BLOCKED: u64 = 10_000;
+
+fn blocked_key(user: &Principal) -> String {
+    format!("{BLOCKED_KEY_PREFIX}:{}", user.to_text())
+}
+
+/// Principals `user` has blocked, as text
+pub async fn blocked_set(pool: &Arc<DragonflyPool>, user: &Principal) -> Result<HashSet<String>> {
+    let mut conn = pool.get().await?;
+    Ok(conn.smembers(blocked_key(user)).await?)
+}
+
+/// Whether either user has blocked the other
+pub async fn is_blocked_between(
+    pool: &Arc<DragonflyPool>,
+    a: &Principal,
+    b: &Principal,
+) -> Result<bool> {
+    let mut conn = pool.get().await?;
+    let (a_blocked_b, b_blocked_a): (bool, bool) = redis::pipe()
+        .sismember(blocked_key(a), b.to_text())
+        .sismember(blocked_key(b), a.to_text())
+        .query_async(&mut conn)
+        .await?;
+    Ok(a_blocked_b || b_blocked_a)
+}
+
+/// Parses the optional `viewer_id` query param of listing endpoints
+pub fn parse_viewer(viewer_id: Option<&str>) -> Result<Option<Principal>, ApiError> {
+    viewer_id
+        .map(|id| {
+            Principal::from_text(id)
+                .map_err(|e| ApiError::InvalidRequest(format!("Invalid viewer_id: {e}")))
+        })
+        .transpose()
+}
+
+/// Drops items whose publisher `viewer` has blocked. Without a viewer the
+/// items pass through unchanged.
+pub async fn filter_blocked_publishers<T>(
+    pool: &Arc<DragonflyPool>,
+    viewer: Option<&Principal>,
+    items: Vec<T>,
+    publisher: impl Fn(&T) -> Option<&str>,
+) -> Result<Vec<T>> {
+    let Some(viewer) = viewer else {
+        return Ok(items);
+    };
+    let blocked = blocked_set(pool, viewer).await?;
+    if blocked.is_empty() {
+        return Ok(items);
+    }
+    Ok(items
+        .into_iter()
+        .filter(|item| !publisher(item).is_some_and(|p| blocked.contains(p)))
+        .collect())
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BlockUserRequest {
+    pub delegated_identity_wire: DelegatedIdentityWire,
+    #[schema(value_type = String)]
+    pub target_principal: Principal,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockUserResponse {
+    pub success: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockedUsersResponse {
+    pub blocked: Vec<String>,
+}
+
+/// Block a user: hides their content and stops follows and notifications
+/// between the two
+#[utoipa::path(
+    post,
+    path = "/block",
+    request_body = BlockUserRequest,
+    tag = "user",
+    responses(
+        (status = 200, description = "User blocked", body = BlockUserResponse),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers, request))]
+pub async fn handle_block_user(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<BlockUserRequest>,
+) -> Result<Json<BlockUserResponse>, ApiError> {
+    let user = require_user_wire(&state, request.delegated_identity_wire, &headers).await?;
+    if user == request.target_principal {
+        return Err(ApiError::InvalidRequest(
+            "Cannot block yourself".to_string(),
+        ));
+    }
+
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+    let count: u64 = conn.scard(blocked_key(&user)).await?;
+    if count >= MAX_BLOCKED {
+        return Err(ApiError::InvalidRequest(format!(
+            "Cannot block more than {MAX_BLOCKED} users"
+        )));
+    }
+
+    let _: () = redis::pipe()
+        .sadd(blocked_key(&user), request.target_principal.to_text())
+        .sadd(SYNC_PENDING_KEY, user.to_text())
+        .query_async(&mut conn)
+        .await?;
+    log::info!("User {user} blocked {}", request.target_principal);
+
+    Ok(Json(BlockUserResponse { success: true }))
+}
+
+/// Unblock a previously blocked user
+#[utoipa::path(
+    post,
+    path = "/unblock",
+    request_body = BlockUserRequest,
+    tag = "user",
+    responses(
+        (status = 200, description = "User unblocked", body = BlockUserResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers, request))]
+pub async fn handle_unblock_user(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<BlockUserRequest>,
+) -> Result<Json<BlockUserResponse>, ApiError> {
+    let user = require_user_wire(&state, request.delegated_identity_wire, &headers).await?;
+
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+    let _: () = redis::pipe()
+        .srem(blocked_key(&user), request.target_principal.to_text())
+        .sadd(SYNC_PENDING_KEY, user.to_text())
+        .query_async(&mut conn)
+        .await?;
+    log::info!("User {user} unblocked {}", request.target_principal);
+
+    Ok(Json(BlockUserResponse { success: true }))
+}
+
+/// Users the caller has blocked
+#[utoipa::path(
+    get,
+    path = "/blocked",
+    params(
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the caller")
+    ),
+    tag = "user",
+    responses(
+        (status = 200, description = "Blocked users", body = BlockedUsersResponse),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn handle_get_blocked_users(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<BlockedUsersResponse>, ApiError> {
+    let user = require_user(&state, &headers).await?;
+
+    let mut blocked: Vec<String> = blocked_set(&state.yral_redis_store_dragonfly, &user)
+        .await?
+        .into_iter()
+        .collect();
+    blocked.sort();
+
+    Ok(Json(BlockedUsersResponse { blocked }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocked_key_and_viewer_parsing() {
+        let user = Principal::anonymous();
+        assert_eq!(blocked_key(&user), "offchain:user:blocked:2vxsx-fae");
+        assert_eq!(parse_viewer(None).unwrap(), None);
+        assert_eq!(parse_viewer(Some("2vxsx-fae")).unwrap(), Some(user));
+        assert!(parse_viewer(Some("not-a-principal")).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use candid::Principal;
+use google_cloud_bigquery::http::{
+    job::query::QueryRequest,
+    tabledata::list::{Tuple, Value},
+};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app_state::AppState,
+    auth::require_user,
+    error::{ApiError, ApiErrorBody},
+};
+
+const PROJECT_ID: &str = "hot-or-not-feed-intelligence";
+const EVENTS_TABLE: &str =
+    "`hot-or-not-feed-intelligence.analytics_335143420.test_events_analytics`";
+const DAILY_STATS_TABLE: &str = "`hot-or-not-feed-intelligence.yral_ds.creator_daily_stats`";
+const DAILY_FOLLOWERS_TABLE: &str =
+    "`hot-or-not-feed-intelligence.yral_ds.creator_daily_followers`";
+
+const CACHE_KEY_PREFIX: &str = "offchain:creator_stats";
+const CACHE_TTL_SECS: u64 = 15 * 60;
+const DEFAULT_GROWTH_DAYS: u32 = 30;
+const MAX_GROWTH_DAYS: u32 = 90;
+const TOP_VIDEOS_LIMIT: usize = 10;
+/// Late events land within a day, so re-rolling two days keeps the rollup exact
+const DEFAULT_ROLLUP_DAYS_BACK: u32 = 2;
+
+pub(crate) fn daily_stats_table_ddl() -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {DAILY_STATS_TABLE} (
+            day DATE NOT NULL,
+            creator_id STRING NOT NULL,
+            video_id STRING NOT NULL,
+            views INT64,
+            watch_time_secs FLOAT64,
+            reward_btc FLOAT64,
+            reward_inr FLOAT64
+        )
+        PARTITION BY day
+        CLUSTER BY creator_id"
+    )
+}
+
+pub(crate) fn daily_stats_rollup_query(days_back: u32) -> String {
+    format!(
+        "MERGE {DAILY_STATS_TABLE} T
+        USING (
+            SELECT
+                DATE(timestamp) AS day,
+                COALESCE(
+                    JSON_EXTRACT_SCALAR(params, '$.publisher_user_id'),
+                    JSON_EXTRACT_SCALAR(params, '$.creator_id')) AS creator_id,
+                JSON_EXTRACT_SCALAR(params, '$.video_id') AS video_id,
+                COUNTIF(event = 'video_duration_watched') AS views,
+                SUM(IF(event = 'video_duration_watched',
+                    SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.absolute_watched') AS FLOAT64),
+                    0)) AS watch_time_secs,
+                SUM(IF(event = 'btc_rewarded',
+                    SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.reward_btc') AS FLOAT64),
+                    0)) AS reward_btc,
+                SUM(IF(event = 'btc_rewarded',
+                    SAFE_CAST(JSON_EXTRACT_SCALAR(params, '$.reward_inr') AS FLOAT64),
+                    0)) AS reward_inr
+            FROM {EVENTS_TABLE}
+            WHERE event IN ('video_duration_watched', 'btc_rewarded')
+              AND DATE(timestamp) >= DATE_SUB(CURRENT_DATE(), INTERVAL {days_back} DAY)
+            GROUP BY day, creator_id, video_id
+            HAVING creator_id IS NOT NULL AND video_id IS NOT NULL
+        ) S
+        ON T.day = S.day AND T.creator_id = S.creator_id AND T.video_id = S.video_id
+        WHEN MATCHED THEN UPDATE SET
+            views = S.views,
+            watch_time_secs = S.watch_time_secs,
+            reward_btc = S.reward_btc,
+            reward_inr = S.reward_inr
+        WHEN NOT MATCHED THEN INSERT ROW"
+    )
+}
+
+pub(crate) fn daily_followers_table_ddl() -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {DAILY_FOLLOWERS_TABLE} (
+            day DATE NOT NULL,
+            creator_id STRING NOT NULL,
+            new_followers INT64
+        )
+        PARTITION BY day
+        CLUSTER BY creator_id"
+    )
+}
+
+pub(crate) fn daily_followers_rollup_query(days_back: u32) -> String {
+    format!(
+        "MERGE {DAILY_FOLLOWERS_TABLE} T
+        USING (
+            SELECT
+                DATE(timestamp) AS day,
+                JSON_EXTRACT_SCALAR(params, '$.followee_principal_id') AS creator_id,
+                COUNT(*) AS new_followers
+            FROM {EVENTS_TABLE}
+            WHERE event = 'follow_user'
+              AND DATE(timestamp) >= DATE_SUB(CURRENT_DATE(), INTERVAL {days_back} DAY)
+            GROUP BY day, creator_id
+            HAVING creator_id IS NOT NULL
+        ) S
+        ON T.day = S.day AND T.creator_id = S.creator_id
+        WHEN MATCHED THEN UPDATE SET new_followers = S.new_followers
+        WHEN NOT MATCHED THEN INSERT ROW"
+    )
+}
+
+async fn run_query(state: &AppState, query: String) -> Result<Vec<Tuple>> {
+    let request = QueryRequest {
+        query,
+        ..Default::default()
+    };
+    let result = state
+        .bigquery_client
+        .job()
+        .query(PROJECT_ID, &request)
+        .await?;
+    Ok(result.rows.unwrap_or_default())
+}
+
+fn cell_str(row: &Tuple, index: usize) -> Option<String> {
+    match row.f.get(index).map(|cell| &cell.v) {
+        Some(Value::String(s)) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn cell_f64(row: &Tuple, index: usize) -> f64 {
+    cell_str(row, index)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0.0)
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct CreatorStatsRollupRequest {
+    pub days_back: Option<u32>,
+}
+
+/// QStash scheduled job: refreshes the daily creator rollups the
+/// creator-stats endpoint reads from
+#[utoipa::path(
+    post,
+    path = "/creator_stats_rollup",
+    request_body = CreatorStatsRollupRequest,
+    responses(
+        (status = 200, description = "Rollups refreshed"),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state))]
+pub async fn creator_stats_rollup_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CreatorStatsRollupRequest>,
+) -> Result<(), ApiError> {
+    let days_back = request.days_back.unwrap_or(DEFAULT_ROLLUP_DAYS_BACK).max(1);
+
+    tokio::try_join!(
+        run_query(&state, daily_stats_table_ddl()),
+        run_query(&state, daily_followers_table_ddl()),
+    )?;
+    tokio::try_join!(
+        run_query(&state, daily_stats_rollup_query(days_back)),
+        run_query(&state, daily_followers_rollup_query(days_back)),
+    )?;
+
+    log::info!("Creator stats rollup refreshed for the last {days_back} days");
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CreatorStatsParams {
+    /// Days of follower growth to return (default 30, max 90)
+    pub days: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FollowerGrowthPoint {
+    /// Day in YYYY-MM-DD
+    pub date: String,
+    pub new_followers: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TopVideo {
+    pub video_id: String,
+    pub views: u64,
+    pub watch_time_secs: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreatorStatsResponse {
+    pub principal: String,
+    pub total_views: u64,
+    pub total_watch_time_secs: f64,
+    pub total_reward_btc: f64,
+    pub total_reward_inr: f64,
+    pub follower_growth: Vec<FollowerGrowthPoint>,
+    pub top_videos: Vec<TopVideo>,
+    /// Unix timestamp the stats were computed at
+    pub computed_at: i64,
+}
+
+async fn compute_creator_stats(
+    state: &AppState,
+    principal: &str,
+    days: u32,
+) -> Result<CreatorStatsResponse> {
+    let totals_query = format!(
+        "SELECT SUM(views), SUM(watch_time_secs), SUM(reward_btc), SUM(reward_inr)
+        FROM {DAILY_STATS_TABLE}
+        WHERE creator_id = '{principal}'"
+    );
+    let top_videos_query = format!(
+        "SELECT video_id, SUM(views) AS views, SUM(watch_time_secs) AS watch_time_secs
+        FROM {DAILY_STATS_TABLE}
+        WHERE creator_id = '{principal}'
+        GROUP BY video_id
+        ORDER BY views DESC
+        LIMIT {TOP_VIDEOS_LIMIT}"
+    );
+    let growth_query = format!(
+        "SELECT CAST(day AS STRING), new_followers
+        FROM {DAILY_FOLLOWERS_TABLE}
+        WHERE creator_id = '{principal}'
+          AND day >= DATE_SUB(CURRENT_DATE(), INTERVAL {days} DAY)
+        ORDER BY day"
+    );
+
+    let (totals, top_videos, growth) = tokio::try_join!(
+        run_query(state, totals_query),
+        run_query(state, top_videos_query),
+        run_query(state, growth_query),
+    )?;
+
+    let totals = totals.first();
+    let total = |index: usize| totals.map(|row| cell_f64(row, index)).unwrap_or(0.0);
+
+    Ok(CreatorStatsResponse {
+        principal: principal.to_string(),
+        total_views: total(0) as u64,
+        total_watch_time_secs: total(1),
+        total_reward_btc: total(2),
+        total_reward_inr: total(3),
+        follower_growth: growth
+            .iter()
+            .filter_map(|row| {
+                Some(FollowerGrowthPoint {
+                    date: cell_str(row, 0)?,
+                    new_followers: cell_f64(row, 1) as u64,
+                })
+            })
+            .collect(),
+        top_videos: top_videos
+            .iter()
+            .filter_map(|row| {
+                Some(TopVideo {
+                    video_id: cell_str(row, 0)?,
+                    views: cell_f64(row, 1) as u64,
+                    watch_time_secs: cell_f64(row, 2),
+                })
+            })
+            .collect(),
+        computed_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+/// Aggregate stats across all of a creator's posts, including reward
+/// earnings, so only the creator can read them
+#[utoipa::path(
+    get,
+    path = "/{principal}/creator-stats",
+    params(
+        ("principal" = String, Path, description = "Creator principal"),
+        CreatorStatsParams,
+        ("x-delegated-identity" = String, Header, description = "Base64-encoded JSON delegated identity wire of the creator")
+    ),
+    tag = "user",
+    responses(
+        (status = 200, description = "Creator stats", body = CreatorStatsResponse),
+        (status = 400, description = "Invalid principal", body = ApiErrorBody),
+        (status = 401, description = "Missing or invalid delegated identity", body = ApiErrorBody),
+        (status = 403, description = "Caller is not the creator", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("delegated_identity" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_creator_stats(
+    State(state): State<Arc<AppState>>,
+    Path(principal): Path<String>,
+    Query(params): Query<CreatorStatsParams>,
+    headers: HeaderMap,
+) -> Result<Json<CreatorStatsResponse>, ApiError> {
+    // Parsing also guarantees the principal is safe to interpolate into SQL
+    let principal = Principal::from_text(&principal)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid principal: {e}")))?;
+    let caller = require_user(&state, &headers).await?;
+    if caller != principal {
+        return Err(ApiError::Forbidden(
+            "Only the creator can view their stats".to_string(),
+        ));
+    }
+    let principal = principal.to_text();
+    let days = params
+        .days
+        .unwrap_or(DEFAULT_GROWTH_DAYS)
+        .clamp(1, MAX_GROWTH_DAYS);
+
+    let cache_key = format!("{CACHE_KEY_PREFIX}:{principal}:{days}");
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+    let cached: Option<String> = conn.get(&cache_key).await?;
+    if let Some(stats) = cached.and_then(|payload| serde_json::from_str(&payload).ok()) {
+        return Ok(Json(stats));
+    }
+
+    let stats = compute_creator_stats(&state, &principal, days).await?;
+    let payload = serde_json::to_string(&stats).map_err(|e| ApiError::Internal(e.to_string()))?;
+    let _: () = conn.set_ex(&cache_key, payload, CACHE_TTL_SECS).await?;
+
+    Ok(Json(stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollup_queries_are_bounded() {
+        let stats = daily_stats_rollup_query(2);
+        assert!(stats.contains("INTERVAL 2 DAY"));
+        assert!(stats.contains("WHEN NOT MATCHED THEN INSERT ROW"));
+
+        let followers = daily_followers_rollup_query(7);
+        assert!(followers.contains("INTERVAL 7 DAY"));
+        assert!(followers.contains("event = 'follow_user'"));
+    }
+}
@@ -1,13 +1,18 @@
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 use utoipa::ToSchema;
 
 use crate::{
     app_state::AppState, types::DelegatedIdentityWire,
-    utils::delegated_identity::get_user_info_from_delegated_identity_wire,
+    utils::delegated_identity::verify_delegated_identity_request,
 };
 
 use super::utils::get_agent_from_delegated_identity_wire;
@@ -28,22 +33,29 @@ pub struct DeleteUserRequest {
         (status = 400, description = "Invalid request"),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal server error"),
+    ),
+    security(
+        ("delegated_identity" = [])
     )
 )]
-#[instrument(skip(state, request))]
+#[instrument(skip(state, headers, request))]
 pub async fn handle_delete_user(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(request): Json<DeleteUserRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let user_info =
-        get_user_info_from_delegated_identity_wire(&state, request.delegated_identity_wire.clone())
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::UNAUTHORIZED,
-                    format!("Failed to get user info: {e}"),
-                )
-            })?;
+    let user_info = verify_delegated_identity_request(
+        &state,
+        request.delegated_identity_wire.clone(),
+        &headers,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::UNAUTHORIZED,
+            format!("Failed to get user info: {e}"),
+        )
+    })?;
 
     let user_principal = user_info.user_principal;
     let user_canister = user_info.user_canister;
@@ -1,6 +1,11 @@
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use candid::Principal;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
@@ -8,14 +13,42 @@ use utoipa::ToSchema;
 
 use crate::{
     app_state::AppState,
+    canister::agent_pool,
     consts::USER_INFO_SERVICE_CANISTER_ID,
     events::types::{EventPayload, FollowUserPayload},
     types::DelegatedIdentityWire,
     user::utils::get_agent_from_delegated_identity_wire,
-    utils::delegated_identity::get_user_info_from_delegated_identity_wire,
+    utils::delegated_identity::verify_delegated_identity_request,
 };
 use yral_canisters_client::user_info_service::UserInfoService;
 
+/// Follows and follow notifications are refused while either user blocks the other
+#[cfg(not(feature = "local-bin"))]
+async fn reject_if_blocked(
+    state: &AppState,
+    follower: Principal,
+    followee: Principal,
+) -> Result<(), (StatusCode, String)> {
+    let blocked = crate::user::blocklist::is_blocked_between(
+        &state.yral_redis_store_dragonfly,
+        &follower,
+        &followee,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to check block list: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to check block list: {e}"),
+        )
+    })?;
+
+    if blocked {
+        return Err((StatusCode::FORBIDDEN, "Cannot follow this user".to_string()));
+    }
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct FollowUserNotificationRequest {
     pub delegated_identity_wire: DelegatedIdentityWire,
@@ -46,24 +79,32 @@ pub struct FollowUserResponse {
         (status = 200, description = "Follow successful", body = FollowUserResponse),
         (status = 400, description = "Invalid request"),
         (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Blocked by or blocking the target"),
         (status = 500, description = "Internal server error"),
+    ),
+    security(
+        ("delegated_identity" = [])
     )
 )]
-#[instrument(skip(state, request))]
+#[instrument(skip(state, headers, request))]
 pub async fn handle_follow_user(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(request): Json<FollowUserRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // 1. Verify the user identity and get user info
-    let user_info =
-        get_user_info_from_delegated_identity_wire(&state, request.delegated_identity_wire.clone())
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::UNAUTHORIZED,
-                    format!("Failed to get user info: {e}"),
-                )
-            })?;
+    let user_info = verify_delegated_identity_request(
+        &state,
+        request.delegated_identity_wire.clone(),
+        &headers,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::UNAUTHORIZED,
+            format!("Failed to get user info: {e}"),
+        )
+    })?;
 
     let follower_principal = user_info.user_principal;
 
@@ -78,6 +119,9 @@ pub async fn handle_follow_user(
         ));
     }
 
+    #[cfg(not(feature = "local-bin"))]
+    reject_if_blocked(&state, follower_principal, request.target_principal).await?;
+
     // 2. Get agent for canister call
     let user_agent = get_agent_from_delegated_identity_wire(&request.delegated_identity_wire)
         .await
@@ -92,9 +136,12 @@ pub async fn handle_follow_user(
     // 3. Call user_info_service.follow_user()
     let user_info_service = UserInfoService(*USER_INFO_SERVICE_CANISTER_ID, &user_agent);
 
-    match user_info_service
-        .follow_user(request.target_principal)
-        .await
+    match agent_pool::update(
+        *USER_INFO_SERVICE_CANISTER_ID,
+        "follow_user",
+        user_info_service.follow_user(request.target_principal),
+    )
+    .await
     {
         Ok(yral_canisters_client::user_info_service::Result_::Ok) => {
             tracing::info!(
@@ -143,24 +190,32 @@ pub async fn handle_follow_user(
         (status = 200, description = "Notification sent successfully", body = FollowUserResponse),
         (status = 400, description = "Invalid request"),
         (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Blocked by or blocking the target"),
         (status = 500, description = "Internal server error"),
+    ),
+    security(
+        ("delegated_identity" = [])
     )
 )]
-#[instrument(skip(state, request))]
+#[instrument(skip(state, headers, request))]
 pub async fn handle_follow_user_notification(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(request): Json<FollowUserNotificationRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // 1. Verify the user identity and get user info
-    let user_info =
-        get_user_info_from_delegated_identity_wire(&state, request.delegated_identity_wire.clone())
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::UNAUTHORIZED,
-                    format!("Failed to get user info: {e}"),
-                )
-            })?;
+    let user_info = verify_delegated_identity_request(
+        &state,
+        request.delegated_identity_wire.clone(),
+        &headers,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::UNAUTHORIZED,
+            format!("Failed to get user info: {e}"),
+        )
+    })?;
 
     let follower_principal = user_info.user_principal;
 
@@ -175,6 +230,9 @@ pub async fn handle_follow_user_notification(
         ));
     }
 
+    #[cfg(not(feature = "local-bin"))]
+    reject_if_blocked(&state, follower_principal, request.target_principal).await?;
+
     // 2. Send notification event (without calling canister)
     let follow_payload = FollowUserPayload {
         follower_principal_id: follower_principal,
@@ -1,11 +1,17 @@
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use candid::Principal;
 use http::header;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
+use super::migration_job::{load_job, run_job, store_job, UserMigrationJob};
 use crate::{
     app_state::AppState, qstash::service_canister_migration::MigrateIndividualUserRequest,
 };
@@ -25,45 +31,126 @@ impl From<MigrateIndividualUserRequest> for MigrateIndividualUserRequestSchema {
     }
 }
 
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct UserMigrationParams {
+    /// Report what each step would change without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+fn check_migration_auth(state: &AppState, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let auth_token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim_start_matches("Bearer ").to_string());
+
+    match auth_token {
+        Some(token) if token == state.user_migration_api_key => Ok(()),
+        _ => Err((StatusCode::UNAUTHORIZED, "Unauthorized".to_string())),
+    }
+}
+
+/// Starts or resumes a user's migration. A dry run executes inline and
+/// returns the report; otherwise the job runs on QStash and can be polled.
 #[utoipa::path(
     post,
     path = "/start_user_migration",
+    params(UserMigrationParams),
     request_body = MigrateIndividualUserRequestSchema,
     tag = "user",
     responses(
-        (status = 200, description = "Accept Request to Migrate Individual User"),
+        (status = 200, description = "Migration job", body = UserMigrationJob),
         (status = 400, description = "Invalid request"),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal server error"),
+    ),
+    security(
+        ("bearer" = [])
     )
 )]
-#[instrument(skip(state, request))]
+#[instrument(skip(state, headers, request))]
 pub async fn handle_user_migration(
     State(state): State<Arc<AppState>>,
-    headers: axum::http::HeaderMap,
+    Query(params): Query<UserMigrationParams>,
+    headers: HeaderMap,
     Json(request): Json<MigrateIndividualUserRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let auth_token = headers
-        .get(header::AUTHORIZATION)
-        .and_then(|value| value.to_str().ok())
-        .map(|value| value.trim_start_matches("Bearer ").to_string());
+) -> Result<Json<UserMigrationJob>, (StatusCode, String)> {
+    check_migration_auth(&state, &headers)?;
+    let pool = &state.service_cansister_migration_redis_pool;
 
-    if let Some(token) = auth_token {
-        if token != state.user_migration_api_key {
-            return Err((StatusCode::UNAUTHORIZED, "Unauthorized".to_string()));
-        }
-    } else {
-        return Err((StatusCode::UNAUTHORIZED, "Unauthorized".to_string()));
+    if params.dry_run {
+        let mut job = UserMigrationJob::new(&request, true);
+        run_job(&state, &mut job)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        return Ok(Json(job));
     }
 
+    let job = match load_job(pool, &request.user_principal, false)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        Some(job) if job.is_finished() => return Ok(Json(job)),
+        Some(job) => job,
+        None => {
+            let job = UserMigrationJob::new(&request, false);
+            store_job(pool, &job)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            job
+        }
+    };
+
     state
         .qstash_client
-        .migrate_individual_user_to_service_canister(&request)
+        .publish_job(&request)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok((
-        StatusCode::OK,
-        "User migration request accepted".to_string(),
+    Ok(Json(job))
+}
+
+/// Per-step progress of a user's migration
+#[utoipa::path(
+    get,
+    path = "/user_migration_status/{user_principal}",
+    params(
+        ("user_principal" = String, Path, description = "User principal"),
+        UserMigrationParams
+    ),
+    tag = "user",
+    responses(
+        (status = 200, description = "Migration job", body = UserMigrationJob),
+        (status = 400, description = "Invalid principal"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "No migration for this user"),
+        (status = 500, description = "Internal server error"),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_user_migration_status(
+    State(state): State<Arc<AppState>>,
+    Path(user_principal): Path<String>,
+    Query(params): Query<UserMigrationParams>,
+    headers: HeaderMap,
+) -> Result<Json<UserMigrationJob>, (StatusCode, String)> {
+    check_migration_auth(&state, &headers)?;
+    let user_principal = Principal::from_text(&user_principal)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid principal: {e}")))?;
+
+    load_job(
+        &state.service_cansister_migration_redis_pool,
+        &user_principal,
+        params.dry_run,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map(Json)
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        format!("No migration for {user_principal}"),
     ))
 }
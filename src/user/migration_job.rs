@@ -0,0 +1,329 @@
+//! Resumable user migration.
+//!
+//! A migration runs as an ordered list of steps, each with its own status
+//! persisted in the migration Redis. The QStash worker picks up at the first
+//! step that isn't finished, so a retry after a failure resumes rather than
+//! starting over. A dry run walks the same steps inline, writing nothing but
+//! its own report, and records what each step would change.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use candid::Principal;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use yral_canisters_client::{ic::USER_INFO_SERVICE_ID, user_post_service::UserPostService};
+
+use crate::{
+    app_state::AppState,
+    canister::agent_pool,
+    consts::USER_POST_SERVICE_CANISTER_ID,
+    qstash::service_canister_migration::{apply_metadata_mapping, MigrateIndividualUserRequest},
+    types::RedisPool,
+};
+
+const KEY_PREFIX: &str = "user_migration:job";
+/// Dry-run reports only need to outlive the support ticket
+const DRY_RUN_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+const POSTS_PAGE_SIZE: u64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationStep {
+    Metadata,
+    Posts,
+    WatchHistory,
+    RewardsBalance,
+}
+
+pub const STEPS: &[MigrationStep] = &[
+    MigrationStep::Metadata,
+    MigrationStep::Posts,
+    MigrationStep::WatchHistory,
+    MigrationStep::RewardsBalance,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StepState {
+    Pending,
+    Running,
+    Done,
+    /// Nothing to do for this user
+    Skipped,
+    Failed,
+}
+
+impl StepState {
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Done | Self::Skipped)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StepStatus {
+    pub step: MigrationStep,
+    pub state: StepState,
+    /// What changed, or would change on a dry run, or why it failed
+    pub detail: Option<String>,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UserMigrationJob {
+    #[schema(value_type = String)]
+    pub user_principal: Principal,
+    #[schema(value_type = String)]
+    pub user_canister: Principal,
+    pub dry_run: bool,
+    pub steps: Vec<StepStatus>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl UserMigrationJob {
+    pub fn new(request: &MigrateIndividualUserRequest, dry_run: bool) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            user_principal: request.user_principal,
+            user_canister: request.user_canister,
+            dry_run,
+            steps: STEPS
+                .iter()
+                .map(|step| StepStatus {
+                    step: *step,
+                    state: StepState::Pending,
+                    detail: None,
+                    updated_at: now,
+                })
+                .collect(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.steps.iter().all(|step| step.state.is_finished())
+    }
+
+    fn request(&self) -> MigrateIndividualUserRequest {
+        MigrateIndividualUserRequest {
+            user_canister: self.user_canister,
+            user_principal: self.user_principal,
+        }
+    }
+
+    fn set_step(&mut self, index: usize, state: StepState, detail: Option<String>) {
+        let now = chrono::Utc::now().timestamp();
+        self.steps[index].state = state;
+        self.steps[index].detail = detail;
+        self.steps[index].updated_at = now;
+        self.updated_at = now;
+    }
+}
+
+fn job_key(user_principal: &Principal, dry_run: bool) -> String {
+    if dry_run {
+        format!("{KEY_PREFIX}:dry_run:{user_principal}")
+    } else {
+        format!("{KEY_PREFIX}:{user_principal}")
+    }
+}
+
+pub async fn load_job(
+    pool: &RedisPool,
+    user_principal: &Principal,
+    dry_run: bool,
+) -> Result<Option<UserMigrationJob>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.get(job_key(user_principal, dry_run)).await?;
+    payload
+        .map(|payload| serde_json::from_str(&payload).map_err(Into::into))
+        .transpose()
+}
+
+pub async fn store_job(pool: &RedisPool, job: &UserMigrationJob) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let key = job_key(&job.user_principal, job.dry_run);
+    let payload = serde_json::to_string(job)?;
+    if job.dry_run {
+        let _: () = conn.set_ex(key, payload, DRY_RUN_TTL_SECS).await?;
+    } else {
+        let _: () = conn.set(key, payload).await?;
+    }
+    Ok(())
+}
+
+/// Runs one step, returning its final state and a description
+async fn run_step(
+    state: &AppState,
+    request: &MigrateIndividualUserRequest,
+    step: MigrationStep,
+    dry_run: bool,
+) -> Result<(StepState, String), String> {
+    match step {
+        MigrationStep::Metadata => {
+            let metadata = state
+                .yral_metadata_client
+                .get_user_metadata_v2(request.user_principal.to_text())
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "User metadata not found".to_string())?;
+            if metadata.user_canister_id == USER_INFO_SERVICE_ID {
+                return Ok((
+                    StepState::Skipped,
+                    "Metadata already points at the user info service".to_string(),
+                ));
+            }
+
+            let change = format!(
+                "user_canister_id {} -> {USER_INFO_SERVICE_ID}",
+                metadata.user_canister_id
+            );
+            if dry_run {
+                return Ok((StepState::Done, format!("Would set {change}")));
+            }
+            apply_metadata_mapping(state, request).await?;
+            Ok((StepState::Done, format!("Set {change}")))
+        }
+        MigrationStep::Posts => {
+            // Individual canisters are decommissioned, so there is nothing to
+            // transfer; confirm the user's posts are served by UserPostService
+            let user_post_service = UserPostService(*USER_POST_SERVICE_CANISTER_ID, &state.agent);
+            let mut count = 0;
+            loop {
+                let posts = agent_pool::query(
+                    *USER_POST_SERVICE_CANISTER_ID,
+                    "get_posts_of_this_user_profile_with_pagination_cursor",
+                    || {
+                        user_post_service.get_posts_of_this_user_profile_with_pagination_cursor(
+                            request.user_principal,
+                            count,
+                            count + POSTS_PAGE_SIZE,
+                        )
+                    },
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+                count += posts.len() as u64;
+                if (posts.len() as u64) < POSTS_PAGE_SIZE {
+                    break;
+                }
+            }
+            Ok((
+                StepState::Skipped,
+                format!("{count} posts already in UserPostService"),
+            ))
+        }
+        MigrationStep::WatchHistory => Ok((
+            StepState::Skipped,
+            "Watch history is keyed by principal, nothing to move".to_string(),
+        )),
+        MigrationStep::RewardsBalance => Ok((
+            StepState::Skipped,
+            "Reward balances live on the token ledgers under the principal, nothing to move"
+                .to_string(),
+        )),
+    }
+}
+
+/// Runs every unfinished step in order, persisting progress after each.
+/// Stops at the first failure, leaving the job resumable.
+pub async fn run_job(state: &AppState, job: &mut UserMigrationJob) -> Result<(), String> {
+    let pool = &state.service_cansister_migration_redis_pool;
+    let request = job.request();
+
+    for index in 0..job.steps.len() {
+        if job.steps[index].state.is_finished() {
+            continue;
+        }
+        let step = job.steps[index].step;
+
+        job.set_step(index, StepState::Running, None);
+        store_job(pool, job).await.map_err(|e| e.to_string())?;
+
+        match run_step(state, &request, step, job.dry_run).await {
+            Ok((step_state, detail)) => {
+                log::info!(
+                    "User migration {} step {step:?}: {detail}",
+                    job.user_principal
+                );
+                job.set_step(index, step_state, Some(detail));
+                store_job(pool, job).await.map_err(|e| e.to_string())?;
+            }
+            Err(e) => {
+                log::error!(
+                    "User migration {} step {step:?} failed: {e}",
+                    job.user_principal
+                );
+                job.set_step(index, StepState::Failed, Some(e.clone()));
+                store_job(pool, job).await.map_err(|e| e.to_string())?;
+                return Err(format!("Step {step:?} failed: {e}"));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the user's migration job from its first unfinished step
+#[utoipa::path(
+    post,
+    path = "/run_user_migration",
+    request_body = MigrateIndividualUserRequest,
+    responses(
+        (status = 200, description = "Migration finished"),
+        (status = 500, description = "A step failed; retrying resumes from it", body = String)
+    ),
+    tag = "qstash"
+)]
+pub async fn run_user_migration(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<MigrateIndividualUserRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let pool = &state.service_cansister_migration_redis_pool;
+    let mut job = load_job(pool, &request.user_principal, false)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .unwrap_or_else(|| UserMigrationJob::new(&request, false));
+
+    run_job(&state, &mut job)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_progress() {
+        let request = MigrateIndividualUserRequest {
+            user_canister: Principal::anonymous(),
+            user_principal: Principal::from_slice(&[1; 29]),
+        };
+        let mut job = UserMigrationJob::new(&request, true);
+        assert_eq!(job.steps.len(), STEPS.len());
+        assert!(!job.is_finished());
+
+        job.set_step(0, StepState::Done, None);
+        job.set_step(1, StepState::Failed, Some("boom".to_string()));
+        assert!(!job.is_finished());
+
+        for index in 1..job.steps.len() {
+            job.set_step(index, StepState::Skipped, None);
+        }
+        assert!(job.is_finished());
+        assert_ne!(
+            job_key(&request.user_principal, true),
+            job_key(&request.user_principal, false)
+        );
+
+        let json = serde_json::to_value(&job).unwrap();
+        assert_eq!(json["steps"][3]["step"], "rewards_balance");
+    }
+}
@@ -1,6 +1,11 @@
+#[cfg(not(feature = "local-bin"))]
+pub mod blocklist;
+#[cfg(not(feature = "local-bin"))]
+pub mod creator_stats;
 pub mod delete_user;
 pub mod follow;
 pub mod migrate_user;
+pub mod migration_job;
 pub mod profile_image;
 pub mod utils;
 
@@ -11,7 +16,17 @@ use utoipa_axum::{router::OpenApiRouter, routes};
 use crate::app_state::AppState;
 
 pub fn user_router(state: Arc<AppState>) -> OpenApiRouter {
-    OpenApiRouter::new()
+    let router = OpenApiRouter::new();
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router
+        .routes(routes!(blocklist::handle_block_user))
+        .routes(routes!(blocklist::handle_unblock_user))
+        .routes(routes!(blocklist::handle_get_blocked_users))
+        .routes(routes!(creator_stats::get_creator_stats))
+        .routes(routes!(crate::streaks::handlers::get_streak));
+
+    router
         .routes(routes!(delete_user::handle_delete_user))
         .routes(routes!(
             profile_image::handle_upload_profile_image,
@@ -20,5 +35,6 @@ pub fn user_router(state: Arc<AppState>) -> OpenApiRouter {
         .routes(routes!(follow::handle_follow_user))
         .routes(routes!(follow::handle_follow_user_notification))
         .routes(routes!(migrate_user::handle_user_migration))
+        .routes(routes!(migrate_user::get_user_migration_status))
         .with_state(state)
 }
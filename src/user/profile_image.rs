@@ -1,15 +1,20 @@
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use base64::Engine;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 use utoipa::ToSchema;
 
 use crate::{
-    app_state::AppState, consts::USER_INFO_SERVICE_CANISTER_ID, types::DelegatedIdentityWire,
-    user::utils::get_agent_from_delegated_identity_wire,
-    utils::delegated_identity::get_user_info_from_delegated_identity_wire,
+    app_state::AppState, canister::agent_pool, consts::USER_INFO_SERVICE_CANISTER_ID,
+    types::DelegatedIdentityWire, user::utils::get_agent_from_delegated_identity_wire,
+    utils::delegated_identity::verify_delegated_identity_request,
     utils::s3::upload_profile_image_to_s3,
 };
 use yral_canisters_client::user_info_service::{ProfileUpdateDetails, UserInfoService};
@@ -35,23 +40,30 @@ pub struct UploadProfileImageResponse {
         (status = 400, description = "Invalid request"),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal server error"),
+    ),
+    security(
+        ("delegated_identity" = [])
     )
 )]
-#[instrument(skip(state, request))]
+#[instrument(skip(state, headers, request))]
 pub async fn handle_upload_profile_image(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(request): Json<UploadProfileImageRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // Verify the user identity and get user info
-    let user_info =
-        get_user_info_from_delegated_identity_wire(&state, request.delegated_identity_wire.clone())
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::UNAUTHORIZED,
-                    format!("Failed to get user info: {e}"),
-                )
-            })?;
+    let user_info = verify_delegated_identity_request(
+        &state,
+        request.delegated_identity_wire.clone(),
+        &headers,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::UNAUTHORIZED,
+            format!("Failed to get user info: {e}"),
+        )
+    })?;
 
     let user_principal = user_info.user_principal;
 
@@ -124,9 +136,12 @@ pub async fn handle_upload_profile_image(
         website_url: None,
     };
 
-    match user_info_service
-        .update_profile_details(update_details)
-        .await
+    match agent_pool::update(
+        *USER_INFO_SERVICE_CANISTER_ID,
+        "update_profile_details",
+        user_info_service.update_profile_details(update_details),
+    )
+    .await
     {
         Ok(yral_canisters_client::user_info_service::Result_::Ok) => {
             tracing::info!(
@@ -175,23 +190,30 @@ pub struct DeleteProfileImageRequest {
         (status = 400, description = "Invalid request"),
         (status = 401, description = "Unauthorized"),
         (status = 500, description = "Internal server error"),
+    ),
+    security(
+        ("delegated_identity" = [])
     )
 )]
-#[instrument(skip(state, request))]
+#[instrument(skip(state, headers, request))]
 pub async fn handle_delete_profile_image(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(request): Json<DeleteProfileImageRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // Verify the user identity and get user info
-    let user_info =
-        get_user_info_from_delegated_identity_wire(&state, request.delegated_identity_wire.clone())
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::UNAUTHORIZED,
-                    format!("Failed to get user info: {e}"),
-                )
-            })?;
+    let user_info = verify_delegated_identity_request(
+        &state,
+        request.delegated_identity_wire.clone(),
+        &headers,
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::UNAUTHORIZED,
+            format!("Failed to get user info: {e}"),
+        )
+    })?;
 
     let user_principal = user_info.user_principal;
 
@@ -1,6 +1,6 @@
 use ic_agent::{identity::DelegatedIdentity, Agent};
 
-use crate::types::DelegatedIdentityWire;
+use crate::{canister::agent_pool, types::DelegatedIdentityWire};
 
 pub async fn get_agent_from_delegated_identity_wire(
     identity_wire: &DelegatedIdentityWire,
@@ -12,10 +12,5 @@ pub async fn get_agent_from_delegated_identity_wire(
             .map_err(|e: k256::elliptic_curve::Error| {
                 anyhow::anyhow!("Failed to create delegated identity: {}", e)
             })?;
-    let agent = Agent::builder()
-        .with_identity(identity)
-        .with_url("https://ic0.app")
-        .build()?;
-
-    Ok(agent)
+    Ok(agent_pool::user_agent(identity)?)
 }
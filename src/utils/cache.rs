@@ -0,0 +1,322 @@
+//! In-process cache in front of hot Redis lookups.
+//!
+//! The first tier is a bounded LRU per cache with a per-entry TTL; the second
+//! is the Redis read performed by the loader passed to
+//! [`TwoTierCache::get_or_load`]. Concurrent misses for one key share a single
+//! load. Writers call [`TwoTierCache::invalidate`], which also publishes the
+//! key on a Dragonfly channel so every instance drops its copy; instances that
+//! lose the subscription clear their caches rather than serve stale entries.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use futures::StreamExt;
+use lru::LruCache;
+use once_cell::sync::{Lazy, OnceCell};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+
+use crate::yral_auth::dragonfly::DragonflyPool;
+
+const INVALIDATION_CHANNEL: &str = "offchain:cache:invalidate";
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// Pool used to publish invalidations, set once the listener is started
+static BUS: OnceCell<Arc<DragonflyPool>> = OnceCell::new();
+
+/// Caches by name, so invalidations received from other instances can find
+/// the local copy
+static REGISTRY: Lazy<Mutex<HashMap<&'static str, Vec<Arc<dyn Evict>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Invalidation {
+    cache: String,
+    key: String,
+}
+
+trait Evict: Send + Sync {
+    fn evict(&self, key: &str);
+    fn clear(&self);
+}
+
+struct Entry<V> {
+    value: V,
+    expires_at: Instant,
+}
+
+struct Store<V> {
+    entries: LruCache<String, Entry<V>>,
+    /// Bumped by every eviction so a load that raced an invalidation does not
+    /// put its stale result back
+    generation: u64,
+}
+
+struct Local<V>(Mutex<Store<V>>);
+
+impl<V> Local<V> {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Store<V>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<V: Send> Evict for Local<V> {
+    fn evict(&self, key: &str) {
+        let mut store = self.lock();
+        store.entries.pop(key);
+        store.generation += 1;
+    }
+
+    fn clear(&self) {
+        let mut store = self.lock();
+        store.entries.clear();
+        store.generation += 1;
+    }
+}
+
+pub struct TwoTierCache<V> {
+    name: &'static str,
+    ttl: Duration,
+    local: Arc<Local<V>>,
+    in_flight: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl<V: Clone + Send + 'static> TwoTierCache<V> {
+    /// `name` identifies the cache in invalidation messages and must be
+    /// unique per process
+    pub fn new(name: &'static str, capacity: usize, ttl: Duration) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        let local = Arc::new(Local(Mutex::new(Store {
+            entries: LruCache::new(capacity),
+            generation: 0,
+        })));
+        REGISTRY
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(name)
+            .or_default()
+            .push(local.clone());
+        Self {
+            name,
+            ttl,
+            local,
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get_fresh(&self, key: &str) -> Option<V> {
+        let mut store = self.local.lock();
+        match store.entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.value.clone()),
+            Some(_) => {
+                store.entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// The cached value for `key`, or the result of `load`. Failed loads are
+    /// not cached.
+    pub async fn get_or_load<F, Fut>(&self, key: &str, load: F) -> Result<V>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V>>,
+    {
+        if let Some(value) = self.get_fresh(key) {
+            return Ok(value);
+        }
+
+        let flight = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key.to_string())
+            .or_default()
+            .clone();
+        let _guard = flight.lock().await;
+
+        // Filled by the caller we waited on
+        let result = match self.get_fresh(key) {
+            Some(value) => Ok(value),
+            None => self.load(key, load).await,
+        };
+
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        // Ours plus the map's; anyone else is still waiting and will re-check
+        if Arc::strong_count(&flight) <= 2 {
+            in_flight.remove(key);
+        }
+        result
+    }
+
+    async fn load<F, Fut>(&self, key: &str, load: F) -> Result<V>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V>>,
+    {
+        let generation = self.local.lock().generation;
+        let result = load().await;
+        if let Ok(value) = &result {
+            let mut store = self.local.lock();
+            if store.generation == generation {
+                store.entries.put(
+                    key.to_string(),
+                    Entry {
+                        value: value.clone(),
+                        expires_at: Instant::now() + self.ttl,
+                    },
+                );
+            }
+        }
+        result
+    }
+
+    /// Drops `key` here and on every other instance
+    pub async fn invalidate(&self, key: &str) {
+        self.local.evict(key);
+
+        let Some(pool) = BUS.get() else {
+            return;
+        };
+        let message = Invalidation {
+            cache: self.name.to_string(),
+            key: key.to_string(),
+        };
+        let published = async {
+            let payload = serde_json::to_string(&message)?;
+            let mut conn = pool.get().await?;
+            let _: u64 = conn.publish(INVALIDATION_CHANNEL, payload).await?;
+            anyhow::Ok(())
+        }
+        .await;
+        if let Err(e) = published {
+            log::warn!(
+                "Failed to publish invalidation of {}:{key}: {e:?}",
+                self.name
+            );
+        }
+    }
+}
+
+fn dispatch(invalidation: &Invalidation) {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    for cache in registry
+        .get(invalidation.cache.as_str())
+        .into_iter()
+        .flatten()
+    {
+        cache.evict(&invalidation.key);
+    }
+}
+
+fn clear_all() {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    for cache in registry.values().flatten() {
+        cache.clear();
+    }
+}
+
+async fn listen(pool: &DragonflyPool) -> Result<()> {
+    let mut pubsub = pool.pubsub().await?;
+    pubsub.subscribe(INVALIDATION_CHANNEL).await?;
+    // Invalidations sent while unsubscribed were missed
+    clear_all();
+
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let payload: String = message.get_payload()?;
+        match serde_json::from_str::<Invalidation>(&payload) {
+            Ok(invalidation) => dispatch(&invalidation),
+            Err(e) => log::warn!("Ignoring malformed cache invalidation {payload:?}: {e}"),
+        }
+    }
+    Ok(())
+}
+
+/// Publishes local invalidations through `pool` and applies those of other
+/// instances. Until this runs, invalidations only affect this process.
+pub fn spawn_invalidation_listener(pool: Arc<DragonflyPool>) {
+    if BUS.set(pool.clone()).is_err() {
+        log::warn!("Cache invalidation listener already started");
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            match listen(&pool).await {
+                Ok(()) => log::warn!("Cache invalidation subscription ended"),
+                Err(e) => log::error!("Cache invalidation subscription failed: {e:?}"),
+            }
+            clear_all();
+            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_single_flight_and_invalidation() {
+        let cache = Arc::new(TwoTierCache::<u32>::new(
+            "test_single_flight",
+            8,
+            Duration::from_secs(60),
+        ));
+        let loads = Arc::new(AtomicU32::new(0));
+
+        let tasks: Vec<_> = (0..10)
+            .map(|_| {
+                let cache = cache.clone();
+                let loads = loads.clone();
+                tokio::spawn(async move {
+                    cache
+                        .get_or_load("k", || async move {
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                            Ok(loads.fetch_add(1, Ordering::SeqCst) + 1)
+                        })
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), 1);
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        cache.invalidate("k").await;
+        let value = cache.get_or_load("k", || async { Ok(2) }).await.unwrap();
+        assert_eq!(value, 2);
+
+        // Remote invalidations reach the cache through the registry
+        dispatch(&Invalidation {
+            cache: "test_single_flight".to_string(),
+            key: "k".to_string(),
+        });
+        let value = cache.get_or_load("k", || async { Ok(3) }).await.unwrap();
+        assert_eq!(value, 3);
+    }
+
+    #[tokio::test]
+    async fn test_expired_and_failed_loads_are_not_served() {
+        let cache = TwoTierCache::<u32>::new("test_expiry", 8, Duration::from_millis(10));
+        assert!(cache
+            .get_or_load("k", || async { anyhow::bail!("redis down") })
+            .await
+            .is_err());
+        assert_eq!(cache.get_or_load("k", || async { Ok(1) }).await.unwrap(), 1);
+        assert_eq!(cache.get_or_load("k", || async { Ok(2) }).await.unwrap(), 1);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(cache.get_or_load("k", || async { Ok(3) }).await.unwrap(), 3);
+    }
+}
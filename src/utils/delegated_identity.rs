@@ -1,17 +1,229 @@
+//! Delegated identity wire verification.
+//!
+//! Every wire is checked for expired delegations, and optionally for
+//! delegations valid longer than `delegated_identity.max_delegation_lifetime_secs`,
+//! which bounds how long a leaked wire stays usable.
+//!
+//! Every user-authenticated route goes through
+//! [`verify_delegated_identity_request`], directly or via
+//! [`crate::auth::require_user`], which verifies the wire and then checks an
+//! `x-identity-nonce: <unix_secs>.<random>` header. Account linking checks
+//! the nonce against the authenticated identity only, since one request can
+//! carry a single nonce signature. The
+//! nonce must come with `x-identity-signature`, the session key's ECDSA
+//! signature (base64) over [`nonce_message`], so a fresh nonce cannot be
+//! attached to a captured wire without the session key. A nonce older than
+//! `delegated_identity.max_age_secs` is rejected, and each nonce is accepted
+//! once per delegation signature. Nonces are only required once
+//! `delegated_identity.require_nonce` is turned on, which should wait until
+//! clients send them. If Dragonfly is
+//! unreachable the nonce is not recorded and the request is let through.
+
+use std::time::Duration;
+
+use base64::Engine;
 use candid::Principal;
-use ic_agent::{identity::DelegatedIdentity, Identity};
+use http::HeaderMap;
+use ic_agent::{
+    identity::{DelegatedIdentity, SignedDelegation},
+    Identity,
+};
+use k256::{
+    ecdsa::{signature::Verifier, Signature, VerifyingKey},
+    pkcs8::DecodePublicKey,
+};
+use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
 
 use crate::{app_state::AppState, types::DelegatedIdentityWire};
 
+/// Header carrying a single-use request nonce, `<unix_secs>.<random>`
+pub const IDENTITY_NONCE_HEADER: &str = "x-identity-nonce";
+/// Header carrying the session key's signature over [`nonce_message`], base64
+pub const IDENTITY_SIGNATURE_HEADER: &str = "x-identity-signature";
+
+const NONCE_KEY_PREFIX: &str = "offchain:identity:nonce";
+const MAX_NONCE_LEN: usize = 128;
+/// Allowed clock drift between clients and this service
+const CLOCK_SKEW_SECS: u64 = 30;
+
 pub struct UserInfo {
     pub user_principal: Principal,
     pub user_canister: Principal,
 }
 
+/// Rejects a chain with an expired delegation, or with one that stays valid
+/// for longer than `max_lifetime`
+pub fn check_delegation_chain(
+    chain: &[SignedDelegation],
+    now: Duration,
+    max_lifetime: Option<Duration>,
+) -> Result<(), anyhow::Error> {
+    let now_ns = now.as_nanos() as u64;
+    for signed in chain {
+        let expiration = signed.delegation.expiration;
+        if expiration <= now_ns {
+            anyhow::bail!("Delegation expired");
+        }
+        if let Some(max_lifetime) = max_lifetime {
+            let limit = (now + max_lifetime + Duration::from_secs(CLOCK_SKEW_SECS)).as_nanos();
+            if expiration as u128 > limit {
+                anyhow::bail!(
+                    "Delegation is valid for longer than {}s",
+                    max_lifetime.as_secs()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Splits `<unix_secs>.<random>` and checks the timestamp is within `max_age`
+pub fn parse_nonce(nonce: &str, now: Duration, max_age: Duration) -> Result<(), anyhow::Error> {
+    let (timestamp, random) = nonce
+        .split_once('.')
+        .ok_or_else(|| anyhow::anyhow!("Malformed {IDENTITY_NONCE_HEADER}"))?;
+    let valid_random = (8..=MAX_NONCE_LEN).contains(&random.len())
+        && random
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+    if !valid_random {
+        anyhow::bail!("Malformed {IDENTITY_NONCE_HEADER}");
+    }
+    let timestamp = Duration::from_secs(
+        timestamp
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Malformed {IDENTITY_NONCE_HEADER}"))?,
+    );
+
+    let skew = Duration::from_secs(CLOCK_SKEW_SECS);
+    if timestamp > now + skew || timestamp + max_age < now {
+        anyhow::bail!("{IDENTITY_NONCE_HEADER} is outside the allowed window");
+    }
+    Ok(())
+}
+
+/// What the session key signs to vouch for a nonce
+pub fn nonce_message(nonce: &str) -> Vec<u8> {
+    format!("yral-identity-nonce:{nonce}").into_bytes()
+}
+
+/// Checks `signature` (base64, 64-byte r||s over SHA-256) against the
+/// DER-encoded secp256k1 session key the delegation chain ends in
+pub fn verify_nonce_signature(
+    session_key: &[u8],
+    nonce: &str,
+    signature: &str,
+) -> Result<(), anyhow::Error> {
+    let key = VerifyingKey::from_public_key_der(session_key)
+        .map_err(|_| anyhow::anyhow!("Session key is not a secp256k1 key"))?;
+    let signature = base64::engine::general_purpose::STANDARD
+        .decode(signature.trim())
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| anyhow::anyhow!("Malformed {IDENTITY_SIGNATURE_HEADER}"))?;
+    let signature = signature.normalize_s().unwrap_or(signature);
+    key.verify(&nonce_message(nonce), &signature)
+        .map_err(|_| anyhow::anyhow!("Invalid {IDENTITY_SIGNATURE_HEADER}"))
+}
+
+fn nonce_key(wire: &DelegatedIdentityWire, nonce: &str) -> String {
+    let mut hasher = Sha256::new();
+    for signed in &wire.delegation_chain {
+        hasher.update(&signed.signature);
+    }
+    let signature_hash = hex::encode(&hasher.finalize()[..16]);
+    format!("{NONCE_KEY_PREFIX}:{signature_hash}:{nonce}")
+}
+
+async fn check_nonce(
+    state: &AppState,
+    wire: &DelegatedIdentityWire,
+    headers: &HeaderMap,
+) -> Result<(), anyhow::Error> {
+    let config = &state.config.delegated_identity;
+    let Some(nonce) = headers.get(IDENTITY_NONCE_HEADER) else {
+        if config.require_nonce {
+            anyhow::bail!("Missing {IDENTITY_NONCE_HEADER} header");
+        }
+        return Ok(());
+    };
+    let nonce = nonce
+        .to_str()
+        .map_err(|_| anyhow::anyhow!("Malformed {IDENTITY_NONCE_HEADER}"))?;
+    let max_age = Duration::from_secs(config.max_age_secs);
+    parse_nonce(nonce, now(), max_age)?;
+
+    let signature = headers
+        .get(IDENTITY_SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| anyhow::anyhow!("Missing {IDENTITY_SIGNATURE_HEADER} header"))?;
+    let session_key = wire
+        .delegation_chain
+        .last()
+        .map(|signed| signed.delegation.pubkey.as_slice())
+        .ok_or_else(|| anyhow::anyhow!("Delegation chain is empty"))?;
+    verify_nonce_signature(session_key, nonce, signature)?;
+
+    let key = nonce_key(wire, nonce);
+    let first_use = async {
+        let mut conn = state.yral_redis_store_dragonfly.get().await?;
+        let stored: bool = redis::cmd("SET")
+            .arg(&key)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(config.max_age_secs + CLOCK_SKEW_SECS)
+            .query_async::<Option<String>>(&mut conn)
+            .await?
+            .is_some();
+        anyhow::Ok(stored)
+    }
+    .await;
+
+    match first_use {
+        Ok(true) => Ok(()),
+        Ok(false) => anyhow::bail!("{IDENTITY_NONCE_HEADER} already used"),
+        Err(e) => {
+            log::warn!("Failed to record identity nonce, allowing request: {e:?}");
+            Ok(())
+        }
+    }
+}
+
+fn now() -> Duration {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// [`get_user_info_from_delegated_identity_wire`] plus the request nonce check,
+/// which only runs once the wire itself has verified
+pub async fn verify_delegated_identity_request(
+    state: &AppState,
+    delegated_identity_wire: DelegatedIdentityWire,
+    headers: &HeaderMap,
+) -> Result<UserInfo, anyhow::Error> {
+    let wire = delegated_identity_wire.clone();
+    let user_info =
+        get_user_info_from_delegated_identity_wire(state, delegated_identity_wire).await?;
+    check_nonce(state, &wire, headers).await?;
+    Ok(user_info)
+}
+
 pub async fn get_user_info_from_delegated_identity_wire(
     state: &AppState,
     delegated_identity_wire: DelegatedIdentityWire,
 ) -> Result<UserInfo, anyhow::Error> {
+    check_delegation_chain(
+        &delegated_identity_wire.delegation_chain,
+        now(),
+        state
+            .config
+            .delegated_identity
+            .max_delegation_lifetime_secs
+            .map(Duration::from_secs),
+    )?;
     let identity = DelegatedIdentity::try_from(delegated_identity_wire)
         .map_err(|e| anyhow::anyhow!("Failed to parse delegated identity wire: {}", e))?;
     let user_principal = identity
@@ -27,3 +239,84 @@ pub async fn get_user_info_from_delegated_identity_wire(
         user_canister,
     })
 }
+
+/// Header carrying the delegated identity on GET endpoints, which have no body
+/// for `delegated_identity_wire`. The value is the wire as base64-encoded JSON.
+pub const DELEGATED_IDENTITY_HEADER: &str = "x-delegated-identity";
+
+pub fn delegated_identity_wire_from_headers(
+    headers: &HeaderMap,
+) -> Result<DelegatedIdentityWire, anyhow::Error> {
+    let value = headers
+        .get(DELEGATED_IDENTITY_HEADER)
+        .ok_or_else(|| anyhow::anyhow!("Missing {DELEGATED_IDENTITY_HEADER} header"))?
+        .to_str()
+        .map_err(|e| anyhow::anyhow!("Invalid {DELEGATED_IDENTITY_HEADER} header: {}", e))?;
+    let json = base64::engine::general_purpose::STANDARD
+        .decode(value.trim())
+        .map_err(|e| anyhow::anyhow!("Failed to decode delegated identity: {}", e))?;
+
+    serde_json::from_slice(&json)
+        .map_err(|e| anyhow::anyhow!("Failed to parse delegated identity wire: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use ic_agent::identity::Delegation;
+
+    use super::*;
+
+    #[test]
+    fn test_delegation_and_nonce_checks() {
+        let now = Duration::from_secs(1_700_000_000);
+        let delegation = |expires_in: Duration| SignedDelegation {
+            delegation: Delegation {
+                pubkey: vec![],
+                expiration: (now + expires_in).as_nanos() as u64,
+                targets: None,
+            },
+            signature: vec![1],
+        };
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        assert!(check_delegation_chain(&[delegation(day)], now, None).is_ok());
+        assert!(check_delegation_chain(&[delegation(Duration::ZERO)], now, None).is_err());
+        assert!(check_delegation_chain(&[delegation(day * 30)], now, Some(day * 7)).is_err());
+        assert!(check_delegation_chain(&[delegation(day)], now, Some(day * 7)).is_ok());
+
+        let max_age = Duration::from_secs(300);
+        assert!(parse_nonce("1700000000.a1b2c3d4e5", now, max_age).is_ok());
+        assert!(parse_nonce("1699999000.a1b2c3d4e5", now, max_age).is_err());
+        assert!(parse_nonce("1700001000.a1b2c3d4e5", now, max_age).is_err());
+        assert!(parse_nonce("1700000000.short", now, max_age).is_err());
+        assert!(parse_nonce("not-a-nonce", now, max_age).is_err());
+    }
+
+    #[test]
+    fn test_nonce_signature() {
+        use k256::{
+            ecdsa::{signature::Signer, SigningKey},
+            pkcs8::EncodePublicKey,
+        };
+
+        let session = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let session_key = session
+            .verifying_key()
+            .to_public_key_der()
+            .unwrap()
+            .into_vec();
+        let nonce = "1700000000.a1b2c3d4e5";
+        let sign = |key: &SigningKey, nonce: &str| {
+            let signature: Signature = key.sign(&nonce_message(nonce));
+            base64::engine::general_purpose::STANDARD.encode(signature.to_bytes())
+        };
+
+        assert!(verify_nonce_signature(&session_key, nonce, &sign(&session, nonce)).is_ok());
+        // A signature for one nonce does not vouch for another
+        let other_nonce = "1700000000.f6e5d4c3b2";
+        assert!(verify_nonce_signature(&session_key, other_nonce, &sign(&session, nonce)).is_err());
+        let other = SigningKey::from_slice(&[9u8; 32]).unwrap();
+        assert!(verify_nonce_signature(&session_key, nonce, &sign(&other, nonce)).is_err());
+        assert!(verify_nonce_signature(&session_key, nonce, "not-base64!").is_err());
+    }
+}
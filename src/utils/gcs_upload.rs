@@ -0,0 +1,287 @@
+//! Resumable GCS uploads for large objects.
+//!
+//! Objects are sent through a resumable upload session in chunks of
+//! `GCS_UPLOAD_CHUNK_BYTES`. When a chunk fails with a network error, 429 or
+//! 5xx, the session is asked how much it has persisted and the upload resumes
+//! from there after a backoff, up to `GCS_UPLOAD_MAX_RETRIES` times in a row.
+//! A session GCS has expired is restarted once from the beginning.
+//!
+//! The CRC32C of the content is sent with the session, so GCS rejects an
+//! object whose bytes don't match it, and is checked again against the
+//! finished object. At most `GCS_UPLOAD_MAX_PARALLEL` uploads run at a time
+//! per process.
+
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use axum::body::Bytes;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use once_cell::sync::Lazy;
+use reqwest::{header, Client, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::Semaphore;
+
+use crate::{app_state::AppState, utils::env_parse};
+
+const UPLOAD_SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_write";
+/// Chunks other than the last must be a multiple of this
+const CHUNK_ALIGNMENT: usize = 256 * 1024;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+static CHUNK_BYTES: Lazy<usize> = Lazy::new(|| {
+    let chunk = env_parse("GCS_UPLOAD_CHUNK_BYTES", 8 * 1024 * 1024);
+    (chunk / CHUNK_ALIGNMENT).max(1) * CHUNK_ALIGNMENT
+});
+static MAX_RETRIES: Lazy<u32> = Lazy::new(|| env_parse("GCS_UPLOAD_MAX_RETRIES", 5));
+static UPLOAD_PERMITS: Lazy<Semaphore> =
+    Lazy::new(|| Semaphore::new(env_parse("GCS_UPLOAD_MAX_PARALLEL", 4).max(1)));
+
+const CRC32C_POLY: u32 = 0x82f6_3b78;
+
+static CRC32C_TABLE: Lazy<[u32; 256]> = Lazy::new(|| {
+    let mut table = [0u32; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut crc = i as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ CRC32C_POLY
+            } else {
+                crc >> 1
+            };
+        }
+        *entry = crc;
+    }
+    table
+});
+
+/// CRC32C (Castagnoli), the checksum GCS keeps for every object
+pub fn crc32c(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC32C_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Base64 of the big-endian checksum, as GCS reports it
+fn crc32c_base64(data: &[u8]) -> String {
+    BASE64.encode(crc32c(data).to_be_bytes())
+}
+
+/// Bytes the session has persisted, from the `Range: bytes=0-N` header of
+/// a 308 response; none when the header is absent
+fn persisted_bytes(range: Option<&str>) -> Result<usize> {
+    let Some(range) = range else {
+        return Ok(0);
+    };
+    let last = range
+        .strip_prefix("bytes=0-")
+        .and_then(|last| last.parse::<usize>().ok())
+        .ok_or_else(|| anyhow!("Unexpected Range header from GCS: {range}"))?;
+    Ok(last + 1)
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadedObject {
+    crc32c: Option<String>,
+}
+
+/// Where a session stands after a chunk or a status query
+enum Progress {
+    /// Persisted this many bytes, send the rest
+    Incomplete(usize),
+    Done(UploadedObject),
+    /// The session is gone and the upload must start over
+    Expired,
+    /// Failed in a way worth retrying
+    Transient(String),
+}
+
+pub struct ResumableUpload<'a> {
+    pub bucket: &'a str,
+    pub name: &'a str,
+    pub content_type: &'a str,
+    pub metadata: HashMap<String, String>,
+}
+
+impl ResumableUpload<'_> {
+    /// Uploads `data`, resuming after transient failures
+    pub async fn upload(&self, state: &AppState, data: Bytes) -> Result<()> {
+        let _permit = UPLOAD_PERMITS
+            .acquire()
+            .await
+            .context("GCS upload semaphore closed")?;
+        let client = Client::new();
+        let checksum = crc32c_base64(&data);
+
+        for _ in 0..2 {
+            let session = self.start_session(state, &client, &checksum).await?;
+            match self.send(&client, &session, &data).await? {
+                Some(object) => {
+                    if object.crc32c.as_deref() != Some(checksum.as_str()) {
+                        bail!(
+                            "CRC32C mismatch for gs://{}/{}: sent {checksum}, stored {:?}",
+                            self.bucket,
+                            self.name,
+                            object.crc32c
+                        );
+                    }
+                    return Ok(());
+                }
+                None => log::warn!(
+                    "GCS upload session for gs://{}/{} expired, restarting",
+                    self.bucket,
+                    self.name
+                ),
+            }
+        }
+        bail!(
+            "GCS upload session for gs://{}/{} expired twice",
+            self.bucket,
+            self.name
+        )
+    }
+
+    async fn start_session(
+        &self,
+        state: &AppState,
+        client: &Client,
+        checksum: &str,
+    ) -> Result<String> {
+        let token = state.get_access_token(&[UPLOAD_SCOPE]).await;
+        let response = client
+            .post(format!(
+                "https://storage.googleapis.com/upload/storage/v1/b/{}/o?uploadType=resumable",
+                self.bucket
+            ))
+            .bearer_auth(token)
+            .header("X-Upload-Content-Type", self.content_type)
+            .json(&json!({
+                "name": self.name,
+                "contentType": self.content_type,
+                "crc32c": checksum,
+                "metadata": self.metadata,
+            }))
+            .send()
+            .await?
+            .error_for_status()
+            .context("Failed to start GCS upload session")?;
+
+        response
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .map(str::to_string)
+            .context("GCS upload session has no Location")
+    }
+
+    /// Sends `data` through the session. `None` when the session expired.
+    async fn send(
+        &self,
+        client: &Client,
+        session: &str,
+        data: &Bytes,
+    ) -> Result<Option<UploadedObject>> {
+        let total = data.len();
+        let mut offset = 0;
+        let mut failures = 0;
+        loop {
+            let end = (offset + *CHUNK_BYTES).min(total);
+            let mut progress =
+                put_chunk(client, session, data.slice(offset..end), offset, total).await?;
+            if !matches!(progress, Progress::Transient(_)) {
+                failures = 0;
+            }
+
+            // After a failed chunk, ask the session how much it kept
+            while let Progress::Transient(reason) = progress {
+                if failures >= *MAX_RETRIES {
+                    bail!(
+                        "GCS upload of gs://{}/{} failed at byte {offset}: {reason}",
+                        self.bucket,
+                        self.name
+                    );
+                }
+                failures += 1;
+                log::warn!(
+                    "GCS upload of gs://{}/{} failed at byte {offset}, resuming (attempt {failures}): {reason}",
+                    self.bucket,
+                    self.name
+                );
+                tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(failures - 1)).await;
+                progress = put_chunk(client, session, Bytes::new(), offset, total).await?;
+            }
+
+            match progress {
+                Progress::Incomplete(persisted) => offset = persisted,
+                Progress::Done(object) => return Ok(Some(object)),
+                Progress::Expired => return Ok(None),
+                Progress::Transient(_) => unreachable!("retried above"),
+            }
+        }
+    }
+}
+
+/// Sends one chunk; an empty chunk only asks for the session's status
+async fn put_chunk(
+    client: &Client,
+    session: &str,
+    chunk: Bytes,
+    offset: usize,
+    total: usize,
+) -> Result<Progress> {
+    let content_range = if chunk.is_empty() {
+        format!("bytes */{total}")
+    } else {
+        format!("bytes {offset}-{}/{total}", offset + chunk.len() - 1)
+    };
+    match client
+        .put(session)
+        .header(header::CONTENT_RANGE, content_range)
+        .body(chunk)
+        .send()
+        .await
+    {
+        Ok(response) => progress(response).await,
+        Err(e) => Ok(Progress::Transient(e.to_string())),
+    }
+}
+
+async fn progress(response: reqwest::Response) -> Result<Progress> {
+    let status = response.status();
+    match status.as_u16() {
+        200 | 201 => Ok(Progress::Done(response.json().await?)),
+        308 => Ok(Progress::Incomplete(persisted_bytes(
+            response
+                .headers()
+                .get(header::RANGE)
+                .and_then(|range| range.to_str().ok()),
+        )?)),
+        404 | 410 => Ok(Progress::Expired),
+        _ if is_transient(status) => Ok(Progress::Transient(format!("GCS returned {status}"))),
+        _ => Err(anyhow!(
+            "GCS upload rejected with {status}: {}",
+            response.text().await.unwrap_or_default()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32c_and_range() {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+        assert_eq!(crc32c(b""), 0);
+        assert_eq!(crc32c_base64(b""), "AAAAAA==");
+
+        assert_eq!(persisted_bytes(None).unwrap(), 0);
+        assert_eq!(persisted_bytes(Some("bytes=0-262143")).unwrap(), 262_144);
+        assert!(persisted_bytes(Some("bytes=5-9")).is_err());
+        assert_eq!(*CHUNK_BYTES % CHUNK_ALIGNMENT, 0);
+    }
+}
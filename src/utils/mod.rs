@@ -1,7 +1,21 @@
 pub mod api_response;
+pub mod cache;
 pub mod delegated_identity;
 pub mod gcs;
+pub mod gcs_upload;
 pub mod grpc_clients;
 pub mod naitik_multi_service_client;
+pub mod read_replicas;
 pub mod s3;
 pub mod time;
+
+/// `key` parsed as `T`, or `default` when it is unset or doesn't parse
+pub(crate) fn env_parse<T>(key: &str, default: T) -> T
+where
+    T: std::str::FromStr,
+{
+    std::env::var(key)
+        .ok()
+        .and_then(|value| value.parse::<T>().ok())
+        .unwrap_or(default)
+}
@@ -0,0 +1,201 @@
+//! Content hash keyed reuse of expensive detector verdicts.
+//!
+//! Verdicts are keyed by the file's SHA-256 (`VIDEO_CONTENT_HASH`, written
+//! during dedup), so only byte-identical re-uploads reuse the AI video
+//! detector and NSFW verdicts computed for the first copy. Near-duplicates
+//! that merely share a phash are always scored on their own.
+//! Entries are only reused while they were produced by the current model
+//! version and their age-decayed confidence stays above the configured floor.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    kvrocks::{tables, CachedDetectorVerdict, KvrocksClient},
+    utils::env_parse,
+};
+
+pub const AI_VIDEO_DETECTOR: &str = "ai_video_detector";
+pub const NSFW_V2: &str = "nsfw_v2";
+
+#[derive(Debug, Clone)]
+pub struct VerdictCachePolicy {
+    pub detector: &'static str,
+    /// Verdicts scored by any other model version are re-scored.
+    pub model_version: String,
+    /// Hard upper bound on reuse, also used as the kvrocks TTL.
+    pub max_age_secs: i64,
+    /// Cached confidence halves every `half_life_secs`.
+    pub half_life_secs: i64,
+    /// Decayed confidence below this forces a re-score.
+    pub min_confidence: f64,
+}
+
+impl VerdictCachePolicy {
+    /// Model version is configured per detector (`AI_VIDEO_DETECTOR_MODEL_VERSION`,
+    /// `NSFW_V2_MODEL_VERSION`); the decay knobs are shared across detectors.
+    pub fn from_env(detector: &'static str) -> Self {
+        let model_version_var = format!("{}_MODEL_VERSION", detector.to_uppercase());
+        Self {
+            detector,
+            model_version: std::env::var(model_version_var).unwrap_or_else(|_| "v1".to_string()),
+            max_age_secs: env_parse("VERDICT_CACHE_MAX_AGE_SECS", 30 * 24 * 60 * 60),
+            half_life_secs: env_parse("VERDICT_CACHE_HALF_LIFE_SECS", 60 * 24 * 60 * 60),
+            min_confidence: env_parse("VERDICT_CACHE_MIN_CONFIDENCE", 0.6),
+        }
+    }
+
+    pub fn decayed_confidence(&self, cached: &CachedDetectorVerdict, now: i64) -> f64 {
+        let age = (now - cached.scored_at).max(0) as f64;
+        if self.half_life_secs <= 0 {
+            return cached.confidence;
+        }
+        cached.confidence * 0.5f64.powf(age / self.half_life_secs as f64)
+    }
+
+    pub fn is_reusable(&self, cached: &CachedDetectorVerdict, now: i64) -> bool {
+        cached.detector == self.detector
+            && cached.model_version == self.model_version
+            && now - cached.scored_at <= self.max_age_secs
+            && self.decayed_confidence(cached, now) >= self.min_confidence
+    }
+
+    /// Returns the cached result for `content_hash` if the policy allows reuse.
+    /// Cache failures are logged and treated as a miss so scoring always proceeds.
+    pub async fn lookup<T: DeserializeOwned>(
+        &self,
+        kvrocks_client: &KvrocksClient,
+        content_hash: &str,
+    ) -> Option<(T, CachedDetectorVerdict)> {
+        let cached = match kvrocks_client
+            .get(
+                &tables::DETECTOR_VERDICT_CACHE,
+                &tables::detector_verdict_id(self.detector, content_hash),
+            )
+            .await
+        {
+            Ok(Some(cached)) => cached,
+            Ok(None) => return None,
+            Err(e) => {
+                log::warn!(
+                    "Failed to read {} verdict cache for hash {}: {}",
+                    self.detector,
+                    content_hash,
+                    e
+                );
+                return None;
+            }
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        if !self.is_reusable(&cached, now) {
+            log::info!(
+                "Stale {} verdict for hash {} (model_version={}, scored_at={}, decayed_confidence={:.2}); re-scoring",
+                self.detector,
+                content_hash,
+                cached.model_version,
+                cached.scored_at,
+                self.decayed_confidence(&cached, now)
+            );
+            return None;
+        }
+
+        match serde_json::from_value(cached.result.clone()) {
+            Ok(result) => Some((result, cached)),
+            Err(e) => {
+                log::warn!(
+                    "Failed to decode cached {} verdict for hash {}: {}",
+                    self.detector,
+                    content_hash,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    pub async fn store<T: Serialize>(
+        &self,
+        kvrocks_client: &KvrocksClient,
+        content_hash: &str,
+        video_id: &str,
+        confidence: f64,
+        result: &T,
+    ) {
+        let result = match serde_json::to_value(result) {
+            Ok(result) => result,
+            Err(e) => {
+                log::warn!("Failed to serialize {} verdict: {}", self.detector, e);
+                return;
+            }
+        };
+
+        let data = CachedDetectorVerdict {
+            content_hash: content_hash.to_string(),
+            detector: self.detector.to_string(),
+            model_version: self.model_version.clone(),
+            source_video_id: video_id.to_string(),
+            confidence,
+            result,
+            scored_at: chrono::Utc::now().timestamp(),
+        };
+
+        if let Err(e) = kvrocks_client
+            .put_ex(
+                &tables::DETECTOR_VERDICT_CACHE,
+                &tables::detector_verdict_id(&data.detector, &data.content_hash),
+                &data,
+                self.max_age_secs.max(1) as u64,
+            )
+            .await
+        {
+            log::error!(
+                "Error caching {} verdict for video {}: {}",
+                self.detector,
+                video_id,
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> VerdictCachePolicy {
+        VerdictCachePolicy {
+            detector: AI_VIDEO_DETECTOR,
+            model_version: "v2".to_string(),
+            max_age_secs: 1000,
+            half_life_secs: 500,
+            min_confidence: 0.6,
+        }
+    }
+
+    fn cached(model_version: &str, confidence: f64, scored_at: i64) -> CachedDetectorVerdict {
+        CachedDetectorVerdict {
+            content_hash: "abc".to_string(),
+            detector: AI_VIDEO_DETECTOR.to_string(),
+            model_version: model_version.to_string(),
+            source_video_id: "video".to_string(),
+            confidence,
+            result: serde_json::Value::Null,
+            scored_at,
+        }
+    }
+
+    #[test]
+    fn test_verdict_reuse_policy() {
+        let policy = policy();
+
+        assert!(policy.is_reusable(&cached("v2", 0.95, 0), 0));
+        // Model upgrade invalidates the verdict
+        assert!(!policy.is_reusable(&cached("v1", 0.95, 0), 0));
+        // Past max age
+        assert!(!policy.is_reusable(&cached("v2", 0.95, 0), 1001));
+        // One half-life decays 0.95 to 0.475, below the floor
+        assert!(!policy.is_reusable(&cached("v2", 0.95, 0), 500));
+        // Low-confidence verdicts are never reused
+        assert!(!policy.is_reusable(&cached("v2", 0.55, 0), 0));
+    }
+}
@@ -0,0 +1,574 @@
+//! Scheduled audit of where recently uploaded videos can be fetched from.
+//!
+//! The `video_availability_audit` schedule samples recent uploads from
+//! BigQuery and HEAD-checks each storage origin: the Storj bucket the video's
+//! NSFW verdict points at, Cloudflare Stream and, when asked for, the
+//! `yral-videos` GCS bucket. The outcome per origin is stored in kvrocks under
+//! `offchain:video_availability:{video_id}`.
+//!
+//! Missing copies are healed from one that is still there: a Storj copy is
+//! re-ingested through the `storj_ingest` job, and a GCS copy is re-uploaded
+//! from the video's Storj bucket by the `video_availability/gcs_reupload`
+//! job. Cloudflare Stream can't be refilled from here, so those are
+//! only reported. A video is healed at most once per [`HEAL_COOLDOWN_SECS`].
+//! Runs that find anything missing post a summary to
+//! `GCHAT_VIDEO_AVAILABILITY_WEBHOOK_URL`.
+
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use axum::{extract::State, response::IntoResponse, Json};
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use google_cloud_bigquery::http::{
+    job::query::QueryRequest,
+    tabledata::list::{Tuple, Value},
+};
+use http::StatusCode;
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    consts::{get_storj_video_url, CLOUDFLARE_STREAM_CUSTOMER_SUBDOMAIN},
+    error::{ApiError, ApiErrorBody},
+    kvrocks::{tables, OriginAvailability, VideoAvailability},
+    offchain_service::send_message_gchat_webhook,
+    qstash::job::{Lane, PublishOptions},
+};
+
+const DEFAULT_SAMPLE_SIZE: usize = 200;
+const MAX_SAMPLE_SIZE: usize = 2_000;
+const DEFAULT_LOOKBACK_HOURS: u32 = 24 * 7;
+const CHECK_CONCURRENCY: usize = 16;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+/// A video healed within this window isn't re-enqueued by later runs
+pub const HEAL_COOLDOWN_SECS: i64 = 24 * 60 * 60;
+/// Missing video ids listed in the alert
+const ALERT_MAX_LISTED: usize = 20;
+
+const GCS_VIDEOS_BUCKET: &str = "yral-videos";
+const GCS_READ_SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_only";
+
+static VIDEO_AVAILABILITY_WEBHOOK_URL: Lazy<Option<String>> =
+    Lazy::new(|| std::env::var("GCHAT_VIDEO_AVAILABILITY_WEBHOOK_URL").ok());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Storj,
+    CloudflareStream,
+    Gcs,
+}
+
+impl Origin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Origin::Storj => "storj",
+            Origin::CloudflareStream => "cloudflare_stream",
+            Origin::Gcs => "gcs",
+        }
+    }
+}
+
+/// Only a definite answer from the origin counts as missing; timeouts and
+/// 5xx are inconclusive and never trigger a heal
+fn is_missing(status: &OriginAvailability) -> bool {
+    matches!(status.http_status, Some(404 | 410))
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct VideoAvailabilityAuditRequest {
+    pub sample_size: Option<usize>,
+    /// Only videos uploaded within this many hours are sampled
+    pub lookback_hours: Option<u32>,
+    /// Also check the `yral-videos` GCS bucket, which new uploads no longer
+    /// write to
+    #[serde(default)]
+    pub check_gcs: bool,
+    /// Record statuses without enqueueing any re-uploads
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default, Clone, Serialize, ToSchema)]
+pub struct VideoAvailabilityAuditSummary {
+    pub sampled: usize,
+    /// Videos with at least one missing origin
+    pub missing_videos: usize,
+    /// Missing copies per origin
+    pub missing_by_origin: BTreeMap<String, usize>,
+    /// Origin checks that neither found nor ruled out the video
+    pub inconclusive: usize,
+    pub heals_enqueued: usize,
+    pub heals_failed: usize,
+    /// Skipped because the video was healed within the cooldown
+    pub heals_skipped: usize,
+}
+
+#[derive(Debug, Clone)]
+struct SampledVideo {
+    video_id: String,
+    publisher_user_id: String,
+    post_id: Option<String>,
+}
+
+enum HealOutcome {
+    Enqueued,
+    Failed,
+    Skipped,
+    NotNeeded,
+}
+
+async fn sample_recent_videos(
+    state: &AppState,
+    sample_size: usize,
+    lookback_hours: u32,
+) -> Result<Vec<SampledVideo>> {
+    let query = format!(
+        "SELECT video_id, ANY_VALUE(publisher_user_id), ANY_VALUE(post_id)
+         FROM (
+           SELECT JSON_EXTRACT_SCALAR(params, '$.video_id') AS video_id,
+                  JSON_EXTRACT_SCALAR(params, '$.publisher_user_id') AS publisher_user_id,
+                  JSON_EXTRACT_SCALAR(params, '$.post_id') AS post_id
+           FROM `hot-or-not-feed-intelligence.analytics_335143420.test_events_analytics`
+           WHERE event = 'video_upload_successful'
+             AND timestamp >= TIMESTAMP_SUB(CURRENT_TIMESTAMP(), INTERVAL {lookback_hours} HOUR)
+         )
+         WHERE video_id IS NOT NULL AND publisher_user_id IS NOT NULL
+         GROUP BY video_id
+         ORDER BY RAND()
+         LIMIT {sample_size}"
+    );
+
+    let response = state
+        .bigquery_client
+        .job()
+        .query(
+            "hot-or-not-feed-intelligence",
+            &QueryRequest {
+                query,
+                ..Default::default()
+            },
+        )
+        .await
+        .context("Failed to sample recent uploads")?;
+
+    let cell = |row: &Tuple, i: usize| {
+        row.f.get(i).and_then(|cell| match &cell.v {
+            Value::String(value) => Some(value.clone()),
+            _ => None,
+        })
+    };
+    Ok(response
+        .rows
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|row| {
+            Some(SampledVideo {
+                video_id: cell(row, 0)?,
+                publisher_user_id: cell(row, 1)?,
+                post_id: cell(row, 2),
+            })
+        })
+        .collect())
+}
+
+async fn head(request: reqwest::RequestBuilder) -> OriginAvailability {
+    match request.timeout(CHECK_TIMEOUT).send().await {
+        Ok(response) => {
+            let status = response.status();
+            OriginAvailability {
+                available: status.is_success(),
+                http_status: Some(status.as_u16()),
+                error: None,
+            }
+        }
+        Err(e) => OriginAvailability {
+            available: false,
+            http_status: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+async fn check_origins(
+    state: &AppState,
+    client: &Client,
+    video: &SampledVideo,
+    is_nsfw: bool,
+    check_gcs: bool,
+) -> BTreeMap<String, OriginAvailability> {
+    let mut origins = BTreeMap::new();
+    origins.insert(
+        Origin::Storj.as_str().to_string(),
+        head(client.head(get_storj_video_url(
+            &video.publisher_user_id,
+            &video.video_id,
+            is_nsfw,
+        )))
+        .await,
+    );
+    origins.insert(
+        Origin::CloudflareStream.as_str().to_string(),
+        head(client.head(format!(
+            "https://{CLOUDFLARE_STREAM_CUSTOMER_SUBDOMAIN}.cloudflarestream.com/{}/manifest/video.m3u8",
+            video.video_id
+        )))
+        .await,
+    );
+    if check_gcs {
+        let token = state.get_access_token(&[GCS_READ_SCOPE]).await;
+        origins.insert(
+            Origin::Gcs.as_str().to_string(),
+            head(
+                client
+                    .get(format!(
+                        "https://storage.googleapis.com/storage/v1/b/{GCS_VIDEOS_BUCKET}/o/{}.mp4",
+                        video.video_id
+                    ))
+                    .bearer_auth(token),
+            )
+            .await,
+        );
+    }
+    origins
+}
+
+/// Re-uploads a video to GCS from the Storj bucket it lives in
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GcsReuploadRequest {
+    pub video_id: String,
+    pub publisher_user_id: String,
+    pub post_id: String,
+    /// Picks the NSFW Storj bucket over the SFW one
+    pub is_nsfw: bool,
+}
+
+/// Queues re-creation of missing copies from one that is still there
+async fn heal(
+    state: &AppState,
+    video: &SampledVideo,
+    is_nsfw: bool,
+    origins: &BTreeMap<String, OriginAvailability>,
+) -> Result<bool> {
+    let available = |origin: Origin| {
+        origins
+            .get(origin.as_str())
+            .is_some_and(|status| status.available)
+    };
+    let missing = |origin: Origin| origins.get(origin.as_str()).is_some_and(is_missing);
+    let mut healed = false;
+
+    if missing(Origin::Storj) && available(Origin::CloudflareStream) {
+        let args: storj_interface::duplicate::Args = serde_json::from_value(json!({
+            "publisher_user_id": video.publisher_user_id,
+            "video_id": video.video_id,
+            "is_nsfw": is_nsfw,
+            "metadata": {},
+        }))
+        .context("Failed to build storj duplicate args")?;
+        state
+            .qstash_client
+            .publish_job_with(&args, PublishOptions::in_lane(Lane::Bulk))
+            .await?;
+        healed = true;
+    }
+
+    if missing(Origin::Gcs) && available(Origin::Storj) {
+        let post_id = video
+            .post_id
+            .clone()
+            .context("No post_id to re-upload to GCS with")?;
+        state
+            .qstash_client
+            .publish_job(&GcsReuploadRequest {
+                video_id: video.video_id.clone(),
+                publisher_user_id: video.publisher_user_id.clone(),
+                post_id,
+                is_nsfw,
+            })
+            .await?;
+        healed = true;
+    }
+
+    Ok(healed)
+}
+
+async fn audit_video(
+    state: &AppState,
+    client: &Client,
+    video: SampledVideo,
+    check_gcs: bool,
+    dry_run: bool,
+) -> (VideoAvailability, HealOutcome) {
+    let is_nsfw = match state
+        .kvrocks_client
+        .get(&tables::VIDEO_NSFW, &video.video_id)
+        .await
+    {
+        Ok(nsfw) => nsfw.is_some_and(|nsfw| nsfw.is_nsfw),
+        Err(e) => {
+            log::warn!("Failed to read NSFW verdict of {}: {e:?}", video.video_id);
+            false
+        }
+    };
+    let previous = state
+        .kvrocks_client
+        .get(&tables::VIDEO_AVAILABILITY, &video.video_id)
+        .await
+        .ok()
+        .flatten();
+
+    let origins = check_origins(state, client, &video, is_nsfw, check_gcs).await;
+    let now = Utc::now().timestamp();
+    let mut healed_at = previous.and_then(|previous| previous.healed_at);
+
+    let outcome = if !origins.values().any(is_missing) || dry_run {
+        HealOutcome::NotNeeded
+    } else if healed_at.is_some_and(|at| now - at < HEAL_COOLDOWN_SECS) {
+        HealOutcome::Skipped
+    } else {
+        match heal(state, &video, is_nsfw, &origins).await {
+            Ok(true) => {
+                healed_at = Some(now);
+                HealOutcome::Enqueued
+            }
+            Ok(false) => HealOutcome::NotNeeded,
+            Err(e) => {
+                log::error!("Failed to heal video {}: {e:?}", video.video_id);
+                HealOutcome::Failed
+            }
+        }
+    };
+
+    let record = VideoAvailability {
+        video_id: video.video_id.clone(),
+        publisher_user_id: video.publisher_user_id,
+        origins,
+        checked_at: now,
+        healed_at,
+    };
+    if let Err(e) = state
+        .kvrocks_client
+        .put(&tables::VIDEO_AVAILABILITY, &video.video_id, &record)
+        .await
+    {
+        log::warn!("Failed to store availability of {}: {e:?}", video.video_id);
+    }
+    (record, outcome)
+}
+
+fn summarize(results: &[(VideoAvailability, HealOutcome)]) -> VideoAvailabilityAuditSummary {
+    let mut summary = VideoAvailabilityAuditSummary {
+        sampled: results.len(),
+        ..Default::default()
+    };
+    for (record, outcome) in results {
+        let mut missing = false;
+        for (origin, status) in &record.origins {
+            if is_missing(status) {
+                missing = true;
+                *summary.missing_by_origin.entry(origin.clone()).or_default() += 1;
+            } else if !status.available {
+                summary.inconclusive += 1;
+            }
+        }
+        summary.missing_videos += missing as usize;
+        match outcome {
+            HealOutcome::Enqueued => summary.heals_enqueued += 1,
+            HealOutcome::Failed => summary.heals_failed += 1,
+            HealOutcome::Skipped => summary.heals_skipped += 1,
+            HealOutcome::NotNeeded => {}
+        }
+    }
+    summary
+}
+
+fn alert_text(
+    summary: &VideoAvailabilityAuditSummary,
+    results: &[(VideoAvailability, HealOutcome)],
+) -> String {
+    let by_origin = summary
+        .missing_by_origin
+        .iter()
+        .map(|(origin, count)| format!("{origin}: {count}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let missing: Vec<String> = results
+        .iter()
+        .filter_map(|(record, _)| {
+            let origins: Vec<String> = record
+                .origins
+                .iter()
+                .filter(|(_, status)| is_missing(status))
+                .map(|(origin, _)| origin.clone())
+                .collect();
+            (!origins.is_empty()).then(|| format!("• {} ({})", record.video_id, origins.join(", ")))
+        })
+        .collect();
+
+    let mut text = format!(
+        "*Video availability audit*: {} of {} sampled videos are missing copies ({by_origin}). \
+         Re-uploads enqueued: {}, failed: {}, in cooldown: {}.",
+        summary.missing_videos,
+        summary.sampled,
+        summary.heals_enqueued,
+        summary.heals_failed,
+        summary.heals_skipped
+    );
+    for line in missing.iter().take(ALERT_MAX_LISTED) {
+        text.push('\n');
+        text.push_str(line);
+    }
+    if missing.len() > ALERT_MAX_LISTED {
+        text.push_str(&format!("\n…and {} more", missing.len() - ALERT_MAX_LISTED));
+    }
+    text
+}
+
+pub async fn run_audit(
+    state: &AppState,
+    request: &VideoAvailabilityAuditRequest,
+) -> Result<VideoAvailabilityAuditSummary> {
+    let sample_size = request
+        .sample_size
+        .unwrap_or(DEFAULT_SAMPLE_SIZE)
+        .clamp(1, MAX_SAMPLE_SIZE);
+    let lookback_hours = request
+        .lookback_hours
+        .unwrap_or(DEFAULT_LOOKBACK_HOURS)
+        .max(1);
+    let videos = sample_recent_videos(state, sample_size, lookback_hours).await?;
+
+    let client = Client::new();
+    let results: Vec<(VideoAvailability, HealOutcome)> = stream::iter(videos)
+        .map(|video| audit_video(state, &client, video, request.check_gcs, request.dry_run))
+        .buffer_unordered(CHECK_CONCURRENCY)
+        .collect()
+        .await;
+
+    let summary = summarize(&results);
+    if summary.missing_videos > 0 {
+        if let Some(url) = VIDEO_AVAILABILITY_WEBHOOK_URL.as_deref() {
+            let text = alert_text(&summary, &results);
+            if let Err(e) = send_message_gchat_webhook(url, json!({ "text": text })).await {
+                log::error!("Failed to send video availability alert: {e:?}");
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// QStash job: re-upload a video missing from GCS. Errors make QStash retry.
+#[utoipa::path(
+    post,
+    path = "/video_availability/gcs_reupload",
+    tag = "qstash",
+    request_body = GcsReuploadRequest,
+    responses(
+        (status = 200, description = "Video re-uploaded to GCS"),
+        (status = 500, description = "Re-upload failed", body = ApiErrorBody)
+    )
+)]
+#[instrument(skip(state))]
+pub async fn gcs_reupload_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<GcsReuploadRequest>,
+) -> Result<(), ApiError> {
+    crate::events::event::upload_gcs_from_storj(
+        &state,
+        &request.video_id,
+        &request.publisher_user_id,
+        request.post_id,
+        &Utc::now().to_rfc3339(),
+        request.is_nsfw,
+    )
+    .await
+    .map_err(|e| {
+        ApiError::Internal(format!("GCS re-upload of {} failed: {e}", request.video_id))
+    })?;
+    log::info!("Re-uploaded video {} to GCS", request.video_id);
+    Ok(())
+}
+
+/// QStash scheduled job: audit storage origins of recent uploads
+#[utoipa::path(
+    post,
+    path = "/video_availability_audit",
+    tag = "qstash",
+    request_body = VideoAvailabilityAuditRequest,
+    responses(
+        (status = 200, description = "Audit summary", body = VideoAvailabilityAuditSummary),
+        (status = 500, description = "Audit failed", body = ApiErrorBody)
+    )
+)]
+#[instrument(skip(state))]
+pub async fn video_availability_audit_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<VideoAvailabilityAuditRequest>,
+) -> impl IntoResponse {
+    match run_audit(&state, &request).await {
+        Ok(summary) => {
+            log::info!("Video availability audit completed: {:?}", summary);
+            (StatusCode::OK, Json(summary)).into_response()
+        }
+        Err(e) => {
+            log::error!("Video availability audit failed: {:?}", e);
+            ApiError::Internal(format!("Video availability audit failed: {}", e)).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(http_status: Option<u16>) -> OriginAvailability {
+        OriginAvailability {
+            available: http_status.is_some_and(|status| (200..300).contains(&status)),
+            http_status,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let record = |video_id: &str, storj, cloudflare| VideoAvailability {
+            video_id: video_id.to_string(),
+            publisher_user_id: "2vxsx-fae".to_string(),
+            origins: BTreeMap::from([
+                (Origin::Storj.as_str().to_string(), status(storj)),
+                (
+                    Origin::CloudflareStream.as_str().to_string(),
+                    status(cloudflare),
+                ),
+            ]),
+            checked_at: 0,
+            healed_at: None,
+        };
+        let results = vec![
+            (record("a", Some(200), Some(200)), HealOutcome::NotNeeded),
+            (record("b", Some(404), Some(200)), HealOutcome::Enqueued),
+            (record("c", Some(404), Some(410)), HealOutcome::Skipped),
+            (record("d", None, Some(503)), HealOutcome::NotNeeded),
+        ];
+
+        let summary = summarize(&results);
+        assert_eq!(summary.sampled, 4);
+        assert_eq!(summary.missing_videos, 2);
+        assert_eq!(summary.missing_by_origin["storj"], 2);
+        assert_eq!(summary.missing_by_origin["cloudflare_stream"], 1);
+        assert_eq!(summary.inconclusive, 2);
+        assert_eq!(summary.heals_enqueued, 1);
+        assert_eq!(summary.heals_skipped, 1);
+
+        let text = alert_text(&summary, &results);
+        assert!(text.contains("2 of 4"));
+        assert!(text.contains("• c (cloudflare_stream, storj)"));
+        assert!(!text.contains("• a"));
+    }
+}
@@ -1,3 +1,6 @@
 pub mod nsfw_api;
 pub mod queue;
+pub mod thumbnails;
+pub mod transcode;
+pub mod transcribe;
 pub mod worker;
@@ -5,6 +5,8 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use thiserror::Error;
 
+use crate::pipeline::telemetry;
+
 type HmacSha256 = Hmac<Sha256>;
 
 #[derive(Clone)]
@@ -116,6 +118,7 @@ impl NsfwApiClient {
             .join(path.trim_start_matches('/'))
             .map_err(|e| NsfwApiError::Terminal(format!("invalid detect URL: {e}")))?;
 
+        telemetry::count_provider_call();
         let response = self
             .client
             .post(url)
@@ -140,6 +143,7 @@ impl NsfwApiClient {
             .join(path.trim_start_matches('/'))
             .map_err(|e| NsfwApiError::Terminal(format!("invalid status URL: {e}")))?;
 
+        telemetry::count_provider_call();
         let response = self
             .client
             .get(url)
@@ -9,6 +9,10 @@ use crate::yral_auth::dragonfly::DragonflyPool;
 const SCHEDULED_KEY: &str = "offchain:video_processing:scheduled";
 const JOB_KEY_PREFIX: &str = "offchain:video_processing:job";
 const LOCK_KEY_PREFIX: &str = "offchain:video_processing:lock";
+const STREAM_READY_KEY_PREFIX: &str = "offchain:video_processing:stream_ready";
+/// Transcode-complete markers outlive any realistic gap between the Cloudflare
+/// webhook and the upload event
+const STREAM_READY_TTL_SECS: u64 = 24 * 60 * 60;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -102,7 +106,7 @@ impl VideoProcessingJob {
 
 pub async fn enqueue_video_processing_job(
     pool: &Arc<DragonflyPool>,
-    job: VideoProcessingJob,
+    mut job: VideoProcessingJob,
 ) -> Result<()> {
     let mut conn = pool.get().await?;
     let key = job_key(&job.video_id);
@@ -128,6 +132,12 @@ pub async fn enqueue_video_processing_job(
         return Ok(());
     }
 
+    // Transcode already finished before the upload event arrived; skip the fallback delay.
+    let stream_ready: bool = conn.exists(stream_ready_key(&job.video_id)).await?;
+    if stream_ready && job.phase == VideoProcessingPhase::DedupPending {
+        job.next_run_at = job.next_run_at.min(chrono::Utc::now().timestamp());
+    }
+
     let payload = serde_json::to_string(&job)?;
     let _: () = conn.set(&key, payload).await?;
     let _: () = conn
@@ -218,6 +228,43 @@ pub async fn schedule_nsfw_handoff_job(
     save_and_schedule(pool, &mut job).await
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamReadyOutcome {
+    /// Dedup was waiting on the fallback delay and is now due
+    Expedited,
+    /// The job is already due or past dedup
+    NotWaiting,
+    /// No job yet; the marker makes the upcoming enqueue due immediately
+    MarkedReady,
+}
+
+/// Records that the video finished transcoding and pulls a waiting dedup run
+/// forward, so processing no longer depends on the fixed dedup delay.
+pub async fn mark_stream_ready(
+    pool: &Arc<DragonflyPool>,
+    video_id: &str,
+) -> Result<StreamReadyOutcome> {
+    {
+        let mut conn = pool.get().await?;
+        let _: () = conn
+            .set_ex(stream_ready_key(video_id), 1, STREAM_READY_TTL_SECS)
+            .await?;
+    }
+
+    let Some(mut job) = load_job(pool, video_id).await? else {
+        return Ok(StreamReadyOutcome::MarkedReady);
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    if job.phase != VideoProcessingPhase::DedupPending || job.next_run_at <= now {
+        return Ok(StreamReadyOutcome::NotWaiting);
+    }
+
+    job.next_run_at = now;
+    save_and_schedule(pool, &mut job).await?;
+    Ok(StreamReadyOutcome::Expedited)
+}
+
 pub async fn fetch_due_video_ids(
     pool: &Arc<DragonflyPool>,
     now_timestamp: i64,
@@ -236,6 +283,12 @@ pub async fn fetch_due_video_ids(
     Ok(ids)
 }
 
+pub async fn scheduled_job_count(pool: &Arc<DragonflyPool>) -> Result<u64> {
+    let mut conn = pool.get().await?;
+    let count: u64 = conn.zcard(SCHEDULED_KEY).await?;
+    Ok(count)
+}
+
 pub async fn remove_from_schedule(pool: &Arc<DragonflyPool>, video_id: &str) -> Result<()> {
     let mut conn = pool.get().await?;
     let _: () = conn.zrem(SCHEDULED_KEY, video_id).await?;
@@ -278,6 +331,10 @@ fn lock_key(video_id: &str) -> String {
     format!("{LOCK_KEY_PREFIX}:{video_id}")
 }
 
+fn stream_ready_key(video_id: &str) -> String {
+    format!("{STREAM_READY_KEY_PREFIX}:{video_id}")
+}
+
 pub fn nsfw_job_id(video_id: &str, policy_version: &str, source_object_version: &str) -> String {
     format!("nsfw:{video_id}:{policy_version}:{source_object_version}")
 }
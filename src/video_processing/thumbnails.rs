@@ -0,0 +1,260 @@
+use std::{process::Command, sync::Arc};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+    kvrocks::{tables, VideoThumbnail, VideoThumbnails},
+    pipeline::{telemetry, Step},
+    setup_context,
+    utils::env_parse,
+};
+
+#[derive(Debug, Clone)]
+pub struct ThumbnailConfig {
+    pub enabled: bool,
+    /// Offsets into the video, in seconds; offsets past the end are skipped
+    pub timestamps_secs: Vec<f64>,
+    pub width: u32,
+    pub bucket: String,
+}
+
+impl ThumbnailConfig {
+    pub fn from_env() -> Self {
+        let timestamps_secs = std::env::var("VIDEO_THUMBNAIL_TIMESTAMPS_SECS")
+            .ok()
+            .map(|value| parse_timestamps(&value))
+            .filter(|timestamps| !timestamps.is_empty())
+            .unwrap_or_else(|| vec![0.5, 2.0, 5.0]);
+
+        Self {
+            enabled: env_parse("VIDEO_THUMBNAILS_ENABLED", true),
+            timestamps_secs,
+            width: env_parse("VIDEO_THUMBNAIL_WIDTH", 480),
+            bucket: std::env::var("VIDEO_THUMBNAIL_BUCKET")
+                .unwrap_or_else(|_| "yral-video-thumbnails".to_string()),
+        }
+    }
+}
+
+fn parse_timestamps(value: &str) -> Vec<f64> {
+    let mut timestamps: Vec<f64> = value
+        .split(',')
+        .filter_map(|part| part.trim().parse::<f64>().ok())
+        .filter(|ts| ts.is_finite() && *ts >= 0.0)
+        .collect();
+    timestamps.sort_by(|a, b| a.total_cmp(b));
+    timestamps.dedup();
+    timestamps
+}
+
+fn thumbnail_object_path(video_id: &str, index: usize) -> String {
+    format!("{video_id}/thumb-{index}.jpg")
+}
+
+/// Grabs a single JPEG frame at `timestamp_secs`. Returns `None` when the
+/// offset is past the end of the video.
+async fn extract_thumbnail(
+    video_url: &str,
+    timestamp_secs: f64,
+    width: u32,
+) -> Result<Option<Vec<u8>>> {
+    let video_url = video_url.to_string();
+    let output = tokio::task::spawn_blocking(move || {
+        Command::new("ffmpeg")
+            .arg("-loglevel")
+            .arg("error")
+            .arg("-ss")
+            .arg(format!("{timestamp_secs:.3}"))
+            .arg("-i")
+            .arg(&video_url)
+            .arg("-frames:v")
+            .arg("1")
+            .arg("-vf")
+            .arg(format!("scale={width}:-2"))
+            .arg("-f")
+            .arg("image2")
+            .arg("-c:v")
+            .arg("mjpeg")
+            .arg("pipe:1")
+            .output()
+    })
+    .await??;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "ffmpeg failed to extract thumbnail at {timestamp_secs}s: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok((!output.stdout.is_empty()).then_some(output.stdout))
+}
+
+/// Extracts thumbnails at the configured offsets, uploads them to GCS and
+/// records their URLs in kvrocks.
+#[instrument(skip(state, config))]
+pub async fn generate_thumbnails(
+    state: &AppState,
+    config: &ThumbnailConfig,
+    video_id: &str,
+    video_url: &str,
+) -> Result<VideoThumbnails> {
+    setup_context!(video_id, Step::Thumbnails);
+
+    let mut frames = Vec::new();
+    for &timestamp_secs in &config.timestamps_secs {
+        match extract_thumbnail(video_url, timestamp_secs, config.width).await? {
+            Some(frame) => frames.push((timestamp_secs, frame)),
+            None => break,
+        }
+    }
+
+    if frames.is_empty() {
+        return Err(anyhow::anyhow!("No thumbnails extracted for {video_id}"));
+    }
+
+    let uploads = frames
+        .into_iter()
+        .enumerate()
+        .map(|(index, (timestamp_secs, frame))| {
+            let path = thumbnail_object_path(video_id, index);
+            let bucket = config.bucket.clone();
+            async move {
+                state
+                    .gcs_client
+                    .object()
+                    .create(&bucket, frame, &path, "image/jpeg")
+                    .await
+                    .with_context(|| format!("Failed to upload thumbnail {path}"))?;
+                Ok::<_, anyhow::Error>(VideoThumbnail {
+                    timestamp_secs,
+                    url: format!("https://storage.googleapis.com/{bucket}/{path}"),
+                    width: config.width,
+                })
+            }
+        });
+
+    let thumbnails = futures::future::try_join_all(uploads).await?;
+
+    let record = VideoThumbnails {
+        video_id: video_id.to_string(),
+        thumbnails,
+        generated_at: chrono::Utc::now().timestamp(),
+    };
+    state
+        .kvrocks_client
+        .put(&tables::VIDEO_THUMBNAILS, &record.video_id, &record)
+        .await?;
+
+    log::info!(
+        "Generated {} thumbnails for {}",
+        record.thumbnails.len(),
+        video_id
+    );
+
+    Ok(record)
+}
+
+/// Fire-and-forget thumbnail generation for the post-upload pipeline. A
+/// failure only costs thumbnails and never blocks moderation.
+pub fn spawn_thumbnail_generation(state: Arc<AppState>, video_id: String, video_url: String) {
+    let config = ThumbnailConfig::from_env();
+    if !config.enabled {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let generated = telemetry::measure(
+            &state,
+            &video_id,
+            Step::Thumbnails,
+            generate_thumbnails(&state, &config, &video_id, &video_url),
+        )
+        .await;
+        if let Err(err) = generated {
+            log::error!("Thumbnail generation failed for {video_id}: {err:?}");
+        }
+    });
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ThumbnailResponse {
+    /// Offset into the video in seconds
+    pub timestamp_secs: f64,
+    pub url: String,
+    pub width: u32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VideoThumbnailsResponse {
+    pub video_id: String,
+    pub thumbnails: Vec<ThumbnailResponse>,
+    /// Unix timestamp of generation
+    pub generated_at: i64,
+}
+
+impl From<VideoThumbnails> for VideoThumbnailsResponse {
+    fn from(record: VideoThumbnails) -> Self {
+        Self {
+            video_id: record.video_id,
+            thumbnails: record
+                .thumbnails
+                .into_iter()
+                .map(|thumbnail| ThumbnailResponse {
+                    timestamp_secs: thumbnail.timestamp_secs,
+                    url: thumbnail.url,
+                    width: thumbnail.width,
+                })
+                .collect(),
+            generated_at: record.generated_at,
+        }
+    }
+}
+
+/// Get generated thumbnails for a video
+#[utoipa::path(
+    get,
+    path = "/{video_id}/thumbnails",
+    params(
+        ("video_id" = String, Path, description = "Video ID")
+    ),
+    tag = "videos",
+    responses(
+        (status = 200, description = "Thumbnails for the video", body = VideoThumbnailsResponse),
+        (status = 404, description = "Thumbnails not generated yet", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+#[instrument(skip(state))]
+pub async fn get_video_thumbnails_handler(
+    State(state): State<Arc<AppState>>,
+    Path(video_id): Path<String>,
+) -> Result<Json<VideoThumbnailsResponse>, ApiError> {
+    let record = state
+        .kvrocks_client
+        .get(&tables::VIDEO_THUMBNAILS, &video_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("No thumbnails for video {video_id}")))?;
+
+    Ok(Json(record.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamps() {
+        assert_eq!(parse_timestamps("5, 0.5,2,2,-1,abc"), vec![0.5, 2.0, 5.0]);
+        assert!(parse_timestamps("").is_empty());
+    }
+}
@@ -0,0 +1,602 @@
+use std::{
+    collections::HashMap,
+    path::{Path as FsPath, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use ffmpeg_next::{
+    codec, encoder, format, media,
+    software::scaling,
+    util::{format::Pixel, frame, rational::Rational},
+    Dictionary, Packet,
+};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    duplicate_video::phash::{download_video_from_storj, extract_metadata},
+    error::{ApiError, ApiErrorBody},
+    kvrocks::{tables, VideoRendition, VideoRenditions},
+    pipeline::{telemetry, Step},
+    setup_context,
+    utils::{env_parse, gcs_upload::ResumableUpload},
+};
+
+const HLS_SEGMENT_SECS: u32 = 4;
+const MASTER_PLAYLIST: &str = "master.m3u8";
+/// Concurrent segment uploads per rendition directory
+const UPLOAD_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RenditionProfile {
+    pub name: &'static str,
+    /// Length of the shorter side, so portrait and landscape sources of the
+    /// same quality get the same rung
+    pub short_edge: u32,
+    pub video_bitrate_kbps: u32,
+    /// Used for the advertised bandwidth; the source AAC track is copied as is
+    pub audio_bitrate_kbps: u32,
+}
+
+pub const RENDITION_PROFILES: &[RenditionProfile] = &[
+    RenditionProfile {
+        name: "480p",
+        short_edge: 480,
+        video_bitrate_kbps: 1000,
+        audio_bitrate_kbps: 96,
+    },
+    RenditionProfile {
+        name: "720p",
+        short_edge: 720,
+        video_bitrate_kbps: 2500,
+        audio_bitrate_kbps: 128,
+    },
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TranscodeVideoRequest {
+    pub video_id: String,
+    pub publisher_user_id: String,
+}
+
+pub fn transcoding_enabled() -> bool {
+    env_parse("VIDEO_TRANSCODE_ENABLED", true)
+}
+
+fn renditions_bucket() -> String {
+    std::env::var("VIDEO_RENDITIONS_BUCKET").unwrap_or_else(|_| "yral-video-renditions".to_string())
+}
+
+/// Profiles larger than the source's short edge are dropped so we don't
+/// upscale. A source smaller than every profile still gets the lowest one so
+/// it has HLS at all.
+fn applicable_profiles(source_width: u32, source_height: u32) -> Vec<RenditionProfile> {
+    let short_edge = source_width.min(source_height);
+    let profiles: Vec<RenditionProfile> = RENDITION_PROFILES
+        .iter()
+        .copied()
+        .filter(|profile| profile.short_edge <= short_edge)
+        .collect();
+
+    if profiles.is_empty() {
+        RENDITION_PROFILES.iter().copied().take(1).collect()
+    } else {
+        profiles
+    }
+}
+
+/// Output size with the shorter side at `short_edge`, keeping the source
+/// aspect ratio and rounding both sides to even as libx264 requires
+fn rendition_size(source_width: u32, source_height: u32, short_edge: u32) -> (u32, u32) {
+    let even = |side: f64| {
+        let side = side.round() as u32;
+        side + side % 2
+    };
+    if source_width == 0 || source_height == 0 {
+        return (0, 0);
+    }
+    if source_width <= source_height {
+        let height = source_height as f64 * short_edge as f64 / source_width as f64;
+        (even(short_edge as f64), even(height))
+    } else {
+        let width = source_width as f64 * short_edge as f64 / source_height as f64;
+        (even(width), even(short_edge as f64))
+    }
+}
+
+fn master_playlist(renditions: &[(RenditionProfile, u32, u32)]) -> String {
+    let mut playlist = String::from("#EXTM3U\n#EXT-X-VERSION:3\n");
+    for (profile, width, height) in renditions {
+        let bandwidth = (profile.video_bitrate_kbps + profile.audio_bitrate_kbps) * 1000;
+        playlist.push_str(&format!(
+            "#EXT-X-STREAM-INF:BANDWIDTH={bandwidth},RESOLUTION={width}x{height},NAME=\"{}\"\n{}.m3u8\n",
+            profile.name, profile.name
+        ));
+    }
+    playlist
+}
+
+/// Decodes the source video, scales it and encodes it with libx264
+struct VideoTranscoder {
+    input_index: usize,
+    output_index: usize,
+    time_base: Rational,
+    decoder: codec::decoder::Video,
+    encoder: codec::encoder::video::Encoder,
+    scaler: scaling::Context,
+}
+
+impl VideoTranscoder {
+    fn new(
+        ictx: &format::context::Input,
+        octx: &mut format::context::Output,
+        profile: RenditionProfile,
+        (width, height): (u32, u32),
+    ) -> Result<Self> {
+        let input = ictx
+            .streams()
+            .best(media::Type::Video)
+            .context("Source has no video stream")?;
+        let time_base = input.time_base();
+        let decoder = codec::context::Context::from_parameters(input.parameters())?
+            .decoder()
+            .video()?;
+
+        let x264 = encoder::find_by_name("libx264").context("libx264 encoder not available")?;
+        let global_header = octx
+            .format()
+            .flags()
+            .contains(format::flag::Flags::GLOBAL_HEADER);
+        let mut output = octx.add_stream(x264)?;
+
+        let mut encoder = codec::context::Context::new_with_codec(x264)
+            .encoder()
+            .video()?;
+        encoder.set_width(width);
+        encoder.set_height(height);
+        encoder.set_format(Pixel::YUV420P);
+        encoder.set_time_base(time_base);
+        let frame_rate = input.avg_frame_rate();
+        if frame_rate.numerator() > 0 && frame_rate.denominator() > 0 {
+            encoder.set_frame_rate(Some(frame_rate));
+            // A keyframe at every segment boundary keeps segments even
+            let fps = frame_rate.numerator() as f64 / frame_rate.denominator() as f64;
+            encoder.set_gop((fps * HLS_SEGMENT_SECS as f64).round() as u32);
+        }
+        encoder.set_bit_rate(profile.video_bitrate_kbps as usize * 1000);
+        encoder.set_max_bit_rate(profile.video_bitrate_kbps as usize * 1070);
+        if global_header {
+            encoder.set_flags(codec::flag::Flags::GLOBAL_HEADER);
+        }
+
+        let mut options = Dictionary::new();
+        options.set("preset", "veryfast");
+        options.set("profile", "main");
+        options.set(
+            "bufsize",
+            &(profile.video_bitrate_kbps as usize * 2000).to_string(),
+        );
+        let encoder = encoder.open_with(options)?;
+        output.set_parameters(&encoder);
+        let output_index = output.index();
+
+        let scaler = scaling::Context::get(
+            decoder.format(),
+            decoder.width(),
+            decoder.height(),
+            Pixel::YUV420P,
+            width,
+            height,
+            scaling::Flags::BICUBIC,
+        )?;
+
+        Ok(Self {
+            input_index: input.index(),
+            output_index,
+            time_base,
+            decoder,
+            encoder,
+            scaler,
+        })
+    }
+
+    fn send_packet(&mut self, packet: &Packet, octx: &mut format::context::Output) -> Result<()> {
+        self.decoder.send_packet(packet)?;
+        self.encode_decoded(octx)
+    }
+
+    fn finish(&mut self, octx: &mut format::context::Output) -> Result<()> {
+        self.decoder.send_eof()?;
+        self.encode_decoded(octx)?;
+        self.encoder.send_eof()?;
+        self.write_encoded(octx)
+    }
+
+    fn encode_decoded(&mut self, octx: &mut format::context::Output) -> Result<()> {
+        let mut decoded = frame::Video::empty();
+        while self.decoder.receive_frame(&mut decoded).is_ok() {
+            let mut scaled = frame::Video::empty();
+            self.scaler.run(&decoded, &mut scaled)?;
+            scaled.set_pts(decoded.timestamp());
+            self.encoder.send_frame(&scaled)?;
+            self.write_encoded(octx)?;
+        }
+        Ok(())
+    }
+
+    fn write_encoded(&mut self, octx: &mut format::context::Output) -> Result<()> {
+        let output_time_base = octx
+            .stream(self.output_index)
+            .context("Missing video output stream")?
+            .time_base();
+        let mut encoded = Packet::empty();
+        while self.encoder.receive_packet(&mut encoded).is_ok() {
+            encoded.set_stream(self.output_index);
+            encoded.rescale_ts(self.time_base, output_time_base);
+            encoded.write_interleaved(octx)?;
+        }
+        Ok(())
+    }
+}
+
+/// Copies the source AAC track into the rendition. Uploads are AAC in
+/// practice; any other codec is dropped with a warning so the rendition is
+/// still produced, just without audio.
+struct AudioCopy {
+    input_index: usize,
+    output_index: usize,
+    time_base: Rational,
+}
+
+impl AudioCopy {
+    fn new(
+        ictx: &format::context::Input,
+        octx: &mut format::context::Output,
+    ) -> Result<Option<Self>> {
+        let Some(input) = ictx.streams().best(media::Type::Audio) else {
+            return Ok(None);
+        };
+        let parameters = input.parameters();
+        if parameters.id() != codec::Id::AAC {
+            log::warn!(
+                "Dropping {:?} audio track, only AAC can be copied into HLS renditions",
+                parameters.id()
+            );
+            return Ok(None);
+        }
+
+        let mut output = octx.add_stream(encoder::find(codec::Id::None))?;
+        output.set_parameters(parameters);
+        // The source container's tag doesn't apply to MPEG-TS, and
+        // ffmpeg_next has no safe setter for it.
+        // SAFETY: `as_mut_ptr` points at the `AVCodecParameters` owned by the
+        // stream just added to `octx`, which outlives this block and isn't
+        // aliased while `output` holds the mutable borrow of `octx`.
+        unsafe {
+            (*output.parameters().as_mut_ptr()).codec_tag = 0;
+        }
+
+        Ok(Some(Self {
+            input_index: input.index(),
+            output_index: output.index(),
+            time_base: input.time_base(),
+        }))
+    }
+
+    fn write(&self, mut packet: Packet, octx: &mut format::context::Output) -> Result<()> {
+        let output_time_base = octx
+            .stream(self.output_index)
+            .context("Missing audio output stream")?
+            .time_base();
+        packet.set_stream(self.output_index);
+        packet.rescale_ts(self.time_base, output_time_base);
+        packet.set_position(-1);
+        packet.write_interleaved(octx)?;
+        Ok(())
+    }
+}
+
+/// Encodes one rendition into `<name>.m3u8` and its segments. Blocking.
+fn transcode_rendition(
+    source: &FsPath,
+    output_dir: &FsPath,
+    profile: RenditionProfile,
+    size: (u32, u32),
+) -> Result<()> {
+    let mut ictx = format::input(&source).context("Failed to open source video")?;
+    let playlist = output_dir.join(format!("{}.m3u8", profile.name));
+    let mut octx = format::output_as(&playlist, "hls")
+        .with_context(|| format!("Failed to create {}", playlist.display()))?;
+
+    let mut video = VideoTranscoder::new(&ictx, &mut octx, profile, size)?;
+    let audio = AudioCopy::new(&ictx, &mut octx)?;
+
+    let mut options = Dictionary::new();
+    options.set("hls_time", &HLS_SEGMENT_SECS.to_string());
+    options.set("hls_playlist_type", "vod");
+    options.set(
+        "hls_segment_filename",
+        &output_dir
+            .join(format!("{}_%03d.ts", profile.name))
+            .to_string_lossy(),
+    );
+    octx.write_header_with(options)?;
+
+    for (stream, packet) in ictx.packets() {
+        if stream.index() == video.input_index {
+            video.send_packet(&packet, &mut octx)?;
+        } else if let Some(audio) = audio
+            .as_ref()
+            .filter(|audio| audio.input_index == stream.index())
+        {
+            audio.write(packet, &mut octx)?;
+        }
+    }
+    video.finish(&mut octx)?;
+    octx.write_trailer()?;
+
+    Ok(())
+}
+
+fn content_type_for(path: &FsPath) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("m3u8") => "application/vnd.apple.mpegurl",
+        Some("ts") => "video/mp2t",
+        _ => "application/octet-stream",
+    }
+}
+
+async fn upload_output_dir(
+    state: &AppState,
+    bucket: &str,
+    prefix: &str,
+    output_dir: &FsPath,
+) -> Result<()> {
+    let mut files = Vec::new();
+    let mut entries = tokio::fs::read_dir(output_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_file() {
+            files.push(entry.path());
+        }
+    }
+
+    let uploads = stream::iter(files).map(|path| async move {
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .context("Invalid rendition file name")?
+            .to_string();
+        let object_path = format!("{prefix}/{file_name}");
+        let bytes = tokio::fs::read(&path).await?;
+        ResumableUpload {
+            bucket,
+            name: &object_path,
+            content_type: content_type_for(&path),
+            metadata: HashMap::new(),
+        }
+        .upload(state, bytes.into())
+        .await
+        .with_context(|| format!("Failed to upload {object_path}"))?;
+        Ok::<_, anyhow::Error>(())
+    });
+
+    uploads
+        .buffer_unordered(UPLOAD_CONCURRENCY)
+        .try_collect::<Vec<()>>()
+        .await?;
+    Ok(())
+}
+
+async fn transcode_in_dir(
+    state: &AppState,
+    req: &TranscodeVideoRequest,
+    work_dir: &FsPath,
+) -> Result<VideoRenditions> {
+    let source = work_dir.join(format!("{}.mp4", req.video_id));
+    download_video_from_storj(&req.publisher_user_id, &req.video_id, &source).await?;
+
+    let metadata = extract_metadata(&source, req.video_id.clone())?;
+    let output_dir: PathBuf = work_dir.join("hls");
+    tokio::fs::create_dir_all(&output_dir).await?;
+
+    let mut produced = Vec::new();
+    for profile in applicable_profiles(metadata.width, metadata.height) {
+        let size = rendition_size(metadata.width, metadata.height, profile.short_edge);
+        let (source, output_dir) = (source.clone(), output_dir.clone());
+        tokio::task::spawn_blocking(move || {
+            transcode_rendition(&source, &output_dir, profile, size)
+        })
+        .await??;
+        produced.push((profile, size.0, size.1));
+    }
+
+    tokio::fs::write(output_dir.join(MASTER_PLAYLIST), master_playlist(&produced)).await?;
+
+    let bucket = renditions_bucket();
+    let prefix = format!("{}/hls", req.video_id);
+    upload_output_dir(state, &bucket, &prefix, &output_dir).await?;
+
+    let base_url = format!("https://storage.googleapis.com/{bucket}/{prefix}");
+    Ok(VideoRenditions {
+        video_id: req.video_id.clone(),
+        master_playlist_url: format!("{base_url}/{MASTER_PLAYLIST}"),
+        renditions: produced
+            .into_iter()
+            .map(|(profile, width, height)| VideoRendition {
+                name: profile.name.to_string(),
+                width,
+                height,
+                bitrate_kbps: profile.video_bitrate_kbps + profile.audio_bitrate_kbps,
+                playlist_url: format!("{base_url}/{}.m3u8", profile.name),
+            })
+            .collect(),
+        source_width: metadata.width,
+        source_height: metadata.height,
+        transcoded_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+/// QStash job: transcodes the original into HLS renditions for adaptive
+/// playback and records them in kvrocks. Errors make QStash retry the job.
+#[utoipa::path(
+    post,
+    path = "/transcode_video",
+    request_body = TranscodeVideoRequest,
+    responses(
+        (status = 200, description = "Renditions stored"),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state))]
+pub async fn transcode_video_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<TranscodeVideoRequest>,
+) -> Result<(), ApiError> {
+    setup_context!(&req.video_id, Step::Transcode);
+
+    if let Some(existing) = state
+        .kvrocks_client
+        .get(&tables::VIDEO_RENDITIONS, &req.video_id)
+        .await?
+    {
+        log::info!(
+            "Renditions already exist for {} ({} renditions), skipping",
+            req.video_id,
+            existing.renditions.len()
+        );
+        return Ok(());
+    }
+
+    let work_dir = std::env::temp_dir().join(format!("transcode_{}", Uuid::new_v4()));
+    tokio::fs::create_dir_all(&work_dir)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to create work dir: {e}")))?;
+
+    let result = telemetry::measure(
+        &state,
+        &req.video_id,
+        Step::Transcode,
+        transcode_in_dir(&state, &req, &work_dir),
+    )
+    .await;
+    let _ = tokio::fs::remove_dir_all(&work_dir).await;
+    let renditions = result?;
+
+    state
+        .kvrocks_client
+        .put(&tables::VIDEO_RENDITIONS, &renditions.video_id, &renditions)
+        .await?;
+
+    log::info!(
+        "Transcoded {} into {} renditions",
+        req.video_id,
+        renditions.renditions.len()
+    );
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RenditionResponse {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub bitrate_kbps: u32,
+    pub playlist_url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VideoRenditionsResponse {
+    pub video_id: String,
+    /// HLS master playlist for adaptive playback
+    pub master_playlist_url: String,
+    pub renditions: Vec<RenditionResponse>,
+}
+
+impl From<VideoRenditions> for VideoRenditionsResponse {
+    fn from(record: VideoRenditions) -> Self {
+        Self {
+            video_id: record.video_id,
+            master_playlist_url: record.master_playlist_url,
+            renditions: record
+                .renditions
+                .into_iter()
+                .map(|rendition| RenditionResponse {
+                    name: rendition.name,
+                    width: rendition.width,
+                    height: rendition.height,
+                    bitrate_kbps: rendition.bitrate_kbps,
+                    playlist_url: rendition.playlist_url,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Get HLS renditions for a video
+#[utoipa::path(
+    get,
+    path = "/{video_id}/renditions",
+    params(
+        ("video_id" = String, Path, description = "Video ID")
+    ),
+    tag = "videos",
+    responses(
+        (status = 200, description = "Renditions for the video", body = VideoRenditionsResponse),
+        (status = 404, description = "Video not transcoded yet", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    )
+)]
+#[instrument(skip(state))]
+pub async fn get_video_renditions_handler(
+    State(state): State<Arc<AppState>>,
+    Path(video_id): Path<String>,
+) -> Result<Json<VideoRenditionsResponse>, ApiError> {
+    let record = state
+        .kvrocks_client
+        .get(&tables::VIDEO_RENDITIONS, &video_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("No renditions for video {video_id}")))?;
+
+    Ok(Json(record.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rendition_ladder() {
+        let names = |width: u32, height: u32| -> Vec<&str> {
+            applicable_profiles(width, height)
+                .into_iter()
+                .map(|p| p.name)
+                .collect()
+        };
+        assert_eq!(names(1920, 1080), vec!["480p", "720p"]);
+        assert_eq!(names(1280, 720), vec!["480p", "720p"]);
+        assert_eq!(names(960, 540), vec!["480p"]);
+        assert_eq!(names(640, 360), vec!["480p"]);
+        // Portrait sources are laddered by their width
+        assert_eq!(names(1080, 1920), vec!["480p", "720p"]);
+        assert_eq!(names(540, 960), vec!["480p"]);
+
+        assert_eq!(rendition_size(1920, 1080, 720), (1280, 720));
+        assert_eq!(rendition_size(1080, 1920, 720), (720, 1280));
+        assert_eq!(rendition_size(886, 1920, 480), (480, 1040));
+
+        let playlist = master_playlist(&[(RENDITION_PROFILES[0], 480, 854)]);
+        assert!(playlist.contains("BANDWIDTH=1096000,RESOLUTION=480x854"));
+        assert!(playlist.ends_with("480p.m3u8\n"));
+    }
+}
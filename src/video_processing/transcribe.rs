@@ -0,0 +1,387 @@
+use std::{path::Path, process::Command, sync::Arc};
+
+use anyhow::{Context, Result};
+use axum::{extract::State, Json};
+use google_cloud_bigquery::http::tabledata::insert_all::{InsertAllRequest, Row};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    duplicate_video::phash::download_video_from_storj,
+    error::{ApiError, ApiErrorBody},
+    events::{event::Event, warehouse_events::WarehouseEvent},
+    pipeline::{telemetry, Step},
+    setup_context,
+    utils::env_parse,
+};
+
+pub const VIDEO_TRANSCRIBED_EVENT: &str = "video_transcribed";
+
+/// Per-deployment toggle, off by default since every upload costs an STT call
+pub fn transcription_enabled() -> bool {
+    env_parse("STT_ENABLED", false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SttProvider {
+    OpenAi,
+    Deepgram,
+}
+
+impl SttProvider {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "openai" | "whisper" => Some(SttProvider::OpenAi),
+            "deepgram" => Some(SttProvider::Deepgram),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            SttProvider::OpenAi => "openai",
+            SttProvider::Deepgram => "deepgram",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub text: String,
+    /// Language detected by the provider (BCP-47 / ISO-639-1)
+    pub language: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct SttClient {
+    client: reqwest::Client,
+    provider: SttProvider,
+    api_key: String,
+    model: String,
+}
+
+impl SttClient {
+    /// `None` when speech-to-text is disabled for this deployment
+    /// (`STT_ENABLED=false`, the default) or not configured.
+    pub fn from_env() -> Option<Self> {
+        if !transcription_enabled() {
+            return None;
+        }
+
+        let provider_name = std::env::var("STT_PROVIDER").unwrap_or_else(|_| "openai".into());
+        let Some(provider) = SttProvider::parse(&provider_name) else {
+            log::error!("Unknown STT_PROVIDER '{provider_name}', speech-to-text disabled");
+            return None;
+        };
+        let Ok(api_key) = std::env::var("STT_API_KEY") else {
+            log::error!("STT_ENABLED is set but STT_API_KEY is missing");
+            return None;
+        };
+        let default_model = match provider {
+            SttProvider::OpenAi => "whisper-1",
+            SttProvider::Deepgram => "nova-2",
+        };
+
+        Some(Self {
+            client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(300))
+                .build()
+                .ok()?,
+            provider,
+            api_key,
+            model: std::env::var("STT_MODEL").unwrap_or_else(|_| default_model.to_string()),
+        })
+    }
+
+    pub async fn transcribe(&self, audio: Vec<u8>) -> Result<Transcript> {
+        telemetry::count_provider_call();
+        match self.provider {
+            SttProvider::OpenAi => self.transcribe_openai(audio).await,
+            SttProvider::Deepgram => self.transcribe_deepgram(audio).await,
+        }
+    }
+
+    async fn transcribe_openai(&self, audio: Vec<u8>) -> Result<Transcript> {
+        #[derive(Deserialize)]
+        struct OpenAiTranscription {
+            text: String,
+            language: Option<String>,
+        }
+
+        let file = reqwest::multipart::Part::bytes(audio)
+            .file_name("audio.mp3")
+            .mime_str("audio/mpeg")?;
+        let form = reqwest::multipart::Form::new()
+            .part("file", file)
+            .text("model", self.model.clone())
+            .text("response_format", "verbose_json");
+
+        let response: OpenAiTranscription = self
+            .client
+            .post("https://api.openai.com/v1/audio/transcriptions")
+            .bearer_auth(&self.api_key)
+            .multipart(form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(Transcript {
+            text: response.text,
+            language: response.language,
+        })
+    }
+
+    async fn transcribe_deepgram(&self, audio: Vec<u8>) -> Result<Transcript> {
+        let response: serde_json::Value = self
+            .client
+            .post("https://api.deepgram.com/v1/listen")
+            .query(&[
+                ("model", self.model.as_str()),
+                ("detect_language", "true"),
+                ("punctuate", "true"),
+            ])
+            .header("Authorization", format!("Token {}", self.api_key))
+            .header("Content-Type", "audio/mpeg")
+            .body(audio)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let channel = &response["results"]["channels"][0];
+        Ok(Transcript {
+            text: channel["alternatives"][0]["transcript"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            language: channel["detected_language"].as_str().map(str::to_string),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TranscribeVideoRequest {
+    pub video_id: String,
+    pub publisher_user_id: String,
+}
+
+#[derive(Debug, Serialize)]
+struct VideoTranscriptRow {
+    video_id: String,
+    transcript: String,
+    language: Option<String>,
+    provider: String,
+    model: String,
+    transcribed_at: String,
+}
+
+fn has_audio_stream(video_path: &Path) -> Result<bool> {
+    let ictx = ffmpeg_next::format::input(video_path).context("Failed to open video file")?;
+    let has_audio = ictx
+        .streams()
+        .best(ffmpeg_next::media::Type::Audio)
+        .is_some();
+    Ok(has_audio)
+}
+
+/// Mono 16kHz MP3 keeps uploads well under provider size limits
+async fn extract_audio(video_path: &Path, audio_path: &Path) -> Result<()> {
+    let video_path = video_path.to_path_buf();
+    let audio_path = audio_path.to_path_buf();
+
+    let output = tokio::task::spawn_blocking(move || {
+        Command::new("ffmpeg")
+            .arg("-loglevel")
+            .arg("error")
+            .arg("-y")
+            .arg("-i")
+            .arg(&video_path)
+            .args([
+                "-vn",
+                "-ac",
+                "1",
+                "-ar",
+                "16000",
+                "-c:a",
+                "libmp3lame",
+                "-b:a",
+                "48k",
+            ])
+            .arg(&audio_path)
+            .output()
+    })
+    .await??;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "ffmpeg failed to extract audio: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(())
+}
+
+async fn transcribe_in_dir(
+    stt_client: &SttClient,
+    req: &TranscribeVideoRequest,
+    work_dir: &Path,
+) -> Result<Option<Transcript>> {
+    let video_path = work_dir.join(format!("{}.mp4", req.video_id));
+    download_video_from_storj(&req.publisher_user_id, &req.video_id, &video_path).await?;
+
+    if !has_audio_stream(&video_path)? {
+        return Ok(None);
+    }
+
+    let audio_path = work_dir.join("audio.mp3");
+    extract_audio(&video_path, &audio_path).await?;
+    let audio = tokio::fs::read(&audio_path).await?;
+
+    stt_client.transcribe(audio).await.map(Some)
+}
+
+async fn store_transcript(
+    state: &AppState,
+    stt_client: &SttClient,
+    video_id: &str,
+    transcript: &Transcript,
+) -> Result<()> {
+    let row_data = VideoTranscriptRow {
+        video_id: video_id.to_string(),
+        transcript: transcript.text.clone(),
+        language: transcript.language.clone(),
+        provider: stt_client.provider.as_str().to_string(),
+        model: stt_client.model.clone(),
+        transcribed_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let request = InsertAllRequest {
+        // insert_id makes QStash redeliveries idempotent on the BigQuery side
+        rows: vec![Row {
+            insert_id: Some(format!("transcript-{video_id}")),
+            json: row_data,
+        }],
+        ..Default::default()
+    };
+
+    state
+        .bigquery_client
+        .tabledata()
+        .insert(
+            "hot-or-not-feed-intelligence",
+            "yral_ds",
+            "video_transcripts",
+            &request,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// QStash job: extracts the audio track, transcribes it with the configured
+/// STT provider, stores the transcript in BigQuery and emits
+/// `video_transcribed`.
+#[utoipa::path(
+    post,
+    path = "/transcribe_video",
+    request_body = TranscribeVideoRequest,
+    responses(
+        (status = 200, description = "Transcript stored"),
+        (status = 500, description = "Internal server error", body = ApiErrorBody)
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state))]
+pub async fn transcribe_video_handler(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<TranscribeVideoRequest>,
+) -> Result<(), ApiError> {
+    setup_context!(&req.video_id, Step::Transcribe);
+
+    let Some(stt_client) = SttClient::from_env() else {
+        log::info!(
+            "Speech-to-text disabled, skipping transcription for {}",
+            req.video_id
+        );
+        return Ok(());
+    };
+
+    let work_dir = std::env::temp_dir().join(format!("transcribe_{}", Uuid::new_v4()));
+    tokio::fs::create_dir_all(&work_dir)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to create work dir: {e}")))?;
+
+    let result = telemetry::measure(
+        &state,
+        &req.video_id,
+        Step::Transcribe,
+        transcribe_in_dir(&stt_client, &req, &work_dir),
+    )
+    .await;
+    let _ = tokio::fs::remove_dir_all(&work_dir).await;
+
+    let Some(transcript) = result? else {
+        log::info!(
+            "Video {} has no audio track, nothing to transcribe",
+            req.video_id
+        );
+        return Ok(());
+    };
+
+    store_transcript(&state, &stt_client, &req.video_id, &transcript).await?;
+
+    if let Err(e) = crate::posts::search::index_transcript(
+        &state.yral_redis_store_dragonfly,
+        &req.video_id,
+        &transcript.text,
+    )
+    .await
+    {
+        log::error!("Failed to index transcript for {}: {e:?}", req.video_id);
+    }
+
+    let params = serde_json::json!({
+        "video_id": req.video_id,
+        "publisher_user_id": req.publisher_user_id,
+        "language": transcript.language,
+        "transcript_length": transcript.text.chars().count(),
+        "provider": stt_client.provider.as_str(),
+    });
+    Event::new(WarehouseEvent {
+        event: VIDEO_TRANSCRIBED_EVENT.to_string(),
+        params: params.to_string(),
+    })
+    .stream_to_bigquery(&state);
+
+    log::info!(
+        "Transcribed {} ({} chars, language={:?})",
+        req.video_id,
+        transcript.text.len(),
+        transcript.language
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stt_provider() {
+        assert_eq!(SttProvider::parse("OpenAI"), Some(SttProvider::OpenAi));
+        assert_eq!(SttProvider::parse("whisper"), Some(SttProvider::OpenAi));
+        assert_eq!(
+            SttProvider::parse(" deepgram "),
+            Some(SttProvider::Deepgram)
+        );
+        assert_eq!(SttProvider::parse("google"), None);
+    }
+}
@@ -11,9 +11,10 @@ use uuid::Uuid;
 use crate::{
     app_state::AppState,
     consts::get_storj_video_url,
-    pipeline::Step,
+    pipeline::{telemetry, Step},
     qstash::{self, duplicate::VideoPublisherDataV2},
     setup_context,
+    utils::env_parse,
     video_processing::{
         nsfw_api::{NsfwApiClient, NsfwApiError, VideoDetectRequest},
         queue::{
@@ -21,6 +22,7 @@ use crate::{
             save_and_schedule, save_and_unschedule, try_acquire_lock, VideoProcessingJob,
             VideoProcessingPhase,
         },
+        thumbnails, transcode, transcribe,
     },
 };
 
@@ -90,6 +92,19 @@ async fn run_worker(
     );
 
     loop {
+        #[cfg(not(feature = "local-bin"))]
+        if crate::maintenance::paused(
+            &state.yral_redis_store_dragonfly,
+            crate::maintenance::Subsystem::VideoProcessing,
+        )
+        .await
+        .is_some()
+        {
+            log::info!("Video processing is under maintenance, skipping tick");
+            tokio::time::sleep(Duration::from_secs(config.tick_seconds)).await;
+            continue;
+        }
+
         if let Err(err) = process_due_jobs(state.clone(), nsfw_client.clone(), config.clone()).await
         {
             log::error!("Video processing worker tick failed: {err:?}");
@@ -202,7 +217,7 @@ async fn process_dedup_pending(
         "job": &job,
     });
 
-    // Dedup may return Ok without calling the continuation when AI approval blocks the video.
+    // Dedup may return Ok without calling the continuation when AI approval or the dedup auto-block policy blocks the video.
     let callback_called = Arc::new(AtomicBool::new(false));
     let callback_called_for_dedup = callback_called.clone();
     let callback_pool = state.yral_redis_store_dragonfly.clone();
@@ -212,29 +227,27 @@ async fn process_dedup_pending(
         post_id: job.post_id.clone(),
     };
 
-    let dedup_result = qstash::duplicate::VideoHashDuplication
-        .process_video_deduplication_v2(
-            &state.bigquery_client,
-            &state.milvus_client,
-            &state.rewards_module.dragonfly_pool,
-            &state.kvrocks_client,
-            &job.video_id,
-            &job.source_video_uri,
-            publisher_data,
-            30,
-            move |video_id, _post_id, _timestamp, _publisher_user_id| {
-                let video_id = video_id.to_string();
-                let callback_pool = callback_pool.clone();
-                let callback_called_for_dedup = callback_called_for_dedup.clone();
-
-                Box::pin(async move {
-                    // Replace the old upload_video_gcs callback with a durable phase transition.
-                    callback_called_for_dedup.store(true, Ordering::SeqCst);
-                    queue::mark_nsfw_enqueue_pending(&callback_pool, &video_id).await
-                })
-            },
-        )
-        .await;
+    let dedup = qstash::duplicate::VideoHashDuplication.process_video_deduplication_v2(
+        &state.bigquery_client,
+        &state.milvus_client,
+        &state.rewards_module.dragonfly_pool,
+        &state.kvrocks_client,
+        &job.video_id,
+        &job.source_video_uri,
+        publisher_data,
+        move |video_id, _post_id, _timestamp, _publisher_user_id| {
+            let video_id = video_id.to_string();
+            let callback_pool = callback_pool.clone();
+            let callback_called_for_dedup = callback_called_for_dedup.clone();
+
+            Box::pin(async move {
+                // Replace the old upload_video_gcs callback with a durable phase transition.
+                callback_called_for_dedup.store(true, Ordering::SeqCst);
+                queue::mark_nsfw_enqueue_pending(&callback_pool, &video_id).await
+            })
+        },
+    );
+    let dedup_result = telemetry::measure(&state, &job.video_id, Step::Deduplication, dedup).await;
 
     match dedup_result {
         Ok(()) if callback_called.load(Ordering::SeqCst) => {
@@ -242,6 +255,38 @@ async fn process_dedup_pending(
                 "Dedup completed for {}; NSFW enqueue phase scheduled",
                 job.video_id
             );
+            thumbnails::spawn_thumbnail_generation(
+                state.clone(),
+                job.video_id.clone(),
+                job.source_video_uri.clone(),
+            );
+            if transcode::transcoding_enabled() {
+                if let Err(err) = state
+                    .qstash_client
+                    .publish_job(&transcode::TranscodeVideoRequest {
+                        video_id: job.video_id.clone(),
+                        publisher_user_id: job.publisher_user_id.clone(),
+                    })
+                    .await
+                {
+                    log::error!("Failed to queue transcode for {}: {err:?}", job.video_id);
+                }
+            }
+            if transcribe::transcription_enabled() {
+                if let Err(err) = state
+                    .qstash_client
+                    .publish_job(&transcribe::TranscribeVideoRequest {
+                        video_id: job.video_id.clone(),
+                        publisher_user_id: job.publisher_user_id.clone(),
+                    })
+                    .await
+                {
+                    log::error!(
+                        "Failed to queue transcription for {}: {err:?}",
+                        job.video_id
+                    );
+                }
+            }
         }
         Ok(()) => {
             job.phase = VideoProcessingPhase::Completed;
@@ -296,7 +341,14 @@ async fn process_nsfw_enqueue_pending(
         trace_id: Some(job.trace_id.clone()),
     };
 
-    match nsfw_client.detect_video(&request).await {
+    let detected = telemetry::measure(
+        &state,
+        &job.video_id,
+        Step::NsfwApiHandoff,
+        nsfw_client.detect_video(&request),
+    )
+    .await;
+    match detected {
         Ok(response) => {
             log::info!(
                 "NSFW detect accepted for {}: job_id={}, status={}, trace_id={:?}, response_video_id={}",
@@ -346,7 +398,14 @@ async fn process_nsfw_poll_pending(
         "job": &job,
     });
 
-    match nsfw_client.video_status(&job.video_id).await {
+    let status = telemetry::measure(
+        &state,
+        &job.video_id,
+        Step::NsfwApiStatusPoll,
+        nsfw_client.video_status(&job.video_id),
+    )
+    .await;
+    match status {
         Ok(response) => {
             log::info!(
                 "NSFW status for {}: response_video_id={}, job_id={}, status={}, attempts={}, trace_id={:?}, last_error_code={:?}, last_error_message={:?}, final_result_present={}",
@@ -585,6 +644,11 @@ async fn mark_completed(state: &AppState, job: &mut VideoProcessingJob) -> Resul
     job.last_error = None;
     save_and_unschedule(&state.yral_redis_store_dragonfly, job).await?;
     log::info!("Video processing completed for {}", job.video_id);
+    telemetry::record_upload_to_live(
+        state,
+        &job.video_id,
+        job.upload_created_at.as_deref().unwrap_or(&job.created_at),
+    );
     Ok(())
 }
 
@@ -617,16 +681,6 @@ enum RetryCounter {
     NsfwPoll,
 }
 
-fn env_parse<T>(key: &str, default: T) -> T
-where
-    T: std::str::FromStr,
-{
-    std::env::var(key)
-        .ok()
-        .and_then(|value| value.parse::<T>().ok())
-        .unwrap_or(default)
-}
-
 pub fn new_upload_job(
     video_id: String,
     publisher_user_id: String,
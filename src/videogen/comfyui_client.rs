@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::time::Duration;
 use tracing::info;
+use utoipa::ToSchema;
 use videogen_common::VideoGenError;
 
 const VIDEO_SECONDS: u32 = 15;
@@ -91,7 +92,7 @@ pub enum VideoGenMode {
 }
 
 /// Webhook payload received from ComfyUI API wrapper
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct ComfyUIWebhookPayload {
     pub id: String,
     pub status: String,
@@ -102,7 +103,7 @@ pub struct ComfyUIWebhookPayload {
     pub extra: Value,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct ComfyUIOutput {
     pub filename: String,
     pub local_path: Option<String>,
@@ -7,6 +7,7 @@ use candid::Principal;
 use serde::Deserialize;
 use std::sync::Arc;
 use tracing::instrument;
+use utoipa::IntoParams;
 use videogen_common::{types_v2::VideoUploadHandling, TokenType, VideoGenError, VideoGenResponse};
 
 use crate::{
@@ -20,7 +21,7 @@ use crate::{
 use yral_canisters_client::rate_limits::TokenType as CanisterTokenType;
 
 /// Query parameters for webhook URL
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct ComfyUIWebhookQueryParams {
     pub principal: String,
     pub counter: u64,
@@ -63,6 +64,16 @@ fn convert_comfyui_status(
 }
 
 /// Handle ComfyUI webhook notifications
+#[utoipa::path(
+    post,
+    path = "/webhook",
+    params(ComfyUIWebhookQueryParams),
+    request_body = ComfyUIWebhookPayload,
+    responses(
+        (status = 200, description = "Webhook processed")
+    ),
+    tag = "webhooks"
+)]
 #[instrument(skip(state, payload))]
 pub async fn handle_comfyui_webhook(
     State(state): State<Arc<AppState>>,
@@ -134,6 +145,11 @@ pub async fn handle_comfyui_webhook(
             .extra
             .get("encrypted_identity")
             .and_then(|v| v.as_str().map(|s| s.to_string())),
+        model_id: Some(video_gen_request.model_name.clone()),
+        prompt_hash: payload
+            .extra
+            .get("prompt_hash")
+            .and_then(|v| v.as_str().map(|s| s.to_string())),
     };
 
     // Use existing callback handler logic
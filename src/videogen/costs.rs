@@ -0,0 +1,455 @@
+//! Spend tracking for completed video generations.
+//!
+//! Every successful generation is priced from [`MODEL_PRICING`] (list-price
+//! estimates per second of output), streamed to the `videogen_costs`
+//! BigQuery table and added to a per-day running total in Dragonfly, which
+//! the summary endpoint reads. The first time a day's total crosses the
+//! `videogen.daily_budget_usd` runtime config an alert is posted to
+//! `GCHAT_VIDEOGEN_ALERTS_WEBHOOK_URL`.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::Result;
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{Duration, NaiveDate, Utc};
+use google_cloud_bigquery::http::tabledata::insert_all::{InsertAllRequest, Row};
+use once_cell::sync::Lazy;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+    offchain_service::send_message_gchat_webhook,
+    videogen::QstashVideoGenCallback,
+    yral_auth::dragonfly::DragonflyPool,
+};
+
+const PROJECT_ID: &str = "hot-or-not-feed-intelligence";
+const DATASET_ID: &str = "yral_ds";
+const COSTS_TABLE: &str = "videogen_costs";
+
+const KEY_PREFIX: &str = "offchain:videogen:costs";
+const TOTALS_TTL_SECS: i64 = 90 * 24 * 60 * 60;
+/// Callbacks can be redelivered; a job is only counted once within this
+const RECORDED_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+const TOTAL_FIELD: &str = "_total";
+const MAX_SUMMARY_DAYS: u32 = 90;
+
+static ALERTS_WEBHOOK_URL: Lazy<Option<String>> =
+    Lazy::new(|| std::env::var("GCHAT_VIDEOGEN_ALERTS_WEBHOOK_URL").ok());
+
+pub struct ModelPricing {
+    pub model: &'static str,
+    pub usd_per_second: f64,
+    /// Length of the clip we request from the provider
+    pub duration_secs: u32,
+}
+
+/// Unlisted models are recorded at zero cost with `priced = false`
+pub const MODEL_PRICING: &[ModelPricing] = &[
+    ModelPricing {
+        model: "wan2_5",
+        usd_per_second: 0.10,
+        duration_secs: 5,
+    },
+    ModelPricing {
+        model: "wan2_5_fast",
+        usd_per_second: 0.068,
+        duration_secs: 5,
+    },
+    ModelPricing {
+        model: "speech_to_video",
+        usd_per_second: 0.068,
+        duration_secs: 5,
+    },
+    // Self-hosted on ComfyUI; GPU time per second of output
+    ModelPricing {
+        model: "ltx2",
+        usd_per_second: 0.02,
+        duration_secs: 5,
+    },
+    ModelPricing {
+        model: "inttest",
+        usd_per_second: 0.0,
+        duration_secs: 0,
+    },
+];
+
+fn pricing_for(model: &str) -> Option<&'static ModelPricing> {
+    MODEL_PRICING
+        .iter()
+        .find(|pricing| pricing.model.eq_ignore_ascii_case(model))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CostRecord {
+    pub request_principal: String,
+    pub request_counter: u64,
+    pub provider: String,
+    pub model: String,
+    pub duration_secs: u32,
+    pub estimated_usd: f64,
+    /// False when the model has no entry in the pricing table
+    pub priced: bool,
+    pub token_type: String,
+    pub deducted_amount: Option<u64>,
+    pub created_at: String,
+}
+
+impl CostRecord {
+    pub fn for_callback(callback: &QstashVideoGenCallback, provider: &str) -> Self {
+        let model = callback
+            .model_id
+            .clone()
+            .unwrap_or_else(|| callback.property.clone());
+        let pricing = pricing_for(&model);
+        let duration_secs = pricing.map_or(0, |pricing| pricing.duration_secs);
+
+        Self {
+            request_principal: callback.request_key.principal.to_text(),
+            request_counter: callback.request_key.counter,
+            provider: provider.to_string(),
+            model,
+            duration_secs,
+            estimated_usd: pricing
+                .map_or(0.0, |pricing| pricing.usd_per_second * duration_secs as f64),
+            priced: pricing.is_some(),
+            token_type: format!("{:?}", callback.token_type),
+            deducted_amount: callback.deducted_amount,
+            created_at: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+fn totals_key(date: NaiveDate) -> String {
+    format!("{KEY_PREFIX}:daily:{date}")
+}
+
+fn recorded_key(record: &CostRecord) -> String {
+    format!(
+        "{KEY_PREFIX}:recorded:{}:{}",
+        record.request_principal, record.request_counter
+    )
+}
+
+fn alerted_key(date: NaiveDate) -> String {
+    format!("{KEY_PREFIX}:alerted:{date}")
+}
+
+fn to_micros(usd: f64) -> i64 {
+    (usd * 1_000_000.0).round() as i64
+}
+
+/// Adds a job to today's totals. Returns today's total in USD, or `None`
+/// when the job was already counted.
+async fn add_to_daily_totals(
+    pool: &Arc<DragonflyPool>,
+    record: &CostRecord,
+) -> Result<Option<f64>> {
+    let mut conn = pool.get().await?;
+    let first: Option<String> = redis::cmd("SET")
+        .arg(recorded_key(record))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(RECORDED_TTL_SECS)
+        .query_async(&mut conn)
+        .await?;
+    if first.is_none() {
+        return Ok(None);
+    }
+
+    let key = totals_key(Utc::now().date_naive());
+    let micros = to_micros(record.estimated_usd);
+    let (total_micros,): (i64,) = redis::pipe()
+        .hincr(&key, format!("{}:usd_micros", record.model), micros)
+        .ignore()
+        .hincr(&key, format!("{}:jobs", record.model), 1)
+        .ignore()
+        .hincr(&key, format!("{TOTAL_FIELD}:jobs"), 1)
+        .ignore()
+        .hincr(&key, format!("{TOTAL_FIELD}:usd_micros"), micros)
+        .expire(&key, TOTALS_TTL_SECS)
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+
+    Ok(Some(total_micros as f64 / 1_000_000.0))
+}
+
+async fn insert_cost_row(state: &AppState, record: &CostRecord) -> Result<()> {
+    let request = InsertAllRequest {
+        rows: vec![Row {
+            insert_id: Some(format!(
+                "videogen_cost_{}_{}",
+                record.request_principal, record.request_counter
+            )),
+            json: serde_json::to_value(record)?,
+        }],
+        ignore_unknown_values: Some(false),
+        skip_invalid_rows: Some(false),
+        ..Default::default()
+    };
+
+    let result = state
+        .bigquery_client
+        .tabledata()
+        .insert(PROJECT_ID, DATASET_ID, COSTS_TABLE, &request)
+        .await?;
+    if let Some(errors) = result.insert_errors.filter(|errors| !errors.is_empty()) {
+        anyhow::bail!("Failed to insert videogen cost row: {errors:?}");
+    }
+    Ok(())
+}
+
+fn daily_budget_usd() -> Option<f64> {
+    crate::config::runtime::runtime().videogen.daily_budget_usd
+}
+
+/// Alerts once per day, when the total first exceeds the budget
+async fn check_budget(pool: &Arc<DragonflyPool>, total_usd: f64) -> Result<()> {
+    let Some(budget) = daily_budget_usd() else {
+        return Ok(());
+    };
+    if total_usd <= budget {
+        return Ok(());
+    }
+
+    let today = Utc::now().date_naive();
+    let mut conn = pool.get().await?;
+    let first: Option<String> = redis::cmd("SET")
+        .arg(alerted_key(today))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(2 * 24 * 60 * 60)
+        .query_async(&mut conn)
+        .await?;
+    if first.is_none() {
+        return Ok(());
+    }
+
+    log::warn!("Videogen spend ${total_usd:.2} exceeded the daily budget of ${budget:.2}");
+    let Some(webhook_url) = ALERTS_WEBHOOK_URL.as_ref() else {
+        log::warn!("GCHAT_VIDEOGEN_ALERTS_WEBHOOK_URL not set, skipping chat alert");
+        return Ok(());
+    };
+    send_message_gchat_webhook(
+        webhook_url,
+        json!({
+            "text": format!(
+                "*Videogen daily budget exceeded* for {today}: ${total_usd:.2} spent against a budget of ${budget:.2}"
+            )
+        }),
+    )
+    .await
+}
+
+/// Re-checks today's spend whenever the budget is changed at runtime, so
+/// lowering it below what was already spent alerts right away
+pub fn spawn_budget_watcher(pool: Arc<DragonflyPool>) {
+    let mut config = crate::config::runtime::subscribe();
+    tokio::spawn(async move {
+        let mut budget = config.borrow_and_update().videogen.daily_budget_usd;
+        while config.changed().await.is_ok() {
+            let changed = config.borrow_and_update().videogen.daily_budget_usd;
+            if changed == budget {
+                continue;
+            }
+            budget = changed;
+
+            let result: Result<()> = async {
+                let mut conn = pool.get().await?;
+                let total_micros: Option<i64> = conn
+                    .hget(
+                        totals_key(Utc::now().date_naive()),
+                        format!("{TOTAL_FIELD}:usd_micros"),
+                    )
+                    .await?;
+                drop(conn);
+                check_budget(&pool, total_micros.unwrap_or(0) as f64 / 1_000_000.0).await
+            }
+            .await;
+            if let Err(e) = result {
+                log::error!("Failed to re-check videogen budget: {e:?}");
+            }
+        }
+    });
+}
+
+/// Records a completed generation. Failures are logged; they never fail the
+/// callback.
+pub async fn record_completed_job(state: &AppState, record: CostRecord) {
+    if !record.priced {
+        log::warn!(
+            "No pricing for videogen model {}, recording zero cost",
+            record.model
+        );
+    }
+
+    let pool = &state.yral_redis_store_dragonfly;
+    match add_to_daily_totals(pool, &record).await {
+        Ok(Some(total_usd)) => {
+            if let Err(e) = check_budget(pool, total_usd).await {
+                log::error!("Failed to check videogen budget: {e:?}");
+            }
+        }
+        Ok(None) => {
+            log::debug!(
+                "Cost for {}_{} already recorded",
+                record.request_principal,
+                record.request_counter
+            );
+            return;
+        }
+        Err(e) => log::error!("Failed to update videogen cost totals: {e:?}"),
+    }
+
+    if let Err(e) = insert_cost_row(state, &record).await {
+        log::error!("Failed to record videogen cost: {e:?}");
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CostSummaryParams {
+    /// Number of days back from today, including today (default 7, max 90)
+    pub days: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct ModelCost {
+    pub model: String,
+    pub jobs: u64,
+    pub estimated_usd: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DailyCost {
+    pub date: String,
+    pub jobs: u64,
+    pub estimated_usd: f64,
+    pub over_budget: bool,
+    pub models: Vec<ModelCost>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CostSummaryResponse {
+    pub daily_budget_usd: Option<f64>,
+    /// Newest first
+    pub days: Vec<DailyCost>,
+}
+
+fn daily_cost(date: NaiveDate, fields: BTreeMap<String, i64>) -> DailyCost {
+    let mut total = ModelCost::default();
+    let mut models: BTreeMap<String, ModelCost> = BTreeMap::new();
+    for (field, value) in fields {
+        let Some((model, metric)) = field.rsplit_once(':') else {
+            continue;
+        };
+        let entry = if model == TOTAL_FIELD {
+            &mut total
+        } else {
+            models
+                .entry(model.to_string())
+                .or_insert_with(|| ModelCost {
+                    model: model.to_string(),
+                    ..Default::default()
+                })
+        };
+        match metric {
+            "jobs" => entry.jobs = value.max(0) as u64,
+            "usd_micros" => entry.estimated_usd = value as f64 / 1_000_000.0,
+            _ => {}
+        }
+    }
+
+    DailyCost {
+        date: date.to_string(),
+        jobs: total.jobs,
+        estimated_usd: total.estimated_usd,
+        over_budget: daily_budget_usd().is_some_and(|budget| total.estimated_usd > budget),
+        models: models.into_values().collect(),
+    }
+}
+
+/// Estimated videogen spend per day with a per-model breakdown
+#[utoipa::path(
+    get,
+    path = "/costs/summary",
+    params(CostSummaryParams),
+    tag = "VideoGen",
+    responses(
+        (status = 200, description = "Daily spend", body = CostSummaryResponse),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn get_costs_summary(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<CostSummaryParams>,
+    headers: HeaderMap,
+) -> Result<Json<CostSummaryResponse>, ApiError> {
+    require_operator(&headers)?;
+
+    let days = params.days.unwrap_or(7).clamp(1, MAX_SUMMARY_DAYS);
+    let today = Utc::now().date_naive();
+    let dates: Vec<NaiveDate> = (0..days)
+        .map(|offset| today - Duration::days(offset as i64))
+        .collect();
+
+    let mut conn = state.yral_redis_store_dragonfly.get().await?;
+    let mut pipe = redis::pipe();
+    for date in &dates {
+        pipe.hgetall(totals_key(*date));
+    }
+    let totals: Vec<BTreeMap<String, i64>> = pipe.query_async(&mut conn).await?;
+
+    Ok(Json(CostSummaryResponse {
+        daily_budget_usd: daily_budget_usd(),
+        days: dates
+            .into_iter()
+            .zip(totals)
+            .map(|(date, fields)| daily_cost(date, fields))
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_cost_breakdown() {
+        assert_eq!(pricing_for("WAN2_5").map(|p| p.model), Some("wan2_5"));
+        assert!(pricing_for("unknown").is_none());
+
+        let fields = BTreeMap::from([
+            (format!("{TOTAL_FIELD}:jobs"), 3),
+            (format!("{TOTAL_FIELD}:usd_micros"), to_micros(1.1)),
+            ("wan2_5:jobs".to_string(), 2),
+            ("wan2_5:usd_micros".to_string(), to_micros(1.0)),
+            ("ltx2:jobs".to_string(), 1),
+            ("ltx2:usd_micros".to_string(), to_micros(0.1)),
+        ]);
+        let day = daily_cost(NaiveDate::from_ymd_opt(2025, 1, 2).unwrap(), fields);
+        assert_eq!(day.date, "2025-01-02");
+        assert_eq!(day.jobs, 3);
+        assert!((day.estimated_usd - 1.1).abs() < 1e-9);
+        assert_eq!(day.models.len(), 2);
+        assert_eq!(day.models[0].model, "ltx2");
+        assert_eq!(day.models[1].jobs, 2);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::app_state::AppState;
+use crate::error::{ApiError, ApiErrorBody};
 use crate::utils::gcs::maybe_upload_image_to_gcs;
 use axum::{extract::State, http::StatusCode, Json};
 use cloud_storage::Client;
@@ -38,22 +39,22 @@ async fn process_input_image(
     request_body = videogen_common::VideoGenRequestWithIdentity,
     responses(
         (status = 200, description = "Video generation started successfully", body = videogen_common::VideoGenQueuedResponse),
-        (status = 400, description = "Invalid input", body = videogen_common::VideoGenError),
-        (status = 401, description = "Authentication failed - Invalid identity", body = videogen_common::VideoGenError),
-        (status = 402, description = "Insufficient balance", body = videogen_common::VideoGenError),
-        (status = 429, description = "Rate limit exceeded", body = videogen_common::VideoGenError),
-        (status = 502, description = "Provider error", body = videogen_common::VideoGenError),
-        (status = 503, description = "Service unavailable", body = videogen_common::VideoGenError),
+        (status = 400, description = "Invalid input", body = ApiErrorBody),
+        (status = 401, description = "Authentication failed - Invalid identity", body = ApiErrorBody),
+        (status = 402, description = "Insufficient balance", body = ApiErrorBody),
+        (status = 429, description = "Rate limit exceeded", body = ApiErrorBody),
+        (status = 502, description = "Provider error", body = ApiErrorBody),
+        (status = 503, description = "Service unavailable", body = ApiErrorBody),
     ),
-    tag = "VideoGen"
+    tag = "VideoGen",
+    security(
+        ("delegated_identity" = [])
+    )
 )]
 pub async fn generate_video_with_identity(
     State(app_state): State<Arc<AppState>>,
     Json(identity_request): Json<videogen_common::VideoGenRequestWithIdentity>,
-) -> Result<
-    Json<videogen_common::VideoGenQueuedResponse>,
-    (StatusCode, Json<videogen_common::VideoGenError>),
-> {
+) -> Result<Json<videogen_common::VideoGenQueuedResponse>, ApiError> {
     // Validate identity and extract user principal
     let user_principal = super::utils::validate_delegated_identity(&identity_request)?;
 
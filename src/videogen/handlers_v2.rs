@@ -1,5 +1,9 @@
 use axum::debug_handler;
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
 use ic_agent::identity::{DelegatedIdentity, Identity};
 use std::sync::Arc;
 use videogen_common::{
@@ -8,6 +12,8 @@ use videogen_common::{
 };
 
 use crate::app_state::AppState;
+use crate::canister::agent_pool;
+use crate::error::{ApiError, ApiErrorBody};
 use crate::utils::gcs::{maybe_upload_image_to_gcs, upload_audio_if_needed};
 use cloud_storage::Client;
 
@@ -83,41 +89,77 @@ pub async fn get_providers_all() -> Json<ProvidersResponse> {
     Json(ADAPTER_REGISTRY.get_all_providers())
 }
 
+#[derive(Debug, Default, serde::Deserialize, utoipa::IntoParams)]
+pub struct GenerateVideoParams {
+    /// Start a new generation even if an identical request was queued in
+    /// the last two minutes
+    #[serde(default)]
+    pub force: bool,
+}
+
+fn queued_response(
+    request_key: crate::videogen::VideoGenRequestKey,
+    provider: String,
+) -> VideoGenQueuedResponseV2 {
+    VideoGenQueuedResponseV2 {
+        operation_id: format!("{}_{}", request_key.principal, request_key.counter),
+        provider,
+        request_key: videogen_common::VideoGenRequestKey {
+            principal: request_key.principal,
+            counter: request_key.counter,
+        },
+    }
+}
+
 /// Generate a video using unified request structure (V2 API)
+///
+/// An identical request (same user, model, prompt and image) made shortly
+/// after another returns the earlier request's key instead of generating
+/// again, unless `force` is set.
 #[utoipa::path(
     post,
     path = "/generate",
+    params(GenerateVideoParams),
     request_body = VideoGenRequestWithIdentityV2,
     responses(
-        (status = 200, description = "Video generation started successfully", body = VideoGenQueuedResponseV2),
-        (status = 400, description = "Invalid input", body = VideoGenError),
-        (status = 401, description = "Authentication failed - Invalid identity", body = VideoGenError),
-        (status = 402, description = "Insufficient balance", body = VideoGenError),
-        (status = 429, description = "Rate limit exceeded", body = VideoGenError),
-        (status = 502, description = "Provider error", body = VideoGenError),
-        (status = 503, description = "Service unavailable", body = VideoGenError),
+        (status = 200, description = "Video generation started successfully, or the identical in-flight request", body = VideoGenQueuedResponseV2),
+        (status = 400, description = "Invalid input", body = ApiErrorBody),
+        (status = 401, description = "Authentication failed - Invalid identity", body = ApiErrorBody),
+        (status = 402, description = "Insufficient balance", body = ApiErrorBody),
+        (status = 409, description = "An identical request is still being started", body = ApiErrorBody),
+        (status = 429, description = "Rate limit exceeded", body = ApiErrorBody),
+        (status = 502, description = "Provider error", body = ApiErrorBody),
+        (status = 503, description = "Service unavailable", body = ApiErrorBody),
     ),
-    tag = "VideoGen V2"
+    tag = "VideoGen V2",
+    security(
+        ("delegated_identity" = [])
+    )
 )]
 #[debug_handler]
 pub async fn generate_video_with_identity_v2(
     State(app_state): State<Arc<AppState>>,
+    Query(params): Query<GenerateVideoParams>,
     Json(mut identity_request): Json<VideoGenRequestWithIdentityV2>,
-) -> Result<Json<VideoGenQueuedResponseV2>, (StatusCode, Json<VideoGenError>)> {
+) -> Result<Json<VideoGenQueuedResponseV2>, ApiError> {
     // Validate identity and extract user principal
     let user_principal = validate_delegated_identity_v2(&identity_request)?;
 
     // Check if model is available
     if !ADAPTER_REGISTRY.is_model_available(&identity_request.request.model_id) {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(VideoGenError::InvalidInput(format!(
+        return Err(ApiError::VideoGen {
+            status: StatusCode::BAD_REQUEST,
+            error: VideoGenError::InvalidInput(format!(
                 "Model '{}' is not available",
                 identity_request.request.model_id
-            ))),
-        ));
+            )),
+        });
     }
 
+    #[cfg(not(feature = "local-bin"))]
+    let image_fingerprint =
+        super::request_dedup::image_fingerprint(identity_request.request.image.as_ref());
+
     // process audio if present - upload large audio to GCS
     process_input_audio(
         &mut identity_request.request.audio,
@@ -140,10 +182,41 @@ pub async fn generate_video_with_identity_v2(
         .map_err(|e| (StatusCode::BAD_REQUEST, Json(e)))?;
 
     // Get provider for response
-    let provider = video_gen_input.provider();
+    let provider = video_gen_input.provider().to_string();
+
+    #[cfg(not(feature = "local-bin"))]
+    let fingerprint = {
+        use super::request_dedup::{claim, fingerprint, Claim};
+
+        let fingerprint = fingerprint(
+            user_principal,
+            video_gen_input.model_id(),
+            video_gen_input.get_prompt(),
+            image_fingerprint.as_deref(),
+        );
+        match claim(
+            &app_state.yral_redis_store_dragonfly,
+            &fingerprint,
+            params.force,
+        )
+        .await?
+        {
+            Claim::InFlight(request_key) => {
+                log::info!(
+                    "Returning in-flight request {}_{} for duplicate generation by {user_principal}",
+                    request_key.principal,
+                    request_key.counter
+                );
+                return Ok(Json(queued_response(request_key, provider)));
+            }
+            Claim::Claimed => fingerprint,
+        }
+    };
+    #[cfg(feature = "local-bin")]
+    let _ = params.force;
 
     // Use common processing function
-    let request_key = super::utils::process_video_generation(
+    let result = super::utils::process_video_generation(
         &app_state,
         user_principal,
         video_gen_input,
@@ -151,17 +224,24 @@ pub async fn generate_video_with_identity_v2(
         identity_request.delegated_identity.clone(),
         identity_request.upload_handling,
     )
-    .await?;
+    .await;
+
+    #[cfg(not(feature = "local-bin"))]
+    match &result {
+        Ok(request_key) => {
+            super::request_dedup::complete(
+                &app_state.yral_redis_store_dragonfly,
+                &fingerprint,
+                request_key,
+            )
+            .await
+        }
+        Err(_) => {
+            super::request_dedup::release(&app_state.yral_redis_store_dragonfly, &fingerprint).await
+        }
+    }
 
-    // Build and return response
-    Ok(Json(VideoGenQueuedResponseV2 {
-        operation_id: format!("{}_{}", request_key.principal, request_key.counter),
-        provider: provider.to_string(),
-        request_key: videogen_common::VideoGenRequestKey {
-            principal: request_key.principal,
-            counter: request_key.counter,
-        },
-    }))
+    Ok(Json(queued_response(result?, provider)))
 }
 
 /// Validates the delegated identity and returns the user principal (V2 version)
@@ -214,8 +294,8 @@ pub struct InProgressVideoResponse {
     ),
     responses(
         (status = 200, description = "List of in-progress videos", body = InProgressVideoResponse),
-        (status = 400, description = "Invalid principal", body = VideoGenError),
-        (status = 502, description = "Canister error", body = VideoGenError),
+        (status = 400, description = "Invalid principal", body = ApiErrorBody),
+        (status = 502, description = "Canister error", body = ApiErrorBody),
     ),
     tag = "VideoGen V2"
 )]
@@ -223,33 +303,23 @@ pub struct InProgressVideoResponse {
 pub async fn get_in_progress_videos(
     State(app_state): State<Arc<AppState>>,
     axum::extract::Path(principal): axum::extract::Path<String>,
-) -> Result<Json<InProgressVideoResponse>, (StatusCode, Json<VideoGenError>)> {
+) -> Result<Json<InProgressVideoResponse>, ApiError> {
     use crate::consts::RATE_LIMITS_CANISTER_ID;
     use std::str::FromStr;
     use yral_canisters_client::rate_limits::{RateLimits, VideoGenRequestStatus};
 
-    let user_principal = candid::Principal::from_str(&principal).map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(VideoGenError::InvalidInput(format!(
-                "Invalid principal: {e}"
-            ))),
-        )
-    })?;
+    let user_principal = candid::Principal::from_str(&principal)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid principal: {e}")))?;
 
     let rate_limits_client = RateLimits(*RATE_LIMITS_CANISTER_ID, &app_state.agent);
 
-    let requests = rate_limits_client
-        .get_user_video_generation_requests(user_principal, None, None)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::BAD_GATEWAY,
-                Json(VideoGenError::NetworkError(format!(
-                    "Failed to fetch video generation requests: {e}"
-                ))),
-            )
-        })?;
+    let requests = agent_pool::query(
+        *RATE_LIMITS_CANISTER_ID,
+        "get_user_video_generation_requests",
+        || rate_limits_client.get_user_video_generation_requests(user_principal, None, None),
+    )
+    .await
+    .map_err(|e| ApiError::Canister(format!("Failed to fetch video generation requests: {e}")))?;
 
     let in_progress_videos = requests
         .into_iter()
@@ -295,8 +365,8 @@ pub struct AllVideoStatusResponse {
     ),
     responses(
         (status = 200, description = "List of all video statuses", body = AllVideoStatusResponse),
-        (status = 400, description = "Invalid principal", body = VideoGenError),
-        (status = 502, description = "Canister error", body = VideoGenError),
+        (status = 400, description = "Invalid principal", body = ApiErrorBody),
+        (status = 502, description = "Canister error", body = ApiErrorBody),
     ),
     tag = "VideoGen V2"
 )]
@@ -304,33 +374,23 @@ pub struct AllVideoStatusResponse {
 pub async fn get_all_video_status(
     State(app_state): State<Arc<AppState>>,
     axum::extract::Path(principal): axum::extract::Path<String>,
-) -> Result<Json<AllVideoStatusResponse>, (StatusCode, Json<VideoGenError>)> {
+) -> Result<Json<AllVideoStatusResponse>, ApiError> {
     use crate::consts::RATE_LIMITS_CANISTER_ID;
     use std::str::FromStr;
     use yral_canisters_client::rate_limits::{RateLimits, VideoGenRequestStatus};
 
-    let user_principal = candid::Principal::from_str(&principal).map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(VideoGenError::InvalidInput(format!(
-                "Invalid principal: {e}"
-            ))),
-        )
-    })?;
+    let user_principal = candid::Principal::from_str(&principal)
+        .map_err(|e| ApiError::InvalidRequest(format!("Invalid principal: {e}")))?;
 
     let rate_limits_client = RateLimits(*RATE_LIMITS_CANISTER_ID, &app_state.agent);
 
-    let requests = rate_limits_client
-        .get_user_video_generation_requests(user_principal, None, None)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::BAD_GATEWAY,
-                Json(VideoGenError::NetworkError(format!(
-                    "Failed to fetch video generation requests: {e}"
-                ))),
-            )
-        })?;
+    let requests = agent_pool::query(
+        *RATE_LIMITS_CANISTER_ID,
+        "get_user_video_generation_requests",
+        || rate_limits_client.get_user_video_generation_requests(user_principal, None, None),
+    )
+    .await
+    .map_err(|e| ApiError::Canister(format!("Failed to fetch video generation requests: {e}")))?;
 
     let all_videos = requests
         .into_iter()
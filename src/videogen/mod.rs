@@ -1,5 +1,7 @@
 pub mod comfyui_client;
 pub mod comfyui_webhook;
+#[cfg(not(feature = "local-bin"))]
+pub mod costs;
 pub mod crypto;
 pub mod handlers;
 pub mod handlers_v2;
@@ -10,11 +12,14 @@ pub mod qstash_process;
 pub mod qstash_types;
 pub mod rate_limit;
 pub mod replicate_webhook;
+#[cfg(not(feature = "local-bin"))]
+pub mod request_dedup;
 pub mod router;
 pub mod token_operations;
 pub mod types;
 pub mod upload_ai_generated_video_to_canister_in_drafts;
 pub mod utils;
+pub mod watermark;
 pub mod webhook_signature;
 
 // pub use handlers::generate_video; // Commented out as per user request
@@ -89,6 +89,7 @@ pub async fn generate_with_context(
         "principal": context.request_key.principal.to_string(),
         "counter": context.request_key.counter,
         "encrypted_identity": context.encrypted_identity,
+        "prompt_hash": crate::provenance::prompt_hash(&model.prompt),
     });
 
     let response = comfyui_client
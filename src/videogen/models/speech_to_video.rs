@@ -140,7 +140,8 @@ pub async fn generate_with_context(
         },
         webhook: Some(webhook_url),
         metadata: Some(serde_json::json!({
-            "encrypted_identity": context.encrypted_identity
+            "encrypted_identity": context.encrypted_identity,
+            "prompt_hash": crate::provenance::prompt_hash(&prompt),
         })),
     };
 
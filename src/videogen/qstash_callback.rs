@@ -4,12 +4,15 @@ use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::instrument;
+use utoipa::ToSchema;
 use videogen_common::types_v2::VideoUploadHandling;
 use yral_canisters_client::rate_limits::{RateLimits, VideoGenRequestKey, VideoGenRequestStatus};
 
 use crate::{
     app_state::AppState,
+    canister::agent_pool,
     consts::RATE_LIMITS_CANISTER_ID,
+    provenance::GenerationProvenance,
     videogen::{
         qstash_types::{QstashVideoGenCallback, VideoGenCallbackResult},
         upload_ai_generated_video_to_canister_in_drafts::UploadAiVideoToCanisterRequest,
@@ -21,7 +24,7 @@ use crate::{
 use super::utils::rollback_balance_on_failure;
 
 /// QStash callback wrapper structure
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct QStashCallbackWrapper {
     pub status: u16,
     pub body: String, // Base64 encoded response body
@@ -89,9 +92,12 @@ async fn update_rate_limit_status(
     request_key: VideoGenRequestKey,
     status: VideoGenRequestStatus,
 ) -> Result<(), (StatusCode, String)> {
-    match rate_limits_client
-        .update_video_generation_status(request_key.clone(), status)
-        .await
+    match agent_pool::update(
+        *RATE_LIMITS_CANISTER_ID,
+        "update_video_generation_status",
+        rate_limits_client.update_video_generation_status(request_key.clone(), status),
+    )
+    .await
     {
         Ok(result) => match result {
             yral_canisters_client::rate_limits::Result1::Ok => {
@@ -129,9 +135,12 @@ async fn decrement_counter_for_failure(
         request_key.counter
     );
 
-    if let Err(e) = rate_limits_client
-        .decrement_video_generation_counter_v_1(request_key, property)
-        .await
+    if let Err(e) = agent_pool::update(
+        *RATE_LIMITS_CANISTER_ID,
+        "decrement_video_generation_counter_v_1",
+        rate_limits_client.decrement_video_generation_counter_v_1(request_key, property),
+    )
+    .await
     {
         log::error!("Failed to decrement counter: {e}");
         // Don't fail the callback if decrement fails
@@ -169,6 +178,15 @@ pub async fn handle_video_gen_callback_internal(
 
     update_rate_limit_status(&rate_limits_client, request_key.clone(), status.clone()).await?;
 
+    #[cfg(not(feature = "local-bin"))]
+    if let VideoGenCallbackResult::Success(response) = &callback.result {
+        super::costs::record_completed_job(
+            &state,
+            super::costs::CostRecord::for_callback(&callback, &response.provider),
+        )
+        .await;
+    }
+
     // 4. Handle failure cleanup if needed
     if should_decrement {
         // Decrement counter
@@ -216,15 +234,28 @@ pub async fn handle_video_gen_callback_internal(
                     None
                 };
 
+                let provenance = match &callback.result {
+                    VideoGenCallbackResult::Success(response) => Some(GenerationProvenance {
+                        provider: response.provider.clone(),
+                        model: callback
+                            .model_id
+                            .clone()
+                            .unwrap_or_else(|| callback.property.clone()),
+                        prompt_hash: callback.prompt_hash.clone(),
+                        generated_at: chrono::Utc::now().to_rfc3339(),
+                    }),
+                    VideoGenCallbackResult::Failure(_) => None,
+                };
+
                 state
                     .qstash_client
-                    .upload_ai_generated_video_to_canister_in_drafts(
-                        UploadAiVideoToCanisterRequest {
-                            ai_video_url: ai_video_url.clone(),
-                            user_id: callback.request_key.principal,
-                            delegated_identity,
-                        },
-                    )
+                    .publish_job(&UploadAiVideoToCanisterRequest {
+                        ai_video_url: ai_video_url.clone(),
+                        user_id: callback.request_key.principal,
+                        delegated_identity,
+                        model_id: callback.model_id.clone(),
+                        provenance,
+                    })
                     .await
                     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
@@ -239,6 +270,17 @@ pub async fn handle_video_gen_callback_internal(
 }
 
 /// Handle video generation completion callback from Qstash
+#[utoipa::path(
+    post,
+    path = "/video_gen_callback",
+    request_body = QStashCallbackWrapper,
+    responses(
+        (status = 200, description = "Callback processed"),
+        (status = 400, description = "Malformed callback body"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "qstash"
+)]
 #[instrument(skip(state))]
 pub async fn handle_video_gen_callback(
     State(state): State<Arc<AppState>>,
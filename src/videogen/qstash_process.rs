@@ -29,6 +29,16 @@ pub async fn process_prompt_generation(
 }
 
 /// Process video generation request from Qstash queue
+#[utoipa::path(
+    post,
+    path = "/process_video_gen",
+    request_body = QstashVideoGenRequest,
+    responses(
+        (status = 200, description = "Generation result, delivered to the callback", body = serde_json::Value),
+        (status = 500, description = "Generation failed")
+    ),
+    tag = "qstash"
+)]
 #[instrument(skip(state))]
 pub async fn process_video_generation(
     State(state): State<Arc<AppState>>,
@@ -70,6 +80,8 @@ pub async fn process_video_generation(
     };
 
     let supports_webhook = request.input.supports_webhook_callbacks();
+    let model_id = request.input.model_id().to_string();
+    let prompt_hash = crate::provenance::prompt_hash(request.input.get_prompt());
 
     // Prepare callback data for non-webhook or error cases
     let callback_result = match result {
@@ -88,6 +100,8 @@ pub async fn process_video_generation(
         token_type: request.token_type,
         handle_video_upload: request.handle_video_upload,
         encrypted_identity: request.encrypted_identity,
+        model_id: Some(model_id),
+        prompt_hash: Some(prompt_hash),
     };
 
     // For webhook-based models, only handle failures here
@@ -116,13 +130,28 @@ pub async fn process_video_generation(
     })?))
 }
 
+/// Uploads a generated video to the user's drafts
+#[utoipa::path(
+    post,
+    path = "/upload_ai_generated_video_to_canister_in_drafts",
+    request_body = UploadAiVideoToCanisterRequest,
+    responses(
+        (status = 200, description = "Video uploaded to drafts"),
+        (status = 500, description = "Upload failed", body = String)
+    ),
+    tag = "qstash"
+)]
 pub async fn upload_ai_generated_video_to_canister_in_drafts(
+    State(state): State<Arc<AppState>>,
     Json(request): Json<UploadAiVideoToCanisterRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     match upload_ai_generated_video_to_canister_impl(
+        &state,
         &request.ai_video_url,
         request.user_id,
         request.delegated_identity,
+        request.model_id.as_deref(),
+        request.provenance.as_ref(),
     )
     .await
     {
@@ -1,11 +1,13 @@
 use candid::Principal;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use videogen_common::{types_v2::VideoUploadHandling, TokenType, VideoGenInput, VideoGenResponse};
 
 /// Request structure for queueing video generation to Qstash
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct QstashVideoGenRequest {
     /// User principal making the request
+    #[schema(value_type = String)]
     pub user_principal: Principal,
     /// The video generation input
     pub input: VideoGenInput,
@@ -24,8 +26,9 @@ pub struct QstashVideoGenRequest {
 }
 
 /// Key structure matching rate limit canister
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct VideoGenRequestKey {
+    #[schema(value_type = String)]
     pub principal: Principal,
     pub counter: u64,
 }
@@ -48,6 +51,13 @@ pub struct QstashVideoGenCallback {
     pub handle_video_upload: Option<VideoUploadHandling>,
     /// Encrypted delegated identity for user registration in canister
     pub encrypted_identity: Option<String>,
+    /// Model that ran, for cost accounting; absent on callbacks queued
+    /// before it was added
+    #[serde(default)]
+    pub model_id: Option<String>,
+    /// Hash of the prompt for the provenance manifest
+    #[serde(default)]
+    pub prompt_hash: Option<String>,
 }
 
 /// Result types for callback
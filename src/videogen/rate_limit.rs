@@ -8,6 +8,7 @@ use yral_canisters_client::user_info_service::{SessionType, UserInfoService};
 
 use crate::{
     app_state::AppState,
+    canister::agent_pool,
     consts::{RATE_LIMITS_CANISTER_ID, USER_INFO_SERVICE_CANISTER_ID},
 };
 use videogen_common::{TokenType, VideoGenError};
@@ -16,9 +17,12 @@ use videogen_common::{TokenType, VideoGenError};
 async fn check_user_registration(user_principal: Principal, app_state: &Arc<AppState>) -> bool {
     let user_info_service = UserInfoService(*USER_INFO_SERVICE_CANISTER_ID, &app_state.agent);
 
-    let result = match user_info_service
-        .get_user_session_type(user_principal)
-        .await
+    let result = match agent_pool::query(
+        *USER_INFO_SERVICE_CANISTER_ID,
+        "get_user_session_type",
+        || user_info_service.get_user_session_type(user_principal),
+    )
+    .await
     {
         Ok(result) => result,
         Err(e) => {
@@ -61,17 +65,18 @@ pub async fn fetch_request(
         counter,
     };
 
-    let request_result = rate_limits_client
-        .get_video_generation_request(videogen_request_key)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::SERVICE_UNAVAILABLE,
-                VideoGenError::NetworkError(format!(
-                    "Failed to fetch video generation request: {e}"
-                )),
-            )
-        })?;
+    let request_result = agent_pool::query(
+        *RATE_LIMITS_CANISTER_ID,
+        "get_video_generation_request",
+        || rate_limits_client.get_video_generation_request(videogen_request_key.clone()),
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            VideoGenError::NetworkError(format!("Failed to fetch video generation request: {e}")),
+        )
+    })?;
 
     match request_result {
         Some(request) => {
@@ -145,8 +150,10 @@ pub async fn verify_rate_limit_and_create_request_v1(
     };
 
     // Use the v2 method that handles both rate limit check and request creation
-    let request_key_result = rate_limits_client
-        .create_video_generation_request_v_2(
+    let request_key_result = agent_pool::update(
+        *RATE_LIMITS_CANISTER_ID,
+        "create_video_generation_request_v_2",
+        rate_limits_client.create_video_generation_request_v_2(
             user_principal,
             model.to_string(),
             prompt.to_string(),
@@ -155,16 +162,15 @@ pub async fn verify_rate_limit_and_create_request_v1(
             is_registered,
             is_paid,
             payment_amount_str,
+        ),
+    )
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            VideoGenError::NetworkError(format!("Failed to create video generation request: {e}")),
         )
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::SERVICE_UNAVAILABLE,
-                VideoGenError::NetworkError(format!(
-                    "Failed to create video generation request: {e}"
-                )),
-            )
-        })?;
+    })?;
 
     match request_key_result {
         yral_canisters_client::rate_limits::Result_::Ok(key) => {
@@ -1,16 +1,17 @@
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    Json,
 };
 use candid::Principal;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
 use videogen_common::{types_v2::VideoUploadHandling, TokenType, VideoGenError, VideoGenResponse};
 
 use crate::{
     app_state::AppState,
+    error::{ApiError, ApiErrorBody},
     videogen::{
         qstash_types::{QstashVideoGenCallback, VideoGenCallbackResult},
         webhook_signature::{verify_webhook_signature, WebhookHeaders},
@@ -20,7 +21,7 @@ use crate::{
 use yral_canisters_client::rate_limits::TokenType as CanisterTokenType;
 
 /// Replicate webhook payload structure
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct ReplicateWebhookPayload {
     pub id: String,
     pub version: String,
@@ -32,7 +33,7 @@ pub struct ReplicateWebhookPayload {
 }
 
 /// Query parameters for webhook URL
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct WebhookQueryParams {
     pub principal: String,
     pub counter: u64,
@@ -102,43 +103,74 @@ fn convert_replicate_status(payload: &ReplicateWebhookPayload) -> VideoGenCallba
 }
 
 /// Handle Replicate webhook notifications
+#[utoipa::path(
+    post,
+    path = "/webhook",
+    params(
+        WebhookQueryParams,
+        ("webhook-id" = String, Header, description = "Replicate webhook ID"),
+        ("webhook-timestamp" = String, Header, description = "Replicate webhook timestamp"),
+        ("webhook-signature" = String, Header, description = "Replicate webhook signature")
+    ),
+    request_body = ReplicateWebhookPayload,
+    responses(
+        (status = 200, description = "Webhook processed"),
+        (status = 400, description = "Malformed payload", body = ApiErrorBody),
+        (status = 401, description = "Missing or invalid signature, or stale timestamp", body = ApiErrorBody),
+        (status = 409, description = "Webhook id already processed (replay)", body = ApiErrorBody),
+        (status = 500, description = "Webhook secret not configured or processing failed", body = ApiErrorBody)
+    ),
+    tag = "webhooks"
+)]
 #[instrument(skip(state, payload_bytes))]
 pub async fn handle_replicate_webhook(
     State(state): State<Arc<AppState>>,
     Query(params): Query<WebhookQueryParams>,
     headers: axum::http::HeaderMap,
     payload_bytes: axum::body::Bytes,
-) -> Result<StatusCode, (StatusCode, Json<VideoGenError>)> {
+) -> Result<StatusCode, ApiError> {
     log::info!("Received Replicate webhook notification");
 
     // Extract and verify webhook headers
     let webhook_headers = WebhookHeaders::from_http_headers(&headers).map_err(|e| {
         log::error!("Failed to extract webhook headers: {:?}", e);
-        let (status, _message) = e.into();
-        (status, Json(VideoGenError::AuthError))
+        ApiError::from(e)
     })?;
 
     // Get signing secret
-    let signing_secret =
-        get_webhook_signing_secret().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(e)))?;
+    let signing_secret = get_webhook_signing_secret().map_err(|_| {
+        ApiError::Internal("Replicate webhook signing secret not configured".to_string())
+    })?;
 
     // Verify webhook signature
     verify_webhook_signature(&webhook_headers, &payload_bytes, &signing_secret).map_err(|e| {
         log::error!("Webhook signature verification failed: {:?}", e);
-        let (status, _message) = e.into();
-        (status, Json(VideoGenError::AuthError))
+        ApiError::from(e)
     })?;
 
     // Parse webhook payload
-    let payload: ReplicateWebhookPayload = serde_json::from_slice(&payload_bytes).map_err(|e| {
-        log::error!("Failed to parse webhook payload: {e}");
-        (
-            StatusCode::BAD_REQUEST,
-            Json(VideoGenError::ProviderError(format!(
-                "Failed to parse webhook payload: {e}"
-            ))),
-        )
-    })?;
+    let payload: ReplicateWebhookPayload = serde_json::from_slice(&payload_bytes)
+        .map_err(|e| ApiError::InvalidRequest(format!("Failed to parse webhook payload: {e}")))?;
+
+    // A signed delivery stays valid for the whole timestamp window, so
+    // remember its id and reject a second copy
+    #[cfg(not(feature = "local-bin"))]
+    if !super::webhook_signature::claim_webhook_id(
+        &state.yral_redis_store_dragonfly,
+        &webhook_headers.id,
+    )
+    .await?
+    {
+        log::warn!(
+            "Rejecting replayed Replicate webhook {} for prediction {}",
+            webhook_headers.id,
+            payload.id
+        );
+        return Err(ApiError::Conflict(format!(
+            "Webhook {} was already processed",
+            webhook_headers.id
+        )));
+    }
 
     log::info!(
         "Processing Replicate webhook for prediction {} with status {}",
@@ -195,12 +227,37 @@ pub async fn handle_replicate_webhook(
                     .as_ref()
                     .and_then(|m| m.get("encrypted_identity"))
                     .and_then(|v| v.as_str().map(|s| s.to_string())),
+                model_id: Some(video_gen_request.model_name.clone()),
+                prompt_hash: payload
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.get("prompt_hash"))
+                    .and_then(|v| v.as_str().map(|s| s.to_string())),
             };
 
             // Use existing callback handler logic
-            crate::videogen::qstash_callback::handle_video_gen_callback_internal(state, callback)
+            if let Err((status, error)) =
+                crate::videogen::qstash_callback::handle_video_gen_callback_internal(
+                    state.clone(),
+                    callback,
+                )
+                .await
+            {
+                // Let Replicate's retry through
+                #[cfg(not(feature = "local-bin"))]
+                if let Err(e) = super::webhook_signature::release_webhook_id(
+                    &state.yral_redis_store_dragonfly,
+                    &webhook_headers.id,
+                )
                 .await
-                .map_err(|(status, error)| (status, Json(VideoGenError::ProviderError(error))))?;
+                {
+                    log::warn!("Failed to release webhook id {}: {e}", webhook_headers.id);
+                }
+                return Err(ApiError::VideoGen {
+                    status,
+                    error: VideoGenError::ProviderError(error),
+                });
+            }
 
             log::info!(
                 "Successfully processed Replicate webhook for prediction {}",
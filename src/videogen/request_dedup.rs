@@ -0,0 +1,145 @@
+//! Collapses identical generation requests fired in quick succession.
+//!
+//! A request is identified by its user, model, prompt and input image. The
+//! first request claims a Dragonfly key for that fingerprint and, once queued,
+//! stores its request key there; an identical request within the TTL gets the
+//! stored key back instead of a second paid generation.
+
+use candid::Principal;
+use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
+use videogen_common::ImageData;
+
+use crate::{error::ApiError, videogen::VideoGenRequestKey, yral_auth::dragonfly::DragonflyPool};
+
+const KEY_PREFIX: &str = "offchain:videogen:dedup";
+const DEDUP_TTL_SECS: u64 = 120;
+/// Held while the first request is still being queued
+const PENDING: &str = "pending";
+
+pub enum Claim {
+    /// An identical request was already queued
+    InFlight(VideoGenRequestKey),
+    /// This request owns the fingerprint; call [`complete`] or [`release`]
+    Claimed,
+}
+
+/// Hash of the input image as the user sent it, before any GCS upload
+/// rewrites it to a fresh URL
+pub fn image_fingerprint(image: Option<&ImageData>) -> Option<String> {
+    let bytes = match image? {
+        ImageData::Url(url) => url.as_bytes(),
+        ImageData::Base64(input) => input.data.as_bytes(),
+    };
+    Some(hex::encode(Sha256::digest(bytes)))
+}
+
+pub fn fingerprint(
+    user: Principal,
+    model_id: &str,
+    prompt: &str,
+    image_fingerprint: Option<&str>,
+) -> String {
+    let digest = Sha256::new()
+        .chain_update(user.as_slice())
+        .chain_update([0])
+        .chain_update(model_id.as_bytes())
+        .chain_update([0])
+        .chain_update(prompt.trim().as_bytes())
+        .chain_update([0])
+        .chain_update(image_fingerprint.unwrap_or_default().as_bytes())
+        .finalize();
+    hex::encode(digest)
+}
+
+fn dedup_key(fingerprint: &str) -> String {
+    format!("{KEY_PREFIX}:{fingerprint}")
+}
+
+/// Claims the fingerprint, or returns the request already holding it.
+/// `force` takes the fingerprint over for an intentional re-run.
+pub async fn claim(
+    pool: &DragonflyPool,
+    fingerprint: &str,
+    force: bool,
+) -> Result<Claim, ApiError> {
+    let mut conn = pool.get().await?;
+    let key = dedup_key(fingerprint);
+
+    if force {
+        let _: () = conn.set_ex(&key, PENDING, DEDUP_TTL_SECS).await?;
+        return Ok(Claim::Claimed);
+    }
+
+    let claimed: Option<String> = redis::cmd("SET")
+        .arg(&key)
+        .arg(PENDING)
+        .arg("NX")
+        .arg("EX")
+        .arg(DEDUP_TTL_SECS)
+        .query_async(&mut conn)
+        .await?;
+    if claimed.is_some() {
+        return Ok(Claim::Claimed);
+    }
+
+    let existing: Option<String> = conn.get(&key).await?;
+    match existing.and_then(|existing| serde_json::from_str(&existing).ok()) {
+        Some(request_key) => Ok(Claim::InFlight(request_key)),
+        None => Err(ApiError::Conflict(
+            "An identical generation request is already being started".to_string(),
+        )),
+    }
+}
+
+/// Points the fingerprint at the queued request
+pub async fn complete(pool: &DragonflyPool, fingerprint: &str, request_key: &VideoGenRequestKey) {
+    let result: anyhow::Result<()> = async {
+        let mut conn = pool.get().await?;
+        let _: () = conn
+            .set_ex(
+                dedup_key(fingerprint),
+                serde_json::to_string(request_key)?,
+                DEDUP_TTL_SECS,
+            )
+            .await?;
+        Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        log::warn!("Failed to record videogen dedup key: {e}");
+    }
+}
+
+/// Frees the fingerprint after a failed request so the user can retry
+pub async fn release(pool: &DragonflyPool, fingerprint: &str) {
+    let result: anyhow::Result<()> = async {
+        let mut conn = pool.get().await?;
+        let _: () = conn.del(dedup_key(fingerprint)).await?;
+        Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        log::warn!("Failed to release videogen dedup key: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        let user = Principal::from_slice(&[1; 29]);
+        let image = image_fingerprint(Some(&ImageData::Url("https://a/b.png".to_string())));
+
+        let base = fingerprint(user, "veo3", "a cat", image.as_deref());
+        assert_eq!(base, fingerprint(user, "veo3", " a cat ", image.as_deref()));
+        assert_ne!(base, fingerprint(user, "veo3", "a cat", None));
+        assert_ne!(base, fingerprint(user, "ltx2", "a cat", image.as_deref()));
+        assert_ne!(
+            base,
+            fingerprint(Principal::anonymous(), "veo3", "a cat", image.as_deref())
+        );
+    }
+}
@@ -1,4 +1,3 @@
-use axum::{routing::post, Router};
 use std::sync::Arc;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
@@ -9,9 +8,12 @@ use crate::{
 
 /// V1 API routes for video generation
 pub fn videogen_router<S>(state: Arc<AppState>) -> OpenApiRouter<S> {
-    OpenApiRouter::new()
-        .routes(routes!(handlers::generate_video_with_identity))
-        .with_state(state)
+    let router = OpenApiRouter::new().routes(routes!(handlers::generate_video_with_identity));
+
+    #[cfg(not(feature = "local-bin"))]
+    let router = router.routes(routes!(crate::videogen::costs::get_costs_summary));
+
+    router.with_state(state)
 }
 
 /// V2 API routes for video generation
@@ -25,19 +27,16 @@ pub fn videogen_router_v2<S>(state: Arc<AppState>) -> OpenApiRouter<S> {
         .with_state(state)
 }
 
-/// Replicate webhook router - separate from API docs since it's an internal endpoint
-pub fn replicate_webhook_router(state: Arc<AppState>) -> Router<Arc<AppState>> {
-    Router::new()
-        .route(
-            "/webhook",
-            post(replicate_webhook::handle_replicate_webhook),
-        )
+/// Replicate webhook router - documented in the internal API docs
+pub fn replicate_webhook_router<S>(state: Arc<AppState>) -> OpenApiRouter<S> {
+    OpenApiRouter::new()
+        .routes(routes!(replicate_webhook::handle_replicate_webhook))
         .with_state(state)
 }
 
-/// ComfyUI webhook router - separate from API docs since it's an internal endpoint
-pub fn comfyui_webhook_router(state: Arc<AppState>) -> Router<Arc<AppState>> {
-    Router::new()
-        .route("/webhook", post(comfyui_webhook::handle_comfyui_webhook))
+/// ComfyUI webhook router - documented in the internal API docs
+pub fn comfyui_webhook_router<S>(state: Arc<AppState>) -> OpenApiRouter<S> {
+    OpenApiRouter::new()
+        .routes(routes!(comfyui_webhook::handle_comfyui_webhook))
         .with_state(state)
 }
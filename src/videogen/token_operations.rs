@@ -202,9 +202,13 @@ pub async fn deduct_balance_with_cleanup(
                 counter: request_key.counter,
             };
 
-            if let Err(dec_err) = rate_limits_client
-                .decrement_video_generation_counter_v_1(canister_key, property.to_string())
-                .await
+            if let Err(dec_err) = crate::canister::agent_pool::update(
+                *crate::consts::RATE_LIMITS_CANISTER_ID,
+                "decrement_video_generation_counter_v_1",
+                rate_limits_client
+                    .decrement_video_generation_counter_v_1(canister_key, property.to_string()),
+            )
+            .await
             {
                 log::error!("Failed to decrement rate limit counter after balance deduction failure: {dec_err}");
             }
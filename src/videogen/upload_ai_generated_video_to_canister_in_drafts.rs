@@ -5,21 +5,37 @@ use crate::{
     app_state::AppState,
     consts::{STORJ_INTERFACE_TOKEN, USER_POST_SERVICE_CANISTER_ID, YRAL_UPLOAD_SERVICE},
 };
+use crate::{
+    provenance::{GenerationProvenance, ProvenanceRecord},
+    videogen::watermark::{self, WatermarkConfig},
+};
 use candid::Principal;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use utoipa::ToSchema;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct UploadAiVideoToCanisterRequest {
     pub ai_video_url: String,
+    #[schema(value_type = String)]
     pub user_id: Principal,
     pub delegated_identity: Option<yral_types::delegated_identity::DelegatedIdentityWire>,
+    /// Selects the watermark policy; absent on messages queued before it
+    /// was added
+    #[serde(default)]
+    pub model_id: Option<String>,
+    /// Embedded as a C2PA manifest before upload
+    #[serde(default)]
+    pub provenance: Option<GenerationProvenance>,
 }
 
 pub async fn upload_ai_generated_video_to_canister_impl(
+    state: &AppState,
     ai_video_url: &str,
     user_id: Principal,
     delegated_identity: Option<yral_types::delegated_identity::DelegatedIdentityWire>,
+    model_id: Option<&str>,
+    provenance: Option<&GenerationProvenance>,
 ) -> Result<(), Box<dyn Error>> {
     let mut request = reqwest::Client::new().get(ai_video_url);
 
@@ -45,7 +61,15 @@ pub async fn upload_ai_generated_video_to_canister_impl(
         .into());
     }
 
-    let video_bytes = video_fetch_response.bytes().await?;
+    let mut video_bytes = video_fetch_response.bytes().await?.to_vec();
+
+    let watermark_config = WatermarkConfig::from_env();
+    if watermark_config.applies_to(model_id) {
+        video_bytes = watermark::apply(&video_bytes, &watermark_config)
+            .await
+            .map_err(|e| format!("Failed to watermark generated video: {e:?}"))?;
+    }
+
     let get_video_upload_url = YRAL_UPLOAD_SERVICE.join("/get-upload-url")?;
     let client = reqwest::Client::new();
     let get_video_upload_res = client
@@ -108,9 +132,30 @@ pub async fn upload_ai_generated_video_to_canister_impl(
         .video_id
         .ok_or_else(|| "Video ID not found in response".to_string())?;
 
+    // Sign after watermarking, since re-encoding would drop the manifest
+    if let Some(provenance) = provenance {
+        let signed_video = crate::provenance::embed(&video_bytes, &video_id, provenance)
+            .await
+            .map_err(|e| format!("Failed to embed provenance manifest: {e:?}"))?;
+        let signed = signed_video.is_some();
+        if let Some(signed_video) = signed_video {
+            video_bytes = signed_video;
+        }
+        crate::provenance::record_provenance(
+            state,
+            &ProvenanceRecord {
+                video_id: video_id.clone(),
+                publisher_user_id: user_id.to_text(),
+                provenance: provenance.clone(),
+                signed,
+            },
+        )
+        .await;
+    }
+
     let stream_upload_form = reqwest::multipart::Form::new().part(
         "file",
-        reqwest::multipart::Part::bytes(video_bytes.to_vec())
+        reqwest::multipart::Part::bytes(video_bytes)
             .file_name(format!("{}.mp4", video_id))
             .mime_str("video/mp4")
             .map_err(|e| Box::<dyn Error>::from(format!("Failed to set MIME type: {e}")))?,
@@ -14,6 +14,7 @@ use super::qstash_types::QstashVideoGenRequest;
 use super::token_operations::add_token_balance;
 use crate::app_state::AppState;
 use crate::consts::OFF_CHAIN_AGENT_URL;
+use crate::qstash::job::PublishOptions;
 
 /// Metadata extracted from a video generation request
 pub struct RequestMetadata {
@@ -82,19 +83,15 @@ pub fn create_user_agent_if_dolr(
     token_type: &TokenType,
 ) -> Result<Option<Agent>, (StatusCode, Json<VideoGenError>)> {
     if matches!(token_type, TokenType::Dolr) {
-        let agent = Agent::builder()
-            .with_identity(identity)
-            .with_url("https://ic0.app")
-            .build()
-            .map_err(|e| {
-                log::error!("Failed to build agent: {e}");
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(VideoGenError::NetworkError(
-                        "Failed to create agent".to_string(),
-                    )),
-                )
-            })?;
+        let agent = crate::canister::agent_pool::user_agent(identity).map_err(|e| {
+            log::error!("Failed to build agent: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(VideoGenError::NetworkError(
+                    "Failed to create agent".to_string(),
+                )),
+            )
+        })?;
         Ok(Some(agent))
     } else {
         Ok(None)
@@ -173,12 +170,11 @@ pub async fn queue_to_qstash_with_rollback(
     // Attempt to queue
     if let Err(e) = app_state
         .qstash_client
-        .queue_video_generation(
+        .publish_job_with(
             &qstash_request,
-            if uses_webhook {
-                None
-            } else {
-                Some(&callback_url)
+            PublishOptions {
+                callback_url: (!uses_webhook).then_some(callback_url),
+                ..Default::default()
             },
         )
         .await
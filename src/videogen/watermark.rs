@@ -0,0 +1,227 @@
+//! Visible provenance overlay for generated videos.
+//!
+//! Burns an "AI generated" label, and optionally a logo, into a corner of the
+//! video before it is uploaded to drafts. Configured through env:
+//! `VIDEOGEN_WATERMARK_MODELS` (comma-separated model ids, `*` for all,
+//! empty to disable), `VIDEOGEN_WATERMARK_LOGO` (path to a PNG),
+//! `VIDEOGEN_WATERMARK_CORNER` and `VIDEOGEN_WATERMARK_LABEL`.
+
+use std::{path::PathBuf, process::Command};
+
+use anyhow::{Context, Result};
+use uuid::Uuid;
+
+const DEFAULT_LABEL: &str = "AI generated";
+const MARGIN_PX: u32 = 24;
+/// Logo width as a fraction of the video width
+const LOGO_WIDTH_RATIO: f32 = 0.12;
+/// Padding drawn around the label text
+const LABEL_BORDER_PX: u32 = 8;
+/// Space between the logo and the label's padded box
+const LABEL_GAP_PX: u32 = 8 + LABEL_BORDER_PX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "top_left" => Some(Self::TopLeft),
+            "top_right" => Some(Self::TopRight),
+            "bottom_left" => Some(Self::BottomLeft),
+            "bottom_right" => Some(Self::BottomRight),
+            _ => None,
+        }
+    }
+
+    /// ffmpeg x/y expressions for an item `w`x`h` inside a `W`x`H` frame.
+    /// `beside` is the width of a box already in the corner; the item is
+    /// pushed past it horizontally and shares its top or bottom edge.
+    fn position(self, w: &str, h: &str, beside: Option<&str>) -> (String, String) {
+        let inset = match beside {
+            Some(width) => format!("{MARGIN_PX}+{width}"),
+            None => MARGIN_PX.to_string(),
+        };
+        let far_x = match beside {
+            Some(_) => format!("W-{w}-({inset})"),
+            None => format!("W-{w}-{inset}"),
+        };
+        let near_y = MARGIN_PX.to_string();
+        let far_y = format!("H-{h}-{MARGIN_PX}");
+        match self {
+            Self::TopLeft => (inset, near_y),
+            Self::TopRight => (far_x, near_y),
+            Self::BottomLeft => (inset, far_y),
+            Self::BottomRight => (far_x, far_y),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WatermarkConfig {
+    /// `None` watermarks every model
+    pub models: Option<Vec<String>>,
+    pub logo_path: Option<PathBuf>,
+    pub corner: Corner,
+    pub label: String,
+}
+
+impl WatermarkConfig {
+    pub fn from_env() -> Self {
+        let models = match std::env::var("VIDEOGEN_WATERMARK_MODELS") {
+            Ok(value) if value.trim() == "*" => None,
+            Ok(value) => Some(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|model| !model.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            ),
+            Err(_) => None,
+        };
+        Self {
+            models,
+            logo_path: std::env::var("VIDEOGEN_WATERMARK_LOGO")
+                .ok()
+                .map(PathBuf::from),
+            corner: std::env::var("VIDEOGEN_WATERMARK_CORNER")
+                .ok()
+                .and_then(|value| Corner::parse(&value))
+                .unwrap_or(Corner::BottomRight),
+            label: std::env::var("VIDEOGEN_WATERMARK_LABEL")
+                .unwrap_or_else(|_| DEFAULT_LABEL.to_string()),
+        }
+    }
+
+    /// Unknown models are watermarked unless an explicit list excludes them
+    pub fn applies_to(&self, model_id: Option<&str>) -> bool {
+        match (&self.models, model_id) {
+            (None, _) => true,
+            (Some(models), Some(model_id)) => models.iter().any(|model| model == model_id),
+            (Some(models), None) => !models.is_empty(),
+        }
+    }
+
+    /// `-filter_complex` graph; the logo, when present, is input 1
+    fn filter_graph(&self) -> String {
+        let label = self
+            .label
+            .replace('\\', "\\\\")
+            .replace('\'', "\\'")
+            .replace(':', "\\:");
+        // The logo is scaled to a fixed share of the frame width, so its box
+        // is known up front and the label can sit beside it at any size
+        let logo_box = format!("W*{LOGO_WIDTH_RATIO}+{LABEL_GAP_PX}");
+        let beside = self.logo_path.is_some().then_some(logo_box.as_str());
+        let (label_x, label_y) = self.corner.position("text_w", "text_h", beside);
+        let drawtext = format!(
+            "drawtext=text='{label}':fontcolor=white@0.85:fontsize=h/28:box=1:boxcolor=black@0.35:boxborderw={LABEL_BORDER_PX}:x={label_x}:y={label_y}"
+        );
+
+        if self.logo_path.is_none() {
+            return format!("[0:v]{drawtext}[out]");
+        }
+        let (logo_x, logo_y) = self.corner.position("w", "h", None);
+        format!(
+            "[1:v][0:v]scale2ref=w=main_w*{LOGO_WIDTH_RATIO}:h=ow/mdar[logo][base];\
+             [base][logo]overlay=x={logo_x}:y={logo_y}[marked];\
+             [marked]{drawtext}[out]"
+        )
+    }
+}
+
+/// The video with the watermark burned in. Audio is copied untouched.
+pub async fn apply(video: &[u8], config: &WatermarkConfig) -> Result<Vec<u8>> {
+    let work_dir = std::env::temp_dir().join(format!("watermark_{}", Uuid::new_v4()));
+    tokio::fs::create_dir_all(&work_dir).await?;
+    let result = apply_in(&work_dir, video, config).await;
+    let _ = tokio::fs::remove_dir_all(&work_dir).await;
+    result
+}
+
+async fn apply_in(
+    work_dir: &std::path::Path,
+    video: &[u8],
+    config: &WatermarkConfig,
+) -> Result<Vec<u8>> {
+    let input = work_dir.join("input.mp4");
+    let output = work_dir.join("output.mp4");
+    tokio::fs::write(&input, video).await?;
+
+    let filter_graph = config.filter_graph();
+    let logo_path = config.logo_path.clone();
+    let (input_arg, output_arg) = (input.clone(), output.clone());
+    let ffmpeg = tokio::task::spawn_blocking(move || {
+        let mut command = Command::new("ffmpeg");
+        command
+            .args(["-loglevel", "error", "-y", "-i"])
+            .arg(&input_arg);
+        if let Some(logo_path) = &logo_path {
+            command.arg("-i").arg(logo_path);
+        }
+        command
+            .arg("-filter_complex")
+            .arg(&filter_graph)
+            .args(["-map", "[out]", "-map", "0:a:0?"])
+            .args(["-c:v", "libx264", "-preset", "veryfast", "-crf", "20"])
+            .args(["-c:a", "copy", "-movflags", "+faststart"])
+            .arg(&output_arg)
+            .output()
+    })
+    .await??;
+
+    if !ffmpeg.status.success() {
+        return Err(anyhow::anyhow!(
+            "ffmpeg failed to watermark video: {}",
+            String::from_utf8_lossy(&ffmpeg.stderr)
+        ));
+    }
+
+    tokio::fs::read(&output)
+        .await
+        .context("Failed to read watermarked video")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watermark_config() {
+        let mut config = WatermarkConfig {
+            models: Some(vec!["wan2_5".to_string()]),
+            logo_path: None,
+            corner: Corner::BottomRight,
+            label: "AI: generated".to_string(),
+        };
+        assert!(config.applies_to(Some("wan2_5")));
+        assert!(!config.applies_to(Some("ltx2")));
+        assert!(config.applies_to(None));
+
+        let graph = config.filter_graph();
+        assert!(graph.starts_with("[0:v]drawtext=text='AI\\: generated'"));
+        assert!(graph.contains("x=W-text_w-24:y=H-text_h-24"));
+
+        config.logo_path = Some(PathBuf::from("/logo.png"));
+        config.corner = Corner::TopLeft;
+        let graph = config.filter_graph();
+        assert!(graph.contains("overlay=x=24:y=24[marked]"));
+        assert!(graph.contains("x=24+W*0.12+16:y=24[out]"));
+
+        config.corner = Corner::BottomRight;
+        let graph = config.filter_graph();
+        assert!(graph.contains("overlay=x=W-w-24:y=H-h-24[marked]"));
+        assert!(graph.contains("x=W-text_w-(24+W*0.12+16):y=H-text_h-24[out]"));
+        assert!(graph.ends_with("[out]"));
+
+        config.models = Some(vec![]);
+        assert!(!config.applies_to(None));
+        assert_eq!(Corner::parse("middle"), None);
+    }
+}
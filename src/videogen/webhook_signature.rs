@@ -4,8 +4,19 @@ use hmac::{Hmac, Mac};
 use sha2::Sha256;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::error::ApiError;
+
 type HmacSha256 = Hmac<Sha256>;
 
+/// Deliveries signed further than this from now are rejected
+const TIMESTAMP_TOLERANCE_SECS: u64 = 5 * 60;
+/// A replay has to carry a timestamp inside the tolerance on either side of
+/// now, so ids only need remembering for twice that
+#[cfg(not(feature = "local-bin"))]
+const SEEN_ID_TTL_SECS: u64 = 2 * TIMESTAMP_TOLERANCE_SECS;
+#[cfg(not(feature = "local-bin"))]
+const SEEN_ID_KEY_PREFIX: &str = "offchain:replicate_webhook:seen";
+
 /// Error types for webhook signature verification
 #[derive(Debug)]
 pub enum WebhookError {
@@ -42,6 +53,24 @@ impl From<WebhookError> for (StatusCode, String) {
     }
 }
 
+/// Unsigned and badly signed deliveries are both 401 for the webhook route
+impl From<WebhookError> for ApiError {
+    fn from(error: WebhookError) -> Self {
+        let unauthorized = matches!(
+            error,
+            WebhookError::InvalidSignature
+                | WebhookError::TimestampOutOfRange
+                | WebhookError::MissingHeaders
+        );
+        let (_, message): (StatusCode, String) = error.into();
+        if unauthorized {
+            ApiError::Unauthorized(message)
+        } else {
+            ApiError::InvalidRequest(message)
+        }
+    }
+}
+
 /// Replicate webhook headers
 pub struct WebhookHeaders {
     pub id: String,
@@ -162,15 +191,44 @@ fn validate_timestamp(timestamp_str: &str) -> Result<(), WebhookError> {
         .map_err(|_| WebhookError::InvalidTimestamp)?
         .as_secs();
 
-    const FIVE_MINUTES: u64 = 5 * 60; // 5 minutes in seconds
-
-    if timestamp.abs_diff(current_time) > FIVE_MINUTES {
+    if timestamp.abs_diff(current_time) > TIMESTAMP_TOLERANCE_SECS {
         return Err(WebhookError::TimestampOutOfRange);
     }
 
     Ok(())
 }
 
+/// Records a verified webhook id. Returns false when the id was already
+/// seen, i.e. the delivery is a replay.
+#[cfg(not(feature = "local-bin"))]
+pub async fn claim_webhook_id(
+    pool: &crate::yral_auth::dragonfly::DragonflyPool,
+    id: &str,
+) -> anyhow::Result<bool> {
+    let mut conn = pool.get().await?;
+    let claimed: Option<String> = redis::cmd("SET")
+        .arg(format!("{SEEN_ID_KEY_PREFIX}:{id}"))
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(SEEN_ID_TTL_SECS)
+        .query_async(&mut conn)
+        .await?;
+    Ok(claimed.is_some())
+}
+
+/// Forgets a webhook id so Replicate's retry of a delivery we failed to
+/// process is not rejected as a replay
+#[cfg(not(feature = "local-bin"))]
+pub async fn release_webhook_id(
+    pool: &crate::yral_auth::dragonfly::DragonflyPool,
+    id: &str,
+) -> anyhow::Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = redis::AsyncCommands::del(&mut conn, format!("{SEEN_ID_KEY_PREFIX}:{id}")).await?;
+    Ok(())
+}
+
 /// Constant time string comparison to prevent timing attacks
 fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
     if a.len() != b.len() {
@@ -211,6 +269,26 @@ mod tests {
         assert_eq!(webhook_headers.signature, "v1=abcd1234");
     }
 
+    #[test]
+    fn test_webhook_error_statuses() {
+        assert_eq!(
+            ApiError::from(WebhookError::InvalidSignature).status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            ApiError::from(WebhookError::TimestampOutOfRange).status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            ApiError::from(WebhookError::MissingHeaders).status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            ApiError::from(WebhookError::InvalidTimestamp).status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
     #[test]
     fn test_constant_time_eq() {
         assert!(constant_time_eq(b"hello", b"hello"));
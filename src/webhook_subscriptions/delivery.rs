@@ -0,0 +1,193 @@
+use std::{net::SocketAddr, sync::Arc, time::Instant};
+
+use axum::{extract::State, http::HeaderMap, Json};
+use http::{header::CONTENT_TYPE, StatusCode};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use super::{
+    load_subscription, record_delivery, resolve_target, signature_header, DeliveryRecord,
+    WebhookEnvelope,
+};
+use crate::{
+    app_state::AppState,
+    error::{ApiError, ApiErrorBody},
+};
+
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+
+/// Client pinned to the addresses `resolve_target` just checked, so the
+/// connection cannot land somewhere a second DNS lookup points to
+fn pinned_client(host: &str, addrs: &[SocketAddr]) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(host, addrs)
+        .build()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDeliveryJob {
+    pub subscription_id: String,
+    pub envelope: WebhookEnvelope,
+}
+
+/// Statuses worth retrying; any other 4xx means the subscriber rejected the
+/// payload and will keep doing so
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+/// QStash job: delivers one webhook. Returns 502 on retryable failures so
+/// QStash redelivers with backoff.
+#[utoipa::path(
+    post,
+    path = "/webhooks/deliver",
+    request_body = WebhookDeliveryJob,
+    responses(
+        (status = 200, description = "Delivered, or dropped for good"),
+        (status = 502, description = "Subscriber unreachable or failing", body = ApiErrorBody)
+    ),
+    tag = "qstash"
+)]
+#[instrument(skip(state, headers, job), fields(subscription_id = %job.subscription_id))]
+pub async fn deliver_webhook_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(job): Json<WebhookDeliveryJob>,
+) -> Result<StatusCode, ApiError> {
+    let pool = &state.yral_redis_store_dragonfly;
+    let Some(subscription) = load_subscription(pool, &job.subscription_id).await? else {
+        log::info!(
+            "Webhook subscription {} removed, dropping delivery",
+            job.subscription_id
+        );
+        return Ok(StatusCode::OK);
+    };
+    let event = job.envelope.event;
+    if !subscription.wants(event) {
+        log::info!(
+            "Webhook subscription {} no longer wants {}, dropping delivery",
+            subscription.id,
+            event.as_str()
+        );
+        return Ok(StatusCode::OK);
+    }
+
+    let attempt = headers
+        .get("Upstash-Retried")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u32>().ok())
+        .unwrap_or(0)
+        + 1;
+    let body = serde_json::to_vec(&job.envelope).map_err(anyhow::Error::from)?;
+    let timestamp = chrono::Utc::now().timestamp();
+
+    let started = Instant::now();
+    let (host, addrs) = match resolve_target(&subscription.url).await {
+        Ok(target) => target,
+        Err(e) => {
+            // Not retried: the subscriber has to be fixed by an operator
+            log::warn!(
+                "Webhook subscription {} has an unusable target, dropping delivery: {e}",
+                subscription.id
+            );
+            let record = DeliveryRecord {
+                delivery_id: job.envelope.delivery_id.clone(),
+                event,
+                attempt,
+                attempted_at: timestamp,
+                duration_ms: started.elapsed().as_millis() as u64,
+                status: None,
+                success: false,
+                error: Some(e),
+            };
+            if let Err(e) = record_delivery(pool, &subscription.id, &record).await {
+                log::warn!(
+                    "Failed to record webhook delivery {}: {e:?}",
+                    record.delivery_id
+                );
+            }
+            return Ok(StatusCode::OK);
+        }
+    };
+    let client = pinned_client(&host, &addrs).map_err(anyhow::Error::from)?;
+    let result = client
+        .post(&subscription.url)
+        .header(CONTENT_TYPE, "application/json")
+        .header("X-Yral-Event", event.as_str())
+        .header("X-Yral-Delivery", &job.envelope.delivery_id)
+        .header(
+            "X-Yral-Signature",
+            signature_header(&subscription.secret, timestamp, &body),
+        )
+        .body(body)
+        .send()
+        .await;
+
+    let mut record = DeliveryRecord {
+        delivery_id: job.envelope.delivery_id.clone(),
+        event,
+        attempt,
+        attempted_at: timestamp,
+        duration_ms: started.elapsed().as_millis() as u64,
+        status: None,
+        success: false,
+        error: None,
+    };
+    let retry = match result {
+        Ok(response) => {
+            let status = response.status();
+            record.status = Some(status.as_u16());
+            record.success = status.is_success();
+            !record.success && is_retryable(status)
+        }
+        Err(e) => {
+            record.error = Some(e.to_string());
+            true
+        }
+    };
+
+    if let Err(e) = record_delivery(pool, &subscription.id, &record).await {
+        log::warn!(
+            "Failed to record webhook delivery {}: {e:?}",
+            record.delivery_id
+        );
+    }
+
+    if retry {
+        return Err(ApiError::Upstream(format!(
+            "Webhook delivery {} to {} failed (attempt {attempt}): {}",
+            record.delivery_id,
+            subscription.id,
+            record
+                .error
+                .unwrap_or_else(|| format!("status {:?}", record.status))
+        )));
+    }
+    if !record.success {
+        log::warn!(
+            "Webhook delivery {} rejected by {} with {:?}, not retrying",
+            record.delivery_id,
+            subscription.id,
+            record.status
+        );
+    }
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(is_retryable(StatusCode::BAD_GATEWAY));
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_retryable(StatusCode::BAD_REQUEST));
+        assert!(!is_retryable(StatusCode::GONE));
+    }
+}
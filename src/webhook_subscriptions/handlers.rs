@@ -0,0 +1,301 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+
+use super::{
+    generate_secret, load_deliveries, load_subscription, load_subscriptions, remove_subscription,
+    store_subscription, DeliveryRecord, WebhookEvent, WebhookSubscription, DELIVERY_LOG_LEN,
+};
+use crate::{
+    app_state::AppState,
+    auth::require_operator,
+    error::{ApiError, ApiErrorBody},
+};
+
+/// A subscriber as the admin API shows it; the secret is only returned on
+/// creation
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SubscriptionView {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub enabled: bool,
+    /// Last four characters of the signing secret
+    pub secret_hint: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl From<&WebhookSubscription> for SubscriptionView {
+    fn from(subscription: &WebhookSubscription) -> Self {
+        let secret_len = subscription.secret.chars().count();
+        Self {
+            id: subscription.id.clone(),
+            name: subscription.name.clone(),
+            url: subscription.url.clone(),
+            events: subscription.events.clone(),
+            enabled: subscription.enabled,
+            secret_hint: subscription
+                .secret
+                .chars()
+                .skip(secret_len.saturating_sub(4))
+                .collect(),
+            created_at: subscription.created_at,
+            updated_at: subscription.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateSubscriptionRequest {
+    pub name: String,
+    /// HTTPS endpoint receiving the webhooks
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    /// At least 16 characters; generated when omitted
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateSubscriptionResponse {
+    pub subscription: SubscriptionView,
+    /// Signing secret; not shown again
+    pub secret: String,
+}
+
+/// Registered webhook subscribers
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Subscribers", body = Vec<SubscriptionView>),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn list_subscriptions(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<SubscriptionView>>, ApiError> {
+    require_operator(&headers)?;
+
+    let subscriptions = load_subscriptions(&state.yral_redis_store_dragonfly).await?;
+    Ok(Json(
+        subscriptions.iter().map(SubscriptionView::from).collect(),
+    ))
+}
+
+/// Register a webhook subscriber
+#[utoipa::path(
+    post,
+    path = "",
+    request_body = CreateSubscriptionRequest,
+    tag = "admin",
+    responses(
+        (status = 200, description = "Subscriber created", body = CreateSubscriptionResponse),
+        (status = 400, description = "Invalid subscriber", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers, request))]
+pub async fn create_subscription(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<CreateSubscriptionRequest>,
+) -> Result<Json<CreateSubscriptionResponse>, ApiError> {
+    require_operator(&headers)?;
+
+    let now = chrono::Utc::now().timestamp();
+    let subscription = WebhookSubscription {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: request.name,
+        url: request.url,
+        secret: request.secret.unwrap_or_else(generate_secret),
+        events: request.events,
+        enabled: true,
+        created_at: now,
+        updated_at: now,
+    };
+    subscription
+        .validate()
+        .await
+        .map_err(ApiError::InvalidRequest)?;
+
+    store_subscription(&state.yral_redis_store_dragonfly, &subscription).await?;
+    log::warn!(
+        "Webhook subscriber {} ({}) registered for {:?}",
+        subscription.id,
+        subscription.url,
+        subscription.events
+    );
+
+    Ok(Json(CreateSubscriptionResponse {
+        subscription: SubscriptionView::from(&subscription),
+        secret: subscription.secret,
+    }))
+}
+
+/// Fields left out are unchanged
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateSubscriptionRequest {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub events: Option<Vec<WebhookEvent>>,
+    pub enabled: Option<bool>,
+    /// Replaces the signing secret
+    pub secret: Option<String>,
+}
+
+/// Update a webhook subscriber; queued deliveries use the new settings
+#[utoipa::path(
+    put,
+    path = "/{id}",
+    params(("id" = String, Path, description = "Subscriber id")),
+    request_body = UpdateSubscriptionRequest,
+    tag = "admin",
+    responses(
+        (status = 200, description = "Updated subscriber", body = SubscriptionView),
+        (status = 400, description = "Invalid subscriber", body = ApiErrorBody),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Unknown subscriber", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers, request))]
+pub async fn update_subscription(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<UpdateSubscriptionRequest>,
+) -> Result<Json<SubscriptionView>, ApiError> {
+    require_operator(&headers)?;
+
+    let pool = &state.yral_redis_store_dragonfly;
+    let mut subscription = load_subscription(pool, &id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Webhook subscriber {id} not found")))?;
+    if let Some(name) = request.name {
+        subscription.name = name;
+    }
+    if let Some(url) = request.url {
+        subscription.url = url;
+    }
+    if let Some(events) = request.events {
+        subscription.events = events;
+    }
+    if let Some(enabled) = request.enabled {
+        subscription.enabled = enabled;
+    }
+    if let Some(secret) = request.secret {
+        subscription.secret = secret;
+    }
+    subscription.updated_at = chrono::Utc::now().timestamp();
+    subscription
+        .validate()
+        .await
+        .map_err(ApiError::InvalidRequest)?;
+
+    store_subscription(pool, &subscription).await?;
+    log::warn!("Webhook subscriber {id} updated");
+
+    Ok(Json(SubscriptionView::from(&subscription)))
+}
+
+/// Remove a webhook subscriber and its delivery log
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    params(("id" = String, Path, description = "Subscriber id")),
+    tag = "admin",
+    responses(
+        (status = 200, description = "Subscriber removed"),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Unknown subscriber", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn delete_subscription(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<(), ApiError> {
+    require_operator(&headers)?;
+
+    if !remove_subscription(&state.yral_redis_store_dragonfly, &id).await? {
+        return Err(ApiError::NotFound(format!(
+            "Webhook subscriber {id} not found"
+        )));
+    }
+    log::warn!("Webhook subscriber {id} removed");
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListDeliveriesParams {
+    /// Defaults to 50, at most 200
+    pub limit: Option<usize>,
+}
+
+/// Recent delivery attempts for a subscriber, newest first
+#[utoipa::path(
+    get,
+    path = "/{id}/deliveries",
+    params(("id" = String, Path, description = "Subscriber id"), ListDeliveriesParams),
+    tag = "admin",
+    responses(
+        (status = 200, description = "Delivery attempts", body = Vec<DeliveryRecord>),
+        (status = 401, description = "Unauthorized", body = ApiErrorBody),
+        (status = 404, description = "Unknown subscriber", body = ApiErrorBody),
+        (status = 500, description = "Internal server error", body = ApiErrorBody),
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn list_deliveries(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(params): Query<ListDeliveriesParams>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<DeliveryRecord>>, ApiError> {
+    require_operator(&headers)?;
+
+    let pool = &state.yral_redis_store_dragonfly;
+    if load_subscription(pool, &id).await?.is_none() {
+        return Err(ApiError::NotFound(format!(
+            "Webhook subscriber {id} not found"
+        )));
+    }
+    let limit = params
+        .limit
+        .unwrap_or(50)
+        .clamp(1, DELIVERY_LOG_LEN as usize);
+
+    Ok(Json(load_deliveries(pool, &id, limit).await?))
+}
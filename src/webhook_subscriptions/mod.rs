@@ -0,0 +1,389 @@
+//! Outbound webhooks for partner integrations.
+//!
+//! Operators register subscribers under `/api/v1/admin/webhooks`, each with
+//! a URL, a signing secret and the [`WebhookEvent`]s it wants. When one of
+//! those events happens, [`dispatch`] publishes one QStash job per matching
+//! subscriber; `/qstash/webhooks/deliver` then POSTs the payload, and a
+//! failed delivery is retried by QStash with exponential backoff.
+//!
+//! Every request carries `X-Yral-Event`, `X-Yral-Delivery` (stable across
+//! retries, for deduplication) and `X-Yral-Signature: t=<unix>,v1=<hex>`,
+//! where `v1` is the HMAC-SHA256 of `<t>.<body>` under the subscriber's
+//! secret. The last [`DELIVERY_LOG_LEN`] attempts per subscriber are kept
+//! for the delivery-log endpoint, with the subscriber's status but never its
+//! response body.
+//!
+//! Subscriber hosts must resolve to public addresses only. That is checked
+//! when a subscriber is saved and again on every delivery, which connects to
+//! the addresses it just checked so a rebinding DNS answer cannot redirect it.
+
+pub mod delivery;
+pub mod handlers;
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{app_state::AppState, yral_auth::dragonfly::DragonflyPool};
+
+const SUBSCRIPTIONS_KEY: &str = "offchain:webhooks:subscriptions";
+const DELIVERIES_KEY_PREFIX: &str = "offchain:webhooks:deliveries:";
+pub const DELIVERY_LOG_LEN: isize = 200;
+
+pub fn webhooks_admin_router(state: Arc<AppState>) -> OpenApiRouter {
+    OpenApiRouter::new()
+        .routes(routes!(
+            handlers::list_subscriptions,
+            handlers::create_subscription
+        ))
+        .routes(routes!(
+            handlers::update_subscription,
+            handlers::delete_subscription
+        ))
+        .routes(routes!(handlers::list_deliveries))
+        .with_state(state)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    VideoApproved,
+    TournamentFinalized,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::VideoApproved => "video_approved",
+            Self::TournamentFinalized => "tournament_finalized",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSubscription {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    pub enabled: bool,
+    /// Unix timestamp in seconds
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl WebhookSubscription {
+    async fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name is required".to_string());
+        }
+        if self.secret.len() < 16 {
+            return Err("secret must be at least 16 characters".to_string());
+        }
+        if self.events.is_empty() {
+            return Err("events must not be empty".to_string());
+        }
+        resolve_target(&self.url).await?;
+        Ok(())
+    }
+
+    pub fn wants(&self, event: WebhookEvent) -> bool {
+        self.enabled && self.events.contains(&event)
+    }
+}
+
+/// Whether a subscriber may be reached at `ip`: no private, loopback,
+/// link-local, shared, multicast or otherwise reserved ranges
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && ip.octets()[2] == 0)
+                || (a == 198 && (b == 18 || b == 19)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && ip.segments()[1] == 0x0db8)
+                || (first == 0x64 && ip.segments()[1] == 0xff9b))
+        }
+    }
+}
+
+/// Parses a subscriber URL and resolves its host, failing unless it is
+/// https and every address it resolves to is public. Returns the host and
+/// the addresses to connect to.
+pub async fn resolve_target(url: &str) -> Result<(String, Vec<SocketAddr>), String> {
+    let url = reqwest::Url::parse(url).map_err(|e| format!("invalid url: {e}"))?;
+    if url.scheme() != "https" {
+        return Err("url must use https".to_string());
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| "url must have a host".to_string())?
+        .to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await
+        .map_err(|e| format!("cannot resolve {host}: {e}"))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("{host} does not resolve"));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(format!(
+            "{host} resolves to non-public address {}",
+            addr.ip()
+        ));
+    }
+    Ok((host, addrs))
+}
+
+/// One delivery attempt
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeliveryRecord {
+    pub delivery_id: String,
+    pub event: WebhookEvent,
+    /// 1 for the first attempt
+    pub attempt: u32,
+    /// Unix timestamp in seconds
+    pub attempted_at: i64,
+    pub duration_ms: u64,
+    /// Subscriber's HTTP status; absent when the request failed
+    pub status: Option<u16>,
+    pub success: bool,
+    /// Why the request failed before getting a status; response bodies are
+    /// never kept
+    pub error: Option<String>,
+}
+
+/// Body of every webhook request
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookEnvelope {
+    pub delivery_id: String,
+    pub event: WebhookEvent,
+    /// Unix timestamp in seconds
+    pub occurred_at: i64,
+    pub data: serde_json::Value,
+}
+
+/// Value of `X-Yral-Signature` for a request body sent at `timestamp`
+pub fn signature_header(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!(
+        "t={timestamp},v1={}",
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+pub fn generate_secret() -> String {
+    use rand::Rng;
+    let bytes: [u8; 32] = rand::rng().random();
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+pub async fn load_subscriptions(pool: &DragonflyPool) -> Result<Vec<WebhookSubscription>> {
+    let mut conn = pool.get().await?;
+    let entries: HashMap<String, String> = conn.hgetall(SUBSCRIPTIONS_KEY).await?;
+    let mut subscriptions: Vec<WebhookSubscription> = entries
+        .into_values()
+        .filter_map(|payload| serde_json::from_str(&payload).ok())
+        .collect();
+    subscriptions.sort_by_key(|subscription| subscription.created_at);
+    Ok(subscriptions)
+}
+
+pub async fn load_subscription(
+    pool: &DragonflyPool,
+    id: &str,
+) -> Result<Option<WebhookSubscription>> {
+    let mut conn = pool.get().await?;
+    let payload: Option<String> = conn.hget(SUBSCRIPTIONS_KEY, id).await?;
+    Ok(payload.and_then(|payload| serde_json::from_str(&payload).ok()))
+}
+
+async fn store_subscription(
+    pool: &DragonflyPool,
+    subscription: &WebhookSubscription,
+) -> Result<()> {
+    let mut conn = pool.get().await?;
+    let _: () = conn
+        .hset(
+            SUBSCRIPTIONS_KEY,
+            &subscription.id,
+            serde_json::to_string(subscription)?,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Removes a subscriber and its delivery log; false if it did not exist
+async fn remove_subscription(pool: &DragonflyPool, id: &str) -> Result<bool> {
+    let mut conn = pool.get().await?;
+    let removed: u32 = conn.hdel(SUBSCRIPTIONS_KEY, id).await?;
+    let _: () = conn.del(format!("{DELIVERIES_KEY_PREFIX}{id}")).await?;
+    Ok(removed > 0)
+}
+
+pub async fn record_delivery(
+    pool: &DragonflyPool,
+    subscription_id: &str,
+    record: &DeliveryRecord,
+) -> Result<()> {
+    let key = format!("{DELIVERIES_KEY_PREFIX}{subscription_id}");
+    let mut conn = pool.get().await?;
+    let _: () = redis::pipe()
+        .lpush(&key, serde_json::to_string(record)?)
+        .ltrim(&key, 0, DELIVERY_LOG_LEN - 1)
+        .query_async(&mut conn)
+        .await?;
+    Ok(())
+}
+
+/// Most recent attempts first
+pub async fn load_deliveries(
+    pool: &DragonflyPool,
+    subscription_id: &str,
+    limit: usize,
+) -> Result<Vec<DeliveryRecord>> {
+    let mut conn = pool.get().await?;
+    let entries: Vec<String> = conn
+        .lrange(
+            format!("{DELIVERIES_KEY_PREFIX}{subscription_id}"),
+            0,
+            limit.saturating_sub(1) as isize,
+        )
+        .await?;
+    Ok(entries
+        .iter()
+        .filter_map(|payload| serde_json::from_str(payload).ok())
+        .collect())
+}
+
+/// Queues `event` for every subscriber that wants it. Failures are logged;
+/// callers have already committed the change the event describes.
+pub async fn dispatch(state: &AppState, event: WebhookEvent, data: serde_json::Value) {
+    let subscriptions = match load_subscriptions(&state.yral_redis_store_dragonfly).await {
+        Ok(subscriptions) => subscriptions,
+        Err(e) => {
+            log::error!(
+                "Failed to load webhook subscriptions for {}: {e:?}",
+                event.as_str()
+            );
+            return;
+        }
+    };
+
+    let occurred_at = chrono::Utc::now().timestamp();
+    for subscription in subscriptions.iter().filter(|s| s.wants(event)) {
+        let job = delivery::WebhookDeliveryJob {
+            subscription_id: subscription.id.clone(),
+            envelope: WebhookEnvelope {
+                delivery_id: uuid::Uuid::new_v4().to_string(),
+                event,
+                occurred_at,
+                data: data.clone(),
+            },
+        };
+        if let Err(e) = state.qstash_client.publish_job(&job).await {
+            log::error!(
+                "Failed to queue {} webhook for {}: {e:?}",
+                event.as_str(),
+                subscription.id
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_signature_and_validation() {
+        let signature = signature_header("whsec_test_secret", 1_700_000_000, br#"{"a":1}"#);
+        let (timestamp, digest) = signature.split_once(",v1=").unwrap();
+        assert_eq!(timestamp, "t=1700000000");
+        assert_eq!(digest.len(), 64);
+        assert_ne!(
+            signature,
+            signature_header("whsec_other_secret", 1_700_000_000, br#"{"a":1}"#)
+        );
+
+        let mut subscription = WebhookSubscription {
+            id: "sub".to_string(),
+            name: "partner".to_string(),
+            url: "https://93.184.216.34/hooks".to_string(),
+            secret: generate_secret(),
+            events: vec![WebhookEvent::VideoApproved],
+            enabled: true,
+            created_at: 0,
+            updated_at: 0,
+        };
+        assert!(subscription.validate().await.is_ok());
+        assert!(subscription.wants(WebhookEvent::VideoApproved));
+        assert!(!subscription.wants(WebhookEvent::TournamentFinalized));
+
+        subscription.url = "http://93.184.216.34/hooks".to_string();
+        assert!(subscription.validate().await.is_err());
+        subscription.url = "https://169.254.169.254/latest/meta-data".to_string();
+        assert!(subscription.validate().await.is_err());
+        subscription.url = "https://[::1]/hooks".to_string();
+        assert!(subscription.validate().await.is_err());
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in [
+            "10.0.0.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "172.16.5.4",
+            "192.168.1.1",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use axum::{body::Bytes, extract::State, http::HeaderMap, response::IntoResponse};
+use hmac::{Hmac, Mac};
+use http::StatusCode;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::sync::Arc;
+
+use crate::{app_state::AppState, video_processing::queue::mark_stream_ready};
+
+static CLOUDFLARE_STREAM_WEBHOOK_SECRET: Lazy<String> = Lazy::new(|| {
+    std::env::var("CLOUDFLARE_STREAM_WEBHOOK_SECRET")
+        .expect("CLOUDFLARE_STREAM_WEBHOOK_SECRET must be set")
+});
+
+/// Maximum age of a signed webhook before it is treated as a replay
+const SIGNATURE_TOLERANCE_SECS: i64 = 5 * 60;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamWebhookPayload {
+    /// Stream video uid, which is the video_id across the pipeline
+    pub uid: String,
+    #[serde(default)]
+    pub ready_to_stream: bool,
+    pub status: Option<StreamStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamStatus {
+    pub state: Option<String>,
+    pub err_reason_code: Option<String>,
+    pub err_reason_text: Option<String>,
+}
+
+/// Verifies a `Webhook-Signature: time=<unix>,sig1=<hex>` header, where
+/// `sig1` is HMAC-SHA256 of `"<time>.<body>"` keyed with the webhook secret.
+fn verify_stream_signature(body: &[u8], header: &str, secret: &str, now: i64) -> bool {
+    type HmacSha256 = Hmac<Sha256>;
+
+    let mut time = None;
+    let mut signature = None;
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("time", value)) => time = Some(value),
+            Some(("sig1", value)) => signature = Some(value),
+            _ => {}
+        }
+    }
+
+    let (Some(time), Some(signature)) = (time, signature) else {
+        return false;
+    };
+    let Ok(timestamp) = time.parse::<i64>() else {
+        return false;
+    };
+    if (now - timestamp).abs() > SIGNATURE_TOLERANCE_SECS {
+        return false;
+    }
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+
+    let Ok(mut mac) = HmacSha256::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(time.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Cloudflare Stream webhook - starts dedup/NSFW processing as soon as the
+/// video is ready to stream. The fixed dedup delay stays as the fallback for
+/// missed deliveries.
+pub async fn cloudflare_stream_webhook_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let signature = headers
+        .get("webhook-signature")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if !verify_stream_signature(
+        &body,
+        signature,
+        &CLOUDFLARE_STREAM_WEBHOOK_SECRET,
+        chrono::Utc::now().timestamp(),
+    ) {
+        log::warn!("Cloudflare Stream webhook: invalid signature");
+        return (StatusCode::UNAUTHORIZED, "Invalid signature");
+    }
+
+    let payload: StreamWebhookPayload = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            log::warn!("Cloudflare Stream webhook: invalid payload: {}", e);
+            return (StatusCode::BAD_REQUEST, "Invalid payload");
+        }
+    };
+
+    let state_name = payload
+        .status
+        .as_ref()
+        .and_then(|s| s.state.as_deref())
+        .unwrap_or("unknown");
+
+    if state_name == "error" {
+        let status = payload.status.as_ref();
+        log::error!(
+            "Cloudflare Stream transcode failed for {}: {:?} {:?}",
+            payload.uid,
+            status.and_then(|s| s.err_reason_code.as_deref()),
+            status.and_then(|s| s.err_reason_text.as_deref())
+        );
+        return (StatusCode::OK, "Ignored");
+    }
+
+    if !payload.ready_to_stream {
+        log::info!(
+            "Cloudflare Stream webhook: {} not ready yet (state={})",
+            payload.uid,
+            state_name
+        );
+        return (StatusCode::OK, "Ignored");
+    }
+
+    match mark_stream_ready(&state.yral_redis_store_dragonfly, &payload.uid).await {
+        Ok(outcome) => {
+            log::info!(
+                "Cloudflare Stream webhook: {} ready to stream, {:?}",
+                payload.uid,
+                outcome
+            );
+            (StatusCode::OK, "OK")
+        }
+        Err(e) => {
+            log::error!(
+                "Cloudflare Stream webhook: failed to mark {} ready: {:?}",
+                payload.uid,
+                e
+            );
+            // Non-2xx makes Cloudflare retry the delivery
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to process")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, time: i64, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{time}.").as_bytes());
+        mac.update(body);
+        format!(
+            "time={time},sig1={}",
+            hex::encode(mac.finalize().into_bytes())
+        )
+    }
+
+    #[test]
+    fn test_verify_stream_signature() {
+        let body = br#"{"uid":"abc","readyToStream":true}"#;
+        let now = 1_700_000_000;
+        let header = sign("secret", now, body);
+
+        assert!(verify_stream_signature(body, &header, "secret", now));
+        assert!(!verify_stream_signature(body, &header, "other", now));
+        assert!(!verify_stream_signature(b"{}", &header, "secret", now));
+        // Stale deliveries are rejected as replays
+        assert!(!verify_stream_signature(
+            body,
+            &header,
+            "secret",
+            now + SIGNATURE_TOLERANCE_SECS + 1
+        ));
+        assert!(!verify_stream_signature(
+            body,
+            "sig1=deadbeef",
+            "secret",
+            now
+        ));
+    }
+}
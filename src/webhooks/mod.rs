@@ -1,3 +1,5 @@
+#[cfg(not(feature = "local-bin"))]
+pub mod cloudflare_stream;
 pub mod sentry;
 
 pub use sentry::sentry_webhook_handler;
@@ -7,10 +7,13 @@ use redis::AsyncConnectionConfig;
 use redis::Client;
 use redis::ClientTlsConfig;
 use redis::ConnectionAddr;
-use redis::{AsyncCommands, RedisError};
+use redis::{AsyncCommands, ErrorKind, RedisError};
+use serde::Serialize;
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
+use utoipa::ToSchema;
 
 pub const REDIS_SENTINEL_PORT: u16 = 26379;
 pub const SENTINEL_SERVICE_NAME: &str = "mymaster";
@@ -20,6 +23,12 @@ pub const YRAL_AUTH_REDIS_KEY_PREFIX: &str = "yral-auth";
 
 const SENTINEL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
 
+const MAX_RETRY_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(50);
+/// Consecutive connection failures before requests fail fast
+const CIRCUIT_FAILURE_THRESHOLD: u32 = 5;
+const CIRCUIT_OPEN_DURATION: Duration = Duration::from_secs(10);
+
 pub fn format_to_dragonfly_key(key_prefix: &str, key: &str) -> String {
     format!("{key_prefix}:{key}")
 }
@@ -43,21 +52,21 @@ pub fn normalize_pem(pem: String) -> Vec<u8> {
 pub fn get_redis_store_ca_cert() -> Result<Vec<u8>, anyhow::Error> {
     Ok(normalize_pem(
         std::env::var("DRAGONFLY_REDIS_STORE_CA_CERT")
-            .expect("DRAGONFLY_REDIS_STORE_CA_CERT env var not set"),
+            .map_err(|_| anyhow::anyhow!("DRAGONFLY_REDIS_STORE_CA_CERT env var not set"))?,
     ))
 }
 
 pub fn get_redis_store_client_cert() -> Result<Vec<u8>, anyhow::Error> {
     Ok(normalize_pem(
         std::env::var("DRAGONFLY_REDIS_STORE_CLIENT_CERT")
-            .expect("DRAGONFLY_REDIS_STORE_CLIENT_CERT env var not set"),
+            .map_err(|_| anyhow::anyhow!("DRAGONFLY_REDIS_STORE_CLIENT_CERT env var not set"))?,
     ))
 }
 
 pub fn get_redis_store_client_key() -> Result<Vec<u8>, anyhow::Error> {
     Ok(normalize_pem(
         std::env::var("DRAGONFLY_REDIS_STORE_CLIENT_KEY")
-            .expect("DRAGONFLY_REDIS_STORE_CLIENT_KEY env var not set"),
+            .map_err(|_| anyhow::anyhow!("DRAGONFLY_REDIS_STORE_CLIENT_KEY env var not set"))?,
     ))
 }
 
@@ -75,17 +84,17 @@ fn build_tls_certs(
     }
 }
 
-fn get_redis_store_hosts() -> Vec<String> {
+fn get_redis_store_hosts() -> Result<Vec<String>, anyhow::Error> {
     let hosts_str = std::env::var("DRAGONFLY_REDIS_STORE_HOSTS")
-        .expect("DRAGONFLY_REDIS_STORE_HOSTS environment variable not set")
+        .map_err(|_| anyhow::anyhow!("DRAGONFLY_REDIS_STORE_HOSTS environment variable not set"))?
         .trim()
         .to_string();
 
-    hosts_str
+    Ok(hosts_str
         .split(',')
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
-        .collect()
+        .collect())
 }
 
 /// Connection source for DragonflyPool — either Sentinel-managed or a direct client
@@ -99,6 +108,81 @@ enum ConnectionSource {
     },
 }
 
+fn is_connection_error(e: &RedisError) -> bool {
+    e.is_connection_dropped() || e.is_timeout() || e.is_connection_refusal() || e.is_io_error()
+}
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Connection counters plus the circuit breaker state they drive
+#[derive(Default)]
+struct PoolStats {
+    connections_created: AtomicU64,
+    connection_errors: AtomicU64,
+    retries: AtomicU64,
+    circuit_opened: AtomicU64,
+    rejected_while_open: AtomicU64,
+    consecutive_failures: AtomicU32,
+    /// Unix millis until which requests fail fast; 0 when closed
+    open_until_ms: AtomicI64,
+}
+
+impl PoolStats {
+    fn is_open(&self) -> bool {
+        self.open_until_ms.load(Ordering::Relaxed) > now_millis()
+    }
+
+    fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        self.open_until_ms.store(0, Ordering::Relaxed);
+    }
+
+    fn record_failure(&self) {
+        self.connection_errors.fetch_add(1, Ordering::Relaxed);
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        // Once tripped, every failed probe re-opens the circuit
+        if failures >= CIRCUIT_FAILURE_THRESHOLD {
+            let open_until = now_millis() + CIRCUIT_OPEN_DURATION.as_millis() as i64;
+            let previous = self.open_until_ms.swap(open_until, Ordering::Relaxed);
+            if previous <= now_millis() {
+                self.circuit_opened.fetch_add(1, Ordering::Relaxed);
+                tracing::error!(
+                    consecutive_failures = failures,
+                    "Redis circuit breaker opened for {:?}",
+                    CIRCUIT_OPEN_DURATION
+                );
+            }
+        }
+    }
+
+    fn snapshot(&self) -> PoolStatsSnapshot {
+        PoolStatsSnapshot {
+            connections_created: self.connections_created.load(Ordering::Relaxed),
+            connection_errors: self.connection_errors.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            circuit_opened: self.circuit_opened.load(Ordering::Relaxed),
+            rejected_while_open: self.rejected_while_open.load(Ordering::Relaxed),
+            consecutive_failures: self.consecutive_failures.load(Ordering::Relaxed),
+            circuit_open: self.is_open(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PoolStatsSnapshot {
+    pub connections_created: u64,
+    pub connection_errors: u64,
+    pub retries: u64,
+    /// Times the circuit breaker has tripped
+    pub circuit_opened: u64,
+    /// Requests failed fast while the circuit was open
+    pub rejected_while_open: u64,
+    pub consecutive_failures: u32,
+    pub circuit_open: bool,
+}
+
 /// Simple connection pool — wraps either a SentinelConnectionManager or a direct Client.
 /// MultiplexedConnection handles multiplexing internally, so we just need one connection.
 #[derive(Clone)]
@@ -106,6 +190,7 @@ pub struct DragonflyPool {
     connection_source: Arc<ConnectionSource>,
     /// Cached connection - MultiplexedConnection is cheap to clone
     cached_conn: Arc<RwLock<Option<MultiplexedConnection>>>,
+    stats: Arc<PoolStats>,
 }
 
 impl DragonflyPool {
@@ -113,6 +198,7 @@ impl DragonflyPool {
         Arc::new(Self {
             connection_source: Arc::new(ConnectionSource::Sentinel(connection_manager)),
             cached_conn: Arc::new(RwLock::new(None)),
+            stats: Arc::new(PoolStats::default()),
         })
     }
 
@@ -128,11 +214,26 @@ impl DragonflyPool {
                 config,
             }),
             cached_conn: Arc::new(RwLock::new(None)),
+            stats: Arc::new(PoolStats::default()),
         })
     }
 
+    pub fn stats(&self) -> PoolStatsSnapshot {
+        self.stats.snapshot()
+    }
+
     /// Get a connection - returns cached or creates new one
     pub async fn get(&self) -> std::result::Result<MultiplexedConnection, RedisError> {
+        if self.stats.is_open() {
+            self.stats
+                .rejected_while_open
+                .fetch_add(1, Ordering::Relaxed);
+            return Err(RedisError::from((
+                ErrorKind::ClientError,
+                "Redis circuit breaker open",
+            )));
+        }
+
         // Try cached connection first
         {
             let guard = self.cached_conn.read().await;
@@ -185,13 +286,35 @@ impl DragonflyPool {
 
     /// Create a new connection from the underlying source (Sentinel or direct)
     async fn create_connection(&self) -> std::result::Result<MultiplexedConnection, RedisError> {
-        match &*self.connection_source {
+        let result = match &*self.connection_source {
             ConnectionSource::Sentinel(manager) => manager.connect().await,
             ConnectionSource::Direct { client, config } => {
                 client
                     .get_multiplexed_async_connection_with_config(config)
                     .await
             }
+        };
+
+        match &result {
+            Ok(_) => {
+                self.stats
+                    .connections_created
+                    .fetch_add(1, Ordering::Relaxed);
+                self.stats.record_success();
+            }
+            Err(_) => self.stats.record_failure(),
+        }
+        result
+    }
+
+    /// Dedicated pub/sub connection to the current master; not pooled since a
+    /// subscribed connection cannot run other commands
+    pub async fn pubsub(&self) -> std::result::Result<redis::aio::PubSub, RedisError> {
+        match &*self.connection_source {
+            ConnectionSource::Sentinel(manager) => {
+                manager.get_master_client().await?.get_async_pubsub().await
+            }
+            ConnectionSource::Direct { client, .. } => client.get_async_pubsub().await,
         }
     }
 
@@ -201,6 +324,8 @@ impl DragonflyPool {
         *guard = None;
     }
 
+    /// Runs `operation`, reconnecting with exponential backoff on connection
+    /// errors. Other errors are returned straight away.
     pub async fn execute_with_retry<F, Fut, T>(
         &self,
         mut operation: F,
@@ -209,22 +334,31 @@ impl DragonflyPool {
         F: FnMut(MultiplexedConnection) -> Fut,
         Fut: std::future::Future<Output = std::result::Result<T, RedisError>>,
     {
-        let conn = self.get().await?;
-        match operation(conn).await {
-            Ok(result) => Ok(result),
-            Err(e)
-                if e.is_connection_dropped()
-                    || e.is_timeout()
-                    || e.is_connection_refusal()
-                    || e.is_io_error() =>
-            {
-                tracing::warn!(error = %e, "Connection error detected, invalidating cache and retrying");
-                self.invalidate().await;
-
-                let fresh_conn = self.get().await?;
-                operation(fresh_conn).await
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            // Failures to connect are already counted by `create_connection`
+            let (result, from_operation) = match self.get().await {
+                Ok(conn) => (operation(conn).await, true),
+                Err(e) => (Err(e), false),
+            };
+
+            match result {
+                Ok(result) => return Ok(result),
+                Err(e) if is_connection_error(&e) => {
+                    if from_operation {
+                        self.stats.record_failure();
+                    }
+                    if attempt >= MAX_RETRY_ATTEMPTS {
+                        return Err(e);
+                    }
+                    tracing::warn!(error = %e, attempt, "Connection error detected, invalidating cache and retrying");
+                    self.stats.retries.fetch_add(1, Ordering::Relaxed);
+                    self.invalidate().await;
+                    tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+                }
+                Err(e) => return Err(e),
             }
-            Err(e) => Err(e),
         }
     }
 
@@ -464,7 +598,7 @@ pub async fn init_dragonfly_redis_store(
 
     let tls_certs = build_tls_certs(ca_cert_bytes, client_cert_bytes, client_key_bytes);
 
-    let hosts = get_redis_store_hosts();
+    let hosts = get_redis_store_hosts()?;
 
     let conn_addr: Vec<ConnectionAddr> = hosts
         .iter()
@@ -476,8 +610,9 @@ pub async fn init_dragonfly_redis_store(
         })
         .collect();
 
-    let dragonfly_pass = std::env::var("DRAGONFLY_REDIS_STORE_PASSWORD")
-        .expect("DRAGONFLY_REDIS_STORE_PASSWORD environment variable not set");
+    let dragonfly_pass = std::env::var("DRAGONFLY_REDIS_STORE_PASSWORD").map_err(|_| {
+        anyhow::anyhow!("DRAGONFLY_REDIS_STORE_PASSWORD environment variable not set")
+    })?;
 
     let mut builder = SentinelClientBuilder::new(
         conn_addr,
@@ -492,9 +627,7 @@ pub async fn init_dragonfly_redis_store(
     builder = builder.set_client_to_redis_certificates(tls_certs.clone());
     builder = builder.set_client_to_redis_tls_mode(redis::TlsMode::Secure);
 
-    let sentinel_client = builder
-        .build()
-        .expect("Failed to build SentinelClient for cluster 2");
+    let sentinel_client = builder.build()?;
     let conn_man =
         SentinelConnectionManager::new(sentinel_client, SENTINEL_SERVICE_NAME.to_string())?;
 
@@ -522,13 +655,13 @@ pub async fn init_dragonfly_redis_for_test() -> Result<Arc<DragonflyPool>, anyho
         .install_default()
         .ok();
 
-    let ca_bytes = get_redis_store_ca_cert().expect("Failed to read CA cert");
-    let cert_bytes = get_redis_store_client_cert().expect("Failed to read client cert");
-    let key_bytes = get_redis_store_client_key().expect("Failed to read client key");
+    let ca_bytes = get_redis_store_ca_cert()?;
+    let cert_bytes = get_redis_store_client_cert()?;
+    let key_bytes = get_redis_store_client_key()?;
 
     let tls_certs = build_tls_certs(ca_bytes.clone(), cert_bytes.clone(), key_bytes.clone());
 
-    let hosts = get_redis_store_hosts();
+    let hosts = get_redis_store_hosts()?;
 
     let conn_addr: Vec<ConnectionAddr> = hosts
         .iter()
@@ -540,8 +673,9 @@ pub async fn init_dragonfly_redis_for_test() -> Result<Arc<DragonflyPool>, anyho
         })
         .collect();
 
-    let dragonfly_pass = std::env::var("DRAGONFLY_REDIS_STORE_PASSWORD")
-        .expect("DRAGONFLY_REDIS_STORE_PASSWORD environment variable not set");
+    let dragonfly_pass = std::env::var("DRAGONFLY_REDIS_STORE_PASSWORD").map_err(|_| {
+        anyhow::anyhow!("DRAGONFLY_REDIS_STORE_PASSWORD environment variable not set")
+    })?;
 
     let mut builder = SentinelClientBuilder::new(
         conn_addr,
@@ -555,7 +689,7 @@ pub async fn init_dragonfly_redis_for_test() -> Result<Arc<DragonflyPool>, anyho
     builder = builder.set_client_to_redis_certificates(tls_certs.clone());
     builder = builder.set_client_to_redis_tls_mode(redis::TlsMode::Secure);
 
-    let sentinel_client = builder.build().expect("Failed to build SentinelClient");
+    let sentinel_client = builder.build()?;
     let conn_man =
         SentinelConnectionManager::new(sentinel_client, SENTINEL_SERVICE_NAME.to_string())?;
 
@@ -581,6 +715,25 @@ mod tests {
     use super::*;
     use redis::AsyncCommands;
 
+    #[test]
+    fn test_circuit_breaker_trips_and_resets() {
+        let stats = PoolStats::default();
+        for _ in 0..CIRCUIT_FAILURE_THRESHOLD - 1 {
+            stats.record_failure();
+        }
+        assert!(!stats.is_open());
+
+        stats.record_failure();
+        stats.record_failure();
+        let snapshot = stats.snapshot();
+        assert!(snapshot.circuit_open);
+        assert_eq!(snapshot.circuit_opened, 1);
+
+        stats.record_success();
+        assert!(!stats.is_open());
+        assert_eq!(stats.snapshot().consecutive_failures, 0);
+    }
+
     #[tokio::test]
     async fn test_dragonfly_connection() {
         let pool = init_dragonfly_redis_for_test()